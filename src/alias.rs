@@ -0,0 +1,192 @@
+//! The command line before clap sees it: own names for commands from `[aliases]` in the
+//! config are replaced by what they stand for, and mistyped commands get suggestions.
+
+use crate::suggest;
+use anyhow::{anyhow, Result};
+use std::collections::BTreeMap;
+
+/// Own names for commands with arguments, like `brb = "break 5"`
+pub type Aliases = BTreeMap<String, String>;
+
+/// What mob knows of its own command line
+pub struct Commands {
+    /// Commands and their built-in aliases
+    pub names: Vec<String>,
+    /// Options before the command that take a value, like `--dir`
+    pub valued: Vec<String>,
+}
+
+impl Commands {
+    /// Where the command is in `args`, after the program and the options before it
+    fn position(&self, args: &[String]) -> Option<usize> {
+        let mut args = args.iter().enumerate().skip(1);
+        while let Some((index, arg)) = args.next() {
+            if arg == "--" {
+                return None;
+            }
+            if !arg.starts_with('-') {
+                return Some(index);
+            }
+            if self.valued.iter().any(|option| option == arg) {
+                args.next();
+            }
+        }
+        None
+    }
+
+    fn knows(&self, name: &str) -> bool {
+        self.names.iter().any(|known| known == name)
+    }
+}
+
+/// `args` with the alias in place of the command replaced by what it stands for, also when
+/// that is another alias. A command mob doesn't know but is close to one is an error.
+pub fn expand(args: Vec<String>, aliases: &Aliases, commands: &Commands) -> Result<Vec<String>> {
+    let index = match commands.position(&args) {
+        Some(index) => index,
+        None => return Ok(args),
+    };
+    let mut args = args;
+    let mut expanded: Vec<String> = vec![];
+    loop {
+        let name = args[index].clone();
+        if commands.knows(&name) {
+            if aliases.contains_key(&name) {
+                log::warn!(
+                    "The alias {} in the config is left out, mob {} is a command",
+                    name,
+                    name
+                );
+            }
+            return Ok(args);
+        }
+        let replacement = match aliases.get(&name) {
+            Some(replacement) => replacement,
+            None => break,
+        };
+        if expanded.contains(&name) {
+            expanded.push(name);
+            return Err(anyhow!(
+                "The aliases {} refer to each other in a loop",
+                expanded.join(" -> ")
+            ));
+        }
+        expanded.push(name);
+        args.splice(
+            index..=index,
+            replacement.split_whitespace().map(String::from),
+        );
+        if args.len() <= index {
+            return Ok(args);
+        }
+    }
+
+    let name = &args[index];
+    let known = commands
+        .names
+        .iter()
+        .chain(aliases.keys())
+        .map(String::as_str);
+    let close = suggest::close(name, known);
+    if close.is_empty() {
+        return Ok(args);
+    }
+    Err(anyhow!(
+        "mob has no command {}, did you mean {}?",
+        name,
+        close.join(" or ")
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn commands() -> Commands {
+        Commands {
+            names: [
+                "start", "next", "n", "rotate", "done", "stop", "finish", "break", "help",
+            ]
+            .iter()
+            .map(|name| name.to_string())
+            .collect(),
+            valued: vec!["--dir".into(), "--timeout".into()],
+        }
+    }
+
+    fn aliases(aliases: &[(&str, &str)]) -> Aliases {
+        aliases
+            .iter()
+            .map(|(name, replacement)| (name.to_string(), replacement.to_string()))
+            .collect()
+    }
+
+    fn expand(args: &str, aliases: &Aliases) -> Result<String> {
+        let args = args.split_whitespace().map(String::from).collect();
+        super::expand(args, aliases, &commands()).map(|args| args.join(" "))
+    }
+
+    #[test]
+    fn expands_aliases() {
+        let aliases = aliases(&[
+            ("brb", "break 5"),
+            ("lunch", "break --lunch"),
+            ("bye", "brb"),
+        ]);
+        assert_eq!(expand("mob brb", &aliases).unwrap(), "mob break 5");
+        assert_eq!(
+            expand("mob --timeout 10 lunch --until 13:00", &aliases).unwrap(),
+            "mob --timeout 10 break --lunch --until 13:00"
+        );
+        assert_eq!(expand("mob bye", &aliases).unwrap(), "mob break 5");
+        // Only the command is an alias, arguments stay as they are
+        assert_eq!(expand("mob start brb", &aliases).unwrap(), "mob start brb");
+        assert_eq!(
+            expand("mob --dir brb next", &aliases).unwrap(),
+            "mob --dir brb next"
+        );
+        assert_eq!(expand("mob --version", &aliases).unwrap(), "mob --version");
+    }
+
+    #[test]
+    fn stops_loops() {
+        let aliases = aliases(&[("a", "b"), ("b", "c --yes"), ("c", "a")]);
+        let err = expand("mob a", &aliases).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "The aliases a -> b -> c -> a refer to each other in a loop"
+        );
+        let itself = self::aliases(&[("again", "again")]);
+        assert!(expand("mob again", &itself).is_err());
+    }
+
+    #[test]
+    fn commands_win_over_aliases() {
+        let aliases = aliases(&[("next", "done"), ("n", "done"), ("go", "next")]);
+        assert_eq!(expand("mob next", &aliases).unwrap(), "mob next");
+        assert_eq!(expand("mob n", &aliases).unwrap(), "mob n");
+        assert_eq!(expand("mob go", &aliases).unwrap(), "mob next");
+    }
+
+    #[test]
+    fn suggests_commands() {
+        let aliases = aliases(&[("brb", "break 5")]);
+        let err = expand("mob fnish", &aliases).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "mob has no command fnish, did you mean finish?"
+        );
+        let err = expand("mob srart", &aliases).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "mob has no command srart, did you mean start?"
+        );
+        let err = expand("mob brbb", &aliases).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "mob has no command brbb, did you mean brb?"
+        );
+        // Nothing close, clap tells what mob has
+        assert_eq!(expand("mob coffee", &aliases).unwrap(), "mob coffee");
+    }
+}
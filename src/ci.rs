@@ -0,0 +1,207 @@
+//! Running inside CI, where make targets call mob and nobody is there to answer. The
+//! variables CI services set turn off what would hang or disturb a build: prompts, timers,
+//! notifications and the update check. `MOB_FORCE_INTERACTIVE=1` keeps them for setups
+//! that look like CI but have someone at the terminal.
+
+use crate::config::Config;
+use std::cell::Cell;
+
+pub const FORCE_ENV: &str = "MOB_FORCE_INTERACTIVE";
+
+/// Variables CI services set and the service they name, the generic ones last
+const SERVICES: &[(&str, &str)] = &[
+    ("GITHUB_ACTIONS", "GitHub Actions"),
+    ("GITLAB_CI", "GitLab CI"),
+    ("BUILDKITE", "Buildkite"),
+    ("CIRCLECI", "CircleCI"),
+    ("TRAVIS", "Travis CI"),
+    ("JENKINS_URL", "Jenkins"),
+    ("TEAMCITY_VERSION", "TeamCity"),
+    ("TF_BUILD", "Azure Pipelines"),
+    ("BITBUCKET_BUILD_NUMBER", "Bitbucket Pipelines"),
+    ("CODEBUILD_BUILD_ID", "AWS CodeBuild"),
+    ("DRONE", "Drone"),
+    ("APPVEYOR", "AppVeyor"),
+    ("CI", "CI"),
+    ("CONTINUOUS_INTEGRATION", "CI"),
+];
+
+/// What this run of mob may do, derived once from the environment
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RunContext {
+    /// The CI service mob runs in, none outside CI
+    pub ci: Option<&'static str>,
+    /// Someone may be asked, otherwise prompts take their default or fail
+    pub interactive: bool,
+    /// Timers count down, otherwise turns only get their time
+    pub timers: bool,
+    /// The say and notify commands run and the desktop is told
+    pub notifications: bool,
+    pub update_check: bool,
+}
+
+impl Default for RunContext {
+    fn default() -> Self {
+        RunContext {
+            ci: None,
+            interactive: true,
+            timers: true,
+            notifications: true,
+            update_check: true,
+        }
+    }
+}
+
+thread_local! {
+    static CONTEXT: Cell<RunContext> = Cell::new(RunContext::default());
+}
+
+impl RunContext {
+    /// The context of the environment as `var` reads it, like `std::env::var`
+    pub fn detect(var: impl Fn(&str) -> Option<String>) -> Self {
+        let set = |name: &str| {
+            var(name)
+                .map(|value| value.trim().to_lowercase())
+                .filter(|value| !matches!(value.as_str(), "" | "0" | "false" | "no"))
+                .is_some()
+        };
+        let ci = SERVICES
+            .iter()
+            .find(|(name, _)| set(name))
+            .map(|(_, service)| *service);
+        match ci {
+            Some(_) if !set(FORCE_ENV) => RunContext {
+                ci,
+                interactive: false,
+                timers: false,
+                notifications: false,
+                update_check: false,
+            },
+            _ => RunContext {
+                ci,
+                ..RunContext::default()
+            },
+        }
+    }
+
+    /// Whether something was turned off for CI
+    pub fn degraded(&self) -> bool {
+        RunContext { ci: None, ..*self } != RunContext::default()
+    }
+
+    /// What to tell once before anything else, when running degraded
+    pub fn notice(&self) -> Option<String> {
+        match (self.ci, self.degraded()) {
+            (Some(service), true) => Some(format!(
+                "Running in {}: not asking, no timers, notifications or update check. Set {}=1 to be asked.",
+                service, FORCE_ENV
+            )),
+            _ => None,
+        }
+    }
+
+    /// Turns off in `config` what this context doesn't allow
+    pub fn apply(&self, config: &mut Config) {
+        config.timer.silent |= !self.notifications;
+        config.update.check &= self.update_check;
+    }
+}
+
+/// Makes `context` the one of this run
+pub fn set(context: RunContext) {
+    CONTEXT.with(|current| current.set(context));
+}
+
+/// The context of this run, outside CI unless it was set
+pub fn current() -> RunContext {
+    CONTEXT.with(|current| current.get())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn detect(vars: &[(&str, &str)]) -> RunContext {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        RunContext::detect(|name| vars.get(name).cloned())
+    }
+
+    #[test]
+    fn detects_ci_services() {
+        let cases = vec![
+            (vec![], None),
+            (
+                vec![("GITHUB_ACTIONS", "true"), ("CI", "true")],
+                Some("GitHub Actions"),
+            ),
+            (vec![("GITLAB_CI", "true")], Some("GitLab CI")),
+            (vec![("BUILDKITE", "true")], Some("Buildkite")),
+            (
+                vec![("JENKINS_URL", "https://ci.example.com/")],
+                Some("Jenkins"),
+            ),
+            (vec![("TF_BUILD", "True")], Some("Azure Pipelines")),
+            (vec![("CI", "1")], Some("CI")),
+            (vec![("CI", "false")], None),
+            (vec![("CI", "")], None),
+            (vec![("CI", "0"), ("GITLAB_CI", "no")], None),
+        ];
+        for (vars, expected) in cases {
+            let context = detect(&vars);
+            assert_eq!(context.ci, expected, "{:?}", vars);
+            assert_eq!(context.degraded(), expected.is_some(), "{:?}", vars);
+        }
+    }
+
+    #[test]
+    fn turns_everything_off_in_ci() {
+        let context = detect(&[("CI", "true")]);
+        assert!(!context.interactive);
+        assert!(!context.timers);
+        assert!(!context.notifications);
+        assert!(!context.update_check);
+        assert_eq!(
+            context.notice().unwrap(),
+            "Running in CI: not asking, no timers, notifications or update check. \
+             Set MOB_FORCE_INTERACTIVE=1 to be asked."
+        );
+        assert_eq!(detect(&[]).notice(), None);
+    }
+
+    #[test]
+    fn can_be_forced_interactive() {
+        let context = detect(&[("GITHUB_ACTIONS", "true"), (FORCE_ENV, "1")]);
+        assert_eq!(context.ci, Some("GitHub Actions"));
+        assert!(!context.degraded());
+        assert_eq!(context.notice(), None);
+        assert_eq!(detect(&[(FORCE_ENV, "1")]), RunContext::default());
+        assert!(detect(&[("CI", "true"), (FORCE_ENV, "0")]).degraded());
+    }
+
+    #[test]
+    fn silences_the_config() {
+        let mut config = Config::default();
+        config.update.check = true;
+        detect(&[]).apply(&mut config);
+        assert!(!config.timer.silent);
+        assert!(config.update.check);
+
+        detect(&[("BUILDKITE", "true")]).apply(&mut config);
+        assert!(config.timer.silent);
+        assert!(!config.desktop_notifications());
+        assert!(config.commands().is_empty());
+        assert!(!config.update.check);
+    }
+
+    #[test]
+    fn is_the_context_of_the_run_once_set() {
+        assert_eq!(current(), RunContext::default());
+        set(detect(&[("CI", "true")]));
+        assert!(!current().interactive);
+        set(RunContext::default());
+    }
+}
@@ -0,0 +1,114 @@
+//! What time it is, asked of the machine by `mob` and of a fixed clock by tests.
+
+use crate::timekeeping::Instant;
+use chrono::{DateTime, FixedOffset, Local, NaiveDate, Utc};
+#[cfg(any(test, feature = "test-util"))]
+use std::cell::Cell;
+
+pub trait Clock {
+    fn now_utc(&self) -> DateTime<Utc>;
+
+    fn now(&self) -> Instant {
+        self.now_utc().into()
+    }
+
+    /// Now where the mob is, breaks and the workday are in local time
+    fn now_local(&self) -> DateTime<FixedOffset>;
+    /// `at` in local time, to show it
+    fn local(&self, at: DateTime<Utc>) -> DateTime<FixedOffset>;
+
+    fn today(&self) -> NaiveDate {
+        self.now_local().naive_local().date()
+    }
+}
+
+/// The clock and timezone of this machine
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now_utc(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+
+    fn now_local(&self) -> DateTime<FixedOffset> {
+        let now = Local::now();
+        now.with_timezone(now.offset())
+    }
+
+    fn local(&self, at: DateTime<Utc>) -> DateTime<FixedOffset> {
+        // The offset at that time, which differs from now across a DST change
+        let local = at.with_timezone(&Local);
+        local.with_timezone(local.offset())
+    }
+}
+
+/// Stands still at a time in a fixed timezone, until told to move on
+#[cfg(any(test, feature = "test-util"))]
+pub struct FakeClock {
+    now: Cell<DateTime<FixedOffset>>,
+}
+
+#[cfg(any(test, feature = "test-util"))]
+impl FakeClock {
+    pub fn at(now: DateTime<FixedOffset>) -> Self {
+        FakeClock {
+            now: Cell::new(now),
+        }
+    }
+
+    /// 1 June 2021 at `hour`:`minute` in UTC+1
+    pub fn on_june_first(hour: u32, minute: u32) -> Self {
+        use chrono::TimeZone;
+        Self::at(
+            FixedOffset::east(3600)
+                .ymd(2021, 6, 1)
+                .and_hms(hour, minute, 0),
+        )
+    }
+
+    pub fn advance(&self, duration: chrono::Duration) {
+        self.now.set(self.now.get() + duration);
+    }
+}
+
+/// Mid-morning, for tests the time doesn't matter to
+#[cfg(any(test, feature = "test-util"))]
+impl Default for FakeClock {
+    fn default() -> Self {
+        Self::on_june_first(10, 0)
+    }
+}
+
+#[cfg(any(test, feature = "test-util"))]
+impl Clock for FakeClock {
+    fn now_utc(&self) -> DateTime<Utc> {
+        self.now.get().with_timezone(&Utc)
+    }
+
+    fn now_local(&self) -> DateTime<FixedOffset> {
+        self.now.get()
+    }
+
+    fn local(&self, at: DateTime<Utc>) -> DateTime<FixedOffset> {
+        at.with_timezone(&self.now.get().timezone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn fake_clock_moves_when_told() {
+        let clock = FakeClock::on_june_first(23, 30);
+        assert_eq!(clock.now_local().to_rfc3339(), "2021-06-01T23:30:00+01:00");
+        assert_eq!(clock.now_utc().to_rfc3339(), "2021-06-01T22:30:00+00:00");
+        assert_eq!(clock.today(), NaiveDate::from_ymd(2021, 6, 1));
+
+        clock.advance(Duration::minutes(45));
+        assert_eq!(clock.today(), NaiveDate::from_ymd(2021, 6, 2));
+        let shown = clock.local(clock.now_utc() + Duration::hours(1));
+        assert_eq!(shown.format("%H:%M").to_string(), "01:15");
+    }
+}
@@ -0,0 +1,175 @@
+use crate::session::away::{self, Change};
+use crate::{clock::Clock, config::Config, duration, session, until};
+use anyhow::Result;
+use chrono::{DateTime, TimeZone, Utc};
+use clap::{self, Clap};
+
+#[derive(Clap, Debug)]
+pub struct AwayOpts {
+    /// When you are back, like 15:00 or +30m
+    #[clap(
+        long,
+        required_unless_present = "duration",
+        conflicts_with = "duration"
+    )]
+    until: Option<String>,
+
+    /// The time given with --until is tomorrow
+    #[clap(long, requires = "until")]
+    tomorrow: bool,
+
+    /// How long you are away, like 90m or 2h
+    #[clap(long = "for", value_name = "DURATION")]
+    duration: Option<String>,
+}
+
+impl AwayOpts {
+    fn until<Tz: TimeZone>(&self, now: &DateTime<Tz>) -> Result<DateTime<Utc>> {
+        match (&self.until, &self.duration) {
+            (Some(until), _) => Ok(until::parse(until, now, self.tomorrow)?),
+            (None, Some(duration)) => Ok(now.with_timezone(&Utc) + duration::parse(duration)?),
+            (None, None) => unreachable!("clap requires one of them"),
+        }
+    }
+}
+
+/// Has the rotation skip me for a while
+pub struct Away<'a> {
+    store: &'a dyn session::Store,
+    clock: &'a dyn Clock,
+    opts: AwayOpts,
+    config: Config,
+}
+
+impl<'a> Away<'a> {
+    pub fn new(
+        store: &'a impl session::Store,
+        clock: &'a impl Clock,
+        opts: AwayOpts,
+        config: Config,
+    ) -> Away<'a> {
+        Self {
+            store,
+            clock,
+            opts,
+            config,
+        }
+    }
+
+    pub fn run(&self) -> Result<()> {
+        let now = self.clock.now_local();
+        let until = self.opts.until(&now)?;
+        let session = away::transition(
+            self.store.load()?,
+            &self.config.name,
+            Change::Away { until },
+            now.with_timezone(&Utc),
+        )?;
+        self.store.save(session)?;
+        log::info!(
+            "The rotation skips you until {}, or until mob back",
+            self.clock.local(until).format("%H:%M")
+        );
+        Ok(())
+    }
+}
+
+/// Puts me back in the rotation
+pub struct Back<'a> {
+    store: &'a dyn session::Store,
+    clock: &'a dyn Clock,
+    config: Config,
+}
+
+impl<'a> Back<'a> {
+    pub fn new(store: &'a impl session::Store, clock: &'a impl Clock, config: Config) -> Back<'a> {
+        Self {
+            store,
+            clock,
+            config,
+        }
+    }
+
+    pub fn run(&self) -> Result<()> {
+        let now = self.clock.now_utc();
+        let session = self.store.load()?;
+        if session.away.until(&self.config.name, now).is_none() {
+            log::info!("You weren't away");
+            return Ok(());
+        }
+        let session = away::transition(session, &self.config.name, Change::Back, now)?;
+        self.store.save(session)?;
+        log::info!("Welcome back, you are in the rotation again");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::FakeClock;
+    use crate::git::fake::MemoryStore;
+    use crate::session::Store;
+    use chrono::FixedOffset;
+
+    fn until(args: &[&str]) -> Result<String> {
+        let now = FixedOffset::east(3600).ymd(2021, 6, 1).and_hms(14, 0, 0);
+        let opts = AwayOpts::try_parse_from(args).map_err(|err| anyhow::anyhow!("{}", err))?;
+        opts.until(&now).map(|until| {
+            until
+                .with_timezone(&now.timezone())
+                .format("%d %H:%M")
+                .to_string()
+        })
+    }
+
+    #[test]
+    fn until_or_for() {
+        assert_eq!(until(&["away", "--until", "15:00"]).unwrap(), "01 15:00");
+        assert_eq!(until(&["away", "--until", "+45m"]).unwrap(), "01 14:45");
+        assert_eq!(
+            until(&["away", "--until", "9:00", "--tomorrow"]).unwrap(),
+            "02 09:00"
+        );
+        assert_eq!(until(&["away", "--for", "1h"]).unwrap(), "01 15:00");
+        assert_eq!(until(&["away", "--for", "2d"]).unwrap(), "03 14:00");
+        assert!(until(&["away", "--until", "13:00"]).is_err());
+        assert!(until(&["away", "--for", "soon"]).is_err());
+        assert!(until(&["away"]).is_err());
+        assert!(until(&["away", "--until", "15:00", "--for", "1h"]).is_err());
+    }
+
+    #[test]
+    fn away_and_back() {
+        let memory = MemoryStore::default();
+        let store = session::SessionStore::new(&memory);
+        store
+            .save(session::Session {
+                drivers: session::Drivers::new(vec!["alice".into(), "bob".into()]),
+                ..session::Session::default()
+            })
+            .unwrap();
+        let config = Config {
+            name: "bob".into(),
+            ..Config::default()
+        };
+
+        let clock = FakeClock::on_june_first(14, 0);
+        let opts = AwayOpts::parse_from(["away", "--for", "1h"]);
+        Away::new(&store, &clock, opts, config.clone())
+            .run()
+            .unwrap();
+        let until = store.load().unwrap().away.until("bob", clock.now_utc());
+        assert_eq!(until, Some(clock.now_utc() + chrono::Duration::hours(1)));
+
+        Back::new(&store, &clock, config.clone()).run().unwrap();
+        assert!(store.load().unwrap().away.is_empty());
+
+        let stranger = Config {
+            name: "carol".into(),
+            ..config
+        };
+        let opts = AwayOpts::parse_from(["away", "--for", "1h"]);
+        assert!(Away::new(&store, &clock, opts, stranger).run().is_err());
+    }
+}
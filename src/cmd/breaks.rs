@@ -0,0 +1,721 @@
+use super::push;
+use crate::timekeeping::{ClockTime, Instant, Minutes};
+use crate::{clock::Clock, config::Config, deadline, git, schedule, session, timer, until};
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, TimeZone, Utc};
+use clap::{self, Clap};
+use session::{roles, State};
+
+#[derive(Clap, Debug)]
+pub struct BreakOpts {
+    /// How long the break lasts, from the session settings when left out
+    #[clap(name = "MINUTES", conflicts_with = "until")]
+    minutes: Option<Minutes>,
+
+    /// When the break ends, like 13:30 or +15m
+    #[clap(long)]
+    until: Option<String>,
+
+    /// The time given with --until is tomorrow
+    #[clap(long, requires = "until")]
+    tomorrow: bool,
+
+    /// Take the fixed break that is open now, or lunch, instead of a break
+    #[clap(long)]
+    lunch: bool,
+}
+
+pub struct Break<'a> {
+    git: &'a dyn git::Git,
+    store: &'a dyn session::Store,
+    timer: &'a dyn timer::Timer,
+    clock: &'a dyn Clock,
+    opts: BreakOpts,
+    config: Config,
+    interactive: bool,
+}
+
+impl<'a> Break<'a> {
+    pub fn new(
+        git: &'a impl git::Git,
+        store: &'a impl session::Store,
+        timer: &'a impl timer::Timer,
+        clock: &'a impl Clock,
+        opts: BreakOpts,
+        config: Config,
+    ) -> Break<'a> {
+        Self {
+            git,
+            store,
+            timer,
+            clock,
+            opts,
+            config,
+            interactive: deadline::attended(),
+        }
+    }
+
+    pub fn run(&self) -> Result<()> {
+        let session = self.store.load()?;
+        roles::check(&session, &self.config.name, roles::Action::Break)?;
+        match &session.state {
+            State::Paused { reason, .. } => {
+                return Err(anyhow!(
+                    "The session is paused for {}, run mob start to resume it before a break",
+                    reason
+                ))
+            }
+            State::Stopped => {
+                log::warn!("No current mob session, run mob start");
+                return Ok(());
+            }
+            _ => (),
+        }
+        // Only a break told how long to last moves the end of the one going on
+        let longer = self.opts.minutes.is_some() || self.opts.until.is_some() || self.opts.lunch;
+        let on_break = session
+            .times
+            .break_until
+            .filter(|until| *until > self.clock.now());
+        if let (Some(until), false) = (on_break, longer) {
+            log::warn!(
+                "The mob is on a break until {} already, give the minutes to make it longer",
+                self.clock.local(until.utc()).format("%H:%M")
+            );
+            return Ok(());
+        }
+        let now = self.clock.now_local();
+        let settings = session.settings.clone().unwrap_or_default();
+        let window = self.window(&now, &settings);
+        let end = self.end(&now, &settings, window)?;
+        let session = self.hand_back(session, &settings.commit_message)?;
+        let name = match (self.opts.lunch, window) {
+            (true, Some(window)) => Some(window.name.clone()),
+            (true, None) => Some("Lunch".to_string()),
+            (false, _) => None,
+        };
+        take(
+            self.store,
+            self.timer,
+            self.clock,
+            session,
+            name.as_deref(),
+            end,
+        )
+    }
+
+    /// The session with the work of the driver taking the break pushed as `mob next` does,
+    /// waiting for them to run mob start afterwards
+    fn hand_back(
+        &self,
+        session: session::Session,
+        commit_message: &str,
+    ) -> Result<session::Session> {
+        let me = &self.config.name;
+        if !matches!(&session.state, State::Working { driver } if driver == me) {
+            return Ok(session);
+        }
+        let mut new_files = session.new_files.clone();
+        push::commit_turn(
+            self.git,
+            &self.config,
+            &session.branches.branch,
+            commit_message,
+            &mut new_files,
+            self.interactive,
+        )?;
+        if let Err(err) = self.timer.cancel() {
+            log::warn!("Could not cancel the timer: {:#}", err);
+        }
+        log::info!("It's your turn again after the break, run mob start");
+        Ok(session::Session {
+            state: State::WaitingForNext {
+                next: Some(me.clone()),
+            },
+            times: session::Times {
+                last_activity: Some(self.clock.now()),
+                waiting_since: Some(self.clock.now()),
+                claimed: None,
+                ..session.times
+            },
+            new_files,
+            ..session
+        })
+    }
+
+    /// The window `--lunch` takes, the one open now or else lunch
+    fn window<'s, Tz: TimeZone>(
+        &self,
+        now: &DateTime<Tz>,
+        settings: &'s session::Settings,
+    ) -> Option<&'s session::Window> {
+        if !self.opts.lunch {
+            return None;
+        }
+        schedule::window_at(&settings.windows, ClockTime::of(now))
+            .map(|(window, _)| window)
+            .or_else(|| {
+                settings
+                    .windows
+                    .iter()
+                    .find(|window| window.name.eq_ignore_ascii_case("lunch"))
+            })
+    }
+
+    fn end<Tz: TimeZone>(
+        &self,
+        now: &DateTime<Tz>,
+        settings: &session::Settings,
+        window: Option<&session::Window>,
+    ) -> Result<DateTime<Utc>> {
+        if let Some(until) = &self.opts.until {
+            return Ok(until::parse(until, now, self.opts.tomorrow)?);
+        }
+        let minutes = match (self.opts.minutes, self.opts.lunch) {
+            (Some(minutes), _) => minutes,
+            (None, true) => match window {
+                Some(window) => return Ok(until::at(window.end, now, false)?),
+                None => return Err(anyhow!("How long is lunch? Give the minutes or --until")),
+            },
+            (None, false) => settings
+                .break_duration
+                .filter(|minutes| minutes.get() > 0)
+                .unwrap_or(schedule::DEFAULT_BREAK_MINUTES),
+        };
+        if minutes.get() < 1 {
+            return Err(anyhow!("A break needs to last at least a minute"));
+        }
+        Ok(now.with_timezone(&Utc) + minutes.duration())
+    }
+}
+
+/// Remembers the break, or the break in `window`, that ends at `end` and runs its timer
+pub(super) fn take(
+    store: &dyn session::Store,
+    timer: &dyn timer::Timer,
+    clock: &dyn Clock,
+    session: session::Session,
+    window: Option<&str>,
+    end: DateTime<Utc>,
+) -> Result<()> {
+    let (session, countdown) = booked(timer, clock, session, window, end);
+    store.save(session)?;
+    countdown.start(timer, clock);
+    Ok(())
+}
+
+/// The countdown of a break that was saved
+#[derive(Debug, PartialEq)]
+pub(super) struct Countdown {
+    title: String,
+    end: DateTime<Utc>,
+}
+
+impl Countdown {
+    /// The break is on whether or not its timer runs, `mob status` shows when it ends
+    pub(super) fn start(&self, timer: &dyn timer::Timer, clock: &dyn Clock) {
+        let started = timer.start(
+            timer::Countdown::Break,
+            &format!(
+                "{} until {}",
+                self.title,
+                clock.local(self.end).format("%H:%M")
+            ),
+            self.end - clock.now_utc(),
+            &format!("{} is over, mob start", self.title),
+        );
+        if let Err(err) = started {
+            log::warn!(
+                "The countdown of the {} is not running: {:#}",
+                self.title.to_lowercase(),
+                err
+            );
+        }
+    }
+}
+
+/// The session with the break, or the break in `window`, ending at `end` booked, and the
+/// countdown to start once it is saved
+pub(super) fn booked(
+    timer: &dyn timer::Timer,
+    clock: &dyn Clock,
+    session: session::Session,
+    window: Option<&str>,
+    end: DateTime<Utc>,
+) -> (session::Session, Countdown) {
+    let now = clock.now();
+    let (times, window, fresh) = book(session.times.clone(), now, window, end.into());
+    let end = times.break_until.map_or(end, Instant::utc);
+    let history = if fresh {
+        session
+            .history
+            .push(session::Record::Break { at: now.utc() })
+    } else {
+        log::info!(
+            "The mob is on a break already, it now lasts until {}",
+            clock.local(end).format("%H:%M")
+        );
+        if let Err(err) = timer.cancel() {
+            log::warn!("Could not cancel the timer of the break: {:#}", err);
+        }
+        session.history.clone()
+    };
+    let countdown = Countdown {
+        title: window.unwrap_or_else(|| "Break".to_string()),
+        end,
+    };
+    (
+        session::Session {
+            times,
+            history,
+            ..session
+        },
+        countdown,
+    )
+}
+
+/// The times with the break, or the break in `window`, ending at `end` taken `now`, and the
+/// window the mob is on. A break still going on isn't taken a second time but lasts until
+/// the later end, and turns into `window` when it was a short one. False then.
+fn book(
+    mut times: session::Times,
+    now: Instant,
+    window: Option<&str>,
+    end: Instant,
+) -> (session::Times, Option<String>, bool) {
+    let until = match times.break_until.filter(|until| *until > now) {
+        Some(until) => until,
+        None => {
+            match window {
+                Some(window) => {
+                    times.last_lunch = Some(now);
+                    times.windows.insert(window.to_string(), now);
+                }
+                None => times.last_break = Some(now),
+            }
+            times.break_until = Some(end);
+            return (times, window.map(String::from), true);
+        }
+    };
+    let window = match (times.last_window(), window) {
+        (Some(current), _) => Some(current.to_string()),
+        (None, Some(window)) => {
+            // The window started with the short break, which isn't one anymore
+            let started = times.last_break.take().unwrap_or(now);
+            times.last_lunch = Some(started);
+            times.windows.insert(window.to_string(), started);
+            Some(window.to_string())
+        }
+        (None, None) => None,
+    };
+    times.break_until = Some(until.max(end));
+    (times, window, false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::FakeClock;
+    use crate::cmd::fixtures::{alice, working};
+    use crate::git::fake::{FakeGit, MemoryStore};
+    use crate::session::Store;
+    use crate::timer::fake::FailingTimer;
+    use chrono::Duration;
+    use std::cell::RefCell;
+
+    #[derive(Default)]
+    struct RecordingTimer {
+        started: RefCell<Vec<(String, i64)>>,
+    }
+
+    impl timer::Timer for RecordingTimer {
+        fn start(
+            &self,
+            _: timer::Countdown,
+            title: &str,
+            duration: Duration,
+            _: &str,
+        ) -> Result<()> {
+            self.started
+                .borrow_mut()
+                .push((title.to_string(), duration.num_minutes()));
+            Ok(())
+        }
+    }
+
+    fn end(args: &[&str], settings: session::Settings) -> Result<String> {
+        let memory = MemoryStore::default();
+        let store = session::SessionStore::new(&memory);
+        let timer = RecordingTimer::default();
+        let clock = FakeClock::on_june_first(12, 0);
+        let now = clock.now_local();
+        let command = Break::new(
+            &git::NoopGit,
+            &store,
+            &timer,
+            &clock,
+            BreakOpts::parse_from(args),
+            Config::default(),
+        );
+        command
+            .end(&now, &settings, command.window(&now, &settings))
+            .map(|end| {
+                end.with_timezone(&now.timezone())
+                    .format("%d %H:%M")
+                    .to_string()
+            })
+    }
+
+    fn window(name: &str, start: u32, end: u32) -> session::Window {
+        session::Window {
+            name: name.into(),
+            start: ClockTime::hm(start, 0),
+            end: ClockTime::hm(end, 0),
+        }
+    }
+
+    #[test]
+    fn ends_with_the_open_window() {
+        let settings = session::Settings {
+            windows: vec![window("Lunch", 13, 14), window("Tea", 12, 13)],
+            ..session::Settings::default()
+        };
+        assert_eq!(
+            end(&["break", "--lunch"], settings.clone()).unwrap(),
+            "01 13:00"
+        );
+        let later = session::Settings {
+            windows: vec![window("Lunch", 13, 14), window("Tea", 15, 16)],
+            ..settings
+        };
+        assert_eq!(end(&["break", "--lunch"], later).unwrap(), "01 14:00");
+    }
+
+    #[test]
+    fn ends() {
+        let settings = session::Settings {
+            break_duration: Some(Minutes(10)),
+            windows: vec![window("Lunch", 11, 13)],
+            ..session::Settings::default()
+        };
+        assert_eq!(end(&["break"], settings.clone()).unwrap(), "01 12:10");
+        assert_eq!(end(&["break", "7"], settings.clone()).unwrap(), "01 12:07");
+        assert_eq!(
+            end(&["break", "--until", "13:30"], settings.clone()).unwrap(),
+            "01 13:30"
+        );
+        assert_eq!(
+            end(&["break", "--until", "+42m"], settings.clone()).unwrap(),
+            "01 12:42"
+        );
+        assert_eq!(
+            end(
+                &["break", "--until", "8:00", "--tomorrow"],
+                settings.clone()
+            )
+            .unwrap(),
+            "02 08:00"
+        );
+        assert_eq!(
+            end(&["break", "--lunch"], settings.clone()).unwrap(),
+            "01 13:00"
+        );
+        assert!(end(&["break", "--until", "11:00"], settings.clone()).is_err());
+        assert!(end(&["break", "0"], settings).is_err());
+        assert_eq!(
+            end(&["break"], session::Settings::default()).unwrap(),
+            "01 12:05"
+        );
+        assert!(end(&["break", "--lunch"], session::Settings::default()).is_err());
+    }
+
+    #[test]
+    fn until_excludes_minutes() {
+        assert!(BreakOpts::try_parse_from(["break", "5", "--until", "13:30"]).is_err());
+        assert!(BreakOpts::try_parse_from(["break", "--tomorrow"]).is_err());
+    }
+
+    #[test]
+    fn extends_or_converts_a_running_break() {
+        let clock = FakeClock::on_june_first(11, 58);
+        let now = clock.now();
+        let minutes = |minutes| now + Duration::minutes(minutes);
+        let on_break = session::Times {
+            last_break: Some(minutes(-2)),
+            break_until: Some(minutes(3)),
+            ..session::Times::default()
+        };
+
+        // A longer break only moves the end
+        let (times, window, fresh) = book(on_break.clone(), now, None, minutes(10));
+        assert!(!fresh);
+        assert_eq!(window, None);
+        assert_eq!(times.break_until, Some(minutes(10)));
+        assert_eq!(times.last_break, on_break.last_break);
+        let (times, _, _) = book(on_break.clone(), now, None, minutes(1));
+        assert_eq!(times.break_until, Some(minutes(3)));
+
+        // Lunch at the boundary turns the break into lunch since it started, so the mob
+        // rested as long as before
+        let (times, window, fresh) = book(on_break.clone(), now, Some("Lunch"), minutes(62));
+        assert!(!fresh);
+        assert_eq!(window.as_deref(), Some("Lunch"));
+        assert_eq!(times.break_until, Some(minutes(62)));
+        assert_eq!(times.last_break, None);
+        assert_eq!(times.last_lunch, Some(minutes(-2)));
+        assert_eq!(times.window_taken("Lunch"), Some(minutes(-2)));
+        assert_eq!(times.last_window(), Some("Lunch"));
+        assert_eq!(times.rested(), on_break.rested());
+
+        // A short break during lunch stays lunch
+        let (times, window, fresh) = book(times, minutes(5), None, minutes(15));
+        assert!(!fresh);
+        assert_eq!(window.as_deref(), Some("Lunch"));
+        assert_eq!(times.break_until, Some(minutes(62)));
+        assert_eq!(times.last_break, None);
+
+        // Ended right now, so this is a new one
+        let (times, _, fresh) = book(on_break.clone(), minutes(3), None, minutes(8));
+        assert!(fresh);
+        assert_eq!(times.last_break, Some(minutes(3)));
+        assert_eq!(times.break_until, Some(minutes(8)));
+    }
+
+    #[test]
+    fn takes_a_break_once() {
+        let memory = MemoryStore::default();
+        let store = session::SessionStore::new(&memory);
+        let timer = RecordingTimer::default();
+        let clock = FakeClock::on_june_first(11, 58);
+        let now = clock.now_utc();
+        take(
+            &store,
+            &timer,
+            &clock,
+            session::Session::default(),
+            None,
+            now + Duration::minutes(5),
+        )
+        .unwrap();
+        let session = store.load().unwrap();
+        take(
+            &store,
+            &timer,
+            &clock,
+            session,
+            Some("Lunch"),
+            now + Duration::minutes(62),
+        )
+        .unwrap();
+
+        let session = store.load().unwrap();
+        assert_eq!(session.history.records().len(), 1);
+        assert_eq!(session.times.last_break, None);
+        assert_eq!(session.times.last_lunch, Some(now.into()));
+        assert_eq!(session.times.last_window(), Some("Lunch"));
+        let started = timer.started.borrow();
+        assert_eq!(started[1], ("Lunch until 13:00".to_string(), 62));
+    }
+
+    #[test]
+    fn no_break_while_paused() {
+        let memory = MemoryStore::default();
+        let store = session::SessionStore::new(&memory);
+        store
+            .save(session::Session {
+                state: session::State::Paused {
+                    reason: "an incident".into(),
+                    driver: None,
+                    remaining: None,
+                },
+                ..session::Session::default()
+            })
+            .unwrap();
+        let timer = RecordingTimer::default();
+        let err = Break::new(
+            &git::NoopGit,
+            &store,
+            &timer,
+            &FakeClock::default(),
+            BreakOpts::parse_from(["break"]),
+            Config::default(),
+        )
+        .run()
+        .unwrap_err();
+        assert!(
+            err.to_string().contains("paused for an incident"),
+            "{}",
+            err
+        );
+        assert!(timer.started.borrow().is_empty());
+    }
+
+    #[test]
+    fn pushes_the_work_and_hands_the_turn_back() {
+        let mut git = FakeGit::default();
+        git.snapshot.branch = Some("mob-session".into());
+        git.snapshot.changed = 1;
+        git.snapshot.unstaged = 1;
+        let memory = MemoryStore::default();
+        let store = session::SessionStore::new(&memory);
+        store.save(working()).unwrap();
+        let timer = RecordingTimer::default();
+        let clock = FakeClock::on_june_first(11, 0);
+
+        Break::new(
+            &git,
+            &store,
+            &timer,
+            &clock,
+            BreakOpts::parse_from(["break", "5"]),
+            alice(),
+        )
+        .run()
+        .unwrap();
+
+        let calls = git.calls();
+        assert!(calls.contains(&"add --all".to_string()), "{:?}", calls);
+        assert!(
+            calls.iter().any(|call| call.starts_with("push ")),
+            "{:?}",
+            calls
+        );
+        let session = store.load().unwrap();
+        assert_eq!(
+            session.state,
+            State::WaitingForNext {
+                next: Some("alice".into())
+            }
+        );
+        assert_eq!(session.times.last_break, Some(clock.now()));
+        assert_eq!(
+            session.times.break_until,
+            Some(clock.now() + Duration::minutes(5))
+        );
+        assert_eq!(
+            timer.started.borrow()[0],
+            ("Break until 11:05".to_string(), 5)
+        );
+
+        // Someone else's turn stays theirs, their work isn't here
+        let git = FakeGit::default();
+        store.save(working()).unwrap();
+        clock.advance(Duration::minutes(10));
+        Break::new(
+            &git,
+            &store,
+            &timer,
+            &clock,
+            BreakOpts::parse_from(["break"]),
+            Config {
+                name: "bob".into(),
+                ..Config::default()
+            },
+        )
+        .run()
+        .unwrap();
+        assert!(git.calls().is_empty());
+        assert_eq!(store.load().unwrap().state, working().state);
+    }
+
+    #[test]
+    fn warns_instead_of_a_second_break() {
+        let memory = MemoryStore::default();
+        let store = session::SessionStore::new(&memory);
+        let timer = RecordingTimer::default();
+        let clock = FakeClock::on_june_first(11, 0);
+        let on_break = session::Session {
+            times: session::Times {
+                last_break: Some(clock.now()),
+                break_until: Some(clock.now() + Duration::minutes(5)),
+                ..session::Times::default()
+            },
+            ..working()
+        };
+        let command = |args: &[&str]| {
+            Break::new(
+                &git::NoopGit,
+                &store,
+                &timer,
+                &clock,
+                BreakOpts::parse_from(args),
+                alice(),
+            )
+            .run()
+            .unwrap()
+        };
+
+        for session in [session::Session::default(), on_break.clone()] {
+            store.save(session.clone()).unwrap();
+            command(&["break"]);
+            assert_eq!(store.load().unwrap(), session);
+        }
+        assert!(timer.started.borrow().is_empty());
+
+        // Told how long, it lasts longer
+        command(&["break", "15"]);
+        let session = store.load().unwrap();
+        assert_eq!(
+            session.times.break_until,
+            Some(clock.now() + Duration::minutes(15))
+        );
+        assert_eq!(session.times.last_break, on_break.times.last_break);
+    }
+
+    #[test]
+    fn takes_the_break_without_a_timer() {
+        let memory = MemoryStore::default();
+        let store = session::SessionStore::new(&memory);
+        let clock = FakeClock::on_june_first(11, 0);
+        let end = clock.now_utc() + Duration::minutes(10);
+
+        take(
+            &store,
+            &FailingTimer,
+            &clock,
+            session::Session::default(),
+            None,
+            end,
+        )
+        .unwrap();
+
+        assert_eq!(*memory.saves.borrow(), 2);
+        let session = store.load().unwrap();
+        assert_eq!(session.times.last_break, Some(clock.now()));
+        assert_eq!(session.times.break_until, Some(end.into()));
+        assert_eq!(
+            session.history.records(),
+            &[session::Record::Break {
+                at: clock.now_utc()
+            }]
+        );
+    }
+
+    #[test]
+    fn remembers_the_end() {
+        let memory = MemoryStore::default();
+        let store = session::SessionStore::new(&memory);
+        let timer = RecordingTimer::default();
+        let clock = FakeClock::on_june_first(13, 0);
+        let end = clock.now_utc() + Duration::minutes(30);
+
+        take(
+            &store,
+            &timer,
+            &clock,
+            session::Session::default(),
+            Some("Tea"),
+            end,
+        )
+        .unwrap();
+
+        let times = store.load().unwrap().times;
+        assert_eq!(times.break_until, Some(end.into()));
+        assert!(times.last_lunch.is_some());
+        assert_eq!(times.window_taken("Tea"), times.last_lunch);
+        assert_eq!(times.last_window(), Some("Tea"));
+        assert!(times.last_break.is_none());
+        let started = timer.started.borrow();
+        assert_eq!(started[0], ("Tea until 13:30".to_string(), 30));
+    }
+}
@@ -0,0 +1,261 @@
+use super::limits;
+use crate::session::claim::{self, Error};
+use crate::session::roles::{self, Role};
+use crate::{clock::Clock, config::Config, deadline, git, prompt, record, session};
+use anyhow::{anyhow, Result};
+
+/// Tries at most this often when others change the session meanwhile
+const ATTEMPTS: usize = 3;
+
+/// Reserves the next turn when anyone may take it, observers become drivers first
+pub struct Claim<'a> {
+    store: &'a dyn session::Store,
+    clock: &'a dyn Clock,
+    config: Config,
+    interactive: bool,
+}
+
+impl<'a> Claim<'a> {
+    pub fn new(store: &'a impl session::Store, clock: &'a impl Clock, config: Config) -> Claim<'a> {
+        Self {
+            store,
+            clock,
+            config,
+            interactive: deadline::attended(),
+        }
+    }
+
+    pub fn run(&self) -> Result<()> {
+        let session = self.store.load()?;
+        if roles::role(&session, &self.config.name) == Role::Observer {
+            if !self.become_driver(&session)? {
+                return Ok(());
+            }
+            // Driving doesn't depend on the turn being free now
+            return match self.claim() {
+                Err(err) => match err.downcast_ref::<Error>() {
+                    Some(err) => {
+                        log::info!("{}", err);
+                        Ok(())
+                    }
+                    None => Err(err),
+                },
+                ok => ok,
+            };
+        }
+        self.claim()
+    }
+
+    fn claim(&self) -> Result<()> {
+        // The save only succeeds on the session as loaded, so of two claims the first wins
+        let claimed = changing(self.store, |session| {
+            claim::claim(session, &self.config.name, self.clock.now_utc())
+        });
+        match claimed {
+            Ok(()) => log::info!("The next turn is yours, run mob start when you are ready"),
+            Err(err) => match err.downcast_ref::<Error>() {
+                Some(Error::Claimed(other)) => {
+                    log::warn!("{} already claimed the next turn", other)
+                }
+                Some(Error::Yours) => log::info!("The next turn is already yours"),
+                _ => return Err(err),
+            },
+        }
+        Ok(())
+    }
+
+    /// Moves me from the observers to the drivers once confirmed, false when declined
+    fn become_driver(&self, session: &session::Session) -> Result<bool> {
+        let me = self.config.name.as_str();
+        let prompt = "You are observing, become one of the drivers?";
+        let become_driver = match deadline::answer(prompt, None)? {
+            Some(become_driver) => become_driver,
+            None if self.interactive => {
+                record::answer(prompt, prompt::terminal().confirm(prompt, true)?)
+            }
+            None => {
+                return Err(anyhow!(
+                    "You are observing, run mob claim in a terminal to become a driver"
+                ))
+            }
+        };
+        if !become_driver {
+            log::info!("You keep observing");
+            return Ok(false);
+        }
+        let admitted = limits::admit(
+            &session.drivers,
+            session.get_driver(),
+            me,
+            self.config.limits.max_drivers,
+            false,
+            self.interactive,
+        )?;
+        if admitted.is_none() {
+            return Ok(false);
+        }
+        changing(self.store, |session| {
+            let drivers = session.drivers.clone().insert(session.get_driver(), me);
+            Ok::<_, anyhow::Error>(roles::promote(session, me, drivers))
+        })?;
+        log::info!("You are one of the drivers now");
+        Ok(true)
+    }
+}
+
+/// Gives back the next turn I claimed
+pub struct Unclaim<'a> {
+    store: &'a dyn session::Store,
+    config: Config,
+}
+
+impl<'a> Unclaim<'a> {
+    pub fn new(store: &'a impl session::Store, config: Config) -> Unclaim<'a> {
+        Self { store, config }
+    }
+
+    pub fn run(&self) -> Result<()> {
+        changing(self.store, |session| {
+            claim::unclaim(session, &self.config.name)
+        })?;
+        log::info!("Anyone can take the next turn again");
+        Ok(())
+    }
+}
+
+/// Saves what `change` makes of the session, again on the new session when others saved
+/// theirs first
+pub(super) fn changing<E: Into<anyhow::Error>>(
+    store: &dyn session::Store,
+    change: impl Fn(session::Session) -> Result<session::Session, E>,
+) -> Result<()> {
+    let mut attempts = 1;
+    loop {
+        let session = change(store.load()?).map_err(Into::into)?;
+        match store.save(session) {
+            Err(
+                err @ session::Error::Git(git::store::Error::Changed(_))
+                | err @ session::Error::Overtaken(_),
+            ) if attempts < ATTEMPTS => {
+                log::debug!("The session changed meanwhile: {}", err);
+                attempts += 1;
+            }
+            result => return Ok(result?),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::FakeClock;
+    use crate::cmd::fixtures::config;
+    use crate::git::fake::MemoryStore;
+    use crate::session::{State, Store};
+    use std::cell::RefCell;
+
+    /// Lets bob claim first, between alice loading the session and saving her claim
+    struct RacingStore {
+        memory: MemoryStore,
+        race: RefCell<Option<&'static str>>,
+    }
+
+    impl git::Store for RacingStore {
+        fn load(&self, document: &str) -> Result<Vec<u8>, git::store::Error> {
+            self.memory.load(document)
+        }
+
+        fn save(&self, document: &str, data: &[u8]) -> Result<(), git::store::Error> {
+            self.memory.save(document, data)
+        }
+
+        fn clean(&self, document: &str) -> Result<(), git::store::Error> {
+            self.memory.clean(document)
+        }
+
+        fn swap(
+            &self,
+            document: &str,
+            expected: Option<&[u8]>,
+            data: &[u8],
+        ) -> Result<(), git::store::Error> {
+            if let Some(other) = self.race.borrow_mut().take() {
+                let store = session::SessionStore::new(&self.memory);
+                Claim::new(&store, &FakeClock::on_june_first(10, 0), config(other))
+                    .run()
+                    .unwrap();
+            }
+            self.memory.swap(document, expected, data)
+        }
+    }
+
+    fn waiting_for_anyone(memory: &MemoryStore) {
+        session::SessionStore::new(memory)
+            .save(session::Session {
+                drivers: session::Drivers::new(vec!["alice".into(), "bob".into()]),
+                state: State::WaitingForNext { next: None },
+                ..session::Session::default()
+            })
+            .unwrap();
+    }
+
+    #[test]
+    fn first_claim_wins() {
+        let racing = RacingStore {
+            memory: MemoryStore::default(),
+            race: RefCell::new(Some("bob")),
+        };
+        waiting_for_anyone(&racing.memory);
+        let store = session::SessionStore::new(&racing);
+        let clock = FakeClock::on_june_first(10, 0);
+
+        Claim::new(&store, &clock, config("alice")).run().unwrap();
+        let session = store.load().unwrap();
+        assert_eq!(
+            session.state,
+            State::WaitingForNext {
+                next: Some("bob".into())
+            }
+        );
+        assert_eq!(session.times.claimed, Some(clock.now()));
+        assert!(Unclaim::new(&store, config("alice")).run().is_err());
+    }
+
+    #[test]
+    fn claim_and_unclaim() {
+        let memory = MemoryStore::default();
+        waiting_for_anyone(&memory);
+        let store = session::SessionStore::new(&memory);
+        let clock = FakeClock::on_june_first(10, 0);
+
+        Claim::new(&store, &clock, config("alice")).run().unwrap();
+        Claim::new(&store, &clock, config("alice")).run().unwrap();
+        assert_eq!(
+            store.load().unwrap().state,
+            State::WaitingForNext {
+                next: Some("alice".into())
+            }
+        );
+
+        Unclaim::new(&store, config("alice")).run().unwrap();
+        let session = store.load().unwrap();
+        assert_eq!(session.state, State::WaitingForNext { next: None });
+        assert_eq!(session.times.claimed, None);
+
+        store
+            .save(session::Session {
+                state: State::Working {
+                    driver: "bob".into(),
+                },
+                ..session
+            })
+            .unwrap();
+        let err = Claim::new(&store, &clock, config("alice"))
+            .run()
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "bob is driving, claim the turn after they ran mob next"
+        );
+    }
+}
@@ -0,0 +1,278 @@
+use crate::events::{self, Event};
+use crate::{clock::Clock, config::Config, deadline, prompt, record, session, state_dir::StateDir};
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use clap::{self, Clap};
+use serde::Serialize;
+use session::{roles, teardown, State};
+
+const BACKUP_FILE: &str = "backup.json";
+
+#[derive(Clap, Debug, Clone)]
+pub struct CleanOpts {
+    /// Why an active session is abandoned, kept in the backup and the event log
+    #[clap(long, conflicts_with = "no-reason")]
+    reason: Option<String>,
+
+    /// Abandon an active session without giving a reason
+    #[clap(long)]
+    no_reason: bool,
+
+    /// Set from the global `--yes`, doesn't ask and needs --reason or --no-reason for an
+    /// active session
+    #[clap(skip)]
+    pub yes: bool,
+
+    /// Clean every session of the repository, not only the one of --session
+    #[clap(long)]
+    pub all: bool,
+}
+
+#[derive(Debug, PartialEq)]
+enum Reason {
+    Given(Option<String>),
+    Ask,
+}
+
+#[derive(Serialize)]
+struct Backup<'a> {
+    session: &'a session::Session,
+    abandoned: Option<Abandoned<'a>>,
+}
+
+#[derive(Serialize)]
+struct Abandoned<'a> {
+    by: &'a str,
+    at: DateTime<Utc>,
+    reason: &'a Option<String>,
+}
+
+pub struct Clean<'a> {
+    store: &'a dyn session::Store,
+    events: &'a dyn events::Sink,
+    clock: &'a dyn Clock,
+    state_dir: StateDir,
+    opts: CleanOpts,
+    config: Config,
+    interactive: bool,
+    processes: &'a dyn teardown::Processes,
+}
+
+impl<'a> Clean<'a> {
+    pub fn new(
+        store: &'a impl session::Store,
+        events: &'a impl events::Sink,
+        clock: &'a impl Clock,
+        state_dir: StateDir,
+        opts: CleanOpts,
+        config: Config,
+    ) -> Clean<'a> {
+        Self {
+            store,
+            events,
+            clock,
+            state_dir,
+            opts,
+            config,
+            interactive: deadline::attended(),
+            processes: &teardown::System,
+        }
+    }
+
+    pub fn run(&self) -> Result<()> {
+        // A broken session is what clean is for, so it must not stop us
+        let session = match self.store.load() {
+            Ok(session) => session,
+            Err(err) => {
+                log::debug!("Cleaning unreadable session: {}", err);
+                self.teardown();
+                return Ok(self.store.clean()?);
+            }
+        };
+        roles::check(&session, &self.config.name, roles::Action::Settings)?;
+
+        if session.state == State::Stopped {
+            self.backup(&session, None)?;
+            self.teardown();
+            return Ok(self.store.clean()?);
+        }
+
+        let reason = match self.reason()? {
+            Reason::Given(reason) => reason,
+            Reason::Ask => {
+                let question = "The session is active, why is it abandoned?";
+                let reason = record::answer(
+                    question,
+                    prompt::terminal().input(question, Some(""), &|_| Ok(()))?,
+                );
+                Some(reason.trim().to_string()).filter(|reason| !reason.is_empty())
+            }
+        };
+
+        self.backup(&session, Some(&reason))?;
+        self.events.emit(&Event::SessionAbandoned {
+            session: session.id.clone(),
+            by: self.config.name.clone(),
+            reason,
+            from: session.state,
+        })?;
+        self.teardown();
+        self.store.clean()?;
+        log::info!(
+            "Abandoned the session, a backup is in {}",
+            self.state_dir.file(BACKUP_FILE).display()
+        );
+        Ok(())
+    }
+
+    /// How to get a reason for abandoning an active session
+    fn reason(&self) -> Result<Reason> {
+        let given = match (&self.opts.reason, self.opts.no_reason) {
+            (Some(reason), _) => Some(Some(reason.clone())),
+            (None, true) => Some(None),
+            (None, false) => None,
+        };
+        match given {
+            Some(reason) if self.interactive || self.opts.yes => Ok(Reason::Given(reason)),
+            None if self.interactive && !self.opts.yes => Ok(Reason::Ask),
+            _ => Err(anyhow!(
+                "The session is active, cleaning it unattended needs --yes with --reason or --no-reason"
+            )),
+        }
+    }
+
+    fn teardown(&self) {
+        teardown::teardown(&self.state_dir, self.processes, self.clock.now_utc()).report();
+    }
+
+    fn backup(&self, session: &session::Session, reason: Option<&Option<String>>) -> Result<()> {
+        let backup = Backup {
+            session,
+            abandoned: reason.map(|reason| Abandoned {
+                by: &self.config.name,
+                at: self.clock.now_utc(),
+                reason,
+            }),
+        };
+        self.state_dir.write(BACKUP_FILE, &backup)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::FakeClock;
+    use crate::events::Recorder;
+    use crate::git::fake::MemoryStore;
+    use crate::session::Store;
+
+    fn clean(args: &[&str], interactive: bool, state: State) -> (Result<()>, Vec<Event>, bool) {
+        let memory = MemoryStore::default();
+        let store = session::SessionStore::new(&memory);
+        store
+            .save(session::Session {
+                id: Some("3f2a9c1e7b04".into()),
+                state,
+                ..session::Session::default()
+            })
+            .unwrap();
+        let events = Recorder::default();
+        let dir = tempfile::tempdir().unwrap();
+        let clock = FakeClock::default();
+        let mut clean = Clean::new(
+            &store,
+            &events,
+            &clock,
+            StateDir::new(dir.path().into()),
+            CleanOpts {
+                yes: args.contains(&"--yes"),
+                ..CleanOpts::parse_from(args.iter().filter(|arg| **arg != "--yes"))
+            },
+            Config {
+                name: "alice".into(),
+                ..Config::default()
+            },
+        );
+        clean.interactive = interactive;
+
+        let result = clean.run();
+        let cleaned = memory.documents.borrow().is_empty();
+        let events = events.events.borrow().clone();
+        (result, events, cleaned)
+    }
+
+    fn working() -> State {
+        State::Working {
+            driver: "bob".into(),
+        }
+    }
+
+    #[test]
+    fn unattended_active_needs_yes_and_reason() {
+        for args in [
+            &["clean"][..],
+            &["clean", "--yes"],
+            &["clean", "--reason", "oops"],
+            &["clean", "--no-reason"],
+        ] {
+            let (result, events, cleaned) = clean(args, false, working());
+            assert!(result.is_err(), "{:?}", args);
+            assert!(events.is_empty(), "{:?}", args);
+            assert!(!cleaned, "{:?}", args);
+        }
+    }
+
+    #[test]
+    fn unattended_with_yes() {
+        let (result, events, cleaned) = clean(
+            &["clean", "--yes", "--reason", "wrong repo"],
+            false,
+            working(),
+        );
+        assert!(result.is_ok());
+        assert!(cleaned);
+        assert_eq!(
+            events,
+            vec![Event::SessionAbandoned {
+                session: Some("3f2a9c1e7b04".into()),
+                by: "alice".into(),
+                reason: Some("wrong repo".into()),
+                from: working(),
+            }]
+        );
+
+        let (result, events, _) = clean(&["clean", "--yes", "--no-reason"], false, working());
+        assert!(result.is_ok());
+        assert!(matches!(
+            &events[..],
+            [Event::SessionAbandoned { reason: None, .. }]
+        ));
+    }
+
+    #[test]
+    fn interactive_reason_flags() {
+        let (result, events, _) = clean(&["clean", "--reason", "done elsewhere"], true, working());
+        assert!(result.is_ok());
+        assert!(matches!(
+            &events[..],
+            [Event::SessionAbandoned { reason: Some(reason), .. }] if reason == "done elsewhere"
+        ));
+        // Asking only happens when nothing was given
+        let (result, _, cleaned) = clean(&["clean", "--yes"], true, working());
+        assert!(result.is_err());
+        assert!(!cleaned);
+    }
+
+    #[test]
+    fn stopped_needs_no_reason() {
+        let (result, events, cleaned) = clean(&["clean"], false, State::Stopped);
+        assert!(result.is_ok());
+        assert!(cleaned);
+        assert!(events.is_empty());
+    }
+
+    #[test]
+    fn reason_conflicts_with_no_reason() {
+        assert!(CleanOpts::try_parse_from(["clean", "--reason", "x", "--no-reason"]).is_err());
+    }
+}
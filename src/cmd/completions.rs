@@ -0,0 +1,27 @@
+use crate::completions::{self, Shell, SHELLS};
+use anyhow::Result;
+use clap::{self, App, Clap};
+
+#[derive(Clap, Debug)]
+pub struct CompletionsOpts {
+    /// The shell to complete in, one of bash, zsh, fish and powershell
+    #[clap(name = "SHELL", possible_values = SHELLS)]
+    shell: Shell,
+}
+
+/// Prints the completion script of the whole command line, which `app` is
+pub struct Completions<'a> {
+    app: App<'a>,
+    opts: CompletionsOpts,
+}
+
+impl<'a> Completions<'a> {
+    pub fn new(app: App<'a>, opts: CompletionsOpts) -> Completions<'a> {
+        Self { app, opts }
+    }
+
+    pub fn run(&self) -> Result<()> {
+        print!("{}", completions::script(self.opts.shell, &self.app, "mob"));
+        Ok(())
+    }
+}
@@ -0,0 +1,121 @@
+use crate::config::{self, Source, Target};
+use crate::git::config::Scope;
+use anyhow::Result;
+use clap::{self, Clap};
+
+#[derive(Clap, Debug)]
+pub struct ConfigOpts {
+    #[clap(subcommand)]
+    action: Action,
+}
+
+#[derive(Clap, Debug)]
+enum Action {
+    /// Print the config as mob reads it and where it came from
+    ///
+    /// Later ones win: the defaults, ~/.mob, the mob.* keys of git config, then variables
+    /// like MOB_REMOTE or MOB_DONE_MANY_COMMITS, which name a key with underscores for dots.
+    #[clap(name = "show")]
+    Show,
+
+    /// Print a key of the config, failing for keys mob doesn't have
+    #[clap(name = "get")]
+    Get(GetOpts),
+
+    /// Set a key of the config, adding to keys that take a list
+    #[clap(name = "set")]
+    Set(SetOpts),
+}
+
+#[derive(Clap, Debug)]
+struct GetOpts {
+    /// Like done.many_commits, or mob.done.many-commits as git config names it
+    #[clap(name = "KEY")]
+    key: String,
+}
+
+#[derive(Clap, Debug)]
+struct SetOpts {
+    /// Write to the git config of the repository instead of ~/.mob
+    #[clap(long)]
+    git: bool,
+
+    /// Write to the git config of the user, for every repository
+    #[clap(long, requires = "git")]
+    global: bool,
+
+    /// Like done.many_commits, or mob.done.many-commits as git config names it
+    #[clap(name = "KEY")]
+    key: String,
+
+    #[clap(name = "VALUE")]
+    value: String,
+}
+
+/// Runs before the config is loaded, so a broken or missing one can be fixed with it
+pub struct Configure {
+    opts: ConfigOpts,
+    source: Source,
+}
+
+impl Configure {
+    pub fn new(opts: ConfigOpts, source: Source) -> Configure {
+        Self { opts, source }
+    }
+
+    pub fn run(&self) -> Result<()> {
+        match &self.opts.action {
+            Action::Show => print!("{}", config::show(&self.source)?),
+            Action::Get(get) => {
+                if let Some(value) = config::get(&self.source, &get.key)? {
+                    println!("{}", value);
+                }
+            }
+            Action::Set(set) => {
+                let target = match (set.git, set.global) {
+                    (false, _) => Target::File,
+                    (true, false) => Target::Git(Scope::Repository),
+                    (true, true) => Target::Git(Scope::Global),
+                };
+                config::set(&self.source, &set.key, &set.value, target)?
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timekeeping::Minutes;
+    use std::fs;
+
+    #[test]
+    fn sets_keys_of_the_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(".mob");
+        fs::write(&path, "name = \"alice\"\nremote = \"origin\"\n").unwrap();
+        let set = |args: &[&str]| {
+            let args = [&["config", "set"], args].concat();
+            Configure::new(ConfigOpts::parse_from(args), Source::file(&path)).run()
+        };
+
+        set(&["done.many_commits", "8"]).unwrap();
+        set(&["mob.handover.nag-after", "4"]).unwrap();
+        let config = config::load(&Source::file(&path)).unwrap();
+        assert_eq!(config.done.many_commits, 8);
+        assert_eq!(config.handover.nag_after, Minutes(4));
+
+        let err = set(&["done.many_commits", "many"]).unwrap_err();
+        assert!(err.to_string().contains("not a whole number"), "{}", err);
+        let err = set(&["done.many_comits", "8"]).unwrap_err();
+        assert!(err.to_string().contains("not a setting of mob"), "{}", err);
+        assert_eq!(
+            config::load(&Source::file(&path))
+                .unwrap()
+                .done
+                .many_commits,
+            8
+        );
+    }
+}
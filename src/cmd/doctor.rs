@@ -0,0 +1,257 @@
+use super::lfs;
+use super::remote::{access, choose_remote, Access};
+use crate::session::{self, duplicates};
+use crate::{config::Config, git, state_dir::StateDir};
+use anyhow::Result;
+use clap::{self, Clap};
+
+#[derive(Clap, Debug)]
+pub struct DoctorOpts {
+    /// Repair what was found and save the session
+    #[clap(long)]
+    fix: bool,
+}
+
+/// Finds damage old versions of mob left in the session, and a remote that takes no pushes
+pub struct Doctor<'a> {
+    git: &'a dyn git::Git,
+    store: &'a dyn session::Store,
+    state_dir: StateDir,
+    opts: DoctorOpts,
+    config: Config,
+}
+
+impl<'a> Doctor<'a> {
+    pub fn new(
+        git: &'a impl git::Git,
+        store: &'a impl session::Store,
+        state_dir: StateDir,
+        opts: DoctorOpts,
+        config: Config,
+    ) -> Doctor<'a> {
+        Self {
+            git,
+            store,
+            state_dir,
+            opts,
+            config,
+        }
+    }
+
+    pub fn run(&self) -> Result<()> {
+        let duplicates = self.duplicates()?;
+        let remote = self.remote()?;
+        let lfs = self.lfs()?;
+        let signing = self.signing();
+        if !duplicates && !remote && !lfs && !signing {
+            log::info!("No problems found");
+        }
+        Ok(())
+    }
+
+    fn duplicates(&self) -> Result<bool> {
+        let session = self.store.load()?;
+        let found = duplicates::find(&session);
+        if found.is_empty() {
+            return Ok(false);
+        }
+
+        for duplicate in &found {
+            let variants: Vec<String> = duplicate
+                .variants
+                .iter()
+                .map(|variant| format!("{:?}", variant))
+                .collect();
+            log::info!(
+                "{} are the same driver, keeping {:?}",
+                variants.join(", "),
+                duplicate.canonical
+            );
+        }
+        if !self.opts.fix {
+            log::info!("Run mob doctor --fix to merge them");
+            return Ok(true);
+        }
+
+        self.store.save(duplicates::merge(session, &found))?;
+        log::info!("Merged the drivers");
+        Ok(true)
+    }
+
+    /// Git LFS not being set up here, mob next would refuse to commit
+    fn lfs(&self) -> Result<bool> {
+        if !self.git.is_repository() {
+            return Ok(false);
+        }
+        match lfs::problem(self.git)? {
+            Some(problem) => {
+                log::warn!("{}", problem);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Commits that are to be signed but can't be
+    fn signing(&self) -> bool {
+        if !self.config.commit.sign || !self.git.is_repository() {
+            return false;
+        }
+        match git::signing::check(self.git) {
+            Ok(()) => false,
+            Err(err) => {
+                log::warn!("{:#}", err);
+                true
+            }
+        }
+    }
+
+    fn remote(&self) -> Result<bool> {
+        if !self.git.is_repository() {
+            return Ok(false);
+        }
+        let remote = match access(self.git, &self.config.remote)? {
+            Access::Writable | Access::Unknown => return Ok(false),
+            Access::ReadOnly => {
+                log::warn!("{} doesn't accept your pushes", self.config.remote);
+                return Ok(true);
+            }
+            Access::Mirror { writable } => writable,
+        };
+        if !self.opts.fix {
+            log::info!(
+                "{} doesn't accept your pushes but {} does, run mob doctor --fix to use it",
+                self.config.remote,
+                remote
+            );
+            return Ok(true);
+        }
+        choose_remote(&self.state_dir, &remote)?;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::fake::MemoryStore;
+    use crate::session::Store;
+
+    fn doctor<'a>(store: &'a session::SessionStore, args: &[&str]) -> Doctor<'a> {
+        Doctor::new(
+            &git::NoopGit,
+            store,
+            StateDir::new(std::env::temp_dir().join("mob-doctor-test")),
+            DoctorOpts::parse_from(args),
+            Config::default(),
+        )
+    }
+
+    fn messy() -> session::Session {
+        session::Session {
+            id: Some("3f2a9c1e7b04".into()),
+            drivers: session::Drivers::new(vec!["anna".into(), "bob".into(), "Anna ".into()]),
+            state: session::State::WaitingForNext {
+                next: Some("Anna ".into()),
+            },
+            ..session::Session::default()
+        }
+    }
+
+    #[test]
+    fn reports_without_fix() {
+        let memory = MemoryStore::default();
+        let store = session::SessionStore::new(&memory);
+        store.save(messy()).unwrap();
+        let saves = *memory.saves.borrow();
+
+        doctor(&store, &["doctor"]).run().unwrap();
+
+        assert_eq!(*memory.saves.borrow(), saves);
+        assert_eq!(store.load().unwrap(), messy());
+    }
+
+    #[test]
+    fn fixes() {
+        let memory = MemoryStore::default();
+        let store = session::SessionStore::new(&memory);
+        store.save(messy()).unwrap();
+
+        doctor(&store, &["doctor", "--fix"]).run().unwrap();
+
+        let session = store.load().unwrap();
+        assert_eq!(session.drivers.all(), vec!["anna", "bob"]);
+        assert_eq!(
+            session.state,
+            session::State::WaitingForNext {
+                next: Some("anna".into())
+            }
+        );
+        assert!(duplicates::find(&session).is_empty());
+    }
+
+    #[test]
+    fn switches_from_a_mirror() {
+        let memory = MemoryStore::default();
+        let store = session::SessionStore::new(&memory);
+        let mut git = git::fake::FakeGit {
+            remotes: vec!["origin".into(), "upstream".into()],
+            ..git::fake::FakeGit::default()
+        };
+        git.urls
+            .insert("origin".into(), "git://github.com/team/repo".into());
+        git.urls
+            .insert("upstream".into(), "git@github.com:team/repo.git".into());
+        git.revs.insert("HEAD".into(), "a1".into());
+        git.fail(
+            "push --dry-run --no-verify --quiet origin",
+            0,
+            "fatal: read-only",
+        );
+        let dir = tempfile::tempdir().unwrap();
+        let state_dir = StateDir::new(dir.path().to_path_buf());
+
+        Doctor::new(
+            &git,
+            &store,
+            state_dir.clone(),
+            DoctorOpts::parse_from(["doctor", "--fix"]),
+            Config::default(),
+        )
+        .run()
+        .unwrap();
+        assert_eq!(
+            crate::cmd::chosen_remote(&state_dir).as_deref(),
+            Some("upstream")
+        );
+    }
+
+    #[test]
+    fn points_out_lfs_without_setup() {
+        let memory = MemoryStore::default();
+        let store = session::SessionStore::new(&memory);
+        store.save(session::Session::default()).unwrap();
+        let git = git::fake::FakeGit {
+            lfs: git::Lfs::NotInstalled,
+            ..git::fake::FakeGit::default()
+        };
+        let doctor = Doctor::new(
+            &git,
+            &store,
+            StateDir::new(std::env::temp_dir().join("mob-doctor-test")),
+            DoctorOpts::parse_from(["doctor"]),
+            Config::default(),
+        );
+        assert!(doctor.lfs().unwrap());
+
+        let ready = git::fake::FakeGit {
+            lfs: git::Lfs::Ready,
+            ..git::fake::FakeGit::default()
+        };
+        let doctor = Doctor {
+            git: &ready,
+            ..doctor
+        };
+        assert!(!doctor.lfs().unwrap());
+    }
+}
@@ -1,22 +1,197 @@
-use crate::{config::Config, git, session};
-use anyhow::Result;
-use session::State;
+use super::{editor, push, resolve, stash, stats};
+use crate::config::{Config, Confirmation, DoneConfig};
+use crate::{clock::Clock, deadline, env, git, prompt, record, session, state_dir::StateDir};
+use anyhow::{anyhow, Result};
+use clap::{self, Clap};
+use session::{roles, teardown, State};
+use std::{fmt, str::FromStr};
+
+#[derive(Clap, Debug)]
+pub struct DoneOpts {
+    /// Set from the global `--yes`, merges into the base branch without asking
+    #[clap(skip)]
+    pub yes: bool,
+
+    /// Merge a branch that shares no history with the base branch, like an orphan branch
+    #[clap(long)]
+    allow_unrelated_histories: bool,
+
+    /// Deliver to this branch instead of the base branch, like a release branch
+    #[clap(long)]
+    target: Option<String>,
+
+    /// How the mob's commits get onto --target, rebase also brings along the
+    /// commits of the base branch the target lacks [default: cherry-pick]
+    #[clap(long, requires = "target", possible_values = &["cherry-pick", "rebase"])]
+    strategy: Option<Strategy>,
+
+    /// Finish delivering to --target after resolving the conflicts of replaying onto it
+    #[clap(long = "continue", requires = "target")]
+    resume: bool,
+
+    /// The message of the squashed work, without it the editor opens with the subjects of
+    /// the mob's commits. With --no-squash the message of the merge.
+    #[clap(long, short)]
+    message: Option<String>,
+
+    /// Merge with every commit of the session instead of squashing them into one
+    #[clap(long)]
+    no_squash: bool,
+}
+
+/// How the mob's commits are replayed onto another target branch
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Strategy {
+    CherryPick,
+    Rebase,
+}
+
+impl FromStr for Strategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "cherry-pick" => Ok(Strategy::CherryPick),
+            "rebase" => Ok(Strategy::Rebase),
+            _ => Err(format!("unknown strategy {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for Strategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Strategy::CherryPick => "cherry-pick",
+            Strategy::Rebase => "rebase",
+        })
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Gate {
+    Proceed,
+    Ask,
+    Refuse,
+}
+
+/// Whether merging `commits` commits into the base branch needs a confirmation first
+fn gate(config: &DoneConfig, commits: usize, interactive: bool) -> Gate {
+    let required = match config.require_confirmation {
+        Confirmation::Always => true,
+        Confirmation::WhenMany => commits >= config.many_commits,
+        Confirmation::Never => false,
+    };
+    match (required, interactive) {
+        (false, _) => Gate::Proceed,
+        (true, true) => Gate::Ask,
+        (true, false) => Gate::Refuse,
+    }
+}
+
+const MESSAGE_HELP: &str = "\
+# Describe what the mob did, the subjects of its commits are above to start from.
+# Lines starting with # are left out, an empty message stops mob done.
+";
+
+/// The subjects of `commits` as `git log --oneline` lists them, each once
+fn subjects(commits: &[String]) -> Vec<&str> {
+    let mut subjects: Vec<&str> = vec![];
+    for commit in commits {
+        let subject = commit
+            .split_once(' ')
+            .map(|(_, subject)| subject.trim())
+            .unwrap_or_default();
+        if !subject.is_empty() && !subjects.contains(&subject) {
+            subjects.push(subject);
+        }
+    }
+    subjects
+}
+
+/// The commit to run by hand with `trailers`, quoted for a POSIX shell as names can have a `'`
+fn commit_hint(trailers: &[String]) -> String {
+    let trailers: String = trailers
+        .iter()
+        .map(|trailer| format!(" --trailer {}", env::quote(trailer, None)))
+        .collect();
+    format!("git commit -m 'describe what changed'{}", trailers)
+}
+
+/// `Co-authored-by` trailers for everyone but `me` who drove, with the email from
+/// `done.emails`. Drivers without one are left out unless `done.placeholder_emails`.
+fn co_authors(drivers: &[String], me: &str, config: &DoneConfig) -> Vec<String> {
+    let mut missing = vec![];
+    let trailers = drivers
+        .iter()
+        .filter(|driver| driver.as_str() != me)
+        .filter_map(|driver| {
+            let email = match config.emails.get(driver) {
+                Some(email) => email.clone(),
+                None if config.placeholder_emails => format!(
+                    "{}@mob.invalid",
+                    driver
+                        .to_lowercase()
+                        .split_whitespace()
+                        .collect::<Vec<_>>()
+                        .join(".")
+                ),
+                None => {
+                    missing.push(driver.as_str());
+                    return None;
+                }
+            };
+            Some(format!("Co-authored-by: {} <{}>", driver, email))
+        })
+        .collect();
+    if !missing.is_empty() {
+        log::warn!(
+            "Not crediting {} as co-authors, add their emails to done.emails in ~/.mob",
+            missing.join(", ")
+        );
+    }
+    trailers
+}
 
 pub struct Done<'a> {
     git: &'a dyn git::Git,
     store: &'a dyn session::Store,
+    clock: &'a dyn Clock,
+    state_dir: StateDir,
+    opts: DoneOpts,
     config: Config,
+    interactive: bool,
+    editor: String,
+    processes: &'a dyn teardown::Processes,
 }
 
 impl<'a> Done<'a> {
-    pub fn new(git: &'a impl git::Git, store: &'a impl session::Store, config: Config) -> Done<'a> {
-        Self { git, store, config }
+    pub fn new(
+        git: &'a impl git::Git,
+        store: &'a impl session::Store,
+        clock: &'a impl Clock,
+        state_dir: StateDir,
+        opts: DoneOpts,
+        config: Config,
+    ) -> Done<'a> {
+        Self {
+            git,
+            store,
+            clock,
+            state_dir,
+            opts,
+            config,
+            interactive: deadline::attended(),
+            editor: editor::command(),
+            processes: &teardown::System,
+        }
     }
 
     pub fn run(&self) -> Result<()> {
         let me = self.config.name.clone();
 
+        self.check_repository()?;
         let session = self.store.load()?;
+        roles::check(&session, &me, roles::Action::Done)?;
         match &session.state {
             State::Stopped => {
                 log::warn!("No current mob session, run mob start");
@@ -24,25 +199,57 @@ impl<'a> Done<'a> {
             State::Working { driver } if driver == me.as_str() => self.done(session)?,
             State::Working { driver } => {
                 log::warn!("{} is currently working", driver);
-                let take_over = dialoguer::Confirm::new()
-                    .with_prompt("Merge anyway with risk of loosing work?")
-                    .default(false)
-                    .interact()?;
+                let prompt = "Merge anyway with risk of loosing work?";
+                let take_over = match deadline::answer(prompt, Some(false))? {
+                    Some(take_over) => take_over,
+                    None => record::answer(prompt, prompt::terminal().confirm(prompt, false)?),
+                };
 
                 if take_over {
                     self.done(session)?;
                 }
             }
-            State::WaitingForNext { .. } => self.done(session)?,
+            State::WaitingForNext { .. } | State::Paused { .. } => self.done(session)?,
         }
         Ok(())
     }
 
+    /// Refuses the options about merging without a repository, they would be ignored
+    fn check_repository(&self) -> Result<()> {
+        let merging = [
+            (
+                "--allow-unrelated-histories",
+                self.opts.allow_unrelated_histories,
+            ),
+            ("--target", self.opts.target.is_some()),
+            ("--strategy", self.opts.strategy.is_some()),
+            ("--continue", self.opts.resume),
+            ("--message", self.opts.message.is_some()),
+            ("--no-squash", self.opts.no_squash),
+        ];
+        match merging.iter().find(|(_, set)| *set) {
+            Some((option, _)) if !self.git.is_repository() => Err(anyhow!(
+                "{} needs a git repository, this session has none",
+                option
+            )),
+            _ => Ok(()),
+        }
+    }
+
     fn done(&self, session: session::Session) -> Result<()> {
-        if !self.git.tree_is_clean()? {
+        if !self.git.is_repository() {
+            log::info!("Session stopped");
+            return self.stop(session, None);
+        }
+        if self.opts.resume {
+            return self.resume(session);
+        }
+
+        let snapshot = self.git.snapshot()?;
+        if !snapshot.is_clean() {
             log::info!("Working tree is dirty, committing first");
 
-            if !self.on_branch(session.branches.branch.as_str())? {
+            if !snapshot.is_on(session.branches.branch.as_str()) {
                 log::error!(
                     "You must be on branch {} to run done",
                     session.branches.branch.as_str()
@@ -51,63 +258,1164 @@ impl<'a> Done<'a> {
             }
 
             self.git.run(&["add", "--all"])?;
-            self.git.run(&[
-                "commit",
-                "--message",
-                session.settings.as_ref().unwrap().commit_message.as_str(),
-                "--no-verify",
-            ])?;
+            git::signing::commit(
+                self.git,
+                self.config.commit.sign,
+                &[
+                    "--message",
+                    session.settings.as_ref().unwrap().commit_message.as_str(),
+                    "--no-verify",
+                ],
+            )?;
         }
 
         let remote_branches = session.branches.with_remote(&self.config.remote);
 
-        self.git.run(&[
-            "push",
-            "--no-verify",
-            self.config.remote.as_str(),
-            session.branches.branch.as_str(),
-        ])?;
+        push::push(
+            self.git,
+            &self.config.remote,
+            &session.branches.branch,
+            false,
+        )?;
 
         self.git.run(&["fetch", "--all", "--prune"])?;
-        self.git
-            .run(&["checkout", session.branches.base_branch.as_str()])?;
 
-        self.git
-            .run(&["merge", remote_branches.base_branch.as_str(), "--ff-only"])?;
+        if let Some(target) = &self.opts.target {
+            let target = self.resolve_target(&session, target)?;
+            return self.retarget(session, &target);
+        }
 
-        self.git.run(&[
-            "merge",
-            "--squash",
-            "--ff",
-            session.branches.branch.as_str(),
-        ])?;
+        let unrelated = self
+            .git
+            .merge_base(&remote_branches.base_branch, &session.branches.branch)?
+            .is_none();
+        if unrelated && !self.opts.allow_unrelated_histories {
+            return Err(anyhow!(
+                "The histories of {} and {} are unrelated, run mob done --allow-unrelated-histories to merge them anyway",
+                session.branches.branch,
+                remote_branches.base_branch
+            ));
+        }
+
+        if !self.confirm(&remote_branches.base_branch, &session.branches.branch)? {
+            log::warn!("Nothing was merged, the session is still running");
+            return Ok(());
+        }
+
+        let range = format!(
+            "{}..{}",
+            remote_branches.base_branch, session.branches.branch
+        );
+        let message = self.message(&range)?;
+        let base = session.branches.base_branch.clone();
+        self.land(
+            session,
+            &base,
+            &remote_branches.base_branch,
+            unrelated,
+            message,
+        )
+    }
+
+    /// The message of the squash, from --message or written in the editor starting from
+    /// the subjects of the commits in `range`. Without anyone to write it the squash stays
+    /// staged.
+    fn message(&self, range: &str) -> Result<Option<String>> {
+        if self.opts.message.is_some() || self.opts.no_squash || !self.interactive {
+            return Ok(self.opts.message.clone());
+        }
+        let commits = self.git.log_oneline(range)?;
+        let text = format!("{}\n\n{}", subjects(&commits).join("\n"), MESSAGE_HELP);
+        let message = editor::uncommented(&editor::edit(&self.editor, "mob-done-", &text)?);
+        if message.is_empty() {
+            return Err(anyhow!(
+                "The message is empty, nothing was merged and the session is still running"
+            ));
+        }
+        Ok(Some(message))
+    }
+
+    /// Squashes the mob branch onto `base` from `remote_base` and commits it with `message`,
+    /// or merges it with --no-squash, and ends the session
+    fn land(
+        &self,
+        session: session::Session,
+        base: &str,
+        remote_base: &str,
+        unrelated: bool,
+        message: Option<String>,
+    ) -> Result<()> {
+        self.git.run(&["checkout", base])?;
+
+        self.git.run(&["merge", "--ff-only", "--", remote_base])?;
+
+        let mut trailers = co_authors(&session.drivers.all(), &self.config.name, &self.config.done);
+        trailers.extend(session.id.iter().map(|id| format!("Mob-Session: {}", id)));
+        let branch = session.branches.branch.as_str();
+        let committed = message.is_some();
+        let message = message.unwrap_or_else(|| format!("Merge {} into {}", branch, base));
+        let message = match trailers.is_empty() {
+            true => message,
+            false => format!("{}\n\n{}", message, trailers.join("\n")),
+        };
+        let sign = self.config.commit.sign && self.opts.no_squash;
+        let mut merge = match self.opts.no_squash {
+            true => vec!["merge", "--no-ff", "--message", message.as_str()],
+            false => vec!["merge", "--squash", "--ff"],
+        };
+        if sign {
+            merge.push("-S");
+        }
+        if unrelated {
+            merge.push("--allow-unrelated-histories");
+        }
+        merge.extend(&["--", branch]);
+        match self.git.run(&merge) {
+            Err(err) if sign => {
+                let err = git::signing::explain(err);
+                return Err(self.undo_merge(err, branch, base));
+            }
+            Err(err) if self.opts.no_squash => return Err(self.undo_merge(err, branch, base)),
+            merged => merged?,
+        }
 
         // Delete mob branch
         self.git
-            .run(&["branch", "-D", session.branches.branch.as_str()])?;
+            .run(&["branch", "-D", "--", session.branches.branch.as_str()])?;
         self.git.run(&[
             "push",
             "--no-verify",
             &self.config.remote,
             "--delete",
+            "--",
             session.branches.branch.as_str(),
         ])?;
 
+        let target = Some(base).filter(|base| *base != session.branches.base_branch);
+        if let Some(target) = target {
+            log::info!(
+                "Delivered to {} instead of {}",
+                target,
+                session.branches.base_branch
+            );
+        }
+        self.stop(session, target)?;
+        if self.opts.no_squash {
+            log::info!("Merged with all its commits, push {} to share them", base);
+            return Ok(());
+        }
+        if committed {
+            match git::signing::commit(self.git, self.config.commit.sign, &["--message", &message]) {
+                Ok(()) => return Ok(()),
+                // The session is over either way, what is left is an ordinary commit
+                Err(err) => log::warn!("Could not commit the squashed work: {:#}", err),
+            }
+        }
         log::info!("Run git diff --staged and then");
-        log::info!("git commit -m 'describe what changed'");
+        log::info!("{}", commit_hint(&trailers));
+        Ok(())
+    }
+
+    /// Takes back a merge that stopped, telling which files conflict. The session goes on
+    /// on the mob branch as if `mob done` had not run.
+    fn undo_merge(&self, err: anyhow::Error, branch: &str, base: &str) -> anyhow::Error {
+        let conflicted = match self.git.conflicted() {
+            Ok(conflicted) if !conflicted.is_empty() => conflicted,
+            _ => return err,
+        };
+        if let Err(err) = self
+            .git
+            .run(&["merge", "--abort"])
+            .and_then(|_| self.git.run(&["checkout", branch]))
+        {
+            return anyhow!(
+                "{} conflicts with {} in {} and the merge could not be taken back: {:#}",
+                branch,
+                base,
+                conflicted.join(", "),
+                err
+            );
+        }
+        anyhow!(
+            "{} conflicts with {} in {}, nothing was merged. Merge {} into {} and resolve the conflicts, then run mob done --no-squash again",
+            branch,
+            base,
+            conflicted.join(", "),
+            base,
+            branch
+        )
+    }
+
+    /// Replays the mob's commits onto `target` and lands them there instead of the base
+    fn retarget(&self, session: session::Session, target: &str) -> Result<()> {
+        let remote_branches = session.branches.with_remote(&self.config.remote);
+        let remote_target = self.remote_target(&session, target);
+        let branch = session.branches.branch.as_str();
+        let fork = match self.git.merge_base(&remote_branches.base_branch, branch)? {
+            Some(fork) => fork,
+            None => {
+                return Err(anyhow!(
+                    "The histories of {} and {} are unrelated, so there are no commits of the mob to deliver to {}",
+                    branch,
+                    remote_branches.base_branch,
+                    target
+                ))
+            }
+        };
+        if !self.confirm_target(
+            &session.branches.base_branch,
+            target,
+            &remote_target,
+            &fork,
+            branch,
+        )? {
+            log::warn!("Nothing was delivered, the session is still running");
+            return Ok(());
+        }
+        let message = self.message(&format!("{}..{}", fork, branch))?;
+
+        let strategy = self.opts.strategy.unwrap_or(Strategy::CherryPick);
+        let replayed = match strategy {
+            Strategy::CherryPick => {
+                self.git
+                    .run(&["checkout", "-B", branch, remote_target.as_str()])?;
+                // The mob branch was just pushed, its remote tip has all the commits
+                let commits = format!("{}..{}", fork, remote_branches.branch);
+                self.git.run(&["cherry-pick", commits.as_str()])
+            }
+            Strategy::Rebase => self.git.run(&["rebase", remote_target.as_str(), branch]),
+        };
+        if let Err(err) = replayed {
+            return Err(anyhow!(
+                "{:#}\nResolve the conflicts, run git {} --continue and then mob done --target {} --continue",
+                err,
+                strategy,
+                target
+            ));
+        }
+        self.land(session, target, &remote_target, false, message)
+    }
+
+    /// Lands on --target what got replayed onto it before the conflicts were resolved
+    fn resume(&self, session: session::Session) -> Result<()> {
+        let target = self.opts.target.as_deref().unwrap_or_default();
+        for (head, command) in &[
+            ("CHERRY_PICK_HEAD", "cherry-pick"),
+            ("REBASE_HEAD", "rebase"),
+        ] {
+            if self.git.rev_parse(head)?.is_some() {
+                return Err(anyhow!(
+                    "The {} onto {} is still going on, run git {} --continue once the conflicts are resolved",
+                    command,
+                    target,
+                    command
+                ));
+            }
+        }
+        let remote_target = self.remote_target(&session, target);
+        let branch = session.branches.branch.clone();
+        let message = self.message(&format!("{}..{}", remote_target, branch))?;
+        self.land(session, target, &remote_target, false, message)
+    }
+
+    /// The target, or which of the close ones was meant when the remote doesn't have it
+    fn resolve_target(&self, session: &session::Session, target: &str) -> Result<String> {
+        if self.git.has_branch(&self.remote_target(session, target))? {
+            return Ok(target.to_string());
+        }
+        let remote = session.branches.base_remote(&self.config.remote);
+        let missing = format!("There is no branch {} on {}", target, remote);
+        let existing = resolve::remote_branches(self.git, remote)?;
+        resolve::did_you_mean(&missing, target, &existing, self.interactive)?
+            .ok_or_else(|| anyhow!("{}, nothing was delivered", missing))
+    }
+
+    /// The target on the remote of the base branch
+    fn remote_target(&self, session: &session::Session, target: &str) -> String {
+        format!(
+            "{}/{}",
+            session.branches.base_remote(&self.config.remote),
+            target
+        )
+    }
+
+    /// Shows what lands on the base branch and asks if the config wants it
+    fn confirm(&self, base: &str, branch: &str) -> Result<bool> {
+        let range = format!("{}..{}", base, branch);
+        let commits = self.git.log_oneline(&range)?;
+        log::info!("{} commits will be merged into {}", commits.len(), base);
+        for commit in &commits {
+            println!("  {}", commit);
+        }
+        print!("{}", self.git.diffstat(&range)?);
+
+        if self.opts.yes {
+            return Ok(true);
+        }
+        match gate(&self.config.done, commits.len(), self.interactive) {
+            Gate::Proceed => Ok(true),
+            Gate::Ask => {
+                let question = format!("Merge these into {}?", base);
+                Ok(match deadline::answer(&question, Some(false))? {
+                    Some(merge) => merge,
+                    None => {
+                        record::answer(&question, prompt::terminal().confirm(&question, false)?)
+                    }
+                })
+            }
+            Gate::Refuse => Err(anyhow!(
+                "Merging into {} needs a confirmation, run mob done --yes",
+                base
+            )),
+        }
+    }
+
+    /// Shows what lands on `target` and what of `base` comes along to it, then asks
+    /// whether to go ahead as delivering elsewhere always needs a confirmation
+    fn confirm_target(
+        &self,
+        base: &str,
+        target: &str,
+        remote_target: &str,
+        fork: &str,
+        branch: &str,
+    ) -> Result<bool> {
+        let range = format!("{}..{}", fork, branch);
+        let commits = self.git.log_oneline(&range)?;
+        log::info!(
+            "{} commits will be delivered to {} instead of {}",
+            commits.len(),
+            target,
+            base
+        );
+        for commit in &commits {
+            println!("  {}", commit);
+        }
+        print!("{}", self.git.diffstat(&range)?);
+
+        let along = self
+            .git
+            .log_oneline(&format!("{}..{}", remote_target, fork))?;
+        if !along.is_empty() {
+            match self.opts.strategy {
+                Some(Strategy::Rebase) => log::warn!(
+                    "{} commits of {} are not on {} and come along",
+                    along.len(),
+                    base,
+                    target
+                ),
+                _ => log::info!(
+                    "{} commits of {} are not on {} and stay behind, run mob done --target {} --strategy rebase to bring them along",
+                    along.len(),
+                    base,
+                    target,
+                    target
+                ),
+            }
+            for commit in &along {
+                println!("  {}", commit);
+            }
+        }
+
+        if self.opts.yes {
+            return Ok(true);
+        }
+        if !self.interactive {
+            return Err(anyhow!(
+                "Delivering to {} instead of {} needs a confirmation, run mob done --target {} --yes",
+                target,
+                base,
+                target
+            ));
+        }
+        let question = format!("Deliver these to {}?", target);
+        Ok(match deadline::answer(&question, Some(false))? {
+            Some(deliver) => deliver,
+            None => record::answer(&question, prompt::terminal().confirm(&question, false)?),
+        })
+    }
 
+    /// Stops the session, noting in its report which `target` it was delivered to
+    /// instead of the base branch
+    fn stop(&self, session: session::Session, target: Option<&str>) -> Result<()> {
         let session = session::Session {
             state: State::Stopped,
             ..session
         };
-        self.store.save(session)?;
+        self.store.save(session.clone())?;
+        teardown::teardown(&self.state_dir, self.processes, self.clock.now_utc()).report();
+        if self.git.is_repository() {
+            stash::restore(self.git, &self.state_dir, self.interactive)?;
+        }
+        stats::archive(
+            &self.state_dir,
+            &self.config,
+            &session,
+            target,
+            self.clock.now_utc(),
+        );
+        let repo = self.git.root_commit().ok().flatten();
+        stats::metrics(
+            &self.config,
+            &session,
+            repo.as_deref(),
+            self.clock.now_utc(),
+        );
         Ok(())
     }
+}
 
-    fn on_branch(&self, branch: &str) -> Result<bool> {
-        Ok(match self.git.current_branch()? {
-            Some(name) => name == branch,
-            None => false,
-        })
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::FakeClock;
+    use crate::git::fake::{FakeGit, MemoryStore};
+    use crate::session::Store;
+
+    /// The options of `args`, with the global `--yes` among them
+    fn opts(args: &[&str]) -> DoneOpts {
+        DoneOpts {
+            yes: args.contains(&"--yes"),
+            ..DoneOpts::parse_from(args.iter().filter(|arg| **arg != "--yes"))
+        }
+    }
+
+    fn done(git: &FakeGit, base_remote: Option<&str>) -> Vec<String> {
+        run(git, base_remote, &["done"], Confirmation::Never, false).unwrap();
+        git.calls()
+    }
+
+    fn run(
+        git: &FakeGit,
+        base_remote: Option<&str>,
+        args: &[&str],
+        confirmation: Confirmation,
+        interactive: bool,
+    ) -> Result<()> {
+        let mut config = Config {
+            name: "alice".into(),
+            ..Config::default()
+        };
+        config.done.require_confirmation = confirmation;
+        run_with(git, base_remote, args, config, interactive)
+    }
+
+    fn run_with(
+        git: &FakeGit,
+        base_remote: Option<&str>,
+        args: &[&str],
+        config: Config,
+        interactive: bool,
+    ) -> Result<()> {
+        let memory = MemoryStore::default();
+        let store = session::SessionStore::new(&memory);
+        store
+            .save(session::Session {
+                id: Some("3f2a9c1e7b04".into()),
+                branches: session::Branches {
+                    base_remote: base_remote.map(String::from),
+                    ..session::Branches::default()
+                },
+                state: State::WaitingForNext { next: None },
+                settings: Some(session::Settings::default()),
+                ..session::Session::default()
+            })
+            .unwrap();
+        let state_dir = tempfile::tempdir().unwrap();
+        let clock = FakeClock::default();
+        let mut done = Done::new(
+            git,
+            &store,
+            &clock,
+            StateDir::new(state_dir.path().into()),
+            opts(args),
+            config,
+        );
+        done.interactive = interactive;
+        done.run()
+    }
+
+    #[test]
+    fn refuses_merging_options_without_a_repository() {
+        let memory = MemoryStore::default();
+        let store = session::SessionStore::new(&memory);
+        let state_dir = tempfile::tempdir().unwrap();
+        let clock = FakeClock::default();
+        let done = Done::new(
+            &git::NoopGit,
+            &store,
+            &clock,
+            StateDir::new(state_dir.path().into()),
+            opts(&["done", "--no-squash"]),
+            Config::default(),
+        );
+        assert_eq!(
+            done.run().unwrap_err().to_string(),
+            "--no-squash needs a git repository, this session has none"
+        );
+    }
+
+    #[test]
+    fn credits_co_authors() {
+        let drivers: Vec<String> = vec!["alice".into(), "bob".into(), "Carol Ann".into()];
+        let mut config = DoneConfig::default();
+        config.emails.insert("bob".into(), "bob@example.com".into());
+        assert_eq!(
+            co_authors(&drivers, "alice", &config),
+            vec!["Co-authored-by: bob <bob@example.com>"]
+        );
+        config.placeholder_emails = true;
+        assert_eq!(
+            co_authors(&drivers, "bob", &config),
+            vec![
+                "Co-authored-by: alice <alice@mob.invalid>",
+                "Co-authored-by: Carol Ann <carol.ann@mob.invalid>"
+            ]
+        );
+    }
+
+    #[test]
+    fn quotes_the_trailers_of_the_commit_hint() {
+        let trailers = vec![
+            "Co-authored-by: Dan O'Brien <dan@example.com>".to_string(),
+            "Mob-Session: 3f2a9c1e7b04".to_string(),
+        ];
+        assert_eq!(
+            commit_hint(&trailers),
+            "git commit -m 'describe what changed' \
+             --trailer 'Co-authored-by: Dan O'\\''Brien <dan@example.com>' \
+             --trailer 'Mob-Session: 3f2a9c1e7b04'"
+        );
+    }
+
+    /// The arguments of the commit on the base branch, none when the squash stays staged.
+    /// Someone is there to use `editor`, nobody without it.
+    fn commit_args(git: &FakeGit, args: &[&str], editor: Option<&str>) -> Result<Vec<String>> {
+        let memory = MemoryStore::default();
+        let store = session::SessionStore::new(&memory);
+        store
+            .save(session::Session {
+                id: Some("3f2a9c1e7b04".into()),
+                state: State::WaitingForNext { next: None },
+                drivers: session::Drivers::new(vec!["alice".into(), "bob".into()]),
+                settings: Some(session::Settings::default()),
+                ..session::Session::default()
+            })
+            .unwrap();
+        let mut config = Config {
+            name: "alice".into(),
+            ..Config::default()
+        };
+        config.done.require_confirmation = Confirmation::Never;
+        config
+            .done
+            .emails
+            .insert("bob".into(), "bob@example.com".into());
+        let state_dir = tempfile::tempdir().unwrap();
+        let clock = FakeClock::default();
+        let mut done = Done::new(
+            git,
+            &store,
+            &clock,
+            StateDir::new(state_dir.path().into()),
+            opts(args),
+            config,
+        );
+        done.interactive = editor.is_some();
+        if let Some(editor) = editor {
+            done.editor = editor.to_string();
+        }
+        done.run()?;
+        let calls = git.calls.borrow();
+        Ok(calls
+            .iter()
+            .find(|call| call[0] == "commit")
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    #[test]
+    fn commits_with_trailers() {
+        let commit = |args: &[&str], placeholder_emails| {
+            let git = FakeGit::default();
+            let memory = MemoryStore::default();
+            let store = session::SessionStore::new(&memory);
+            store
+                .save(session::Session {
+                    id: Some("3f2a9c1e7b04".into()),
+                    state: State::WaitingForNext { next: None },
+                    drivers: session::Drivers::new(vec![
+                        "alice".into(),
+                        "bob".into(),
+                        "carol".into(),
+                    ]),
+                    settings: Some(session::Settings::default()),
+                    ..session::Session::default()
+                })
+                .unwrap();
+            let mut config = Config {
+                name: "alice".into(),
+                ..Config::default()
+            };
+            config.done.require_confirmation = Confirmation::Never;
+            config
+                .done
+                .emails
+                .insert("bob".into(), "bob@example.com".into());
+            config.done.placeholder_emails = placeholder_emails;
+            let state_dir = tempfile::tempdir().unwrap();
+            let clock = FakeClock::default();
+            let mut done = Done::new(
+                &git,
+                &store,
+                &clock,
+                StateDir::new(state_dir.path().into()),
+                opts(args),
+                config,
+            );
+            done.interactive = false;
+            done.run().unwrap();
+            assert_eq!(store.load().unwrap().state, State::Stopped);
+            let calls = git.calls.borrow();
+            calls
+                .iter()
+                .find(|call| call[0] == "commit")
+                .cloned()
+                .unwrap_or_default()
+        };
+
+        assert_eq!(
+            commit(&["done", "--message", "Add login"], false),
+            vec![
+                "commit",
+                "--message",
+                "Add login\n\nCo-authored-by: bob <bob@example.com>\nMob-Session: 3f2a9c1e7b04"
+            ]
+        );
+        assert_eq!(
+            commit(&["done", "-m", "Add login"], true),
+            vec![
+                "commit",
+                "--message",
+                "Add login\n\nCo-authored-by: bob <bob@example.com>\nCo-authored-by: carol <carol@mob.invalid>\nMob-Session: 3f2a9c1e7b04"
+            ]
+        );
+        // Without a message and nobody to write one the squash stays staged for the mob
+        assert!(commit(&["done"], true).is_empty());
+    }
+
+    #[test]
+    fn lists_each_subject_once() {
+        let commits: Vec<String> = vec![
+            "a1b2c3d mob next [ci-skip]".into(),
+            "e4f5a6b Add the login form".into(),
+            "c7d8e9f mob next [ci-skip]".into(),
+            "0a1b2c3".into(),
+        ];
+        assert_eq!(
+            subjects(&commits),
+            vec!["mob next [ci-skip]", "Add the login form"]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn writes_the_message_in_the_editor() {
+        let git = FakeGit {
+            log: vec!["a1b2c3d Add the login form".into()],
+            ..FakeGit::default()
+        };
+        assert_eq!(
+            commit_args(&git, &["done"], Some("printf 'Add login\\n\\n# help\\n' >")).unwrap(),
+            vec![
+                "commit",
+                "--message",
+                "Add login\n\nCo-authored-by: bob <bob@example.com>\nMob-Session: 3f2a9c1e7b04"
+            ]
+        );
+        // Left as it is the subjects are the message
+        let git = FakeGit {
+            log: vec![
+                "a1b2c3d Add the login form".into(),
+                "e4f5a6b mob next [ci-skip]".into(),
+                "c7d8e9f mob next [ci-skip]".into(),
+            ],
+            ..FakeGit::default()
+        };
+        assert_eq!(
+            commit_args(&git, &["done"], Some("true")).unwrap()[2],
+            "Add the login form\nmob next [ci-skip]\n\nCo-authored-by: bob <bob@example.com>\nMob-Session: 3f2a9c1e7b04"
+        );
+        // --message is taken as it is
+        let git = FakeGit::default();
+        assert_eq!(
+            commit_args(&git, &["done", "-m", "Add logout"], Some("false")).unwrap(),
+            vec![
+                "commit",
+                "--message",
+                "Add logout\n\nCo-authored-by: bob <bob@example.com>\nMob-Session: 3f2a9c1e7b04"
+            ]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn stops_at_an_empty_message() {
+        let git = FakeGit {
+            log: vec!["a1b2c3d Add the login form".into()],
+            ..FakeGit::default()
+        };
+        let err = commit_args(&git, &["done"], Some("printf '# only the help\\n' >")).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "The message is empty, nothing was merged and the session is still running"
+        );
+        assert!(!git.calls().iter().any(|call| call.starts_with("checkout")
+            || call.starts_with("merge")
+            || call.starts_with("branch -D")));
+    }
+
+    #[test]
+    fn keeps_commits_without_squash() {
+        let git = FakeGit::default();
+        run(
+            &git,
+            None,
+            &["done", "--no-squash"],
+            Confirmation::Never,
+            false,
+        )
+        .unwrap();
+        let calls = git.calls();
+        assert!(calls.contains(
+            &"merge --no-ff --message Merge mob-session into master\n\nMob-Session: 3f2a9c1e7b04 -- mob-session"
+                .to_string()
+        ));
+        assert!(!calls.iter().any(|call| call.contains("--squash")));
+        assert!(calls.contains(&"branch -D -- mob-session".to_string()));
+        assert!(calls.contains(&"push --no-verify origin --delete -- mob-session".to_string()));
+        assert!(!calls.iter().any(|call| call.starts_with("commit")));
+    }
+
+    #[test]
+    fn signs_what_lands() {
+        let mut config = Config {
+            name: "alice".into(),
+            ..Config::default()
+        };
+        config.commit.sign = true;
+        let git = FakeGit::default();
+        let args = &["done", "-m", "Add login"];
+        run_with(&git, None, args, config.clone(), false).unwrap();
+        assert!(git
+            .calls()
+            .iter()
+            .any(|call| call.starts_with("commit -S --message Add login")));
+
+        let git = FakeGit::default();
+        run_with(&git, None, &["done", "--no-squash"], config, false).unwrap();
+        let calls = git.calls();
+        let merge = calls
+            .iter()
+            .find(|call| call.starts_with("merge --no-ff"))
+            .unwrap();
+        assert!(merge.contains(" -S -- mob-session"), "{}", merge);
+    }
+
+    #[test]
+    fn stops_at_conflicts_without_squash() {
+        let git = FakeGit {
+            conflicted: vec!["src/lib.rs".into(), "README.md".into()],
+            ..FakeGit::default()
+        };
+        git.fail(
+            "merge --no-ff",
+            0,
+            "CONFLICT (content): Merge conflict in src/lib.rs",
+        );
+        let err = run(
+            &git,
+            None,
+            &["done", "--no-squash"],
+            Confirmation::Never,
+            false,
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "mob-session conflicts with master in src/lib.rs, README.md, nothing was merged. Merge master into mob-session and resolve the conflicts, then run mob done --no-squash again"
+        );
+        let calls = git.calls();
+        let merged = calls
+            .iter()
+            .position(|call| call.starts_with("merge --no-ff"))
+            .unwrap();
+        assert_eq!(
+            calls[merged + 1..],
+            ["merge --abort", "checkout mob-session"]
+        );
+    }
+
+    #[test]
+    fn merges_base_from_session_remote() {
+        let calls = done(&FakeGit::default(), None);
+        assert!(calls.contains(&"merge --ff-only -- origin/master".to_string()));
+        assert!(calls.contains(&"push --no-verify origin -- mob-session".to_string()));
+    }
+
+    #[test]
+    fn merges_base_from_base_remote() {
+        let calls = done(&FakeGit::default(), Some("upstream"));
+        assert!(calls.contains(&"merge --ff-only -- upstream/master".to_string()));
+        assert!(calls.contains(&"push --no-verify origin -- mob-session".to_string()));
+        assert!(calls.contains(&"push --no-verify origin --delete -- mob-session".to_string()));
+    }
+
+    #[test]
+    fn queries_the_tree_once() {
+        let git = FakeGit {
+            snapshot: git::Snapshot {
+                branch: Some("mob-session".into()),
+                unstaged: 1,
+                ..git::Snapshot::default()
+            },
+            ..FakeGit::default()
+        };
+        let calls = done(&git, None);
+        assert!(calls.contains(&"add --all".to_string()));
+        assert_eq!(*git.snapshots.borrow(), 1);
+    }
+
+    #[test]
+    fn unrelated_histories_take_a_flag() {
+        let orphan = || FakeGit {
+            merge_base: None,
+            ..FakeGit::default()
+        };
+        let git = orphan();
+        let err = run(&git, None, &["done"], Confirmation::Never, false).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "The histories of mob-session and origin/master are unrelated, run mob done --allow-unrelated-histories to merge them anyway"
+        );
+        assert!(!merged(&git));
+
+        let git = orphan();
+        let args = &["done", "--allow-unrelated-histories"];
+        run(&git, None, args, Confirmation::Never, false).unwrap();
+        assert!(git.calls().contains(
+            &"merge --squash --ff --allow-unrelated-histories -- mob-session".to_string()
+        ));
+        // Related histories merge as before, with or without the flag
+        let git = FakeGit::default();
+        run(&git, None, args, Confirmation::Never, false).unwrap();
+        assert!(git
+            .calls()
+            .contains(&"merge --squash --ff -- mob-session".to_string()));
+    }
+
+    /// What ran once the mob branch was pushed and fetched
+    fn after_fetch(git: &FakeGit) -> Vec<String> {
+        let calls = git.calls();
+        let fetch = calls.iter().position(|call| call == "fetch --all --prune");
+        calls[fetch.unwrap() + 1..].to_vec()
+    }
+
+    fn landed_on_release() -> Vec<&'static str> {
+        vec![
+            "checkout release/1.4",
+            "merge --ff-only -- origin/release/1.4",
+            "merge --squash --ff -- mob-session",
+            "branch -D -- mob-session",
+            "push --no-verify origin --delete -- mob-session",
+        ]
+    }
+
+    /// A remote with release branches to deliver to
+    fn releases() -> FakeGit {
+        FakeGit {
+            branches: ["origin/master", "origin/release/1.3", "origin/release/1.4"]
+                .iter()
+                .map(|branch| branch.to_string())
+                .collect(),
+            ..FakeGit::default()
+        }
+    }
+
+    #[test]
+    fn cherry_picks_onto_the_target() {
+        let git = releases();
+        let args = &["done", "--target", "release/1.4", "--yes"];
+        run(&git, None, args, Confirmation::Never, false).unwrap();
+        let mut expected = vec![
+            "checkout -B mob-session origin/release/1.4",
+            "cherry-pick c0ffee..origin/mob-session",
+        ];
+        expected.extend(landed_on_release());
+        assert_eq!(after_fetch(&git), expected);
+    }
+
+    #[test]
+    fn rebases_onto_the_target() {
+        let git = releases();
+        let args = &[
+            "done",
+            "--target",
+            "release/1.4",
+            "--strategy",
+            "rebase",
+            "--yes",
+        ];
+        run(&git, None, args, Confirmation::Never, false).unwrap();
+        let mut expected = vec!["rebase origin/release/1.4 mob-session"];
+        expected.extend(landed_on_release());
+        assert_eq!(after_fetch(&git), expected);
+    }
+
+    #[test]
+    fn another_target_needs_a_confirmation() {
+        let git = releases();
+        let args = &["done", "--target", "release/1.4"];
+        let err = run(&git, None, args, Confirmation::Never, false).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Delivering to release/1.4 instead of master needs a confirmation, run mob done --target release/1.4 --yes"
+        );
+        assert!(after_fetch(&git).is_empty());
+
+        let git = releases();
+        crate::record::replay(vec![crate::record::Answer {
+            question: "Deliver these to release/1.4?".into(),
+            answer: serde_json::json!(true),
+        }]);
+        let args = &["done", "--target", "release/1.4", "-m", "Add login"];
+        let asked = run(&git, None, args, Confirmation::Never, true);
+        crate::record::stop_replay();
+        asked.unwrap();
+        assert!(after_fetch(&git).contains(&"cherry-pick c0ffee..origin/mob-session".to_string()));
+    }
+
+    #[test]
+    fn continues_after_conflicts() {
+        let git = releases();
+        git.fail(
+            "cherry-pick",
+            0,
+            "CONFLICT (content): Merge conflict in src/lib.rs",
+        );
+        let args = &["done", "--target", "release/1.4", "--yes"];
+        let err = run(&git, None, args, Confirmation::Never, false).unwrap_err();
+        assert!(
+            err.to_string().ends_with(
+                "Resolve the conflicts, run git cherry-pick --continue and then mob done --target release/1.4 --continue"
+            ),
+            "{}",
+            err
+        );
+        assert!(!merged(&git));
+
+        let mut picking = FakeGit::default();
+        picking
+            .revs
+            .insert("CHERRY_PICK_HEAD".into(), "abc1234".into());
+        let args = &["done", "--target", "release/1.4", "--continue"];
+        let err = run(&picking, None, args, Confirmation::Never, false).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "The cherry-pick onto release/1.4 is still going on, run git cherry-pick --continue once the conflicts are resolved"
+        );
+        assert!(!merged(&picking));
+
+        // Resolved, nothing is committed, pushed or replayed again
+        let git = FakeGit::default();
+        run(&git, None, args, Confirmation::Never, false).unwrap();
+        assert_eq!(git.calls(), landed_on_release());
+    }
+
+    #[test]
+    fn reports_the_target() {
+        let git = releases();
+        let memory = MemoryStore::default();
+        let store = session::SessionStore::new(&memory);
+        let clock = FakeClock::default();
+        store
+            .save(session::Session {
+                state: State::WaitingForNext { next: None },
+                settings: Some(session::Settings::default()),
+                times: session::Times {
+                    started: Some(clock.now() - chrono::Duration::hours(2)),
+                    ..session::Times::default()
+                },
+                ..session::Session::default()
+            })
+            .unwrap();
+        let state_dir = tempfile::tempdir().unwrap();
+        let state_dir = StateDir::new(state_dir.path().into());
+        let config = Config {
+            name: "alice".into(),
+            ..Config::default()
+        };
+        let dirs = crate::stats::dirs(&state_dir, &config.stats);
+        Done::new(
+            &git,
+            &store,
+            &clock,
+            state_dir,
+            opts(&["done", "--target", "release/1.4", "--yes"]),
+            config,
+        )
+        .run()
+        .unwrap();
+
+        let reports = crate::stats::read(&dirs, None);
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].target.as_deref(), Some("release/1.4"));
+    }
+
+    #[test]
+    fn suggests_a_close_target() {
+        let git = releases();
+        let args = &["done", "--target", "relese/1.4", "--yes"];
+        let err = run(&git, None, args, Confirmation::Never, false).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "There is no branch relese/1.4 on origin, did you mean release/1.4 or release/1.3?"
+        );
+        assert!(after_fetch(&git).is_empty());
+
+        let err = run(
+            &git,
+            None,
+            &["done", "--target", "hotfix", "--yes"],
+            Confirmation::Never,
+            false,
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "There is no branch hotfix on origin, nothing was delivered"
+        );
+
+        let git = releases();
+        crate::record::replay(vec![crate::record::Answer {
+            question: "There is no branch relese/1.4 on origin, did you mean one of these?".into(),
+            answer: serde_json::json!(0),
+        }]);
+        let delivered = run(&git, None, args, Confirmation::Never, false);
+        crate::record::stop_replay();
+        delivered.unwrap();
+        assert!(after_fetch(&git).contains(&"checkout release/1.4".to_string()));
+    }
+
+    fn merged(git: &FakeGit) -> bool {
+        git.calls()
+            .iter()
+            .any(|call| call.starts_with("merge --squash"))
+    }
+
+    #[test]
+    fn gate_decisions() {
+        let config = |require_confirmation| DoneConfig {
+            require_confirmation,
+            many_commits: 3,
+            ..DoneConfig::default()
+        };
+        let always = config(Confirmation::Always);
+        let when_many = config(Confirmation::WhenMany);
+        let never = config(Confirmation::Never);
+
+        assert_eq!(gate(&always, 1, true), Gate::Ask);
+        assert_eq!(gate(&always, 1, false), Gate::Refuse);
+        assert_eq!(gate(&when_many, 2, true), Gate::Proceed);
+        assert_eq!(gate(&when_many, 2, false), Gate::Proceed);
+        assert_eq!(gate(&when_many, 3, true), Gate::Ask);
+        assert_eq!(gate(&when_many, 3, false), Gate::Refuse);
+        assert_eq!(gate(&never, 100, false), Gate::Proceed);
+    }
+
+    #[test]
+    fn unattended_per_config() {
+        let commits = |count| FakeGit {
+            log: (0..count).map(|i| format!("abc{} wip", i)).collect(),
+            ..FakeGit::default()
+        };
+
+        let git = commits(1);
+        assert!(run(&git, None, &["done"], Confirmation::Always, false).is_err());
+        assert!(!merged(&git));
+
+        let git = commits(1);
+        run(&git, None, &["done", "--yes"], Confirmation::Always, false).unwrap();
+        assert!(merged(&git));
+
+        let git = commits(1);
+        crate::record::replay(vec![crate::record::Answer {
+            question: "Merge these into origin/master?".into(),
+            answer: serde_json::json!(true),
+        }]);
+        let asked = run(
+            &git,
+            None,
+            &["done", "-m", "Add login"],
+            Confirmation::Always,
+            true,
+        );
+        crate::record::stop_replay();
+        asked.unwrap();
+        assert!(merged(&git));
+
+        let git = commits(1);
+        run(&git, None, &["done"], Confirmation::WhenMany, false).unwrap();
+        assert!(merged(&git));
+
+        let git = commits(5);
+        assert!(run(&git, None, &["done"], Confirmation::WhenMany, false).is_err());
+        assert!(!merged(&git));
+
+        let git = commits(5);
+        run(&git, None, &["done"], Confirmation::Never, false).unwrap();
+        assert!(merged(&git));
+    }
+
+    #[test]
+    fn keeps_someone_elses_turn_under_a_deadline() {
+        let git = FakeGit::default();
+        let memory = MemoryStore::default();
+        let store = session::SessionStore::new(&memory);
+        store
+            .save(session::Session {
+                state: State::Working {
+                    driver: "bob".into(),
+                },
+                settings: Some(session::Settings::default()),
+                ..session::Session::default()
+            })
+            .unwrap();
+        let state_dir = tempfile::tempdir().unwrap();
+        let config = Config {
+            name: "alice".into(),
+            ..Config::default()
+        };
+
+        deadline::set(Some(std::time::Duration::from_secs(30)));
+        let done = Done::new(
+            &git,
+            &store,
+            &FakeClock::default(),
+            StateDir::new(state_dir.path().into()),
+            DoneOpts::parse_from(["done"]),
+            config,
+        )
+        .run();
+        deadline::set(None);
+
+        done.unwrap();
+        assert!(!merged(&git));
+        assert_eq!(
+            store.load().unwrap().state,
+            State::Working {
+                driver: "bob".into()
+            }
+        );
     }
 }
@@ -0,0 +1,340 @@
+use super::{editor, limits, Status, StatusOpts};
+use crate::{clock::Clock, config::Config, deadline, git, prompt, reorder, session};
+use anyhow::{anyhow, Result};
+use clap::{self, Clap};
+use session::{roles, State};
+
+#[derive(Clap, Debug)]
+pub struct DriversOpts {
+    #[clap(subcommand)]
+    action: Action,
+}
+
+#[derive(Clap, Debug)]
+enum Action {
+    /// Show the rotation as mob status does
+    #[clap(name = "list")]
+    List,
+
+    /// Add someone at the end of the rotation
+    #[clap(name = "add")]
+    Add(Name),
+
+    /// Take someone out of the rotation, like when they left for the day
+    #[clap(name = "remove")]
+    Remove(Name),
+
+    /// Put the rotation in a new order, in a list to sort or in $EDITOR
+    #[clap(name = "reorder")]
+    Reorder(ReorderOpts),
+}
+
+#[derive(Clap, Debug)]
+struct ReorderOpts {
+    /// Edit the rotation as a numbered list in $VISUAL or $EDITOR
+    #[clap(long)]
+    editor: bool,
+}
+
+#[derive(Clap, Debug)]
+struct Name {
+    #[clap(name = "NAME")]
+    name: String,
+}
+
+pub struct Drivers<'a> {
+    store: &'a dyn session::Store,
+    clock: &'a dyn Clock,
+    opts: DriversOpts,
+    config: Config,
+}
+
+impl<'a> Drivers<'a> {
+    pub fn new(
+        store: &'a impl session::Store,
+        clock: &'a impl Clock,
+        opts: DriversOpts,
+        config: Config,
+    ) -> Drivers<'a> {
+        Self {
+            store,
+            clock,
+            opts,
+            config,
+        }
+    }
+
+    pub fn run(&self) -> Result<()> {
+        let session = self.store.load()?;
+        match &self.opts.action {
+            Action::List => {
+                if session.drivers.all().is_empty() {
+                    log::info!("Nobody is in the rotation yet, run mob start");
+                    return Ok(());
+                }
+                let status = Status::of(
+                    StatusOpts::parse_from(["status"]),
+                    &git::NoopGit,
+                    self.store,
+                    self.clock,
+                    self.config.clone(),
+                );
+                print!("{}", status.drivers(&session, self.clock.now_utc()));
+                Ok(())
+            }
+            Action::Add(Name { name }) => {
+                roles::check(&session, &self.config.name, roles::Action::Settings)?;
+                if session.drivers.contains(name) {
+                    log::info!("{} is already in the rotation", name);
+                    return Ok(());
+                }
+                let drivers =
+                    session
+                        .drivers
+                        .clone()
+                        .admit(None, name, self.config.limits.max_drivers)?;
+                self.store.save(session::Session { drivers, ..session })?;
+                log::info!("Added {} at the end of the rotation", name);
+                Ok(())
+            }
+            Action::Remove(Name { name }) => {
+                roles::check(&session, &self.config.name, roles::Action::Settings)?;
+                let min = self.config.limits.min_drivers;
+                let session = remove(session, name, min, self.clock.now_utc())?;
+                self.store.save(session)?;
+                Ok(())
+            }
+            Action::Reorder(opts) => {
+                roles::check(&session, &self.config.name, roles::Action::Settings)?;
+                let drivers = session.drivers.all();
+                if drivers.len() < 2 {
+                    log::info!("Nobody to reorder, the rotation has {}", drivers.len());
+                    return Ok(());
+                }
+                deadline::answer::<()>("Reorder the drivers", None)?;
+                let order = match opts.editor || !cfg!(feature = "interactive") {
+                    true => reorder::parse(
+                        &editor::edit(
+                            &editor::command(),
+                            "mob-rotation-",
+                            &reorder::render(&drivers),
+                        )?,
+                        &drivers,
+                    )?,
+                    false => {
+                        let names: Vec<&str> = drivers.iter().map(String::as_str).collect();
+                        let order = prompt::terminal()
+                            .sort("Use [space] and ↓↑ to move a driver", &names)?;
+                        Some(order.into_iter().map(|at| drivers[at].clone()).collect())
+                    }
+                };
+                match order {
+                    Some(order) => self.store.save(reordered(
+                        session,
+                        order,
+                        &self.config.name,
+                        self.clock.now_utc(),
+                    ))?,
+                    None => log::info!("Nothing to reorder, the rotation stays as it is"),
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// The session with the rotation in `order` as `by` put it. Whoever is next stays next.
+fn reordered(
+    session: session::Session,
+    order: Vec<String>,
+    by: &str,
+    now: chrono::DateTime<chrono::Utc>,
+) -> session::Session {
+    if order == session.drivers.all() {
+        log::info!("The rotation stays as it is");
+        return session;
+    }
+    log::info!("The rotation is {}", order.join(", "));
+    if let State::WaitingForNext { next: Some(next) } = &session.state {
+        log::info!("{} is still next", next);
+    }
+    session::Session {
+        history: session.history.push(session::Record::Reorder {
+            by: by.to_string(),
+            drivers: order.clone(),
+            at: now,
+        }),
+        drivers: session::Drivers::new(order),
+        ..session
+    }
+}
+
+/// The session without `name` in the rotation. Turns that were theirs go to whoever
+/// follows them.
+fn remove(
+    session: session::Session,
+    name: &str,
+    min: Option<usize>,
+    now: chrono::DateTime<chrono::Utc>,
+) -> Result<session::Session> {
+    if !session.drivers.contains(name) {
+        return Err(anyhow!("{} is not in the rotation", name));
+    }
+    if session.drivers.all().len() == 1 && session.state != State::Stopped {
+        return Err(anyhow!(
+            "{} is the only driver, run mob done or mob clean to end the session instead",
+            name
+        ));
+    }
+    // A stopped session keeps its rotation for the next start
+    let drivers = limits::dismiss(&session.drivers, name, min, session.state == State::Stopped)?;
+    let following = session.drivers.next(name, &session.away, now);
+    let state = match session.state {
+        State::Working { driver } if driver == name => {
+            log::warn!(
+                "{} was driving, work they didn't push stays on their machine",
+                name
+            );
+            match &following {
+                Some(next) => log::info!("{} is next, mob start takes over", session::shown(next)),
+                None => log::info!("Nobody in particular is next, anyone may run mob start"),
+            }
+            State::WaitingForNext { next: following }
+        }
+        State::WaitingForNext { next: Some(next) } if next == name => {
+            match &following {
+                Some(next) => log::info!("{} is next instead", session::shown(next)),
+                None => log::info!("Nobody in particular is next instead"),
+            }
+            State::WaitingForNext { next: following }
+        }
+        state => state,
+    };
+    log::info!("Removed {} from the rotation", name);
+    Ok(session::Session {
+        state,
+        drivers,
+        away: session.away.remove(name),
+        ..session
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::FakeClock;
+    use crate::git::fake::MemoryStore;
+    use crate::session::Store as _;
+
+    fn session(state: State) -> session::Session {
+        session::Session {
+            state,
+            drivers: session::Drivers::new(vec!["alice".into(), "bob".into(), "carol".into()]),
+            settings: Some(session::Settings::default()),
+            ..session::Session::default()
+        }
+    }
+
+    fn run(session: session::Session, args: &[&str]) -> Result<session::Session> {
+        let memory = MemoryStore::default();
+        let store = session::SessionStore::new(&memory);
+        store.save(session).unwrap();
+        let config = Config {
+            name: "alice".into(),
+            ..Config::default()
+        };
+        let clock = FakeClock::default();
+        Drivers::new(&store, &clock, DriversOpts::parse_from(args), config).run()?;
+        Ok(store.load().unwrap())
+    }
+
+    #[test]
+    fn adds_and_removes() {
+        let working = || {
+            session(State::Working {
+                driver: "alice".into(),
+            })
+        };
+        let added = run(working(), &["drivers", "add", "dave"]).unwrap();
+        assert_eq!(added.drivers.all(), vec!["alice", "bob", "carol", "dave"]);
+        let again = run(added.clone(), &["drivers", "add", "dave"]).unwrap();
+        assert_eq!(again, added);
+
+        let removed = run(working(), &["drivers", "remove", "carol"]).unwrap();
+        assert_eq!(removed.drivers.all(), vec!["alice", "bob"]);
+        assert_eq!(removed.state, working().state);
+
+        let err = run(working(), &["drivers", "remove", "erin"]).unwrap_err();
+        assert_eq!(err.to_string(), "erin is not in the rotation");
+        run(working(), &["drivers", "list"]).unwrap();
+    }
+
+    #[test]
+    fn promotes_who_follows() {
+        let now = FakeClock::default().now_utc();
+        let driving = remove(
+            session(State::Working {
+                driver: "bob".into(),
+            }),
+            "bob",
+            None,
+            now,
+        )
+        .unwrap();
+        assert_eq!(
+            driving.state,
+            State::WaitingForNext {
+                next: Some("carol".into())
+            }
+        );
+        assert_eq!(driving.drivers.all(), vec!["alice", "carol"]);
+
+        // The last driver is followed by the first
+        let next = remove(
+            session(State::WaitingForNext {
+                next: Some("carol".into()),
+            }),
+            "carol",
+            None,
+            now,
+        )
+        .unwrap();
+        assert_eq!(
+            next.state,
+            State::WaitingForNext {
+                next: Some("alice".into())
+            }
+        );
+
+        let alone = session::Session {
+            drivers: session::Drivers::new(vec!["alice".into()]),
+            ..session(State::Working {
+                driver: "alice".into(),
+            })
+        };
+        assert!(remove(alone, "alice", None, now).is_err());
+    }
+
+    #[test]
+    fn records_the_new_order() {
+        let now = FakeClock::default().now_utc();
+        let waiting = session(State::WaitingForNext {
+            next: Some("carol".into()),
+        });
+        let order: Vec<String> = vec!["carol".into(), "alice".into(), "bob".into()];
+        let changed = reordered(waiting.clone(), order.clone(), "alice", now);
+        assert_eq!(changed.drivers.all(), order);
+        assert_eq!(changed.state, waiting.state);
+        assert_eq!(
+            changed.history.records().last(),
+            Some(&session::Record::Reorder {
+                by: "alice".into(),
+                drivers: order,
+                at: now,
+            })
+        );
+
+        let same = reordered(waiting.clone(), waiting.drivers.all(), "alice", now);
+        assert_eq!(same, waiting);
+    }
+}
@@ -0,0 +1,80 @@
+//! Text to write in the editor, the way git asks for commit messages
+
+use crate::command::Command;
+use crate::os;
+use anyhow::{anyhow, Result};
+use std::{env, fs};
+
+/// The editor git would use, without asking git
+pub fn command() -> String {
+    env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string())
+}
+
+/// `text` as `editor` left it in a file named after `prefix`. The editor runs in a shell so
+/// it can have arguments.
+pub fn edit(editor: &str, prefix: &str, text: &str) -> Result<String> {
+    let file = tempfile::Builder::new()
+        .prefix(prefix)
+        .suffix(".txt")
+        .tempfile()?;
+    fs::write(file.path(), text)?;
+    let script = format!("{} \"$1\"", editor);
+    let path = file.path().to_string_lossy();
+    let status = Command::new(os::command("sh")).interact(["-c", &script, "sh", &path])?;
+    if !status.success() {
+        return Err(anyhow!(
+            "{} exited with {}, nothing was changed",
+            editor,
+            status
+        ));
+    }
+    Ok(fs::read_to_string(file.path())?)
+}
+
+/// The lines of `text` that don't start with `#`, without the blank ones around them
+pub fn uncommented(text: &str) -> String {
+    let lines: Vec<&str> = text
+        .lines()
+        .map(|line| line.trim_end())
+        .filter(|line| !line.starts_with('#'))
+        .collect();
+    lines.join("\n").trim_matches('\n').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn edits_in_the_editor() {
+        let edited = edit(
+            "printf '2. carol\\n1. alice\\n' >",
+            "mob-rotation-",
+            "1. alice\n2. carol\n",
+        )
+        .unwrap();
+        assert_eq!(edited, "2. carol\n1. alice\n");
+        let unchanged = edit("true", "mob-rotation-", "1. alice\n").unwrap();
+        assert_eq!(unchanged, "1. alice\n");
+
+        let err = edit("false", "mob-rotation-", "1. alice\n").unwrap_err();
+        assert!(
+            err.to_string()
+                .starts_with("false exited with exit status: 1"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn leaves_out_comments() {
+        assert_eq!(
+            uncommented("\nAdd login\n\n- Check the password  \n# Lines starting with #\n\n"),
+            "Add login\n\n- Check the password"
+        );
+        assert_eq!(uncommented("# only comments\n\n"), "");
+    }
+}
@@ -0,0 +1,435 @@
+//! Parking the session at once when an incident scatters the mob. Every step runs whatever
+//! happened to the ones before it, and the summary tells which ones need a hand.
+
+use super::{claim::changing, push};
+use crate::{clock::Clock, config::Config, git, session, timer};
+use anyhow::{anyhow, Result};
+use clap::{self, Clap};
+use session::State;
+
+/// Starts the message of the commit with whatever was in the tree
+const MARKER: &str = "EMERGENCY PARK";
+
+#[derive(Clap, Debug)]
+pub struct EmergencyOpts {
+    /// What happened, for the channel and mob status
+    #[clap(default_value = "an emergency")]
+    reason: String,
+}
+
+enum Outcome {
+    Done(String),
+    Skipped(String),
+}
+
+struct Step {
+    name: &'static str,
+    result: Result<Outcome>,
+    /// What to do by hand when it failed
+    manual: String,
+}
+
+/// Commits and pushes the work, pauses the session, cancels the timer and tells the channel
+pub struct Emergency<'a> {
+    git: &'a dyn git::Git,
+    store: &'a dyn session::Store,
+    timer: &'a dyn timer::Timer,
+    channel: &'a dyn timer::Notifier,
+    clock: &'a dyn Clock,
+    opts: EmergencyOpts,
+    config: Config,
+}
+
+impl<'a> Emergency<'a> {
+    pub fn new(
+        git: &'a impl git::Git,
+        store: &'a impl session::Store,
+        timer: &'a impl timer::Timer,
+        channel: &'a impl timer::Notifier,
+        clock: &'a impl Clock,
+        opts: EmergencyOpts,
+        config: Config,
+    ) -> Emergency<'a> {
+        Self {
+            git,
+            store,
+            timer,
+            channel,
+            clock,
+            opts,
+            config,
+        }
+    }
+
+    pub fn run(&self) -> Result<()> {
+        let snapshot = if self.git.is_repository() {
+            Some(self.git.snapshot())
+        } else {
+            None
+        };
+        let branch = match &snapshot {
+            Some(Ok(snapshot)) => snapshot.branch.clone(),
+            _ => None,
+        };
+        let steps = self.park(snapshot);
+        print!("{}", summary(&steps, branch.as_deref()));
+
+        match steps.iter().filter(|step| step.result.is_err()).count() {
+            0 => Ok(()),
+            failed => Err(anyhow!(
+                "{} of {} steps need manual attention",
+                failed,
+                steps.len()
+            )),
+        }
+    }
+
+    fn park(&self, snapshot: Option<Result<git::Snapshot>>) -> Vec<Step> {
+        let remote = self.config.remote.as_str();
+        let branch = match &snapshot {
+            Some(Ok(snapshot)) => snapshot.branch.clone(),
+            _ => None,
+        }
+        .unwrap_or_else(|| "<branch>".to_string());
+
+        let commit = self.commit(snapshot.as_ref());
+        let committed = matches!(commit, Ok(Outcome::Done(_)));
+        vec![
+            Step {
+                name: "commit",
+                result: commit,
+                manual: format!(
+                    "git add --all && git commit --message '{}' --no-verify",
+                    MARKER
+                ),
+            },
+            Step {
+                name: "push",
+                result: self.push(snapshot.as_ref(), committed),
+                manual: format!("git push --no-verify {} {}", remote, branch),
+            },
+            Step {
+                name: "session",
+                result: self.pause(),
+                manual: "mob emergency again once mob status works".to_string(),
+            },
+            Step {
+                name: "timer",
+                result: self.cancel(),
+                manual: "Stop the timer in the room".to_string(),
+            },
+            Step {
+                name: "channel",
+                result: self.tell(&branch),
+                manual: "Tell the team that the mob stopped".to_string(),
+            },
+        ]
+    }
+
+    fn commit(&self, snapshot: Option<&Result<git::Snapshot>>) -> Result<Outcome> {
+        let snapshot = match snapshot {
+            None => return Ok(Outcome::Skipped("no repository".into())),
+            Some(Ok(snapshot)) => snapshot,
+            Some(Err(err)) => return Err(anyhow!("{:#}", err)),
+        };
+        if snapshot.is_clean() {
+            return Ok(Outcome::Skipped("nothing to commit".into()));
+        }
+        let message = format!("{}: {}", MARKER, self.opts.reason);
+        self.git.run(&["add", "--all"])?;
+        git::signing::commit(
+            self.git,
+            self.config.commit.sign,
+            &["--message", message.as_str(), "--no-verify"],
+        )?;
+        Ok(Outcome::Done(format!(
+            "{} files as {}",
+            snapshot.files(),
+            MARKER
+        )))
+    }
+
+    fn push(&self, snapshot: Option<&Result<git::Snapshot>>, committed: bool) -> Result<Outcome> {
+        let snapshot = match snapshot {
+            None => return Ok(Outcome::Skipped("no repository".into())),
+            Some(Ok(snapshot)) => snapshot,
+            Some(Err(_)) => return Err(anyhow!("git status failed, the branch is unknown")),
+        };
+        let branch = snapshot
+            .branch
+            .as_deref()
+            .ok_or_else(|| anyhow!("no branch is checked out"))?;
+        let upstream = snapshot.upstream.is_none();
+        if !committed && snapshot.ahead == 0 && !upstream {
+            return Ok(Outcome::Skipped("nothing to push".into()));
+        }
+        push::push(self.git, &self.config.remote, branch, upstream)?;
+        Ok(Outcome::Done(format!(
+            "{} to {}",
+            branch, self.config.remote
+        )))
+    }
+
+    fn pause(&self) -> Result<Outcome> {
+        if self.store.load()?.state == State::Stopped {
+            return Ok(Outcome::Skipped("no session is running".into()));
+        }
+        changing(self.store, |session| {
+            Ok::<_, anyhow::Error>(session::Session {
+                state: State::Paused {
+                    reason: self.opts.reason.clone(),
+                    driver: None,
+                    remaining: None,
+                },
+                times: session::Times {
+                    last_activity: Some(self.clock.now()),
+                    claimed: None,
+                    break_until: None,
+                    ..session.times
+                },
+                ..session
+            })
+        })?;
+        Ok(Outcome::Done(format!("paused for {}", self.opts.reason)))
+    }
+
+    fn cancel(&self) -> Result<Outcome> {
+        if self.config.timer.broadcast.is_none() {
+            return Ok(Outcome::Skipped("no timer.broadcast".into()));
+        }
+        self.timer.cancel()?;
+        Ok(Outcome::Done("cancelled on the broadcast".into()))
+    }
+
+    fn tell(&self, branch: &str) -> Result<Outcome> {
+        if self.config.handover.channel_command.is_none() {
+            return Ok(Outcome::Skipped("no handover.channel_command".into()));
+        }
+        self.channel.notify(&format!(
+            "The mob stopped for {}, the work is parked on {}",
+            self.opts.reason, branch
+        ))?;
+        Ok(Outcome::Done("told".into()))
+    }
+}
+
+fn summary(steps: &[Step], branch: Option<&str>) -> String {
+    let mut out = String::new();
+    for step in steps {
+        let line = match &step.result {
+            Ok(Outcome::Done(what)) => format!("✅ {}: {}", step.name, what),
+            Ok(Outcome::Skipped(why)) => format!("➖ {}: skipped, {}", step.name, why),
+            Err(err) => format!(
+                "❌ {}: {:#}\n   Do it by hand: {}",
+                step.name, err, step.manual
+            ),
+        };
+        out.push_str(&line);
+        out.push('\n');
+    }
+    if let Some(branch) = branch {
+        out.push_str(&format!("The work is on {}\n", branch));
+    }
+    out.push_str("Run mob start to resume, on any machine\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::FakeClock;
+    use crate::cmd::fixtures;
+    use crate::config::HandoverConfig;
+    use crate::git::fake::{FakeGit, MemoryStore};
+    use crate::session::Store;
+    use std::cell::RefCell;
+
+    #[derive(Default)]
+    struct Fakes {
+        told: RefCell<Vec<String>>,
+        cancelled: RefCell<usize>,
+        clock: FakeClock,
+        /// Which of the steps outside git fail
+        failing: Vec<&'static str>,
+    }
+
+    impl timer::Timer for Fakes {
+        fn start(&self, _: timer::Countdown, _: &str, _: chrono::Duration, _: &str) -> Result<()> {
+            Ok(())
+        }
+
+        fn cancel(&self) -> Result<()> {
+            if self.failing.contains(&"timer") {
+                return Err(anyhow!("Connection refused"));
+            }
+            *self.cancelled.borrow_mut() += 1;
+            Ok(())
+        }
+    }
+
+    impl timer::Notifier for Fakes {
+        fn notify(&self, message: &str) -> Result<()> {
+            if self.failing.contains(&"channel") {
+                return Err(anyhow!("curl: (6) Could not resolve host"));
+            }
+            self.told.borrow_mut().push(message.to_string());
+            Ok(())
+        }
+    }
+
+    /// Loads but can't save, like without a network
+    struct Offline<'a>(session::SessionStore<'a>);
+
+    impl<'a> session::Store for Offline<'a> {
+        fn load(&self) -> Result<session::Session, session::Error> {
+            self.0.load()
+        }
+
+        fn save(&self, _: session::Session) -> Result<(), session::Error> {
+            Err(session::Error::Git(git::store::Error::Conflict(anyhow!(
+                "could not push mob-meta-state"
+            ))))
+        }
+
+        fn clean(&self) -> Result<(), session::Error> {
+            self.0.clean()
+        }
+    }
+
+    fn config() -> Config {
+        let mut config = Config {
+            handover: HandoverConfig {
+                channel_command: Some("curl -d MESSAGE https://chat".into()),
+                ..HandoverConfig::default()
+            },
+            ..fixtures::alice()
+        };
+        config.timer.broadcast = Some("tcp://led:4444".into());
+        config
+    }
+
+    fn dirty() -> FakeGit {
+        let mut git = fixtures::dirty();
+        git.snapshot.upstream = Some("origin/mob-session".into());
+        git.snapshot.changed = 2;
+        git.snapshot.unstaged = 2;
+        git
+    }
+
+    fn working(memory: &MemoryStore) -> session::SessionStore<'_> {
+        let store = session::SessionStore::new(memory);
+        store.save(fixtures::working()).unwrap();
+        store
+    }
+
+    fn names(steps: &[Step], failed: bool) -> Vec<&'static str> {
+        steps
+            .iter()
+            .filter(|step| step.result.is_err() == failed)
+            .map(|step| step.name)
+            .collect()
+    }
+
+    fn emergency<'a>(
+        git: &'a FakeGit,
+        store: &'a impl session::Store,
+        fakes: &'a Fakes,
+    ) -> Emergency<'a> {
+        let opts = EmergencyOpts::parse_from(["emergency", "prod is down"]);
+        Emergency::new(git, store, fakes, fakes, &fakes.clock, opts, config())
+    }
+
+    #[test]
+    fn parks_everything() {
+        let git = dirty();
+        let memory = MemoryStore::default();
+        let store = working(&memory);
+        let fakes = Fakes::default();
+        let command = emergency(&git, &store, &fakes);
+        let steps = command.park(Some(Ok(git.snapshot.clone())));
+
+        assert!(names(&steps, true).is_empty());
+        assert_eq!(
+            git.calls(),
+            vec![
+                "add --all",
+                "commit --message EMERGENCY PARK: prod is down --no-verify",
+                "push --no-verify origin -- mob-session",
+            ]
+        );
+        assert_eq!(
+            store.load().unwrap().state,
+            State::Paused {
+                reason: "prod is down".into(),
+                driver: None,
+                remaining: None,
+            }
+        );
+        assert_eq!(*fakes.cancelled.borrow(), 1);
+        assert_eq!(
+            *fakes.told.borrow(),
+            vec!["The mob stopped for prod is down, the work is parked on mob-session"]
+        );
+        let summary = summary(&steps, Some("mob-session"));
+        assert!(summary.starts_with("✅ commit: 2 files as EMERGENCY PARK\n"));
+        assert!(summary.contains("The work is on mob-session\nRun mob start to resume"));
+    }
+
+    #[test]
+    fn goes_on_when_a_step_fails() {
+        for failing in &["commit", "push", "session", "timer", "channel"] {
+            let mut git = dirty();
+            git.snapshot.ahead = 1;
+            match *failing {
+                "commit" => git.fail("commit", 0, "error: gpg failed to sign the data"),
+                "push" => git.fail("push", 0, "fatal: unable to access: Could not resolve host"),
+                _ => (),
+            }
+            let memory = MemoryStore::default();
+            let store = Offline(working(&memory));
+            let fakes = Fakes {
+                failing: vec![failing],
+                ..Fakes::default()
+            };
+            let steps = if *failing == "session" {
+                emergency(&git, &store, &fakes).park(Some(Ok(git.snapshot.clone())))
+            } else {
+                emergency(&git, &store.0, &fakes).park(Some(Ok(git.snapshot.clone())))
+            };
+
+            assert_eq!(names(&steps, true), vec![*failing]);
+            assert_eq!(names(&steps, false).len(), 4, "{}", failing);
+            // What was committed before is pushed even when the commit failed
+            assert!(git.calls().iter().any(|call| call.starts_with("push")));
+            let summary = summary(&steps, Some("mob-session"));
+            assert!(
+                summary.contains(&format!("❌ {}: ", failing)),
+                "{}",
+                summary
+            );
+            assert!(summary.contains("Do it by hand: "));
+        }
+    }
+
+    #[test]
+    fn skips_what_there_is_nothing_to_do_for() {
+        let mut git = FakeGit::default();
+        git.snapshot.upstream = Some("origin/master".into());
+        let memory = MemoryStore::default();
+        let store = session::SessionStore::new(&memory);
+        let fakes = Fakes::default();
+        let mut command = emergency(&git, &store, &fakes);
+        command.config = fixtures::alice();
+        let steps = command.park(Some(Ok(git.snapshot.clone())));
+
+        assert!(names(&steps, true).is_empty());
+        assert!(steps
+            .iter()
+            .all(|step| matches!(step.result, Ok(Outcome::Skipped(_)))));
+        assert!(git.calls().is_empty());
+        assert!(fakes.told.borrow().is_empty());
+
+        let failed = emergency(&git, &store, &fakes).park(Some(Err(anyhow!("not a git repo"))));
+        assert_eq!(names(&failed, true), vec!["commit", "push"]);
+    }
+}
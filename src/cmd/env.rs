@@ -0,0 +1,32 @@
+use crate::completions::{Shell, SHELLS};
+use crate::{env, session};
+use anyhow::Result;
+use clap::{self, Clap};
+
+#[derive(Clap, Debug)]
+pub struct EnvOpts {
+    /// Print for fish or powershell instead of a POSIX shell
+    #[clap(long, possible_values = SHELLS)]
+    shell: Option<Shell>,
+}
+
+/// Prints the session as environment variables, for `eval "$(mob env)"`
+pub struct Env<'a> {
+    store: &'a dyn session::Store,
+    opts: EnvOpts,
+}
+
+impl<'a> Env<'a> {
+    pub fn new(store: &'a impl session::Store, opts: EnvOpts) -> Env<'a> {
+        Self { store, opts }
+    }
+
+    pub fn run(&self) -> Result<()> {
+        let session = self.store.load()?;
+        print!(
+            "{}",
+            env::exports(&env::variables(&session), self.opts.shell)
+        );
+        Ok(())
+    }
+}
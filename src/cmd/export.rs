@@ -0,0 +1,64 @@
+use crate::session::bundle::Bundle;
+use crate::{config::Config, git, session};
+use anyhow::{anyhow, Result};
+use clap::{self, Clap};
+use session::State;
+use std::fs;
+use std::path::PathBuf;
+
+#[derive(Clap, Debug)]
+pub struct ExportOpts {
+    /// File to write the session to
+    #[clap(long)]
+    file: PathBuf,
+}
+
+/// Packs the session into a file for `mob import`, as when the repository moves host
+pub struct Export<'a> {
+    git: &'a dyn git::Git,
+    store: &'a dyn session::Store,
+    opts: ExportOpts,
+    config: Config,
+}
+
+impl<'a> Export<'a> {
+    pub fn new(
+        git: &'a impl git::Git,
+        store: &'a impl session::Store,
+        opts: ExportOpts,
+        config: Config,
+    ) -> Export<'a> {
+        Self {
+            git,
+            store,
+            opts,
+            config,
+        }
+    }
+
+    pub fn run(&self) -> Result<()> {
+        let session = self.store.load()?;
+        if session.state == State::Stopped && session.drivers.all().is_empty() {
+            return Err(anyhow!("There is no session to export, run mob start"));
+        }
+
+        let bundle = Bundle {
+            root: if self.git.is_repository() {
+                self.git.root_commit()?
+            } else {
+                None
+            },
+            remote: self.config.remote.clone(),
+            session,
+        };
+        fs::write(&self.opts.file, bundle.to_vec()?)?;
+
+        log::info!(
+            "Exported the session to {}, push {} to the new remote and run mob import --file {} --remote <remote> there",
+            self.opts.file.display(),
+            bundle.session.branches.branch,
+            self.opts.file.display()
+        );
+        Ok(())
+    }
+}
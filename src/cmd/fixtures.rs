@@ -0,0 +1,39 @@
+//! The config, session and working tree most command tests start from.
+
+use crate::config::Config;
+use crate::git::fake::FakeGit;
+use crate::session::{self, State};
+
+/// Config for the member called `name`, defaults otherwise
+pub fn config(name: &str) -> Config {
+    Config {
+        name: name.into(),
+        ..Config::default()
+    }
+}
+
+pub fn alice() -> Config {
+    config("alice")
+}
+
+/// Alice driving with bob next, default settings
+pub fn working() -> session::Session {
+    session::Session {
+        id: Some("3f2a9c1e7b04".into()),
+        state: State::Working {
+            driver: "alice".into(),
+        },
+        drivers: session::Drivers::new(vec!["alice".into(), "bob".into()]),
+        settings: Some(session::Settings::default()),
+        ..session::Session::default()
+    }
+}
+
+/// On the mob branch with one unstaged change
+pub fn dirty() -> FakeGit {
+    let mut git = FakeGit::default();
+    git.snapshot.branch = Some("mob-session".into());
+    git.snapshot.changed = 1;
+    git.snapshot.unstaged = 1;
+    git
+}
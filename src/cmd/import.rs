@@ -0,0 +1,304 @@
+use crate::session::bundle::Bundle;
+use crate::{config::Config, git, session};
+use anyhow::{anyhow, Result};
+use clap::{self, Clap};
+use session::State;
+use std::fs;
+use std::path::PathBuf;
+
+#[derive(Clap, Debug)]
+pub struct ImportOpts {
+    /// File written by mob export
+    #[clap(long)]
+    file: PathBuf,
+
+    /// Remote to keep the session on, defaults to the configured one
+    #[clap(long)]
+    pub remote: Option<String>,
+
+    /// Import into another project or over a running session
+    #[clap(long)]
+    force: bool,
+}
+
+/// Recreates an exported session against this repository and remote
+pub struct Import<'a> {
+    git: &'a dyn git::Git,
+    store: &'a dyn session::Store,
+    opts: ImportOpts,
+    config: Config,
+}
+
+impl<'a> Import<'a> {
+    pub fn new(
+        git: &'a impl git::Git,
+        store: &'a impl session::Store,
+        opts: ImportOpts,
+        config: Config,
+    ) -> Import<'a> {
+        Self {
+            git,
+            store,
+            opts,
+            config,
+        }
+    }
+
+    fn remote(&self) -> &str {
+        self.opts.remote.as_deref().unwrap_or(&self.config.remote)
+    }
+
+    pub fn run(&self) -> Result<()> {
+        let bundle = Bundle::from_slice(&fs::read(&self.opts.file)?)?;
+        let remote = self.remote();
+        let remotes = self.git.remotes()?;
+        if self.git.is_repository() && !remotes.iter().any(|r| r == remote) {
+            return Err(anyhow!("There is no remote {} in this repository", remote));
+        }
+
+        let root = if self.git.is_repository() {
+            self.git.root_commit()?
+        } else {
+            None
+        };
+        let problems = check(&bundle, root.as_deref(), &self.store.load()?);
+        if !problems.is_empty() && !self.opts.force {
+            return Err(anyhow!(
+                "Not importing, {}. Run again with --force to import anyway",
+                problems.join(", ")
+            ));
+        }
+        for problem in &problems {
+            log::warn!("Importing although {}", problem);
+        }
+
+        let session = session::Session {
+            branches: repoint(bundle.session.branches, remote, &remotes),
+            ..bundle.session
+        };
+        if self.git.is_repository() {
+            self.check_branch(&session.branches, remote)?;
+        }
+        self.store.save(session)?;
+        log::info!("Imported the session from {} to {}", bundle.remote, remote);
+        Ok(())
+    }
+
+    /// The branch is pushed by git, import only tells when that is still missing
+    fn check_branch(&self, branches: &session::Branches, remote: &str) -> Result<()> {
+        self.git.run(&["fetch", remote])?;
+        let remote_branch = branches.with_remote(remote).branch;
+        if !self.git.has_branch(&remote_branch)? {
+            log::warn!(
+                "{} isn't on {} yet, push it with git push {} {}",
+                branches.branch,
+                remote,
+                remote,
+                branches.branch
+            );
+        }
+        Ok(())
+    }
+}
+
+/// What speaks against importing `bundle` into a repository with `root` and `current` session
+fn check(bundle: &Bundle, root: Option<&str>, current: &session::Session) -> Vec<String> {
+    let mut problems = vec![];
+    match (bundle.root.as_deref(), root) {
+        (Some(theirs), Some(ours)) if theirs == ours => (),
+        (Some(_), Some(_)) => problems.push("the session is from another project".to_string()),
+        _ => problems.push("it can't be told if the session is from this project".to_string()),
+    }
+    if current.state != State::Stopped {
+        problems.push("a session is running here already".to_string());
+    }
+    problems
+}
+
+/// The base stays on its own remote only when this repository has it, the session remote is implied
+fn repoint(branches: session::Branches, remote: &str, remotes: &[String]) -> session::Branches {
+    let base_remote = match branches.base_remote {
+        Some(base) if base == remote => None,
+        Some(base) if remotes.contains(&base) => Some(base),
+        Some(base) => {
+            log::warn!(
+                "There is no remote {} here, the base branch {} is taken from {}",
+                base,
+                branches.base_branch,
+                remote
+            );
+            None
+        }
+        None => None,
+    };
+    session::Branches {
+        base_remote,
+        ..branches
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::{Export, ExportOpts};
+    use crate::git::fake::{FakeGit, MemoryStore};
+    use crate::session::Store;
+    use chrono::TimeZone;
+
+    fn config() -> Config {
+        Config {
+            name: "alice".into(),
+            ..Config::default()
+        }
+    }
+
+    fn running() -> session::Session {
+        session::Session {
+            id: Some("3f2a9c1e7b04".into()),
+            drivers: session::Drivers::new(vec!["alice".into(), "bob".into()]),
+            settings: Some(session::Settings::default()),
+            state: State::WaitingForNext {
+                next: Some("bob".into()),
+            },
+            history: session::History::new(vec![session::Record::Turn {
+                driver: "alice".into(),
+                at: chrono::Utc.ymd(2021, 6, 1).and_hms(9, 0, 0),
+            }]),
+            branches: session::Branches {
+                branch: "feature-x".into(),
+                base_branch: "main".into(),
+                base_remote: Some("upstream".into()),
+            },
+            ..session::Session::default()
+        }
+    }
+
+    fn export(session: &session::Session, git: &FakeGit, file: &std::path::Path) {
+        let memory = MemoryStore::default();
+        let store = session::SessionStore::new(&memory);
+        store.save(session.clone()).unwrap();
+        let opts = ExportOpts::parse_from(["export", "--file", file.to_str().unwrap()]);
+        Export::new(git, &store, opts, config()).run().unwrap();
+    }
+
+    fn import(git: &FakeGit, memory: &MemoryStore, args: &[&str]) -> Result<session::Session> {
+        let store = session::SessionStore::new(memory);
+        Import::new(git, &store, ImportOpts::parse_from(args), config()).run()?;
+        Ok(store.load()?)
+    }
+
+    #[test]
+    fn moves_between_remotes() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("session.json");
+        let file = path.to_str().unwrap();
+        let old = FakeGit {
+            remotes: vec!["origin".into(), "upstream".into()],
+            ..FakeGit::default()
+        };
+        export(&running(), &old, &path);
+
+        let new = FakeGit {
+            remotes: vec!["origin".into(), "neworigin".into()],
+            ..FakeGit::default()
+        }
+        .with_branches(&["neworigin/feature-x"]);
+        let memory = MemoryStore::default();
+        let imported = import(
+            &new,
+            &memory,
+            &["import", "--file", file, "--remote", "neworigin"],
+        )
+        .unwrap();
+
+        assert_eq!(
+            imported,
+            session::Session {
+                branches: session::Branches {
+                    base_remote: None,
+                    ..running().branches
+                },
+                ..running()
+            }
+        );
+        assert_eq!(new.calls(), vec!["fetch neworigin"]);
+    }
+
+    #[test]
+    fn refuses_other_projects_and_running_sessions() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("session.json");
+        let file = path.to_str().unwrap();
+        export(&running(), &FakeGit::default(), &path);
+
+        let other = FakeGit {
+            root: Some("beef".into()),
+            ..FakeGit::default()
+        };
+        let memory = MemoryStore::default();
+        let err = import(&other, &memory, &["import", "--file", file]).unwrap_err();
+        assert!(err.to_string().contains("another project"), "{}", err);
+        assert!(memory.documents.borrow().is_empty());
+        assert!(import(&other, &memory, &["import", "--file", file, "--force"]).is_ok());
+
+        let err = import(&FakeGit::default(), &memory, &["import", "--file", file]).unwrap_err();
+        assert!(err.to_string().contains("running here"), "{}", err);
+
+        let err = import(
+            &FakeGit::default(),
+            &MemoryStore::default(),
+            &["import", "--file", file, "--remote", "gone"],
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("no remote gone"), "{}", err);
+    }
+
+    #[test]
+    fn checks() {
+        let bundle = |root: Option<&str>| Bundle {
+            root: root.map(String::from),
+            remote: "origin".into(),
+            session: running(),
+        };
+        let stopped = session::Session::default();
+
+        assert!(check(&bundle(Some("c0ffee")), Some("c0ffee"), &stopped).is_empty());
+        assert_eq!(
+            check(&bundle(Some("c0ffee")), Some("beef"), &running()),
+            vec![
+                "the session is from another project",
+                "a session is running here already"
+            ]
+        );
+        assert_eq!(check(&bundle(None), Some("c0ffee"), &stopped).len(), 1);
+        assert_eq!(check(&bundle(Some("c0ffee")), None, &stopped).len(), 1);
+    }
+
+    #[test]
+    fn repoints_the_base() {
+        let remotes: Vec<String> = vec!["neworigin".into(), "upstream".into()];
+        let base = |remote: Option<&str>| session::Branches {
+            base_remote: remote.map(String::from),
+            ..session::Branches::default()
+        };
+        let repoint = |remote| repoint(base(remote), "neworigin", &remotes).base_remote;
+
+        assert_eq!(repoint(None), None);
+        assert_eq!(repoint(Some("neworigin")), None);
+        assert_eq!(repoint(Some("upstream")), Some("upstream".into()));
+        assert_eq!(repoint(Some("gone")), None);
+    }
+
+    #[test]
+    fn nothing_to_export() {
+        let dir = tempfile::tempdir().unwrap();
+        let memory = MemoryStore::default();
+        let store = session::SessionStore::new(&memory);
+        let path = dir.path().join("session.json");
+        let opts = ExportOpts::parse_from(["export", "--file", path.to_str().unwrap()]);
+        assert!(Export::new(&FakeGit::default(), &store, opts, config())
+            .run()
+            .is_err());
+        assert!(!path.exists());
+    }
+}
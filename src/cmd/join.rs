@@ -0,0 +1,266 @@
+use super::limits;
+use crate::{config::Config, deadline, git, session};
+use anyhow::Result;
+use clap::{self, Clap};
+use session::{roles, State};
+
+#[derive(Clap, Debug, Default)]
+pub struct JoinOpts {
+    /// Join even when the mob would have more than limits.max_drivers
+    #[clap(long)]
+    force: bool,
+
+    /// Follow the session without taking turns, mob claim makes you a driver later
+    #[clap(long)]
+    observe: bool,
+}
+
+/// Sets up this machine for a running session without taking the turn, safe to repeat
+pub struct Join<'a> {
+    git: &'a dyn git::Git,
+    store: &'a dyn session::Store,
+    opts: JoinOpts,
+    config: Config,
+    interactive: bool,
+}
+
+impl<'a> Join<'a> {
+    pub fn new(
+        git: &'a impl git::Git,
+        store: &'a impl session::Store,
+        opts: JoinOpts,
+        config: Config,
+    ) -> Join<'a> {
+        Self {
+            git,
+            store,
+            opts,
+            config,
+            interactive: deadline::attended(),
+        }
+    }
+
+    pub fn run(&self) -> Result<()> {
+        let me = self.config.name.as_str();
+
+        let session = self.store.load()?;
+        if session.state == State::Stopped {
+            log::warn!("No current mob session, run mob start");
+            return Ok(());
+        }
+
+        let session = if session.drivers.contains(me) || session.observers.contains(me) {
+            if self.opts.observe && roles::role(&session, me) == roles::Role::Driver {
+                log::warn!("You are one of the drivers already, and stay one");
+            }
+            session
+        } else if self.opts.observe {
+            let session = roles::observe(session, me);
+            self.store.save(session.clone())?;
+            log::info!("Added you to the observers");
+            session
+        } else {
+            let drivers = match limits::admit(
+                &session.drivers,
+                None,
+                me,
+                self.config.limits.max_drivers,
+                self.opts.force,
+                self.interactive,
+            )? {
+                Some(drivers) => drivers,
+                None => return Ok(()),
+            };
+            let session = session::Session { drivers, ..session };
+            self.store.save(session.clone())?;
+            log::info!("Added you to the drivers");
+            session
+        };
+
+        if self.git.is_repository() {
+            self.checkout(&session.branches)?;
+        }
+
+        self.banner(&session);
+        Ok(())
+    }
+
+    fn checkout(&self, branches: &session::Branches) -> Result<()> {
+        let branch = branches.branch.as_str();
+        self.git.check_branch_name(branch)?;
+        let remote_branch = branches.with_remote(&self.config.remote).branch;
+
+        let snapshot = self.git.snapshot()?;
+        if snapshot.is_on(branch) && snapshot.upstream.as_deref() == Some(remote_branch.as_str()) {
+            return Ok(());
+        }
+        if !snapshot.is_clean() {
+            log::warn!("Working tree is not clean, check out {} yourself", branch);
+            return Ok(());
+        }
+
+        self.git.run(&["fetch", "--all", "--prune"])?;
+        if !self.git.has_branch(&remote_branch)? {
+            log::warn!("{} isn't pushed yet, wait for the driver", remote_branch);
+            return Ok(());
+        }
+
+        if self.git.has_branch(branch)? {
+            // Keep what is local, it may have work that isn't pushed
+            self.git.run(&["checkout", branch])?;
+            self.git.run(&[
+                "branch",
+                &format!("--set-upstream-to={}", remote_branch),
+                "--",
+                branch,
+            ])?;
+            self.git
+                .run(&["merge", "--ff-only", "--", &remote_branch])?;
+        } else {
+            self.git
+                .run(&["checkout", "-b", branch, "--track", &remote_branch])?;
+        }
+        Ok(())
+    }
+
+    fn banner(&self, session: &session::Session) {
+        let me = self.config.name.as_str();
+        let name = |driver: &str| {
+            if driver == me {
+                "you".to_string()
+            } else {
+                driver.to_string()
+            }
+        };
+
+        log::info!("Joined the mob on {}", session.branches.branch);
+        if let Some(settings) = &session.settings {
+            let breaks = match (settings.break_interval, settings.break_duration) {
+                (Some(interval), duration) => format!(
+                    ", breaks of {} minutes every {} minutes",
+                    duration.unwrap_or(crate::schedule::DEFAULT_BREAK_MINUTES),
+                    interval
+                ),
+                (None, _) => "".to_string(),
+            };
+            log::info!("Turns of {} minutes{}", settings.work_duration, breaks);
+        }
+        match &session.state {
+            State::Working { driver } => log::info!("{} driving", name(driver)),
+            State::WaitingForNext { next: Some(next) } => {
+                log::info!("Waiting for {} to run mob start", name(next))
+            }
+            _ => log::info!("Waiting for anyone to run mob start"),
+        }
+        let drivers: Vec<String> = session.drivers.all().iter().map(|d| name(d)).collect();
+        log::info!("Drivers: {}", drivers.join(", "));
+        if !session.observers.is_empty() {
+            let observers: Vec<String> = session.observers.all().iter().map(|o| name(o)).collect();
+            log::info!("Observers: {}", observers.join(", "));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::fake::{FakeGit, MemoryStore};
+    use crate::session::Store;
+    use crate::state_dir::StateDir;
+
+    #[test]
+    fn join_from_fresh_clone() {
+        let memory = MemoryStore::default();
+        let session_store = session::SessionStore::new(&memory);
+        session_store
+            .save(session::Session {
+                drivers: session::Drivers::new(vec!["alice".into(), "bob".into()]),
+                state: State::Working {
+                    driver: "bob".into(),
+                },
+                settings: Some(session::Settings::default()),
+                ..session::Session::default()
+            })
+            .unwrap();
+        let state_dir = tempfile::tempdir().unwrap();
+        let store = session::SeenStore::new(
+            &session_store,
+            StateDir::new(state_dir.path().join("mob")),
+            "carol",
+            true,
+        );
+        let config = || Config {
+            name: "carol".into(),
+            ..Config::default()
+        };
+
+        let git = FakeGit::default().with_branches(&["origin/mob-session"]);
+        Join::new(&git, &store, JoinOpts::default(), config())
+            .run()
+            .unwrap();
+
+        let session = session_store.load().unwrap();
+        assert_eq!(session.drivers.all(), vec!["alice", "bob", "carol"]);
+        assert_eq!(
+            session.state,
+            State::Working {
+                driver: "bob".into()
+            }
+        );
+        assert_eq!(
+            git.calls(),
+            vec![
+                "fetch --all --prune",
+                "checkout -b mob-session --track origin/mob-session"
+            ]
+        );
+        assert!(state_dir.path().join("mob").join("last_seen.json").exists());
+
+        // Running it again changes nothing
+        let saves = *memory.saves.borrow();
+        let git = FakeGit {
+            snapshot: git::Snapshot {
+                branch: Some("mob-session".into()),
+                upstream: Some("origin/mob-session".into()),
+                ..git::Snapshot::default()
+            },
+            ..FakeGit::default().with_branches(&["mob-session", "origin/mob-session"])
+        };
+        Join::new(&git, &store, JoinOpts::default(), config())
+            .run()
+            .unwrap();
+        assert_eq!(*memory.saves.borrow(), saves);
+        assert!(git.calls().is_empty());
+        assert_eq!(session_store.load().unwrap(), session);
+    }
+
+    #[test]
+    fn keeps_local_branch() {
+        let memory = MemoryStore::default();
+        let store = session::SessionStore::new(&memory);
+        store
+            .save(session::Session {
+                drivers: session::Drivers::new(vec!["carol".into()]),
+                state: State::WaitingForNext { next: None },
+                ..session::Session::default()
+            })
+            .unwrap();
+        let git = FakeGit::default().with_branches(&["mob-session", "origin/mob-session"]);
+        let config = Config {
+            name: "carol".into(),
+            ..Config::default()
+        };
+        Join::new(&git, &store, JoinOpts::default(), config)
+            .run()
+            .unwrap();
+        assert_eq!(
+            git.calls(),
+            vec![
+                "fetch --all --prune",
+                "checkout mob-session",
+                "branch --set-upstream-to=origin/mob-session -- mob-session",
+                "merge --ff-only -- origin/mob-session"
+            ]
+        );
+    }
+}
@@ -0,0 +1,166 @@
+//! The layers every command runs in, for the `mob` binary and the async commands alike: the
+//! lock that lets one command at a time change the sessions of a machine, the queue of turns
+//! handed over offline, the stores that follow the remote tip, tell what others changed and
+//! keep the env file, and taking in commits pushed past mob.
+
+use super::Outside;
+use crate::clock::SystemClock;
+use crate::config::Config;
+use crate::git::{self, queue};
+use crate::lock;
+use crate::session::{self, EnvStore, SeenStore, SessionStore, TipStore};
+use crate::state_dir::StateDir;
+use anyhow::{anyhow, Result};
+
+/// What a command runs against inside the layers
+pub struct Layers<'a> {
+    /// The git and the documents of the command, both through the queue
+    pub queue: &'a queue::Queue<'a>,
+    /// The session as saved, without telling or keeping anything
+    pub session_store: &'a SessionStore<'a>,
+    /// The session through all the layers, what commands load and save
+    pub store: &'a EnvStore<'a>,
+    /// What the session keeps on this machine
+    pub state_dir: &'a StateDir,
+    /// The state dir of all sessions
+    pub root: &'a StateDir,
+}
+
+/// Commands, named as on the command line, that leave the session and the state dir as they
+/// are, so they run while another command holds the lock
+pub fn read_only(command: &str) -> bool {
+    matches!(
+        command,
+        "status"
+            | "watch"
+            | "stats"
+            | "verify"
+            | "export"
+            | "env"
+            | "update"
+            | "replay"
+            | "config"
+            | "completions"
+    )
+}
+
+/// Where a session keeps what is local to this machine, the default one directly in the
+/// state dir so sessions from before names keep their state
+pub fn session_dir(state_dir: &StateDir, name: &str) -> StateDir {
+    match name {
+        session::DEFAULT_SESSION => state_dir.clone(),
+        name => StateDir::new(state_dir.path().join("sessions").join(name)),
+    }
+}
+
+/// Whether turns of the session `name` were handed over offline and wait for `mob sync`,
+/// fetching would drop them
+pub fn pending(state_dir: &StateDir, name: &str) -> bool {
+    queue::Pending::read(&session_dir(state_dir, name)).is_some()
+}
+
+/// Runs `command`, named as on the command line, on the session `name` inside the layers
+pub fn layered<T>(
+    command: &str,
+    name: &str,
+    git: &impl git::Git,
+    store: &impl git::Store,
+    state_dir: StateDir,
+    config: &Config,
+    run: impl FnOnce(&Layers) -> Result<T>,
+) -> Result<T> {
+    // Sessions share the lock, one command at a time runs on a machine
+    let held = match read_only(command) {
+        true => None,
+        false => Some(lock::acquire(&state_dir, lock::TIMEOUT, &lock::alive)?),
+    };
+    let root = state_dir;
+    let state_dir = session_dir(&root, name);
+    // Turns handed over without a connection wait for mob sync, nothing else may pass them
+    let mode = match (command, config.git.offline) {
+        ("start", true) | ("next", true) | ("sync", true) => queue::Mode::Offline,
+        ("next", false) | ("sync", false) => queue::Mode::Fallback,
+        _ => queue::Mode::Online,
+    };
+    let queue = queue::Queue::new(git, store, state_dir.clone(), &config.remote, mode);
+    if queue::Pending::read(&state_dir).is_some()
+        && matches!(command, "join" | "done" | "clean" | "emergency" | "import")
+    {
+        return Err(anyhow!(
+            "Turns handed over offline aren't pushed yet, run mob sync first"
+        ));
+    }
+    let session_store = SessionStore::named(&queue, name);
+    let tip_store = TipStore::new(&session_store, &queue, &config.remote);
+    // Tell what others changed before acting on it, status shows it all anyway
+    let announce = command != "status";
+    let seen_store = SeenStore::new(&tip_store, state_dir.clone(), &config.name, announce);
+    let store = EnvStore::new(&seen_store, state_dir.clone());
+    // Commits pushed past mob are taken in before acting on a session that missed them
+    if !read_only(command) && !matches!(command, "clean" | "import") {
+        Outside::new(&queue, &store, &SystemClock, config.clone()).run()?;
+    }
+    // Watching the remote runs until the session stops, it takes the lock around each pull
+    if command == "watch-remote" {
+        drop(held);
+    }
+    run(&Layers {
+        queue: &queue,
+        session_store: &session_store,
+        store: &store,
+        state_dir: &state_dir,
+        root: &root,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::fixtures::{alice, working};
+    use crate::env::ENV_FILE;
+    use crate::git::fake::MemoryStore;
+    use crate::session::{State, Store as _};
+
+    #[test]
+    fn read_only_commands_run_while_the_lock_is_held() {
+        let dir = tempfile::tempdir().unwrap();
+        let state_dir = StateDir::new(dir.path().into());
+        let _held = lock::acquire(&state_dir, lock::TIMEOUT, &lock::alive).unwrap();
+        let memory = MemoryStore::default();
+        let session = layered(
+            "status",
+            session::DEFAULT_SESSION,
+            &git::NoopGit,
+            &memory,
+            state_dir,
+            &alice(),
+            |layers| Ok(layers.store.load()?),
+        );
+        assert_eq!(session.unwrap().state, State::Stopped);
+    }
+
+    #[test]
+    fn keeps_the_env_file_of_the_named_session() {
+        let dir = tempfile::tempdir().unwrap();
+        let state_dir = StateDir::new(dir.path().into());
+        let memory = MemoryStore::default();
+        layered(
+            "start",
+            "payments",
+            &git::NoopGit,
+            &memory,
+            state_dir.clone(),
+            &alice(),
+            |layers| Ok(layers.store.save(working())?),
+        )
+        .unwrap();
+
+        let named = session_dir(&state_dir, "payments");
+        assert!(named.path().join(ENV_FILE).exists());
+        assert!(!state_dir.path().join(ENV_FILE).exists());
+        let saved = SessionStore::named(&memory, "payments").load().unwrap();
+        assert_eq!(saved.state, working().state);
+        // Nobody waits for the lock once the command ended
+        lock::acquire(&state_dir, lock::TIMEOUT, &lock::alive).unwrap();
+    }
+}
@@ -0,0 +1,70 @@
+//! Keeping files Git LFS tracks out of the history itself. Hooks are skipped on our
+//! commits, so a missing LFS setup would go unnoticed until the history is bloated.
+
+use crate::git::{self, Lfs};
+use anyhow::Result;
+
+/// Why committing what is staged would store files LFS tracks as regular blobs, `None`
+/// when it wouldn't
+pub(super) fn problem(git: &dyn git::Git) -> Result<Option<String>> {
+    match git.lfs()? {
+        Lfs::Unused => return Ok(None),
+        Lfs::NotInstalled => {
+            return Ok(Some(
+                "This repository stores files with Git LFS but git lfs is not installed, \
+                 install it and run git lfs install"
+                    .into(),
+            ))
+        }
+        Lfs::NotInitialized => {
+            return Ok(Some(
+                "This repository stores files with Git LFS but it is not set up, \
+                 run git lfs install"
+                    .into(),
+            ))
+        }
+        Lfs::Ready => (),
+    }
+    let unconverted = git.lfs_unconverted()?;
+    if unconverted.is_empty() {
+        return Ok(None);
+    }
+    let files = unconverted.join(" ");
+    Ok(Some(format!(
+        "Git LFS tracks {} but they are staged as regular files, \
+         run git rm --cached -- {} and git add -- {} to stage them through LFS",
+        files, files, files
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::fake::FakeGit;
+
+    fn problem_with(lfs: Lfs, unconverted: &[&str]) -> Option<String> {
+        let git = FakeGit {
+            lfs,
+            lfs_unconverted: unconverted.iter().map(|path| path.to_string()).collect(),
+            ..FakeGit::default()
+        };
+        problem(&git).unwrap()
+    }
+
+    #[test]
+    fn only_when_lfs_would_be_skipped() {
+        assert_eq!(problem_with(Lfs::Unused, &["art/logo.psd"]), None);
+        assert_eq!(problem_with(Lfs::Ready, &[]), None);
+        assert!(problem_with(Lfs::NotInstalled, &[])
+            .unwrap()
+            .contains("git lfs is not installed"));
+        assert!(problem_with(Lfs::NotInitialized, &[])
+            .unwrap()
+            .ends_with("run git lfs install"));
+        assert_eq!(
+            problem_with(Lfs::Ready, &["art/logo.psd"]).unwrap(),
+            "Git LFS tracks art/logo.psd but they are staged as regular files, \
+             run git rm --cached -- art/logo.psd and git add -- art/logo.psd to stage them through LFS"
+        );
+    }
+}
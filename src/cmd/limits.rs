@@ -0,0 +1,208 @@
+//! Keeping the mob within `limits.max_drivers` wherever someone joins it, and at
+//! `limits.min_drivers` from its start until it ends.
+
+use crate::{deadline, prompt, record, session};
+use anyhow::{anyhow, Result};
+
+/// The drivers with `name` added after `after`. Beyond the limit that takes `force` or a
+/// confirmation, `None` when it was declined.
+pub(super) fn admit(
+    drivers: &session::Drivers,
+    after: Option<String>,
+    name: &str,
+    max: Option<usize>,
+    force: bool,
+    interactive: bool,
+) -> Result<Option<session::Drivers>> {
+    // Those who drove before under such a name keep driving
+    if session::placeholder(name) && !drivers.contains(name) {
+        if !force {
+            return Err(anyhow!(
+                "{} can't be told apart from nobody in particular, set another name with \
+                 mob config set name, or run with --force to join anyway",
+                session::shown(name)
+            ));
+        }
+        log::warn!(
+            "{} can't be told apart from nobody in particular, joining anyway",
+            session::shown(name)
+        );
+    }
+    let err = match drivers.clone().admit(after.clone(), name, max) {
+        Ok(drivers) => return Ok(Some(drivers)),
+        Err(err) => err,
+    };
+    if force {
+        log::warn!("{}, joining anyway", err);
+        return Ok(Some(drivers.clone().insert(after, name)));
+    }
+    let prompt = format!("Join a mob of {} anyway?", err.drivers);
+    // Growing past the limit needs someone to decide it
+    let join = match deadline::answer(&prompt, None) {
+        Ok(Some(join)) => join,
+        Ok(None) if interactive => {
+            log::warn!("{}", err);
+            record::answer(&prompt, prompt::terminal().confirm(prompt.as_str(), false)?)
+        }
+        _ => return Err(anyhow!("{}, run with --force to join anyway", err)),
+    };
+    if !join {
+        log::info!("Not joining, the mob stays at {}", drivers.all().len());
+        return Ok(None);
+    }
+    Ok(Some(drivers.clone().insert(after, name)))
+}
+
+/// Whether a new session of `drivers` may start. Below the minimum that takes `force` or a
+/// confirmation, unless `enforce` refuses it.
+pub(super) fn enough(
+    drivers: &session::Drivers,
+    min: Option<usize>,
+    enforce: bool,
+    force: bool,
+    interactive: bool,
+) -> Result<bool> {
+    let err = match drivers.require(min) {
+        Ok(()) => return Ok(true),
+        Err(err) => err,
+    };
+    if enforce {
+        return Err(anyhow!(
+            "{}, limits.enforce allows no session with fewer",
+            err
+        ));
+    }
+    if force {
+        log::warn!("{}, starting anyway", err);
+        return Ok(true);
+    }
+    let prompt = format!("Start a mob of {} anyway?", err.drivers);
+    let start = match deadline::answer(&prompt, None) {
+        Ok(Some(start)) => start,
+        Ok(None) if interactive => {
+            log::warn!("{}", err);
+            record::answer(&prompt, prompt::terminal().confirm(prompt.as_str(), false)?)
+        }
+        _ => return Err(anyhow!("{}, run with --force to start anyway", err)),
+    };
+    if !start {
+        log::info!("Not starting, the mob has {}", err.drivers);
+    }
+    Ok(start)
+}
+
+/// The drivers without `name`, unless that takes the mob below its minimum while the
+/// session goes on
+pub(super) fn dismiss(
+    drivers: &session::Drivers,
+    name: &str,
+    min: Option<usize>,
+    ending: bool,
+) -> Result<session::Drivers> {
+    match ending {
+        true => Ok(drivers.clone().remove(name)),
+        false => drivers
+            .clone()
+            .dismiss(name, min)
+            .map_err(|err| anyhow!("{}, run mob done to end the session instead", err)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn drivers() -> session::Drivers {
+        session::Drivers::new(vec!["alice".into(), "bob".into()])
+    }
+
+    #[test]
+    fn refuses_unattended() {
+        let err = admit(&drivers(), None, "carol", Some(2), false, false).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "The mob would have 3 drivers, limits.max_drivers allows 2, run with --force to join anyway"
+        );
+
+        deadline::set(Some(std::time::Duration::from_secs(30)));
+        let result = admit(&drivers(), None, "carol", Some(2), false, true);
+        deadline::set(None);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn joins_with_force_or_within_the_limit() {
+        let joined = |max, force| {
+            admit(&drivers(), None, "carol", max, force, false)
+                .unwrap()
+                .map(|drivers| drivers.all().len())
+        };
+        assert_eq!(joined(Some(2), true), Some(3));
+        assert_eq!(joined(Some(3), false), Some(3));
+        assert_eq!(joined(None, false), Some(3));
+        // Those who drove before are never turned away
+        let again = admit(&drivers(), None, "bob", Some(2), false, false).unwrap();
+        assert_eq!(again, Some(drivers()));
+    }
+
+    #[test]
+    fn admits_placeholder_names_only_with_force() {
+        let err = admit(&drivers(), None, "anyone", None, false, false).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "\"anyone\" can't be told apart from nobody in particular, set another name with \
+             mob config set name, or run with --force to join anyway"
+        );
+        let forced = admit(&drivers(), None, "anyone", None, true, false)
+            .unwrap()
+            .unwrap();
+        assert!(forced.contains("anyone"));
+        // Grandfathered once among the drivers
+        let again = admit(&forced, None, "anyone", None, false, false).unwrap();
+        assert_eq!(again, Some(forced));
+    }
+
+    #[test]
+    fn keeps_the_minimum_from_start_to_end() {
+        let mob = |size: usize| {
+            let names = ["alice", "bob", "carol", "dave"];
+            session::Drivers::new(names[..size].iter().map(|name| name.to_string()).collect())
+        };
+        // Starting, below the minimum only with --force as nobody can confirm
+        let cases = vec![
+            (2, Some(3), false),
+            (3, Some(3), true),
+            (4, Some(3), true),
+            (1, None, true),
+        ];
+        for (size, min, expected) in cases {
+            assert_eq!(
+                enough(&mob(size), min, false, false, false).is_ok(),
+                expected,
+                "{} of {:?}",
+                size,
+                min
+            );
+        }
+        assert!(enough(&mob(2), Some(3), false, true, false).unwrap());
+        let err = enough(&mob(2), Some(3), true, true, true).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "limits.min_drivers requires 3 drivers, the mob would have 2, limits.enforce allows no session with fewer"
+        );
+
+        // Joining only ever grows the mob
+        let joined = admit(&mob(2), None, "carol", None, false, false).unwrap();
+        assert_eq!(joined, Some(mob(3)));
+
+        // Removing, down to exactly the minimum
+        assert_eq!(dismiss(&mob(4), "dave", Some(3), false).unwrap(), mob(3));
+        let err = dismiss(&mob(3), "carol", Some(3), false).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "limits.min_drivers requires 3 drivers, the mob would have 2, run mob done to end the session instead"
+        );
+        assert_eq!(dismiss(&mob(3), "carol", Some(3), true).unwrap(), mob(2));
+        assert_eq!(dismiss(&mob(3), "carol", None, false).unwrap(), mob(2));
+    }
+}
@@ -0,0 +1,77 @@
+//! Keeping conflict markers out of the commits of a turn. Hooks are skipped on our commits,
+//! so markers left after fiddling with a merge would reach everyone with the next push.
+
+use crate::git::{markers, Git};
+use crate::{deadline, prompt, record};
+use anyhow::{anyhow, Result};
+
+/// Asks before committing staged files with conflict markers, other than the `allowed`
+/// ones, and refuses when nobody can be asked
+pub(super) fn check(git: &dyn Git, allowed: &[String], interactive: bool) -> Result<()> {
+    let files: Vec<String> = markers::find(&git.staged_diff()?)
+        .into_iter()
+        .filter(|file| !markers::allowed(file, allowed))
+        .collect();
+    if files.is_empty() {
+        return Ok(());
+    }
+    let listed = files.join(", ");
+    log::warn!("Conflict markers are staged in {}", listed);
+    let question = "Commit the conflict markers anyway?";
+    let commit = match deadline::answer(question, Some(false))? {
+        Some(commit) => commit,
+        None if interactive => {
+            record::answer(question, prompt::terminal().confirm(question, false)?)
+        }
+        None => false,
+    };
+    match commit {
+        true => Ok(()),
+        false => Err(anyhow!(
+            "Not committing the conflict markers in {}, resolve them or add the files to \
+             commit.allow_markers in ~/.mob",
+            listed
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::fake::FakeGit;
+
+    const STAGED: &str = "diff --git a/src/lib.rs b/src/lib.rs\n+++ b/src/lib.rs\n\
+                          @@ -1,0 +1,3 @@\n+<<<<<<< HEAD\n+=======\n+>>>>>>> feature\n\
+                          diff --git a/docs/merging.md b/docs/merging.md\n+++ b/docs/merging.md\n\
+                          @@ -1,0 +1 @@\n+<<<<<<< ours\n";
+
+    fn staged(diff: &str) -> FakeGit {
+        FakeGit {
+            staged: diff.into(),
+            ..FakeGit::default()
+        }
+    }
+
+    #[test]
+    fn refuses_markers_without_anyone_to_ask() {
+        let err = check(&staged(STAGED), &[], false).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Not committing the conflict markers in src/lib.rs, docs/merging.md, resolve them \
+             or add the files to commit.allow_markers in ~/.mob"
+        );
+        let err = check(&staged(STAGED), &["docs/*.md".into()], false).unwrap_err();
+        assert!(
+            err.to_string().contains(" in src/lib.rs, resolve"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn commits_allowed_files_and_clean_changes() {
+        let git = staged(STAGED);
+        check(&git, &["*.md".into(), "src/**".into()], false).unwrap();
+        check(&staged(""), &[], false).unwrap();
+    }
+}
@@ -1,10 +1,421 @@
+mod away;
+mod breaks;
+mod claim;
+mod clean;
+mod completions;
+mod config;
+mod doctor;
 mod done;
+mod drivers;
+mod editor;
+mod emergency;
+mod env;
+mod export;
+#[cfg(test)]
+mod fixtures;
+mod import;
+mod join;
+mod layers;
+mod lfs;
+mod limits;
+mod markers;
 mod next;
 mod order;
+mod outside;
+mod pause;
+mod preset;
+mod push;
+mod remote;
+mod resolve;
+mod skip;
 mod start;
+mod stash;
+mod stats;
 mod status;
-pub use done::Done;
-pub use next::Next;
+mod sync;
+mod untracked;
+mod update;
+mod verify;
+mod watch;
+mod watch_remote;
+mod whitespace;
+pub use away::{Away, AwayOpts, Back};
+pub use breaks::{Break, BreakOpts};
+pub use claim::{Claim, Unclaim};
+pub use clean::{Clean, CleanOpts};
+pub use completions::{Completions, CompletionsOpts};
+pub use config::{ConfigOpts, Configure};
+pub use doctor::{Doctor, DoctorOpts};
+pub use done::{Done, DoneOpts};
+pub use drivers::{Drivers, DriversOpts};
+pub use emergency::{Emergency, EmergencyOpts};
+pub use env::{Env, EnvOpts};
+pub use export::{Export, ExportOpts};
+pub use import::{Import, ImportOpts};
+pub use join::{Join, JoinOpts};
+pub use layers::{layered, pending, read_only, session_dir, Layers};
+pub use next::{Next, NextOpts};
 pub use order::Order;
+pub use outside::Outside;
+pub use pause::{Pause, PauseOpts, Resume};
+pub use preset::{Preset, PresetOpts};
+pub use remote::{check_remote, chosen_remote};
+pub use skip::{Skip, SkipOpts};
 pub use start::{Start, StartOpts};
+pub use stats::{Stats, StatsOpts};
 pub use status::{Status, StatusOpts};
+pub use sync::Sync;
+pub use update::{Update, UpdateOpts};
+pub use verify::{Verify, VerifyOpts};
+pub use watch::Watch;
+pub use watch_remote::{WatchRemote, WatchRemoteOpts};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::FakeClock;
+    use crate::timer::fake::NoTimer;
+    use crate::{git, session, session::Store};
+    use clap::Clap;
+    use fixtures::config;
+
+    fn start(store: &session::SessionStore, name: &str) {
+        let opts = StartOpts::parse_from(["start", "--local"]);
+        Start::new(
+            &git::NoopGit,
+            store,
+            &NoTimer,
+            &FakeClock::default(),
+            opts,
+            config(name),
+        )
+        .run()
+        .unwrap();
+    }
+
+    #[test]
+    fn local_rotation() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_store = git::FileStore::new(dir.path());
+        let store = session::SessionStore::new(&file_store);
+        store
+            .save(session::Session {
+                settings: Some(session::Settings::default()),
+                ..session::Session::default()
+            })
+            .unwrap();
+
+        start(&store, "alice");
+        assert!(git::FileStore::exists(dir.path()));
+        Next::new(
+            &git::NoopGit,
+            &store,
+            &NoTimer,
+            &FakeClock::default(),
+            NextOpts::parse_from(["next"]),
+            config("alice"),
+        )
+        .run()
+        .unwrap();
+
+        start(&store, "bob");
+        Next::new(
+            &git::NoopGit,
+            &store,
+            &NoTimer,
+            &FakeClock::default(),
+            NextOpts::parse_from(["next"]),
+            config("bob"),
+        )
+        .run()
+        .unwrap();
+        match store.load().unwrap().state {
+            session::State::WaitingForNext { next } => assert_eq!(next.as_deref(), Some("alice")),
+            other => panic!("Expected alice to be next, got {:?}", other),
+        }
+
+        start(&store, "alice");
+        let state_dir = crate::state_dir::StateDir::new(dir.path().join(".mob-local.d"));
+        Done::new(
+            &git::NoopGit,
+            &store,
+            &FakeClock::default(),
+            state_dir.clone(),
+            DoneOpts::parse_from(["done"]),
+            config("alice"),
+        )
+        .run()
+        .unwrap();
+
+        let session = store.load().unwrap();
+        assert!(matches!(session.state, session::State::Stopped));
+        assert_eq!(session.drivers.all(), vec!["alice", "bob"]);
+
+        let reports = crate::stats::read(&[state_dir.file("reports")], None);
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].turns["alice"], 2);
+        assert_eq!(reports[0].turns["bob"], 1);
+    }
+
+    #[test]
+    fn keeps_the_session_id() {
+        let memory = git::fake::MemoryStore::default();
+        let store = session::SessionStore::new(&memory);
+        store
+            .save(session::Session {
+                settings: Some(session::Settings::default()),
+                ..session::Session::default()
+            })
+            .unwrap();
+        let clock = FakeClock::default();
+        let start = |name: &str| {
+            let opts = StartOpts::parse_from(["start", "--local"]);
+            Start::new(&git::NoopGit, &store, &NoTimer, &clock, opts, config(name))
+                .run()
+                .unwrap()
+        };
+        let id = || store.load().unwrap().id;
+
+        start("alice");
+        let started = id().expect("mob start gives the session an id");
+        Next::new(
+            &git::NoopGit,
+            &store,
+            &NoTimer,
+            &clock,
+            NextOpts::parse_from(["next"]),
+            config("alice"),
+        )
+        .run()
+        .unwrap();
+        start("bob");
+        for _ in 0..2 {
+            Pause::new(
+                &store,
+                &NoTimer,
+                &clock,
+                PauseOpts::parse_from(["pause"]),
+                config("bob"),
+            )
+            .run()
+            .unwrap();
+        }
+        assert_eq!(id().as_ref(), Some(&started));
+
+        let git = git::fake::FakeGit::default();
+        let dir = tempfile::tempdir().unwrap();
+        let state_dir = crate::state_dir::StateDir::new(dir.path().into());
+        let mut opts = DoneOpts::parse_from(["done", "-m", "Add login"]);
+        opts.yes = true;
+        Done::new(&git, &store, &clock, state_dir.clone(), opts, config("bob"))
+            .run()
+            .unwrap();
+        let trailer = format!("Mob-Session: {}", started);
+        assert!(
+            git.calls()
+                .iter()
+                .any(|call| call.starts_with("commit") && call.contains(&trailer)),
+            "{:?}",
+            git.calls()
+        );
+        let reports = crate::stats::read(&[state_dir.file("reports")], None);
+        assert_eq!(reports[0].session.as_ref(), Some(&started));
+
+        // The next session is another one
+        clock.advance(chrono::Duration::hours(1));
+        start("alice");
+        assert_ne!(id().as_ref(), Some(&started));
+    }
+
+    #[test]
+    fn max_drivers() {
+        let memory = git::fake::MemoryStore::default();
+        let store = session::SessionStore::new(&memory);
+        store
+            .save(session::Session {
+                settings: Some(session::Settings::default()),
+                ..session::Session::default()
+            })
+            .unwrap();
+        let limited = |name: &str| {
+            let mut config = config(name);
+            config.limits.max_drivers = Some(2);
+            config
+        };
+        let start = |name: &str, args: &[&str]| {
+            let opts = StartOpts::parse_from(args);
+            Start::new(
+                &git::NoopGit,
+                &store,
+                &NoTimer,
+                &FakeClock::default(),
+                opts,
+                limited(name),
+            )
+            .run()
+        };
+        let next = |name: &str| {
+            Next::new(
+                &git::NoopGit,
+                &store,
+                &NoTimer,
+                &FakeClock::default(),
+                NextOpts::parse_from(["next"]),
+                limited(name),
+            )
+            .run()
+            .unwrap()
+        };
+
+        start("alice", &["start", "--local"]).unwrap();
+        next("alice");
+        start("bob", &["start", "--local"]).unwrap();
+        next("bob");
+        let err = start("carol", &["start", "--local"]).unwrap_err();
+        assert!(err.to_string().contains("--force"), "{}", err);
+        let join = |opts: &[&str]| {
+            Join::new(
+                &git::NoopGit,
+                &store,
+                JoinOpts::parse_from(opts),
+                limited("carol"),
+            )
+            .run()
+        };
+        assert!(join(&["join"]).is_err());
+        assert_eq!(store.load().unwrap().drivers.all(), vec!["alice", "bob"]);
+
+        // Who is in already comes back without one
+        start("alice", &["start", "--local"]).unwrap();
+        next("alice");
+        join(&["join", "--force"]).unwrap();
+        assert_eq!(
+            store.load().unwrap().drivers.all(),
+            vec!["alice", "bob", "carol"]
+        );
+        start("dave", &["start", "--local", "--force"]).unwrap();
+        assert_eq!(store.load().unwrap().drivers.all().len(), 4);
+    }
+
+    #[test]
+    fn observers() {
+        let memory = git::fake::MemoryStore::default();
+        let store = session::SessionStore::new(&memory);
+        store
+            .save(session::Session {
+                settings: Some(session::Settings::default()),
+                ..session::Session::default()
+            })
+            .unwrap();
+        start(&store, "alice");
+        Join::new(
+            &git::NoopGit,
+            &store,
+            JoinOpts::parse_from(["join", "--observe"]),
+            config("olga"),
+        )
+        .run()
+        .unwrap();
+        let session = store.load().unwrap();
+        assert_eq!(session.drivers.all(), vec!["alice"]);
+        assert_eq!(session.observers.all(), vec!["olga"]);
+
+        let refused = |result: anyhow::Result<()>, action| {
+            let err = result.unwrap_err();
+            assert_eq!(
+                err.downcast_ref::<session::roles::NotADriver>(),
+                Some(&session::roles::NotADriver { action }),
+                "{}",
+                err
+            );
+        };
+        use session::roles::Action;
+        let opts = StartOpts::parse_from(["start", "--local"]);
+        refused(
+            Start::new(
+                &git::NoopGit,
+                &store,
+                &NoTimer,
+                &FakeClock::default(),
+                opts,
+                config("olga"),
+            )
+            .run(),
+            Action::Start,
+        );
+        refused(
+            Next::new(
+                &git::NoopGit,
+                &store,
+                &NoTimer,
+                &FakeClock::default(),
+                NextOpts::parse_from(["next"]),
+                config("olga"),
+            )
+            .run(),
+            Action::Next,
+        );
+        let state_dir = tempfile::tempdir().unwrap();
+        refused(
+            Done::new(
+                &git::NoopGit,
+                &store,
+                &FakeClock::default(),
+                crate::state_dir::StateDir::new(state_dir.path().join("mob")),
+                DoneOpts::parse_from(["done"]),
+                config("olga"),
+            )
+            .run(),
+            Action::Done,
+        );
+        refused(
+            Break::new(
+                &git::NoopGit,
+                &store,
+                &NoTimer,
+                &FakeClock::default(),
+                BreakOpts::parse_from(["break"]),
+                config("olga"),
+            )
+            .run(),
+            Action::Break,
+        );
+        refused(
+            Order::new(&store, &FakeClock::default(), config("olga")).run(),
+            Action::Settings,
+        );
+        assert_eq!(store.load().unwrap(), session);
+
+        // Claiming takes a confirmation, then the observer drives like everyone else
+        Next::new(
+            &git::NoopGit,
+            &store,
+            &NoTimer,
+            &FakeClock::default(),
+            NextOpts::parse_from(["next"]),
+            config("alice"),
+        )
+        .run()
+        .unwrap();
+        assert!(Claim::new(&store, &FakeClock::default(), config("olga"))
+            .run()
+            .is_err());
+        crate::record::replay(vec![crate::record::Answer {
+            question: "You are observing, become one of the drivers?".into(),
+            answer: serde_json::json!(true),
+        }]);
+        let claimed = Claim::new(&store, &FakeClock::default(), config("olga")).run();
+        crate::record::stop_replay();
+        claimed.unwrap();
+        let session = store.load().unwrap();
+        assert_eq!(session.drivers.all(), vec!["alice", "olga"]);
+        assert!(session.observers.is_empty());
+        assert_eq!(
+            session.state,
+            session::State::WaitingForNext {
+                next: Some("olga".into())
+            }
+        );
+        start(&store, "olga");
+    }
+}
@@ -1,22 +1,96 @@
-use crate::{config::Config, git, session};
-use anyhow::Result;
-use session::State;
+use super::{breaks, push};
+use crate::schedule::{self, Kind};
+use crate::timekeeping::Minutes;
+use crate::{
+    clock::Clock, config::Config, deadline, duration, git, prompt, record, session, timer, until,
+};
+use anyhow::{anyhow, Result};
+use chrono::{Duration, Timelike};
+use clap::{self, Clap};
+use session::{answers, roles, State};
+
+#[derive(Clap, Debug)]
+pub struct NextOpts {
+    /// Hand over even when the turn is shorter than the session's minimum turn
+    #[clap(long)]
+    force: bool,
+
+    /// What the turn did, after the session's commit message in the WIP commit
+    #[clap(short, long)]
+    message: Option<String>,
+}
+
+#[derive(Debug, PartialEq)]
+enum Gate {
+    Proceed,
+    Ask,
+    Refuse,
+}
+
+/// Whether handing over a turn that lasted `elapsed` needs a confirmation first. Turns
+/// without a known start, as in sessions of older versions, never do.
+fn gate(min_turn: Minutes, elapsed: Option<Duration>, interactive: bool) -> Gate {
+    let short = matches!(elapsed, Some(elapsed) if elapsed < min_turn.duration());
+    match (short, interactive) {
+        (false, _) => Gate::Proceed,
+        (true, true) => Gate::Ask,
+        (true, false) => Gate::Refuse,
+    }
+}
+
+/// The WIP commit message, the session's `marker` first so `mob done` still tells WIP
+/// commits apart. An empty `summary` leaves the marker alone.
+fn wip_message(marker: &str, summary: Option<&str>) -> String {
+    match summary.map(str::trim).filter(|summary| !summary.is_empty()) {
+        Some(summary) => format!("{}: {}", marker, summary),
+        None => marker.to_string(),
+    }
+}
+
+/// A break the schedule has due, what `mob next` says and asks about it
+#[derive(Debug, PartialEq)]
+struct Offer {
+    title: String,
+    kind: Kind,
+    minutes: i64,
+    message: String,
+}
 
 pub struct Next<'a> {
     git: &'a dyn git::Git,
     store: &'a dyn session::Store,
+    timer: &'a dyn timer::Timer,
+    clock: &'a dyn Clock,
+    opts: NextOpts,
     config: Config,
+    interactive: bool,
 }
 
 impl<'a> Next<'a> {
-    pub fn new(git: &'a impl git::Git, store: &'a impl session::Store, config: Config) -> Next<'a> {
-        Self { git, store, config }
+    pub fn new(
+        git: &'a impl git::Git,
+        store: &'a impl session::Store,
+        timer: &'a impl timer::Timer,
+        clock: &'a impl Clock,
+        opts: NextOpts,
+        config: Config,
+    ) -> Next<'a> {
+        Self {
+            git,
+            store,
+            timer,
+            clock,
+            opts,
+            config,
+            interactive: deadline::attended(),
+        }
     }
 
     pub fn run(&self) -> Result<()> {
         let me = &self.config.name;
 
         let session = self.store.load()?;
+        roles::check(&session, me, roles::Action::Next)?;
         match &session.state {
             State::Stopped => {
                 log::warn!("No current mob session, run mob start");
@@ -32,45 +106,650 @@ impl<'a> Next<'a> {
                     None => log::info!("Waiting for someone to run start"),
                 };
             }
+            State::Paused { reason, .. } => {
+                log::warn!(
+                    "The session is paused for {}, run mob start to resume",
+                    reason
+                )
+            }
         };
         Ok(())
     }
 
-    fn next(&self, session: session::Session) -> Result<()> {
-        if self.git.tree_is_clean()? {
-            log::info!("Nothing was changed, so nothing to commit");
-        } else {
-            self.git.run(&["add", "--all"])?;
-            self.git.run(&[
-                "commit",
-                "--message",
-                session.settings.as_ref().unwrap().commit_message.as_str(),
-                "--no-verify",
-            ])?;
-
-            self.git.run(&[
-                "push",
-                "--no-verify",
-                self.config.remote.as_str(),
-                session.branches.branch.as_str(),
-            ])?;
+    /// How long the turn lasted when that is shorter than the mob agreed on
+    fn short_turn(&self, session: &session::Session) -> Option<Duration> {
+        let min_turn = session.settings.as_ref()?.min_turn_minutes;
+        let now = self.clock.now_utc();
+        // A clock that jumped back can't tell how long the turn was
+        let elapsed = session
+            .history
+            .last_driven(&self.config.name)
+            .filter(|started| *started <= now)
+            .map(|started| now - started);
+        match gate(min_turn, elapsed, self.interactive) {
+            Gate::Proceed => None,
+            _ => elapsed,
         }
+    }
 
-        let next_driver = session.drivers.next(&self.config.name);
-        let next_driver_name = match next_driver {
-            Some(ref driver) => driver,
-            None => "anyone!",
-        };
+    fn next(&self, session: session::Session) -> Result<()> {
+        let short = self.short_turn(&session);
+        if let (Some(elapsed), false) = (short, self.opts.force) {
+            let lasted = format!(
+                "Your turn has lasted {} minutes, the mob agreed on at least {}",
+                elapsed.num_minutes(),
+                session.settings.as_ref().unwrap().min_turn_minutes
+            );
+            // Unattended only a replay answers, anything else has to say --force
+            if !(self.interactive || record::replaying()) {
+                return Err(anyhow!(
+                    "{}, run mob next --force to hand over anyway",
+                    lasted
+                ));
+            }
+            log::warn!("{}", lasted);
+            let question = "Hand over anyway?";
+            let hand_over = match deadline::answer(question, Some(false))? {
+                Some(hand_over) => hand_over,
+                None => record::answer(question, prompt::terminal().confirm(question, false)?),
+            };
+            if !hand_over {
+                log::info!("Keep driving, it's still your turn");
+                return Ok(());
+            }
+        }
+        let message = wip_message(
+            &session.settings.as_ref().unwrap().commit_message,
+            self.summary()?.as_deref(),
+        );
+        let mut new_files = session.new_files.clone();
+        push::commit_turn(
+            self.git,
+            &self.config,
+            &session.branches.branch,
+            &message,
+            &mut new_files,
+            self.interactive,
+        )?;
 
+        let next_driver =
+            session
+                .drivers
+                .next(&self.config.name, &session.away, self.clock.now_utc());
         let session = session::Session {
             state: State::WaitingForNext {
                 next: next_driver.clone(),
             },
+            times: session::Times {
+                last_activity: Some(self.clock.now()),
+                waiting_since: Some(self.clock.now()),
+                claimed: None,
+                ..session.times
+            },
+            history: match short {
+                Some(_) => session.history.push(session::Record::ShortTurn {
+                    driver: self.config.name.clone(),
+                    at: self.clock.now_utc(),
+                }),
+                None => session.history,
+            },
+            new_files,
             ..session
         };
 
+        // An accepted break is saved with the handover, it only counts once it started
+        let (session, countdown) = self.take_break(session)?;
         self.store.save(session)?;
-        log::info!("Next driver: {}", next_driver_name);
+        if let Err(err) = self.timer.cancel() {
+            log::warn!("Could not cancel the timer: {:#}", err);
+        }
+        match &next_driver {
+            Some(driver) => log::info!("Next driver: {}", session::shown(driver)),
+            None => log::info!("Nobody in particular is next, anyone may run mob start"),
+        }
+        if let Some(countdown) = countdown {
+            countdown.start(self.timer, self.clock);
+        }
         Ok(())
     }
+
+    /// What the turn did as given with `--message`, or as told at the terminal
+    fn summary(&self) -> Result<Option<String>> {
+        if self.opts.message.is_some() || !self.git.is_repository() {
+            return Ok(self.opts.message.clone());
+        }
+        let question = "What did this turn do? Empty for nothing to add";
+        let summary = match deadline::answer(question, Some(String::new()))? {
+            Some(summary) => summary,
+            None if self.interactive && self.config.handover.ask_message => record::answer(
+                question,
+                prompt::terminal().input(question, Some(""), &|_| Ok(()))?,
+            ),
+            None => String::new(),
+        };
+        Ok(Some(summary))
+    }
+
+    /// The break or lunch the schedule has due with what to tell about it, `None` when
+    /// there is nothing to offer
+    fn offer(&self, session: &session::Session) -> Option<Offer> {
+        let settings = session.settings.as_ref()?;
+        let now = schedule::now(self.clock, settings.zone().as_ref());
+        let suggestion = schedule::suggest(&now, session, settings)?;
+        let minutes = suggestion.duration.num_minutes();
+        let window = suggestion.window.unwrap_or_else(|| "Lunch".into());
+        let message = match suggestion.kind {
+            Kind::Window => format!("It's {} time, {} minutes", window, minutes),
+            Kind::Break => format!("Time for a {} minute break", minutes),
+            Kind::WrapUp => format!(
+                "The workday ends in {} minutes, run mob done to wrap up",
+                minutes
+            ),
+            // We were just here
+            Kind::Stale => return None,
+        };
+        let title = match suggestion.kind {
+            Kind::Window => window,
+            _ => "Break".to_string(),
+        };
+        Some(Offer {
+            title,
+            kind: suggestion.kind,
+            minutes,
+            message,
+        })
+    }
+
+    /// Offers the break or lunch the schedule has due, the session with the answer and the
+    /// countdown of the break when it was accepted
+    fn take_break(
+        &self,
+        session: session::Session,
+    ) -> Result<(session::Session, Option<breaks::Countdown>)> {
+        let Offer {
+            title,
+            kind,
+            minutes,
+            message,
+        } = match self.offer(&session) {
+            Some(offer) => offer,
+            None => return Ok((session, None)),
+        };
+        log::info!("{}", message);
+        // Unattended the break is taken when the mob wants it, and only what was asked is learned
+        let asked = self.interactive || record::replaying();
+        if kind == Kind::WrapUp || !(asked || self.config.breaks.auto) {
+            return Ok((session, None));
+        }
+
+        let now = self.clock.now_local();
+        let context = answers::Context {
+            hour: now.hour(),
+            rested: session
+                .times
+                .rested()
+                .map(|rested| duration::elapsed(rested, self.clock.now_utc()).num_minutes())
+                .unwrap_or(0),
+        };
+        let learn = self.config.breaks.learn && asked;
+        let learned = Some(&session.answers)
+            .filter(|_| learn)
+            .and_then(|answers| answers.learned(context));
+        let skip = |answer: &str| matches!(answer.trim(), "n" | "no");
+        // Only the default follows what was answered before, the question is still asked
+        let (default, because) = match learned {
+            Some(learned) if !learned.took => ("no".to_string(), format!(" ({})", learned)),
+            Some(learned) => (format!("+{}m", minutes), format!(" ({})", learned)),
+            None => (format!("+{}m", minutes), String::new()),
+        };
+        let question = format!(
+            "Start the {} timer? Until a time like 13:30, +15m or no{}",
+            title.to_lowercase(),
+            because
+        );
+        let unattended = Some(default.clone()).filter(|_| self.config.breaks.auto);
+        let answer = match deadline::answer(&question, unattended)? {
+            Some(answer) => answer,
+            None => record::answer(
+                &question,
+                prompt::terminal().input(&question, Some(&default), &|answer| {
+                    if skip(answer) {
+                        return Ok(());
+                    }
+                    until::parse(answer, &now, false)
+                        .map(|_| ())
+                        .map_err(|err| err.to_string())
+                })?,
+            ),
+        };
+        let session = if learn {
+            session::Session {
+                answers: session.answers.clone().push(answers::Answer {
+                    at: self.clock.now_utc(),
+                    context,
+                    took: !skip(&answer),
+                }),
+                ..session
+            }
+        } else {
+            session
+        };
+        if skip(&answer) {
+            return Ok((session, None));
+        }
+
+        let end = until::parse(&answer, &now, false)?;
+        let (session, countdown) = breaks::booked(
+            self.timer,
+            self.clock,
+            session,
+            Some(title.as_str()).filter(|_| kind == Kind::Window),
+            end,
+        );
+        Ok((session, Some(countdown)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::FakeClock;
+    use crate::git::{fake::FakeGit, fake::MemoryStore, Lfs, NoopGit};
+    use crate::session::Store;
+    use crate::timekeeping::ClockTime;
+    use crate::timer::fake::{FailingTimer, NoTimer};
+    use chrono::Duration;
+
+    fn opts() -> NextOpts {
+        NextOpts::parse_from(["next"])
+    }
+
+    fn offer(clock: &FakeClock) -> Option<Offer> {
+        let memory = MemoryStore::default();
+        let store = session::SessionStore::new(&memory);
+        let session = session::Session {
+            state: State::Working {
+                driver: "alice".into(),
+            },
+            settings: Some(session::Settings {
+                break_duration: Some(Minutes(10)),
+                break_interval: Some(Minutes(60)),
+                windows: vec![session::Window {
+                    name: "Lunch".into(),
+                    start: ClockTime::hm(12, 0),
+                    end: ClockTime::hm(13, 0),
+                }],
+                ..session::Settings::default()
+            }),
+            times: session::Times {
+                started: Some(clock.now() - Duration::hours(3)),
+                last_activity: Some(clock.now() - Duration::minutes(5)),
+                ..session::Times::default()
+            },
+            ..session::Session::default()
+        };
+        Next::new(&NoopGit, &store, &NoTimer, clock, opts(), Config::default()).offer(&session)
+    }
+
+    #[test]
+    fn offers_what_is_due_by_the_clock() {
+        assert_eq!(
+            offer(&FakeClock::on_june_first(12, 10)),
+            Some(Offer {
+                title: "Lunch".into(),
+                kind: Kind::Window,
+                minutes: 50,
+                message: "It's Lunch time, 50 minutes".into(),
+            })
+        );
+        let morning = offer(&FakeClock::on_june_first(10, 0)).unwrap();
+        assert_eq!(morning.kind, Kind::Break);
+        assert_eq!(morning.message, "Time for a 10 minute break");
+    }
+
+    #[test]
+    fn hands_over_to_a_driver_named_anyone() {
+        let memory = MemoryStore::default();
+        let store = session::SessionStore::new(&memory);
+        let clock = FakeClock::default();
+        let working = |drivers: &[&str]| session::Session {
+            state: State::Working {
+                driver: "alice".into(),
+            },
+            drivers: session::Drivers::new(drivers.iter().map(|name| name.to_string()).collect()),
+            settings: Some(session::Settings::default()),
+            ..session::Session::default()
+        };
+        let config = Config {
+            name: "alice".into(),
+            ..Config::default()
+        };
+        let next = |drivers: &[&str]| {
+            store.save(working(drivers)).unwrap();
+            Next::new(&NoopGit, &store, &NoTimer, &clock, opts(), config.clone())
+                .run()
+                .unwrap();
+            store.load().unwrap().state
+        };
+        assert_eq!(
+            next(&["alice", "anyone"]),
+            State::WaitingForNext {
+                next: Some("anyone".into())
+            }
+        );
+        assert_eq!(next(&["alice"]), State::WaitingForNext { next: None });
+    }
+
+    #[test]
+    fn saves_the_break_with_the_handover() {
+        let clock = FakeClock::on_june_first(10, 0);
+        let memory = MemoryStore::default();
+        let store = session::SessionStore::new(&memory);
+        let working = session::Session {
+            state: State::Working {
+                driver: "alice".into(),
+            },
+            drivers: session::Drivers::new(vec!["alice".into(), "bob".into()]),
+            settings: Some(session::Settings {
+                break_duration: Some(Minutes(10)),
+                break_interval: Some(Minutes(60)),
+                ..session::Settings::default()
+            }),
+            times: session::Times {
+                started: Some(clock.now() - Duration::hours(3)),
+                ..session::Times::default()
+            },
+            ..session::Session::default()
+        };
+        store.save(working).unwrap();
+        let saves = *memory.saves.borrow();
+        let config = Config {
+            name: "alice".into(),
+            ..Config::default()
+        };
+
+        record::replay(vec![record::Answer {
+            question: "Start the break timer? Until a time like 13:30, +15m or no".into(),
+            answer: serde_json::json!("+10m"),
+        }]);
+        let handed_over = Next::new(&NoopGit, &store, &FailingTimer, &clock, opts(), config).run();
+        record::stop_replay();
+        handed_over.unwrap();
+
+        // Only the progress document, once
+        assert_eq!(*memory.saves.borrow(), saves + 1);
+        let session = store.load().unwrap();
+        assert_eq!(
+            session.state,
+            State::WaitingForNext {
+                next: Some("bob".into())
+            }
+        );
+        assert_eq!(session.times.last_break, Some(clock.now()));
+        assert_eq!(
+            session.times.break_until,
+            Some(clock.now() + Duration::minutes(10))
+        );
+        assert_eq!(
+            session.history.records().last(),
+            Some(&session::Record::Break {
+                at: clock.now_utc()
+            })
+        );
+    }
+
+    #[test]
+    fn declined_breaks_are_not_taken() {
+        let clock = FakeClock::on_june_first(10, 0);
+        let memory = MemoryStore::default();
+        let store = session::SessionStore::new(&memory);
+        let working = session::Session {
+            state: State::Working {
+                driver: "alice".into(),
+            },
+            drivers: session::Drivers::new(vec!["alice".into(), "bob".into()]),
+            settings: Some(session::Settings {
+                break_interval: Some(Minutes(60)),
+                ..session::Settings::default()
+            }),
+            times: session::Times {
+                started: Some(clock.now() - Duration::hours(3)),
+                ..session::Times::default()
+            },
+            ..session::Session::default()
+        };
+        store.save(working).unwrap();
+        let config = Config {
+            name: "alice".into(),
+            ..Config::default()
+        };
+
+        record::replay(vec![record::Answer {
+            question: "Start the break timer? Until a time like 13:30, +15m or no".into(),
+            answer: serde_json::json!("no"),
+        }]);
+        let handed_over = Next::new(&NoopGit, &store, &FailingTimer, &clock, opts(), config).run();
+        record::stop_replay();
+        handed_over.unwrap();
+
+        let session = store.load().unwrap();
+        assert!(matches!(session.state, State::WaitingForNext { .. }));
+        assert_eq!(session.times.last_break, None);
+        assert_eq!(session.times.break_until, None);
+        assert!(!session.answers.is_empty());
+    }
+
+    #[test]
+    fn unattended_takes_breaks_per_config() {
+        let clock = FakeClock::on_june_first(10, 0);
+        let handover = |auto: bool| {
+            let memory = MemoryStore::default();
+            let store = session::SessionStore::new(&memory);
+            store
+                .save(session::Session {
+                    state: State::Working {
+                        driver: "alice".into(),
+                    },
+                    drivers: session::Drivers::new(vec!["alice".into(), "bob".into()]),
+                    settings: Some(session::Settings {
+                        break_duration: Some(Minutes(10)),
+                        break_interval: Some(Minutes(60)),
+                        ..session::Settings::default()
+                    }),
+                    times: session::Times {
+                        started: Some(clock.now() - Duration::hours(3)),
+                        ..session::Times::default()
+                    },
+                    ..session::Session::default()
+                })
+                .unwrap();
+            let mut config = Config {
+                name: "alice".into(),
+                ..Config::default()
+            };
+            config.breaks.auto = auto;
+
+            deadline::take_defaults(true);
+            let handed_over = Next::new(&NoopGit, &store, &NoTimer, &clock, opts(), config).run();
+            deadline::take_defaults(false);
+            handed_over.unwrap();
+            store.load().unwrap()
+        };
+
+        let session = handover(false);
+        assert_eq!(session.times.break_until, None);
+        let session = handover(true);
+        assert_eq!(
+            session.times.break_until,
+            Some(clock.now() + Duration::minutes(10))
+        );
+        // Nobody answered, nothing to learn from
+        assert!(session.answers.is_empty());
+    }
+
+    #[test]
+    fn refuses_to_commit_around_lfs() {
+        let mut git = FakeGit {
+            lfs: Lfs::NotInitialized,
+            ..FakeGit::default()
+        };
+        git.snapshot.branch = Some("mob-session".into());
+        git.snapshot.changed = 1;
+        git.snapshot.unstaged = 1;
+        let memory = MemoryStore::default();
+        let store = session::SessionStore::new(&memory);
+        let working = session::Session {
+            state: State::Working {
+                driver: "alice".into(),
+            },
+            drivers: session::Drivers::new(vec!["alice".into(), "bob".into()]),
+            settings: Some(session::Settings::default()),
+            ..session::Session::default()
+        };
+        store.save(working.clone()).unwrap();
+        let config = Config {
+            name: "alice".into(),
+            ..Config::default()
+        };
+
+        let clock = FakeClock::default();
+        let err = Next::new(&git, &store, &NoTimer, &clock, opts(), config)
+            .run()
+            .unwrap_err();
+        assert!(err.to_string().ends_with("run git lfs install"), "{}", err);
+        assert_eq!(git.calls(), vec!["add --all"]);
+        assert_eq!(store.load().unwrap().state, working.state);
+    }
+
+    #[test]
+    fn adds_the_message_to_the_wip_commit() {
+        let cases = vec![
+            (None, "mob sync [skip ci]"),
+            (Some(""), "mob sync [skip ci]"),
+            (Some("  "), "mob sync [skip ci]"),
+            (Some(" parse the config "), "mob sync [skip ci]: parse the config"),
+        ];
+        for (summary, expected) in cases {
+            assert_eq!(wip_message("mob sync [skip ci]", summary), expected);
+        }
+
+        let mut git = FakeGit::default();
+        git.snapshot.branch = Some("mob-session".into());
+        git.snapshot.changed = 1;
+        git.snapshot.unstaged = 1;
+        let memory = MemoryStore::default();
+        let store = session::SessionStore::new(&memory);
+        store
+            .save(session::Session {
+                state: State::Working {
+                    driver: "alice".into(),
+                },
+                drivers: session::Drivers::new(vec!["alice".into(), "bob".into()]),
+                settings: Some(session::Settings::default()),
+                ..session::Session::default()
+            })
+            .unwrap();
+        let config = Config {
+            name: "alice".into(),
+            ..Config::default()
+        };
+        let opts = NextOpts::parse_from(["next", "-m", "parse the config"]);
+        let clock = FakeClock::default();
+        Next::new(&git, &store, &NoTimer, &clock, opts, config)
+            .run()
+            .unwrap();
+        assert!(git.calls().contains(
+            &"commit --message mob sync [skip ci]: parse the config --no-verify".to_string()
+        ));
+    }
+
+    #[test]
+    fn gates_short_turns() {
+        let minutes = |minutes| Some(Duration::minutes(minutes));
+        assert_eq!(gate(Minutes(10), minutes(3), true), Gate::Ask);
+        assert_eq!(gate(Minutes(10), minutes(3), false), Gate::Refuse);
+        assert_eq!(gate(Minutes(10), minutes(10), false), Gate::Proceed);
+        assert_eq!(gate(Minutes(0), minutes(0), false), Gate::Proceed);
+        // Sessions of older versions don't know when the turn started
+        assert_eq!(gate(Minutes(10), None, false), Gate::Proceed);
+    }
+
+    #[test]
+    fn asks_before_short_turns() {
+        let clock = FakeClock::default();
+        let working = |started: Option<i64>| session::Session {
+            id: Some("3f2a9c1e7b04".into()),
+            state: State::Working {
+                driver: "alice".into(),
+            },
+            drivers: session::Drivers::new(vec!["alice".into(), "bob".into()]),
+            settings: Some(session::Settings {
+                min_turn_minutes: Minutes(10),
+                ..session::Settings::default()
+            }),
+            history: session::History::new(
+                started
+                    .map(|minutes| session::Record::Turn {
+                        driver: "alice".into(),
+                        at: clock.now_utc() - Duration::minutes(minutes),
+                    })
+                    .into_iter()
+                    .collect(),
+            ),
+            ..session::Session::default()
+        };
+        let config = || Config {
+            name: "alice".into(),
+            ..Config::default()
+        };
+        let memory = MemoryStore::default();
+        let store = session::SessionStore::new(&memory);
+
+        store.save(working(Some(3))).unwrap();
+        let err = Next::new(&NoopGit, &store, &NoTimer, &clock, opts(), config())
+            .run()
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Your turn has lasted 3 minutes, the mob agreed on at least 10, run mob next --force to hand over anyway"
+        );
+        assert_eq!(store.load().unwrap(), working(Some(3)));
+
+        let force = NextOpts::parse_from(["next", "--force"]);
+        Next::new(&NoopGit, &store, &NoTimer, &clock, force, config())
+            .run()
+            .unwrap();
+        let session = store.load().unwrap();
+        assert!(matches!(session.state, State::WaitingForNext { .. }));
+        assert_eq!(
+            session.history.records().last(),
+            Some(&session::Record::ShortTurn {
+                driver: "alice".into(),
+                at: clock.now_utc(),
+            })
+        );
+
+        store.save(working(Some(3))).unwrap();
+        record::replay(vec![record::Answer {
+            question: "Hand over anyway?".into(),
+            answer: serde_json::json!(true),
+        }]);
+        let handed_over = Next::new(&NoopGit, &store, &NoTimer, &clock, opts(), config()).run();
+        record::stop_replay();
+        handed_over.unwrap();
+        assert!(matches!(
+            store.load().unwrap().state,
+            State::WaitingForNext { .. }
+        ));
+
+        for started in [Some(12), None] {
+            store.save(working(started)).unwrap();
+            Next::new(&NoopGit, &store, &NoTimer, &clock, opts(), config())
+                .run()
+                .unwrap();
+            let session = store.load().unwrap();
+            assert!(matches!(session.state, State::WaitingForNext { .. }));
+            assert_eq!(session.history, working(started).history);
+        }
+    }
 }
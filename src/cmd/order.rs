@@ -1,18 +1,25 @@
-use crate::session;
+use crate::{clock::Clock, config::Config, deadline, prompt, session};
 use anyhow::Result;
-use dialoguer::{Confirm, Select, Sort};
+use session::roles;
 
 pub struct Order<'a> {
     store: &'a dyn session::Store,
+    clock: &'a dyn Clock,
+    config: Config,
 }
 
 impl<'a> Order<'a> {
-    pub fn new(store: &'a impl session::Store) -> Order<'a> {
-        Self { store }
+    pub fn new(store: &'a impl session::Store, clock: &'a impl Clock, config: Config) -> Order<'a> {
+        Self {
+            store,
+            clock,
+            config,
+        }
     }
 
     pub fn run(&self) -> Result<()> {
         let session = self.store.load()?;
+        roles::check(&session, &self.config.name, roles::Action::Settings)?;
 
         let drivers = session.drivers.all();
 
@@ -25,10 +32,9 @@ impl<'a> Order<'a> {
             return Ok(());
         }
 
-        let order = Sort::new()
-            .with_prompt("Use [space] and ↓↑ to move driver")
-            .items(drivers.as_slice())
-            .interact()?;
+        deadline::answer::<()>("Reorder the drivers", None)?;
+        let names: Vec<&str> = drivers.iter().map(String::as_str).collect();
+        let order = prompt::terminal().sort("Use [space] and ↓↑ to move driver", &names)?;
 
         let ordered_drivers =
             session::Drivers::new(order.into_iter().map(|i| drivers[i].clone()).collect());
@@ -52,23 +58,24 @@ impl<'a> Order<'a> {
                     })
                     .expect("Previous driver not found, this should not happen");
 
-                let next_driver = ordered_drivers.next(previous_driver.as_str());
+                let next_driver = ordered_drivers.next(
+                    previous_driver.as_str(),
+                    &session.away,
+                    self.clock.now_utc(),
+                );
                 let next_driver_name = next_driver.as_ref().unwrap();
 
-                let next_driver = if Confirm::new()
-                    .with_prompt(format!("So {} should be next?", next_driver_name))
-                    .interact()?
-                {
-                    next_driver
-                } else {
-                    let ordered = ordered_drivers.all();
-                    let next = Select::new()
-                        .with_prompt("Who should be next?")
-                        .items(ordered.as_slice())
-                        .interact()?;
-
-                    Some(ordered[next].clone())
-                };
+                let prompt = prompt::terminal();
+                let next_driver =
+                    if prompt.confirm(&format!("So {} should be next?", next_driver_name), true)? {
+                        next_driver
+                    } else {
+                        let ordered = ordered_drivers.all();
+                        let names: Vec<&str> = ordered.iter().map(String::as_str).collect();
+                        let next = prompt.select("Who should be next?", &names, 0)?;
+
+                        Some(ordered[next].clone())
+                    };
 
                 log::info!("Next driver: {}", next_driver.as_ref().unwrap());
 
@@ -0,0 +1,198 @@
+use crate::session::outside::{self, Moved};
+use crate::{clock::Clock, config::Config, deadline, git, prompt, record, session};
+use anyhow::Result;
+use session::State;
+
+/// How the mob branch moved on `remote` since the session was saved while nobody drove,
+/// `None` when it didn't. Fetches the branch to tell.
+pub(super) fn find(
+    git: &dyn git::Git,
+    session: &session::Session,
+    remote: &str,
+) -> Result<Option<Moved>> {
+    let recorded = match (&session.state, &session.tip) {
+        (State::WaitingForNext { .. }, Some(recorded)) if git.is_repository() => recorded,
+        _ => return Ok(None),
+    };
+    let branch = session.branches.branch.as_str();
+    git.run(&["fetch", "--no-tags", remote, branch])?;
+    let tip = match git.rev_parse(&format!("{}/{}", remote, branch))? {
+        Some(tip) if outside::moved(session, &tip) => tip,
+        _ => return Ok(None),
+    };
+    let authors = git.log_authors(&format!("{}..{}", recorded, tip))?;
+    Ok(Some(Moved { tip, authors }))
+}
+
+/// Notices commits pushed to the mob branch with plain git and offers to take them into the
+/// session, runs before the commands that change it
+pub struct Outside<'a> {
+    git: &'a dyn git::Git,
+    store: &'a dyn session::Store,
+    clock: &'a dyn Clock,
+    config: Config,
+    interactive: bool,
+}
+
+impl<'a> Outside<'a> {
+    pub fn new(
+        git: &'a impl git::Git,
+        store: &'a impl session::Store,
+        clock: &'a impl Clock,
+        config: Config,
+    ) -> Outside<'a> {
+        Self {
+            git,
+            store,
+            clock,
+            config,
+            interactive: deadline::attended(),
+        }
+    }
+
+    pub fn run(&self) -> Result<()> {
+        let session = self.store.load()?;
+        let moved = match find(self.git, &session, &self.config.remote)? {
+            Some(moved) => moved,
+            None => return Ok(()),
+        };
+        log::warn!("While nobody drove {}", moved);
+
+        let driver = moved.driver(&session);
+        let mut choices = vec![];
+        if let Some(driver) = &driver {
+            choices.push(format!("Mark {} as driving", driver));
+        }
+        choices.push("Record the commits in the history".to_string());
+        choices.push("Leave the session as it is".to_string());
+        let choices: Vec<&str> = choices.iter().map(String::as_str).collect();
+
+        let question = "Take the commits into the session?";
+        let record_only = choices.len() - 2;
+        let choice = match deadline::answer(question, Some(record_only))? {
+            Some(choice) => choice,
+            None if self.interactive => record::answer(
+                question,
+                prompt::terminal().select(question, &choices, 0)?,
+            ),
+            None => record_only,
+        };
+        if choice == choices.len() - 1 {
+            return Ok(());
+        }
+
+        let as_driver = choice == 0 && driver.is_some();
+        let session = outside::adopt(session, &moved, as_driver, self.clock.now_utc());
+        self.store.save(session)?;
+        match driver.filter(|_| as_driver) {
+            Some(driver) => log::info!("{} is driving, they run mob next when done", driver),
+            None => log::info!("Recorded the commits in the history"),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::FakeClock;
+    use crate::cmd::fixtures::alice;
+    use crate::git::fake::{FakeGit, MemoryStore};
+    use crate::session::Store as _;
+
+    fn waiting() -> session::Session {
+        session::Session {
+            id: Some("3f2a9c1e7b04".into()),
+            drivers: session::Drivers::new(vec!["alice".into(), "dave".into()]),
+            settings: Some(session::Settings::default()),
+            state: State::WaitingForNext {
+                next: Some("alice".into()),
+            },
+            tip: Some("a1".into()),
+            ..session::Session::default()
+        }
+    }
+
+    fn pushed_past_mob(authors: &[&str]) -> FakeGit {
+        let mut git = FakeGit::default();
+        git.revs.insert("origin/mob-session".into(), "b2".into());
+        git.authors = authors.iter().map(|author| author.to_string()).collect();
+        git
+    }
+
+    #[test]
+    fn finds_commits_pushed_past_mob() {
+        let git = pushed_past_mob(&["dave", "dave"]);
+        assert_eq!(
+            find(&git, &waiting(), "origin").unwrap(),
+            Some(Moved {
+                tip: "b2".into(),
+                authors: vec!["dave".into(), "dave".into()],
+            })
+        );
+        assert_eq!(git.calls(), vec!["fetch --no-tags origin mob-session"]);
+
+        let unmoved = session::Session {
+            tip: Some("b2".into()),
+            ..waiting()
+        };
+        assert_eq!(find(&git, &unmoved, "origin").unwrap(), None);
+
+        // Nothing to compare, and nothing to fetch for
+        let git = pushed_past_mob(&["dave"]);
+        let untracked = session::Session {
+            tip: None,
+            ..waiting()
+        };
+        assert_eq!(find(&git, &untracked, "origin").unwrap(), None);
+        assert!(git.calls().is_empty());
+    }
+
+    #[test]
+    fn records_what_others_pushed() {
+        let git = pushed_past_mob(&["erin"]);
+        let memory = MemoryStore::default();
+        let store = session::SessionStore::new(&memory);
+        store.save(waiting()).unwrap();
+        let clock = FakeClock::default();
+        Outside::new(&git, &store, &clock, alice()).run().unwrap();
+
+        let session = store.load().unwrap();
+        assert_eq!(session.state, waiting().state);
+        assert_eq!(session.tip.as_deref(), Some("b2"));
+        assert_eq!(
+            session.history.records(),
+            &[session::Record::Outside {
+                authors: vec!["erin".into()],
+                commits: 1,
+                at: clock.now_utc(),
+            }]
+        );
+    }
+
+    #[test]
+    fn marks_the_driver_as_asked() {
+        let git = pushed_past_mob(&["dave"]);
+        let memory = MemoryStore::default();
+        let store = session::SessionStore::new(&memory);
+        store.save(waiting()).unwrap();
+        let clock = FakeClock::default();
+
+        record::replay(vec![record::Answer {
+            question: "Take the commits into the session?".into(),
+            answer: serde_json::json!(0),
+        }]);
+        let adopted = Outside::new(&git, &store, &clock, alice()).run();
+        record::stop_replay();
+        adopted.unwrap();
+
+        let session = store.load().unwrap();
+        assert_eq!(
+            session.state,
+            State::Working {
+                driver: "dave".into()
+            }
+        );
+        assert_eq!(session.history.last_driven("dave"), Some(clock.now_utc()));
+    }
+}
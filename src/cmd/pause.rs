@@ -0,0 +1,323 @@
+use super::status::turn_left;
+use crate::{clock::Clock, config::Config, duration, session, timer};
+use anyhow::Result;
+use chrono::Duration;
+use clap::{self, Clap};
+use session::{roles, State};
+
+#[derive(Clap, Debug)]
+pub struct PauseOpts {
+    /// What interrupts the mob, shown by mob status
+    #[clap(name = "REASON", default_value = "an interruption")]
+    reason: String,
+}
+
+/// Interrupts the turn, like for a meeting, keeping what is left of it for `mob resume`
+pub struct Pause<'a> {
+    store: &'a dyn session::Store,
+    timer: &'a dyn timer::Timer,
+    clock: &'a dyn Clock,
+    opts: PauseOpts,
+    config: Config,
+}
+
+impl<'a> Pause<'a> {
+    pub fn new(
+        store: &'a impl session::Store,
+        timer: &'a impl timer::Timer,
+        clock: &'a impl Clock,
+        opts: PauseOpts,
+        config: Config,
+    ) -> Pause<'a> {
+        Self {
+            store,
+            timer,
+            clock,
+            opts,
+            config,
+        }
+    }
+
+    pub fn run(&self) -> Result<()> {
+        let session = self.store.load()?;
+        roles::check(&session, &self.config.name, roles::Action::Break)?;
+        let now = self.clock.now_utc();
+        let driver = match &session.state {
+            State::Working { driver } => driver.clone(),
+            State::Stopped => {
+                log::warn!("No current mob session, there is nothing to pause");
+                return Ok(());
+            }
+            State::Paused { reason, .. } => {
+                log::warn!("The session is paused already, for {}", reason);
+                return Ok(());
+            }
+            State::WaitingForNext { .. } => {
+                match session.times.break_until.filter(|until| until.utc() > now) {
+                    Some(_) => log::warn!("The mob is on a break, there is no turn to pause"),
+                    None => log::warn!("Nobody is driving, there is no turn to pause"),
+                }
+                return Ok(());
+            }
+        };
+        let remaining = turn_left(&session.times, now).map(|left| left.num_seconds().max(0));
+
+        self.store.save(session::Session {
+            state: State::Paused {
+                reason: self.opts.reason.clone(),
+                driver: Some(driver.clone()),
+                remaining,
+            },
+            times: session::Times {
+                last_activity: Some(now.into()),
+                ..session.times
+            },
+            ..session
+        })?;
+        if let Err(err) = self.timer.cancel() {
+            log::warn!("Could not cancel the timer: {:#}", err);
+        }
+        match remaining {
+            Some(left) => log::info!(
+                "Paused the turn of {} with {} left, run mob resume to go on",
+                driver,
+                duration::format(Duration::seconds(left)).short()
+            ),
+            None => log::info!("Paused the turn of {}, run mob resume to go on", driver),
+        }
+        Ok(())
+    }
+}
+
+/// Gives the interrupted turn back to its driver, with the time that was left of it
+pub struct Resume<'a> {
+    store: &'a dyn session::Store,
+    timer: &'a dyn timer::Timer,
+    clock: &'a dyn Clock,
+    config: Config,
+}
+
+impl<'a> Resume<'a> {
+    pub fn new(
+        store: &'a impl session::Store,
+        timer: &'a impl timer::Timer,
+        clock: &'a impl Clock,
+        config: Config,
+    ) -> Resume<'a> {
+        Self {
+            store,
+            timer,
+            clock,
+            config,
+        }
+    }
+
+    pub fn run(&self) -> Result<()> {
+        let session = self.store.load()?;
+        roles::check(&session, &self.config.name, roles::Action::Start)?;
+        let (driver, remaining) = match &session.state {
+            State::Paused {
+                driver: Some(driver),
+                remaining,
+                ..
+            } => (driver.clone(), remaining.map(Duration::seconds)),
+            State::Paused { reason, .. } => {
+                log::info!(
+                    "The session is paused for {} by mob emergency, run mob start to resume",
+                    reason
+                );
+                return Ok(());
+            }
+            _ => {
+                log::info!("Nothing is paused, mob pause interrupts a turn");
+                return Ok(());
+            }
+        };
+
+        let now = self.clock.now();
+        // Started as long ago as the turn had lasted, so what is left shows as before
+        let lasted = match (session.times.turn_minutes, remaining) {
+            (Some(turn), Some(remaining)) => turn.duration() - remaining,
+            _ => Duration::zero(),
+        };
+        let next = session
+            .drivers
+            .all()
+            .contains(&driver)
+            .then(|| session.drivers.next(&driver, &session.away, now.utc()))
+            .flatten();
+        self.store.save(session::Session {
+            state: State::Working {
+                driver: driver.clone(),
+            },
+            times: session::Times {
+                turn_started: Some(now - lasted),
+                last_activity: Some(now),
+                ..session.times
+            },
+            ..session
+        })?;
+
+        let remaining = match remaining {
+            Some(remaining) => remaining,
+            None => {
+                log::info!("{} drives again", driver);
+                return Ok(());
+            }
+        };
+        if driver != self.config.name {
+            log::info!(
+                "{} drives again with {} left, their timer starts with mob resume on their machine",
+                driver,
+                duration::format(remaining).short()
+            );
+            return Ok(());
+        }
+        let message = format!("mob next {}", next.unwrap_or_default());
+        self.timer.start(
+            timer::Countdown::Turn,
+            "Your turn",
+            remaining,
+            message.trim_end(),
+        )?;
+        log::info!(
+            "Resumed your turn with {} left",
+            duration::format(remaining).short()
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::FakeClock;
+    use crate::cmd::fixtures::config;
+    use crate::git::fake::MemoryStore;
+    use crate::session::Store as _;
+    use crate::timekeeping::Minutes;
+    use std::cell::RefCell;
+
+    #[derive(Default)]
+    struct RecordingTimer {
+        started: RefCell<Vec<(String, Duration)>>,
+        cancelled: RefCell<usize>,
+    }
+
+    impl timer::Timer for RecordingTimer {
+        fn start(&self, _: timer::Countdown, _: &str, duration: Duration, msg: &str) -> Result<()> {
+            self.started.borrow_mut().push((msg.to_string(), duration));
+            Ok(())
+        }
+
+        fn cancel(&self) -> Result<()> {
+            *self.cancelled.borrow_mut() += 1;
+            Ok(())
+        }
+    }
+
+    fn working(clock: &FakeClock) -> session::Session {
+        session::Session {
+            id: Some("3f2a9c1e7b04".into()),
+            state: State::Working {
+                driver: "alice".into(),
+            },
+            drivers: session::Drivers::new(vec!["alice".into(), "bob".into()]),
+            times: session::Times {
+                turn_started: Some(clock.now() - Duration::minutes(4)),
+                turn_minutes: Some(Minutes(10)),
+                ..session::Times::default()
+            },
+            ..session::Session::default()
+        }
+    }
+
+    #[test]
+    fn keeps_what_is_left_of_the_turn() {
+        let memory = MemoryStore::default();
+        let store = session::SessionStore::new(&memory);
+        let clock = FakeClock::default();
+        let timer = RecordingTimer::default();
+        store.save(working(&clock)).unwrap();
+
+        let opts = PauseOpts::parse_from(["pause", "standup"]);
+        Pause::new(&store, &timer, &clock, opts, config("bob"))
+            .run()
+            .unwrap();
+        assert_eq!(
+            store.load().unwrap().state,
+            State::Paused {
+                reason: "standup".into(),
+                driver: Some("alice".into()),
+                remaining: Some(6 * 60),
+            }
+        );
+        assert_eq!(*timer.cancelled.borrow(), 1);
+
+        // The meeting takes half an hour, the turn still has its six minutes
+        clock.advance(Duration::minutes(30));
+        Resume::new(&store, &timer, &clock, config("alice"))
+            .run()
+            .unwrap();
+        let session = store.load().unwrap();
+        assert_eq!(
+            session.state,
+            State::Working {
+                driver: "alice".into()
+            }
+        );
+        assert_eq!(
+            turn_left(&session.times, clock.now_utc()),
+            Some(Duration::minutes(6))
+        );
+        assert_eq!(
+            timer.started.take(),
+            vec![("mob next bob".to_string(), Duration::minutes(6))]
+        );
+    }
+
+    #[test]
+    fn pauses_and_resumes_only_a_turn() {
+        let memory = MemoryStore::default();
+        let store = session::SessionStore::new(&memory);
+        let clock = FakeClock::default();
+        let timer = RecordingTimer::default();
+        let pause = || {
+            let opts = PauseOpts::parse_from(["pause"]);
+            Pause::new(&store, &timer, &clock, opts, config("alice"))
+                .run()
+                .unwrap()
+        };
+        let on_break = session::Session {
+            state: State::WaitingForNext { next: None },
+            times: session::Times {
+                break_until: Some(clock.now() + Duration::minutes(5)),
+                ..session::Times::default()
+            },
+            ..working(&clock)
+        };
+        for session in [session::Session::default(), on_break] {
+            store.save(session.clone()).unwrap();
+            pause();
+            assert_eq!(store.load().unwrap(), session);
+        }
+
+        let emergency = session::Session {
+            state: State::Paused {
+                reason: "prod is down".into(),
+                driver: None,
+                remaining: None,
+            },
+            ..working(&clock)
+        };
+        for session in [working(&clock), emergency] {
+            store.save(session.clone()).unwrap();
+            Resume::new(&store, &timer, &clock, config("alice"))
+                .run()
+                .unwrap();
+            assert_eq!(store.load().unwrap(), session);
+        }
+        assert!(timer.started.borrow().is_empty());
+        assert_eq!(*timer.cancelled.borrow(), 0);
+    }
+}
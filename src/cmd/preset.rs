@@ -0,0 +1,181 @@
+use crate::{config::Config, deadline, preset, prompt, record, session, session::diff};
+use anyhow::{anyhow, Result};
+use clap::{self, Clap};
+use session::{roles, State};
+
+#[derive(Clap, Debug)]
+pub struct PresetOpts {
+    /// Name of the preset, like focus or sprint
+    #[clap(name = "NAME")]
+    name: String,
+}
+
+pub struct Preset<'a> {
+    store: &'a dyn session::Store,
+    opts: PresetOpts,
+    config: Config,
+}
+
+impl<'a> Preset<'a> {
+    pub fn new(store: &'a impl session::Store, opts: PresetOpts, config: Config) -> Preset<'a> {
+        Self {
+            store,
+            opts,
+            config,
+        }
+    }
+
+    pub fn run(&self) -> Result<()> {
+        let name = self.opts.name.as_str();
+        let preset = preset::find(&self.config.presets, name)?;
+
+        let session = self.store.load()?;
+        roles::check(&session, &self.config.name, roles::Action::Settings)?;
+        let current = match &session.settings {
+            Some(settings) => settings.clone(),
+            None => {
+                return Err(anyhow!(
+                    "There is no session yet, run mob start --preset {}",
+                    name
+                ))
+            }
+        };
+
+        let settings = preset.apply(&current);
+        let changes = diff::describe_settings(&current, &settings);
+        if changes.is_empty() {
+            log::info!("The session already uses the durations of {}", name);
+            return Ok(());
+        }
+        for change in &changes {
+            log::info!("{}", change);
+        }
+        settings.warn_suspicious();
+
+        let prompt = format!("Apply preset {} to the session?", name);
+        let apply = match deadline::answer(&prompt, Some(true))? {
+            Some(apply) => apply,
+            None => record::answer(&prompt, prompt::terminal().confirm(prompt.as_str(), true)?),
+        };
+        if apply {
+            self.apply(session, settings)?;
+        }
+        Ok(())
+    }
+
+    fn apply(&self, session: session::Session, settings: session::Settings) -> Result<()> {
+        // The running timer has its duration already, changing it under the driver would surprise
+        if let State::Working { driver } = &session.state {
+            log::info!(
+                "The turn of {} keeps its timer, new durations apply from the next turn",
+                driver
+            );
+        }
+        self.store.save(session::Session {
+            settings: Some(settings),
+            ..session
+        })?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::FakeClock;
+    use crate::cmd::fixtures::config;
+    use crate::cmd::{Next, NextOpts, Start, StartOpts};
+    use crate::git::{fake::MemoryStore, NoopGit};
+    use crate::session::Store;
+    use crate::timekeeping::Minutes;
+    use crate::timer::{Countdown, Timer};
+    use std::cell::RefCell;
+
+    #[derive(Default)]
+    struct RecordingTimer {
+        minutes: RefCell<Vec<i64>>,
+    }
+
+    impl Timer for RecordingTimer {
+        fn start(&self, _: Countdown, _: &str, duration: chrono::Duration, _: &str) -> Result<()> {
+            self.minutes.borrow_mut().push(duration.num_minutes());
+            Ok(())
+        }
+    }
+
+    fn start(store: &session::SessionStore, timer: &RecordingTimer, args: &[&str], name: &str) {
+        let opts = StartOpts::parse_from(args);
+        Start::new(
+            &NoopGit,
+            store,
+            timer,
+            &FakeClock::default(),
+            opts,
+            config(name),
+        )
+        .run()
+        .unwrap();
+    }
+
+    #[test]
+    fn applies_from_the_next_turn() {
+        let memory = MemoryStore::default();
+        let store = session::SessionStore::new(&memory);
+        let timer = RecordingTimer::default();
+
+        start(&store, &timer, &["start", "--preset", "sprint"], "alice");
+        let session = store.load().unwrap();
+        let settings = session.settings.clone().unwrap();
+        assert_eq!(settings.work_duration, Minutes(10));
+        assert_eq!(settings.break_duration, Some(Minutes(10)));
+
+        // Switching while alice's timer runs
+        let focus = preset::find(&preset::builtin(), "focus")
+            .unwrap()
+            .apply(&settings);
+        Preset::new(
+            &store,
+            PresetOpts::parse_from(["preset", "focus"]),
+            config("bob"),
+        )
+        .apply(session, focus)
+        .unwrap();
+        let session = store.load().unwrap();
+        assert!(matches!(session.state, State::Working { ref driver } if driver == "alice"));
+
+        Next::new(
+            &NoopGit,
+            &store,
+            &timer,
+            &FakeClock::default(),
+            NextOpts::parse_from(["next"]),
+            config("alice"),
+        )
+        .run()
+        .unwrap();
+        start(&store, &timer, &["start"], "bob");
+
+        assert_eq!(*timer.minutes.borrow(), vec![10, 25]);
+        assert_eq!(
+            store.load().unwrap().settings.unwrap().break_duration,
+            Some(Minutes(5))
+        );
+    }
+
+    #[test]
+    fn unknown_preset_lists_available() {
+        let memory = MemoryStore::default();
+        let store = session::SessionStore::new(&memory);
+        let err = Preset::new(
+            &store,
+            PresetOpts::parse_from(["preset", "slow"]),
+            config("bob"),
+        )
+        .run()
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "unknown preset `slow`, available presets: focus, sprint"
+        );
+    }
+}
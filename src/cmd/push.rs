@@ -0,0 +1,458 @@
+use super::{lfs, markers, untracked, whitespace};
+use crate::config::Config;
+use crate::git::{
+    rewrite::{self, CommitInfo, Pushed},
+    signing, Git,
+};
+use crate::{deadline, prompt, record, session::NewFiles};
+use anyhow::{anyhow, Result};
+
+/// Files from this size are pushed on their own when the push of a turn fails on the network
+pub const LARGE_FILE_BYTES: u64 = 5 * 1024 * 1024;
+
+/// What git prints when the connection gave up or the push was too large for it, rather than
+/// the remote refusing the commits
+const TRANSIENT: &[&str] = &[
+    "timed out",
+    "RPC failed",
+    "the remote end hung up unexpectedly",
+    "early EOF",
+    "Connection reset",
+    "unexpected disconnect",
+    "HTTP 408",
+    "HTTP 413",
+    "HTTP 502",
+    "HTTP 504",
+];
+
+/// Pushes `branch` to `remote` and reads back what the remote kept. Only the push itself
+/// fails, once it went through not being able to read back is a warning.
+pub(super) fn push(git: &dyn Git, remote: &str, branch: &str, upstream: bool) -> Result<()> {
+    let mut args = vec!["push", "--no-verify"];
+    if upstream {
+        args.push("--set-upstream");
+    }
+    args.extend(&[remote, "--", branch]);
+    git.run(&args)?;
+
+    if let Err(err) = read_back(git, remote, branch) {
+        log::warn!(
+            "Pushed {} but could not read back what {} kept: {:#}",
+            branch,
+            remote,
+            err
+        );
+    }
+    Ok(())
+}
+
+/// When the server rewrote the pushed commit the local branch follows it, so the next driver
+/// doesn't find it diverged
+fn read_back(git: &dyn Git, remote: &str, branch: &str) -> Result<()> {
+    let (pushed, tip) = match (git.rev_parse(branch)?, git.remote_tip(remote, branch)?) {
+        (Some(pushed), Some(tip)) => (pushed, tip),
+        _ => {
+            log::trace!("Could not read back the push of {}", branch);
+            return Ok(());
+        }
+    };
+    if pushed == tip {
+        return Ok(());
+    }
+
+    git.run(&["fetch", "--no-tags", remote, branch])?;
+    let pushed = CommitInfo::read(git, &pushed)?;
+    let tip = CommitInfo::read(git, &tip)?;
+    match rewrite::classify(&pushed, &tip) {
+        Pushed::Same => {}
+        Pushed::Rewritten => {
+            log::info!(
+                "{} rewrote the pushed commit, moving {} to its {}",
+                remote,
+                branch,
+                short(&tip.id)
+            );
+            if git.snapshot()?.is_on(branch) {
+                git.run(&["reset", "--keep", tip.id.as_str()])?;
+            } else {
+                git.run(&["branch", "--force", branch, tip.id.as_str()])?;
+            }
+        }
+        Pushed::Diverged => log::warn!(
+            "{}/{} is at {} and not at the pushed {}, someone else pushed to it",
+            remote,
+            branch,
+            short(&tip.id),
+            short(&pushed.id)
+        ),
+    }
+    Ok(())
+}
+
+/// Commits what changed with the WIP `message` and pushes it with `push_turn`, along with
+/// commits a push that failed before left behind. New files go in as `next.untracked` and
+/// what the mob said about them in `new_files` say, changes that are only whitespace are
+/// handled as `next.whitespace` says. False when there was nothing to push.
+pub(super) fn commit_turn(
+    git: &dyn Git,
+    config: &Config,
+    branch: &str,
+    message: &str,
+    new_files: &mut NewFiles,
+    interactive: bool,
+) -> Result<bool> {
+    if !git.is_repository() {
+        log::trace!("No repository, nothing to commit");
+        return Ok(false);
+    }
+    let (remote, commit) = (&config.remote, &config.commit);
+    let snapshot = git.snapshot()?;
+    if snapshot.is_clean() && snapshot.ahead == 0 {
+        log::info!("Nothing was changed, so nothing to commit");
+        return Ok(false);
+    }
+    if !snapshot.is_clean() {
+        let left_out = untracked::stage(
+            git,
+            config.next.untracked,
+            &snapshot.untracked_files,
+            new_files,
+            interactive,
+        )?;
+        if !left_out.is_empty()
+            && snapshot.ahead == 0
+            && git.staged_numstat(false)?.trim().is_empty()
+        {
+            log::info!("Only new files that are left out changed, so nothing to commit");
+            return Ok(false);
+        }
+        if let Some(problem) = lfs::problem(git)? {
+            return Err(anyhow!(problem));
+        }
+        markers::check(git, &commit.allow_markers, interactive)?;
+        match whitespace::check(git, config.next.whitespace)? {
+            true => signing::commit(git, commit.sign, &["--message", message, "--no-verify"])?,
+            false if snapshot.ahead == 0 => {
+                log::info!("Only whitespace was changed and it was stripped, so nothing to commit");
+                return Ok(false);
+            }
+            false => {}
+        }
+    }
+    push_turn(git, remote, branch, message, commit.sign, interactive)?;
+    Ok(true)
+}
+
+/// Pushes the commit of a turn, `HEAD` on `branch`. When that fails on a weak connection
+/// and the commit has large files, offers to push the small files in a commit of their own
+/// first and the large ones in a second. Both commits get `message` with the part they are.
+pub(super) fn push_turn(
+    git: &dyn Git,
+    remote: &str,
+    branch: &str,
+    message: &str,
+    sign: bool,
+    interactive: bool,
+) -> Result<()> {
+    let err = match push(git, remote, branch, false) {
+        Ok(()) => return Ok(()),
+        Err(err) if transient(&err) => err,
+        Err(err) => return Err(err),
+    };
+    let (large, small): (Vec<_>, Vec<_>) = git
+        .file_sizes("HEAD")?
+        .into_iter()
+        .partition(|(_, size)| *size >= LARGE_FILE_BYTES);
+    // A root commit has nothing to split onto
+    if large.is_empty() || small.is_empty() || git.rev_parse("HEAD~1")?.is_none() {
+        return Err(err);
+    }
+
+    log::warn!("The push failed on the connection: {:#}", err);
+    let prompt = format!(
+        "Push the {} small files first and the {} large ones after?",
+        small.len(),
+        large.len()
+    );
+    let staged = match deadline::answer(&prompt, Some(true))? {
+        Some(staged) => staged,
+        None if !interactive => true,
+        None => record::answer(&prompt, prompt::terminal().confirm(prompt.as_str(), true)?),
+    };
+    if !staged {
+        return Err(err);
+    }
+
+    // Everything stays staged, only the large files wait for the second commit
+    git.run(&["reset", "--soft", "HEAD~1"])?;
+    let mut unstage = vec!["reset", "--quiet", "HEAD", "--"];
+    unstage.extend(large.iter().map(|(path, _)| path.as_str()));
+    git.run(&unstage)?;
+    commit(git, message, sign, "1 of 2")?;
+    push(git, remote, branch, false)?;
+
+    let mut stage = vec!["add", "--"];
+    stage.extend(large.iter().map(|(path, _)| path.as_str()));
+    git.run(&stage)?;
+    commit(git, message, sign, "2 of 2")?;
+    push(git, remote, branch, false).map_err(|err| {
+        anyhow!(
+            "The small files are pushed, the large ones are committed but not pushed yet. \
+             Run mob next again to retry: {:#}",
+            err
+        )
+    })
+}
+
+/// Whether `err` is the connection failing, anything the remote refused is not
+pub(super) fn transient(err: &anyhow::Error) -> bool {
+    let text = format!("{:#}", err);
+    !deadline::timed_out(err) && TRANSIENT.iter().any(|pattern| text.contains(pattern))
+}
+
+fn commit(git: &dyn Git, message: &str, sign: bool, part: &str) -> Result<()> {
+    let part = format!("Mob-Part: {}", part);
+    signing::commit(
+        git,
+        sign,
+        &["--message", message, "--message", part.as_str(), "--no-verify"],
+    )
+}
+
+fn short(id: &str) -> &str {
+    &id[..id.len().min(7)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Untracked, Whitespace};
+    use crate::git::{fake::FakeGit, Snapshot};
+
+    fn git(remote: (&str, &str, &[&str])) -> FakeGit {
+        let (id, tree, parents) = remote;
+        let mut git = FakeGit {
+            snapshot: Snapshot {
+                branch: Some("mob-session".into()),
+                ..Snapshot::default()
+            },
+            ..FakeGit::default()
+        };
+        git.revs.insert("mob-session".into(), "b2b2b2b2b2".into());
+        git.remote_tips.insert("mob-session".into(), id.into());
+        git.commits
+            .insert("b2b2b2b2b2".into(), ("t2".into(), vec!["a1".into()]));
+        git.commits.insert(
+            id.into(),
+            (tree.into(), parents.iter().map(|p| p.to_string()).collect()),
+        );
+        git
+    }
+
+    #[test]
+    fn identical() {
+        let git = git(("b2b2b2b2b2", "t2", &["a1"]));
+        push(&git, "origin", "mob-session", false).unwrap();
+        assert_eq!(git.calls(), vec!["push --no-verify origin -- mob-session"]);
+    }
+
+    #[test]
+    fn follows_a_rewrite() {
+        let git = git(("c3c3c3c3c3", "t2", &["a1"]));
+        push(&git, "origin", "mob-session", true).unwrap();
+        assert_eq!(
+            git.calls(),
+            vec![
+                "push --no-verify --set-upstream origin -- mob-session",
+                "fetch --no-tags origin mob-session",
+                "reset --keep c3c3c3c3c3",
+            ]
+        );
+    }
+
+    #[test]
+    fn moves_a_branch_that_is_not_checked_out() {
+        let mut git = git(("c3c3c3c3c3", "t3", &["b2b2b2b2b2"]));
+        git.snapshot.branch = Some("master".into());
+        push(&git, "origin", "mob-session", false).unwrap();
+        assert_eq!(
+            git.calls().last().unwrap(),
+            "branch --force mob-session c3c3c3c3c3"
+        );
+    }
+
+    #[test]
+    fn leaves_divergence_alone() {
+        let git = git(("c3c3c3c3c3", "t3", &["a1"]));
+        push(&git, "origin", "mob-session", false).unwrap();
+        assert_eq!(
+            git.calls(),
+            vec![
+                "push --no-verify origin -- mob-session",
+                "fetch --no-tags origin mob-session",
+            ]
+        );
+    }
+
+    const HUNG_UP: &str = "error: RPC failed; HTTP 504\nfatal: the remote end hung up unexpectedly";
+
+    fn large_turn() -> FakeGit {
+        let mut git = FakeGit {
+            files: vec![
+                ("src/lib.rs".into(), 420),
+                ("assets/intro.mp4".into(), 80 * 1024 * 1024),
+                ("notes.md".into(), 0),
+            ],
+            ..FakeGit::default()
+        };
+        git.revs.insert("HEAD~1".into(), "a1".into());
+        git
+    }
+
+    #[test]
+    fn pushes_large_files_last() {
+        let git = large_turn();
+        git.fail("push", 0, HUNG_UP);
+        push_turn(&git, "origin", "mob-session", "mob next [ci-skip]", false, false).unwrap();
+        assert_eq!(
+            git.calls(),
+            vec![
+                "push --no-verify origin -- mob-session",
+                "reset --soft HEAD~1",
+                "reset --quiet HEAD -- assets/intro.mp4",
+                "commit --message mob next [ci-skip] --message Mob-Part: 1 of 2 --no-verify",
+                "push --no-verify origin -- mob-session",
+                "add -- assets/intro.mp4",
+                "commit --message mob next [ci-skip] --message Mob-Part: 2 of 2 --no-verify",
+                "push --no-verify origin -- mob-session",
+            ]
+        );
+    }
+
+    #[test]
+    fn signs_the_commits_of_a_turn() {
+        let mut git = large_turn();
+        git.snapshot.changed = 1;
+        git.snapshot.unstaged = 1;
+        git.fail("push", 0, HUNG_UP);
+        let mut config = Config::default();
+        config.commit.sign = true;
+        commit_turn(
+            &git,
+            &config,
+            "mob-session",
+            "wip",
+            &mut NewFiles::default(),
+            false,
+        )
+        .unwrap();
+        let commits: Vec<String> = git
+            .calls()
+            .into_iter()
+            .filter(|call| call.starts_with("commit"))
+            .collect();
+        assert_eq!(commits.len(), 3);
+        assert!(commits.iter().all(|call| call.starts_with("commit -S ")));
+    }
+
+    #[test]
+    fn commits_nothing_when_only_stripped_whitespace_changed() {
+        let git = FakeGit {
+            whitespace_check: "notes.md:3: trailing whitespace.\n+- retro \n".into(),
+            ..FakeGit::default()
+        };
+        git.worktree
+            .borrow_mut()
+            .insert("notes.md".into(), b"# Notes\n\n- retro \n".to_vec());
+        let mut git = git;
+        git.snapshot.changed = 1;
+        git.snapshot.unstaged = 1;
+        let mut config = Config::default();
+        config.next.whitespace = Whitespace::Fix;
+        let pushed = commit_turn(
+            &git,
+            &config,
+            "mob-session",
+            "wip",
+            &mut NewFiles::default(),
+            false,
+        );
+        assert!(!pushed.unwrap());
+        assert_eq!(git.calls(), vec!["add --all", "add -- notes.md"]);
+    }
+
+    #[test]
+    fn commits_nothing_when_only_left_out_files_are_new() {
+        let mut git = FakeGit::default();
+        git.snapshot.untracked = 1;
+        git.snapshot.untracked_files = vec!["notes.txt".into()];
+        let mut config = Config::default();
+        config.next.untracked = Untracked::Exclude;
+        let pushed = commit_turn(
+            &git,
+            &config,
+            "mob-session",
+            "wip",
+            &mut NewFiles::default(),
+            false,
+        );
+        assert!(!pushed.unwrap());
+        assert_eq!(git.calls(), vec!["add --update"]);
+    }
+
+    #[test]
+    fn keeps_the_large_files_committed_when_their_push_fails() {
+        let git = large_turn();
+        git.fail("push", 0, HUNG_UP);
+        // The push of the small files goes through, the one of the large files doesn't
+        git.fail("push", 1, "fatal: unable to access: Operation timed out");
+        let err = push_turn(&git, "origin", "mob-session", "m", false, false).unwrap_err();
+        assert!(err.to_string().contains("Run mob next again"), "{}", err);
+        let calls = git.calls();
+        assert_eq!(calls.len(), 8);
+        assert!(calls[6].ends_with("Mob-Part: 2 of 2 --no-verify"));
+    }
+
+    #[test]
+    fn only_splits_what_the_connection_failed_on() {
+        let git = large_turn();
+        git.fail(
+            "push",
+            0,
+            "! [rejected] mob-session -> mob-session (fetch first)",
+        );
+        assert!(push_turn(&git, "origin", "mob-session", "m", false, false).is_err());
+        assert_eq!(git.calls().len(), 1);
+
+        let git = FakeGit {
+            files: vec![("src/lib.rs".into(), 420)],
+            ..FakeGit::default()
+        };
+        git.fail("push", 0, HUNG_UP);
+        assert!(push_turn(&git, "origin", "mob-session", "m", false, false).is_err());
+        assert_eq!(git.calls().len(), 1);
+
+        // The first commit of the repository has nothing to split onto
+        let mut git = large_turn();
+        git.revs.clear();
+        git.fail("push", 0, HUNG_UP);
+        assert!(push_turn(&git, "origin", "mob-session", "m", false, false).is_err());
+        assert_eq!(git.calls().len(), 1);
+    }
+
+    #[test]
+    fn never_splits_a_push_that_went_through() {
+        let mut git = git(("c3c3c3c3c3", "t2", &["a1"]));
+        git.files = large_turn().files;
+        git.revs.insert("HEAD~1".into(), "a1".into());
+        git.fail("fetch", 0, HUNG_UP);
+        push_turn(&git, "origin", "mob-session", "m", false, false).unwrap();
+        assert_eq!(
+            git.calls(),
+            vec![
+                "push --no-verify origin -- mob-session",
+                "fetch --no-tags origin mob-session",
+            ]
+        );
+    }
+}
@@ -0,0 +1,236 @@
+//! Finding out whether the configured remote takes pushes, for clones of a read-only mirror.
+//! The remote chosen instead is kept in the state dir of the clone, `~/.mob` is left alone.
+
+use super::push;
+use crate::git::{remote_url::RemoteUrl, Git};
+use crate::state_dir::StateDir;
+use crate::{deadline, prompt, record};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+/// The state file with the remote of this clone
+const CHOSEN: &str = "remote.json";
+/// Pushed to with --dry-run, nothing is created
+const CHECK_REF: &str = "HEAD:refs/heads/mob-push-check";
+
+#[derive(Debug, PartialEq)]
+pub enum Access {
+    Writable,
+    /// The check itself failed, like without a network
+    Unknown,
+    ReadOnly,
+    /// Pushes are refused, but `writable` of the same project takes them
+    Mirror {
+        writable: String,
+    },
+}
+
+#[derive(Serialize, Deserialize)]
+struct Chosen {
+    remote: String,
+}
+
+/// The remote chosen for this clone instead of the one in `~/.mob`
+pub fn chosen_remote(state_dir: &StateDir) -> Option<String> {
+    state_dir.read::<Chosen>(CHOSEN).map(|chosen| chosen.remote)
+}
+
+fn can_push(git: &dyn Git, remote: &str) -> Result<Option<bool>> {
+    match git.run(&[
+        "push",
+        "--dry-run",
+        "--no-verify",
+        "--quiet",
+        remote,
+        CHECK_REF,
+    ]) {
+        Ok(()) => Ok(Some(true)),
+        Err(err) if push::transient(&err) || deadline::timed_out(&err) => {
+            log::trace!("Could not check pushing to {}: {:#}", remote, err);
+            Ok(None)
+        }
+        Err(_) => Ok(Some(false)),
+    }
+}
+
+pub fn access(git: &dyn Git, remote: &str) -> Result<Access> {
+    // Without a commit there is nothing to try the push with
+    if git.rev_parse("HEAD")?.is_none() {
+        return Ok(Access::Unknown);
+    }
+    match can_push(git, remote)? {
+        Some(true) => return Ok(Access::Writable),
+        Some(false) => {}
+        None => return Ok(Access::Unknown),
+    }
+    let project = match git
+        .remote_url(remote)?
+        .as_deref()
+        .and_then(RemoteUrl::parse)
+    {
+        Some(project) => project,
+        None => return Ok(Access::ReadOnly),
+    };
+    for other in git.remotes()? {
+        let url = git.remote_url(&other)?;
+        let same = url
+            .as_deref()
+            .and_then(RemoteUrl::parse)
+            .filter(|url| url.same_project(&project))
+            .is_some();
+        if other != remote && same && can_push(git, &other)? == Some(true) {
+            return Ok(Access::Mirror { writable: other });
+        }
+    }
+    Ok(Access::ReadOnly)
+}
+
+/// Checks `remote` and offers to switch to a writable remote of the same project, which is
+/// then returned
+pub fn check_remote(
+    git: &dyn Git,
+    remote: &str,
+    state_dir: &StateDir,
+    interactive: bool,
+) -> Result<Option<String>> {
+    let writable = match access(git, remote)? {
+        Access::Writable | Access::Unknown => return Ok(None),
+        Access::ReadOnly => {
+            log::warn!(
+                "{} doesn't accept your pushes, mob next will fail to hand over",
+                remote
+            );
+            return Ok(None);
+        }
+        Access::Mirror { writable } => writable,
+    };
+    log::warn!(
+        "{} doesn't accept your pushes, {} of the same project does",
+        remote,
+        writable
+    );
+    let prompt = format!("Use {} for mob in this clone?", writable);
+    // Nobody confirms it under a deadline
+    let switch = match deadline::answer(&prompt, Some(false))? {
+        Some(switch) => switch,
+        None if !interactive => false,
+        None => record::answer(&prompt, prompt::terminal().confirm(prompt.as_str(), true)?),
+    };
+    if !switch {
+        log::info!("Set remote in ~/.mob to change the remote");
+        return Ok(None);
+    }
+    choose_remote(state_dir, &writable)?;
+    Ok(Some(writable))
+}
+
+/// Has mob use `remote` in this clone from the next command on
+pub fn choose_remote(state_dir: &StateDir, remote: &str) -> Result<()> {
+    state_dir.write(
+        CHOSEN,
+        &Chosen {
+            remote: remote.to_string(),
+        },
+    )?;
+    log::info!("mob uses {} in this clone from now on", remote);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::fake::FakeGit;
+
+    const DENIED: &str = "remote: Permission to team/repo.git denied to alice.\n\
+                          fatal: unable to access 'https://github.com/team/repo.git/': \
+                          The requested URL returned error: 403";
+
+    fn fake(remotes: &[(&str, &str)]) -> FakeGit {
+        let mut git = FakeGit {
+            remotes: remotes.iter().map(|(name, _)| name.to_string()).collect(),
+            ..FakeGit::default()
+        };
+        for (name, url) in remotes {
+            git.urls.insert(name.to_string(), url.to_string());
+        }
+        git.revs.insert("HEAD".into(), "a1".into());
+        git
+    }
+
+    #[test]
+    fn finds_the_writable_remote_of_a_mirror() {
+        let git = fake(&[
+            ("origin", "https://github.com/team/repo"),
+            ("fork", "git@github.com:alice/repo.git"),
+            ("upstream", "git@github.com:team/repo.git"),
+        ]);
+        git.fail("push --dry-run --no-verify --quiet origin", 0, DENIED);
+        assert_eq!(
+            access(&git, "origin").unwrap(),
+            Access::Mirror {
+                writable: "upstream".into()
+            }
+        );
+        assert_eq!(
+            git.calls(),
+            vec![
+                "push --dry-run --no-verify --quiet origin HEAD:refs/heads/mob-push-check",
+                "push --dry-run --no-verify --quiet upstream HEAD:refs/heads/mob-push-check",
+            ]
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        let state_dir = StateDir::new(dir.path().to_path_buf());
+        assert_eq!(chosen_remote(&state_dir), None);
+        choose_remote(&state_dir, "upstream").unwrap();
+        assert_eq!(chosen_remote(&state_dir).as_deref(), Some("upstream"));
+    }
+
+    #[test]
+    fn only_switches_when_confirmed() {
+        let git = fake(&[
+            ("origin", "https://github.com/team/repo"),
+            ("upstream", "git@github.com:team/repo.git"),
+        ]);
+        let dir = tempfile::tempdir().unwrap();
+        let state_dir = StateDir::new(dir.path().to_path_buf());
+        git.fail("push --dry-run --no-verify --quiet origin", 0, DENIED);
+        assert_eq!(
+            check_remote(&git, "origin", &state_dir, false).unwrap(),
+            None
+        );
+        assert_eq!(chosen_remote(&state_dir), None);
+    }
+
+    #[test]
+    fn read_only_without_alternative() {
+        let git = fake(&[
+            ("origin", "https://github.com/team/repo"),
+            ("other", "https://github.com/team/other"),
+        ]);
+        git.fail("push --dry-run --no-verify --quiet origin", 0, DENIED);
+        assert_eq!(access(&git, "origin").unwrap(), Access::ReadOnly);
+        assert_eq!(git.calls().len(), 1);
+
+        let git = fake(&[
+            ("origin", "https://github.com/team/repo"),
+            ("upstream", "git@github.com:team/repo.git"),
+        ]);
+        git.fail("push --dry-run", 0, DENIED);
+        git.fail("push --dry-run", 0, DENIED);
+        assert_eq!(access(&git, "origin").unwrap(), Access::ReadOnly);
+    }
+
+    #[test]
+    fn writable_or_unknown() {
+        let git = fake(&[("origin", "git@github.com:team/repo.git")]);
+        assert_eq!(access(&git, "origin").unwrap(), Access::Writable);
+
+        git.fail("push", 0, "fatal: unable to access: Operation timed out");
+        assert_eq!(access(&git, "origin").unwrap(), Access::Unknown);
+
+        let mut empty = git;
+        empty.revs.clear();
+        assert_eq!(access(&empty, "origin").unwrap(), Access::Unknown);
+    }
+}
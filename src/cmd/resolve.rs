@@ -0,0 +1,85 @@
+//! Branches named on the command line that don't exist, which are likelier mistyped than
+//! meant to be created.
+
+use crate::{deadline, git, prompt, record, suggest};
+use anyhow::{anyhow, Result};
+
+/// Which of the `existing` names close to `wanted` was meant, `None` when none is close or
+/// none was meant. `missing` tells that `wanted` doesn't exist. Unattended the close names
+/// are an error.
+pub(super) fn did_you_mean(
+    missing: &str,
+    wanted: &str,
+    existing: &[String],
+    interactive: bool,
+) -> Result<Option<String>> {
+    let close = suggest::close(wanted, existing.iter().map(String::as_str));
+    if close.is_empty() {
+        return Ok(None);
+    }
+    let prompt = format!("{}, did you mean one of these?", missing);
+    let selection = match deadline::answer::<usize>(&prompt, None) {
+        Ok(Some(selection)) => selection,
+        Ok(None) if interactive => {
+            let mut items = close.clone();
+            items.push("None of these");
+            record::answer(&prompt, prompt::terminal().select(&prompt, &items, 0)?)
+        }
+        _ => return Err(anyhow!("{}, did you mean {}?", missing, close.join(" or "))),
+    };
+    Ok(close.get(selection).map(|name| name.to_string()))
+}
+
+/// The branches `remote` has, without its name in front
+pub(super) fn remote_branches(git: &dyn git::Git, remote: &str) -> Result<Vec<String>> {
+    let prefix = format!("{}/", remote);
+    Ok(git
+        .branches()?
+        .iter()
+        .filter_map(|branch| branch.strip_prefix(&prefix))
+        .filter(|branch| *branch != "HEAD")
+        .map(String::from)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::fake::FakeGit;
+
+    #[test]
+    fn suggests_unattended_as_an_error() {
+        let existing = vec!["release/1.3".to_string(), "release/1.4".to_string()];
+        let missing = "There is no branch relese/1.4 on origin";
+        let err = did_you_mean(missing, "relese/1.4", &existing, false).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "There is no branch relese/1.4 on origin, did you mean release/1.4 or release/1.3?"
+        );
+        assert_eq!(
+            did_you_mean(missing, "hotfix", &existing, false).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn branches_of_a_remote() {
+        let git = FakeGit {
+            branches: [
+                "master",
+                "origin/HEAD",
+                "origin/master",
+                "upstream/release/1.4",
+            ]
+            .iter()
+            .map(|branch| branch.to_string())
+            .collect(),
+            ..FakeGit::default()
+        };
+        assert_eq!(remote_branches(&git, "origin").unwrap(), vec!["master"]);
+        assert_eq!(
+            remote_branches(&git, "upstream").unwrap(),
+            vec!["release/1.4"]
+        );
+    }
+}
@@ -0,0 +1,167 @@
+use crate::{clock::Clock, config::Config, session};
+use anyhow::{anyhow, Result};
+use clap::{self, Clap};
+use session::{roles, State};
+
+#[derive(Clap, Debug)]
+pub struct SkipOpts {
+    /// Pass the turn to this driver instead of the one after next
+    #[clap(long)]
+    to: Option<String>,
+}
+
+/// Passes the turn on from the awaited driver, who stays in the rotation
+pub struct Skip<'a> {
+    store: &'a dyn session::Store,
+    clock: &'a dyn Clock,
+    opts: SkipOpts,
+    config: Config,
+}
+
+impl<'a> Skip<'a> {
+    pub fn new(
+        store: &'a impl session::Store,
+        clock: &'a impl Clock,
+        opts: SkipOpts,
+        config: Config,
+    ) -> Skip<'a> {
+        Self {
+            store,
+            clock,
+            opts,
+            config,
+        }
+    }
+
+    pub fn run(&self) -> Result<()> {
+        let session = self.store.load()?;
+        roles::check(&session, &self.config.name, roles::Action::Next)?;
+        let skipped = match &session.state {
+            State::WaitingForNext { next } => next.clone(),
+            State::Working { driver } => {
+                return Err(anyhow!(
+                    "{} is driving, the driver hands over with mob next",
+                    driver
+                ))
+            }
+            State::Paused { .. } => {
+                return Err(anyhow!("The session is paused, run mob start to resume"))
+            }
+            State::Stopped => return Err(anyhow!("No current mob session, run mob start")),
+        };
+        let next = match (&self.opts.to, &skipped) {
+            (Some(to), _) if !session.drivers.contains(to) => {
+                return Err(anyhow!(
+                    "{} is not in the rotation of {}",
+                    to,
+                    session.drivers.all().join(", ")
+                ))
+            }
+            (Some(to), _) => to.clone(),
+            // Like someone taken out of the rotation meanwhile
+            (None, Some(skipped)) if !session.drivers.contains(skipped) => {
+                return Err(anyhow!(
+                    "{} is not in the rotation, name who is next with mob skip --to",
+                    skipped
+                ))
+            }
+            (None, Some(skipped)) => {
+                match session
+                    .drivers
+                    .next(skipped, &session.away, self.clock.now_utc())
+                {
+                    Some(next) => next,
+                    None => return Err(anyhow!("{} is the only driver", skipped)),
+                }
+            }
+            (None, None) => {
+                log::info!("Anyone may start, there is nobody to skip");
+                return Ok(());
+            }
+        };
+        if skipped.as_ref() == Some(&next) {
+            log::info!("{} is next already", next);
+            return Ok(());
+        }
+
+        self.store.save(session::Session {
+            state: State::WaitingForNext {
+                next: Some(next.clone()),
+            },
+            times: session::Times {
+                claimed: None,
+                ..session.times
+            },
+            ..session
+        })?;
+        match skipped {
+            Some(skipped) => log::info!("Skipped {}, next driver: {}", skipped, next),
+            None => log::info!("Next driver: {}", next),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::FakeClock;
+    use crate::git::fake::MemoryStore;
+    use crate::session::Store as _;
+
+    fn skip(state: State, args: &[&str]) -> Result<State> {
+        let memory = MemoryStore::default();
+        let store = session::SessionStore::new(&memory);
+        store.save(session::Session {
+            state,
+            drivers: session::Drivers::new(vec!["alice".into(), "bob".into(), "carol".into()]),
+            ..session::Session::default()
+        })?;
+        let config = Config {
+            name: "alice".into(),
+            ..Config::default()
+        };
+        let clock = FakeClock::default();
+        let opts = SkipOpts::parse_from(["skip"].iter().chain(args));
+        Skip::new(&store, &clock, opts, config).run()?;
+        Ok(store.load()?.state)
+    }
+
+    fn waiting(next: Option<&str>) -> State {
+        State::WaitingForNext {
+            next: next.map(String::from),
+        }
+    }
+
+    #[test]
+    fn passes_the_turn_on() {
+        let cases = vec![
+            (waiting(Some("bob")), vec![], waiting(Some("carol"))),
+            (waiting(Some("carol")), vec![], waiting(Some("alice"))),
+            (waiting(Some("bob")), vec!["--to", "alice"], waiting(Some("alice"))),
+            (waiting(None), vec!["--to", "carol"], waiting(Some("carol"))),
+            (waiting(None), vec![], waiting(None)),
+        ];
+        for (state, args, expected) in cases {
+            assert_eq!(skip(state.clone(), &args).unwrap(), expected, "{:?}", state);
+        }
+    }
+
+    #[test]
+    fn refuses_without_a_turn_to_skip() {
+        let working = State::Working {
+            driver: "bob".into(),
+        };
+        assert_eq!(
+            skip(working, &[]).unwrap_err().to_string(),
+            "bob is driving, the driver hands over with mob next"
+        );
+        assert!(skip(State::Stopped, &[]).is_err());
+        assert_eq!(
+            skip(waiting(Some("bob")), &["--to", "dave"])
+                .unwrap_err()
+                .to_string(),
+            "dave is not in the rotation of alice, bob, carol"
+        );
+    }
+}
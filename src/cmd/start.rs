@@ -1,21 +1,79 @@
-use crate::{config::Config, git, session, timer};
+use super::{limits, push, resolve, stash};
+use crate::schedule::{self, Kind, Suggestion};
+use crate::timekeeping::Minutes;
+use crate::timezone::Zone;
+use crate::{
+    clock::Clock, config::Config, deadline, git, handover, preset, prompt, record, session,
+    state_dir::StateDir, timer,
+};
 use anyhow::{anyhow, Result};
 use clap::{self, Clap};
+use session::roles;
 use session::State;
 
 #[derive(Clap, Debug)]
 pub struct StartOpts {
     /// How long you want this work session to last
     #[clap(name = "MINUTES")]
-    minutes: Option<i64>,
+    minutes: Option<Minutes>,
+
+    /// Base branch, `remote/branch` picks another remote than the mob's as in fork workflows
+    #[clap(long)]
+    base: Option<String>,
+
+    /// Feature branch of the session
+    #[clap(long)]
+    branch: Option<String>,
+
+    /// Create --branch even when its name is close to a branch that exists
+    #[clap(long)]
+    create: bool,
+
+    /// Use the durations of a preset for a new session
+    #[clap(long)]
+    preset: Option<String>,
+
+    /// Timezone of the fixed breaks of a new session like Europe/Berlin, for mobs across
+    /// timezones
+    #[clap(long)]
+    timezone: Option<String>,
+
+    /// Only keep rotation and timer, without git. The session is stored in .mob-local.json
+    #[clap(long)]
+    pub local: bool,
+
+    /// Join even when the mob would have more than limits.max_drivers, or start a session of
+    /// fewer than limits.min_drivers
+    #[clap(long)]
+    force: bool,
+
+    /// Stash uncommitted changes without asking, mob done offers them back
+    #[clap(long, conflicts_with = "include-uncommitted")]
+    stash: bool,
+
+    /// Take uncommitted changes into the turn without asking
+    #[clap(long)]
+    include_uncommitted: bool,
+}
+
+/// What becomes of uncommitted changes when the turn starts
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Uncommitted {
+    Stash,
+    Include,
+    Abort,
 }
 
 pub struct Start<'a> {
     git: &'a dyn git::Git,
     store: &'a dyn session::Store,
     timer: &'a dyn timer::Timer,
+    clock: &'a dyn Clock,
     opts: StartOpts,
     config: Config,
+    interactive: bool,
+    /// Where a stash of uncommitted changes is remembered for mob done
+    state_dir: Option<StateDir>,
 }
 
 impl<'a> Start<'a> {
@@ -23,6 +81,7 @@ impl<'a> Start<'a> {
         git: &'a impl git::Git,
         store: &'a impl session::Store,
         timer: &'a impl timer::Timer,
+        clock: &'a impl Clock,
         opts: StartOpts,
         config: Config,
     ) -> Start<'a> {
@@ -30,33 +89,74 @@ impl<'a> Start<'a> {
             git,
             store,
             timer,
+            clock,
             opts,
             config,
+            interactive: deadline::attended(),
+            state_dir: None,
         }
     }
 
+    pub fn state_dir(mut self, state_dir: StateDir) -> Self {
+        self.state_dir = Some(state_dir);
+        self
+    }
+
     pub fn run(&self) -> Result<()> {
         let me = &self.config.name;
+        self.check_repository()?;
+
+        let snapshot = self.git.snapshot()?;
 
-        if !self.git.tree_is_clean()? {
-            return Err(anyhow!("Working tree is not clean"));
+        if let Some(name) = &self.opts.preset {
+            preset::find(&self.config.presets, name)?;
+        }
+        // A key that can't sign would only show at the first handover
+        if self.config.commit.sign && self.git.is_repository() {
+            git::signing::check(self.git)?;
         }
 
         let session = self.store.load()?;
+        roles::check(&session, me, roles::Action::Start)?;
+        self.check_base(&session)?;
+        // The driver's own changes are the turn's work
+        let mine = matches!(&session.state, State::Working { driver } if driver == me);
+        if !snapshot.is_clean() && !mine {
+            match self.uncommitted()? {
+                Uncommitted::Stash => stash::save(self.git, self.state_dir.as_ref())?,
+                Uncommitted::Include => log::info!("Taking your changes into the turn"),
+                Uncommitted::Abort => return Ok(()),
+            }
+        }
+        if let Some(settings) = &session.settings {
+            let now = schedule::now(self.clock, settings.zone().as_ref());
+            warn(schedule::suggest(&now, &session, settings));
+        }
+        match &self.opts.preset {
+            Some(name) if session.state != State::Stopped => log::warn!(
+                "--preset only applies to new sessions, run mob preset {} to switch this one",
+                name
+            ),
+            _ => (),
+        }
 
         match &session.state {
-            State::Stopped => self.start_new(session)?,
+            State::Stopped => self.start_new(session, &snapshot)?,
             State::Working { driver } if driver == me.as_str() => {
                 log::warn!("It's already your turn");
             }
             State::Working { driver } => {
                 log::warn!("{} has not run mob next", driver);
                 let selections = &["Retry", "Take turn with the risk of losing work"];
-                let selection = dialoguer::Select::new()
-                    .with_prompt("What do you want to do?")
-                    .default(0)
-                    .items(&selections[..])
-                    .interact()?;
+                // Retrying would only spin until the deadline
+                let prompt = "Retry or take the turn";
+                let selection = match deadline::answer::<usize>(prompt, None)? {
+                    Some(selection) => selection,
+                    None => record::answer(
+                        prompt,
+                        prompt::terminal().select("What do you want to do?", &selections[..], 0)?,
+                    ),
+                };
 
                 match selection {
                     0 => return self.run(),
@@ -67,8 +167,16 @@ impl<'a> Start<'a> {
                 self.start(session)?
             }
             State::WaitingForNext { next: None } => self.start(session)?,
+            State::Paused { reason, .. } => {
+                log::info!("Resuming the session paused for {}", reason);
+                self.start(session)?
+            }
+            // A turn reserved with handover.reassign_after waits for its driver, newcomers
+            // too. Without it someone new to the mob starts right away, others take over.
             State::WaitingForNext { next: Some(driver) } => {
-                if !session.drivers.contains(self.config.name.as_str()) {
+                if self.config.handover.reassign_after.is_some() {
+                    self.start_reassigned(driver, session.clone())?
+                } else if !session.drivers.contains(self.config.name.as_str()) {
                     self.start(session)?
                 } else {
                     self.take_over(driver, session.clone())?
@@ -79,14 +187,108 @@ impl<'a> Start<'a> {
         Ok(())
     }
 
+    /// Asks what becomes of uncommitted changes, unless --stash or --include-uncommitted
+    /// said so
+    fn uncommitted(&self) -> Result<Uncommitted> {
+        if self.opts.stash {
+            return Ok(Uncommitted::Stash);
+        }
+        if self.opts.include_uncommitted {
+            return Ok(Uncommitted::Include);
+        }
+        let question = "You have uncommitted changes, what do you want to do?";
+        let choices = &[
+            "Stash them, mob done offers them back",
+            "Take them into the turn",
+            "Abort",
+        ];
+        let choice = match deadline::answer::<usize>(question, None)? {
+            Some(choice) => choice,
+            None if self.interactive => {
+                record::answer(question, prompt::terminal().select(question, choices, 0)?)
+            }
+            None => {
+                return Err(anyhow!(
+                    "Working tree is not clean, run mob start --stash or --include-uncommitted"
+                ))
+            }
+        };
+        Ok(match choice {
+            0 => Uncommitted::Stash,
+            1 => Uncommitted::Include,
+            _ => Uncommitted::Abort,
+        })
+    }
+
+    /// Refuses the options about branches without a repository, they would be ignored
+    fn check_repository(&self) -> Result<()> {
+        let branches = [
+            ("--base", self.opts.base.is_some()),
+            ("--branch", self.opts.branch.is_some()),
+            ("--create", self.opts.create),
+        ];
+        match branches.iter().find(|(_, set)| *set) {
+            Some((option, _)) if !self.git.is_repository() => Err(anyhow!(
+                "{} needs a git repository, this session has none",
+                option
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Refuses a --base other than the one of a running session, the mob would be moved
+    /// onto another branch under everyone's feet
+    fn check_base(&self, session: &session::Session) -> Result<()> {
+        let base = match &self.opts.base {
+            Some(base) if session.state != State::Stopped => base,
+            _ => return Ok(()),
+        };
+        let remotes = match self.git.is_repository() {
+            true => self.git.remotes()?,
+            false => vec![],
+        };
+        let (base_remote, base_branch) =
+            session::Branches::parse_base(base, &remotes, &self.config.remote);
+        let branches = &session.branches;
+        if base_branch == branches.base_branch && base_remote == branches.base_remote {
+            return Ok(());
+        }
+        let current = match &branches.base_remote {
+            Some(remote) => format!("{}/{}", remote, branches.base_branch),
+            None => branches.base_branch.clone(),
+        };
+        Err(anyhow!(
+            "The session works on top of {}, not {}. Run mob done first to start one on {}",
+            current,
+            base,
+            base
+        ))
+    }
+
+    /// Starts in place of `from` once their reservation is over, refuses until then
+    fn start_reassigned(&self, from: &str, session: session::Session) -> Result<()> {
+        let now = self.clock.now_utc();
+        if let Some(reservation) = handover::reservation(now, &session, &self.config.handover) {
+            return Err(anyhow!(
+                "The turn is {}, after that anyone may start",
+                reservation
+            ));
+        }
+        log::info!("{} didn't start in time, the turn is open to anyone", from);
+        self.start(session)
+    }
+
     fn take_over(&self, from: &str, session: session::Session) -> Result<()> {
         let take_and_remove = format!("Take turn and remove {} from the mob", from);
         let selections = &["Take turn", take_and_remove.as_str(), "Abort"];
-        let selection = dialoguer::Select::new()
-            .with_prompt(format!("It's {}s turn. What do you want to do?", from).as_str())
-            .default(0)
-            .items(&selections[..])
-            .interact()?;
+        let prompt = format!("It's {}s turn. What do you want to do?", from);
+        let selection = match deadline::answer(&prompt, Some(0))? {
+            Some(selection) => selection,
+            None => record::answer(
+                &prompt,
+                prompt::terminal().select(prompt.as_str(), &selections[..], 0)?,
+            ),
+        };
 
         match selection {
             0 => self.start(session),
@@ -101,31 +303,96 @@ impl<'a> Start<'a> {
         }
     }
 
+    /// Pushes back when the same person keeps driving, false when they'd rather not
+    fn confirm_turn(&self, session: &session::Session) -> Result<bool> {
+        let (turns, others) = match nudge(
+            &session.history,
+            &session.drivers.all(),
+            &self.config.name,
+            self.config.rotation.consecutive_turns,
+        ) {
+            Some(nudge) => nudge,
+            None => return Ok(true),
+        };
+        log::warn!(
+            "This would be your turn {} in a row, {} haven't driven for a while",
+            turns,
+            others.join(", ")
+        );
+        let question = "Drive anyway?";
+        let drive = match deadline::answer(question, Some(true))? {
+            Some(drive) => drive,
+            None if !self.interactive => true,
+            None => record::answer(question, prompt::terminal().confirm(question, true)?),
+        };
+        if !drive {
+            log::info!("Ask {} to run mob start", others[0]);
+        }
+        Ok(drive)
+    }
+
+    fn turn(&self) -> session::Record {
+        session::Record::Turn {
+            driver: self.config.name.clone(),
+            at: self.clock.now_utc(),
+        }
+    }
+
+    /// The drivers with me among them, `None` when I didn't join a mob over its limit
+    fn admit(&self, session: &session::Session) -> Result<Option<session::Drivers>> {
+        limits::admit(
+            &session.drivers,
+            session.get_driver(),
+            &self.config.name,
+            self.config.limits.max_drivers,
+            self.opts.force,
+            self.interactive,
+        )
+    }
+
     fn start(&self, session: session::Session) -> Result<()> {
+        if !self.confirm_turn(&session)? {
+            return Ok(());
+        }
+        let drivers = match self.admit(&session)? {
+            Some(drivers) => drivers,
+            None => return Ok(()),
+        };
         self.git
             .run(&["checkout", session.branches.base_branch.as_str()])?;
         self.git.run(&["fetch", "--all", "--prune"])?;
 
         if self.git.has_branch(session.branches.branch.as_str())? {
             self.git
-                .run(&["branch", "-D", session.branches.branch.as_str()])?;
+                .run(&["branch", "-D", "--", session.branches.branch.as_str()])?;
         }
 
         self.git
             .run(&["checkout", session.branches.branch.as_str()])?;
 
-        let previous_driver = session.get_driver();
         let session = session::Session {
             state: State::Working {
                 driver: self.config.name.clone(),
             },
-            drivers: session
-                .drivers
-                .insert(previous_driver, self.config.name.as_str()),
+            drivers,
+            times: session::Times {
+                last_activity: Some(self.clock.now()),
+                waiting_since: None,
+                claimed: None,
+                break_until: None,
+                ..self.turn_times(session.times, session.settings.as_ref())
+            },
+            history: session.history.push(self.turn()),
+            // Whoever starts is back
+            away: session.away.remove(&self.config.name),
             ..session
         };
 
-        let next_driver = session.drivers.next(self.config.name.as_str());
+        let next_driver = session.drivers.next(
+            self.config.name.as_str(),
+            &session.away,
+            self.clock.now_utc(),
+        );
         let work_duration = session.settings.as_ref().unwrap().work_duration;
 
         self.store.save(session)?;
@@ -133,61 +400,206 @@ impl<'a> Start<'a> {
         self.start_timer(work_duration, next_driver)
     }
 
-    fn start_new(&self, session: session::Session) -> Result<()> {
-        let previous_driver = session.get_driver();
-
-        let settings = match session.settings {
-            Some(settings) => settings,
-            None => session::Settings::ask()?,
+    fn start_new(&self, session: session::Session, snapshot: &git::Snapshot) -> Result<()> {
+        if !self.confirm_turn(&session)? {
+            return Ok(());
+        }
+        let drivers = match self.admit(&session)? {
+            Some(drivers) => drivers,
+            None => return Ok(()),
         };
+        let limits = &self.config.limits;
+        if !limits::enough(
+            &drivers,
+            limits.min_drivers,
+            limits.enforce,
+            self.opts.force,
+            self.interactive,
+        )? {
+            return Ok(());
+        }
 
-        let branches = session::Branches {
-            base_branch: self
-                .git
-                .current_branch()
-                .unwrap_or(None)
-                .unwrap_or(session.branches.base_branch),
-            ..session.branches
+        let settings = match (&self.opts.preset, session.settings) {
+            (Some(name), settings) => {
+                preset::find(&self.config.presets, name)?.apply(&settings.unwrap_or_default())
+            }
+            (None, Some(settings)) => settings,
+            (None, None) => session::Settings::ask()?,
         };
-
-        let branches = session::Branches::ask(branches)?;
-
-        let remote_branches = branches.with_remote(&self.config.remote);
-
-        self.git.run(&["fetch", "--all", "--prune"])?;
-
-        if !self.git.has_branch(remote_branches.branch.as_str())? {
-            return Err(anyhow!(
-                "You need to push your branch `{}` first",
-                branches.base_branch
-            ));
+        let settings = session::Settings {
+            timezone: self.opts.timezone.clone().or(settings.timezone),
+            ..settings
+        };
+        // Not halfway through the session when the first break is due
+        if let Some(zone) = &settings.timezone {
+            Zone::find(zone)?;
         }
+        session::Settings {
+            work_duration: self.opts.minutes.unwrap_or(settings.work_duration),
+            ..settings.clone()
+        }
+        .warn_suspicious();
 
-        self.git.run(&["checkout", branches.base_branch.as_str()])?;
-        self.git
-            .run(&["merge", remote_branches.base_branch.as_str(), "--ff-only"])?;
-
-        self.setup_branch(&branches, &remote_branches)?;
+        let branches = if self.git.is_repository() {
+            self.prepare_branches(session.branches, snapshot)?
+        } else {
+            session.branches
+        };
 
+        let times = self.turn_times(session::Times::default(), Some(&settings));
+        let id = session::id::generate(&branches.branch, &self.config.name, self.clock.now_utc());
         let session = session::Session {
+            id: Some(id),
             state: State::Working {
                 driver: self.config.name.clone(),
             },
-            drivers: session
-                .drivers
-                .insert(previous_driver, self.config.name.as_str()),
+            drivers,
             settings: Some(settings),
             branches,
+            times: session::Times {
+                started: Some(self.clock.now()),
+                last_activity: Some(self.clock.now()),
+                ..times
+            },
+            history: session.history.push(self.turn()),
+            away: session.away.remove(&self.config.name),
+            ..session
         };
 
         self.store.save(session.clone())?;
 
         self.start_timer(
             session.settings.unwrap().work_duration,
-            session.drivers.next(self.config.name.as_str()),
+            session.drivers.next(
+                self.config.name.as_str(),
+                &session.away,
+                self.clock.now_utc(),
+            ),
         )
     }
 
+    fn prepare_branches(
+        &self,
+        branches: session::Branches,
+        snapshot: &git::Snapshot,
+    ) -> Result<session::Branches> {
+        let branches = match &self.opts.base {
+            Some(base) => {
+                let (base_remote, base_branch) =
+                    session::Branches::parse_base(base, &self.git.remotes()?, &self.config.remote);
+                session::Branches {
+                    base_branch,
+                    base_remote,
+                    ..branches
+                }
+            }
+            None => session::Branches {
+                base_branch: snapshot.branch.clone().unwrap_or(branches.base_branch),
+                ..branches
+            },
+        };
+
+        // Several mobs in one repository keep apart with a prefix each
+        let prefix = self.config.git.branch_prefix.as_deref();
+        let branch = self.opts.branch.as_deref().unwrap_or(&branches.branch);
+        let branches = session::Branches {
+            branch: session::Branches::prefixed(branch, prefix),
+            ..branches
+        };
+        let offered = branches.branch.clone();
+
+        let branches = session::Branches::ask(branches)?;
+        let branches = session::Branches {
+            branch: session::Branches::prefixed(&branches.branch, prefix),
+            ..branches
+        };
+        self.git.check_branch_name(&branches.branch)?;
+
+        self.git.run(&["fetch", "--all", "--prune"])?;
+
+        let branches = self.resolve_base(branches)?;
+        // The usual branch of a new session is created without a second thought
+        let branches = if self.opts.branch.is_some() || branches.branch != offered {
+            self.resolve_branch(branches)?
+        } else {
+            branches
+        };
+        let remote_branches = branches.with_remote(&self.config.remote);
+
+        self.git.run(&["checkout", branches.base_branch.as_str()])?;
+        self.git.run(&[
+            "merge",
+            "--ff-only",
+            "--",
+            remote_branches.base_branch.as_str(),
+        ])?;
+
+        self.setup_branch(&branches, &remote_branches)?;
+
+        Ok(branches)
+    }
+
+    /// The base branch, or which of the close ones was meant when the remote doesn't have it
+    fn resolve_base(&self, branches: session::Branches) -> Result<session::Branches> {
+        let remote = branches.base_remote(&self.config.remote).to_string();
+        let remote_base = format!("{}/{}", remote, branches.base_branch);
+        if self.git.has_branch(&remote_base)? {
+            return Ok(branches);
+        }
+        let missing = format!("There is no branch {} on {}", branches.base_branch, remote);
+        let existing = resolve::remote_branches(self.git, &remote)?;
+        match resolve::did_you_mean(&missing, &branches.base_branch, &existing, self.interactive)? {
+            Some(base_branch) => Ok(session::Branches {
+                base_branch,
+                ..branches
+            }),
+            None => Err(anyhow!(
+                "Could not find `{}`, you need to push your branch first",
+                remote_base
+            )),
+        }
+    }
+
+    /// The feature branch, or which of the close ones was meant when it would be created
+    fn resolve_branch(&self, branches: session::Branches) -> Result<session::Branches> {
+        let remote_branch = format!("{}/{}", self.config.remote, branches.branch);
+        if self.opts.create
+            || self.git.has_branch(&branches.branch)?
+            || self.git.has_branch(&remote_branch)?
+        {
+            return Ok(branches);
+        }
+        let remotes = self.git.remotes()?;
+        let mut existing: Vec<String> = self
+            .git
+            .branches()?
+            .into_iter()
+            .filter(|branch| {
+                !remotes
+                    .iter()
+                    .any(|remote| branch.starts_with(&format!("{}/", remote)))
+            })
+            .collect();
+        for branch in resolve::remote_branches(self.git, &self.config.remote)? {
+            if !existing.contains(&branch) {
+                existing.push(branch);
+            }
+        }
+        let missing = format!(
+            "There is no branch {} here or on {}",
+            branches.branch, self.config.remote
+        );
+        match resolve::did_you_mean(&missing, &branches.branch, &existing, self.interactive) {
+            Ok(Some(branch)) => Ok(session::Branches { branch, ..branches }),
+            Ok(None) => Ok(branches),
+            Err(err) => Err(anyhow!(
+                "{} Run mob start --create to create {} anyway",
+                err,
+                branches.branch
+            )),
+        }
+    }
+
     fn setup_branch(
         &self,
         branches: &session::Branches,
@@ -197,14 +609,7 @@ impl<'a> Start<'a> {
             self.git
                 .run(&["checkout", "-b", branches.branch.as_str()])?;
 
-            self.git.run(&[
-                "push",
-                "--no-verify",
-                "--set-upstream",
-                self.config.remote.as_str(),
-                branches.branch.as_str(),
-            ])?;
-            Ok(())
+            push::push(self.git, &self.config.remote, &branches.branch, true)
         };
 
         let has_local_branch = self.git.has_branch(branches.branch.as_str())?;
@@ -218,11 +623,13 @@ impl<'a> Start<'a> {
                     "Remove local branch and checkout remote",
                     "Delete local and remote branch and start fresh",
                 ];
-                let selection = dialoguer::Select::new()
-                    .with_prompt(prompt)
-                    .default(0)
-                    .items(&selections[..])
-                    .interact()?;
+                let selection = match deadline::answer(&prompt, Some(0))? {
+                    Some(selection) => selection,
+                    None => record::answer(
+                        &prompt,
+                        prompt::terminal().select(prompt.as_str(), &selections[..], 0)?,
+                    ),
+                };
 
                 match selection {
                     0 => {
@@ -230,17 +637,20 @@ impl<'a> Start<'a> {
                     }
 
                     1 => {
-                        self.git.run(&["branch", "-D", branches.branch.as_str()])?;
+                        self.git
+                            .run(&["branch", "-D", "--", branches.branch.as_str()])?;
                         self.git.run(&["checkout", branches.branch.as_str()])?;
                     }
                     _ => {
-                        self.git.run(&["branch", "-D", branches.branch.as_str()])?;
+                        self.git
+                            .run(&["branch", "-D", "--", branches.branch.as_str()])?;
                         self.git.run(&[
                             "push",
                             &self.config.remote,
+                            "--no-verify",
                             "--delete",
+                            "--",
                             branches.branch.as_str(),
-                            "--no-verify",
                         ])?;
 
                         create_and_push()?;
@@ -253,25 +663,22 @@ impl<'a> Start<'a> {
                     branches.branch
                 );
                 let selections = &["Push local branch", "Delete local branch and start fresh"];
-                let selection = dialoguer::Select::new()
-                    .with_prompt(prompt)
-                    .default(0)
-                    .items(&selections[..])
-                    .interact()?;
+                let selection = match deadline::answer(&prompt, Some(0))? {
+                    Some(selection) => selection,
+                    None => record::answer(
+                        &prompt,
+                        prompt::terminal().select(prompt.as_str(), &selections[..], 0)?,
+                    ),
+                };
 
                 match selection {
                     0 => {
-                        self.git.run(&[
-                            "push",
-                            "--no-verify",
-                            "--set-upstream",
-                            self.config.remote.as_str(),
-                            branches.branch.as_str(),
-                        ])?;
+                        push::push(self.git, &self.config.remote, &branches.branch, true)?;
                         self.git.run(&["checkout", branches.branch.as_str()])?;
                     }
                     _ => {
-                        self.git.run(&["branch", "-D", branches.branch.as_str()])?;
+                        self.git
+                            .run(&["branch", "-D", "--", branches.branch.as_str()])?;
 
                         create_and_push()?;
                     }
@@ -283,11 +690,13 @@ impl<'a> Start<'a> {
                     "Checkout remote branch",
                     "Delete remote branch and start fresh",
                 ];
-                let selection = dialoguer::Select::new()
-                    .with_prompt(prompt)
-                    .default(0)
-                    .items(&selections[..])
-                    .interact()?;
+                let selection = match deadline::answer(&prompt, Some(0))? {
+                    Some(selection) => selection,
+                    None => record::answer(
+                        &prompt,
+                        prompt::terminal().select(prompt.as_str(), &selections[..], 0)?,
+                    ),
+                };
 
                 match selection {
                     0 => {
@@ -297,9 +706,10 @@ impl<'a> Start<'a> {
                         self.git.run(&[
                             "push",
                             &self.config.remote,
+                            "--no-verify",
                             "--delete",
+                            "--",
                             branches.branch.as_str(),
-                            "--no-verify",
                         ])?;
 
                         create_and_push()?;
@@ -313,7 +723,21 @@ impl<'a> Start<'a> {
         Ok(())
     }
 
-    fn start_timer(&self, minutes: i64, next: Option<String>) -> Result<()> {
+    /// With the turn that starts now, for mob status to tell how long is left
+    fn turn_times(
+        &self,
+        times: session::Times,
+        settings: Option<&session::Settings>,
+    ) -> session::Times {
+        let minutes = settings.map(|settings| settings.work_duration);
+        session::Times {
+            turn_started: Some(self.clock.now()),
+            turn_minutes: self.opts.minutes.or(minutes),
+            ..times
+        }
+    }
+
+    fn start_timer(&self, minutes: Minutes, next: Option<String>) -> Result<()> {
         let minutes = self.opts.minutes.unwrap_or(minutes);
 
         let timer_message = format!(
@@ -325,11 +749,562 @@ impl<'a> Start<'a> {
         );
 
         self.timer.start(
+            timer::Countdown::Turn,
             "Your turn",
-            chrono::Duration::minutes(minutes),
+            minutes.duration(),
             timer_message.as_str(),
         )?;
         log::info!("Done. Run mob next");
         Ok(())
     }
 }
+
+/// How many turns in a row starting would make for `me` and who else could drive, least
+/// recent first. `None` below the threshold, when it is 0 or when nobody else is there.
+fn nudge(
+    history: &session::History,
+    drivers: &[String],
+    me: &str,
+    threshold: usize,
+) -> Option<(usize, Vec<String>)> {
+    let mut others: Vec<String> = drivers
+        .iter()
+        .filter(|driver| *driver != me)
+        .cloned()
+        .collect();
+    if threshold == 0 || others.is_empty() {
+        return None;
+    }
+    let turns = match history.consecutive() {
+        Some((driver, turns)) if driver == me => turns + 1,
+        _ => 1,
+    };
+    if turns < threshold {
+        return None;
+    }
+    others.sort_by_key(|driver| history.last_driven(driver));
+    Some((turns, others))
+}
+
+/// Tells about what the schedule has due before the turn starts
+fn warn(suggestion: Option<Suggestion>) {
+    let suggestion = match suggestion {
+        Some(suggestion) => suggestion,
+        None => return,
+    };
+    let minutes = suggestion.duration.num_minutes();
+    match suggestion.kind {
+        Kind::Stale => log::warn!(
+            "Nothing happened in this session for {} hours, is everyone still here?",
+            suggestion.duration.num_hours()
+        ),
+        Kind::Window => log::warn!(
+            "It's {} time for {} more minutes",
+            suggestion.window.as_deref().unwrap_or("Lunch"),
+            minutes
+        ),
+        Kind::WrapUp => log::warn!(
+            "The workday ends in {} minutes, consider wrapping up with mob done",
+            minutes
+        ),
+        Kind::Break => log::warn!("A break is due, consider taking it before this turn"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::FakeClock;
+    use crate::git::{
+        fake::{FakeGit, MemoryStore},
+        NoopGit,
+    };
+    use crate::timer::fake::NoTimer;
+    use chrono::Duration;
+    use session::{History, Store};
+
+    fn git(branches: &[&str]) -> FakeGit {
+        FakeGit {
+            branches: branches.iter().map(|branch| branch.to_string()).collect(),
+            ..FakeGit::default()
+        }
+    }
+
+    /// The branches `start` goes with, answering with `answers` where it asks
+    fn prepare(
+        git: &FakeGit,
+        args: &[&str],
+        answers: &[(&str, usize)],
+    ) -> Result<session::Branches> {
+        prepare_with(git, args, answers, Config::default())
+    }
+
+    fn prepare_with(
+        git: &FakeGit,
+        args: &[&str],
+        answers: &[(&str, usize)],
+        config: Config,
+    ) -> Result<session::Branches> {
+        let memory = MemoryStore::default();
+        let store = session::SessionStore::new(&memory);
+        let clock = FakeClock::default();
+        let start = Start::new(
+            git,
+            &store,
+            &NoTimer,
+            &clock,
+            StartOpts::parse_from(args),
+            config,
+        );
+        crate::record::replay(
+            answers
+                .iter()
+                .map(|(question, answer)| crate::record::Answer {
+                    question: question.to_string(),
+                    answer: serde_json::json!(answer),
+                })
+                .collect(),
+        );
+        let branches = start.prepare_branches(session::Branches::default(), &git.snapshot);
+        crate::record::stop_replay();
+        branches
+    }
+
+    #[test]
+    fn suggests_a_close_base() {
+        let git = git(&[
+            "master",
+            "origin/master",
+            "origin/release/1.3",
+            "origin/release/1.4",
+        ]);
+        let err = prepare(&git, &["start", "--base", "relese/1.4"], &[]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "There is no branch relese/1.4 on origin, did you mean release/1.4 or release/1.3?"
+        );
+        assert_eq!(git.calls(), vec!["fetch --all --prune"]);
+
+        let question = "There is no branch relese/1.4 on origin, did you mean one of these?";
+        let branches = prepare(&git, &["start", "--base", "relese/1.4"], &[(question, 0)]).unwrap();
+        assert_eq!(branches.base_branch, "release/1.4");
+        assert!(git.calls().contains(&"checkout release/1.4".to_string()));
+
+        // Declined, it is missing as before
+        let git = self::git(&["origin/release/1.4"]);
+        let err = prepare(&git, &["start", "--base", "relese/1.4"], &[(question, 1)]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Could not find `origin/relese/1.4`, you need to push your branch first"
+        );
+    }
+
+    #[test]
+    fn creates_the_branch_from_the_base() {
+        let git = git(&["master", "origin/master", "origin/release/1.4"]);
+        let branches = prepare(&git, &["start", "--base", "origin/release/1.4"], &[]).unwrap();
+        assert_eq!(branches.base_branch, "release/1.4");
+        assert_eq!(branches.base_remote, None);
+        assert_eq!(
+            git.calls(),
+            vec![
+                "fetch --all --prune",
+                "checkout release/1.4",
+                "merge --ff-only -- origin/release/1.4",
+                "checkout -b mob-session",
+                "push --no-verify --set-upstream origin -- mob-session",
+            ]
+        );
+    }
+
+    #[test]
+    fn checks_signing_before_starting() {
+        let memory = MemoryStore::default();
+        let store = session::SessionStore::new(&memory);
+        let clock = FakeClock::default();
+        let git = git(&["master", "origin/master"]);
+        git.fail("commit-tree", 0, "error: gpg failed to sign the data
+No pinentry");
+        let mut config = Config {
+            name: "alice".into(),
+            ..Config::default()
+        };
+        config.commit.sign = true;
+        let opts = StartOpts::parse_from(["start"]);
+        let err = Start::new(&git, &store, &NoTimer, &clock, opts, config)
+            .run()
+            .unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<git::signing::Error>(),
+            Some(&git::signing::Error::Agent)
+        );
+        assert_eq!(git.calls().len(), 1);
+        assert_eq!(*memory.saves.borrow(), 0);
+    }
+
+    #[test]
+    fn refuses_branches_without_a_repository() {
+        let memory = MemoryStore::default();
+        let store = session::SessionStore::new(&memory);
+        let clock = FakeClock::default();
+        let config = Config {
+            name: "alice".into(),
+            ..Config::default()
+        };
+        let opts = StartOpts::parse_from(["start", "--local", "--branch", "feature"]);
+        let err = Start::new(&git::NoopGit, &store, &NoTimer, &clock, opts, config)
+            .run()
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "--branch needs a git repository, this session has none"
+        );
+        assert_eq!(*memory.saves.borrow(), 0);
+    }
+
+    #[test]
+    fn refuses_an_unknown_timezone() {
+        let memory = MemoryStore::default();
+        let store = session::SessionStore::new(&memory);
+        let clock = FakeClock::default();
+        let config = Config {
+            name: "alice".into(),
+            ..Config::default()
+        };
+        let opts = StartOpts::parse_from(["start", "--preset", "focus", "--timezone", "Berlin"]);
+        let err = Start::new(&git::NoopGit, &store, &NoTimer, &clock, opts, config)
+            .run()
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "`Berlin` is not a timezone, use a name like Europe/Berlin"
+        );
+        assert_eq!(*memory.saves.borrow(), 0);
+    }
+
+    #[test]
+    fn starts_below_the_minimum_only_when_forced() {
+        let start = |args: &[&str], enforce| {
+            let memory = MemoryStore::default();
+            let store = session::SessionStore::new(&memory);
+            let clock = FakeClock::default();
+            let mut config = Config {
+                name: "alice".into(),
+                ..Config::default()
+            };
+            config.limits.min_drivers = Some(3);
+            config.limits.enforce = enforce;
+            let opts = StartOpts::parse_from([&["start", "--preset", "focus"], args].concat());
+            let result = Start::new(&git::NoopGit, &store, &NoTimer, &clock, opts, config).run();
+            let saved = *memory.saves.borrow() > 0;
+            result.map(|_| saved)
+        };
+        let err = start(&[], false).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "limits.min_drivers requires 3 drivers, the mob would have 1, run with --force to start anyway"
+        );
+        assert!(start(&["--force"], false).unwrap());
+        assert!(start(&["--force"], true).is_err());
+    }
+
+    #[test]
+    fn refuses_another_base_for_a_running_session() {
+        let memory = MemoryStore::default();
+        let store = session::SessionStore::new(&memory);
+        let clock = FakeClock::default();
+        store
+            .save(session::Session {
+                drivers: session::Drivers::new(vec!["alice".into(), "bob".into()]),
+                state: State::WaitingForNext { next: None },
+                settings: Some(session::Settings::default()),
+                ..session::Session::default()
+            })
+            .unwrap();
+        let git = git(&["master", "origin/master", "origin/release/1.4"]);
+        let start = |base: &str| {
+            let opts = StartOpts::parse_from(["start", "--base", base]);
+            Start::new(&git, &store, &NoTimer, &clock, opts, Config::default()).run()
+        };
+
+        assert_eq!(
+            start("release/1.4").unwrap_err().to_string(),
+            "The session works on top of master, not release/1.4. \
+             Run mob done first to start one on release/1.4"
+        );
+        assert!(git.calls().is_empty());
+
+        start("origin/master").unwrap();
+        assert!(matches!(store.load().unwrap().state, State::Working { .. }));
+    }
+
+    #[test]
+    fn stashes_or_takes_uncommitted_changes() {
+        let start = |args: &[&str]| {
+            let memory = MemoryStore::default();
+            let store = session::SessionStore::new(&memory);
+            store
+                .save(session::Session {
+                    drivers: session::Drivers::new(vec!["alice".into(), "bob".into()]),
+                    state: State::WaitingForNext { next: None },
+                    settings: Some(session::Settings::default()),
+                    ..session::Session::default()
+                })
+                .unwrap();
+            let mut git = git(&["master", "origin/master"]);
+            git.snapshot.unstaged = 1;
+            git.revs.insert("stash@{0}".into(), "5ta5h".into());
+            let state_dir = tempfile::tempdir().unwrap();
+            let clock = FakeClock::default();
+            let mut start = Start::new(
+                &git,
+                &store,
+                &NoTimer,
+                &clock,
+                StartOpts::parse_from(args),
+                Config::default(),
+            )
+            .state_dir(StateDir::new(state_dir.path().into()));
+            start.interactive = false;
+            let result = start.run().map(|_| git.calls());
+            let stashed = state_dir.path().join("stash.json").exists();
+            (result, stashed)
+        };
+
+        let (calls, stashed) = start(&["start", "--stash"]);
+        let calls = calls.unwrap();
+        assert!(calls[0].starts_with("stash push --include-untracked"));
+        assert_eq!(calls[1], "checkout master");
+        assert!(stashed);
+
+        let (calls, stashed) = start(&["start", "--include-uncommitted"]);
+        assert_eq!(calls.unwrap()[0], "checkout master");
+        assert!(!stashed);
+
+        let (err, _) = start(&["start"]);
+        assert_eq!(
+            err.unwrap_err().to_string(),
+            "Working tree is not clean, run mob start --stash or --include-uncommitted"
+        );
+    }
+
+    #[test]
+    fn prefixes_the_feature_branch() {
+        let config = Config {
+            git: crate::config::GitConfig {
+                branch_prefix: Some("mob/".into()),
+                ..Default::default()
+            },
+            ..Config::default()
+        };
+        let git = git(&["master", "origin/master"]);
+        let prepare = |args: &[&str]| prepare_with(&git, args, &[], config.clone());
+        let branch = |args: &[&str]| prepare(args).unwrap().branch;
+        assert_eq!(
+            branch(&["start", "--branch", "payment-flow"]),
+            "mob/payment-flow"
+        );
+        assert_eq!(
+            branch(&["start", "--branch", "mob/payment-flow"]),
+            "mob/payment-flow"
+        );
+        assert_eq!(branch(&["start"]), "mob/mob-session");
+        assert!(git
+            .calls()
+            .contains(&"checkout -b mob/payment-flow".to_string()));
+
+        for name in ["payment flow", "payment..flow"] {
+            let err = prepare(&["start", "--branch", name]).unwrap_err();
+            assert!(
+                err.to_string().contains("is not a valid branch name"),
+                "{}",
+                err
+            );
+        }
+    }
+
+    #[test]
+    fn suggests_or_creates_the_feature_branch() {
+        let git = git(&[
+            "master",
+            "origin/master",
+            "feature/login",
+            "origin/mob-sessions",
+        ]);
+        let err = prepare(&git, &["start", "--branch", "feature/logn"], &[]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "There is no branch feature/logn here or on origin, did you mean feature/login? \
+             Run mob start --create to create feature/logn anyway"
+        );
+        assert!(!git
+            .calls()
+            .iter()
+            .any(|call| call.starts_with("checkout -b")));
+
+        let args = &["start", "--branch", "feature/logn", "--create"];
+        assert_eq!(prepare(&git, args, &[]).unwrap().branch, "feature/logn");
+        assert!(git
+            .calls()
+            .contains(&"checkout -b feature/logn".to_string()));
+
+        // The usual branch is created even with a close one around
+        let git = self::git(&["master", "origin/master", "origin/mob-sessions"]);
+        assert_eq!(
+            prepare(&git, &["start"], &[]).unwrap().branch,
+            "mob-session"
+        );
+        assert!(git.calls().contains(&"checkout -b mob-session".to_string()));
+    }
+
+    #[test]
+    fn nudges_on_the_third_turn() {
+        let drivers: Vec<String> = vec!["alice".into(), "bob".into(), "carol".into()];
+        let nudge =
+            |turns: &[&str], threshold| nudge(&History::of(turns), &drivers, "alice", threshold);
+
+        assert_eq!(nudge(&["alice"], 3), None);
+        assert_eq!(
+            nudge(&["bob", "alice", "alice"], 3),
+            Some((3, vec!["carol".into(), "bob".into()]))
+        );
+        assert_eq!(
+            nudge(&["carol", "bob", "alice", "break", "alice"], 3),
+            Some((3, vec!["carol".into(), "bob".into()]))
+        );
+        assert_eq!(
+            nudge(&["alice", "alice", "alice"], 3),
+            Some((4, vec!["bob".into(), "carol".into()]))
+        );
+        assert_eq!(nudge(&["alice", "alice", "bob"], 3), None);
+        assert_eq!(nudge(&["alice", "alice"], 0), None);
+        assert_eq!(nudge(&["alice"], 2).map(|(turns, _)| turns), Some(2));
+    }
+
+    #[test]
+    fn asks_to_drive_anyway_through_the_recording() {
+        let memory = MemoryStore::default();
+        let store = session::SessionStore::new(&memory);
+        let clock = FakeClock::default();
+        store
+            .save(session::Session {
+                drivers: session::Drivers::new(vec!["alice".into(), "bob".into()]),
+                state: State::WaitingForNext { next: None },
+                settings: Some(session::Settings::default()),
+                history: History::of(&["bob", "alice", "alice"]),
+                ..session::Session::default()
+            })
+            .unwrap();
+        let config = Config {
+            name: "alice".into(),
+            ..Config::default()
+        };
+        crate::record::replay(vec![crate::record::Answer {
+            question: "Drive anyway?".into(),
+            answer: serde_json::json!(false),
+        }]);
+        let opts = StartOpts::parse_from(["start"]);
+        let started = Start::new(&NoopGit, &store, &NoTimer, &clock, opts, config).run();
+        crate::record::stop_replay();
+        started.unwrap();
+        assert_eq!(
+            store.load().unwrap().state,
+            State::WaitingForNext { next: None }
+        );
+    }
+
+    #[test]
+    fn alone_is_never_nudged() {
+        let turns = History::of(&["alice", "alice", "alice", "alice"]);
+        assert_eq!(nudge(&turns, &["alice".into()], "alice", 3), None);
+        assert_eq!(nudge(&turns, &[], "alice", 3), None);
+    }
+
+    #[test]
+    fn reserved_for_the_next_driver() {
+        let memory = MemoryStore::default();
+        let store = session::SessionStore::new(&memory);
+        let clock = FakeClock::default();
+        store
+            .save(session::Session {
+                drivers: session::Drivers::new(vec!["alice".into(), "bob".into()]),
+                state: State::WaitingForNext {
+                    next: Some("bob".into()),
+                },
+                settings: Some(session::Settings::default()),
+                times: session::Times {
+                    waiting_since: Some(clock.now() - Duration::seconds(40)),
+                    ..session::Times::default()
+                },
+                ..session::Session::default()
+            })
+            .unwrap();
+        let start = |name: &str| {
+            let mut config = Config {
+                name: name.into(),
+                ..Config::default()
+            };
+            config.handover.reassign_after = Some(Minutes(2));
+            let opts = StartOpts::parse_from(["start"]);
+            Start::new(&NoopGit, &store, &NoTimer, &clock, opts, config).run()
+        };
+
+        assert_eq!(
+            start("alice").unwrap_err().to_string(),
+            "The turn is reserved for bob (1m 20s left), after that anyone may start"
+        );
+        // Someone new to the mob too
+        assert_eq!(
+            start("carol").unwrap_err().to_string(),
+            "The turn is reserved for bob (1m 20s left), after that anyone may start"
+        );
+        let session = store.load().unwrap();
+        assert!(matches!(session.state, State::WaitingForNext { .. }));
+        assert!(!session.drivers.contains("carol"));
+
+        clock.advance(Duration::seconds(80));
+        start("alice").unwrap();
+        let session = store.load().unwrap();
+        assert_eq!(
+            session.state,
+            State::Working {
+                driver: "alice".into()
+            }
+        );
+        assert_eq!(session.times.turn_started, Some(clock.now()));
+        assert_eq!(
+            session.times.turn_minutes,
+            Some(session::Settings::default().work_duration)
+        );
+    }
+
+    #[test]
+    fn keeps_a_shorter_turn() {
+        let memory = MemoryStore::default();
+        let store = session::SessionStore::new(&memory);
+        let clock = FakeClock::default();
+        store
+            .save(session::Session {
+                drivers: session::Drivers::new(vec!["alice".into()]),
+                state: State::WaitingForNext { next: None },
+                settings: Some(session::Settings::default()),
+                ..session::Session::default()
+            })
+            .unwrap();
+        let config = Config {
+            name: "alice".into(),
+            ..Config::default()
+        };
+        let opts = StartOpts::parse_from(["start", "7"]);
+        let timer = timer::fake::RecordingTimer::default();
+        Start::new(&NoopGit, &store, &timer, &clock, opts, config)
+            .run()
+            .unwrap();
+        assert_eq!(store.load().unwrap().times.turn_minutes, Some(Minutes(7)));
+        let started = timer.started.take();
+        assert_eq!(started.len(), 1);
+        assert_eq!(started[0].countdown, timer::Countdown::Turn);
+        assert_eq!(started[0].title, "Your turn");
+        assert_eq!(started[0].duration, Duration::minutes(7));
+    }
+}
@@ -0,0 +1,120 @@
+//! Changes that were in the tree when `mob start` ran, stashed so they stay out of the mob
+//! and offered back by `mob done`. Which stash it was is kept in the state dir of the clone.
+
+use crate::git::Git;
+use crate::state_dir::StateDir;
+use crate::{deadline, prompt, record};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+/// The state file with the stash of `mob start`
+const STASHED: &str = "stash.json";
+const MESSAGE: &str = "mob start: changes from before the session";
+
+#[derive(Serialize, Deserialize)]
+struct Stashed {
+    commit: String,
+}
+
+/// Stashes the changes of the tree, new files too, and remembers the stash when there is
+/// a `state_dir` to keep it in
+pub(super) fn save(git: &dyn Git, state_dir: Option<&StateDir>) -> Result<()> {
+    git.run(&["stash", "push", "--include-untracked", "--message", MESSAGE])?;
+    let commit = git.rev_parse("stash@{0}")?;
+    match (commit, state_dir) {
+        (Some(commit), Some(state_dir)) => {
+            state_dir.write(STASHED, &Stashed { commit })?;
+            log::info!("Stashed your changes, mob done offers them back");
+        }
+        _ => log::info!("Stashed your changes, run git stash pop after mob done"),
+    }
+    Ok(())
+}
+
+/// Offers back what `mob start` stashed. Changes that conflict with the session's work are
+/// left to resolve, the stash is kept until then.
+pub(super) fn restore(git: &dyn Git, state_dir: &StateDir, interactive: bool) -> Result<()> {
+    let stashed = match state_dir.read::<Stashed>(STASHED) {
+        Some(stashed) => stashed,
+        None => return Ok(()),
+    };
+    let question = "Restore the changes you stashed at mob start?";
+    let restore = match deadline::answer(question, Some(true))? {
+        Some(restore) => restore,
+        None if interactive => record::answer(question, prompt::terminal().confirm(question, true)?),
+        None => true,
+    };
+    state_dir.remove(STASHED)?;
+    if !restore {
+        log::info!("Your changes stay stashed as {}", stashed.commit);
+        return Ok(());
+    }
+
+    // Only the newest stash is popped, others moved it down the list
+    let newest = git.rev_parse("stash@{0}")?;
+    let popped = match newest.as_deref() == Some(stashed.commit.as_str()) {
+        true => git.run(&["stash", "pop"]),
+        false => git.run(&["stash", "apply", stashed.commit.as_str()]),
+    };
+    match popped {
+        Ok(()) => log::info!("Restored the changes you stashed at mob start"),
+        Err(err) => {
+            log::trace!("Restoring the stash failed: {}", err);
+            log::warn!(
+                "Your stashed changes conflict with the session's work, resolve the conflicts \
+                 and drop the stash {} with git stash drop",
+                stashed.commit
+            );
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::fake::FakeGit;
+
+    fn stashed() -> (tempfile::TempDir, StateDir, FakeGit) {
+        let dir = tempfile::tempdir().unwrap();
+        let state_dir = StateDir::new(dir.path().into());
+        let mut git = FakeGit::default();
+        git.revs.insert("stash@{0}".into(), "5ta5h".into());
+        save(&git, Some(&state_dir)).unwrap();
+        (dir, state_dir, git)
+    }
+
+    #[test]
+    fn pops_what_start_stashed() {
+        let (_dir, state_dir, git) = stashed();
+        restore(&git, &state_dir, false).unwrap();
+        assert_eq!(
+            git.calls(),
+            vec![
+                "stash push --include-untracked --message mob start: changes from before the session",
+                "stash pop"
+            ]
+        );
+        assert!(state_dir.read::<Stashed>(STASHED).is_none());
+
+        // Nothing stashed, nothing to restore
+        restore(&git, &state_dir, false).unwrap();
+        assert_eq!(git.calls().len(), 2);
+    }
+
+    #[test]
+    fn applies_a_stash_that_moved_down() {
+        let (_dir, state_dir, mut git) = stashed();
+        git.revs.insert("stash@{0}".into(), "n3w3r".into());
+        restore(&git, &state_dir, false).unwrap();
+        assert_eq!(git.calls().last().unwrap(), "stash apply 5ta5h");
+    }
+
+    #[test]
+    fn leaves_conflicts_to_resolve() {
+        let (_dir, state_dir, git) = stashed();
+        git.fail("stash pop", 0, "CONFLICT (content): Merge conflict in src/lib.rs");
+        restore(&git, &state_dir, false).unwrap();
+        assert!(state_dir.read::<Stashed>(STASHED).is_none());
+    }
+}
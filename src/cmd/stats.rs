@@ -0,0 +1,318 @@
+use crate::metrics::{self, Aggregate};
+use crate::stats::{self, Report, Summary};
+use crate::{clock::Clock, config::Config, duration, session, state_dir::StateDir};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use clap::{self, Clap};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+#[derive(Clap, Debug)]
+pub struct StatsOpts {
+    /// Sum up the sessions `mob done` archived instead of the current one
+    #[clap(long)]
+    all_sessions: bool,
+
+    /// Only sessions that ended this long ago or later, like 30d, 2w or 12h
+    #[clap(long, requires = "all-sessions")]
+    since: Option<String>,
+
+    /// Sum up the metrics that `mob done` wrote there, of all repositories
+    #[clap(long, conflicts_with = "all-sessions", value_name = "dir")]
+    metrics_dir: Option<PathBuf>,
+}
+
+pub struct Stats<'a> {
+    store: &'a dyn session::Store,
+    clock: &'a dyn Clock,
+    state_dir: StateDir,
+    opts: StatsOpts,
+    config: Config,
+}
+
+impl<'a> Stats<'a> {
+    pub fn new(
+        store: &'a impl session::Store,
+        clock: &'a impl Clock,
+        state_dir: StateDir,
+        opts: StatsOpts,
+        config: Config,
+    ) -> Stats<'a> {
+        Self {
+            store,
+            clock,
+            state_dir,
+            opts,
+            config,
+        }
+    }
+
+    pub fn run(&self) -> Result<()> {
+        if self.opts.all_sessions {
+            return self.all_sessions();
+        }
+        if let Some(dir) = &self.opts.metrics_dir {
+            return print_metrics(dir);
+        }
+
+        let session = self.store.load()?;
+        let report = match (&session.state, Report::of(&session, self.clock.now_utc())) {
+            (session::State::Stopped, _) | (_, None) => {
+                log::info!("No current mob session, run mob start or mob stats --all-sessions");
+                return Ok(());
+            }
+            (_, Some(report)) => report,
+        };
+        log::info!(
+            "The session runs for {}",
+            duration::format(report.duration()).human()
+        );
+        print_turns(&report.turns, &report.short_turns);
+        Ok(())
+    }
+
+    fn all_sessions(&self) -> Result<()> {
+        let since = match &self.opts.since {
+            Some(since) => Some(self.clock.now_utc() - duration::parse(since)?),
+            None => None,
+        };
+        let summary = Summary::of(&stats::read(
+            &stats::dirs(&self.state_dir, &self.config.stats),
+            since,
+        ));
+        if summary.suspect > 0 {
+            log::warn!(
+                "Left out {} sessions whose times go backwards, as when the clock jumped back",
+                summary.suspect
+            );
+        }
+        let average = match summary.average() {
+            Some(average) => average,
+            None => {
+                log::info!("No finished sessions yet, mob done archives them");
+                return Ok(());
+            }
+        };
+        log::info!(
+            "{} sessions, {} in total, {} on average",
+            summary.sessions,
+            duration::format(summary.total).human(),
+            duration::format(average).human()
+        );
+        print_turns(&summary.turns, &summary.short_turns);
+        Ok(())
+    }
+}
+
+fn print_metrics(dir: &Path) -> Result<()> {
+    let aggregate = Aggregate::of(&metrics::read(dir)?);
+    for line in metric_lines(&aggregate) {
+        println!("{}", line);
+    }
+    Ok(())
+}
+
+fn metric_lines(aggregate: &Aggregate) -> Vec<String> {
+    let average = match aggregate.average_turn() {
+        Some(average) => average,
+        None => return vec!["No metrics yet, mob done writes them with metrics.dir".into()],
+    };
+    let mut lines = vec![
+        format!(
+            "{} sessions in {} repositories, {} in total",
+            aggregate.sessions,
+            aggregate.repos,
+            duration::format(chrono::Duration::minutes(aggregate.minutes)).human()
+        ),
+        format!(
+            "Turns last {} on average, {} of {} were short",
+            duration::format(average).human(),
+            aggregate.short_turns,
+            aggregate.turns
+        ),
+    ];
+    lines.push(match aggregate.break_adherence() {
+        Some(adherence) => format!(
+            "{} of {} due breaks taken ({}%)",
+            aggregate.breaks, aggregate.breaks_due, adherence
+        ),
+        None => format!("{} breaks taken, none were due", aggregate.breaks),
+    });
+    lines.push("Sessions per week:".into());
+    lines.extend(
+        aggregate
+            .weeks
+            .iter()
+            .map(|(week, sessions)| format!("  {} {:>3}", week, sessions)),
+    );
+    lines
+}
+
+/// Most turns first, with how many of them were shorter than the minimum turn
+fn print_turns(turns: &BTreeMap<String, usize>, short_turns: &BTreeMap<String, usize>) {
+    for line in turn_lines(turns, short_turns) {
+        println!("{}", line);
+    }
+}
+
+fn turn_lines(
+    turns: &BTreeMap<String, usize>,
+    short_turns: &BTreeMap<String, usize>,
+) -> Vec<String> {
+    let mut turns: Vec<_> = turns.iter().collect();
+    turns.sort_by(|a, b| b.1.cmp(a.1));
+    turns
+        .into_iter()
+        .map(|(driver, count)| match short_turns.get(driver) {
+            Some(short) => format!("  {:>3} {}, {} short", count, driver, short),
+            None => format!("  {:>3} {}", count, driver),
+        })
+        .collect()
+}
+
+/// Archives the report of a session that ends `now`, noting the `target` it went to
+/// instead of its base branch. Failing only costs the report
+pub(super) fn archive(
+    state_dir: &StateDir,
+    config: &Config,
+    session: &session::Session,
+    target: Option<&str>,
+    now: DateTime<Utc>,
+) {
+    let report = match Report::of(session, now) {
+        Some(report) => Report {
+            target: target.map(String::from),
+            ..report
+        },
+        None => return,
+    };
+    for dir in stats::dirs(state_dir, &config.stats) {
+        match stats::archive(&dir, &report) {
+            Ok(path) => log::trace!("Archived the session report to {}", path.display()),
+            Err(err) => log::warn!(
+                "Could not archive the session report in {}: {}",
+                dir.display(),
+                err
+            ),
+        }
+    }
+}
+
+/// Appends the anonymized numbers of a session that ends `now` to `metrics.dir`, if set.
+/// `repo` is the first commit of the repository. Failing only costs the numbers
+pub(super) fn metrics(
+    config: &Config,
+    session: &session::Session,
+    repo: Option<&str>,
+    now: DateTime<Utc>,
+) {
+    let dir = match &config.metrics.dir {
+        Some(dir) => dir,
+        None => return,
+    };
+    let report = match Report::of(session, now) {
+        Some(report) if report.suspect => {
+            log::warn!("The times of the session go backwards, no metrics are written for it");
+            return;
+        }
+        Some(report) => report,
+        None => return,
+    };
+    let record = metrics::Record::of(session, &report, repo, config.metrics.include_names);
+    match metrics::append(dir, &record) {
+        Ok(path) => log::trace!("Appended the session metrics to {}", path.display()),
+        Err(err) => log::warn!(
+            "Could not write the session metrics to {}: {}",
+            dir.display(),
+            err
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn since_needs_all_sessions() {
+        assert!(StatsOpts::try_parse_from(["stats", "--since", "30d"]).is_err());
+        assert!(StatsOpts::try_parse_from(["stats", "--all-sessions", "--since", "30d"]).is_ok());
+    }
+
+    #[test]
+    fn shows_short_turns() {
+        let counts = |counts: &[(&str, usize)]| -> BTreeMap<String, usize> {
+            counts
+                .iter()
+                .map(|(driver, count)| (driver.to_string(), *count))
+                .collect()
+        };
+        assert_eq!(
+            turn_lines(
+                &counts(&[("alice", 2), ("bob", 5)]),
+                &counts(&[("alice", 1)])
+            ),
+            vec!["    5 bob", "    2 alice, 1 short"]
+        );
+    }
+
+    #[test]
+    fn writes_metrics_only_when_configured() {
+        let now = chrono::Utc::now();
+        let session = session::Session {
+            state: session::State::Stopped,
+            times: session::Times {
+                started: Some((now - chrono::Duration::hours(2)).into()),
+                ..session::Times::default()
+            },
+            ..session::Session::default()
+        };
+        let dir = tempfile::tempdir().unwrap();
+        let metrics_dir = dir.path().join("metrics");
+        metrics(&Config::default(), &session, Some("f00d"), now);
+        assert!(!metrics_dir.exists());
+
+        let mut config = Config::default();
+        config.metrics.dir = Some(metrics_dir.clone());
+        metrics(&config, &session, Some("f00d"), now);
+        let records = metrics::read(&metrics_dir).unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].repo, Some(metrics::hash("f00d")));
+        assert_eq!(records[0].minutes, 120);
+    }
+
+    #[test]
+    fn shows_metrics() {
+        let mut weeks = BTreeMap::new();
+        weeks.insert("2021-W22".to_string(), 2);
+        weeks.insert("2021-W23".to_string(), 1);
+        let aggregate = Aggregate {
+            sessions: 3,
+            repos: 2,
+            weeks,
+            minutes: 360,
+            turns: 18,
+            short_turns: 2,
+            breaks: 3,
+            breaks_due: 6,
+        };
+        assert_eq!(
+            metric_lines(&aggregate),
+            vec![
+                "3 sessions in 2 repositories, 6 hours and 0 minutes in total",
+                "Turns last 20 minutes on average, 2 of 18 were short",
+                "3 of 6 due breaks taken (50%)",
+                "Sessions per week:",
+                "  2021-W22   2",
+                "  2021-W23   1",
+            ]
+        );
+        assert_eq!(
+            metric_lines(&Aggregate::of(&[])),
+            vec!["No metrics yet, mob done writes them with metrics.dir"]
+        );
+        assert!(
+            StatsOpts::try_parse_from(["stats", "--all-sessions", "--metrics-dir", "m"]).is_err()
+        );
+    }
+}
@@ -1,110 +1,1631 @@
-use crate::{config::Config, session};
+use crate::palette::{Palette, Role};
+use crate::table::{self, Table};
+use crate::timekeeping::{Instant, Minutes};
+use crate::events::EventLog;
+use crate::state_dir::StateDir;
+use crate::{clock::Clock, config::Config, dashboard, deadline, duration, eta, git, handover};
+use crate::{session, warnings};
 use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
 use clap::{self, Clap};
-use console::style;
+use serde::Serialize;
 use session::State;
+use std::fmt::Write;
 
 #[derive(Clap, Debug)]
 pub struct StatusOpts {
-    /// Show raw status
+    /// Show raw status, deprecated for `--json`
     #[clap(short, long)]
     raw: bool,
+
+    /// Fetch the mob branch and tell whether it moved outside mob
+    #[clap(long)]
+    fetch: bool,
+
+    /// Keep the status on screen with what happened recently below it, until interrupted
+    #[clap(short, long)]
+    watch: bool,
+
+    /// How many of the latest turns, breaks and events `--watch` shows
+    #[clap(long, default_value = "8")]
+    recent: usize,
+
+    /// How often `--watch` redraws, like 5s or 1m
+    #[clap(long, default_value = "5s", parse(try_from_str = duration::parse))]
+    interval: chrono::Duration,
+
+    /// Set from the global `--json`, the report then carries the session
+    #[clap(skip)]
+    pub json: bool,
+}
+
+/// The session as `mob status --json` reports it. Spelled out apart from the stored
+/// session so scripts keep working when that changes.
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+struct Report {
+    /// Set by `mob start`, stays the same when the branch is renamed
+    id: Option<String>,
+    state: StateName,
+    driver: Option<String>,
+    next_driver: Option<String>,
+    /// Why `mob emergency` paused the session
+    paused_for: Option<String>,
+    branch: String,
+    base_branch: String,
+    drivers: Vec<String>,
+    /// Estimated, soonest first
+    next_turns: Vec<NextTurn>,
+    last_break: Option<Instant>,
+    break_until: Option<Instant>,
+    settings: Option<SettingsReport>,
+}
+
+/// One of several sessions in the repository, as `mob status --json` lists them
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+struct NamedReport {
+    name: String,
+    #[serde(flatten)]
+    report: Report,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+struct NextTurn {
+    driver: String,
+    in_seconds: i64,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum StateName {
+    Stopped,
+    Working,
+    WaitingForNext,
+    Paused,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+struct SettingsReport {
+    commit_message: String,
+    work_duration: Minutes,
+    break_duration: Option<Minutes>,
+    break_interval: Option<Minutes>,
+    min_turn_minutes: Minutes,
+}
+
+impl Report {
+    fn of(session: &session::Session, now: DateTime<Utc>) -> Report {
+        let (state, driver, next_driver, paused_for) = match &session.state {
+            State::Stopped => (StateName::Stopped, None, None, None),
+            State::Working { driver } => {
+                let next = Some(&session.drivers)
+                    .filter(|drivers| !drivers.all().is_empty())
+                    .and_then(|drivers| drivers.next(driver, &session.away, now));
+                (StateName::Working, Some(driver.clone()), next, None)
+            }
+            State::WaitingForNext { next } => (StateName::WaitingForNext, None, next.clone(), None),
+            State::Paused { reason, driver, .. } => (
+                StateName::Paused,
+                driver.clone(),
+                None,
+                Some(reason.clone()),
+            ),
+        };
+        Report {
+            id: session.id.clone(),
+            state,
+            driver,
+            next_driver,
+            paused_for,
+            branch: session.branches.branch.clone(),
+            base_branch: session.branches.base_branch.clone(),
+            drivers: session.drivers.all(),
+            next_turns: eta::estimate(session, now)
+                .into_iter()
+                .map(|eta| NextTurn {
+                    driver: eta.driver,
+                    in_seconds: eta.wait.num_seconds(),
+                })
+                .collect(),
+            last_break: session.times.last_break,
+            break_until: session.times.break_until,
+            settings: session.settings.as_ref().map(|settings| SettingsReport {
+                commit_message: settings.commit_message.clone(),
+                work_duration: settings.work_duration,
+                break_duration: settings.break_duration,
+                break_interval: settings.break_interval,
+                min_turn_minutes: settings.min_turn_minutes,
+            }),
+        }
+    }
 }
 
 pub struct Status<'a> {
+    git: &'a dyn git::Git,
     store: &'a dyn session::Store,
+    clock: &'a dyn Clock,
     config: Config,
     opts: StatusOpts,
+    palette: Palette,
+    /// Columns of the terminal, `None` when it isn't one
+    width: Option<usize>,
+    /// Every session of the repository by name, listed instead of the one selected
+    sessions: Vec<(String, session::Session)>,
+    /// Where the events of this clone are, shown by `--watch` with the history
+    state_dir: Option<StateDir>,
 }
 
 impl<'a> Status<'a> {
-    pub fn new(opts: StatusOpts, store: &'a impl session::Store, config: Config) -> Status<'a> {
+    pub fn new(
+        opts: StatusOpts,
+        git: &'a impl git::Git,
+        store: &'a impl session::Store,
+        clock: &'a impl Clock,
+        config: Config,
+    ) -> Status<'a> {
+        Self::of(opts, git, store, clock, config)
+    }
+
+    pub(super) fn of(
+        opts: StatusOpts,
+        git: &'a dyn git::Git,
+        store: &'a dyn session::Store,
+        clock: &'a dyn Clock,
+        config: Config,
+    ) -> Status<'a> {
+        let palette = Palette::new(config.display.palette);
         Self {
             opts,
+            git,
             store,
+            clock,
             config,
+            palette,
+            width: table::terminal_width(),
+            sessions: vec![],
+            state_dir: None,
         }
     }
 
+    /// Lists these sessions instead, when the repository has several and none was selected
+    pub fn sessions(mut self, sessions: Vec<(String, session::Session)>) -> Self {
+        self.sessions = sessions;
+        self
+    }
+
+    pub fn state_dir(mut self, state_dir: StateDir) -> Self {
+        self.state_dir = Some(state_dir);
+        self
+    }
+
     pub fn run(&self) -> Result<()> {
+        if !self.sessions.is_empty() {
+            return self.list_sessions();
+        }
         let session = self.store.load()?;
 
+        if self.opts.raw {
+            warnings::push(
+                "status-raw",
+                "--raw is deprecated, use --json for output that scripts can read",
+                "0.1.7",
+            );
+        }
+        if self.opts.json {
+            let report = Report::of(&session, self.clock.now_utc());
+            warnings::set_status(serde_json::to_value(report)?);
+            return Ok(());
+        }
+
         if self.opts.raw {
             println!("{:#?}", session);
             return Ok(());
         }
 
-        self.print_status(&session);
-        self.print_drivers(&session);
+        if self.opts.watch {
+            return self.watch();
+        }
+
+        let snapshot = self.snapshot()?;
+        print!("{}", self.render(&session, &snapshot));
+        if self.opts.fetch {
+            if let Some(moved) = super::outside::find(self.git, &session, &self.config.remote)? {
+                print!("{}", self.render_moved(&moved));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Redraws the status and what happened recently until interrupted or out of time
+    fn watch(&self) -> Result<()> {
+        loop {
+            deadline::check()?;
+            let session = self.store.load()?;
+            let status = self.render(&session, &self.snapshot()?);
+            let events = match &self.state_dir {
+                Some(state_dir) => EventLog::new(state_dir.clone()).recent(self.opts.recent),
+                None => vec![],
+            };
+            let mut entries = dashboard::entries(&session.history, &events, self.clock);
+            entries.drain(..entries.len().saturating_sub(self.opts.recent));
+            let now = self.clock.now_utc();
+            let screen = dashboard::compose(&status, &entries, now, table::terminal_size());
+            // Clears the terminal and starts at its top left
+            print!("\x1b[2J\x1b[H{}", screen);
+            let mut wait = self.opts.interval;
+            if let Some(left) = deadline::remaining() {
+                wait = std::cmp::min(wait, Duration::from_std(left)?);
+            }
+            std::thread::sleep(wait.to_std().unwrap_or_default());
+        }
+    }
+
+    fn snapshot(&self) -> Result<git::Snapshot> {
+        match self.git.is_repository() {
+            true => self.git.snapshot(),
+            false => Ok(git::Snapshot::default()),
+        }
+    }
 
+    fn list_sessions(&self) -> Result<()> {
+        let now = self.clock.now_utc();
+        if self.opts.json {
+            let reports: Vec<NamedReport> = self
+                .sessions
+                .iter()
+                .map(|(name, session)| NamedReport {
+                    name: name.clone(),
+                    report: Report::of(session, now),
+                })
+                .collect();
+            warnings::set_status(serde_json::json!({ "sessions": reports }));
+            return Ok(());
+        }
+        print!("{}", self.render_sessions());
         Ok(())
     }
 
-    fn print_status(&self, session: &session::Session) {
+    fn render_sessions(&self) -> String {
+        let me = self.config.name.as_str();
+        let table = self
+            .sessions
+            .iter()
+            .fold(Table::new(), |table, (name, session)| {
+                let state = match &session.state {
+                    State::Stopped => "stopped".to_string(),
+                    State::Working { driver } if driver == me => "you are driving".to_string(),
+                    State::Working { driver } => {
+                        format!("{} is driving", session::shown(driver))
+                    }
+                    State::WaitingForNext { next: Some(next) } => {
+                        format!("waiting for {}", session::shown(next))
+                    }
+                    State::WaitingForNext { next: None } => "waiting for anyone".to_string(),
+                    State::Paused { reason, .. } => format!("paused, {}", reason),
+                };
+                table.row(vec![name.clone(), state])
+            });
+        let mut out = String::new();
+        writeln!(out, "📚 {} sessions in this repository:", self.sessions.len()).unwrap();
+        for line in table.lines(None).unwrap_or_default() {
+            writeln!(out, "   {}", line).unwrap();
+        }
+        let help = "Run 'mob --session <name> status' for one of them";
+        writeln!(out, "   {}", self.palette.paint(Role::Hint, help)).unwrap();
+        out
+    }
+
+    fn render_moved(&self, moved: &session::outside::Moved) -> String {
+        let mut out = String::new();
+        let line = format!("⚠️  While nobody drove {}", moved);
+        writeln!(out, "\n{}", self.palette.paint(Role::Warning, line)).unwrap();
+        let help = "The next mob command offers to take the commits into the session";
+        writeln!(out, "   {}", self.palette.paint(Role::Hint, help)).unwrap();
+        out
+    }
+
+    fn render(&self, session: &session::Session, snapshot: &git::Snapshot) -> String {
+        let mut out = String::new();
+        self.render_status(&mut out, session, snapshot);
+        self.render_pending(&mut out);
+        self.render_repositories(&mut out, session);
+        self.render_tree(&mut out, &session.state, snapshot);
+        let now = self.clock.now_utc();
+        self.render_break(&mut out, &session.times, now);
+        self.render_drivers(&mut out, session, now);
+        self.render_observers(&mut out, &session.observers);
+        out
+    }
+
+    fn render_status(
+        &self,
+        out: &mut String,
+        session: &session::Session,
+        snapshot: &git::Snapshot,
+    ) {
         let me = self.config.name.clone();
+        let p = &self.palette;
         match &session.state {
             State::Stopped => {
                 let help = "Run 'mob start' to start a new session";
-                println!("✋ {}", style("Stopped").red());
-                println!("   {}", style(help).cyan());
+                writeln!(out, "✋ {}", p.paint(Role::Stopped, "Stopped")).unwrap();
+                writeln!(out, "   {}", p.paint(Role::Hint, help)).unwrap();
             }
             State::Working { driver } => {
                 let driver = if driver == &me {
                     "You are".to_string()
                 } else {
-                    format!("{} is", driver)
+                    format!("{} is", session::shown(driver))
                 };
-                println!("🚗 {} {}", driver, style("driving").green(),);
-                println!("   {}", style("Run 'mob next' when finished").cyan());
-                self.print_branches(&session.branches);
+                write!(out, "🚗 {} {}", driver, p.paint(Role::Current, "driving")).unwrap();
+                match turn_left(&session.times, self.clock.now_utc()) {
+                    Some(left) if left > Duration::zero() => {
+                        let minutes = (left.num_seconds() + 59) / 60;
+                        write!(out, ", {} min remaining", minutes).unwrap();
+                    }
+                    Some(left) if left.num_minutes() < 0 => {
+                        let over = format!("overtime by {} min", -left.num_minutes());
+                        write!(out, ", {}", p.paint(Role::Stopped, over)).unwrap();
+                    }
+                    Some(_) => write!(out, ", {}", p.paint(Role::Stopped, "time is up")).unwrap(),
+                    None => {}
+                }
+                writeln!(out).unwrap();
+                let help = "Run 'mob next' when finished";
+                writeln!(out, "   {}", p.paint(Role::Hint, help)).unwrap();
+                self.render_branches(out, &session.branches, snapshot);
             }
             State::WaitingForNext { next } => {
                 let next = match next {
-                    Some(driver) if driver == &me => "You",
-                    Some(ref driver) => driver,
-                    None => "Anyone",
+                    Some(driver) if driver == &me => "You".to_string(),
+                    Some(driver) => session::shown(driver),
+                    None => "Anyone".to_string(),
                 };
 
-                println!(
+                writeln!(
+                    out,
                     "💤 {} for {} to run 'mob start'",
-                    style("Waiting").blue(),
+                    p.paint(Role::Next, "Waiting"),
                     next
-                );
-                self.print_branches(&session.branches);
+                )
+                .unwrap();
+                if let Some(claimed) = session.times.claimed {
+                    let ago = duration::ago(duration::elapsed(claimed, self.clock.now_utc()));
+                    let help = format!("Claimed {}, 'mob unclaim' lets anyone start", ago);
+                    writeln!(out, "   {}", p.paint(Role::Hint, &help)).unwrap();
+                }
+                let now = self.clock.now_utc();
+                if let Some(reservation) =
+                    handover::reservation(now, session, &self.config.handover)
+                {
+                    let help = format!("The turn is {}, then anyone may start", reservation);
+                    writeln!(out, "   {}", p.paint(Role::Hint, &help)).unwrap();
+                }
+                self.render_branches(out, &session.branches, snapshot);
+            }
+            State::Paused {
+                reason,
+                driver: Some(driver),
+                remaining,
+            } => {
+                let left = match remaining {
+                    Some(left) => format!(
+                        ", {} left of the turn of {}",
+                        duration::format(Duration::seconds(*left)).short(),
+                        session::shown(driver)
+                    ),
+                    None => format!(", the turn of {}", session::shown(driver)),
+                };
+                writeln!(
+                    out,
+                    "⏸️  {} for {}{}",
+                    p.paint(Role::Stopped, "Paused"),
+                    reason,
+                    left
+                )
+                .unwrap();
+                let help = "Run 'mob resume' to go on";
+                writeln!(out, "   {}", p.paint(Role::Hint, help)).unwrap();
+                self.render_branches(out, &session.branches, snapshot);
+            }
+            State::Paused { reason, .. } => {
+                writeln!(
+                    out,
+                    "🚨 {} for {}",
+                    p.paint(Role::Stopped, "Paused"),
+                    reason
+                )
+                .unwrap();
+                let help = "Run 'mob start' to resume";
+                writeln!(out, "   {}", p.paint(Role::Hint, help)).unwrap();
+                self.render_branches(out, &session.branches, snapshot);
             }
         }
     }
 
-    fn print_branches(&self, branches: &session::Branches) {
-        println!(
+    fn render_branches(
+        &self,
+        out: &mut String,
+        branches: &session::Branches,
+        snapshot: &git::Snapshot,
+    ) {
+        if !self.git.is_repository() {
+            return;
+        }
+        let base = match &branches.base_remote {
+            Some(remote) => format!("{}/{}", remote, branches.base_branch),
+            None => branches.base_branch.clone(),
+        };
+        writeln!(
+            out,
             "\n🚚 working on {} with parent {}",
-            style(&branches.branch).red().bold(),
-            style(&branches.base_branch).cyan().bold(),
+            self.palette.paint(Role::Branch, &branches.branch),
+            self.palette.paint(Role::Base, &base),
+        )
+        .unwrap();
+        if !snapshot.is_on(&branches.branch) {
+            return;
+        }
+        if snapshot.ahead > 0 {
+            let unpushed = format!("{} local commits not pushed yet", snapshot.ahead);
+            writeln!(out, "   {}", self.palette.paint(Role::Warning, unpushed)).unwrap();
+        }
+        // A base that isn't fetched yet says nothing about the histories
+        let remote_base = branches.with_remote(&self.config.remote).base_branch;
+        if let Ok(None) = self.git.merge_base("HEAD", &remote_base) {
+            let help = format!(
+                "Shares no history with {}, mob done needs --allow-unrelated-histories",
+                base
+            );
+            writeln!(out, "   {}", self.palette.paint(Role::Muted, help)).unwrap();
+        }
+    }
+
+    /// A line for each repository of a workspace, with its branch, the tip recorded last and
+    /// its changed files
+    /// What was handed over offline and isn't on the remote yet
+    fn render_pending(&self, out: &mut String) {
+        let pending = match self.state_dir.as_ref().and_then(git::queue::Pending::read) {
+            Some(pending) => pending,
+            None => return,
+        };
+        let mut waiting = pending.branches;
+        if !pending.documents.is_empty() {
+            waiting.push("the session".to_string());
+        }
+        let line = format!(
+            "⚠️  This clone is ahead of the remote: {}",
+            waiting.join(", ")
+        );
+        writeln!(out, "\n{}", self.palette.paint(Role::Warning, line)).unwrap();
+        let help = "Run 'mob sync' once online to push it";
+        writeln!(out, "   {}", self.palette.paint(Role::Hint, help)).unwrap();
+    }
+
+    fn render_repositories(&self, out: &mut String, session: &session::Session) {
+        let members = self.git.members();
+        if members.is_empty() || session.state == State::Stopped {
+            return;
+        }
+        let branch = session.branches.branch.as_str();
+        for (index, (name, git)) in members.into_iter().enumerate() {
+            let (line, expected) = match git.snapshot() {
+                Ok(snapshot) => {
+                    let on = snapshot.branch.as_deref().unwrap_or("a detached HEAD");
+                    let mut line = format!("{} on {}", name, on);
+                    if let Some(tip) = session.tips.get(name) {
+                        write!(line, " at {}", &tip[..tip.len().min(7)]).unwrap();
+                    }
+                    if snapshot.files() > 0 {
+                        write!(line, ", {} changed files", snapshot.files()).unwrap();
+                    }
+                    (line, snapshot.is_on(branch))
+                }
+                Err(err) => (format!("{}: {:#}", name, err), false),
+            };
+            let line = match expected {
+                true => line,
+                false => self.palette.paint(Role::Warning, line).to_string(),
+            };
+            match index {
+                0 => writeln!(out, "\n📦 {}", line).unwrap(),
+                _ => writeln!(out, "   {}", line).unwrap(),
+            }
+        }
+    }
+
+    fn render_tree(&self, out: &mut String, state: &State, snapshot: &git::Snapshot) {
+        let files = snapshot.files();
+        let (role, line, hint) = match classify(state, &self.config.name, files > 0) {
+            Tree::Clean => return,
+            Tree::Driving => (
+                Role::Hint,
+                format!("{} changed files, mob next hands them over", files),
+                None,
+            ),
+            Tree::Collides(driver) => (
+                Role::Warning,
+                format!(
+                    "Your working tree has {} changed files not part of the session, they will collide with {}'s turn",
+                    files, driver
+                ),
+                Some("Run 'git stash' to put them aside"),
+            ),
+            Tree::BeforeStart => (
+                Role::Warning,
+                format!("Your working tree has {} changed files not part of the session", files),
+                Some("It's your turn: run 'git stash' for a clean tree, then 'mob start'"),
+            ),
+            Tree::Stray => (
+                Role::Hint,
+                format!("Your working tree has {} changed files not part of the session", files),
+                None,
+            ),
+        };
+        writeln!(out, "\n📝 {}", self.palette.paint(role, line)).unwrap();
+        if let Some(hint) = hint {
+            writeln!(out, "   {}", self.palette.paint(Role::Hint, hint)).unwrap();
+        }
+    }
+
+    fn render_break(&self, out: &mut String, times: &session::Times, now: DateTime<Utc>) {
+        let end = match times.break_until {
+            Some(end) if end.utc() > now => end.utc(),
+            _ => return,
+        };
+        let on = match times.last_window() {
+            Some(window) => format!("{} time", window),
+            None => "On a break".to_string(),
+        };
+        let left = duration::format(end - now).human();
+        writeln!(
+            out,
+            "\n☕ {} until {}, {} left",
+            on,
+            self.palette
+                .paint(Role::Next, self.clock.local(end).format("%H:%M")),
+            left
         )
+        .unwrap();
+    }
+
+    fn render_observers(&self, out: &mut String, observers: &session::Observers) {
+        if observers.is_empty() {
+            return;
+        }
+        let me = self.config.name.as_str();
+        let names: Vec<String> = observers
+            .all()
+            .into_iter()
+            .map(|name| if name == me { "you".into() } else { name })
+            .collect();
+        writeln!(out, "\n👀 Observing: {}", names.join(", ")).unwrap();
+        let help = "'mob claim' makes an observer a driver";
+        writeln!(out, "   {}", self.palette.paint(Role::Hint, help)).unwrap();
     }
 
-    fn print_drivers(&self, session: &session::Session) {
+    /// The rotation as the status shows it, for `mob drivers list`
+    pub(super) fn drivers(&self, session: &session::Session, now: DateTime<Utc>) -> String {
+        let mut out = String::new();
+        self.render_drivers(&mut out, session, now);
+        out
+    }
+
+    fn render_drivers(&self, out: &mut String, session: &session::Session, now: DateTime<Utc>) {
         let drivers = session.drivers.all();
         if drivers.is_empty() {
             return;
         }
 
-        let current = match &session.state {
-            State::Working { driver } => Some(driver),
+        let (current, is_driving) = match &session.state {
+            State::Working { driver } => (Some(driver), true),
             State::WaitingForNext {
                 next: Some(next), ..
-            } => Some(next),
-            _ => None,
+            } => (Some(next), false),
+            _ => (None, false),
+        };
+        let today = self.clock.local(now).naive_local().date();
+        let etas = eta::estimate(session, now);
+        let next_turn = |driver: &str| {
+            etas.iter()
+                .find(|eta| eta.driver == driver)
+                .map(|eta| eta::format(eta.wait))
+        };
+        let rows: Vec<(&str, &String, String, usize)> = drivers
+            .iter()
+            .map(|driver| {
+                let prefix = match current {
+                    Some(name) if name == driver => self.palette.marker(is_driving),
+                    _ => " ",
+                };
+                let turns = session
+                    .history
+                    .records()
+                    .iter()
+                    .filter(|record| match record {
+                        session::Record::Turn { driver: name, at } => {
+                            name == driver && self.clock.local(*at).naive_local().date() == today
+                        }
+                        _ => false,
+                    })
+                    .count();
+                // Greyed out while the rotation skips them
+                let name = match session.away.until(driver, now) {
+                    Some(until) => self
+                        .palette
+                        .paint(
+                            Role::Muted,
+                            format!(
+                                "{} (away until {})",
+                                driver,
+                                self.clock.local(until).format("%H:%M")
+                            ),
+                        )
+                        .to_string(),
+                    None => driver.clone(),
+                };
+                (prefix, driver, name, turns)
+            })
+            .collect();
+        let last = |driver: &str| {
+            session
+                .history
+                .last_driven(driver)
+                .map(|at| duration::ago(duration::elapsed(at, now)))
+        };
+
+        writeln!(out, "\n👯 Drivers ({}):", drivers.len()).unwrap();
+        let warn_drivers = self.config.limits.warn_drivers;
+        if drivers.len() > warn_drivers {
+            let help = format!(
+                "Mobs of more than {} tend to slow down, consider splitting up",
+                warn_drivers
+            );
+            writeln!(out, "   {}", self.palette.paint(Role::Muted, &help)).unwrap();
+        }
+        let mut header = vec!["Driver".into(), "Last drove".into(), "Today".into()];
+        if !etas.is_empty() {
+            header.push("Next turn".into());
+        }
+        let table = rows.iter().fold(
+            Table::new().row(header),
+            |table, (_, driver, name, turns)| {
+                let mut row = vec![
+                    name.clone(),
+                    last(driver).unwrap_or_else(|| "never".into()),
+                    turns.to_string(),
+                ];
+                if !etas.is_empty() {
+                    row.push(next_turn(driver).unwrap_or_default());
+                }
+                table.row(row)
+            },
+        );
+        // The marker and its spaces come before the table
+        let lines = if self.config.display.plain {
+            None
+        } else {
+            table.lines(self.width.map(|width| width.saturating_sub(3)))
+        };
+        match lines {
+            Some(lines) => {
+                writeln!(out, "   {}", self.palette.paint(Role::Muted, &lines[0])).unwrap();
+                for ((prefix, ..), line) in rows.iter().zip(&lines[1..]) {
+                    writeln!(
+                        out,
+                        " {} {}",
+                        self.palette.paint(Role::Marker, prefix),
+                        line
+                    )
+                    .unwrap();
+                }
+            }
+            None => {
+                for (prefix, driver, name, turns) in &rows {
+                    let last = match last(driver) {
+                        Some(ago) => format!("drove {}", ago),
+                        None => "never drove".into(),
+                    };
+                    let mut turns = match turns {
+                        1 => "1 turn today".to_string(),
+                        turns => format!("{} turns today", turns),
+                    };
+                    if let Some(next) = next_turn(driver) {
+                        turns.push_str(&format!(", next turn {}", next));
+                    }
+                    writeln!(
+                        out,
+                        " {} {}, {}, {}",
+                        self.palette.paint(Role::Marker, prefix),
+                        name,
+                        last,
+                        turns
+                    )
+                    .unwrap();
+                }
+            }
+        }
+    }
+}
+
+/// What is left of the turn at `now`, negative in overtime. It started on the clock of the
+/// driver, when that one runs ahead of ours the turn counts as just started.
+pub(super) fn turn_left(times: &session::Times, now: DateTime<Utc>) -> Option<Duration> {
+    let turn = times.turn_minutes?.duration();
+    Some(turn - duration::elapsed(times.turn_started?, now))
+}
+
+/// What local changes mean for the session
+#[derive(Debug, PartialEq)]
+enum Tree {
+    Clean,
+    /// The changes of the turn I'm driving
+    Driving,
+    /// Somebody else drives, my changes will conflict with theirs
+    Collides(String),
+    /// I'm next and `mob start` needs a clean tree
+    BeforeStart,
+    /// Left over while nobody needs the tree
+    Stray,
+}
+
+fn classify(state: &State, me: &str, dirty: bool) -> Tree {
+    if !dirty {
+        return Tree::Clean;
+    }
+    match state {
+        State::Working { driver } if driver == me => Tree::Driving,
+        State::Working { driver } => Tree::Collides(driver.clone()),
+        State::WaitingForNext { next: Some(next) } if next == me => Tree::BeforeStart,
+        State::WaitingForNext { next: None } | State::Paused { .. } => Tree::BeforeStart,
+        State::WaitingForNext { .. } | State::Stopped => Tree::Stray,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::FakeClock;
+    use crate::git::fake::{FakeGit, MemoryStore};
+    use crate::palette::PaletteKind;
+    use crate::session::Store as _;
+
+    #[cfg(feature = "color")]
+    fn render(palette: PaletteKind) -> String {
+        let git = FakeGit::default();
+        let memory = MemoryStore::default();
+        let store = session::SessionStore::new(&memory);
+        let mut config = Config {
+            name: "alice".into(),
+            ..Config::default()
         };
+        config.display.palette = palette;
+
+        let clock = FakeClock::default();
+        let mut status = Status::new(
+            StatusOpts::parse_from(["status"]),
+            &git,
+            &store,
+            &clock,
+            config,
+        );
+        status.palette = status.palette.force_styling(true);
+        status.width = None;
+
+        status.render(
+            &session::Session {
+                drivers: session::Drivers::new(vec!["alice".into(), "bob".into()]),
+                state: State::Working {
+                    driver: "bob".into(),
+                },
+                ..session::Session::default()
+            },
+            &git::Snapshot::default(),
+        )
+    }
+
+    #[cfg(feature = "color")]
+    #[test]
+    fn default_palette() {
+        assert_eq!(
+            render(PaletteKind::Default),
+            "🚗 bob is \u{1b}[32mdriving\u{1b}[0m
+   \u{1b}[36mRun 'mob next' when finished\u{1b}[0m
+
+🚚 working on \u{1b}[31m\u{1b}[1mmob-session\u{1b}[0m with parent \u{1b}[36m\u{1b}[1mmaster\u{1b}[0m
 
-        println!("\n👯 Drivers:");
-        for driver in session.drivers.all() {
-            let prefix = match current {
-                Some(name) if name == &driver => "›",
-                _ => " ",
+👯 Drivers (2):
+   \u{1b}[2mDriver  Last drove  Today  Next turn\u{1b}[0m
+ \u{1b}[31m \u{1b}[0m alice   never       0      in ~10m
+ \u{1b}[31m›\u{1b}[0m bob     never       0      in ~20m
+"
+        );
+    }
+
+    #[cfg(feature = "color")]
+    #[test]
+    fn colorblind_palette() {
+        assert_eq!(
+            render(PaletteKind::Colorblind),
+            "🚗 bob is \u{1b}[38;5;214mdriving\u{1b}[0m
+   \u{1b}[38;5;117mRun 'mob next' when finished\u{1b}[0m
+
+🚚 working on \u{1b}[38;5;175m\u{1b}[1mmob-session\u{1b}[0m with parent \u{1b}[38;5;117m\u{1b}[1mmaster\u{1b}[0m
+
+👯 Drivers (2):
+   \u{1b}[2mDriver  Last drove  Today  Next turn\u{1b}[0m
+ \u{1b}[38;5;214m \u{1b}[0m alice   never       0      in ~10m
+ \u{1b}[38;5;214m›\u{1b}[0m bob     never       0      in ~20m
+"
+        );
+    }
+
+    #[cfg(feature = "color")]
+    #[test]
+    fn mono_palette() {
+        assert_eq!(
+            render(PaletteKind::Mono),
+            "🚗 bob is \u{1b}[1mdriving\u{1b}[0m
+   Run 'mob next' when finished
+
+🚚 working on \u{1b}[1mmob-session\u{1b}[0m with parent master
+
+👯 Drivers (2):
+   \u{1b}[2mDriver  Last drove  Today  Next turn\u{1b}[0m
+ \u{1b}[1m \u{1b}[0m alice   never       0      in ~10m
+ \u{1b}[1m▶\u{1b}[0m bob     never       0      in ~20m
+"
+        );
+    }
+
+    #[test]
+    fn unpushed_commits() {
+        let git = FakeGit::default();
+        let memory = MemoryStore::default();
+        let store = session::SessionStore::new(&memory);
+        let clock = FakeClock::default();
+        let status = Status::new(
+            StatusOpts::parse_from(["status"]),
+            &git,
+            &store,
+            &clock,
+            Config::default(),
+        );
+        let session = session::Session {
+            state: State::Working {
+                driver: "bob".into(),
+            },
+            ..session::Session::default()
+        };
+        let on_branch = |branch: &str| git::Snapshot {
+            branch: Some(branch.into()),
+            ahead: 2,
+            ..git::Snapshot::default()
+        };
+
+        assert!(status
+            .render(&session, &on_branch("mob-session"))
+            .contains("2 local commits not pushed yet"));
+        assert!(!status
+            .render(&session, &on_branch("master"))
+            .contains("not pushed"));
+    }
+
+    #[test]
+    fn unrelated_histories() {
+        let orphan = FakeGit {
+            merge_base: None,
+            ..FakeGit::default()
+        };
+        let related = FakeGit::default();
+        let memory = MemoryStore::default();
+        let store = session::SessionStore::new(&memory);
+        let session = session::Session {
+            state: State::Working {
+                driver: "bob".into(),
+            },
+            ..session::Session::default()
+        };
+        let render = |git: &FakeGit, branch: &str| {
+            let clock = FakeClock::default();
+            let status = Status::new(
+                StatusOpts::parse_from(["status"]),
+                git,
+                &store,
+                &clock,
+                Config::default(),
+            );
+            let snapshot = git::Snapshot {
+                branch: Some(branch.into()),
+                ..git::Snapshot::default()
             };
+            status.render(&session, &snapshot)
+        };
+        let note = "Shares no history with master, mob done needs --allow-unrelated-histories";
+        assert!(render(&orphan, "mob-session").contains(note));
+        assert!(!render(&orphan, "master").contains("no history"));
+        assert!(!render(&related, "mob-session").contains("no history"));
+    }
+
+    #[test]
+    fn repositories_of_a_workspace() {
+        let on = |branch: &str, changed: usize| FakeGit {
+            snapshot: git::Snapshot {
+                branch: Some(branch.into()),
+                changed,
+                ..git::Snapshot::default()
+            },
+            ..FakeGit::default()
+        };
+        let (api, web) = (on("mob-session", 0), on("main", 2));
+        let workspace = git::workspace::Workspace::new(("api", &api), vec![("web", &web)]);
+        let memory = MemoryStore::default();
+        let store = session::SessionStore::new(&memory);
+        let clock = FakeClock::default();
+        let status = Status::new(
+            StatusOpts::parse_from(["status"]),
+            &workspace,
+            &store,
+            &clock,
+            Config::default(),
+        );
+        let mut session = session::Session {
+            state: State::Working {
+                driver: "bob".into(),
+            },
+            ..session::Session::default()
+        };
+        session.tips.insert("api".into(), "1a2b3c4d5e6f".into());
+        let rendered = status.render(&session, &api.snapshot);
+        assert!(
+            rendered
+                .contains("\n📦 api on mob-session at 1a2b3c4\n   web on main, 2 changed files\n"),
+            "{}",
+            rendered
+        );
+
+        // Without a workspace there is the one repository
+        let status = Status::new(
+            StatusOpts::parse_from(["status"]),
+            &api,
+            &store,
+            &clock,
+            Config::default(),
+        );
+        assert!(!status.render(&session, &api.snapshot).contains("📦"));
+    }
+
+    #[test]
+    fn quotes_a_driver_named_anyone() {
+        let fake = FakeGit::default();
+        let memory = MemoryStore::default();
+        let store = session::SessionStore::new(&memory);
+        let clock = FakeClock::default();
+        let status = Status::new(
+            StatusOpts::parse_from(["status"]),
+            &fake,
+            &store,
+            &clock,
+            Config::default(),
+        );
+        let waiting = |next: Option<&str>| session::Session {
+            state: State::WaitingForNext {
+                next: next.map(String::from),
+            },
+            ..session::Session::default()
+        };
+        let snapshot = git::Snapshot::default();
+        assert!(status
+            .render(&waiting(Some("anyone")), &snapshot)
+            .starts_with("💤 Waiting for \"anyone\" to run 'mob start'\n"));
+        assert!(status
+            .render(&waiting(None), &snapshot)
+            .starts_with("💤 Waiting for Anyone to run 'mob start'\n"));
+        let driving = session::Session {
+            state: State::Working {
+                driver: "anyone".into(),
+            },
+            ..session::Session::default()
+        };
+        assert!(status
+            .render(&driving, &snapshot)
+            .starts_with("🚗 \"anyone\" is driving"));
+    }
 
-            println!(" {} {}", style(prefix).red(), driver);
+    #[test]
+    fn warns_of_turns_handed_over_offline() {
+        use crate::git::Git as _;
+        let dir = tempfile::tempdir().unwrap();
+        let state_dir = StateDir::new(dir.path().to_path_buf());
+        let fake = FakeGit::default();
+        let memory = MemoryStore::default();
+        let store = session::SessionStore::new(&memory);
+        let clock = FakeClock::default();
+        let status = || {
+            Status::new(
+                StatusOpts::parse_from(["status"]),
+                &fake,
+                &store,
+                &clock,
+                Config::default(),
+            )
+            .state_dir(state_dir.clone())
+        };
+        let session = session::Session::default();
+        let snapshot = git::Snapshot::default();
+        assert!(!status().render(&session, &snapshot).contains("ahead"));
+
+        let queue = git::queue::Queue::new(
+            &fake,
+            &memory,
+            state_dir.clone(),
+            "origin",
+            git::queue::Mode::Offline,
+        );
+        queue
+            .run(&["push", "--no-verify", "origin", "--", "mob-session"])
+            .unwrap();
+        let rendered = status().render(&session, &snapshot);
+        assert!(
+            rendered.contains(
+                "\n⚠️  This clone is ahead of the remote: mob-session\n   \
+                 Run 'mob sync' once online to push it\n"
+            ),
+            "{}",
+            rendered
+        );
+    }
+
+    #[test]
+    fn classify_local_changes() {
+        let working = |driver: &str| State::Working {
+            driver: driver.into(),
+        };
+        let waiting = |next: Option<&str>| State::WaitingForNext {
+            next: next.map(String::from),
+        };
+        let cases = vec![
+            (State::Stopped, false, Tree::Clean),
+            (working("bob"), false, Tree::Clean),
+            (waiting(Some("alice")), false, Tree::Clean),
+            (State::Stopped, true, Tree::Stray),
+            (working("alice"), true, Tree::Driving),
+            (working("bob"), true, Tree::Collides("bob".into())),
+            (waiting(Some("alice")), true, Tree::BeforeStart),
+            (waiting(None), true, Tree::BeforeStart),
+            (waiting(Some("bob")), true, Tree::Stray),
+            (
+                State::Paused {
+                    reason: "an emergency".into(),
+                    driver: None,
+                    remaining: None,
+                },
+                true,
+                Tree::BeforeStart,
+            ),
+        ];
+        for (state, dirty, expected) in cases {
+            assert_eq!(
+                classify(&state, "alice", dirty),
+                expected,
+                "{:?} {}",
+                state,
+                dirty
+            );
         }
     }
+
+    #[test]
+    fn local_changes() {
+        let git = FakeGit::default();
+        let memory = MemoryStore::default();
+        let store = session::SessionStore::new(&memory);
+        let config = Config {
+            name: "alice".into(),
+            ..Config::default()
+        };
+        let clock = FakeClock::default();
+        let status = Status::new(
+            StatusOpts::parse_from(["status"]),
+            &git,
+            &store,
+            &clock,
+            config,
+        );
+        let render = |state: State| {
+            let mut out = String::new();
+            let snapshot = git::Snapshot {
+                changed: 3,
+                untracked: 1,
+                ..git::Snapshot::default()
+            };
+            status.render_tree(&mut out, &state, &snapshot);
+            out
+        };
+
+        assert_eq!(
+            render(State::Working {
+                driver: "bob".into()
+            }),
+            "\n📝 Your working tree has 4 changed files not part of the session, they will collide with bob's turn
+   Run 'git stash' to put them aside
+"
+        );
+        assert!(render(State::WaitingForNext {
+            next: Some("alice".into())
+        })
+        .contains("then 'mob start'"));
+        assert_eq!(
+            render(State::Working {
+                driver: "alice".into()
+            }),
+            "\n📝 4 changed files, mob next hands them over\n"
+        );
+        assert_eq!(
+            render(State::Stopped),
+            "\n📝 Your working tree has 4 changed files not part of the session\n"
+        );
+    }
+
+    #[test]
+    fn break_until() {
+        let git = FakeGit::default();
+        let memory = MemoryStore::default();
+        let store = session::SessionStore::new(&memory);
+        let clock = FakeClock::default();
+        let status = Status::new(
+            StatusOpts::parse_from(["status"]),
+            &git,
+            &store,
+            &clock,
+            Config::default(),
+        );
+        let now = clock.now_utc();
+        let end = now + chrono::Duration::minutes(42);
+        let render = |times: session::Times| {
+            let mut out = String::new();
+            status.render_break(&mut out, &times, now);
+            out
+        };
+
+        assert_eq!(
+            render(session::Times {
+                last_lunch: Some(now.into()),
+                break_until: Some(end.into()),
+                ..session::Times::default()
+            }),
+            format!(
+                "\n☕ Lunch time until {}, 42 minutes left\n",
+                clock.local(end).format("%H:%M")
+            )
+        );
+        assert!(render(session::Times {
+            last_break: Some(now.into()),
+            break_until: Some(end.into()),
+            ..session::Times::default()
+        })
+        .contains("On a break"));
+        assert_eq!(
+            render(session::Times {
+                break_until: Some(now.into()),
+                ..session::Times::default()
+            }),
+            ""
+        );
+    }
+
+    #[test]
+    fn turn_remaining() {
+        let git = FakeGit::default();
+        let memory = MemoryStore::default();
+        let store = session::SessionStore::new(&memory);
+        let clock = FakeClock::default();
+        let mut status = Status::new(
+            StatusOpts::parse_from(["status"]),
+            &git,
+            &store,
+            &clock,
+            Config::default(),
+        );
+        status.palette = Palette::new(PaletteKind::Mono);
+        let now = clock.now_utc();
+        let driving = |started: i64| {
+            let mut out = String::new();
+            let session = session::Session {
+                state: State::Working {
+                    driver: "bob".into(),
+                },
+                times: session::Times {
+                    turn_started: Some((now - chrono::Duration::seconds(started)).into()),
+                    turn_minutes: Some(Minutes(15)),
+                    ..session::Times::default()
+                },
+                ..session::Session::default()
+            };
+            status.render_status(&mut out, &session, &git::Snapshot::default());
+            out.lines().next().unwrap().to_string()
+        };
+
+        assert_eq!(driving(150), "🚗 bob is driving, 13 min remaining");
+        assert_eq!(driving(15 * 60), "🚗 bob is driving, time is up");
+        assert_eq!(
+            driving(18 * 60 + 30),
+            "🚗 bob is driving, overtime by 3 min"
+        );
+        // Started on a clock that is ahead of ours
+        assert_eq!(driving(-120), "🚗 bob is driving, 15 min remaining");
+    }
+
+    #[test]
+    fn moved_outside_mob() {
+        let git = FakeGit::default();
+        let memory = MemoryStore::default();
+        let store = session::SessionStore::new(&memory);
+        let clock = FakeClock::default();
+        let mut status = Status::new(
+            StatusOpts::parse_from(["status", "--fetch"]),
+            &git,
+            &store,
+            &clock,
+            Config::default(),
+        );
+        status.palette = Palette::new(PaletteKind::Mono);
+        let moved = session::outside::Moved {
+            tip: "b2".into(),
+            authors: vec!["dave".into(), "dave".into()],
+        };
+        assert_eq!(
+            status.render_moved(&moved),
+            "\n⚠️  While nobody drove the branch moved outside mob (2 new commits by dave)
+   The next mob command offers to take the commits into the session
+"
+        );
+    }
+
+    #[test]
+    fn lists_sessions() {
+        let git = FakeGit::default();
+        let memory = MemoryStore::default();
+        let store = session::SessionStore::new(&memory);
+        let clock = FakeClock::default();
+        let working = session::Session {
+            state: State::Working {
+                driver: "bob".into(),
+            },
+            ..session::Session::default()
+        };
+        let mut status = Status::new(
+            StatusOpts::parse_from(["status"]),
+            &git,
+            &store,
+            &clock,
+            Config::default(),
+        )
+        .sessions(vec![
+            ("default".into(), session::Session::default()),
+            ("payments".into(), working),
+        ]);
+        status.palette = Palette::new(PaletteKind::Mono);
+        assert_eq!(
+            status.render_sessions(),
+            "📚 2 sessions in this repository:
+   default   stopped
+   payments  bob is driving
+   Run 'mob --session <name> status' for one of them
+"
+        );
+    }
+
+    #[test]
+    fn claimed() {
+        let git = FakeGit::default();
+        let memory = MemoryStore::default();
+        let store = session::SessionStore::new(&memory);
+        let clock = FakeClock::default();
+        let status = Status::new(
+            StatusOpts::parse_from(["status"]),
+            &git,
+            &store,
+            &clock,
+            Config::default(),
+        );
+        let mut session = session::Session {
+            state: State::WaitingForNext {
+                next: Some("bob".into()),
+            },
+            ..session::Session::default()
+        };
+        session.times.claimed = Some(clock.now() - chrono::Duration::minutes(3));
+        let mut out = String::new();
+        status.render_status(&mut out, &session, &git::Snapshot::default());
+        assert!(
+            out.starts_with(
+                "💤 Waiting for bob to run 'mob start'
+   Claimed 3 minutes ago, 'mob unclaim' lets anyone start
+"
+            ),
+            "{}",
+            out
+        );
+    }
+
+    #[test]
+    fn reserved() {
+        let git = FakeGit::default();
+        let memory = MemoryStore::default();
+        let store = session::SessionStore::new(&memory);
+        let clock = FakeClock::default();
+        let mut config = Config::default();
+        config.handover.reassign_after = Some(Minutes(2));
+        let status = Status::new(
+            StatusOpts::parse_from(["status"]),
+            &git,
+            &store,
+            &clock,
+            config,
+        );
+        let mut session = session::Session {
+            state: State::WaitingForNext {
+                next: Some("bob".into()),
+            },
+            ..session::Session::default()
+        };
+        session.times.waiting_since = Some(clock.now() - chrono::Duration::seconds(40));
+        let render = |session: &session::Session| {
+            let mut out = String::new();
+            status.render_status(&mut out, session, &git::Snapshot::default());
+            out
+        };
+        assert!(render(&session)
+            .contains("The turn is reserved for bob (1m 20s left), then anyone may start"));
+
+        clock.advance(chrono::Duration::minutes(2));
+        assert!(!render(&session).contains("reserved"));
+    }
+
+    fn status_json(session: session::Session) -> serde_json::Value {
+        let memory = MemoryStore::default();
+        let store = session::SessionStore::new(&memory);
+        store.save(session).unwrap();
+        let mut opts = StatusOpts::parse_from(["status"]);
+        opts.json = true;
+        warnings::take_status();
+        Status::new(
+            opts,
+            &FakeGit::default(),
+            &store,
+            &FakeClock::default(),
+            Config::default(),
+        )
+        .run()
+        .unwrap();
+        let report: serde_json::Value = serde_json::from_str(&warnings::to_json(
+            &[],
+            None,
+            warnings::take_status().as_ref(),
+        ))
+        .unwrap();
+        report["status"].clone()
+    }
+
+    #[test]
+    fn reports_json() {
+        let clock = FakeClock::default();
+        let working = session::Session {
+            id: Some("3f2a9c1e7b04".into()),
+            state: State::Working {
+                driver: "alice".into(),
+            },
+            drivers: session::Drivers::new(vec!["alice".into(), "bob".into()]),
+            settings: Some(session::Settings {
+                break_interval: Some(Minutes(55)),
+                ..session::Settings::default()
+            }),
+            times: session::Times {
+                last_break: Some(clock.now()),
+                ..session::Times::default()
+            },
+            ..session::Session::default()
+        };
+        let status = status_json(working.clone());
+        assert_eq!(status["id"], "3f2a9c1e7b04");
+        assert_eq!(status["state"], "working");
+        assert_eq!(status["driver"], "alice");
+        assert_eq!(status["next_driver"], "bob");
+        assert_eq!(status["drivers"], serde_json::json!(["alice", "bob"]));
+        assert_eq!(
+            status["next_turns"],
+            serde_json::json!([
+                {"driver": "bob", "in_seconds": 600},
+                {"driver": "alice", "in_seconds": 1200},
+            ])
+        );
+        assert_eq!(status["branch"], working.branches.branch.as_str());
+        assert_eq!(
+            status["last_break"],
+            serde_json::to_value(clock.now_utc()).unwrap()
+        );
+        assert_eq!(status["settings"]["break_interval"], 55);
+        assert_eq!(
+            status["settings"]["work_duration"],
+            working.settings.unwrap().work_duration.get()
+        );
+
+        let stopped = status_json(session::Session {
+            settings: None,
+            ..session::Session::default()
+        });
+        assert_eq!(stopped["state"], "stopped");
+        assert!(stopped["id"].is_null());
+        assert!(stopped["driver"].is_null());
+        assert!(stopped["next_driver"].is_null());
+        assert!(stopped["settings"].is_null());
+        assert!(stopped["last_break"].is_null());
+        assert_eq!(stopped["next_turns"], serde_json::json!([]));
+
+        let waiting = status_json(session::Session {
+            state: State::WaitingForNext { next: None },
+            ..session::Session::default()
+        });
+        assert_eq!(waiting["state"], "waiting_for_next");
+        assert!(waiting["next_driver"].is_null());
+    }
+
+    #[test]
+    fn raw_is_deprecated() {
+        for json in [true, false] {
+            warnings::take();
+            let git = FakeGit::default();
+            let memory = MemoryStore::default();
+            let store = session::SessionStore::new(&memory);
+            let mut opts = StatusOpts::parse_from(["status", "--raw"]);
+            opts.json = json;
+            let clock = FakeClock::default();
+            Status::new(opts, &git, &store, &clock, Config::default())
+                .run()
+                .unwrap();
+
+            let collected = warnings::take();
+            assert_eq!(collected.len(), 1);
+            assert_eq!(collected[0].code, "status-raw");
+            if json {
+                let report: serde_json::Value =
+                    serde_json::from_str(&warnings::to_json(&collected, None, None)).unwrap();
+                assert_eq!(report["warnings"].as_array().unwrap().len(), 1);
+            } else {
+                let palette = Palette::new(PaletteKind::Default).force_styling(true);
+                let lines = warnings::to_lines(&collected, &palette);
+                assert_eq!(lines.lines().count(), 1);
+                assert_eq!(lines.starts_with("\u{1b}[2m"), cfg!(feature = "color"));
+            }
+        }
+    }
+
+    fn drivers(width: Option<usize>, plain: bool) -> String {
+        drivers_with(width, plain, session::Away::default(), 6)
+    }
+
+    fn drivers_with(
+        width: Option<usize>,
+        plain: bool,
+        away: session::Away,
+        warn_drivers: usize,
+    ) -> String {
+        let git = FakeGit::default();
+        let memory = MemoryStore::default();
+        let store = session::SessionStore::new(&memory);
+        let mut config = Config {
+            name: "alice".into(),
+            ..Config::default()
+        };
+        config.limits.warn_drivers = warn_drivers;
+        config.display.plain = plain;
+        let clock = FakeClock::default();
+        let mut status = Status::new(
+            StatusOpts::parse_from(["status"]),
+            &git,
+            &store,
+            &clock,
+            config,
+        );
+        status.width = width;
+
+        let now = clock.now_utc();
+        let turn = |driver: &str, minutes| session::Record::Turn {
+            driver: driver.into(),
+            at: now - chrono::Duration::minutes(minutes),
+        };
+        let mut out = String::new();
+        status.render_drivers(
+            &mut out,
+            &session::Session {
+                drivers: session::Drivers::new(vec![
+                    "alice".into(),
+                    "李小龙".into(),
+                    "Zoë 🚲".into(),
+                ]),
+                state: State::Working {
+                    driver: "李小龙".into(),
+                },
+                history: session::History::new(vec![
+                    turn("alice", 3 * 24 * 60),
+                    turn("李小龙", 0),
+                    turn("李小龙", 0),
+                ]),
+                away,
+                ..session::Session::default()
+            },
+            now,
+        );
+        out
+    }
+
+    #[test]
+    fn drivers_table() {
+        assert_eq!(
+            drivers(None, false),
+            "
+👯 Drivers (3):
+   Driver  Last drove  Today  Next turn
+   alice   3 days ago  0      in ~20m
+ › 李小龙  just now    2      in ~30m
+   Zoë 🚲  never       0      in ~10m
+"
+        );
+        // Exactly as wide as the terminal
+        assert_eq!(drivers(Some(39), false), drivers(None, false));
+    }
+
+    #[test]
+    fn drivers_list_when_narrow_or_plain() {
+        let list = "
+👯 Drivers (3):
+   alice, drove 3 days ago, 0 turns today, next turn in ~20m
+ › 李小龙, drove just now, 2 turns today, next turn in ~30m
+   Zoë 🚲, never drove, 0 turns today, next turn in ~10m
+";
+        assert_eq!(drivers(Some(38), false), list);
+        assert_eq!(drivers(None, true), list);
+    }
+
+    #[test]
+    fn large_mob() {
+        let warned = drivers_with(None, false, session::Away::default(), 2);
+        assert!(warned.starts_with(
+            "\n👯 Drivers (3):\n   Mobs of more than 2 tend to slow down, consider splitting up\n"
+        ));
+        assert!(!drivers(None, false).contains("slow down"));
+    }
+
+    #[test]
+    fn away_drivers() {
+        let clock = FakeClock::default();
+        let now = clock.now_utc();
+        let until = now + chrono::Duration::hours(1);
+        let session = session::Session {
+            drivers: session::Drivers::new(vec!["alice".into(), "Zoë 🚲".into()]),
+            ..session::Session::default()
+        };
+        let away = session::away::transition(
+            session,
+            "Zoë 🚲",
+            session::away::Change::Away { until },
+            now,
+        )
+        .unwrap()
+        .away;
+        let back = clock.local(until).format("%H:%M");
+        assert!(
+            drivers_with(None, false, away.clone(), 6).contains(&format!(
+                "   Zoë 🚲 (away until {})  never       0      \n",
+                back
+            ))
+        );
+        assert!(drivers_with(None, true, away, 6)
+            .contains(&format!("   Zoë 🚲 (away until {}), never drove", back)));
+    }
 }
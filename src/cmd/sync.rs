@@ -0,0 +1,165 @@
+use super::push;
+use crate::{clock::Clock, config::Config, deadline, git, session};
+use anyhow::{anyhow, Result};
+use session::{roles, State};
+
+/// Pushes the work of the turn as `mob next` would, but the turn, the timer and the rotation
+/// go on. For showing a remote teammate something mid-turn.
+pub struct Sync<'a> {
+    git: &'a dyn git::Git,
+    store: &'a dyn session::Store,
+    clock: &'a dyn Clock,
+    config: Config,
+    interactive: bool,
+}
+
+impl<'a> Sync<'a> {
+    pub fn new(
+        git: &'a impl git::Git,
+        store: &'a impl session::Store,
+        clock: &'a impl Clock,
+        config: Config,
+    ) -> Sync<'a> {
+        Self {
+            git,
+            store,
+            clock,
+            config,
+            interactive: deadline::attended(),
+        }
+    }
+
+    pub fn run(&self) -> Result<()> {
+        let me = &self.config.name;
+
+        let session = self.store.load()?;
+        roles::check(&session, me, roles::Action::Next)?;
+        match &session.state {
+            State::Working { driver } if driver == me.as_str() => self.sync(session),
+            State::Working { driver } => {
+                log::warn!("The current driver is {}, only they can sync", driver);
+                Ok(())
+            }
+            _ => {
+                log::warn!("Nobody is driving, run mob start");
+                Ok(())
+            }
+        }
+    }
+
+    fn sync(&self, session: session::Session) -> Result<()> {
+        if !self.git.is_repository() {
+            return Err(anyhow!("This session has no repository to sync"));
+        }
+        let mut new_files = session.new_files.clone();
+        let pushed = push::commit_turn(
+            self.git,
+            &self.config,
+            &session.branches.branch,
+            &session.settings.as_ref().unwrap().commit_message,
+            &mut new_files,
+            self.interactive,
+        )?;
+        if !pushed {
+            // What was said about new files that were all left out counts still
+            if new_files != session.new_files {
+                self.store.save(session::Session {
+                    new_files,
+                    ..session
+                })?;
+            }
+            return Ok(());
+        }
+
+        log::info!(
+            "Pushed to {}/{}, it's still your turn",
+            self.config.remote,
+            session.branches.branch
+        );
+        self.store.save(session::Session {
+            history: session.history.push(session::Record::Sync {
+                driver: self.config.name.clone(),
+                at: self.clock.now_utc(),
+            }),
+            new_files,
+            ..session
+        })?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::FakeClock;
+    use crate::cmd::fixtures::{alice, dirty, working};
+    use crate::cmd::{Next, NextOpts};
+    use crate::git::fake::{FakeGit, MemoryStore};
+    use crate::session::Store;
+    use crate::timer::fake::NoTimer;
+    use clap::Clap;
+
+    fn next() -> NextOpts {
+        NextOpts::parse_from(["next"])
+    }
+
+    #[test]
+    fn pushes_and_keeps_driving() {
+        let git = dirty();
+        let memory = MemoryStore::default();
+        let store = session::SessionStore::new(&memory);
+        store.save(working()).unwrap();
+        let clock = FakeClock::default();
+        Sync::new(&git, &store, &clock, alice()).run().unwrap();
+
+        let session = store.load().unwrap();
+        assert_eq!(session.state, working().state);
+        assert_eq!(session.times, working().times);
+        assert_eq!(session.drivers, working().drivers);
+        assert_eq!(
+            session.history.records(),
+            &[session::Record::Sync {
+                driver: "alice".into(),
+                at: clock.now_utc(),
+            }]
+        );
+
+        // The same commit and push as mob next
+        let next_git = dirty();
+        let memory = MemoryStore::default();
+        let next_store = session::SessionStore::new(&memory);
+        next_store.save(working()).unwrap();
+        Next::new(&next_git, &next_store, &NoTimer, &clock, next(), alice())
+            .run()
+            .unwrap();
+        assert_eq!(git.calls(), next_git.calls());
+        assert!(git
+            .calls()
+            .contains(&"push --no-verify origin -- mob-session".to_string()));
+    }
+
+    #[test]
+    fn only_the_driver_syncs() {
+        let git = dirty();
+        let memory = MemoryStore::default();
+        let store = session::SessionStore::new(&memory);
+        store.save(working()).unwrap();
+        let bob = Config {
+            name: "bob".into(),
+            ..Config::default()
+        };
+        Sync::new(&git, &store, &FakeClock::default(), bob)
+            .run()
+            .unwrap();
+        assert!(git.calls().is_empty());
+        assert_eq!(store.load().unwrap(), working());
+
+        // Nothing changed, nothing is recorded
+        let clean = FakeGit::default();
+        Sync::new(&clean, &store, &FakeClock::default(), alice())
+            .run()
+            .unwrap();
+        assert!(clean.calls().is_empty());
+        assert_eq!(store.load().unwrap(), working());
+    }
+}
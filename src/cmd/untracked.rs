@@ -0,0 +1,183 @@
+//! Keeping new files the driver only made for themselves, like notes or debug scripts, out
+//! of the commits of a turn as `next.untracked` says. What `ask` was told is kept in the
+//! session.
+
+use crate::config::Untracked;
+use crate::git::Git;
+use crate::session::NewFiles;
+use crate::{prompt, record};
+use anyhow::Result;
+
+const QUESTION: &str = "Which new files go into the turn?";
+
+/// Stages the changes of the turn, of the `new` files only those `mode` and what the mob
+/// said before in `new_files` let in. What was said now is added to it. The new files left
+/// out.
+pub(super) fn stage(
+    git: &dyn Git,
+    mode: Untracked,
+    new: &[String],
+    new_files: &mut NewFiles,
+    interactive: bool,
+) -> Result<Vec<String>> {
+    let (included, decided) = decide(mode, new, new_files.clone(), |unknown| {
+        if !interactive {
+            log::info!(
+                "Not asking about the new files {}, leaving them out as with \
+                 next.untracked = \"exclude\"",
+                unknown.join(", ")
+            );
+            return Ok(None);
+        }
+        let items: Vec<&str> = unknown.iter().map(String::as_str).collect();
+        let checked =
+            prompt::terminal().multi_select(QUESTION, &items, &vec![true; items.len()])?;
+        let chosen: Vec<String> = checked
+            .into_iter()
+            .map(|index| unknown[index].clone())
+            .collect();
+        Ok(Some(record::answer(QUESTION, chosen)))
+    })?;
+    *new_files = decided;
+    for args in commands(included.as_deref()) {
+        git.run(&args)?;
+    }
+    let left_out: Vec<String> = match included {
+        Some(included) => new
+            .iter()
+            .filter(|file| !included.contains(file))
+            .cloned()
+            .collect(),
+        None => vec![],
+    };
+    if !left_out.is_empty() {
+        log::info!(
+            "Leaving the new files {} out of the turn",
+            left_out.join(", ")
+        );
+    }
+    Ok(left_out)
+}
+
+/// Which of the `new` files go into the turn, `None` for all of them, and what the mob said
+/// about them. `ask` is given the files nothing was said about yet and tells which of them to
+/// include, `None` when nobody could answer.
+fn decide(
+    mode: Untracked,
+    new: &[String],
+    new_files: NewFiles,
+    ask: impl FnOnce(&[String]) -> Result<Option<Vec<String>>>,
+) -> Result<(Option<Vec<String>>, NewFiles)> {
+    let unknown = new_files.unknown(new);
+    let new_files = match mode {
+        Untracked::Include => return Ok((None, new_files)),
+        Untracked::Ask if !unknown.is_empty() => match ask(&unknown)? {
+            Some(included) => new_files.decide(&unknown, &included),
+            None => new_files,
+        },
+        Untracked::Ask | Untracked::Exclude => new_files,
+    };
+    Ok((Some(new_files.included(new)), new_files))
+}
+
+/// The git commands staging a turn with all new files, or only the `included` ones
+fn commands(included: Option<&[String]>) -> Vec<Vec<&str>> {
+    let included = match included {
+        Some(included) => included,
+        None => return vec![vec!["add", "--all"]],
+    };
+    let mut commands = vec![vec!["add", "--update"]];
+    if !included.is_empty() {
+        let mut add = vec!["add", "--"];
+        add.extend(included.iter().map(String::as_str));
+        commands.push(add);
+    }
+    commands
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::fake::FakeGit;
+    use anyhow::anyhow;
+
+    fn files(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    fn not_asked(_: &[String]) -> Result<Option<Vec<String>>> {
+        Err(anyhow!("asked"))
+    }
+
+    #[test]
+    fn stages_as_the_mode_says() {
+        assert_eq!(commands(None), vec![vec!["add", "--all"]]);
+        assert_eq!(commands(Some(&[])), vec![vec!["add", "--update"]]);
+        assert_eq!(
+            commands(Some(&files(&["src/parser.rs", "dir with space/"]))),
+            vec![
+                vec!["add", "--update"],
+                vec!["add", "--", "src/parser.rs", "dir with space/"]
+            ]
+        );
+    }
+
+    #[test]
+    fn includes_all_or_what_was_picked() {
+        let new = files(&["notes.txt", "src/parser.rs"]);
+        let picked = NewFiles::default().decide(&files(&["src/parser.rs"]), &new);
+
+        let (included, _) = decide(Untracked::Include, &new, picked.clone(), not_asked).unwrap();
+        assert_eq!(included, None);
+        let (included, _) = decide(Untracked::Exclude, &new, picked.clone(), not_asked).unwrap();
+        assert_eq!(included, Some(files(&["src/parser.rs"])));
+        let (included, _) =
+            decide(Untracked::Exclude, &new, NewFiles::default(), not_asked).unwrap();
+        assert_eq!(included, Some(vec![]));
+    }
+
+    #[test]
+    fn asks_once_about_each_new_file() {
+        let new = files(&["notes.txt", "src/parser.rs"]);
+        let (included, new_files) = decide(Untracked::Ask, &new, NewFiles::default(), |unknown| {
+            assert_eq!(unknown, &new[..]);
+            Ok(Some(files(&["src/parser.rs"])))
+        })
+        .unwrap();
+        assert_eq!(included, Some(files(&["src/parser.rs"])));
+        assert_eq!(
+            new_files.excluded.iter().collect::<Vec<_>>(),
+            vec!["notes.txt"]
+        );
+
+        // The next handover only asks about what is new since
+        let newer = files(&["notes.txt", "src/parser.rs", "debug.sh"]);
+        let (included, new_files) = decide(Untracked::Ask, &newer, new_files, |unknown| {
+            assert_eq!(unknown, &files(&["debug.sh"])[..]);
+            Ok(Some(vec![]))
+        })
+        .unwrap();
+        assert_eq!(included, Some(files(&["src/parser.rs"])));
+        let (_, again) = decide(Untracked::Ask, &newer, new_files.clone(), not_asked).unwrap();
+        assert_eq!(again, new_files);
+    }
+
+    #[test]
+    fn leaves_new_files_out_without_anyone_to_ask() {
+        let git = FakeGit::default();
+        let mut new_files = NewFiles::default();
+        let left_out = stage(
+            &git,
+            Untracked::Ask,
+            &files(&["notes.txt"]),
+            &mut new_files,
+            false,
+        )
+        .unwrap();
+
+        assert_eq!(left_out, files(&["notes.txt"]));
+        assert_eq!(git.calls(), vec!["add --update"]);
+        // Nothing was said, the next one to hand over is asked
+        assert!(new_files.is_empty());
+    }
+}
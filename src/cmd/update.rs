@@ -0,0 +1,96 @@
+use crate::clock::Clock;
+use crate::update::{self, Checker, Http};
+use anyhow::{anyhow, Result};
+use clap::{self, Clap};
+use std::path::PathBuf;
+
+#[derive(Clap, Debug)]
+pub struct UpdateOpts {
+    /// Ask for the latest release now, even when it was checked today
+    #[clap(long)]
+    check: bool,
+}
+
+/// Tells whether there is a newer mob and how to get it
+pub struct Update<'a> {
+    http: &'a dyn Http,
+    clock: &'a dyn Clock,
+    cache: Option<PathBuf>,
+    current: &'a str,
+    opts: UpdateOpts,
+}
+
+impl<'a> Update<'a> {
+    pub fn new(
+        http: &'a impl Http,
+        clock: &'a impl Clock,
+        cache: Option<PathBuf>,
+        current: &'a str,
+        opts: UpdateOpts,
+    ) -> Update<'a> {
+        Self {
+            http,
+            clock,
+            cache,
+            current,
+            opts,
+        }
+    }
+
+    pub fn run(self) -> Result<()> {
+        if !self.opts.check {
+            log::info!("Run cargo install remotemob to update, mob update --check tells if there is a newer mob");
+            return Ok(());
+        }
+        let cache = self
+            .cache
+            .ok_or_else(|| anyhow!("There is no cache directory to keep the check in"))?;
+        match Checker::new(self.http, cache, self.current).check(self.clock.now_utc(), true)? {
+            Some(newer) => {
+                log::info!("Run cargo install remotemob to update");
+                update::announce(newer);
+            }
+            None => log::info!("mob {} is the latest release", self.current),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::FakeClock;
+    use std::cell::RefCell;
+
+    struct Releases(RefCell<usize>);
+
+    impl Http for Releases {
+        fn get(&self, _: &str) -> Result<String> {
+            *self.0.borrow_mut() += 1;
+            Ok(r#"{"tag_name": "v0.5.0", "html_url": "https://github.com/Byron/mob/releases/tag/v0.5.0"}"#.into())
+        }
+    }
+
+    #[test]
+    fn checks_when_asked() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = Some(dir.path().join("update.json"));
+        let http = Releases(RefCell::new(0));
+        let clock = FakeClock::default();
+        let update = |args: &[&str]| {
+            let opts = UpdateOpts::parse_from(args);
+            Update::new(&http, &clock, cache.clone(), "0.4.1", opts).run()
+        };
+
+        update(&["update"]).unwrap();
+        assert_eq!(*http.0.borrow(), 0);
+        assert_eq!(update::take(), None);
+
+        for _ in 0..2 {
+            update(&["update", "--check"]).unwrap();
+            assert_eq!(update::take().unwrap().version, "0.5.0");
+        }
+        // Even after the last check, asking for it goes to GitHub again
+        assert_eq!(*http.0.borrow(), 2);
+    }
+}
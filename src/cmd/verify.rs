@@ -0,0 +1,259 @@
+use crate::session::invariants::{self, Facts, Outcome, Remote};
+use crate::{clock::Clock, config::Config, git, session, state_dir::StateDir, warnings};
+use anyhow::{anyhow, Result};
+use clap::{self, Clap};
+use std::collections::BTreeMap;
+
+#[derive(Clap, Debug)]
+pub struct VerifyOpts {
+    /// Set from the global `--json`, the report then carries every check
+    #[clap(skip)]
+    pub json: bool,
+}
+
+/// Checks what must hold of the session without changing anything
+pub struct Verify<'a> {
+    git: &'a dyn git::Git,
+    documents: &'a dyn git::Store,
+    store: &'a dyn session::Store,
+    clock: &'a dyn Clock,
+    state_dir: StateDir,
+    opts: VerifyOpts,
+    config: Config,
+}
+
+impl<'a> Verify<'a> {
+    pub fn new(
+        git: &'a impl git::Git,
+        documents: &'a impl git::Store,
+        store: &'a impl session::Store,
+        clock: &'a impl Clock,
+        state_dir: StateDir,
+        opts: VerifyOpts,
+        config: Config,
+    ) -> Verify<'a> {
+        Self {
+            git,
+            documents,
+            store,
+            clock,
+            state_dir,
+            opts,
+            config,
+        }
+    }
+
+    pub fn run(&self) -> Result<()> {
+        let session = self.store.load()?;
+        let repository = self.git.is_repository();
+        let branch = &session.branches.branch;
+        let documents = match repository {
+            true => self.documents.documents()?,
+            false => vec![],
+        };
+        let local = |document: &String| {
+            let reference = git::store::reference(document);
+            Ok((document.clone(), self.git.rev_parse(&reference)?))
+        };
+        let facts = Facts {
+            session: &session,
+            now: self.clock.now_utc(),
+            me: &self.config.name,
+            local_branch: match repository {
+                true => Some(self.git.has_branch(branch)?),
+                false => None,
+            },
+            documents: documents.iter().map(local).collect::<Result<_>>()?,
+            remote: self.remote(branch, &documents),
+        };
+        let checks = invariants::check(&facts);
+        let failed = checks
+            .iter()
+            .filter(|(_, outcome)| matches!(outcome, Outcome::Fail(_)))
+            .count();
+
+        if self.opts.json {
+            let checks: Vec<serde_json::Value> = checks
+                .iter()
+                .map(|(invariant, outcome)| {
+                    let mut check = serde_json::to_value(outcome).expect("outcomes serialize");
+                    check["name"] = invariant.name.into();
+                    check["description"] = invariant.description.into();
+                    check
+                })
+                .collect();
+            warnings::set_status(serde_json::json!({ "checks": checks, "ok": failed == 0 }));
+        } else {
+            print!("{}", summary(&checks));
+        }
+        match failed {
+            0 => Ok(()),
+            _ => Err(anyhow!(
+                "{} of {} checks failed, see above",
+                failed,
+                checks.len()
+            )),
+        }
+    }
+
+    /// What the remote has, unless it can't be asked or turns handed over offline stand
+    /// in for it
+    fn remote(&self, branch: &str, documents: &[String]) -> Option<Remote> {
+        if !self.git.is_repository()
+            || self.config.git.offline
+            || git::queue::Pending::read(&self.state_dir).is_some()
+        {
+            return None;
+        }
+        let ask = |branch: &str| match self.git.remote_tip(&self.config.remote, branch) {
+            Ok(tip) => Some(tip),
+            Err(err) => {
+                log::trace!(
+                    "Could not ask {} for {}: {}",
+                    self.config.remote,
+                    branch,
+                    err
+                );
+                None
+            }
+        };
+        let mut remote = Remote {
+            branch: ask(branch)?,
+            documents: BTreeMap::new(),
+        };
+        for document in documents {
+            let tip = ask(&git::store::reference(document))?;
+            remote.documents.insert(document.clone(), tip);
+        }
+        Some(remote)
+    }
+}
+
+fn summary(checks: &[(&invariants::Invariant, Outcome)]) -> String {
+    let mut out = String::new();
+    for (invariant, outcome) in checks {
+        let line = match outcome {
+            Outcome::Pass => format!("✅ {}: {}", invariant.name, invariant.description),
+            Outcome::Fail(problem) => format!("❌ {}: {}", invariant.name, problem),
+            Outcome::Skip(why) => format!("➖ {}: skipped, {}", invariant.name, why),
+        };
+        out.push_str(&line);
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::FakeClock;
+    use crate::cmd::fixtures::working;
+    use crate::git::fake::{FakeGit, MemoryStore};
+    use crate::session::Store;
+
+    fn verify<'a>(
+        git: &'a FakeGit,
+        memory: &'a MemoryStore,
+        store: &'a session::SessionStore,
+        clock: &'a FakeClock,
+        json: bool,
+    ) -> Verify<'a> {
+        Verify::new(
+            git,
+            memory,
+            store,
+            clock,
+            StateDir::new(std::env::temp_dir().join("mob-verify-test")),
+            VerifyOpts { json },
+            Config {
+                name: "alice".into(),
+                ..Config::default()
+            },
+        )
+    }
+
+    fn git() -> FakeGit {
+        let mut git = FakeGit::default();
+        git.branches.insert("mob-session".into());
+        git.remote_tips.insert("mob-session".into(), "a1".into());
+        git
+    }
+
+    #[test]
+    fn passes_a_sound_session() {
+        let (git, memory, clock) = (git(), MemoryStore::default(), FakeClock::default());
+        let store = session::SessionStore::new(&memory);
+        store.save(working()).unwrap();
+        let saves = *memory.saves.borrow();
+
+        verify(&git, &memory, &store, &clock, false).run().unwrap();
+
+        assert_eq!(*memory.saves.borrow(), saves);
+    }
+
+    #[test]
+    fn fails_and_reports_every_check() {
+        let (git, memory, clock) = (
+            FakeGit::default(),
+            MemoryStore::default(),
+            FakeClock::default(),
+        );
+        let store = session::SessionStore::new(&memory);
+        store
+            .save(session::Session {
+                state: session::State::Working {
+                    driver: "carol".into(),
+                },
+                ..working()
+            })
+            .unwrap();
+
+        warnings::take_status();
+        let err = verify(&git, &memory, &store, &clock, true)
+            .run()
+            .unwrap_err();
+
+        assert_eq!(err.to_string(), "2 of 6 checks failed, see above");
+        let report = warnings::take_status().unwrap();
+        assert_eq!(report["ok"], false);
+        let checks = report["checks"].as_array().unwrap();
+        assert_eq!(checks.len(), invariants::ALL.len());
+        assert_eq!(checks[0]["name"], "driver");
+        assert_eq!(checks[0]["outcome"], "fail");
+        assert_eq!(
+            checks[0]["problem"],
+            "carol isn't among the drivers alice, bob"
+        );
+        assert_eq!(checks[1]["outcome"], "fail");
+        assert_eq!(checks[2]["outcome"], "pass");
+    }
+
+    #[test]
+    fn skips_what_needs_the_remote_offline() {
+        let (git, memory, clock) = (
+            FakeGit::default(),
+            MemoryStore::default(),
+            FakeClock::default(),
+        );
+        let store = session::SessionStore::new(&memory);
+        store.save(working()).unwrap();
+        let mut config = Config::default();
+        config.git.offline = true;
+        let verify = Verify {
+            config,
+            ..verify(&git, &memory, &store, &clock, false)
+        };
+
+        let checks = invariants::check(&Facts {
+            session: &working(),
+            now: clock.now_utc(),
+            me: "bob",
+            local_branch: Some(false),
+            documents: BTreeMap::new(),
+            remote: verify.remote("mob-session", &[]),
+        });
+
+        assert!(summary(&checks).contains("➖ branches: skipped, the remote couldn't be asked\n"));
+        verify.run().unwrap();
+    }
+}
@@ -0,0 +1,175 @@
+use crate::handover::{self, Decision, Target};
+use crate::timekeeping::Instant;
+use crate::{clock::Clock, config::Config, deadline, duration, session, timer};
+use anyhow::Result;
+use std::thread;
+
+/// How often the session is re-read to notice that someone started
+const POLL_SECONDS: i64 = 20;
+
+/// Nags while the session waits for the next driver and stops once someone starts
+pub struct Watch<'a> {
+    store: &'a dyn session::Store,
+    local: &'a dyn timer::Notifier,
+    channel: &'a dyn timer::Notifier,
+    clock: &'a dyn Clock,
+    config: Config,
+    sleep: Box<dyn Fn(chrono::Duration) + 'a>,
+}
+
+impl<'a> Watch<'a> {
+    pub fn new(
+        store: &'a impl session::Store,
+        local: &'a impl timer::Notifier,
+        channel: &'a impl timer::Notifier,
+        clock: &'a impl Clock,
+        config: Config,
+    ) -> Watch<'a> {
+        Self {
+            store,
+            local,
+            channel,
+            clock,
+            config,
+            sleep: Box::new(|duration| thread::sleep(duration.to_std().unwrap_or_default())),
+        }
+    }
+
+    pub fn run(&self) -> Result<()> {
+        let me = self.config.name.as_str();
+        let waiting_since = self.store.load()?.times.waiting_since;
+        let mut sent = 0;
+
+        loop {
+            deadline::check()?;
+            let session = self.store.load()?;
+            // Another handover has its own watcher
+            if session.times.waiting_since != waiting_since {
+                return Ok(());
+            }
+
+            let now = self.clock.now_utc();
+            match handover::decide(now, &session, me, &self.config.handover, sent) {
+                Decision::Stop => return Ok(()),
+                Decision::WaitUntil(due) => {
+                    let mut poll = chrono::Duration::seconds(POLL_SECONDS);
+                    if let Some(left) = deadline::remaining() {
+                        poll = std::cmp::min(poll, chrono::Duration::from_std(left)?);
+                    }
+                    (self.sleep)(std::cmp::min(due - now, poll));
+                }
+                Decision::Nag(target) => {
+                    let waited = duration::elapsed(waiting_since.map_or(now, Instant::utc), now)
+                        .num_minutes();
+                    match target {
+                        Target::Me => {
+                            log::warn!("The mob has waited {} minutes for you", waited);
+                            self.local.notify("mob start")?;
+                        }
+                        Target::Channel(next) => {
+                            let next = session::shown(&next);
+                            log::info!("Reminding {}, the mob has waited {} minutes", next, waited);
+                            self.channel.notify(&format!(
+                                "The mob is waiting for {} to run mob start",
+                                next
+                            ))?;
+                        }
+                    }
+                    sent += 1;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::FakeClock;
+    use crate::cmd::fixtures::config;
+    use crate::git::FileStore;
+    use crate::session::{State, Store, Times};
+    use chrono::{DateTime, Utc};
+    use std::cell::{Cell, RefCell};
+
+    #[derive(Default)]
+    struct Recorder(RefCell<Vec<String>>);
+
+    impl timer::Notifier for Recorder {
+        fn notify(&self, message: &str) -> Result<()> {
+            self.0.borrow_mut().push(message.to_string());
+            Ok(())
+        }
+    }
+
+    fn waiting_for(store: &impl Store, next: &str, since: DateTime<Utc>) {
+        store
+            .save(session::Session {
+                state: State::WaitingForNext {
+                    next: Some(next.into()),
+                },
+                times: Times {
+                    waiting_since: Some(since.into()),
+                    ..Times::default()
+                },
+                ..session::Session::default()
+            })
+            .unwrap();
+    }
+
+    #[test]
+    fn stops_when_someone_starts() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_store = FileStore::new(dir.path());
+        let store = session::SessionStore::new(&file_store);
+        let clock = FakeClock::default();
+        waiting_for(
+            &store,
+            "bob",
+            clock.now_utc() - chrono::Duration::minutes(3),
+        );
+
+        let (local, channel) = (Recorder::default(), Recorder::default());
+        let other = session::SessionStore::new(&file_store);
+        let mut watch = Watch::new(&store, &local, &channel, &clock, config("alice"));
+        // bob starts while we wait for the second nag
+        watch.sleep = Box::new(|duration| {
+            clock.advance(duration);
+            let mut session = other.load().unwrap();
+            session.state = State::Working {
+                driver: "bob".into(),
+            };
+            other.save(session).unwrap();
+        });
+        watch.run().unwrap();
+
+        assert_eq!(
+            *channel.0.borrow(),
+            vec!["The mob is waiting for bob to run mob start"]
+        );
+        assert!(local.0.borrow().is_empty());
+    }
+
+    #[test]
+    fn nags_at_most_max_times() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_store = FileStore::new(dir.path());
+        let store = session::SessionStore::new(&file_store);
+        let clock = FakeClock::default();
+        waiting_for(&store, "bob", clock.now_utc());
+
+        let (local, channel) = (Recorder::default(), Recorder::default());
+        let sleeps = Cell::new(0);
+        let mut watch = Watch::new(&store, &local, &channel, &clock, config("bob"));
+        watch.sleep = Box::new(|duration| {
+            sleeps.set(sleeps.get() + 1);
+            clock.advance(duration);
+        });
+        watch.run().unwrap();
+
+        assert_eq!(local.0.borrow().len(), 3);
+        assert!(channel.0.borrow().is_empty());
+        // Polls in between so a start is noticed quickly
+        assert!(sleeps.get() >= 3 * 6);
+    }
+}
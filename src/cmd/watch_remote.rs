@@ -0,0 +1,264 @@
+use crate::follow::{self, Advance, Hold, Step};
+use crate::{config::Config, deadline, duration, git, lock, session, state_dir::StateDir};
+use anyhow::Result;
+use clap::{self, Clap};
+use std::thread;
+
+#[derive(Clap, Debug)]
+pub struct WatchRemoteOpts {
+    /// How often to fetch the mob branch, like 30s or 2m
+    #[clap(long, default_value = "30s", parse(try_from_str = duration::parse))]
+    interval: chrono::Duration,
+}
+
+/// Keeps the mob branch of someone who watches up to date with what the driver pushes, until
+/// the session stops
+pub struct WatchRemote<'a> {
+    git: &'a dyn git::Git,
+    store: &'a dyn session::Store,
+    /// Where the lock is taken around each pull
+    state_dir: StateDir,
+    opts: WatchRemoteOpts,
+    config: Config,
+    sleep: Box<dyn Fn(chrono::Duration) + 'a>,
+}
+
+impl<'a> WatchRemote<'a> {
+    pub fn new(
+        git: &'a impl git::Git,
+        store: &'a impl session::Store,
+        state_dir: StateDir,
+        opts: WatchRemoteOpts,
+        config: Config,
+    ) -> WatchRemote<'a> {
+        Self {
+            git,
+            store,
+            state_dir,
+            opts,
+            config,
+            sleep: Box::new(|duration| thread::sleep(duration.to_std().unwrap_or_default())),
+        }
+    }
+
+    pub fn run(&self) -> Result<()> {
+        let me = self.config.name.as_str();
+        let mut last = None;
+        let mut diverged = false;
+        let mut failures = 0;
+        loop {
+            deadline::check()?;
+            let session = self.store.load()?;
+            let step = follow::decide(&session, me, &self.git.snapshot()?);
+            if last.as_ref() != Some(&step) {
+                tell(&step);
+            }
+            let mut wait = self.opts.interval;
+            match &step {
+                Step::Stop => return Ok(()),
+                Step::Driving | Step::Hold(_) => diverged = false,
+                Step::Follow { branch } => match self.follow(branch) {
+                    Ok(advance) => {
+                        if advance == Advance::Diverged && !diverged {
+                            log::warn!(
+                                "{} has commits that aren't pushed, it is left as it is",
+                                branch
+                            );
+                        }
+                        diverged = advance == Advance::Diverged;
+                        failures = 0;
+                    }
+                    Err(err) => {
+                        failures += 1;
+                        wait = follow::backoff(self.opts.interval, failures);
+                        log::warn!(
+                            "Could not update {}, trying again in {}: {}",
+                            branch,
+                            duration::format(wait).short(),
+                            err
+                        );
+                    }
+                },
+            }
+            last = Some(step);
+            if let Some(left) = deadline::remaining() {
+                wait = std::cmp::min(wait, chrono::Duration::from_std(left)?);
+            }
+            (self.sleep)(wait);
+        }
+    }
+
+    /// Fetches `branch` and fast-forwards the checkout to it, never anything else. Holds the
+    /// lock meanwhile, but not while waiting for the next pull
+    fn follow(&self, branch: &str) -> Result<Advance> {
+        let _lock = lock::acquire(&self.state_dir, lock::TIMEOUT, &lock::alive)?;
+        let remote = self.config.remote.as_str();
+        let tracking = format!("{}/{}", remote, branch);
+        self.git.run(&["fetch", "--no-tags", remote, branch])?;
+        let (head, tip) = match (self.git.rev_parse("HEAD")?, self.git.rev_parse(&tracking)?) {
+            (Some(head), Some(tip)) => (head, tip),
+            _ => return Ok(Advance::UpToDate),
+        };
+        let base = self.git.merge_base(&head, &tip)?;
+        let advance = follow::advance(&head, &tip, base.as_deref());
+        if advance == Advance::FastForward {
+            let commits = self.git.log_oneline(&format!("{}..{}", head, tip))?;
+            self.git.run(&["merge", "--ff-only", tracking.as_str()])?;
+            match commits.len() {
+                1 => log::info!("Pulled 1 commit onto {}", branch),
+                count => log::info!("Pulled {} commits onto {}", count, branch),
+            }
+        }
+        Ok(advance)
+    }
+}
+
+/// Says why the branch is or isn't followed, once each time that changes
+fn tell(step: &Step) {
+    match step {
+        Step::Stop => log::info!("The session stopped, done watching"),
+        Step::Driving => log::info!("You're driving, watching pauses until you hand over"),
+        Step::Hold(Hold::Paused) => log::info!("The session is paused, watching pauses too"),
+        Step::Hold(Hold::OtherBranch) => {
+            log::info!("Not on the mob branch, watching pauses until you check it out")
+        }
+        Step::Hold(Hold::Dirty) => {
+            log::info!("You have changes, watching pauses until the tree is clean")
+        }
+        Step::Follow { branch } => log::info!("Watching {} for what the driver pushes", branch),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::fake::{FakeGit, MemoryStore};
+    use crate::session::{State, Store as _};
+    use std::cell::RefCell;
+
+    fn opts() -> WatchRemoteOpts {
+        WatchRemoteOpts::parse_from(["watch-remote"])
+    }
+
+    fn config() -> Config {
+        Config {
+            name: "alice".into(),
+            ..Config::default()
+        }
+    }
+
+    fn on_mob_branch() -> FakeGit {
+        let mut git = FakeGit::default();
+        git.snapshot.branch = Some("mob-session".into());
+        git.revs.insert("HEAD".into(), "a1".into());
+        git.revs.insert("origin/mob-session".into(), "b2".into());
+        git.merge_base = Some("a1".into());
+        git.log = vec!["b2 wip".into()];
+        git
+    }
+
+    fn working(driver: &str) -> session::Session {
+        session::Session {
+            state: State::Working {
+                driver: driver.into(),
+            },
+            ..session::Session::default()
+        }
+    }
+
+    /// Runs until the session is in each of `states` in turn, one per sleep, then stops it
+    fn watch(git: &FakeGit, states: Vec<State>) -> Vec<chrono::Duration> {
+        let memory = MemoryStore::default();
+        let store = session::SessionStore::new(&memory);
+        store.save(working("bob")).unwrap();
+        let states = RefCell::new(states.into_iter());
+        let sleeps = RefCell::new(vec![]);
+        let state_dir = tempfile::tempdir().unwrap();
+        let state_dir = StateDir::new(state_dir.path().into());
+        let mut watch = WatchRemote::new(git, &store, state_dir.clone(), opts(), config());
+        watch.sleep = Box::new(|duration| {
+            // Others may change the session while watching waits
+            lock::acquire(&state_dir, lock::TIMEOUT, &lock::alive).unwrap();
+            sleeps.borrow_mut().push(duration);
+            let state = states.borrow_mut().next().unwrap_or(State::Stopped);
+            let session = store.load().unwrap();
+            store.save(session::Session { state, ..session }).unwrap();
+        });
+        watch.run().unwrap();
+        sleeps.take()
+    }
+
+    #[test]
+    fn fast_forwards_until_the_session_stops() {
+        let git = on_mob_branch();
+        let sleeps = watch(&git, vec![]);
+        assert_eq!(sleeps, vec![chrono::Duration::seconds(30)]);
+        assert_eq!(
+            git.calls(),
+            vec![
+                "fetch --no-tags origin mob-session",
+                "merge --ff-only origin/mob-session"
+            ]
+        );
+    }
+
+    #[test]
+    fn pauses_while_driving() {
+        let git = on_mob_branch();
+        watch(&git, vec![working("alice").state, working("bob").state]);
+        // Fetched and pulled before and after the turn, never during it
+        assert_eq!(
+            git.calls()
+                .iter()
+                .filter(|call| call.starts_with("merge"))
+                .count(),
+            2
+        );
+        assert_eq!(git.calls().len(), 4);
+    }
+
+    #[test]
+    fn never_pulls_into_changes() {
+        let mut git = on_mob_branch();
+        git.snapshot.untracked = 1;
+        watch(&git, vec![working("bob").state]);
+        assert!(git.calls().is_empty());
+
+        let mut git = on_mob_branch();
+        git.snapshot.branch = Some("main".into());
+        watch(&git, vec![]);
+        assert!(git.calls().is_empty());
+    }
+
+    #[test]
+    fn leaves_local_commits_alone() {
+        let mut git = on_mob_branch();
+        git.merge_base = Some("c0ffee".into());
+        watch(&git, vec![working("bob").state]);
+        assert_eq!(
+            git.calls(),
+            vec![
+                "fetch --no-tags origin mob-session",
+                "fetch --no-tags origin mob-session"
+            ]
+        );
+    }
+
+    #[test]
+    fn backs_off_when_fetches_fail() {
+        let git = on_mob_branch();
+        git.fail("fetch", 0, "Could not resolve host");
+        git.fail("fetch", 0, "Could not resolve host");
+        let bob = working("bob").state;
+        let sleeps = watch(&git, vec![bob.clone(), bob]);
+        assert_eq!(
+            sleeps,
+            vec![
+                chrono::Duration::minutes(1),
+                chrono::Duration::minutes(2),
+                chrono::Duration::seconds(30)
+            ]
+        );
+        assert_eq!(git.calls().last().unwrap(), "merge --ff-only origin/mob-session");
+    }
+}
@@ -0,0 +1,172 @@
+//! Keeping the whitespace editors fight over out of the commits of a turn, as
+//! `next.whitespace` says. Fixing only touches the lines the turn added.
+
+use crate::config::Whitespace;
+use crate::git::{whitespace, Git};
+use anyhow::Result;
+
+/// Strips or points out staged changes that are only whitespace. False when stripping left
+/// nothing to commit.
+pub(super) fn check(git: &dyn Git, mode: Whitespace) -> Result<bool> {
+    if mode == Whitespace::Off {
+        return Ok(true);
+    }
+    if mode == Whitespace::Fix {
+        let fixed = fix(git)?;
+        if !fixed.is_empty() {
+            log::info!(
+                "Stripped the whitespace the turn added to {}",
+                fixed.join(", ")
+            );
+            if git.staged_numstat(false)?.trim().is_empty() {
+                return Ok(false);
+            }
+        }
+    }
+    let only = whitespace::only_whitespace(&git.staged_numstat(false)?, &git.staged_numstat(true)?);
+    if !only.is_empty() {
+        log::warn!(
+            "Only whitespace changed in {}, an editor may be set to change it",
+            only.join(", ")
+        );
+    }
+    if mode == Whitespace::Warn {
+        let marked = whitespace::bom_added(&git.staged_diff()?);
+        if !marked.is_empty() {
+            log::warn!("A byte order mark was added to {}", marked.join(", "));
+        }
+    }
+    Ok(true)
+}
+
+/// Strips trailing whitespace, blank lines at the end and byte order marks the turn added
+/// and stages the files again. The files that changed.
+fn fix(git: &dyn Git) -> Result<Vec<String>> {
+    let mut problems = whitespace::problems(&git.staged_check()?);
+    for file in whitespace::bom_added(&git.staged_diff()?) {
+        problems.entry(file).or_default().bom = true;
+    }
+    let mut fixed = vec![];
+    for (path, problems) in problems {
+        let content = match git.read_file(&path) {
+            Ok(content) => content,
+            Err(err) => {
+                log::warn!("Could not strip the whitespace of {}: {:#}", path, err);
+                continue;
+            }
+        };
+        let stripped = whitespace::strip(&content, &problems);
+        if stripped != content {
+            git.write_file(&path, &stripped)?;
+            fixed.push(path);
+        }
+    }
+    if !fixed.is_empty() {
+        let mut add = vec!["add", "--"];
+        add.extend(fixed.iter().map(String::as_str));
+        git.run(&add)?;
+    }
+    Ok(fixed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::fake::FakeGit;
+
+    /// A turn that added trailing whitespace to a line it changed, and left trailing
+    /// spaces alone that were there before
+    fn turn() -> FakeGit {
+        let git = FakeGit {
+            numstat: "1\t1\tsrc/lib.rs\n1\t1\tREADME.md\n".into(),
+            numstat_ignoring_whitespace: "1\t1\tsrc/lib.rs\n".into(),
+            whitespace_check: "src/lib.rs:2: trailing whitespace.\n+    run(); \n".into(),
+            ..FakeGit::default()
+        };
+        git.worktree.borrow_mut().insert(
+            "src/lib.rs".into(),
+            b"fn main() {  \n    run(); \n}\n".to_vec(),
+        );
+        git
+    }
+
+    #[test]
+    fn leaves_everything_when_off() {
+        let git = turn();
+        assert!(check(&git, Whitespace::Off).unwrap());
+        assert!(git.calls().is_empty());
+        assert_eq!(
+            git.worktree.borrow()["src/lib.rs"],
+            b"fn main() {  \n    run(); \n}\n".to_vec()
+        );
+    }
+
+    #[test]
+    fn warns_without_changing_anything() {
+        let git = turn();
+        assert!(check(&git, Whitespace::Warn).unwrap());
+        assert!(git.calls().is_empty());
+        assert_eq!(
+            git.worktree.borrow()["src/lib.rs"],
+            b"fn main() {  \n    run(); \n}\n".to_vec()
+        );
+    }
+
+    #[test]
+    fn strips_the_lines_the_turn_added() {
+        let git = turn();
+        assert!(check(&git, Whitespace::Fix).unwrap());
+        assert_eq!(git.calls(), vec!["add -- src/lib.rs"]);
+        assert_eq!(
+            git.worktree.borrow()["src/lib.rs"],
+            b"fn main() {  \n    run();\n}\n".to_vec()
+        );
+    }
+
+    #[test]
+    fn strips_byte_order_marks_the_turn_added() {
+        let git = FakeGit {
+            numstat: "1\t1\tProgram.cs\n".into(),
+            numstat_ignoring_whitespace: "1\t1\tProgram.cs\n".into(),
+            staged: "diff --git a/Program.cs b/Program.cs\n--- a/Program.cs\n+++ b/Program.cs\n\
+                     @@ -1 +1 @@\n-using System;\n+\u{feff}using System;\n"
+                .into(),
+            ..FakeGit::default()
+        };
+        git.worktree.borrow_mut().insert(
+            "Program.cs".into(),
+            "\u{feff}using System;\n".as_bytes().to_vec(),
+        );
+        assert!(check(&git, Whitespace::Fix).unwrap());
+        assert_eq!(git.calls(), vec!["add -- Program.cs"]);
+        assert_eq!(
+            git.worktree.borrow()["Program.cs"],
+            b"using System;\n".to_vec()
+        );
+    }
+
+    #[test]
+    fn tells_when_nothing_is_left_to_commit() {
+        // Once stripped the file is as it was, nothing is staged anymore
+        let git = FakeGit {
+            whitespace_check: "README.md:1: trailing whitespace.\n+Mob \n".into(),
+            ..FakeGit::default()
+        };
+        git.worktree
+            .borrow_mut()
+            .insert("README.md".into(), b"Mob \n".to_vec());
+        assert!(!check(&git, Whitespace::Fix).unwrap());
+        assert_eq!(git.worktree.borrow()["README.md"], b"Mob\n".to_vec());
+    }
+
+    #[test]
+    fn skips_files_it_cannot_read() {
+        let git = FakeGit {
+            numstat: "1\t1\tgone.txt\n".into(),
+            whitespace_check: "gone.txt:1: trailing whitespace.\n+x \n".into(),
+            ..FakeGit::default()
+        };
+        assert!(check(&git, Whitespace::Fix).unwrap());
+        assert!(git.calls().is_empty());
+    }
+}
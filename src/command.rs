@@ -1,10 +1,13 @@
+use crate::deadline;
 use anyhow::Error;
 use std::borrow::Cow;
 use std::ffi::OsStr;
 use std::fmt;
-use std::io;
+use std::io::{self, Read};
 use std::path::{Path, PathBuf};
-use std::process;
+use std::process::{self, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
 use thiserror::Error;
 
 /// The decoded output after running a command.
@@ -116,13 +119,42 @@ impl<'name> Command<'name> {
         Ok(())
     }
 
+    /// Start the given command without waiting for it, it keeps running after mob exits.
+    /// Returns its process id.
+    pub fn spawn_detached<S>(&self, args: impl IntoIterator<Item = S>) -> Result<u32, io::Error>
+    where
+        S: AsRef<OsStr>,
+    {
+        let child = self
+            .command(args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()?;
+        Ok(child.id())
+    }
+
+    /// Run the given command at the terminal, like an editor, and wait for it to end.
+    pub fn interact<S>(
+        &self,
+        args: impl IntoIterator<Item = S>,
+    ) -> Result<process::ExitStatus, io::Error>
+    where
+        S: AsRef<OsStr>,
+    {
+        self.command(args).status()
+    }
+
     /// Run the given command, return a string of all output.
     pub fn run<S>(&self, args: impl IntoIterator<Item = S>) -> Result<Output, io::Error>
     where
         S: AsRef<OsStr>,
     {
         let mut cmd = self.command(args);
-        let output = cmd.output()?;
+        let output = match deadline::remaining() {
+            Some(left) => output_within(cmd, left)?,
+            None => cmd.output()?,
+        };
 
         let output = Output {
             status: output.status,
@@ -137,3 +169,82 @@ impl<'name> Command<'name> {
         Ok(output)
     }
 }
+
+/// Like `output()`, killing the process when it runs longer than `left`
+fn output_within(mut cmd: process::Command, left: Duration) -> io::Result<process::Output> {
+    let deadline = Instant::now() + left;
+    let mut child = cmd
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    // Read while waiting, a full pipe would block the child
+    let read = |pipe: Option<Box<dyn Read + Send>>| {
+        thread::spawn(move || {
+            let mut data = vec![];
+            if let Some(mut pipe) = pipe {
+                pipe.read_to_end(&mut data).map(|_| data)
+            } else {
+                Ok(data)
+            }
+        })
+    };
+    let stdout = read(child.stdout.take().map(|pipe| Box::new(pipe) as _));
+    let stderr = read(child.stderr.take().map(|pipe| Box::new(pipe) as _));
+
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if Instant::now() >= deadline {
+            child.kill()?;
+            child.wait()?;
+            // The readers end when whatever the child started closes the pipes
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                deadline::Error::TimedOut,
+            ));
+        }
+        thread::sleep(Duration::from_millis(10));
+    };
+    let join = |reader: thread::JoinHandle<io::Result<Vec<u8>>>| {
+        reader
+            .join()
+            .unwrap_or_else(|_| Err(io::Error::other("reader panicked")))
+    };
+    Ok(process::Output {
+        status,
+        stdout: join(stdout)?,
+        stderr: join(stderr)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::os;
+
+    fn sh(script: &str) -> io::Result<Output> {
+        Command::new(os::command("sh")).run(["-c", script])
+    }
+
+    #[test]
+    fn kills_past_the_deadline() {
+        deadline::set(Some(Duration::from_millis(200)));
+        let started = Instant::now();
+        let err = sh("sleep 5").err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+        assert!(started.elapsed() < Duration::from_secs(4));
+        deadline::set(None);
+    }
+
+    #[test]
+    fn output_within_the_deadline() {
+        deadline::set(Some(Duration::from_secs(30)));
+        let output = sh("echo out; echo err >&2; exit 3").unwrap();
+        assert_eq!(output.stdout, "out\n");
+        assert_eq!(output.stderr, "err\n");
+        assert_eq!(output.status.code(), Some(3));
+        deadline::set(None);
+    }
+}
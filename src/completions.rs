@@ -0,0 +1,147 @@
+//! Completion scripts of the command line, written by clap's generator from what clap knows
+//! of every command, so they can't miss a flag added later. `mob completions zsh >
+//! ~/.zfunc/_mob` installs one.
+
+use clap::App;
+use clap_generate::generate;
+use clap_generate::generators::{Bash, Fish, PowerShell, Zsh};
+use std::str::FromStr;
+
+/// The shells there are scripts for, as `mob completions` takes them
+pub const SHELLS: &[&str] = &["bash", "zsh", "fish", "powershell"];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+    PowerShell,
+}
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+#[error("There are no completions for `{0}`, only for bash, zsh, fish and powershell")]
+pub struct UnknownShell(pub String);
+
+impl FromStr for Shell {
+    type Err = UnknownShell;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.to_lowercase().as_str() {
+            "bash" => Ok(Shell::Bash),
+            "zsh" => Ok(Shell::Zsh),
+            "fish" => Ok(Shell::Fish),
+            "powershell" | "pwsh" => Ok(Shell::PowerShell),
+            _ => Err(UnknownShell(name.to_string())),
+        }
+    }
+}
+
+/// The completion script of `app` for `shell`, installed as `program`
+pub fn script(shell: Shell, app: &App, program: &str) -> String {
+    let mut app = app.clone();
+    let mut out = Vec::new();
+    match shell {
+        Shell::Bash => generate::<Bash, _>(&mut app, program, &mut out),
+        Shell::Zsh => generate::<Zsh, _>(&mut app, program, &mut out),
+        Shell::Fish => generate::<Fish, _>(&mut app, program, &mut out),
+        Shell::PowerShell => generate::<PowerShell, _>(&mut app, program, &mut out),
+    }
+    String::from_utf8(out).expect("clap writes the scripts as UTF-8")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd;
+    use clap::{Arg, IntoApp};
+
+    fn app() -> App<'static> {
+        App::new("mob")
+            .arg(
+                Arg::new("dir")
+                    .long("dir")
+                    .global(true)
+                    .takes_value(true)
+                    .about("Directory of a local session"),
+            )
+            .subcommand(cmd::StartOpts::into_app().name("start"))
+            .subcommand(
+                cmd::NextOpts::into_app()
+                    .name("next")
+                    .visible_aliases(&["n", "rotate"]),
+            )
+            .subcommand(cmd::StatusOpts::into_app().name("status"))
+            .subcommand(cmd::DriversOpts::into_app().name("drivers"))
+            .subcommand(
+                App::new("completions")
+                    .about("Print the script that completes mob's commands")
+                    .arg(Arg::new("SHELL").possible_values(SHELLS)),
+            )
+    }
+
+    #[test]
+    fn parses_shells() {
+        assert_eq!("zsh".parse(), Ok(Shell::Zsh));
+        assert_eq!("PowerShell".parse(), Ok(Shell::PowerShell));
+        assert_eq!(
+            "tcsh".parse::<Shell>().unwrap_err().to_string(),
+            "There are no completions for `tcsh`, only for bash, zsh, fish and powershell"
+        );
+        for name in SHELLS {
+            assert!(name.parse::<Shell>().is_ok(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn covers_every_command_and_flag() {
+        let app = app();
+        let mut expected: Vec<String> = [
+            cmd::StartOpts::into_app(),
+            cmd::NextOpts::into_app(),
+            cmd::StatusOpts::into_app(),
+        ]
+        .iter()
+        .flat_map(|app| {
+            app.get_arguments()
+                .filter_map(|arg| arg.get_long().map(|long| format!("--{}", long)))
+                .collect::<Vec<_>>()
+        })
+        .collect();
+        expected.extend(["--dir", "start", "next", "status", "drivers", "add"].map(String::from));
+        for shell in SHELLS {
+            let script = script(shell.parse().unwrap(), &app, "mob");
+            for word in &expected {
+                let word = word.trim_start_matches('-');
+                assert!(script.contains(word), "{} lacks {}", shell, word);
+            }
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn bash_completes() {
+        let script = script(Shell::Bash, &app(), "mob");
+        let complete = |line: &str| {
+            let words: Vec<&str> = line.split(' ').collect();
+            let program = format!(
+                "{}\nCOMP_WORDS=({})\nCOMP_CWORD={}\n_mob\nprintf '%s\\n' \"${{COMPREPLY[@]}}\"",
+                script,
+                words
+                    .iter()
+                    .map(|word| format!("'{}'", word))
+                    .collect::<Vec<_>>()
+                    .join(" "),
+                words.len() - 1
+            );
+            let output = std::process::Command::new("bash")
+                .args(["-c", &program])
+                .output()
+                .unwrap();
+            assert!(output.status.success(), "{:?}", output);
+            String::from_utf8(output.stdout).unwrap()
+        };
+        assert_eq!(complete("mob sta"), "start\nstatus\n");
+        assert!(complete("mob next --").contains("--dir\n"));
+        assert!(complete("mob drivers ").contains("remove\n"));
+    }
+}
@@ -1,65 +1,396 @@
+use crate::alias::Aliases;
+use crate::broadcast::{self, Broadcast, Sink};
+use crate::deadline;
+use crate::palette::PaletteKind;
+use crate::preset::{self, Presets};
+use crate::timekeeping::Minutes;
+use crate::{git, prompt, session, warnings};
 use anyhow::Result;
 use confy;
-use dialoguer::{Confirm, Input};
 use directories::UserDirs;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::default::Default;
-use std::path;
+use std::{fmt, fs, io, path};
 
 const CONFIG_FILE: &str = ".mob";
+/// Variables like `MOB_REMOTE` override the key of the config they name
+const ENV_PREFIX: &str = "MOB_";
+/// Longer variables than `MOB_` and this many words name no key
+const MAX_ENV_PARTS: usize = 8;
 
-#[derive(Serialize, Deserialize)]
+/// Keys that moved into a section, as (old, new). Files with the old keys keep working.
+const MOVED_KEYS: &[(&str, &str)] = &[
+    ("say_command", "timer.say_command"),
+    ("notify_command", "timer.notify_command"),
+    ("auto_fetch", "git.auto_fetch"),
+];
+
+#[derive(Serialize, Deserialize, Clone)]
 pub struct Config {
     pub name: String,
     pub remote: String,
+    #[serde(default)]
+    pub timer: TimerConfig,
+    #[serde(default)]
+    pub git: GitConfig,
+    #[serde(default)]
+    pub display: DisplayConfig,
+    /// Named durations for `mob preset` and `mob start --preset`
+    #[serde(default = "preset::builtin")]
+    pub presets: Presets,
+    #[serde(default)]
+    pub done: DoneConfig,
+    #[serde(default)]
+    pub commit: CommitConfig,
+    #[serde(default)]
+    pub handover: HandoverConfig,
+    #[serde(default)]
+    pub next: NextConfig,
+    #[serde(default)]
+    pub rotation: RotationConfig,
+    #[serde(default)]
+    pub stats: StatsConfig,
+    #[serde(default)]
+    pub metrics: MetricsConfig,
+    #[serde(default)]
+    pub limits: LimitsConfig,
+    #[serde(default)]
+    pub update: UpdateConfig,
+    #[serde(default)]
+    pub breaks: BreaksConfig,
+    /// Own names for commands, like `brb = "break 5"`
+    #[serde(default)]
+    pub aliases: Aliases,
+}
+
+/// Commands left out are not run, as in files from before the sections
+#[derive(Serialize, Deserialize, Default, Clone)]
+#[non_exhaustive]
+pub struct TimerConfig {
+    /// Run when the timer ends, MESSAGE is replaced with what to say
+    #[serde(alias = "say")]
     pub say_command: Option<String>,
+    /// Shows a notification instead of the one built in
+    #[serde(alias = "notify")]
     pub notify_command: Option<String>,
+    /// Neither says nor shows anything when the timer is up, like in a container without a
+    /// notification daemon
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub silent: bool,
+    /// Where turns and breaks are sent to, `tcp://host:port` or `mqtt://host/topic`
+    pub broadcast: Option<String>,
+    /// Run detached when the timer ends, `{title}` and `{message}` are replaced with those of
+    /// the timer
+    #[serde(alias = "on_timer_end")]
+    pub on_end: Option<String>,
+    /// Rings the bell of the terminal when the timer ends
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub bell: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[non_exhaustive]
+pub struct GitConfig {
+    /// Fetch the session from the remote before every command
+    #[serde(default = "default_auto_fetch")]
+    pub auto_fetch: bool,
+    /// Put before the feature branch of a new session, `mob/` makes `--branch payment-flow`
+    /// the branch `mob/payment-flow`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub branch_prefix: Option<String>,
+    /// Set from the global `--offline`, turns wait for `mob sync` to push them
+    #[serde(skip)]
+    pub offline: bool,
+}
+
+impl Default for GitConfig {
+    fn default() -> Self {
+        Self {
+            auto_fetch: default_auto_fetch(),
+            branch_prefix: None,
+            offline: false,
+        }
+    }
+}
+
+fn default_auto_fetch() -> bool {
+    true
+}
+
+#[derive(Serialize, Deserialize, Default, Clone)]
+#[non_exhaustive]
+pub struct DisplayConfig {
+    pub palette: PaletteKind,
+    /// Lists instead of aligned columns, for screen readers
+    #[serde(default)]
+    pub plain: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[non_exhaustive]
+pub struct DoneConfig {
+    /// When `mob done` asks before merging the commits into the base branch
+    #[serde(default)]
+    pub require_confirmation: Confirmation,
+    /// Number of commits from which `when-many` asks
+    #[serde(default = "default_many_commits")]
+    pub many_commits: usize,
+    /// Emails of the drivers by name, for the `Co-authored-by` trailers of `mob done`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub emails: BTreeMap<String, String>,
+    /// Credit drivers without an email as `name@mob.invalid` instead of leaving them out
+    #[serde(default)]
+    pub placeholder_emails: bool,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Confirmation {
+    Always,
+    #[default]
+    WhenMany,
+    Never,
+}
+
+fn default_many_commits() -> usize {
+    5
+}
+
+#[derive(Serialize, Deserialize, Default, Clone)]
+#[non_exhaustive]
+pub struct CommitConfig {
+    /// Sign the commits of mob next, mob sync and mob done as git is set up to sign
+    #[serde(default)]
+    pub sign: bool,
+    /// Files that may have conflict markers, like docs that explain them, as patterns like
+    /// `*.md` or `tests/fixtures/**`
+    #[serde(default)]
+    pub allow_markers: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[non_exhaustive]
+pub struct HandoverConfig {
+    /// Minutes to wait for the next driver before `mob watch` nags, and between nags
+    #[serde(default = "default_nag_after")]
+    pub nag_after: Minutes,
+    #[serde(default = "default_max_nags")]
+    pub max_nags: u32,
+    /// Tells the others when someone else is awaited, MESSAGE is replaced like in say_command
+    #[serde(default)]
+    pub channel_command: Option<String>,
+    /// Minutes the next driver has to start before anyone may, unset keeps the turn theirs
+    #[serde(default)]
+    pub reassign_after: Option<Minutes>,
+    /// `mob next` asks at the terminal what the turn did when there is no `--message`
+    #[serde(default = "default_ask_message")]
+    pub ask_message: bool,
+}
+
+fn default_nag_after() -> Minutes {
+    Minutes(2)
+}
+
+fn default_max_nags() -> u32 {
+    3
+}
+
+fn default_ask_message() -> bool {
+    true
+}
+
+impl Default for HandoverConfig {
+    fn default() -> Self {
+        Self {
+            nag_after: default_nag_after(),
+            max_nags: default_max_nags(),
+            channel_command: None,
+            reassign_after: None,
+            ask_message: default_ask_message(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Clone)]
+#[non_exhaustive]
+pub struct NextConfig {
+    /// What `mob next` and `mob sync` do about staged changes that are only whitespace
+    #[serde(default)]
+    pub whitespace: Whitespace,
+    /// Which new files `mob next` and `mob sync` commit with the turn
+    #[serde(default)]
+    pub untracked: Untracked,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Whitespace {
+    #[default]
+    Off,
+    /// Lists the files whose changes are only whitespace
+    Warn,
+    /// Strips the trailing whitespace the turn added first, then warns about the rest
+    Fix,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Untracked {
+    #[default]
+    Include,
+    /// Lists the new files nothing was said about in the session yet to pick from
+    Ask,
+    /// Only the new files picked before in the session
+    Exclude,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[non_exhaustive]
+pub struct RotationConfig {
+    /// Turns in a row by the same driver that `mob start` asks about, 0 never asks
+    #[serde(default = "default_consecutive_turns")]
+    pub consecutive_turns: usize,
+}
+
+fn default_consecutive_turns() -> usize {
+    3
+}
+
+impl Default for RotationConfig {
+    fn default() -> Self {
+        Self {
+            consecutive_turns: default_consecutive_turns(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[non_exhaustive]
+pub struct LimitsConfig {
+    /// More drivers only join with --force or when confirmed, unset allows any number
+    pub max_drivers: Option<usize>,
+    /// New sessions of fewer drivers start only when confirmed, and nobody is removed below
+    /// it until the session ends. Unset allows a single driver.
+    pub min_drivers: Option<usize>,
+    /// Refuse to start below `min_drivers` instead of asking
+    #[serde(default)]
+    pub enforce: bool,
+    /// Above this many drivers `mob status` points out the size of the mob
+    #[serde(default = "default_warn_drivers")]
+    pub warn_drivers: usize,
+}
+
+fn default_warn_drivers() -> usize {
+    6
+}
+
+impl Default for LimitsConfig {
+    fn default() -> Self {
+        Self {
+            max_drivers: None,
+            min_drivers: None,
+            enforce: false,
+            warn_drivers: default_warn_drivers(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[non_exhaustive]
+pub struct BreaksConfig {
+    /// Default the break `mob next` offers to what the mob answered at similar times
+    #[serde(default = "default_learn")]
+    pub learn: bool,
+    /// Take the break `mob next` offers when nobody can be asked, as with `--yes`
+    #[serde(default)]
+    pub auto: bool,
+}
+
+fn default_learn() -> bool {
+    true
+}
+
+impl Default for BreaksConfig {
+    fn default() -> Self {
+        Self {
+            learn: default_learn(),
+            auto: false,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Clone)]
+#[non_exhaustive]
+pub struct UpdateConfig {
+    /// Tell once a day when a newer mob is released, asking GitHub for it
+    #[serde(default)]
+    pub check: bool,
+}
+
+#[derive(Serialize, Deserialize, Default, Clone)]
+#[non_exhaustive]
+pub struct StatsConfig {
+    /// Where `mob done` also archives the report of the session, like a shared drive
+    pub shared_dir: Option<path::PathBuf>,
+}
+
+/// Nothing is written unless `dir` is set
+#[derive(Serialize, Deserialize, Default, Clone)]
+#[non_exhaustive]
+pub struct MetricsConfig {
+    /// Where `mob done` appends anonymized numbers of the session for `mob stats --metrics-dir`
+    pub dir: Option<path::PathBuf>,
+    /// Also write the turns per driver by name
+    #[serde(default)]
+    pub include_names: bool,
+}
+
+impl Default for DoneConfig {
+    fn default() -> Self {
+        Self {
+            require_confirmation: Confirmation::default(),
+            many_commits: default_many_commits(),
+            emails: BTreeMap::new(),
+            placeholder_emails: false,
+        }
+    }
 }
 
 impl Config {
     pub fn ask() -> Result<Config> {
+        deadline::answer::<()>("Your name", None)?;
         log::info!("It seems like this is the first time you run mob. Welcome!");
 
         let default = Config::default();
+        let prompt = prompt::terminal();
+
+        let name = prompt.input("Your name", Some(&whoami::realname()), &check_name)?;
 
-        let name = Input::new()
-            .with_prompt("Your name")
-            .default(whoami::realname())
-            .interact()?;
+        let remote = prompt.text("Remote name you will use", &default.remote)?;
 
-        let remote = Input::new()
-            .with_prompt("Remote name you will use")
-            .default(default.remote)
-            .interact()?;
+        let silent = !prompt.confirm("Do you want to be told when the timer is up?", true)?;
 
-        let use_say_comand = Confirm::new()
-            .with_prompt("Do you want to use speech synthesis for prompts?")
-            .default(true)
-            .interact()?;
+        let use_say_comand =
+            !silent && prompt.confirm("Do you want to use speech synthesis for prompts?", true)?;
 
         let say_command = if use_say_comand {
-            Some(
-                Input::new()
-                    .with_prompt("Command to say something on your computer")
-                    .default(default.say_command.unwrap())
-                    .interact()?,
-            )
+            Some(prompt.text(
+                "Command to say something on your computer",
+                default.timer.say_command.as_deref().unwrap(),
+            )?)
         } else {
             None
         };
 
-        let use_notify_command = Confirm::new()
-            .with_prompt("Do you want to show desktop notifications?")
-            .default(true)
-            .interact()?;
+        let use_notify_command = !silent
+            && prompt.confirm(
+                "Do you want to notify with a command of your own instead of the desktop?",
+                false,
+            )?;
 
         let notify_command = if use_notify_command {
-            Some(
-                Input::new()
-                    .with_prompt("Command to notify you (empty input will disable)")
-                    .default(default.notify_command.unwrap())
-                    .interact()?,
-            )
+            Some(prompt.text("Command to notify you", "notify-send 'MESSAGE'")?)
         } else {
             None
         };
@@ -67,15 +398,64 @@ impl Config {
         Ok(Config {
             name,
             remote,
-            say_command,
-            notify_command,
+            timer: TimerConfig {
+                say_command,
+                notify_command,
+                silent,
+                broadcast: None,
+                on_end: None,
+                bell: false,
+            },
+            git: default.git,
+            display: default.display,
+            presets: default.presets,
+            done: default.done,
+            commit: default.commit,
+            handover: default.handover,
+            next: default.next,
+            rotation: default.rotation,
+            stats: default.stats,
+            metrics: default.metrics,
+            limits: default.limits,
+            update: default.update,
+            breaks: default.breaks,
+            aliases: default.aliases,
         })
     }
+    /// Where the timers are sent to, validated when the file was read
+    pub fn broadcast(&self) -> Option<Broadcast<'static>> {
+        let sink = Sink::parse(self.timer.broadcast.as_deref()?).ok()?;
+        Some(Broadcast::new(sink, &self.name, broadcast::Network))
+    }
+
+    /// The say and notify commands, none when silent
     pub fn commands(&self) -> Vec<String> {
-        vec![self.say_command.clone(), self.notify_command.clone()]
-            .into_iter()
-            .flatten()
-            .collect()
+        if self.timer.silent {
+            return vec![];
+        }
+        vec![
+            self.timer.say_command.clone(),
+            self.timer.notify_command.clone(),
+        ]
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+
+    /// Whether the built-in notification of the desktop is shown, it is unless a notify
+    /// command replaces it
+    pub fn desktop_notifications(&self) -> bool {
+        !self.timer.silent && self.timer.notify_command.is_none()
+    }
+
+    /// The command run when a timer ends, none when silent
+    pub fn on_timer_end(&self) -> Option<String> {
+        self.timer.on_end.clone().filter(|_| !self.timer.silent)
+    }
+
+    /// Whether the terminal rings when a timer ends, it doesn't when silent
+    pub fn bell(&self) -> bool {
+        self.timer.bell && !self.timer.silent
     }
 }
 
@@ -85,25 +465,1517 @@ impl Default for Config {
             //name: whoami::user(),
             name: "".to_string(),
             remote: "origin".to_string(),
-            say_command: Some("say 'MESSAGE'".into()),
-            notify_command: Some("/usr/bin/osascript -e 'display notification \"MESSAGE\"'".into()),
+            timer: TimerConfig {
+                say_command: Some("say 'MESSAGE'".into()),
+                notify_command: None,
+                silent: false,
+                broadcast: None,
+                on_end: None,
+                bell: false,
+            },
+            git: GitConfig::default(),
+            display: DisplayConfig::default(),
+            presets: preset::builtin(),
+            done: DoneConfig::default(),
+            commit: CommitConfig::default(),
+            handover: HandoverConfig::default(),
+            next: NextConfig::default(),
+            rotation: RotationConfig::default(),
+            stats: StatsConfig::default(),
+            metrics: MetricsConfig::default(),
+            limits: LimitsConfig::default(),
+            update: UpdateConfig::default(),
+            breaks: BreaksConfig::default(),
+            aliases: Aliases::new(),
+        }
+    }
+}
+
+/// A problem with one key of the config file
+#[derive(Debug, PartialEq)]
+pub struct Problem {
+    /// Like `handover.nag_after`, empty when it is about the whole file
+    pub key: String,
+    pub message: String,
+}
+
+impl Problem {
+    fn new(key: impl Into<String>, message: impl Into<String>) -> Self {
+        Problem {
+            key: key.into(),
+            message: message.into(),
+        }
+    }
+}
+
+/// Every problem found in the config file at once
+#[derive(Debug)]
+pub struct InvalidConfig {
+    pub path: path::PathBuf,
+    pub problems: Vec<Problem>,
+}
+
+impl fmt::Display for InvalidConfig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid configuration in {}", self.path.display())?;
+        for problem in &self.problems {
+            if problem.key.is_empty() {
+                write!(f, "\n  {}", problem.message)?;
+            } else {
+                write!(f, "\n  {}: {}", problem.key, problem.message)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for InvalidConfig {}
+
+fn prefixed(section: &str, problems: Vec<Problem>) -> impl Iterator<Item = Problem> + '_ {
+    problems.into_iter().map(move |problem| Problem {
+        key: format!("{}.{}", section, problem.key),
+        ..problem
+    })
+}
+
+/// Names the messages of mob would confuse with nobody in particular are refused, those in
+/// a session already keep working
+fn check_name(name: &str) -> std::result::Result<(), String> {
+    match session::placeholder(name) {
+        true => Err(format!(
+            "mob says {} for nobody in particular, pick another name",
+            name.trim()
+        )),
+        false => Ok(()),
+    }
+}
+
+fn check_command(key: &str, command: &Option<String>, problems: &mut Vec<Problem>) {
+    if let Some(command) = command {
+        if command.trim().is_empty() {
+            problems.push(Problem::new(key, "is empty, leave it out to disable it"));
+        }
+    }
+}
+
+impl TimerConfig {
+    pub fn validate(&self) -> Vec<Problem> {
+        let mut problems = vec![];
+        check_command("say_command", &self.say_command, &mut problems);
+        check_command("notify_command", &self.notify_command, &mut problems);
+        check_command("on_end", &self.on_end, &mut problems);
+        if let Some(Err(err)) = self.broadcast.as_deref().map(Sink::parse) {
+            problems.push(Problem::new("broadcast", err));
+        }
+        problems
+    }
+}
+
+impl DoneConfig {
+    pub fn validate(&self) -> Vec<Problem> {
+        let mut problems = vec![];
+        if self.many_commits < 1 {
+            problems.push(Problem::new("many_commits", "must be at least 1"));
+        }
+        for (name, email) in &self.emails {
+            if !email.contains('@') || email.contains(char::is_whitespace) {
+                problems.push(Problem::new(
+                    format!("emails.{}", name),
+                    "must be an email address like bob@example.com",
+                ));
+            }
+        }
+        problems
+    }
+}
+
+impl HandoverConfig {
+    pub fn validate(&self) -> Vec<Problem> {
+        let mut problems = vec![];
+        if self.nag_after < Minutes(1) {
+            problems.push(Problem::new("nag_after", "must be at least 1 minute"));
+        }
+        check_command("channel_command", &self.channel_command, &mut problems);
+        if matches!(self.reassign_after, Some(minutes) if minutes < Minutes(1)) {
+            problems.push(Problem::new("reassign_after", "must be at least 1 minute"));
+        }
+        problems
+    }
+}
+
+impl Config {
+    pub fn validate(&self) -> Vec<Problem> {
+        let mut problems = vec![];
+        // Empty names are asked for on first use
+        if !self.name.is_empty() && self.name.trim().is_empty() {
+            problems.push(Problem::new(
+                "name",
+                "must be your name like alice, leave it out to be asked",
+            ));
+        }
+        if self.remote.is_empty() || self.remote.contains(char::is_whitespace) {
+            problems.push(Problem::new("remote", "must be a remote name like origin"));
+        }
+        problems.extend(prefixed("timer", self.timer.validate()));
+        for (name, preset) in &self.presets {
+            if let Err(err) = preset.validate(name) {
+                problems.push(Problem::new(format!("presets.{}", name), err.to_string()));
+            }
+        }
+        problems.extend(prefixed("done", self.done.validate()));
+        problems.extend(prefixed("handover", self.handover.validate()));
+        if self.limits.max_drivers == Some(0) {
+            problems.push(Problem::new(
+                "limits.max_drivers",
+                "must be at least 1, leave it out for no limit",
+            ));
+        }
+        match (self.limits.min_drivers, self.limits.max_drivers) {
+            (Some(0), _) => problems.push(Problem::new(
+                "limits.min_drivers",
+                "must be at least 1, leave it out for no minimum",
+            )),
+            (Some(min), Some(max)) if min > max && max > 0 => problems.push(Problem::new(
+                "limits.min_drivers",
+                format!("must be at most limits.max_drivers, which is {}", max),
+            )),
+            _ => {}
+        }
+        for (name, replacement) in &self.aliases {
+            let key = format!("aliases.{}", name);
+            if name.is_empty() || name.starts_with('-') || name.contains(char::is_whitespace) {
+                problems.push(Problem::new(key, "must be one word like brb"));
+            } else if !matches!(replacement.split_whitespace().next(), Some(word) if !word.starts_with('-'))
+            {
+                problems.push(Problem::new(key, "must start with a command like break"));
+            }
+        }
+        if matches!(&self.stats.shared_dir, Some(dir) if dir.as_os_str().is_empty()) {
+            problems.push(Problem::new(
+                "stats.shared_dir",
+                "is empty, leave it out to only archive locally",
+            ));
+        }
+        if matches!(&self.metrics.dir, Some(dir) if dir.as_os_str().is_empty()) {
+            problems.push(Problem::new(
+                "metrics.dir",
+                "is empty, leave it out to write no metrics",
+            ));
+        }
+        problems
+    }
+}
+
+/// Moves keys from their old place into their section, returning the moved ones
+fn migrate(table: &mut toml::value::Table, problems: &mut Vec<Problem>) -> Vec<String> {
+    let mut moved = vec![];
+    for (old, new) in MOVED_KEYS {
+        let value = match table.remove(*old) {
+            Some(value) => value,
+            None => continue,
+        };
+        let (section, key) = new.split_once('.').expect("moved keys have a section");
+        let section = table
+            .entry(section.to_string())
+            .or_insert_with(|| toml::Value::Table(Default::default()));
+        match section.as_table_mut() {
+            Some(section) if section.contains_key(key) => problems.push(Problem::new(
+                *old,
+                format!("moved to `{}`, which is set too, remove it here", new),
+            )),
+            Some(section) => {
+                section.insert(key.to_string(), value);
+                moved.push(format!("`{}` is now `{}`", old, new));
+            }
+            None => problems.push(Problem::new(
+                *old,
+                format!("moved to `{}`, but that isn't a section", new),
+            )),
+        }
+    }
+    moved
+}
+
+/// Reads a config file, moving old keys to where they belong now
+pub fn parse(content: &str) -> Result<(Config, Vec<String>), Vec<Problem>> {
+    layered(Some(content), &[], &[])
+}
+
+/// The default config as TOML, which tells the type of each key set there
+fn schema() -> toml::Value {
+    toml::Value::try_from(Config::default()).expect("the default config is TOML")
+}
+
+/// `done.many_commits` as git config names it, `mob.done.many-commits`. Git allows no
+/// underscores in names, hyphens stand for them.
+pub fn git_key(key: &str) -> String {
+    format!("mob.{}", key.replace('_', "-"))
+}
+
+/// The key of the file for a git config name, `None` for names outside of `mob.`
+pub fn file_key(git_key: &str) -> Option<String> {
+    git_key
+        .strip_prefix("mob.")
+        .filter(|key| !key.is_empty())
+        .map(|key| key.replace('-', "_"))
+}
+
+fn lookup<'v>(value: &'v toml::Value, key: &str) -> Option<&'v toml::Value> {
+    key.split('.')
+        .try_fold(value, |value, part| value.get(part))
+}
+
+/// Booleans as git spells them
+fn git_bool(value: &str) -> Option<bool> {
+    match value.to_lowercase().as_str() {
+        "true" | "yes" | "on" | "1" => Some(true),
+        "false" | "no" | "off" | "0" | "" => Some(false),
+        _ => None,
+    }
+}
+
+/// `value` with the type of `like`, guessed from the value for keys the default leaves out
+fn typed(value: &str, like: Option<&toml::Value>) -> Result<toml::Value, String> {
+    use toml::Value;
+    match like {
+        Some(Value::Array(items)) => typed(value, items.first()),
+        Some(Value::Boolean(_)) => git_bool(value)
+            .map(Value::Boolean)
+            .ok_or_else(|| format!("`{}` is neither true nor false", value)),
+        Some(Value::Integer(_)) => match (value.parse(), value.parse::<Minutes>()) {
+            (Ok(number), _) => Ok(Value::Integer(number)),
+            // Durations like 10m are read by the key's type, other keys refuse them
+            (Err(_), Ok(_)) => Ok(Value::String(value.to_string())),
+            (Err(_), Err(_)) => Err(format!("`{}` is not a whole number", value)),
+        },
+        Some(Value::Float(_)) => value
+            .parse()
+            .map(Value::Float)
+            .map_err(|_| format!("`{}` is not a number", value)),
+        Some(_) => Ok(Value::String(value.to_string())),
+        None => Ok(match (value, value.parse()) {
+            ("true", _) => Value::Boolean(true),
+            ("false", _) => Value::Boolean(false),
+            (_, Ok(number)) => Value::Integer(number),
+            (value, Err(_)) => Value::String(value.to_string()),
+        }),
+    }
+}
+
+/// Sets the dotted `key` of `table`, adding to the list for keys that take one
+fn insert(
+    table: &mut toml::value::Table,
+    key: &str,
+    value: toml::Value,
+    list: bool,
+) -> Result<(), String> {
+    let mut parts: Vec<&str> = key.split('.').collect();
+    let last = parts.pop().expect("keys have a part");
+    let mut table = table;
+    for part in parts {
+        table = table
+            .entry(part.to_string())
+            .or_insert_with(|| toml::Value::Table(Default::default()))
+            .as_table_mut()
+            .ok_or_else(|| format!("`{}` is a value, it has no keys", part))?;
+    }
+    match table.get_mut(last) {
+        Some(toml::Value::Array(items)) if list => items.push(value),
+        _ if list => drop(table.insert(last.to_string(), toml::Value::Array(vec![value]))),
+        _ => drop(table.insert(last.to_string(), value)),
+    }
+    Ok(())
+}
+
+/// The `mob.*` entries of git config as a table like the file, later ones replacing
+/// earlier ones unless the key takes a list
+fn git_table(
+    entries: &[(String, String)],
+    schema: &toml::Value,
+) -> Result<toml::value::Table, Vec<Problem>> {
+    let mut table = toml::value::Table::new();
+    let mut problems = vec![];
+    for (name, value) in entries {
+        let key = match file_key(name) {
+            Some(key) => key,
+            None => continue,
+        };
+        let like = lookup(schema, &key);
+        let list = matches!(like, Some(toml::Value::Array(_)));
+        let inserted = typed(value, like).and_then(|value| insert(&mut table, &key, value, list));
+        if let Err(message) = inserted {
+            problems.push(Problem::new(name.as_str(), message));
+        }
+    }
+    match problems.is_empty() {
+        true => Ok(table),
+        false => Err(problems),
+    }
+}
+
+/// The key of the config a variable like `MOB_DONE_MANY_COMMITS` overrides, found by
+/// trying each underscore as a dot. `None` for variables of something else like `MOB_TIMEOUT`.
+pub fn env_key(variable: &str) -> Option<String> {
+    let name = variable.strip_prefix(ENV_PREFIX)?.to_lowercase();
+    let parts: Vec<&str> = name.split('_').collect();
+    if parts.iter().any(|part| part.is_empty()) || parts.len() > MAX_ENV_PARTS {
+        return None;
+    }
+    (0..1usize << (parts.len() - 1))
+        .map(|dots| {
+            let mut key = parts[0].to_string();
+            for (at, part) in parts.iter().enumerate().skip(1) {
+                key.push(match dots & (1 << (at - 1)) {
+                    0 => '_',
+                    _ => '.',
+                });
+                key.push_str(part);
+            }
+            key
+        })
+        .find(|key| known(key))
+}
+
+/// The `MOB_*` variables of `env` as a table like the file. Lists are separated by commas
+/// and replace those of the file.
+fn env_table(
+    env: &[(String, String)],
+    schema: &toml::Value,
+) -> Result<toml::value::Table, Vec<Problem>> {
+    let mut table = toml::value::Table::new();
+    let mut problems = vec![];
+    for (variable, value) in env {
+        let key = match env_key(variable) {
+            Some(key) => key,
+            None => continue,
+        };
+        let like = lookup(schema, &key);
+        let typed = match like {
+            Some(toml::Value::Array(_)) => value
+                .split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(|item| typed(item, like))
+                .collect::<Result<Vec<_>, _>>()
+                .map(toml::Value::Array),
+            _ => typed(value, like),
+        };
+        if let Err(message) = typed.and_then(|value| insert(&mut table, &key, value, false)) {
+            problems.push(Problem::new(variable.as_str(), message));
+        }
+    }
+    match problems.is_empty() {
+        true => Ok(table),
+        false => Err(problems),
+    }
+}
+
+/// Puts the keys of `overlay` into `base`, going into the tables both have
+fn merge(base: &mut toml::value::Table, overlay: toml::value::Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base)), toml::Value::Table(overlay)) => merge(base, overlay),
+            (_, value) => drop(base.insert(key, value)),
+        }
+    }
+}
+
+/// The config file, the default without one, with the `mob.*` entries of git config over it
+/// and the `MOB_*` variables of `env` over both
+fn layered(
+    content: Option<&str>,
+    git: &[(String, String)],
+    env: &[(String, String)],
+) -> Result<(Config, Vec<String>), Vec<Problem>> {
+    let schema = schema();
+    let mut value = match content {
+        Some(content) => {
+            toml::from_str(content).map_err(|err| vec![Problem::new("", err.to_string())])?
+        }
+        None => schema.clone(),
+    };
+    let table = value
+        .as_table_mut()
+        .ok_or_else(|| vec![Problem::new("", "is not a table")])?;
+
+    let mut problems = vec![];
+    let moved = migrate(table, &mut problems);
+    match git_table(git, &schema) {
+        Ok(overlay) => merge(table, overlay),
+        Err(more) => problems.extend(more),
+    }
+    match env_table(env, &schema) {
+        Ok(overlay) => merge(table, overlay),
+        Err(more) => problems.extend(more),
+    }
+    if !problems.is_empty() {
+        return Err(problems);
+    }
+
+    let config: Config = value
+        .try_into()
+        .map_err(|err| vec![Problem::new("", err.to_string())])?;
+    let problems = config.validate();
+    if !problems.is_empty() {
+        return Err(problems);
+    }
+    Ok((config, moved))
+}
+
+/// Where the config is read from and stored to on first use
+pub struct Source {
+    path: path::PathBuf,
+    /// The `mob.*` entries of git config, which win over the file
+    git: Vec<(String, String)>,
+    /// The `MOB_*` variables of the environment, which win over both
+    env: Vec<(String, String)>,
+}
+
+impl Source {
+    /// `~/.mob` of the user running mob, under the git config of the user and the repository
+    pub fn user() -> Self {
+        let git = git::config::entries().unwrap_or_else(|err| {
+            log::trace!("Could not read git config: {:#}", err);
+            vec![]
+        });
+        let env = std::env::vars()
+            .filter(|(variable, _)| variable.starts_with(ENV_PREFIX))
+            .collect();
+        Self::file(config_path()).with_git(git).with_env(env)
+    }
+
+    pub fn file(path: impl Into<path::PathBuf>) -> Self {
+        Source {
+            path: path.into(),
+            git: vec![],
+            env: vec![],
+        }
+    }
+
+    /// With `mob.*` entries of git config, in the order git reads them
+    pub fn with_git(self, git: Vec<(String, String)>) -> Self {
+        Source { git, ..self }
+    }
+
+    /// With variables of the environment, those like `MOB_REMOTE` override keys
+    pub fn with_env(self, env: Vec<(String, String)>) -> Self {
+        Source { env, ..self }
+    }
+
+    fn read(&self) -> io::Result<Option<String>> {
+        match fs::read_to_string(&self.path) {
+            Ok(content) => Ok(Some(content)),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    fn invalid(&self, problems: Vec<Problem>) -> InvalidConfig {
+        InvalidConfig {
+            path: self.path.clone(),
+            problems,
         }
     }
 }
 
-pub fn load() -> Result<Config> {
-    let config: Config = confy::load_path(config_path()).map_err(anyhow::Error::from)?;
+pub fn load(source: &Source) -> Result<Config> {
+    let path = &source.path;
+    let config = match source.read()? {
+        None if source.git.is_empty() && source.env.is_empty() => Config::default(),
+        content => {
+            let (config, moved) = layered(content.as_deref(), &source.git, &source.env)
+                .map_err(|problems| source.invalid(problems))?;
+            if !moved.is_empty() {
+                warnings::push(
+                    "config-moved-keys",
+                    format!(
+                        "{} in {}, the old keys still work for now",
+                        moved.join(", "),
+                        path.display()
+                    ),
+                    "0.1.7",
+                );
+            }
+            config
+        }
+    };
+
     if config.name.is_empty() {
         let config = Config::ask()?;
-        confy::store_path(config_path(), &config)?;
-        log::info!("Stored config to {}", config_path().to_str().unwrap());
+        confy::store_path(path, &config)?;
+        log::info!("Stored config to {}", path.display());
         return Ok(config);
     }
     Ok(config)
 }
 
+/// The aliases of the config without asking or storing anything, none when it can't be read.
+/// `load` tells what is wrong with it.
+pub fn aliases(source: &Source) -> Aliases {
+    source
+        .read()
+        .ok()
+        .and_then(|content| layered(content.as_deref(), &source.git, &source.env).ok())
+        .map(|(config, _)| config.aliases)
+        .unwrap_or_default()
+}
+
+/// Where `mob config set` writes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Target {
+    File,
+    Git(git::config::Scope),
+}
+
+/// Fails unless `key` is a setting of mob, which the config keeps when written out again
+fn check_known(config: &Config, key: &str) -> Result<(), Vec<Problem>> {
+    let written = toml::Value::try_from(config).expect("configs are TOML");
+    match lookup(&written, key) {
+        Some(_) => Ok(()),
+        None => Err(vec![Problem::new(key, "is not a setting of mob")]),
+    }
+}
+
+/// The config file with `key` set to `value`, checked like a file that is read
+fn set_in_file(
+    content: &str,
+    git: &[(String, String)],
+    key: &str,
+    value: &str,
+) -> Result<String, Vec<Problem>> {
+    let like = lookup(&schema(), key).cloned();
+    let list = matches!(like, Some(toml::Value::Array(_)));
+    let mut file: toml::Value =
+        toml::from_str(content).map_err(|err| vec![Problem::new("", err.to_string())])?;
+    let table = file
+        .as_table_mut()
+        .ok_or_else(|| vec![Problem::new("", "is not a table")])?;
+    typed(value, like.as_ref())
+        .and_then(|value| insert(table, key, value, list))
+        .map_err(|message| vec![Problem::new(key, message)])?;
+    // Edited in place the comments stay, unless that reads as something else
+    let written = match lookup(&file, key).and_then(|value| edit(content, key, value)) {
+        Some(edited) if toml::from_str::<toml::Value>(&edited).ok().as_ref() == Some(&file) => {
+            edited
+        }
+        _ => toml::to_string(&file).map_err(|err| vec![Problem::new("", err.to_string())])?,
+    };
+    let (config, _) = layered(Some(&written), git, &[])?;
+    check_known(&config, key)?;
+    Ok(written)
+}
+
+/// `content` with `key` set to `value` on a line of its own, keeping comments, unknown keys
+/// and their order. `None` for values that take more than a line.
+fn edit(content: &str, key: &str, value: &toml::Value) -> Option<String> {
+    let (section, last) = match key.rsplit_once('.') {
+        Some((section, last)) => (section, last),
+        None => ("", key),
+    };
+    let line = format!("{} = {}", last, literal(value)?);
+    let mut lines: Vec<String> = content.lines().map(String::from).collect();
+    let mut current = String::new();
+    // Top-level keys come before the first table
+    let mut end = match section.is_empty() {
+        true => Some(0),
+        false => None,
+    };
+    let mut found = None;
+    for (at, text) in lines.iter().enumerate() {
+        let text = text.trim();
+        if text.starts_with('[') {
+            current = text
+                .trim_start_matches('[')
+                .split(']')
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .to_string();
+            if current == section {
+                end = Some(at + 1);
+            }
+            continue;
+        }
+        if current != section || text.is_empty() || text.starts_with('#') {
+            continue;
+        }
+        end = Some(at + 1);
+        let assigns = text
+            .strip_prefix(last)
+            .is_some_and(|rest| rest.trim_start().starts_with('='));
+        if assigns {
+            found = Some(at);
+        }
+    }
+    match (found, end) {
+        (Some(at), _) => {
+            let indent = lines[at].len() - lines[at].trim_start().len();
+            lines[at] = format!("{}{}", &lines[at][..indent], line);
+        }
+        (None, Some(at)) => lines.insert(at, line),
+        (None, None) => {
+            if lines.last().is_some_and(|last| !last.trim().is_empty()) {
+                lines.push(String::new());
+            }
+            lines.push(format!("[{}]", section));
+            lines.push(line);
+        }
+    }
+    Some(lines.join("\n") + "\n")
+}
+
+/// `value` as it is written after `key = `, `None` for tables
+fn literal(value: &toml::Value) -> Option<String> {
+    match value {
+        toml::Value::Table(_) => None,
+        toml::Value::Array(items) => {
+            let items: Option<Vec<String>> = items.iter().map(literal).collect();
+            Some(format!("[{}]", items?.join(", ")))
+        }
+        value => Some(value.to_string()),
+    }
+}
+
+/// The git config name of `key`, checked with `value` added to the entries git has, and
+/// whether it takes a list
+fn set_in_git(
+    content: Option<&str>,
+    git: &[(String, String)],
+    key: &str,
+    value: &str,
+) -> Result<(String, bool), Vec<Problem>> {
+    let name = git_key(key);
+    let mut git = git.to_vec();
+    git.push((name.clone(), value.to_string()));
+    let (config, _) = layered(content, &git, &[])?;
+    check_known(&config, key)?;
+    let list = matches!(lookup(&schema(), key), Some(toml::Value::Array(_)));
+    Ok((name, list))
+}
+
+/// Sets `key` of the config, named as in the file like `done.many_commits` or as in git
+/// config like `mob.done.many-commits`. Keys that take a list get `value` added.
+pub fn set(source: &Source, key: &str, value: &str, target: Target) -> Result<()> {
+    let key = file_key(key).unwrap_or_else(|| key.to_string());
+    if key == "name" {
+        check_name(value).map_err(|err| anyhow::anyhow!("name: {}", err))?;
+    }
+    let content = source.read()?;
+    match target {
+        Target::File => {
+            let content = match content {
+                Some(content) => content,
+                None => {
+                    let config = Config {
+                        name: whoami::realname(),
+                        ..Config::default()
+                    };
+                    log::info!(
+                        "Creating {} with the defaults and {} as your name",
+                        source.path.display(),
+                        config.name
+                    );
+                    toml::to_string(&config)?
+                }
+            };
+            let written = set_in_file(&content, &source.git, &key, value)
+                .map_err(|problems| source.invalid(problems))?;
+            fs::write(&source.path, written)?;
+            log::info!("Set {} in {}", key, source.path.display());
+            let name = git_key(&key);
+            if source.git.iter().any(|(git, _)| *git == name) {
+                log::warn!("{} of git config wins over it, unset it there", name);
+            }
+            for (variable, _) in &source.env {
+                if env_key(variable).as_deref() == Some(key.as_str()) {
+                    log::warn!("{} of the environment wins over it", variable);
+                }
+            }
+        }
+        Target::Git(scope) => {
+            let (name, list) = set_in_git(content.as_deref(), &source.git, &key, value)
+                .map_err(|problems| source.invalid(problems))?;
+            git::config::set(&name, value, scope, list)?;
+            log::info!("Set {} in git config", name);
+        }
+    }
+    Ok(())
+}
+
+/// Whether `key` is a setting of mob, also one that is unset by default
+fn known(key: &str) -> bool {
+    let candidates = [
+        toml::Value::String("1".into()),
+        toml::Value::Integer(1),
+        toml::Value::Boolean(true),
+        toml::Value::Array(vec![toml::Value::String("1".into())]),
+    ];
+    candidates.iter().any(|candidate| {
+        let mut value = schema();
+        let table = value.as_table_mut().expect("the default config is a table");
+        insert(table, key, candidate.clone(), false).is_ok()
+            && value
+                .try_into::<Config>()
+                .is_ok_and(|config| check_known(&config, key).is_ok())
+    })
+}
+
+/// The config as mob reads it, without asking for a name that is missing, and whether
+/// the file exists
+fn effective(source: &Source) -> Result<(Config, bool)> {
+    let content = source.read()?;
+    let exists = content.is_some();
+    let config = match content {
+        None if source.git.is_empty() && source.env.is_empty() => Config::default(),
+        content => {
+            layered(content.as_deref(), &source.git, &source.env)
+                .map_err(|problems| source.invalid(problems))?
+                .0
+        }
+    };
+    Ok((config, exists))
+}
+
+/// The config as mob reads it, as TOML under comments that tell where it came from
+pub fn show(source: &Source) -> Result<String> {
+    let (config, exists) = effective(source)?;
+    let mut shown = match exists {
+        true => format!("# {}\n", source.path.display()),
+        false => format!(
+            "# {} doesn't exist yet, these are the defaults\n",
+            source.path.display()
+        ),
+    };
+    for (name, value) in &source.git {
+        if file_key(name).is_some() {
+            shown.push_str(&format!("# {} = {} of git config wins\n", name, value));
+        }
+    }
+    for (variable, value) in &source.env {
+        if env_key(variable).is_some() {
+            shown.push_str(&format!(
+                "# {}={} of the environment wins\n",
+                variable, value
+            ));
+        }
+    }
+    shown.push_str(&toml::to_string(&config)?);
+    Ok(shown)
+}
+
+/// The value of `key` as mob reads it, named like for `set`, `None` for a setting that
+/// isn't set. Lists have an item on each line.
+pub fn get(source: &Source, key: &str) -> Result<Option<String>> {
+    let key = file_key(key).unwrap_or_else(|| key.to_string());
+    if !known(&key) {
+        return Err(source
+            .invalid(vec![Problem::new(key, "is not a setting of mob")])
+            .into());
+    }
+    let (config, _) = effective(source)?;
+    let written = toml::Value::try_from(&config).expect("configs are TOML");
+    Ok(lookup(&written, &key).map(plain))
+}
+
+/// `value` without the quotes of TOML, for scripts
+fn plain(value: &toml::Value) -> String {
+    match value {
+        toml::Value::String(text) => text.clone(),
+        toml::Value::Array(items) => items.iter().map(plain).collect::<Vec<_>>().join("\n"),
+        toml::Value::Table(_) => toml::to_string(value)
+            .unwrap_or_default()
+            .trim_end()
+            .to_string(),
+        value => value.to_string(),
+    }
+}
+
 fn config_path() -> path::PathBuf {
     let user_dirs = UserDirs::new().unwrap();
     let home_dir = user_dirs.home_dir();
     home_dir.join(CONFIG_FILE)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FLAT: &str = r#"
+name = "alice"
+remote = "origin"
+say_command = "espeak 'MESSAGE'"
+notify_command = "notify-send 'MESSAGE'"
+auto_fetch = false
+"#;
+
+    fn problems(content: &str) -> Vec<Problem> {
+        match parse(content) {
+            Ok(_) => panic!("expected problems"),
+            Err(problems) => problems,
+        }
+    }
+
+    #[test]
+    fn flat_file_keeps_working() {
+        let (config, moved) = parse(FLAT).unwrap();
+        assert_eq!(
+            config.timer.say_command.as_deref(),
+            Some("espeak 'MESSAGE'")
+        );
+        assert_eq!(
+            config.timer.notify_command.as_deref(),
+            Some("notify-send 'MESSAGE'")
+        );
+        assert!(!config.git.auto_fetch);
+        assert_eq!(
+            moved,
+            vec![
+                "`say_command` is now `timer.say_command`",
+                "`notify_command` is now `timer.notify_command`",
+                "`auto_fetch` is now `git.auto_fetch`"
+            ]
+        );
+    }
+
+    #[test]
+    fn sections() {
+        let (config, moved) = parse(
+            r#"
+name = "alice"
+remote = "upstream"
+
+[timer]
+say = "espeak 'MESSAGE'"
+
+[git]
+auto_fetch = false
+
+[handover]
+nag_after = 5
+reassign_after = "10m"
+"#,
+        )
+        .unwrap();
+        assert!(moved.is_empty());
+        assert_eq!(
+            config.timer.say_command.as_deref(),
+            Some("espeak 'MESSAGE'")
+        );
+        assert_eq!(config.timer.notify_command, None);
+        assert!(config.desktop_notifications());
+        assert!(!config.git.auto_fetch);
+        assert_eq!(config.handover.nag_after, Minutes(5));
+        assert_eq!(config.handover.max_nags, 3);
+        assert_eq!(config.handover.reassign_after, Some(Minutes(10)));
+        assert_eq!(config.presets, preset::builtin());
+    }
+
+    #[test]
+    fn notify_command_replaces_the_desktop() {
+        let (config, _) = parse(
+            "name = \"alice\"\nremote = \"origin\"\n\n[timer]\nsay = \"say 'MESSAGE'\"\n\
+             notify = \"notify-send 'MESSAGE'\"\n",
+        )
+        .unwrap();
+        assert!(!config.desktop_notifications());
+        assert_eq!(config.commands().len(), 2);
+
+        let (silent, _) = parse(
+            "name = \"alice\"\nremote = \"origin\"\n\n[timer]\nsay = \"say 'MESSAGE'\"\n\
+             silent = true\n",
+        )
+        .unwrap();
+        assert!(!silent.desktop_notifications());
+        assert!(silent.commands().is_empty());
+    }
+
+    #[test]
+    fn ends_the_timer_with_a_command_or_the_bell() {
+        let file = "name = \"alice\"\nremote = \"origin\"\n\n[timer]\n\
+                    on_timer_end = \"paplay done.oga # {title}\"\nbell = true\n";
+        let (config, _) = parse(file).unwrap();
+        assert_eq!(
+            config.on_timer_end().as_deref(),
+            Some("paplay done.oga # {title}")
+        );
+        assert!(config.bell());
+
+        let (silent, _) = parse(&format!("{}silent = true\n", file)).unwrap();
+        assert_eq!(silent.on_timer_end(), None);
+        assert!(!silent.bell());
+
+        let problems = problems("name = \"a\"\nremote = \"origin\"\n[timer]\non_end = \" \"\n");
+        assert_eq!(problems.len(), 1);
+        assert_eq!(problems[0].key, "timer.on_end");
+    }
+
+    #[test]
+    fn minimal_file_has_defaults() {
+        let (config, _) = parse("name = \"alice\"\nremote = \"origin\"\n").unwrap();
+        assert!(config.git.auto_fetch);
+        assert_eq!(config.timer.say_command, None);
+        assert_eq!(config.done.many_commits, 5);
+    }
+
+    #[test]
+    fn moved_key_in_both_places() {
+        let problems = problems(
+            r#"
+name = "alice"
+remote = "origin"
+say_command = "say 'MESSAGE'"
+auto_fetch = true
+
+[timer]
+say_command = "espeak 'MESSAGE'"
+
+[git]
+auto_fetch = false
+"#,
+        );
+        assert_eq!(
+            problems,
+            vec![
+                Problem::new(
+                    "say_command",
+                    "moved to `timer.say_command`, which is set too, remove it here"
+                ),
+                Problem::new(
+                    "auto_fetch",
+                    "moved to `git.auto_fetch`, which is set too, remove it here"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn moved_key_into_a_value() {
+        let problems = problems("name = \"a\"\nremote = \"origin\"\nauto_fetch = true\ngit = 1\n");
+        assert_eq!(
+            problems,
+            vec![Problem::new(
+                "auto_fetch",
+                "moved to `git.auto_fetch`, but that isn't a section"
+            )]
+        );
+    }
+
+    #[test]
+    fn all_problems_at_once() {
+        let problems = problems(
+            r#"
+name = "alice"
+remote = "my remote"
+
+[timer]
+say_command = " "
+broadcast = "tcp://led.local"
+
+[presets.slow]
+work = 0
+
+[done]
+many_commits = 0
+emails = { bob = "bob at example.com" }
+
+[handover]
+nag_after = 0
+channel_command = ""
+reassign_after = 0
+
+[limits]
+max_drivers = 0
+
+[aliases]
+brb = "break 5"
+"-x" = "next"
+later = "--timeout 5 next"
+
+[metrics]
+dir = ""
+"#,
+        );
+        let keys: Vec<&str> = problems.iter().map(|p| p.key.as_str()).collect();
+        assert_eq!(
+            keys,
+            vec![
+                "remote",
+                "timer.say_command",
+                "timer.broadcast",
+                "presets.slow",
+                "done.many_commits",
+                "done.emails.bob",
+                "handover.nag_after",
+                "handover.channel_command",
+                "handover.reassign_after",
+                "limits.max_drivers",
+                "aliases.-x",
+                "aliases.later",
+                "metrics.dir"
+            ]
+        );
+
+        let rendered = InvalidConfig {
+            path: "/home/alice/.mob".into(),
+            problems,
+        }
+        .to_string();
+        assert!(rendered.starts_with("invalid configuration in /home/alice/.mob\n  remote: "));
+        assert!(rendered.contains("\n  presets.slow: preset `slow` needs a work duration"));
+    }
+
+    #[test]
+    fn names_the_wrong_key_and_what_is_right() {
+        let cases = vec![
+            ("name = \" \"\nremote = \"origin\"\n", "name", "your name like alice"),
+            ("name = \"a\"\nremote = \"\"\n", "remote", "a remote name like origin"),
+            (
+                "name = \"a\"\nremote = \"origin\"\n[presets.slow]\nwork = \"0m\"\n",
+                "presets.slow",
+                "at least one minute",
+            ),
+            (
+                "name = \"a\"\nremote = \"origin\"\n[handover]\nnag_after = 0\n",
+                "handover.nag_after",
+                "at least 1 minute",
+            ),
+        ];
+        for (content, key, message) in cases {
+            let problems = problems(content);
+            assert_eq!(problems.len(), 1, "{}", content);
+            assert_eq!(problems[0].key, key);
+            assert!(problems[0].message.contains(message), "{}", problems[0].message);
+        }
+        // Without a name it is asked for
+        assert!(parse("name = \"\"\nremote = \"origin\"\n").is_ok());
+    }
+
+    #[test]
+    fn type_errors_name_the_key() {
+        let problems =
+            problems("name = \"a\"\nremote = \"origin\"\n[handover]\nnag_after = \"soon\"\n");
+        assert_eq!(problems.len(), 1);
+        assert!(
+            problems[0].message.contains("nag_after"),
+            "{}",
+            problems[0].message
+        );
+        assert!(parse("name = ").is_err());
+    }
+
+    #[test]
+    fn stored_config_reads_back() {
+        let config = Config {
+            name: "alice".into(),
+            ..Config::default()
+        };
+        let (read, moved) = parse(&toml::to_string(&config).unwrap()).unwrap();
+        assert!(moved.is_empty());
+        assert_eq!(read.timer.say_command, config.timer.say_command);
+        assert_eq!(read.presets, config.presets);
+    }
+
+    #[test]
+    fn loads_from_the_given_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("mob.toml");
+        fs::write(&path, FLAT).unwrap();
+        let config = load(&Source::file(&path)).unwrap();
+        assert_eq!(config.name, "alice");
+        assert!(!config.git.auto_fetch);
+
+        fs::write(&path, "name = \"alice\"\nremote = \"\"\n").unwrap();
+        let err = load(&Source::file(&path)).err().unwrap();
+        assert!(err
+            .to_string()
+            .starts_with(&format!("invalid configuration in {}", path.display())));
+    }
+
+    #[test]
+    fn aliases_without_asking() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("mob.toml");
+        assert!(aliases(&Source::file(&path)).is_empty());
+        assert!(!path.exists());
+
+        fs::write(&path, format!("{}\n[aliases]\nbrb = \"break 5\"\n", FLAT)).unwrap();
+        let found = aliases(&Source::file(&path));
+        assert_eq!(found.get("brb").map(String::as_str), Some("break 5"));
+
+        // Broken files are left to load to tell about
+        fs::write(&path, "[aliases]\nbrb = \"break 5\"\n").unwrap();
+        assert!(aliases(&Source::file(&path)).is_empty());
+    }
+
+    fn entries(entries: &[(&str, &str)]) -> Vec<(String, String)> {
+        entries
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn maps_keys_to_git_names() {
+        assert_eq!(git_key("name"), "mob.name");
+        assert_eq!(git_key("done.many_commits"), "mob.done.many-commits");
+        assert_eq!(
+            file_key("mob.handover.reassign-after").as_deref(),
+            Some("handover.reassign_after")
+        );
+        assert_eq!(file_key("mob."), None);
+        assert_eq!(file_key("user.name"), None);
+        for key in &["remote", "git.auto_fetch", "limits.warn_drivers"] {
+            assert_eq!(file_key(&git_key(key)).as_deref(), Some(*key));
+        }
+    }
+
+    #[test]
+    fn git_config_wins_over_the_file() {
+        let (config, _) = layered(
+            Some(FLAT),
+            &entries(&[
+                ("mob.remote", "fork"),
+                ("mob.git.auto-fetch", "yes"),
+                ("mob.done.many-commits", "9"),
+                ("mob.handover.reassign-after", "10"),
+                ("mob.aliases.brb", "break 5"),
+                // The repository comes after the global config and wins
+                ("mob.remote", "upstream"),
+                ("user.name", "ignored"),
+            ]),
+            &[],
+        )
+        .unwrap();
+        assert_eq!(config.name, "alice");
+        assert_eq!(config.remote, "upstream");
+        assert!(config.git.auto_fetch);
+        assert_eq!(config.done.many_commits, 9);
+        assert_eq!(config.handover.reassign_after, Some(Minutes(10)));
+        assert_eq!(
+            config.timer.say_command.as_deref(),
+            Some("espeak 'MESSAGE'")
+        );
+        assert_eq!(
+            config.aliases.get("brb").map(String::as_str),
+            Some("break 5")
+        );
+
+        // Without a file git config alone is enough
+        let (config, _) = layered(None, &entries(&[("mob.name", "bob")]), &[]).unwrap();
+        assert_eq!(config.name, "bob");
+        assert_eq!(config.remote, "origin");
+
+        let dir = tempfile::tempdir().unwrap();
+        let source = Source::file(dir.path().join(".mob")).with_git(entries(&[
+            ("mob.name", "carol"),
+            ("mob.aliases.b", "break"),
+        ]));
+        assert_eq!(load(&source).unwrap().name, "carol");
+        assert_eq!(aliases(&source).get("b").map(String::as_str), Some("break"));
+    }
+
+    #[test]
+    fn variables_name_keys() {
+        let cases = vec![
+            ("MOB_NAME", Some("name")),
+            ("MOB_REMOTE", Some("remote")),
+            ("MOB_DONE_MANY_COMMITS", Some("done.many_commits")),
+            ("MOB_GIT_AUTO_FETCH", Some("git.auto_fetch")),
+            ("MOB_TIMER_SAY_COMMAND", Some("timer.say_command")),
+            ("MOB_LIMITS_MAX_DRIVERS", Some("limits.max_drivers")),
+            ("MOB_COMMIT_ALLOW_MARKERS", Some("commit.allow_markers")),
+            ("MOB_TIMEOUT", None),
+            ("MOB_DRIVER", None),
+            ("MOB_FORCE_INTERACTIVE", None),
+            ("MOB__NAME", None),
+            ("NAME", None),
+        ];
+        for (variable, expected) in cases {
+            assert_eq!(env_key(variable).as_deref(), expected, "{}", variable);
+        }
+    }
+
+    #[test]
+    fn variables_win_over_git_config_and_the_file() {
+        let (config, _) = layered(
+            Some(FLAT),
+            &entries(&[("mob.remote", "fork"), ("mob.done.many-commits", "9")]),
+            &entries(&[
+                ("MOB_NAME", "bob"),
+                ("MOB_REMOTE", "upstream"),
+                ("MOB_GIT_AUTO_FETCH", "yes"),
+                ("MOB_HANDOVER_NAG_AFTER", "10m"),
+                ("MOB_COMMIT_ALLOW_MARKERS", "*.md, docs/**"),
+                ("MOB_TIMEOUT", "60"),
+            ]),
+        )
+        .unwrap();
+        assert_eq!(config.name, "bob");
+        assert_eq!(config.remote, "upstream");
+        assert!(config.git.auto_fetch);
+        assert_eq!(config.done.many_commits, 9);
+        assert_eq!(config.handover.nag_after, Minutes(10));
+        assert_eq!(config.commit.allow_markers, vec!["*.md", "docs/**"]);
+
+        let dir = tempfile::tempdir().unwrap();
+        let source = Source::file(dir.path().join(".mob")).with_env(entries(&[
+            ("MOB_NAME", "carol"),
+            ("MOB_LIMITS_MAX_DRIVERS", "4"),
+        ]));
+        let config = load(&source).unwrap();
+        assert_eq!(
+            (config.name.as_str(), config.limits.max_drivers),
+            ("carol", Some(4))
+        );
+        assert!(!dir.path().join(".mob").exists());
+    }
+
+    #[test]
+    fn tells_the_variables_of_the_wrong_type() {
+        let problems = match layered(
+            Some(FLAT),
+            &[],
+            &entries(&[
+                ("MOB_DONE_MANY_COMMITS", "lots"),
+                ("MOB_UPDATE_CHECK", "maybe"),
+            ]),
+        ) {
+            Ok(_) => panic!("expected problems"),
+            Err(problems) => problems,
+        };
+        assert_eq!(
+            problems,
+            vec![
+                Problem::new("MOB_DONE_MANY_COMMITS", "`lots` is not a whole number"),
+                Problem::new("MOB_UPDATE_CHECK", "`maybe` is neither true nor false"),
+            ]
+        );
+    }
+
+    #[test]
+    fn tells_git_entries_of_the_wrong_type() {
+        let problems = match layered(
+            Some(FLAT),
+            &entries(&[
+                ("mob.done.many-commits", "lots"),
+                ("mob.update.check", "maybe"),
+            ]),
+            &[],
+        ) {
+            Ok(_) => panic!("expected problems"),
+            Err(problems) => problems,
+        };
+        assert_eq!(
+            problems,
+            vec![
+                Problem::new("mob.done.many-commits", "`lots` is not a whole number"),
+                Problem::new("mob.update.check", "`maybe` is neither true nor false"),
+            ]
+        );
+    }
+
+    #[test]
+    fn list_keys_take_every_entry() {
+        let schema: toml::Value = toml::from_str(
+            r#"
+name = ""
+[stats]
+teams = ["none"]
+"#,
+        )
+        .unwrap();
+        let table = git_table(
+            &entries(&[
+                ("mob.stats.teams", "red"),
+                ("mob.name", "alice"),
+                ("mob.stats.teams", "blue"),
+                ("mob.name", "bob"),
+            ]),
+            &schema,
+        )
+        .unwrap();
+        assert_eq!(
+            toml::Value::Table(table),
+            toml::from_str::<toml::Value>(
+                r#"
+name = "bob"
+[stats]
+teams = ["red", "blue"]
+"#
+            )
+            .unwrap()
+        );
+
+        let problems = git_table(
+            &entries(&[("mob.name", "alice"), ("mob.name.first", "alice")]),
+            &schema,
+        )
+        .unwrap_err();
+        assert_eq!(
+            problems,
+            vec![Problem::new(
+                "mob.name.first",
+                "`name` is a value, it has no keys"
+            )]
+        );
+    }
+
+    #[test]
+    fn sets_keys() {
+        let written = set_in_file(FLAT, &[], "limits.max_drivers", "4").unwrap();
+        let (config, _) = parse(&written).unwrap();
+        assert_eq!(config.limits.max_drivers, Some(4));
+        assert_eq!(
+            config.timer.say_command.as_deref(),
+            Some("espeak 'MESSAGE'")
+        );
+
+        assert_eq!(
+            set_in_git(Some(FLAT), &[], "handover.nag_after", "4").unwrap(),
+            ("mob.handover.nag-after".to_string(), false)
+        );
+        assert_eq!(
+            set_in_git(Some(FLAT), &[], "handover.nag_after", "1h").unwrap(),
+            ("mob.handover.nag-after".to_string(), false)
+        );
+        assert!(set_in_git(Some(FLAT), &[], "handover.max_nags", "1h").is_err());
+        assert_eq!(
+            set_in_git(Some(FLAT), &[], "handover.nag_after", "soon").unwrap_err(),
+            vec![Problem::new(
+                "mob.handover.nag-after",
+                "`soon` is not a whole number"
+            )]
+        );
+        assert_eq!(
+            set_in_git(Some(FLAT), &[], "handover.nag", "4").unwrap_err(),
+            vec![Problem::new("handover.nag", "is not a setting of mob")]
+        );
+    }
+
+    #[test]
+    fn sets_keys_in_place() {
+        let content = "# Mine\nname = \"alice\"\nremote = \"origin\"\nlater = 1 # unknown\n\n\
+                       [done]\n# Big ones\n  many_commits = 3\n\n[handover]\nask_message = true\n";
+        let cases = vec![
+            (
+                "done.many_commits",
+                "8",
+                content.replace("  many_commits = 3", "  many_commits = 8"),
+            ),
+            (
+                "remote",
+                "upstream",
+                content.replace("\"origin\"", "\"upstream\""),
+            ),
+            (
+                "done.require_confirmation",
+                "never",
+                content.replace(
+                    "many_commits = 3\n",
+                    "many_commits = 3\nrequire_confirmation = \"never\"\n",
+                ),
+            ),
+            (
+                "limits.max_drivers",
+                "4",
+                format!("{}\n[limits]\nmax_drivers = 4\n", content),
+            ),
+            (
+                "commit.allow_markers",
+                "*.md",
+                format!("{}\n[commit]\nallow_markers = [\"*.md\"]\n", content),
+            ),
+        ];
+        for (key, value, expected) in cases {
+            assert_eq!(
+                set_in_file(content, &[], key, value).unwrap(),
+                expected,
+                "{}",
+                key
+            );
+        }
+
+        // Inline tables are written out again
+        let inline = "name = \"alice\"\nremote = \"origin\"\ndone = { many_commits = 3 }\n";
+        let written = set_in_file(inline, &[], "done.many_commits", "8").unwrap();
+        assert_eq!(parse(&written).unwrap().0.done.many_commits, 8);
+    }
+
+    #[test]
+    fn gets_keys_as_mob_reads_them() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("mob.toml");
+        let source = || Source::file(&path);
+        assert_eq!(get(&source(), "remote").unwrap().as_deref(), Some("origin"));
+        assert!(show(&source())
+            .unwrap()
+            .starts_with(&format!("# {} doesn't exist yet", path.display())));
+
+        fs::write(
+            &path,
+            format!(
+                "{}\n[commit]\nallow_markers = [\"*.md\", \"docs/**\"]\n",
+                FLAT
+            ),
+        )
+        .unwrap();
+        assert_eq!(get(&source(), "name").unwrap().as_deref(), Some("alice"));
+        assert_eq!(
+            get(&source(), "mob.git.auto-fetch").unwrap().as_deref(),
+            Some("false")
+        );
+        assert_eq!(
+            get(&source(), "commit.allow_markers").unwrap().as_deref(),
+            Some("*.md\ndocs/**")
+        );
+        // Settings that are unset by default are known
+        assert_eq!(get(&source(), "limits.max_drivers").unwrap(), None);
+        assert_eq!(get(&source(), "handover.channel_command").unwrap(), None);
+        let err = get(&source(), "done.many_comits").unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("done.many_comits: is not a setting of mob"),
+            "{}",
+            err
+        );
+
+        let git = vec![("mob.remote".to_string(), "upstream".to_string())];
+        assert_eq!(
+            get(&source().with_git(git.clone()), "remote")
+                .unwrap()
+                .as_deref(),
+            Some("upstream")
+        );
+        let shown = show(&source().with_git(git)).unwrap();
+        assert!(shown.starts_with(&format!(
+            "# {}\n# mob.remote = upstream of git config wins\n",
+            path.display()
+        )));
+        assert!(shown.contains("remote = \"upstream\"\n"), "{}", shown);
+        assert!(shown.contains("auto_fetch = false\n"), "{}", shown);
+    }
+
+    #[test]
+    fn creates_the_file_to_set() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("mob.toml");
+        set(&Source::file(&path), "done.many_commits", "8", Target::File).unwrap();
+        let config = load(&Source::file(&path)).unwrap();
+        assert_eq!(config.done.many_commits, 8);
+        assert!(!config.name.is_empty());
+    }
+
+    #[test]
+    fn refuses_names_mob_says_for_nobody() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("mob.toml");
+        let err = set(&Source::file(&path), "name", "Anyone", Target::File).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "name: mob says Anyone for nobody in particular, pick another name"
+        );
+        assert!(!path.exists());
+        set(&Source::file(&path), "name", "anya", Target::File).unwrap();
+        assert_eq!(load(&Source::file(&path)).unwrap().name, "anya");
+    }
+}
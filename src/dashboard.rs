@@ -0,0 +1,280 @@
+//! The screen of `mob status --watch`: the status on top and what happened recently below
+//! it, fitted to the terminal. Small terminals only get the status.
+
+use crate::clock::Clock;
+use crate::events::Event;
+use crate::session::{History, Record};
+use crate::table;
+use chrono::{DateTime, Utc};
+use unicode_width::UnicodeWidthChar;
+
+/// Fewer rows of recent entries than this aren't worth a panel
+const MIN_ENTRIES: usize = 2;
+/// Narrower terminals only get the status
+const MIN_COLUMNS: usize = 30;
+/// The blank line and heading above the entries
+const PANEL_HEADER: usize = 2;
+
+/// Something that happened in the session, with the local time it happened at
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub at: DateTime<Utc>,
+    pub local: String,
+    pub text: String,
+}
+
+/// The records of `history` and the `events` of this clone as entries, oldest first
+pub fn entries(
+    history: &History,
+    events: &[(DateTime<Utc>, Event)],
+    clock: &dyn Clock,
+) -> Vec<Entry> {
+    let records = history.records().iter().map(|record| {
+        let text = match record {
+            Record::Turn { driver, .. } => format!("{} took over", driver),
+            Record::Break { .. } => "Break".to_string(),
+            Record::Sync { driver, .. } => format!("{} synced", driver),
+            Record::ShortTurn { driver, .. } => format!("{} handed over early", driver),
+            Record::Outside {
+                authors, commits, ..
+            } => match commits {
+                1 => format!("1 commit outside mob by {}", authors.join(", ")),
+                commits => format!("{} commits outside mob by {}", commits, authors.join(", ")),
+            },
+            Record::Reorder { by, drivers, .. } => {
+                format!("{} reordered the rotation: {}", by, drivers.join(", "))
+            }
+            Record::Identified { id, .. } => format!("The session got the id {}", id),
+        };
+        (record.at(), text)
+    });
+    let events = events.iter().map(|(at, event)| {
+        let text = match event {
+            Event::SessionAbandoned {
+                by,
+                reason: Some(reason),
+                ..
+            } => format!("{} abandoned the session: {}", by, reason),
+            Event::SessionAbandoned { by, .. } => format!("{} abandoned the session", by),
+        };
+        (*at, text)
+    });
+    let mut entries: Vec<Entry> = records
+        .chain(events)
+        .map(|(at, text)| Entry {
+            at,
+            local: clock.local(at).format("%H:%M").to_string(),
+            text,
+        })
+        .collect();
+    entries.sort_by_key(|entry| entry.at);
+    entries
+}
+
+/// The screen with `status` and below it as many of the newest `entries` as fit in `size`,
+/// rows and columns, with how long ago each was at `now`. Without a size nothing is cut.
+pub fn compose(
+    status: &str,
+    entries: &[Entry],
+    now: DateTime<Utc>,
+    size: Option<(usize, usize)>,
+) -> String {
+    let status: Vec<&str> = status.lines().collect();
+    let (rows, columns) = match size {
+        Some((rows, columns)) => (rows.saturating_sub(1), columns),
+        None => (usize::MAX, usize::MAX),
+    };
+    let room = rows.saturating_sub(status.len() + PANEL_HEADER);
+    let wanted = std::cmp::min(entries.len(), MIN_ENTRIES);
+    let shown = match room >= wanted && columns >= MIN_COLUMNS {
+        true => &entries[entries.len() - std::cmp::min(room, entries.len())..],
+        false => &[],
+    };
+
+    let mut lines: Vec<String> = status
+        .iter()
+        .take(rows)
+        .map(|line| line.to_string())
+        .collect();
+    if !shown.is_empty() {
+        lines.push(String::new());
+        lines.push("🕘 Recent".to_string());
+        for entry in shown {
+            let ago = crate::duration::ago(now - entry.at);
+            lines.push(format!("   {}  {}, {}", entry.local, entry.text, ago));
+        }
+    }
+    lines
+        .iter()
+        .map(|line| format!("{}\n", truncate(line, columns)))
+        .collect()
+}
+
+/// `line` cut to `columns` with an ellipsis, keeping escape codes so colours end where they
+/// should
+pub fn truncate(line: &str, columns: usize) -> String {
+    if table::width(line) <= columns {
+        return line.to_string();
+    }
+    let mut out = String::new();
+    let mut width = 0;
+    let mut styled = false;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // Escape codes end with a letter and take no room
+            out.push(c);
+            styled = true;
+            for c in chars.by_ref() {
+                out.push(c);
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+            continue;
+        }
+        let char_width = c.width().unwrap_or(0);
+        if width + char_width + 1 > columns {
+            break;
+        }
+        width += char_width;
+        out.push(c);
+    }
+    if columns > 0 {
+        out.push('…');
+    }
+    if styled {
+        out.push_str("\x1b[0m");
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::FakeClock;
+    use chrono::Duration;
+
+    const STATUS: &str = "🚗 alice is driving (4m 10s left)\n   \
+                          Run 'mob next' to hand over\n👥 Drivers: alice, bob";
+
+    fn recent() -> Vec<Entry> {
+        let clock = FakeClock::on_june_first(14, 40);
+        let now = clock.now_utc();
+        let history = History::default()
+            .push(Record::Turn {
+                driver: "bob".into(),
+                at: now - Duration::minutes(38),
+            })
+            .push(Record::Break {
+                at: now - Duration::minutes(10),
+            })
+            .push(Record::Turn {
+                driver: "alice".into(),
+                at: now - Duration::minutes(1),
+            });
+        let events = vec![(
+            now - Duration::minutes(25),
+            Event::SessionAbandoned {
+                session: None,
+                by: "carol".into(),
+                reason: Some("wrong repository".into()),
+                from: crate::session::State::Stopped,
+            },
+        )];
+        entries(&history, &events, &clock)
+    }
+
+    fn screen(size: Option<(usize, usize)>) -> String {
+        let now = FakeClock::on_june_first(14, 40).now_utc();
+        compose(STATUS, &recent(), now, size)
+    }
+
+    #[test]
+    fn tells_what_happened_in_order() {
+        let texts: Vec<(String, String)> = recent()
+            .into_iter()
+            .map(|entry| (entry.local, entry.text))
+            .collect();
+        assert_eq!(
+            texts,
+            vec![
+                ("14:02".into(), "bob took over".into()),
+                ("14:15".into(), "carol abandoned the session: wrong repository".into()),
+                ("14:30".into(), "Break".into()),
+                ("14:39".into(), "alice took over".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn shows_everything_when_it_fits() {
+        assert_eq!(
+            screen(Some((24, 80))),
+            "🚗 alice is driving (4m 10s left)\n   \
+             Run 'mob next' to hand over\n👥 Drivers: alice, bob\n\n🕘 Recent\n   \
+             14:02  bob took over, 38 minutes ago\n   \
+             14:15  carol abandoned the session: wrong repository, 25 minutes ago\n   \
+             14:30  Break, 10 minutes ago\n   \
+             14:39  alice took over, 1 minute ago\n"
+        );
+        assert_eq!(screen(None), screen(Some((24, 80))));
+    }
+
+    #[test]
+    fn keeps_the_newest_entries_of_short_terminals() {
+        assert_eq!(
+            screen(Some((8, 80))),
+            "🚗 alice is driving (4m 10s left)\n   \
+             Run 'mob next' to hand over\n👥 Drivers: alice, bob\n\n🕘 Recent\n   \
+             14:30  Break, 10 minutes ago\n   \
+             14:39  alice took over, 1 minute ago\n"
+        );
+    }
+
+    #[test]
+    fn cuts_long_lines_of_narrow_terminals() {
+        assert_eq!(
+            screen(Some((24, 34))),
+            "🚗 alice is driving (4m 10s left)\n   \
+             Run 'mob next' to hand over\n👥 Drivers: alice, bob\n\n🕘 Recent\n   \
+             14:02  bob took over, 38 minut…\n   \
+             14:15  carol abandoned the ses…\n   \
+             14:30  Break, 10 minutes ago\n   \
+             14:39  alice took over, 1 minu…\n"
+        );
+    }
+
+    #[test]
+    fn only_shows_the_status_on_small_terminals() {
+        let status = "🚗 alice is driving (4m 10s left)\n   \
+                      Run 'mob next' to hand over\n👥 Drivers: alice, bob\n";
+        // Too low for two entries under the status
+        assert_eq!(screen(Some((7, 80))), status);
+        // Too narrow for entries to say anything
+        assert_eq!(
+            screen(Some((24, 20))),
+            "🚗 alice is driving…\n   Run 'mob next' t…\n👥 Drivers: alice, …\n"
+        );
+        // Too low for the status too
+        assert_eq!(
+            screen(Some((3, 80))),
+            "🚗 alice is driving (4m 10s left)\n   Run 'mob next' to hand over\n"
+        );
+        // Nothing happened yet
+        let now = FakeClock::on_june_first(14, 40).now_utc();
+        assert_eq!(compose(STATUS, &[], now, Some((24, 80))), status);
+    }
+
+    #[test]
+    fn truncates_around_escape_codes() {
+        assert_eq!(truncate("alice", 5), "alice");
+        assert_eq!(truncate("alice", 4), "ali…");
+        assert_eq!(truncate("李小龙", 4), "李…");
+        assert_eq!(truncate("alice", 0), "");
+        assert_eq!(
+            truncate("\x1b[31malice\x1b[0m is driving", 8),
+            "\x1b[31malice\x1b[0m i…\x1b[0m"
+        );
+    }
+}
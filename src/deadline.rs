@@ -0,0 +1,253 @@
+//! The deadline of the whole command, given with `--timeout` or `MOB_TIMEOUT` by bots that run
+//! mob and must never hang. Under a deadline nobody is asked: prompts take their default or
+//! fail, git is killed once it is up and timers run detached instead of in the terminal.
+//! `--yes` and running without a terminal take the defaults the same way, without the
+//! deadline.
+
+use crate::{ci, prompt, record};
+use serde::{de::DeserializeOwned, Serialize};
+use std::cell::Cell;
+use std::time::{Duration, Instant};
+
+/// Exit code of a command that ran out of time, as with timeout(1)
+pub const EXIT_CODE: i32 = 124;
+pub const ENV: &str = "MOB_TIMEOUT";
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum Error {
+    #[error("the command didn't finish within its timeout")]
+    TimedOut,
+
+    #[error("\"{0}\" needs an answer, which nobody can give with a timeout")]
+    NoAnswer(String),
+
+    #[error("\"{0}\" needs an answer, which nobody can give in CI, set MOB_FORCE_INTERACTIVE=1 to be asked")]
+    NoAnswerInCi(String),
+
+    #[error("\"{0}\" needs an answer, which nobody can give with --yes or without a terminal")]
+    NoAnswerUnattended(String),
+
+    #[error("`{0}` is not a timeout in seconds")]
+    Invalid(String),
+}
+
+thread_local! {
+    static DEADLINE: Cell<Option<Instant>> = const { Cell::new(None) };
+    static DEFAULTS: Cell<bool> = const { Cell::new(false) };
+}
+
+/// The timeout from the `--timeout` flag or the environment, the flag wins
+pub fn timeout(flag: Option<u64>, env: Option<String>) -> Result<Option<Duration>, Error> {
+    let seconds = match (flag, env) {
+        (Some(seconds), _) => seconds,
+        (None, Some(env)) if env.trim().is_empty() => return Ok(None),
+        (None, Some(env)) => env
+            .trim()
+            .parse()
+            .map_err(|_| Error::Invalid(env.clone()))?,
+        (None, None) => return Ok(None),
+    };
+    if seconds == 0 {
+        return Err(Error::Invalid(seconds.to_string()));
+    }
+    Ok(Some(Duration::from_secs(seconds)))
+}
+
+/// Starts the deadline `timeout` from now, or lifts it
+pub fn set(timeout: Option<Duration>) {
+    DEADLINE.with(|deadline| deadline.set(timeout.map(|timeout| Instant::now() + timeout)));
+}
+
+pub fn active() -> bool {
+    DEADLINE.with(|deadline| deadline.get().is_some())
+}
+
+/// Time left until the deadline, zero once it passed
+pub fn remaining() -> Option<Duration> {
+    DEADLINE.with(|deadline| {
+        deadline
+            .get()
+            .map(|deadline| deadline.saturating_duration_since(Instant::now()))
+    })
+}
+
+/// Fails once the deadline has passed
+pub fn check() -> Result<(), Error> {
+    match remaining() {
+        Some(left) if left == Duration::ZERO => Err(Error::TimedOut),
+        _ => Ok(()),
+    }
+}
+
+/// Makes every prompt take its default without a deadline, as with `--yes` or when nobody
+/// is at a terminal
+pub fn take_defaults(defaults: bool) {
+    DEFAULTS.with(|current| current.set(defaults));
+}
+
+fn defaults() -> bool {
+    DEFAULTS.with(|current| current.get())
+}
+
+/// Whether someone may be asked, at a terminal, without a deadline and outside CI
+pub fn attended() -> bool {
+    !active()
+        && !defaults()
+        && !record::replaying()
+        && ci::current().interactive
+        && prompt::attended()
+}
+
+/// What a prompt answers without asking, `None` when it should ask. Under a deadline that's
+/// `default`, and an error for prompts without a safe one, as in CI or with `--yes`. A replay
+/// answers as recorded.
+pub fn answer<T: Serialize + DeserializeOwned>(
+    question: &str,
+    default: Option<T>,
+) -> Result<Option<T>, Error> {
+    if record::replaying() {
+        return match record::replayed(question).or(default) {
+            Some(answer) => Ok(Some(answer)),
+            None => Err(Error::NoAnswer(question.to_string())),
+        };
+    }
+    let in_ci = !ci::current().interactive;
+    if !active() && !in_ci && !defaults() {
+        return Ok(None);
+    }
+    match default {
+        Some(default) => {
+            log::info!("Not asking \"{}\", answering {}", question, shown(&default));
+            Ok(Some(record::answer(question, default)))
+        }
+        None if active() => Err(Error::NoAnswer(question.to_string())),
+        None if in_ci => Err(Error::NoAnswerInCi(question.to_string())),
+        None => Err(Error::NoAnswerUnattended(question.to_string())),
+    }
+}
+
+/// A default answer as the log tells it
+fn shown<T: Serialize>(answer: &T) -> String {
+    match serde_json::to_value(answer) {
+        Ok(serde_json::Value::Bool(true)) => "yes".into(),
+        Ok(serde_json::Value::Bool(false)) => "no".into(),
+        Ok(serde_json::Value::String(answer)) if !answer.is_empty() => answer,
+        Ok(serde_json::Value::String(_)) => "nothing".into(),
+        _ => "with the default".into(),
+    }
+}
+
+/// Runs `work` with the deadline moved back by `grace`, so what was started is finished whole
+pub fn with_grace<T>(grace: Duration, work: impl FnOnce() -> T) -> T {
+    let before = DEADLINE.with(|deadline| {
+        let before = deadline.get();
+        deadline.set(before.map(|before| before.max(Instant::now()) + grace));
+        before
+    });
+    let result = work();
+    DEADLINE.with(|deadline| deadline.set(before));
+    result
+}
+
+/// Whether `err` comes from running out of time
+pub fn timed_out(err: &anyhow::Error) -> bool {
+    err.chain().any(|cause| {
+        cause.downcast_ref::<Error>() == Some(&Error::TimedOut)
+            || cause
+                .downcast_ref::<std::io::Error>()
+                .map(|err| err.kind() == std::io::ErrorKind::TimedOut)
+                .unwrap_or(false)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timeouts() {
+        let seconds = |seconds| Ok(Some(Duration::from_secs(seconds)));
+        assert_eq!(timeout(None, None), Ok(None));
+        assert_eq!(timeout(Some(30), None), seconds(30));
+        assert_eq!(timeout(None, Some(" 45 ".into())), seconds(45));
+        assert_eq!(timeout(Some(30), Some("45".into())), seconds(30));
+        assert_eq!(timeout(None, Some("".into())), Ok(None));
+        assert!(timeout(None, Some("soon".into())).is_err());
+        assert!(timeout(None, Some("-1".into())).is_err());
+        assert!(timeout(Some(0), None).is_err());
+    }
+
+    #[test]
+    fn prompts_take_the_default() {
+        set(None);
+        assert_eq!(answer("Retry?", Some(true)), Ok(None));
+        assert!(check().is_ok());
+
+        set(Some(Duration::from_secs(60)));
+        assert!(!attended());
+        assert_eq!(answer("Retry?", Some(true)), Ok(Some(true)));
+        assert_eq!(
+            answer::<bool>("Reorder", None),
+            Err(Error::NoAnswer("Reorder".into()))
+        );
+        set(None);
+    }
+
+    #[test]
+    fn nobody_is_asked_in_ci() {
+        set(None);
+        ci::set(ci::RunContext::detect(|name| match name {
+            "GITHUB_ACTIONS" => Some("true".into()),
+            _ => None,
+        }));
+        assert!(!attended());
+        assert_eq!(answer("Retry?", Some(true)), Ok(Some(true)));
+        assert_eq!(
+            answer::<bool>("Reorder", None),
+            Err(Error::NoAnswerInCi("Reorder".into()))
+        );
+        ci::set(ci::RunContext::default());
+        assert_eq!(answer("Retry?", Some(true)), Ok(None));
+    }
+
+    #[test]
+    fn yes_takes_the_defaults_without_a_deadline() {
+        set(None);
+        take_defaults(true);
+        assert!(!attended());
+        assert_eq!(answer("Retry?", Some(true)), Ok(Some(true)));
+        assert_eq!(
+            answer("Summary", Some(String::new())),
+            Ok(Some(String::new()))
+        );
+        assert_eq!(
+            answer::<bool>("Reorder", None),
+            Err(Error::NoAnswerUnattended("Reorder".into()))
+        );
+        assert!(check().is_ok());
+        take_defaults(false);
+        assert_eq!(answer("Retry?", Some(true)), Ok(None));
+        assert_eq!(shown(&false), "no");
+        assert_eq!(shown(&"+10m"), "+10m");
+        assert_eq!(shown(&0), "with the default");
+    }
+
+    #[test]
+    fn expires() {
+        set(Some(Duration::from_millis(1)));
+        std::thread::sleep(Duration::from_millis(5));
+        assert_eq!(remaining(), Some(Duration::ZERO));
+        assert_eq!(check(), Err(Error::TimedOut));
+        assert!(timed_out(&anyhow::Error::from(Error::TimedOut)));
+        assert!(timed_out(&anyhow::Error::from(std::io::Error::new(
+            std::io::ErrorKind::TimedOut,
+            Error::TimedOut
+        ))));
+        assert!(!timed_out(&anyhow::anyhow!("push rejected")));
+
+        with_grace(Duration::from_secs(60), || assert!(check().is_ok()));
+        assert_eq!(check(), Err(Error::TimedOut));
+        set(None);
+    }
+}
@@ -1,3 +1,6 @@
+use crate::timekeeping::Instant;
+use crate::warnings;
+
 pub struct FormattedDuration(chrono::Duration);
 
 impl FormattedDuration {
@@ -23,6 +26,18 @@ impl FormattedDuration {
         }
     }
 
+    /// Like `1h 5m`, `1m 20s` or `45s`
+    pub fn short(&self) -> String {
+        let (h, m, s) = self.hms();
+        if h > 0 {
+            format!("{}h {}m", h, m)
+        } else if m > 0 {
+            format!("{}m {}s", m, s)
+        } else {
+            format!("{}s", s.max(0))
+        }
+    }
+
     fn hms(&self) -> (i64, i64, i64) {
         let h = self.0.num_hours();
         let m = self.0.num_minutes() - h * 60;
@@ -34,3 +49,131 @@ impl FormattedDuration {
 pub fn format(duration: chrono::Duration) -> FormattedDuration {
     FormattedDuration(duration)
 }
+
+/// How long ago something happened, in the largest unit that fits
+pub fn ago(duration: chrono::Duration) -> String {
+    let plural = |count: i64, unit: &str| match count {
+        1 => format!("1 {} ago", unit),
+        count => format!("{} {}s ago", count, unit),
+    };
+    if duration.num_days() > 0 {
+        plural(duration.num_days(), "day")
+    } else if duration.num_hours() > 0 {
+        plural(duration.num_hours(), "hour")
+    } else if duration.num_minutes() > 0 {
+        plural(duration.num_minutes(), "minute")
+    } else {
+        "just now".to_string()
+    }
+}
+
+/// The time from `since` to `now`, zero when `since` is still to come. That happens when
+/// the clock jumped back, as on laptops waking from sleep, and is warned about once.
+pub fn elapsed(since: impl Into<Instant>, now: impl Into<Instant>) -> chrono::Duration {
+    let (since, now) = (since.into(), now.into());
+    let elapsed = now - since;
+    if elapsed >= chrono::Duration::zero() {
+        return elapsed;
+    }
+    log::debug!("{} lies {} after now", since, format(-elapsed).short());
+    warnings::push(
+        "clock-skew",
+        "the clock is behind times in the session, durations since then count as zero",
+        "0.1.7",
+    );
+    chrono::Duration::zero()
+}
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+#[error("`{0}` is not a duration like 30s, 12h, 30d or 2w")]
+pub struct Invalid(String);
+
+/// A duration like `30s`, `90m`, `12h`, `30d` or `2w`
+pub fn parse(input: &str) -> Result<chrono::Duration, Invalid> {
+    use chrono::Duration;
+    let input = input.trim();
+    let invalid = || Invalid(input.to_string());
+    let unit = input.chars().last().ok_or_else(invalid)?;
+    let count = input[..input.len() - unit.len_utf8()]
+        .parse::<i64>()
+        .ok()
+        .filter(|count| *count > 0)
+        .ok_or_else(invalid)?;
+    match unit {
+        's' => Ok(Duration::seconds(count)),
+        'm' => Ok(Duration::minutes(count)),
+        'h' => Ok(Duration::hours(count)),
+        'd' => Ok(Duration::days(count)),
+        'w' => Ok(Duration::weeks(count)),
+        _ => Err(invalid()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    #[test]
+    fn parse() {
+        let cases = vec![
+            ("30d", Some(Duration::days(30))),
+            ("2w", Some(Duration::weeks(2))),
+            ("12h", Some(Duration::hours(12))),
+            ("30s", Some(Duration::seconds(30))),
+            (" 90m ", Some(Duration::minutes(90))),
+            ("0d", None),
+            ("-3d", None),
+            ("d", None),
+            ("30", None),
+            ("30y", None),
+            ("", None),
+            ("3ä", None),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(super::parse(input).ok(), expected, "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn short() {
+        let short = |duration| super::format(duration).short();
+        assert_eq!(short(Duration::seconds(80)), "1m 20s");
+        assert_eq!(short(Duration::seconds(45)), "45s");
+        assert_eq!(short(Duration::minutes(65)), "1h 5m");
+    }
+
+    #[test]
+    fn elapsed() {
+        use chrono::TimeZone;
+        let now = chrono::Utc.ymd(2021, 6, 1).and_hms(10, 0, 0);
+        crate::warnings::take();
+        assert_eq!(
+            super::elapsed(now - Duration::minutes(5), now),
+            Duration::minutes(5)
+        );
+        assert!(crate::warnings::take().is_empty());
+        // Woken from sleep with the clock 49 minutes behind
+        assert_eq!(
+            super::elapsed(now + Duration::minutes(49), now),
+            Duration::zero()
+        );
+        assert_eq!(
+            super::elapsed(now + Duration::seconds(1), now),
+            Duration::zero()
+        );
+        let warned = crate::warnings::take();
+        assert_eq!(warned.len(), 1);
+        assert_eq!(warned[0].code, "clock-skew");
+    }
+
+    #[test]
+    fn ago() {
+        assert_eq!(super::ago(Duration::seconds(59)), "just now");
+        assert_eq!(super::ago(Duration::seconds(-5)), "just now");
+        assert_eq!(super::ago(Duration::minutes(1)), "1 minute ago");
+        assert_eq!(super::ago(Duration::minutes(59)), "59 minutes ago");
+        assert_eq!(super::ago(Duration::minutes(61)), "1 hour ago");
+        assert_eq!(super::ago(Duration::hours(30)), "1 day ago");
+        assert_eq!(super::ago(Duration::days(3)), "3 days ago");
+    }
+}
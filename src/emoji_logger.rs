@@ -1,4 +1,5 @@
 extern crate log;
+use crate::palette::{self, Palette, Role};
 use env_logger::{
     self,
     fmt::{Color, Style},
@@ -13,7 +14,7 @@ pub fn init(level: &str) {
         use std::io::Write;
 
         let mut style = f.style();
-        let emoji = level_style(&mut style, record.level());
+        let emoji = level_style(&mut style, &Palette::global(), record.level());
 
         writeln!(f, " {}  {}", emoji, style.value(record.args()))
     });
@@ -21,27 +22,31 @@ pub fn init(level: &str) {
     builder.init()
 }
 
-fn level_style(style: &mut Style, level: Level) -> &'static str {
-    match level {
-        Level::Trace => {
-            style.set_color(Color::Magenta);
-            "🔍"
-        }
-        Level::Debug => {
-            style.set_color(Color::Blue);
-            "›"
-        }
-        Level::Info => {
-            style.set_color(Color::Green);
-            ">"
-        }
-        Level::Warn => {
-            style.set_color(Color::Yellow);
-            "⚠️"
-        }
-        Level::Error => {
-            style.set_color(Color::Red);
-            "⚡"
-        }
+fn level_style(style: &mut Style, palette: &Palette, level: Level) -> &'static str {
+    let (role, emoji) = match level {
+        Level::Trace => (Role::Trace, "🔍"),
+        Level::Debug => (Role::Debug, "›"),
+        Level::Info => (Role::Info, ">"),
+        Level::Warn => (Role::Warning, "⚠️"),
+        Level::Error => (Role::Error, "⚡"),
+    };
+
+    if let Some(color) = palette.color(role) {
+        style.set_color(log_color(color));
+    }
+    // The log formatter has no underline, the emoji tells the levels apart
+    style.set_bold(palette.is_bold(role) || palette.is_underlined(role));
+    emoji
+}
+
+fn log_color(color: palette::Color) -> Color {
+    match color {
+        palette::Color::Red => Color::Red,
+        palette::Color::Green => Color::Green,
+        palette::Color::Yellow => Color::Yellow,
+        palette::Color::Blue => Color::Blue,
+        palette::Color::Magenta => Color::Magenta,
+        palette::Color::Cyan => Color::Cyan,
+        palette::Color::Color256(index) => Color::Ansi256(index),
     }
 }
@@ -0,0 +1,122 @@
+//! The session as environment variables, for Makefiles and scripts that behave differently
+//! during a mob session. `mob env` prints them and every save keeps `env.sh` in the state
+//! dir up to date for scripts to source.
+
+use crate::completions::Shell;
+use crate::session::{Session, State};
+
+/// The file in the state dir, for POSIX shells
+pub const ENV_FILE: &str = "env.sh";
+
+/// The variables of `session` and their values, empty when there is nothing to tell
+pub fn variables(session: &Session) -> Vec<(&'static str, String)> {
+    let (active, state, driver) = match &session.state {
+        State::Stopped => ("0", "stopped", ""),
+        State::Working { driver } => ("1", "working", driver.as_str()),
+        State::WaitingForNext { .. } => ("1", "waiting", ""),
+        State::Paused { .. } => ("1", "paused", ""),
+    };
+    let branch = match session.state {
+        State::Stopped => "",
+        _ => session.branches.branch.as_str(),
+    };
+    vec![
+        ("MOB_ACTIVE", active.to_string()),
+        ("MOB_DRIVER", driver.to_string()),
+        ("MOB_BRANCH", branch.to_string()),
+        ("MOB_STATE", state.to_string()),
+    ]
+}
+
+/// Sets `variables` in `shell`, the POSIX shells without one
+pub fn exports(variables: &[(&str, String)], shell: Option<Shell>) -> String {
+    variables
+        .iter()
+        .map(|(name, value)| {
+            let value = quote(value, shell);
+            match shell {
+                None | Some(Shell::Bash) | Some(Shell::Zsh) => {
+                    format!("export {}={}\n", name, value)
+                }
+                Some(Shell::Fish) => format!("set -gx {} {}\n", name, value),
+                Some(Shell::PowerShell) => format!("$env:{} = {}\n", name, value),
+            }
+        })
+        .collect()
+}
+
+/// `value` in single quotes, which nothing expands in but each shell escapes differently
+pub(crate) fn quote(value: &str, shell: Option<Shell>) -> String {
+    let escaped = match shell {
+        None | Some(Shell::Bash) | Some(Shell::Zsh) => value.replace('\'', r"'\''"),
+        Some(Shell::Fish) => value.replace('\\', r"\\").replace('\'', r"\'"),
+        // Typographic quotes end a string in PowerShell too
+        Some(Shell::PowerShell) => value
+            .chars()
+            .flat_map(|c| match c {
+                '\'' | '‘' | '’' | '‚' | '‛' => vec![c, c],
+                c => vec![c],
+            })
+            .collect(),
+    };
+    format!("'{}'", escaped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tells_the_session() {
+        let session = Session {
+            state: State::Working {
+                driver: "alice".into(),
+            },
+            ..Session::default()
+        };
+        assert_eq!(
+            exports(&variables(&session), None),
+            "export MOB_ACTIVE='1'\nexport MOB_DRIVER='alice'\n\
+             export MOB_BRANCH='mob-session'\nexport MOB_STATE='working'\n"
+        );
+        assert_eq!(
+            variables(&Session::default()),
+            vec![
+                ("MOB_ACTIVE", "0".to_string()),
+                ("MOB_DRIVER", String::new()),
+                ("MOB_BRANCH", String::new()),
+                ("MOB_STATE", "stopped".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn quotes_for_each_shell() {
+        let posix = None;
+        let fish = Some(Shell::Fish);
+        let powershell = Some(Shell::PowerShell);
+        let cases = vec![
+            ("alice", posix, "'alice'"),
+            ("o'brien", posix, r"'o'\''brien'"),
+            ("$HOME `id` \\n", posix, r"'$HOME `id` \n'"),
+            ("", posix, "''"),
+            ("o'brien", fish, r"'o\'brien'"),
+            (r"back\slash", fish, r"'back\\slash'"),
+            ("$HOME (id)", fish, "'$HOME (id)'"),
+            ("o'brien", powershell, "'o''brien'"),
+            ("o’brien", powershell, "'o’’brien'"),
+            ("$env:PATH `n", powershell, "'$env:PATH `n'"),
+        ];
+        for (value, shell, expected) in cases {
+            assert_eq!(quote(value, shell), expected, "{} in {:?}", value, shell);
+        }
+        assert_eq!(
+            exports(&[("MOB_DRIVER", "alice".into())], fish),
+            "set -gx MOB_DRIVER 'alice'\n"
+        );
+        assert_eq!(
+            exports(&[("MOB_DRIVER", "alice".into())], powershell),
+            "$env:MOB_DRIVER = 'alice'\n"
+        );
+    }
+}
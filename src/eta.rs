@@ -0,0 +1,234 @@
+//! When each driver's next turn comes up if the rotation goes on as it is, for the mob to
+//! plan around their slot. Only an estimate, a turn handed over early or a lunch shifts it.
+
+use crate::schedule::DEFAULT_BREAK_MINUTES;
+use crate::session::{Session, State};
+use crate::timekeeping::{Instant, Minutes};
+use chrono::{DateTime, Duration, Utc};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Eta {
+    pub driver: String,
+    /// From now until the turn starts
+    pub wait: Duration,
+}
+
+/// The next turn of everyone in the rotation, soonest first. Drivers who are away are left
+/// out, the rotation skips them. Nothing is estimated while nobody knows who is next.
+pub fn estimate(session: &Session, now: DateTime<Utc>) -> Vec<Eta> {
+    let now = Instant::from(now);
+    let settings = session.settings.clone().unwrap_or_default();
+    let turn = std::cmp::max(settings.work_duration.duration(), Duration::zero());
+    let drivers = session.drivers.all();
+    let (current, mut at) = match &session.state {
+        State::Working { driver } => {
+            // Driving on without a mob next started another turn, the latest counts.
+            // Sessions of older versions don't know when or for how long.
+            let times = &session.times;
+            let started = times
+                .turn_started
+                .or_else(|| session.history.last_driven(driver).map(Instant::from))
+                .or(times.last_activity)
+                .unwrap_or(now);
+            let current = times.turn_minutes.map_or(turn, Minutes::duration);
+            // A turn that started on a clock ahead of ours can't have more than all of it left
+            let end = std::cmp::max(started + current, now);
+            (driver, std::cmp::min(end, now + current))
+        }
+        State::WaitingForNext { next: Some(next) } => (next, now),
+        _ => return vec![],
+    };
+    let index = match drivers.iter().position(|driver| driver == current) {
+        Some(index) => index,
+        None => return vec![],
+    };
+
+    let break_every = settings
+        .break_interval
+        .filter(|interval| interval.get() > 0)
+        .map(Minutes::duration);
+    let break_for = settings
+        .break_duration
+        .unwrap_or(DEFAULT_BREAK_MINUTES)
+        .duration();
+    let mut rested = session.times.rested().unwrap_or(now);
+    let mut etas: Vec<Eta> = vec![];
+    let waiting = !matches!(session.state, State::Working { .. });
+    // Who waits for next drives first, after them the rest in order and the one driving now
+    // once everyone else had their turn
+    let order = (0..drivers.len())
+        .map(|step| &drivers[(index + step + !waiting as usize) % drivers.len()])
+        .filter(|driver| session.away.until(driver, now.utc()).is_none());
+    for driver in order {
+        if etas.iter().any(|eta| &eta.driver == driver) {
+            continue;
+        }
+        // mob next suggests the break, the turn after it starts later
+        if break_every.is_some_and(|every| at - rested >= every) {
+            at = at + break_for;
+            rested = at;
+        }
+        etas.push(Eta {
+            driver: driver.clone(),
+            wait: at - now,
+        });
+        at = at + turn;
+    }
+    etas
+}
+
+/// Like `in ~18m`, rounded up to the minute. The turn that is up now is `now`.
+pub fn format(wait: Duration) -> String {
+    let minutes = (wait.num_seconds() + 59) / 60;
+    match minutes {
+        0 => "now".to_string(),
+        minutes if minutes < 60 => format!("in ~{}m", minutes),
+        minutes => format!("in ~{}h {}m", minutes / 60, minutes % 60),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::{away, Drivers, History, Record, Settings, Times};
+    use chrono::TimeZone;
+
+    fn at(minute: u32) -> DateTime<Utc> {
+        Utc.ymd(2021, 6, 1).and_hms(10, minute, 0)
+    }
+
+    fn session(state: State, started: u32) -> Session {
+        let driver = match &state {
+            State::Working { driver } => driver.clone(),
+            _ => "alice".into(),
+        };
+        Session {
+            state,
+            drivers: Drivers::new(vec!["alice".into(), "bob".into(), "carol".into()]),
+            settings: Some(Settings {
+                work_duration: Minutes(10),
+                ..Settings::default()
+            }),
+            history: History::default().push(Record::Turn {
+                driver,
+                at: at(started),
+            }),
+            ..Session::default()
+        }
+    }
+
+    fn working(driver: &str, started: u32) -> Session {
+        session(
+            State::Working {
+                driver: driver.into(),
+            },
+            started,
+        )
+    }
+
+    fn minutes(etas: Vec<Eta>) -> Vec<(String, i64)> {
+        etas.into_iter()
+            .map(|eta| (eta.driver, eta.wait.num_minutes()))
+            .collect()
+    }
+
+    /// What is estimated, of which session and the expected wait in minutes
+    type Case = (&'static str, Session, Vec<(&'static str, i64)>);
+
+    #[test]
+    fn estimates_the_rotation() {
+        let away = away::transition(
+            working("alice", 30),
+            "bob",
+            away::Change::Away { until: at(59) },
+            at(38),
+        )
+        .unwrap()
+        .away;
+        let breaks = Settings {
+            work_duration: Minutes(10),
+            break_interval: Some(Minutes(20)),
+            break_duration: Some(Minutes(5)),
+            ..Settings::default()
+        };
+        let cases: Vec<Case> = vec![
+            (
+                "alice drives for 2 more minutes",
+                working("alice", 30),
+                vec![("bob", 2), ("carol", 12), ("alice", 22)],
+            ),
+            (
+                "from the last driver around to the first",
+                working("carol", 30),
+                vec![("alice", 2), ("bob", 12), ("carol", 22)],
+            ),
+            (
+                "an overdue turn ends any moment",
+                working("bob", 0),
+                vec![("carol", 0), ("alice", 10), ("bob", 20)],
+            ),
+            (
+                "who is next starts now",
+                session(
+                    State::WaitingForNext {
+                        next: Some("bob".into()),
+                    },
+                    30,
+                ),
+                vec![("bob", 0), ("carol", 10), ("alice", 20)],
+            ),
+            (
+                "the rotation skips who is away",
+                Session {
+                    away: away.clone(),
+                    ..working("alice", 30)
+                },
+                vec![("carol", 2), ("alice", 12)],
+            ),
+            (
+                "driving on started a later turn",
+                Session {
+                    history: working("alice", 30).history.push(Record::Turn {
+                        driver: "alice".into(),
+                        at: at(35),
+                    }),
+                    ..working("alice", 30)
+                },
+                vec![("bob", 7), ("carol", 17), ("alice", 27)],
+            ),
+            (
+                "a break is due after the second turn",
+                Session {
+                    settings: Some(breaks),
+                    times: Times {
+                        started: Some(at(30).into()),
+                        ..Times::default()
+                    },
+                    ..working("alice", 30)
+                },
+                vec![("bob", 2), ("carol", 17), ("alice", 27)],
+            ),
+            (
+                "anyone may be next",
+                session(State::WaitingForNext { next: None }, 0),
+                vec![],
+            ),
+            ("stopped", session(State::Stopped, 0), vec![]),
+        ];
+        for (case, session, expected) in cases {
+            let expected: Vec<(String, i64)> = expected
+                .into_iter()
+                .map(|(driver, minutes)| (driver.to_string(), minutes))
+                .collect();
+            assert_eq!(minutes(estimate(&session, at(38))), expected, "{}", case);
+        }
+    }
+
+    #[test]
+    fn formats_the_wait() {
+        assert_eq!(format(Duration::zero()), "now");
+        assert_eq!(format(Duration::seconds(30)), "in ~1m");
+        assert_eq!(format(Duration::minutes(18)), "in ~18m");
+        assert_eq!(format(Duration::minutes(75)), "in ~1h 15m");
+    }
+}
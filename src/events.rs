@@ -0,0 +1,153 @@
+//! Things that happened in a session, for tools and post-mortems to pick up.
+
+use crate::session::State;
+use crate::state_dir::StateDir;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+const EVENTS_FILE: &str = "events.jsonl";
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "event")]
+pub enum Event {
+    /// `mob clean` removed a session that wasn't stopped
+    SessionAbandoned {
+        /// The id of the session, none for sessions from before ids
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        session: Option<String>,
+        by: String,
+        reason: Option<String>,
+        from: State,
+    },
+}
+
+pub trait Sink {
+    fn emit(&self, event: &Event) -> Result<()>;
+}
+
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    at: DateTime<Utc>,
+    #[serde(flatten)]
+    event: Event,
+}
+
+/// Appends every event as a JSON line to `events.jsonl` in the state directory
+pub struct EventLog {
+    state_dir: StateDir,
+}
+
+impl EventLog {
+    pub fn new(state_dir: StateDir) -> Self {
+        EventLog { state_dir }
+    }
+
+    /// The last `count` events with when they happened, oldest first. Lines that don't read
+    /// as an event, like those of a newer mob, are skipped.
+    pub fn recent(&self, count: usize) -> Vec<(DateTime<Utc>, Event)> {
+        let content = match fs::read_to_string(self.state_dir.file(EVENTS_FILE)) {
+            Ok(content) => content,
+            Err(_) => return vec![],
+        };
+        let mut events: Vec<(DateTime<Utc>, Event)> = content
+            .lines()
+            .filter_map(|line| match serde_json::from_str::<Entry>(line) {
+                Ok(entry) => Some((entry.at, entry.event)),
+                Err(err) => {
+                    log::trace!("Skipping unreadable event {}: {}", line, err);
+                    None
+                }
+            })
+            .collect();
+        events.drain(..events.len().saturating_sub(count));
+        events
+    }
+}
+
+impl Sink for EventLog {
+    fn emit(&self, event: &Event) -> Result<()> {
+        log::trace!("event {:?}", event);
+        self.state_dir.append(
+            EVENTS_FILE,
+            &Entry {
+                at: Utc::now(),
+                event: event.clone(),
+            },
+        )
+    }
+}
+
+/// Keeps emitted events for tests to look at
+#[cfg(test)]
+#[derive(Default)]
+pub struct Recorder {
+    pub events: std::cell::RefCell<Vec<Event>>,
+}
+
+#[cfg(test)]
+impl Sink for Recorder {
+    fn emit(&self, event: &Event) -> Result<()> {
+        self.events.borrow_mut().push(event.clone());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn event_log_appends_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = EventLog::new(StateDir::new(dir.path().join("mob")));
+        let event = Event::SessionAbandoned {
+            session: Some("3f2a9c1e7b04".into()),
+            by: "alice".into(),
+            reason: Some("wrong repo".into()),
+            from: State::Working {
+                driver: "bob".into(),
+            },
+        };
+        log.emit(&event).unwrap();
+        log.emit(&event).unwrap();
+
+        let content = fs::read_to_string(dir.path().join("mob").join(EVENTS_FILE)).unwrap();
+        let lines: Vec<&str> = content.lines().collect();
+        assert_eq!(lines.len(), 2);
+        let entry: Entry = serde_json::from_str(lines[0]).unwrap();
+        assert_eq!(entry.event, event);
+        assert!(lines[0].contains("\"event\":\"SessionAbandoned\""));
+        assert!(lines[0].contains("\"session\":\"3f2a9c1e7b04\""));
+    }
+
+    #[test]
+    fn reads_the_recent_events() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = EventLog::new(StateDir::new(dir.path().join("mob")));
+        assert!(log.recent(5).is_empty());
+        for by in ["alice", "bob", "carol"] {
+            log.emit(&Event::SessionAbandoned {
+                session: None,
+                by: by.into(),
+                reason: None,
+                from: State::Stopped,
+            })
+            .unwrap();
+        }
+        let path = dir.path().join("mob").join(EVENTS_FILE);
+        let content = fs::read_to_string(&path).unwrap();
+        fs::write(&path, format!("{}{{\"event\":\"Unknown\"}}\nnot json\n", content)).unwrap();
+
+        let by: Vec<String> = log
+            .recent(2)
+            .into_iter()
+            .map(|(_, event)| match event {
+                Event::SessionAbandoned { by, .. } => by,
+            })
+            .collect();
+        assert_eq!(by, vec!["bob", "carol"]);
+        assert_eq!(log.recent(10).len(), 3);
+    }
+}
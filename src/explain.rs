@@ -0,0 +1,233 @@
+//! What happens in a mob session, told with the durations and branches of the one at hand.
+//! Shown once after the first `mob start` of a user and again with `--explain`.
+
+use crate::schedule::DEFAULT_BREAK_MINUTES;
+use crate::session::{Session, State};
+use crate::timekeeping::Minutes;
+use chrono::{DateTime, Utc};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Marks that the user has seen the explainer, in their own config directory so a new
+/// repository doesn't show it again
+pub fn marker() -> Option<PathBuf> {
+    directories::ProjectDirs::from("", "", "mob").map(|dirs| dirs.config_dir().join("explained"))
+}
+
+/// True the first time it is asked for `marker`, which is then written
+pub fn first_time(marker: &Path) -> bool {
+    if marker.exists() {
+        return false;
+    }
+    let written = marker
+        .parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|_| fs::write(marker, ""));
+    if let Err(err) = written {
+        log::trace!("Could not write {}: {}", marker.display(), err);
+    }
+    true
+}
+
+/// What of the session the explainer tells
+#[derive(Debug, PartialEq)]
+pub struct Facts {
+    pub work_minutes: Minutes,
+    pub branch: String,
+    pub base_branch: String,
+    pub remote: String,
+    /// Who drives after `me`, `None` when anyone may
+    pub next: Option<String>,
+    /// Break duration and interval in minutes
+    pub breaks: Option<(Minutes, Minutes)>,
+}
+
+impl Facts {
+    pub fn of(session: &Session, me: &str, remote: &str, now: DateTime<Utc>) -> Facts {
+        let settings = session.settings.clone().unwrap_or_default();
+        let next = match &session.state {
+            State::WaitingForNext { next } => next.clone(),
+            _ if !session.drivers.contains(me) => None,
+            _ => session.drivers.next(me, &session.away, now),
+        };
+        Facts {
+            work_minutes: settings.work_duration,
+            branch: session.branches.branch.clone(),
+            base_branch: session.branches.base_branch.clone(),
+            remote: remote.to_string(),
+            next,
+            breaks: settings
+                .break_interval
+                .filter(|interval| interval.get() > 0)
+                .map(|interval| {
+                    (
+                        settings.break_duration.unwrap_or(DEFAULT_BREAK_MINUTES),
+                        interval,
+                    )
+                }),
+        }
+    }
+}
+
+fn drive(facts: &Facts) -> String {
+    format!(
+        "You drive for {} minutes on {}, the others navigate.",
+        facts.work_minutes, facts.branch
+    )
+}
+
+fn next(facts: &Facts) -> Vec<String> {
+    let next = facts.next.as_deref().unwrap_or("the next driver");
+    vec![
+        format!(
+            "When the timer ends, run mob next. Your work is pushed to {}/{} and {} is next.",
+            facts.remote, facts.branch, next
+        ),
+        format!(
+            "{} runs mob start to go on from where you left off.",
+            capitalized(next)
+        ),
+    ]
+}
+
+fn breaks(facts: &Facts) -> String {
+    match facts.breaks {
+        Some((duration, interval)) => format!(
+            "Every {} minutes mob next suggests a {} minute break, mob break takes one any time.",
+            interval, duration
+        ),
+        None => "mob break takes a break, mob break --lunch lunch.".to_string(),
+    }
+}
+
+fn done(facts: &Facts) -> String {
+    format!(
+        "When the work is finished, run mob done to squash {} onto {}.",
+        facts.branch, facts.base_branch
+    )
+}
+
+fn capitalized(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// The whole workflow, as shown after the first `mob start`
+pub fn workflow(facts: &Facts) -> String {
+    let mut steps = vec![drive(facts)];
+    steps.extend(next(facts));
+    steps.push(breaks(facts));
+    steps.push(done(facts));
+    let mut out = "How a mob session goes:\n".to_string();
+    for (step, line) in steps.iter().enumerate() {
+        out.push_str(&format!("  {}. {}\n", step + 1, line));
+    }
+    out.push_str("Run any command with --explain to read about it again.\n");
+    out
+}
+
+/// The part of the workflow about `command`, all of it for commands without their own
+pub fn section(command: &str, facts: &Facts) -> String {
+    let lines = match command {
+        "next" => next(facts),
+        "break" => vec![breaks(facts)],
+        "done" => vec![done(facts)],
+        _ => return workflow(facts),
+    };
+    lines
+        .into_iter()
+        .map(|line| format!("{}\n", line))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::{Drivers, Settings};
+    use chrono::TimeZone;
+
+    fn facts() -> Facts {
+        Facts {
+            work_minutes: Minutes(15),
+            branch: "mob-session".into(),
+            base_branch: "main".into(),
+            remote: "origin".into(),
+            next: Some("bob".into()),
+            breaks: Some((Minutes(10), Minutes(55))),
+        }
+    }
+
+    #[test]
+    fn tells_the_workflow() {
+        assert_eq!(
+            workflow(&facts()),
+            "How a mob session goes:
+  1. You drive for 15 minutes on mob-session, the others navigate.
+  2. When the timer ends, run mob next. Your work is pushed to origin/mob-session and bob is next.
+  3. Bob runs mob start to go on from where you left off.
+  4. Every 55 minutes mob next suggests a 10 minute break, mob break takes one any time.
+  5. When the work is finished, run mob done to squash mob-session onto main.
+Run any command with --explain to read about it again.
+"
+        );
+        let anyone = Facts {
+            next: None,
+            breaks: None,
+            ..facts()
+        };
+        assert_eq!(
+            section("next", &anyone),
+            "When the timer ends, run mob next. Your work is pushed to origin/mob-session and the next driver is next.
+The next driver runs mob start to go on from where you left off.
+"
+        );
+        assert_eq!(
+            section("break", &anyone),
+            "mob break takes a break, mob break --lunch lunch.\n"
+        );
+        assert_eq!(
+            section("done", &facts()),
+            "When the work is finished, run mob done to squash mob-session onto main.\n"
+        );
+        assert_eq!(section("status", &facts()), workflow(&facts()));
+    }
+
+    #[test]
+    fn takes_facts_from_the_session() {
+        let now = Utc.ymd(2021, 6, 1).and_hms(10, 0, 0);
+        let session = Session {
+            state: State::Working {
+                driver: "alice".into(),
+            },
+            drivers: Drivers::new(vec!["alice".into(), "bob".into()]),
+            settings: Some(Settings {
+                work_duration: Minutes(20),
+                break_interval: Some(Minutes(50)),
+                ..Settings::default()
+            }),
+            ..Session::default()
+        };
+        let facts = Facts::of(&session, "alice", "upstream", now);
+        assert_eq!(facts.work_minutes, Minutes(20));
+        assert_eq!(facts.remote, "upstream");
+        assert_eq!(facts.next.as_deref(), Some("bob"));
+        assert_eq!(facts.breaks, Some((DEFAULT_BREAK_MINUTES, Minutes(50))));
+
+        // Without settings or drivers nothing panics
+        let stopped = Facts::of(&Session::default(), "alice", "origin", now);
+        assert_eq!(stopped.next, None);
+        assert_eq!(stopped.breaks, None);
+    }
+
+    #[test]
+    fn shows_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let marker = dir.path().join("mob").join("explained");
+        assert!(first_time(&marker));
+        assert!(marker.exists());
+        assert!(!first_time(&marker));
+    }
+}
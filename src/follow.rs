@@ -0,0 +1,163 @@
+//! When `mob watch-remote` may bring the mob branch up to date for someone who watches
+//! without driving. It only ever fast-forwards a clean checkout of the mob branch.
+
+use crate::git::Snapshot;
+use crate::session::{Session, State};
+use chrono::Duration;
+
+/// The longest wait between fetches that keep failing
+const MAX_BACKOFF_MINUTES: i64 = 10;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Step {
+    /// The session stopped, there is nothing left to follow
+    Stop,
+    /// You drive, your work must not move under you
+    Driving,
+    /// Not now, checked again after the interval
+    Hold(Hold),
+    /// Fetch the branch and fast-forward to it
+    Follow { branch: String },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Hold {
+    /// `mob emergency` paused the session
+    Paused,
+    /// Another branch is checked out
+    OtherBranch,
+    /// Changed or new files a pull could clash with
+    Dirty,
+}
+
+/// What to do for `me` with the working tree as in `snapshot`
+pub fn decide(session: &Session, me: &str, snapshot: &Snapshot) -> Step {
+    match &session.state {
+        State::Stopped => return Step::Stop,
+        State::Working { driver } if driver == me => return Step::Driving,
+        State::Paused { .. } => return Step::Hold(Hold::Paused),
+        State::Working { .. } | State::WaitingForNext { .. } => {}
+    }
+    let branch = &session.branches.branch;
+    if !snapshot.is_on(branch) {
+        return Step::Hold(Hold::OtherBranch);
+    }
+    if !snapshot.is_clean() {
+        return Step::Hold(Hold::Dirty);
+    }
+    Step::Follow {
+        branch: branch.clone(),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Advance {
+    UpToDate,
+    FastForward,
+    /// The checkout has commits the remote doesn't, it is left as it is
+    Diverged,
+}
+
+/// How `head` gets to `tip`, given their merge `base`
+pub fn advance(head: &str, tip: &str, base: Option<&str>) -> Advance {
+    match base {
+        _ if head == tip => Advance::UpToDate,
+        Some(base) if base == head => Advance::FastForward,
+        _ => Advance::Diverged,
+    }
+}
+
+/// How long to wait after `failures` fetches in a row failed, doubling up to ten minutes
+pub fn backoff(interval: Duration, failures: u32) -> Duration {
+    let max = Duration::minutes(MAX_BACKOFF_MINUTES);
+    let wait = interval * 2i32.saturating_pow(failures.min(16));
+    std::cmp::min(wait, std::cmp::max(max, interval))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(state: State) -> Session {
+        Session {
+            state,
+            ..Session::default()
+        }
+    }
+
+    fn working(driver: &str) -> State {
+        State::Working {
+            driver: driver.into(),
+        }
+    }
+
+    fn on(branch: &str) -> Snapshot {
+        Snapshot {
+            branch: Some(branch.into()),
+            ..Snapshot::default()
+        }
+    }
+
+    #[test]
+    fn follows_only_a_clean_mob_branch_of_someone_else() {
+        let follow = Step::Follow {
+            branch: "mob-session".into(),
+        };
+        let waiting = State::WaitingForNext { next: None };
+        let paused = State::Paused {
+            reason: "incident".into(),
+            driver: None,
+            remaining: None,
+        };
+        let dirty = |snapshot: Snapshot| Snapshot {
+            unstaged: 1,
+            changed: 1,
+            ..snapshot
+        };
+        let untracked = Snapshot {
+            untracked: 1,
+            ..on("mob-session")
+        };
+        let detached = Snapshot::default();
+        let cases = vec![
+            (working("bob"), on("mob-session"), follow.clone()),
+            (waiting.clone(), on("mob-session"), follow),
+            (working("alice"), on("mob-session"), Step::Driving),
+            (working("alice"), dirty(on("main")), Step::Driving),
+            (State::Stopped, on("mob-session"), Step::Stop),
+            (State::Stopped, dirty(on("main")), Step::Stop),
+            (paused, on("mob-session"), Step::Hold(Hold::Paused)),
+            (working("bob"), on("main"), Step::Hold(Hold::OtherBranch)),
+            (working("bob"), detached, Step::Hold(Hold::OtherBranch)),
+            (waiting, dirty(on("main")), Step::Hold(Hold::OtherBranch)),
+            (working("bob"), dirty(on("mob-session")), Step::Hold(Hold::Dirty)),
+            (working("bob"), untracked, Step::Hold(Hold::Dirty)),
+        ];
+        for (state, snapshot, expected) in cases {
+            let step = decide(&session(state.clone()), "alice", &snapshot);
+            assert_eq!(step, expected, "{:?} on {:?}", state, snapshot);
+        }
+    }
+
+    #[test]
+    fn only_fast_forwards() {
+        assert_eq!(advance("a1", "a1", Some("a1")), Advance::UpToDate);
+        assert_eq!(advance("a1", "b2", Some("a1")), Advance::FastForward);
+        // Local commits on top of the remote
+        assert_eq!(advance("b2", "a1", Some("a1")), Advance::Diverged);
+        assert_eq!(advance("b2", "c3", Some("a1")), Advance::Diverged);
+        assert_eq!(advance("b2", "c3", None), Advance::Diverged);
+    }
+
+    #[test]
+    fn backs_off() {
+        let interval = Duration::seconds(30);
+        assert_eq!(backoff(interval, 0), interval);
+        assert_eq!(backoff(interval, 1), Duration::minutes(1));
+        assert_eq!(backoff(interval, 3), Duration::minutes(4));
+        assert_eq!(backoff(interval, 5), Duration::minutes(10));
+        assert_eq!(backoff(interval, 40), Duration::minutes(10));
+        // Longer intervals than the cap stay as they are
+        assert_eq!(backoff(Duration::minutes(20), 2), Duration::minutes(20));
+    }
+}
@@ -0,0 +1,99 @@
+//! The `mob.*` entries of `git config`, a source of the mob config next to `~/.mob`
+
+use crate::{command, os};
+use anyhow::Result;
+
+/// Where `set` writes an entry
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Scope {
+    /// `.git/config` of the repository in the current directory
+    Repository,
+    /// `~/.gitconfig` of the user
+    Global,
+}
+
+/// The `mob.*` entries of the system, global and repository config in the order git reads
+/// them, so later ones win. Outside of a repository there are only the first two.
+pub fn entries() -> Result<Vec<(String, String)>> {
+    let output =
+        command::Command::new(os::command("git")).run(["config", "--get-regexp", r"^mob\."])?;
+    match output.status.code() {
+        Some(0) => Ok(parse(&output.stdout)),
+        // Nothing matched
+        Some(1) => Ok(vec![]),
+        _ => Err(output.into_error().into()),
+    }
+}
+
+/// Reads the lines `git config --get-regexp` prints, a key and its value after the first
+/// space. A key alone was set without a value, which git takes as true.
+pub fn parse(output: &str) -> Vec<(String, String)> {
+    output
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(|line| match line.split_once(' ') {
+            Some((key, value)) => (key.to_string(), value.to_string()),
+            None => (line.to_string(), "true".to_string()),
+        })
+        .collect()
+}
+
+/// The arguments of `git config` for `set`
+fn set_args<'a>(key: &'a str, value: &'a str, scope: Scope, add: bool) -> Vec<&'a str> {
+    let mut args = vec!["config"];
+    if scope == Scope::Global {
+        args.push("--global");
+    }
+    if add {
+        args.push("--add");
+    }
+    args.extend([key, value]);
+    args
+}
+
+/// Sets `key`, or adds another value to it for keys that take a list
+pub fn set(key: &str, value: &str, scope: Scope, add: bool) -> Result<()> {
+    let args = set_args(key, value, scope, add);
+    log::debug!("git {}", args.join(" "));
+    command::Command::new(os::command("git")).run_checked(args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_get_regexp_output() {
+        let output = "mob.name alice\n\
+                      mob.timer.say-command say 'MESSAGE' now\n\
+                      mob.update.check\n\
+                      mob.remote origin\n\
+                      mob.remote upstream\n";
+        assert_eq!(
+            parse(output),
+            vec![
+                ("mob.name".to_string(), "alice".to_string()),
+                (
+                    "mob.timer.say-command".to_string(),
+                    "say 'MESSAGE' now".to_string()
+                ),
+                ("mob.update.check".to_string(), "true".to_string()),
+                ("mob.remote".to_string(), "origin".to_string()),
+                ("mob.remote".to_string(), "upstream".to_string()),
+            ]
+        );
+        assert!(parse("").is_empty());
+    }
+
+    #[test]
+    fn sets_in_a_scope() {
+        assert_eq!(
+            set_args("mob.name", "alice", Scope::Repository, false),
+            vec!["config", "mob.name", "alice"]
+        );
+        assert_eq!(
+            set_args("mob.stats.teams", "red", Scope::Global, true),
+            vec!["config", "--global", "--add", "mob.stats.teams", "red"]
+        );
+    }
+}
@@ -0,0 +1,276 @@
+//! In-memory doubles of the git facade for tests.
+
+use super::store::{Error, Store};
+use super::{Git, Lfs, Snapshot};
+use anyhow::{anyhow, Result};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Records every git invocation instead of running it.
+pub struct FakeGit {
+    pub calls: RefCell<Vec<Vec<String>>>,
+    pub snapshot: Snapshot,
+    /// How often the working tree was queried
+    pub snapshots: RefCell<usize>,
+    pub branches: HashSet<String>,
+    pub remotes: Vec<String>,
+    pub log: Vec<String>,
+    /// What `log_authors` answers for any range
+    pub authors: Vec<String>,
+    pub root: Option<String>,
+    /// Local branches and the commits they point to
+    pub revs: HashMap<String, String>,
+    /// What `ls-remote` answers, by branch
+    pub remote_tips: HashMap<String, String>,
+    /// Tree and parents of each known commit
+    pub commits: HashMap<String, (String, Vec<String>)>,
+    /// Where each remote points to
+    pub urls: HashMap<String, String>,
+    /// What `file_sizes` answers for any commit
+    pub files: Vec<(String, u64)>,
+    /// What `merge_base` answers for any two commits, `None` for unrelated histories
+    pub merge_base: Option<String>,
+    pub lfs: Lfs,
+    /// What `lfs_unconverted` answers
+    pub lfs_unconverted: Vec<String>,
+    /// What `conflicted` answers
+    pub conflicted: Vec<String>,
+    /// What `staged_diff` answers
+    pub staged: String,
+    /// What `staged_numstat` answers, and with `ignore_whitespace`
+    pub numstat: String,
+    pub numstat_ignoring_whitespace: String,
+    /// What `staged_check` answers
+    pub whitespace_check: String,
+    /// The files of the working tree that were written or can be read
+    pub worktree: RefCell<BTreeMap<String, Vec<u8>>>,
+    /// Calls that fail once, by how their arguments start, after how many such calls
+    /// succeeded and with what git printed
+    pub failing: RefCell<Vec<(String, usize, String)>>,
+}
+
+impl Default for FakeGit {
+    fn default() -> Self {
+        FakeGit {
+            calls: RefCell::new(vec![]),
+            snapshot: Snapshot {
+                branch: Some("master".into()),
+                ..Snapshot::default()
+            },
+            snapshots: RefCell::new(0),
+            branches: HashSet::new(),
+            remotes: vec!["origin".into()],
+            log: vec![],
+            authors: vec![],
+            root: Some("c0ffee".into()),
+            revs: HashMap::new(),
+            remote_tips: HashMap::new(),
+            commits: HashMap::new(),
+            urls: HashMap::new(),
+            files: vec![],
+            merge_base: Some("c0ffee".into()),
+            lfs: Lfs::Unused,
+            lfs_unconverted: vec![],
+            conflicted: vec![],
+            staged: String::new(),
+            numstat: String::new(),
+            numstat_ignoring_whitespace: String::new(),
+            whitespace_check: String::new(),
+            worktree: RefCell::new(BTreeMap::new()),
+            failing: RefCell::new(vec![]),
+        }
+    }
+}
+
+impl FakeGit {
+    pub fn with_branches(mut self, branches: &[&str]) -> Self {
+        self.branches = branches.iter().map(|b| b.to_string()).collect();
+        self
+    }
+
+    /// Has a call starting with `args` fail with `stderr`, once `skip` of them succeeded
+    pub fn fail(&self, args: &str, skip: usize, stderr: &str) {
+        self.failing
+            .borrow_mut()
+            .push((args.to_string(), skip, stderr.to_string()));
+    }
+
+    pub fn calls(&self) -> Vec<String> {
+        self.calls
+            .borrow()
+            .iter()
+            .map(|argv| argv.join(" "))
+            .collect()
+    }
+}
+
+impl Git for FakeGit {
+    fn run(&self, args: &[&str]) -> Result<()> {
+        self.calls
+            .borrow_mut()
+            .push(args.iter().map(|arg| arg.to_string()).collect());
+        let call = args.join(" ");
+        let mut failing = self.failing.borrow_mut();
+        let index = match failing
+            .iter()
+            .position(|(start, _, _)| call.starts_with(start.as_str()))
+        {
+            Some(index) => index,
+            None => return Ok(()),
+        };
+        if failing[index].1 > 0 {
+            failing[index].1 -= 1;
+            return Ok(());
+        }
+        let (_, _, stderr) = failing.remove(index);
+        Err(anyhow!(
+            "process exited with status: exit status: 1\nstderr:\n{}",
+            stderr
+        ))
+    }
+
+    fn snapshot(&self) -> Result<Snapshot> {
+        *self.snapshots.borrow_mut() += 1;
+        Ok(self.snapshot.clone())
+    }
+
+    fn log_oneline(&self, _range: &str) -> Result<Vec<String>> {
+        Ok(self.log.clone())
+    }
+
+    fn log_authors(&self, _range: &str) -> Result<Vec<String>> {
+        Ok(self.authors.clone())
+    }
+
+    fn diffstat(&self, _range: &str) -> Result<String> {
+        Ok(format!(" {} files changed", self.log.len()))
+    }
+
+    fn file_sizes(&self, _commit: &str) -> Result<Vec<(String, u64)>> {
+        Ok(self.files.clone())
+    }
+
+    fn has_branch(&self, branch: &str) -> Result<bool> {
+        Ok(self.branches.contains(branch))
+    }
+
+    fn branches(&self) -> Result<Vec<String>> {
+        let mut branches: Vec<String> = self.branches.iter().cloned().collect();
+        branches.sort();
+        Ok(branches)
+    }
+
+    fn remotes(&self) -> Result<Vec<String>> {
+        Ok(self.remotes.clone())
+    }
+
+    fn remote_url(&self, remote: &str) -> Result<Option<String>> {
+        Ok(self.urls.get(remote).cloned())
+    }
+
+    fn root_commit(&self) -> Result<Option<String>> {
+        Ok(self.root.clone())
+    }
+
+    fn rev_parse(&self, rev: &str) -> Result<Option<String>> {
+        Ok(self.revs.get(rev).cloned())
+    }
+
+    fn remote_tip(&self, _remote: &str, branch: &str) -> Result<Option<String>> {
+        Ok(self.remote_tips.get(branch).cloned())
+    }
+
+    fn commit_tree_id(&self, commit: &str) -> Result<String> {
+        self.commits
+            .get(commit)
+            .map(|(tree, _)| tree.clone())
+            .ok_or_else(|| anyhow!("unknown commit {}", commit))
+    }
+
+    fn commit_parents(&self, commit: &str) -> Result<Vec<String>> {
+        self.commits
+            .get(commit)
+            .map(|(_, parents)| parents.clone())
+            .ok_or_else(|| anyhow!("unknown commit {}", commit))
+    }
+
+    fn merge_base(&self, _one: &str, _other: &str) -> Result<Option<String>> {
+        Ok(self.merge_base.clone())
+    }
+
+    fn lfs(&self) -> Result<Lfs> {
+        Ok(self.lfs)
+    }
+
+    fn lfs_unconverted(&self) -> Result<Vec<String>> {
+        Ok(self.lfs_unconverted.clone())
+    }
+
+    fn conflicted(&self) -> Result<Vec<String>> {
+        Ok(self.conflicted.clone())
+    }
+
+    fn staged_diff(&self) -> Result<String> {
+        Ok(self.staged.clone())
+    }
+
+    fn staged_numstat(&self, ignore_whitespace: bool) -> Result<String> {
+        match ignore_whitespace {
+            true => Ok(self.numstat_ignoring_whitespace.clone()),
+            false => Ok(self.numstat.clone()),
+        }
+    }
+
+    fn staged_check(&self) -> Result<String> {
+        Ok(self.whitespace_check.clone())
+    }
+
+    fn read_file(&self, path: &str) -> Result<Vec<u8>> {
+        self.worktree
+            .borrow()
+            .get(path)
+            .cloned()
+            .ok_or_else(|| anyhow!("no file {}", path))
+    }
+
+    fn write_file(&self, path: &str, data: &[u8]) -> Result<()> {
+        self.worktree
+            .borrow_mut()
+            .insert(path.to_string(), data.to_vec());
+        Ok(())
+    }
+}
+
+/// Keeps the stored documents in memory.
+#[derive(Default)]
+pub struct MemoryStore {
+    pub documents: RefCell<BTreeMap<String, Vec<u8>>>,
+    pub saves: RefCell<usize>,
+}
+
+impl Store for MemoryStore {
+    fn load(&self, document: &str) -> Result<Vec<u8>, Error> {
+        self.documents
+            .borrow()
+            .get(document)
+            .cloned()
+            .ok_or(Error::Missing)
+    }
+
+    fn save(&self, document: &str, data: &[u8]) -> Result<(), Error> {
+        *self.saves.borrow_mut() += 1;
+        self.documents
+            .borrow_mut()
+            .insert(document.to_string(), data.to_vec());
+        Ok(())
+    }
+
+    fn clean(&self, document: &str) -> Result<(), Error> {
+        self.documents.borrow_mut().remove(document);
+        Ok(())
+    }
+
+    fn documents(&self) -> Result<Vec<String>, Error> {
+        Ok(self.documents.borrow().keys().cloned().collect())
+    }
+}
@@ -0,0 +1,76 @@
+use super::store::{Error, Store, SESSION_DOCUMENT};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+pub const LOCAL_SESSION_FILE: &str = ".mob-local.json";
+
+/// Keeps the session in plain files for mobs that don't use git at all.
+pub struct FileStore {
+    dir: PathBuf,
+}
+
+impl FileStore {
+    pub fn new(dir: &Path) -> Self {
+        FileStore { dir: dir.into() }
+    }
+
+    /// `.mob-local.json` for the session, `.mob-local-<document>.json` for the others
+    fn path(&self, document: &str) -> PathBuf {
+        if document == SESSION_DOCUMENT {
+            self.dir.join(LOCAL_SESSION_FILE)
+        } else {
+            self.dir.join(format!(".mob-local-{}.json", document))
+        }
+    }
+
+    /// The document kept in the file `name`, `None` for files that aren't mob's
+    fn document(name: &str) -> Option<String> {
+        if name == LOCAL_SESSION_FILE {
+            return Some(SESSION_DOCUMENT.to_string());
+        }
+        let document = name.strip_prefix(".mob-local-")?.strip_suffix(".json")?;
+        Some(document.to_string())
+    }
+
+    pub fn exists(dir: &Path) -> bool {
+        dir.join(LOCAL_SESSION_FILE).is_file()
+    }
+}
+
+impl Store for FileStore {
+    fn load(&self, document: &str) -> Result<Vec<u8>, Error> {
+        match fs::read(self.path(document)) {
+            Ok(data) => Ok(data),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Err(Error::Missing),
+            Err(err) => Err(Error::Io(err)),
+        }
+    }
+
+    fn save(&self, document: &str, data: &[u8]) -> Result<(), Error> {
+        // Write next to the target and rename so a crash never leaves half a session
+        let path = self.path(document);
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, data)?;
+        fs::rename(&tmp, &path)?;
+        Ok(())
+    }
+
+    fn clean(&self, document: &str) -> Result<(), Error> {
+        match fs::remove_file(self.path(document)) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => Err(Error::Io(err)),
+            _ => Ok(()),
+        }
+    }
+
+    fn documents(&self) -> Result<Vec<String>, Error> {
+        let mut documents = vec![];
+        for entry in fs::read_dir(&self.dir)? {
+            if let Some(document) = entry?.file_name().to_str().and_then(Self::document) {
+                documents.push(document);
+            }
+        }
+        documents.sort();
+        Ok(documents)
+    }
+}
@@ -0,0 +1,88 @@
+//! Telling files Git LFS should store from the blobs git would store instead, from
+//! `.gitattributes`, `git check-attr -z filter` and the staged content.
+
+use serde::{Deserialize, Serialize};
+
+/// Larger staged content is never a pointer, the spec caps them at 1024 bytes
+pub const POINTER_MAX: u64 = 1024;
+
+const POINTER_VERSION: &str = "version https://git-lfs.github.com/spec/v1";
+
+/// How ready the repository is for committing the files LFS tracks
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Lfs {
+    /// `.gitattributes` doesn't mention LFS
+    Unused,
+    /// The repository uses LFS but `git lfs` doesn't run
+    NotInstalled,
+    /// `git lfs` runs but `git lfs install` didn't set up its filter
+    NotInitialized,
+    Ready,
+}
+
+/// Whether an attributes file has any pattern use the LFS filter
+pub fn tracks(gitattributes: &str) -> bool {
+    gitattributes
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.starts_with('#'))
+        .any(|line| {
+            line.split_whitespace()
+                .skip(1)
+                .any(|attr| attr == "filter=lfs")
+        })
+}
+
+/// The paths `git check-attr -z filter` found to have the LFS filter
+pub fn filtered(check_attr: &str) -> Vec<String> {
+    let fields: Vec<&str> = check_attr.split('\0').collect();
+    fields
+        .chunks_exact(3)
+        .filter(|entry| entry[2] == "lfs")
+        .map(|entry| entry[0].to_string())
+        .collect()
+}
+
+/// Whether staged content is an LFS pointer rather than the file itself
+pub fn is_pointer(content: &str) -> bool {
+    content.len() as u64 <= POINTER_MAX && content.starts_with(POINTER_VERSION)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ATTRIBUTES: &str = "# Large assets go to LFS\n\
+                              *.psd filter=lfs diff=lfs merge=lfs -text\n\
+                              *.sh text eol=lf\n";
+
+    #[test]
+    fn finds_lfs_patterns() {
+        assert!(tracks(ATTRIBUTES));
+        assert!(!tracks("*.sh text eol=lf\n"));
+        assert!(!tracks("# *.psd filter=lfs\n"));
+        assert!(!tracks(""));
+        // A pattern named like the attribute isn't one
+        assert!(!tracks("filter=lfs text\n"));
+    }
+
+    #[test]
+    fn parses_check_attr() {
+        let output = "art/logo.psd\0filter\0lfs\0\
+                      src/main.rs\0filter\0unspecified\0\
+                      art/with space.psd\0filter\0lfs\0";
+        assert_eq!(filtered(output), vec!["art/logo.psd", "art/with space.psd"]);
+        assert!(filtered("").is_empty());
+    }
+
+    #[test]
+    fn recognizes_pointers() {
+        let pointer = "version https://git-lfs.github.com/spec/v1\n\
+                       oid sha256:4d7a214614ab2935c943f9e0ff69d22eadbb8f32b1258daaa5e2ca24d17e2393\n\
+                       size 12345\n";
+        assert!(is_pointer(pointer));
+        assert!(!is_pointer("8BPS layers and pixels"));
+        let padded = format!("{:<width$}", pointer, width = POINTER_MAX as usize + 1);
+        assert!(!is_pointer(&padded));
+    }
+}
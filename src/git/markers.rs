@@ -0,0 +1,192 @@
+//! Conflict markers in what is staged, found in `git diff --cached` so exactly what will be
+//! committed is checked and not the working tree.
+
+/// Added lines that start a conflict, its base in diff3 style, or end it. The separator
+/// between the sides is left out, Markdown and reStructuredText underline headings with it
+/// and it never comes without the others.
+const MARKERS: &[&str] = &["<<<<<<<", "|||||||", ">>>>>>>"];
+
+/// The files of `diff` with added lines that are conflict markers, in the order of the diff.
+/// Binary files have no lines to check.
+pub fn find(diff: &str) -> Vec<String> {
+    let mut found = vec![];
+    let mut file: Option<String> = None;
+    let mut markers = false;
+    // Added lines can look like headers too
+    let mut in_hunk = false;
+    let mut end = |file: &mut Option<String>, markers: &mut bool| {
+        if let Some(file) = file.take().filter(|_| *markers) {
+            found.push(file);
+        }
+        *markers = false;
+    };
+    for line in diff.lines() {
+        if line.starts_with("diff --git ") {
+            end(&mut file, &mut markers);
+            in_hunk = false;
+        } else if line.starts_with("@@") {
+            in_hunk = true;
+        } else if let Some(path) = line.strip_prefix("+++ ").filter(|_| !in_hunk) {
+            let path = path.trim_end_matches('\t');
+            file = path
+                .strip_prefix("b/")
+                .or_else(|| quoted(path))
+                .map(String::from);
+        } else if let Some(added) = line.strip_prefix('+').filter(|_| in_hunk) {
+            let added = added.trim_end_matches('\r');
+            markers |= MARKERS.iter().any(|marker| is_marker(added, marker));
+        }
+    }
+    end(&mut file, &mut markers);
+    found
+}
+
+/// Whether `line` is `marker` alone or followed by a space and a label, like
+/// `<<<<<<< HEAD`. Longer runs of the character are something else.
+fn is_marker(line: &str, marker: &str) -> bool {
+    match line.strip_prefix(marker) {
+        Some(rest) => rest.is_empty() || rest.starts_with(' '),
+        None => false,
+    }
+}
+
+/// The path of `+++ "b/with\ttab"`, which git quotes for unusual characters. Escapes are
+/// left as they are, the path is only shown.
+fn quoted(path: &str) -> Option<&str> {
+    path.strip_prefix("\"b/")?.strip_suffix('"')
+}
+
+/// Whether `path` matches one of `patterns`. As in `.gitignore`, a pattern without a slash
+/// matches the file name anywhere, `*` matches within a directory and `**` across them.
+pub fn allowed(path: &str, patterns: &[String]) -> bool {
+    patterns.iter().any(|pattern| {
+        let pattern = pattern.trim_start_matches('/');
+        match pattern.contains('/') {
+            true => glob(pattern.as_bytes(), path.as_bytes()),
+            false => glob(
+                pattern.as_bytes(),
+                path.rsplit('/').next().unwrap_or(path).as_bytes(),
+            ),
+        }
+    })
+}
+
+fn glob(pattern: &[u8], path: &[u8]) -> bool {
+    match pattern {
+        [] => path.is_empty(),
+        [b'*', b'*', rest @ ..] => {
+            let rest = rest.strip_prefix(b"/").unwrap_or(rest);
+            (0..=path.len()).any(|at| glob(rest, &path[at..]))
+        }
+        [b'*', rest @ ..] => (0..=path.len())
+            .take_while(|at| *at == 0 || path[at - 1] != b'/')
+            .any(|at| glob(rest, &path[at..])),
+        [b'?', rest @ ..] => matches!(path, [c, more @ ..] if *c != b'/' && glob(rest, more)),
+        [c, rest @ ..] => matches!(path, [p, more @ ..] if p == c && glob(rest, more)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A conflict left in code, a document explaining conflicts, a heading and an image
+    const DIFF: &str = "\
+diff --git a/src/lib.rs b/src/lib.rs
+index 3b18e51..a2c8d2e 100644
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -10,0 +11,5 @@ pub fn run() {
++<<<<<<< HEAD
++    start();
++=======
++    begin();
++>>>>>>> feature
+diff --git a/docs/merging.md b/docs/merging.md
+new file mode 100644
+index 0000000..e69de29
+--- /dev/null
++++ b/docs/merging.md
+@@ -0,0 +1,7 @@
++Resolving conflicts
++===================
++
++Git marks them like this:
++<<<<<<< ours
++=======
++>>>>>>> theirs
+diff --git a/README.md b/README.md
+index 1111111..2222222 100644
+--- a/README.md
++++ b/README.md
+@@ -1,0 +2,2 @@
++Usage
++=======
+diff --git a/art/logo.png b/art/logo.png
+index 3333333..4444444 100644
+Binary files a/art/logo.png and b/art/logo.png differ
+diff --git a/CHANGELOG.md b/CHANGELOG.md
+index 5555555..6666666 100644
+--- a/CHANGELOG.md
++++ b/CHANGELOG.md
+@@ -3 +2,0 @@
+-<<<<<<< HEAD
+";
+
+    #[test]
+    fn finds_staged_markers() {
+        assert_eq!(find(DIFF), vec!["src/lib.rs", "docs/merging.md"]);
+        assert!(find("").is_empty());
+    }
+
+    #[test]
+    fn ignores_what_only_looks_like_markers() {
+        // A heading underlined with as many characters as a separator
+        let heading = "diff --git a/README.md b/README.md\n+++ b/README.md\n@@ -1,0 +2,2 @@\n\
+                       +Usage\n+=======\n";
+        assert!(find(heading).is_empty());
+        // Longer runs, markers in the middle of a line and removed markers
+        let lines = "diff --git a/a.txt b/a.txt\n+++ b/a.txt\n@@ -1 +1,3 @@\n\
+                     +<<<<<<<<\n+ >>>>>>> indented\n+x <<<<<<< y\n-<<<<<<< HEAD\n";
+        assert!(find(lines).is_empty());
+    }
+
+    #[test]
+    fn reads_unusual_paths() {
+        let diff = "diff --git \"a/with\\ttab.txt\" \"b/with\\ttab.txt\"\n\
+                    +++ \"b/with\\ttab.txt\"\n@@ -0,0 +1 @@\n+>>>>>>> theirs\n\
+                    diff --git a/crlf.txt b/crlf.txt\n+++ b/crlf.txt\n@@ -0,0 +1 @@\n+<<<<<<<\r\n\
+                    diff --git a/space name.txt b/space name.txt\n+++ b/space name.txt\t\n\
+                    @@ -0,0 +1 @@\n+++ b/not a header\n+>>>>>>>\n";
+        assert_eq!(
+            find(diff),
+            vec!["with\\ttab.txt", "crlf.txt", "space name.txt"]
+        );
+    }
+
+    #[test]
+    fn matches_the_allowlist() {
+        let patterns: Vec<String> = vec![
+            "*.md".into(),
+            "tests/fixtures/**".into(),
+            "/ci/*.yml".into(),
+        ];
+        let cases = vec![
+            ("docs/merging.md", true),
+            ("README.md", true),
+            ("tests/fixtures/conflict.txt", true),
+            ("tests/fixtures/deep/er/conflict.txt", true),
+            ("ci/build.yml", true),
+            ("ci/nested/build.yml", false),
+            ("src/lib.rs", false),
+            ("tests/conflict.txt", false),
+            ("README.mdx", false),
+        ];
+        for (path, expected) in cases {
+            assert_eq!(allowed(path, &patterns), expected, "{}", path);
+        }
+        assert!(!allowed("docs/merging.md", &[]));
+        assert!(allowed("src/a.rs", &["src/?.rs".to_string()]));
+        assert!(!allowed("src/ab.rs", &["src/?.rs".to_string()]));
+    }
+}
@@ -1,18 +1,104 @@
+pub mod config;
+#[cfg(any(test, feature = "test-util"))]
+pub mod fake;
+pub mod file_store;
+pub mod lfs;
+pub mod markers;
+mod noop;
+pub mod offline;
+pub mod queue;
+pub mod refname;
+pub mod remote_url;
+pub mod replay;
+pub mod rewrite;
+pub mod signing;
+mod sizes;
+mod snapshot;
 pub mod store;
+pub mod whitespace;
+pub mod workspace;
 use crate::command;
 use crate::os;
-use anyhow::Result;
+use crate::state_dir::StateDir;
+use anyhow::{anyhow, Result};
+pub use file_store::FileStore;
 pub use git2::BranchType;
-use git2::{Commit, Config, Error, Oid, Repository, Signature};
-use std::env;
-use std::path::PathBuf;
+use git2::{Commit, Error, Oid, Repository, Signature};
+pub use lfs::Lfs;
+pub use noop::NoopGit;
+pub use snapshot::Snapshot;
+use std::path::{Path, PathBuf};
+use std::{env, fs, io};
 pub use store::Store;
 
 pub trait Git {
     fn run(&self, args: &[&str]) -> Result<()>;
-    fn tree_is_clean(&self) -> Result<bool>;
+    /// Working tree and branch state, take it once per command and again only after
+    /// changing the tree
+    fn snapshot(&self) -> Result<Snapshot>;
     fn has_branch(&self, branch: &str) -> Result<bool>;
-    fn current_branch(&self) -> Result<Option<String>>;
+    /// Local and remote-tracking branches, named as `has_branch` takes them
+    fn branches(&self) -> Result<Vec<String>>;
+    fn remotes(&self) -> Result<Vec<String>>;
+    /// Where `remote` fetches from, `None` for remotes that don't exist
+    fn remote_url(&self, remote: &str) -> Result<Option<String>>;
+    /// The first commit of the history, it tells clones of one project apart from others
+    fn root_commit(&self) -> Result<Option<String>>;
+    /// The commit `rev` points to, `None` if it doesn't exist
+    fn rev_parse(&self, rev: &str) -> Result<Option<String>>;
+    /// The commit `branch` points to on `remote`, asking the remote itself. A full ref like
+    /// `refs/mob/state` is taken as it is.
+    fn remote_tip(&self, remote: &str, branch: &str) -> Result<Option<String>>;
+    fn commit_tree_id(&self, commit: &str) -> Result<String>;
+    fn commit_parents(&self, commit: &str) -> Result<Vec<String>>;
+    /// The best common ancestor of `one` and `other`, `None` when their histories are
+    /// unrelated like those of an orphan branch
+    fn merge_base(&self, one: &str, other: &str) -> Result<Option<String>>;
+    /// Whether the repository uses Git LFS and is set up for it
+    fn lfs(&self) -> Result<Lfs>;
+    /// Staged files LFS tracks that are staged as the file itself instead of a pointer,
+    /// as happens when its filter was skipped
+    fn lfs_unconverted(&self) -> Result<Vec<String>>;
+    /// Files with unresolved conflicts, as after a merge that stopped
+    fn conflicted(&self) -> Result<Vec<String>>;
+    /// What the next commit changes, as `git diff --cached` shows it without context lines
+    fn staged_diff(&self) -> Result<String>;
+    /// Lines added and deleted per staged file as `git diff --cached --numstat` counts them,
+    /// with `ignore_whitespace` like `-w`
+    fn staged_numstat(&self, ignore_whitespace: bool) -> Result<String>;
+    /// The whitespace problems of the staged lines, as `git diff --cached --check` lists them
+    fn staged_check(&self) -> Result<String>;
+    /// The file at `path` of the working tree, relative to its top
+    fn read_file(&self, path: &str) -> Result<Vec<u8>>;
+    fn write_file(&self, path: &str, data: &[u8]) -> Result<()>;
+
+    fn tree_is_clean(&self) -> Result<bool> {
+        Ok(self.snapshot()?.is_clean())
+    }
+
+    /// One line per commit in `range`, newest first
+    fn log_oneline(&self, range: &str) -> Result<Vec<String>>;
+    /// Who authored each commit in `range`, newest first
+    fn log_authors(&self, range: &str) -> Result<Vec<String>>;
+    fn diffstat(&self, range: &str) -> Result<String>;
+    /// The files `commit` changed with their size, 0 for the ones it removed
+    fn file_sizes(&self, commit: &str) -> Result<Vec<(String, u64)>>;
+
+    /// False when there is no repository and all git work should be skipped
+    fn is_repository(&self) -> bool {
+        true
+    }
+
+    /// Fails with the reason if `name` can't be used as a branch
+    fn check_branch_name(&self, name: &str) -> Result<()> {
+        refname::check_branch(name)
+            .map_err(|reason| anyhow!("`{}` is not a valid branch name, {}", name, reason))
+    }
+
+    /// The repositories of a workspace by name, the primary first. Empty outside of one.
+    fn members(&self) -> Vec<(&str, &dyn Git)> {
+        vec![]
+    }
 }
 
 #[derive(Debug)]
@@ -20,6 +106,7 @@ pub struct CommitFile<'a> {
     pub filename: &'a str,
     pub data: &'a [u8],
     pub message: &'a str,
+    /// The full name of the reference to commit to, like `refs/mob/session`
     pub reference: &'a str,
 }
 
@@ -27,6 +114,7 @@ pub struct GitCommand<'repo> {
     command: command::Command<'repo>,
     repo: Repository,
     pub remote: String,
+    pub auto_fetch: bool,
 }
 
 impl<'repo> GitCommand<'repo> {
@@ -38,6 +126,7 @@ impl<'repo> GitCommand<'repo> {
             command,
             repo,
             remote,
+            auto_fetch: true,
         })
     }
 
@@ -48,14 +137,26 @@ impl<'repo> GitCommand<'repo> {
             command,
             repo,
             remote: "origin".into(),
+            auto_fetch: true,
         }
     }
 
-    fn last_commit(&self, reference: &str) -> Option<Commit> {
-        let absolute_ref = format!("refs/heads/{}", reference);
+    /// The top of the working tree, bare repositories have none
+    fn workdir(&self) -> Result<&Path> {
+        self.repo
+            .workdir()
+            .ok_or_else(|| anyhow!("The repository has no working tree"))
+    }
+
+    /// Local state lives inside `.git` so it is never committed
+    pub fn state_dir(&self) -> StateDir {
+        StateDir::new(self.repo.path().join("mob"))
+    }
 
+    /// The commit `reference` points to, a full name like `refs/heads/main`
+    fn last_commit(&self, reference: &str) -> Option<Commit> {
         self.repo
-            .find_reference(absolute_ref.as_str())
+            .find_reference(reference)
             .and_then(|reference| reference.resolve())
             .and_then(|reference| {
                 self.repo
@@ -65,7 +166,7 @@ impl<'repo> GitCommand<'repo> {
     }
 
     fn get_signature() -> Result<Signature<'static>, Error> {
-        let config = Config::open_default()?;
+        let config = git2::Config::open_default()?;
         let name = config.get_string("user.name")?;
         let email = config.get_string("user.email")?;
         Signature::now(name.as_str(), email.as_str())
@@ -87,9 +188,8 @@ impl<'repo> GitCommand<'repo> {
 
         let signature = GitCommand::get_signature()?;
 
-        let absolute_ref = format!("refs/heads/{}", commit.reference);
         self.repo.commit(
-            Some(absolute_ref.as_str()),
+            Some(commit.reference),
             &signature,
             &signature,
             commit.message,
@@ -125,10 +225,45 @@ impl<'repo> Git for GitCommand<'repo> {
         self.command.run_checked(args)
     }
 
-    fn tree_is_clean(&self) -> Result<bool> {
-        self.command
-            .run_stdout(&["status", "--short"])
-            .map(|output| output.trim().is_empty())
+    fn snapshot(&self) -> Result<Snapshot> {
+        let output = self
+            .command
+            .run_stdout(["status", "--porcelain=v2", "--branch"])?;
+        Ok(Snapshot::parse(&output)?)
+    }
+
+    fn log_oneline(&self, range: &str) -> Result<Vec<String>> {
+        let output = self
+            .command
+            .run_stdout(["log", "--oneline", "--no-decorate", range, "--"])?;
+        Ok(output.lines().map(String::from).collect())
+    }
+
+    fn log_authors(&self, range: &str) -> Result<Vec<String>> {
+        let output = self
+            .command
+            .run_stdout(["log", "--format=%an", range, "--"])?;
+        Ok(output.lines().map(String::from).collect())
+    }
+
+    fn diffstat(&self, range: &str) -> Result<String> {
+        self.command.run_stdout(["diff", "--stat", range, "--"])
+    }
+
+    fn file_sizes(&self, commit: &str) -> Result<Vec<(String, u64)>> {
+        let changed = self.command.run_stdout([
+            "diff-tree",
+            "-r",
+            "--root",
+            "--no-commit-id",
+            "--name-only",
+            "-z",
+            commit,
+        ])?;
+        let tree = self
+            .command
+            .run_stdout(["ls-tree", "-r", "-l", "-z", commit])?;
+        Ok(sizes::parse(&changed, &tree))
     }
 
     fn has_branch(&self, branch: &str) -> Result<bool> {
@@ -138,7 +273,259 @@ impl<'repo> Git for GitCommand<'repo> {
         }
     }
 
-    fn current_branch(&self) -> Result<Option<String>> {
-        return Ok(self.repo.head()?.shorthand().map(String::from));
+    fn branches(&self) -> Result<Vec<String>> {
+        let mut names = vec![];
+        for branch in self.repo.branches(None)? {
+            if let Ok(Some(name)) = branch?.0.name() {
+                names.push(name.to_string());
+            }
+        }
+        Ok(names)
+    }
+
+    fn remotes(&self) -> Result<Vec<String>> {
+        Ok(self
+            .repo
+            .remotes()?
+            .iter()
+            .flatten()
+            .map(String::from)
+            .collect())
+    }
+
+    fn remote_url(&self, remote: &str) -> Result<Option<String>> {
+        match self.repo.find_remote(remote) {
+            Ok(remote) => Ok(remote.url().map(String::from)),
+            Err(err) if err.code() == git2::ErrorCode::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    fn root_commit(&self) -> Result<Option<String>> {
+        let output = self
+            .command
+            .run_stdout(["rev-list", "--max-parents=0", "HEAD"])?;
+        // Merged histories have several, the oldest one comes last
+        Ok(output.lines().last().map(String::from))
+    }
+
+    fn rev_parse(&self, rev: &str) -> Result<Option<String>> {
+        let commit = format!("{}^{{commit}}", rev);
+        let output = self
+            .command
+            .run(["rev-parse", "--verify", "--quiet", commit.as_str()])?;
+        Ok(Some(output.stdout.trim().to_string()).filter(|_| output.status.success()))
+    }
+
+    fn remote_tip(&self, remote: &str, branch: &str) -> Result<Option<String>> {
+        let reference = match branch.starts_with("refs/") {
+            true => branch.to_string(),
+            false => format!("refs/heads/{}", branch),
+        };
+        let output = self
+            .command
+            .run_stdout(["ls-remote", remote, reference.as_str()])?;
+        Ok(output
+            .lines()
+            .find(|line| line.ends_with(reference.as_str()))
+            .and_then(|line| line.split_whitespace().next())
+            .map(String::from))
+    }
+
+    fn commit_tree_id(&self, commit: &str) -> Result<String> {
+        let tree = format!("{}^{{tree}}", commit);
+        Ok(self
+            .command
+            .run_stdout(["rev-parse", tree.as_str()])?
+            .trim()
+            .to_string())
+    }
+
+    fn commit_parents(&self, commit: &str) -> Result<Vec<String>> {
+        let output = self
+            .command
+            .run_stdout(["rev-list", "--parents", "-n", "1", commit, "--"])?;
+        // The commit itself comes first
+        Ok(output
+            .split_whitespace()
+            .skip(1)
+            .map(String::from)
+            .collect())
+    }
+
+    fn merge_base(&self, one: &str, other: &str) -> Result<Option<String>> {
+        let output = self.command.run(["merge-base", one, other])?;
+        match output.status.code() {
+            Some(0) => Ok(Some(output.stdout.trim().to_string())),
+            // Without a common commit it fails silently, unknown revisions tell why
+            Some(1) if output.stderr.trim().is_empty() => Ok(None),
+            _ => Err(output.into_error().into()),
+        }
+    }
+
+    fn lfs(&self) -> Result<Lfs> {
+        let attributes = match self.repo.workdir() {
+            Some(workdir) => match fs::read_to_string(workdir.join(".gitattributes")) {
+                Ok(attributes) => attributes,
+                Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Lfs::Unused),
+                Err(err) => return Err(err.into()),
+            },
+            None => return Ok(Lfs::Unused),
+        };
+        if !lfs::tracks(&attributes) {
+            return Ok(Lfs::Unused);
+        }
+        if !self.command.run(["lfs", "version"])?.status.success() {
+            return Ok(Lfs::NotInstalled);
+        }
+        let filter = self.command.run(["config", "--get", "filter.lfs.clean"])?;
+        Ok(match filter.status.success() {
+            true => Lfs::Ready,
+            false => Lfs::NotInitialized,
+        })
+    }
+
+    fn conflicted(&self) -> Result<Vec<String>> {
+        let output = self
+            .command
+            .run_stdout(["diff", "--name-only", "--diff-filter=U", "-z"])?;
+        Ok(output
+            .split('\0')
+            .filter(|path| !path.is_empty())
+            .map(String::from)
+            .collect())
+    }
+
+    fn staged_diff(&self) -> Result<String> {
+        self.command.run_stdout([
+            "diff",
+            "--cached",
+            "--no-color",
+            "--no-ext-diff",
+            "--unified=0",
+            // Whatever diff.noprefix says
+            "--src-prefix=a/",
+            "--dst-prefix=b/",
+        ])
+    }
+
+    fn staged_numstat(&self, ignore_whitespace: bool) -> Result<String> {
+        let mut args = vec!["diff", "--cached", "--numstat", "--no-ext-diff", "--no-renames"];
+        if ignore_whitespace {
+            args.push("--ignore-all-space");
+        }
+        self.command.run_stdout(args)
+    }
+
+    fn staged_check(&self) -> Result<String> {
+        let output = self.command.run([
+            "diff",
+            "--cached",
+            "--check",
+            "--no-color",
+            "--no-ext-diff",
+            "--no-renames",
+        ])?;
+        // 2 tells that there are problems
+        match output.status.code() {
+            Some(0) | Some(2) => Ok(output.stdout),
+            _ => Err(output.into_error().into()),
+        }
+    }
+
+    fn read_file(&self, path: &str) -> Result<Vec<u8>> {
+        Ok(fs::read(self.workdir()?.join(path))?)
+    }
+
+    fn write_file(&self, path: &str, data: &[u8]) -> Result<()> {
+        Ok(fs::write(self.workdir()?.join(path), data)?)
+    }
+
+    fn lfs_unconverted(&self) -> Result<Vec<String>> {
+        let workdir = match self.repo.workdir() {
+            Some(workdir) => workdir.to_string_lossy().into_owned(),
+            None => return Ok(vec![]),
+        };
+        let staged = self.command.run_stdout([
+            "diff",
+            "--cached",
+            "--name-only",
+            "--diff-filter=AM",
+            "-z",
+        ])?;
+        let staged: Vec<&str> = staged.split('\0').filter(|path| !path.is_empty()).collect();
+        if staged.is_empty() {
+            return Ok(vec![]);
+        }
+        // Staged paths are relative to the top, as check-attr takes them from there
+        let mut args = vec![
+            "-C",
+            workdir.as_str(),
+            "check-attr",
+            "--cached",
+            "-z",
+            "filter",
+            "--",
+        ];
+        args.extend(&staged);
+        let mut unconverted = vec![];
+        for path in lfs::filtered(&self.command.run_stdout(args)?) {
+            let blob = format!(":{}", path);
+            let size: u64 = self
+                .command
+                .run_stdout(["cat-file", "-s", blob.as_str()])?
+                .trim()
+                .parse()?;
+            let pointer = size <= lfs::POINTER_MAX
+                && matches!(
+                    self.command.run_stdout(["cat-file", "blob", blob.as_str()]),
+                    Ok(content) if lfs::is_pointer(&content)
+                );
+            if !pointer {
+                unconverted.push(path);
+            }
+        }
+        Ok(unconverted)
+    }
+
+    fn check_branch_name(&self, name: &str) -> Result<()> {
+        // Our rules explain why, git has the final say for its version
+        refname::check_branch(name)
+            .map_err(|reason| anyhow!("`{}` is not a valid branch name, {}", name, reason))?;
+        self.run_quietly(&["check-ref-format", "--branch", name])
+            .map_err(|_| anyhow!("`{}` is not a valid branch name according to git", name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lfs_unconverted_reports_files_staged_without_the_filter() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let pointer = "version https://git-lfs.github.com/spec/v1\n\
+                       oid sha256:4d7a214614ab2935c943f9e0ff69d22eadbb8f32b1258daaa5e2ca24d17e2393\n\
+                       size 12345\n";
+        let files = [
+            (
+                ".gitattributes",
+                "*.bin filter=lfs diff=lfs merge=lfs -text\n",
+            ),
+            ("pointer.bin", pointer),
+            ("raw.bin", "the file itself\n"),
+            ("notes.txt", "not for LFS\n"),
+        ];
+        // The index takes the content as is, like a clone without `git lfs install` would
+        let mut index = repo.index().unwrap();
+        for (path, content) in &files {
+            fs::write(dir.path().join(path), content).unwrap();
+            index.add_path(Path::new(path)).unwrap();
+        }
+        index.write().unwrap();
+
+        let git = GitCommand::from_repo(repo);
+        assert_eq!(git.lfs_unconverted().unwrap(), vec!["raw.bin".to_string()]);
     }
 }
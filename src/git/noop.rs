@@ -0,0 +1,108 @@
+use super::{Git, Snapshot};
+use anyhow::Result;
+
+/// Stands in for `GitCommand` when mobbing outside of a git repository.
+pub struct NoopGit;
+
+impl Git for NoopGit {
+    fn run(&self, args: &[&str]) -> Result<()> {
+        log::trace!("skipping git {}", args.join(" "));
+        Ok(())
+    }
+
+    fn snapshot(&self) -> Result<Snapshot> {
+        Ok(Snapshot::default())
+    }
+
+    fn log_oneline(&self, _range: &str) -> Result<Vec<String>> {
+        Ok(vec![])
+    }
+
+    fn log_authors(&self, _range: &str) -> Result<Vec<String>> {
+        Ok(vec![])
+    }
+
+    fn diffstat(&self, _range: &str) -> Result<String> {
+        Ok(String::new())
+    }
+
+    fn file_sizes(&self, _commit: &str) -> Result<Vec<(String, u64)>> {
+        Ok(vec![])
+    }
+
+    fn has_branch(&self, _branch: &str) -> Result<bool> {
+        Ok(false)
+    }
+
+    fn branches(&self) -> Result<Vec<String>> {
+        Ok(vec![])
+    }
+
+    fn remotes(&self) -> Result<Vec<String>> {
+        Ok(vec![])
+    }
+
+    fn remote_url(&self, _remote: &str) -> Result<Option<String>> {
+        Ok(None)
+    }
+
+    fn root_commit(&self) -> Result<Option<String>> {
+        Ok(None)
+    }
+
+    fn rev_parse(&self, _rev: &str) -> Result<Option<String>> {
+        Ok(None)
+    }
+
+    fn remote_tip(&self, _remote: &str, _branch: &str) -> Result<Option<String>> {
+        Ok(None)
+    }
+
+    fn commit_tree_id(&self, commit: &str) -> Result<String> {
+        Err(anyhow::anyhow!("no repository to find {} in", commit))
+    }
+
+    fn commit_parents(&self, commit: &str) -> Result<Vec<String>> {
+        Err(anyhow::anyhow!("no repository to find {} in", commit))
+    }
+
+    fn merge_base(&self, _one: &str, _other: &str) -> Result<Option<String>> {
+        Ok(None)
+    }
+
+    fn lfs(&self) -> Result<super::Lfs> {
+        Ok(super::Lfs::Unused)
+    }
+
+    fn lfs_unconverted(&self) -> Result<Vec<String>> {
+        Ok(vec![])
+    }
+
+    fn conflicted(&self) -> Result<Vec<String>> {
+        Ok(vec![])
+    }
+
+    fn staged_diff(&self) -> Result<String> {
+        Ok(String::new())
+    }
+
+    fn staged_numstat(&self, _ignore_whitespace: bool) -> Result<String> {
+        Ok(String::new())
+    }
+
+    fn staged_check(&self) -> Result<String> {
+        Ok(String::new())
+    }
+
+    fn read_file(&self, path: &str) -> Result<Vec<u8>> {
+        Ok(std::fs::read(path)?)
+    }
+
+    fn write_file(&self, path: &str, data: &[u8]) -> Result<()> {
+        Ok(std::fs::write(path, data)?)
+    }
+
+    fn is_repository(&self) -> bool {
+        false
+    }
+}
@@ -0,0 +1,346 @@
+//! The git facade for `--offline`: fetches are skipped, anything that would reach the
+//! remote fails with one message, and the session is read as it was fetched last. Turns are
+//! handed over through the queue in front of it, `mob sync` pushes them later.
+
+use super::store::{self, Store};
+use super::{Git, Lfs, Snapshot};
+use anyhow::{anyhow, Result};
+
+/// What a command does without the remote
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Capability {
+    /// Works on what was fetched last, its output is marked as possibly stale
+    Reads,
+    /// Taking and handing over turns, its pushes and saves wait for `mob sync`
+    Queues,
+    /// Changes what others see, refused before it starts
+    Online,
+}
+
+/// What `command`, named as on the command line, can do offline. Only turns queue, the other
+/// commands that change the session need the remote.
+pub fn capability(command: &str) -> Capability {
+    match command {
+        "status" | "stats" | "export" | "env" | "doctor" | "verify" | "replay" | "help" => {
+            Capability::Reads
+        }
+        "start" | "next" | "sync" => Capability::Queues,
+        _ => Capability::Online,
+    }
+}
+
+/// Refuses commands that need the remote, before anything was changed
+pub fn check(command: &str) -> Result<()> {
+    match capability(command) {
+        Capability::Reads | Capability::Queues => Ok(()),
+        Capability::Online => Err(anyhow!(
+            "mob {} needs the remote, run it again without --offline",
+            command
+        )),
+    }
+}
+
+fn refused(what: &str) -> anyhow::Error {
+    anyhow!(
+        "mob is offline and can't {}, run the command again without --offline",
+        what
+    )
+}
+
+/// Wraps the git and store of a command run with `--offline`
+pub struct Offline<'a> {
+    git: &'a dyn Git,
+    store: &'a dyn Store,
+}
+
+impl<'a> Offline<'a> {
+    pub fn new(git: &'a impl Git, store: &'a impl Store) -> Self {
+        Offline { git, store }
+    }
+}
+
+impl<'a> Git for Offline<'a> {
+    fn run(&self, args: &[&str]) -> Result<()> {
+        match args.first().copied() {
+            Some("fetch") => {
+                log::trace!("offline, skipping git {}", args.join(" "));
+                Ok(())
+            }
+            Some(command @ "push") | Some(command @ "pull") | Some(command @ "ls-remote") => {
+                Err(refused(command))
+            }
+            _ => self.git.run(args),
+        }
+    }
+
+    fn snapshot(&self) -> Result<Snapshot> {
+        self.git.snapshot()
+    }
+
+    fn has_branch(&self, branch: &str) -> Result<bool> {
+        self.git.has_branch(branch)
+    }
+
+    fn branches(&self) -> Result<Vec<String>> {
+        self.git.branches()
+    }
+
+    fn remotes(&self) -> Result<Vec<String>> {
+        self.git.remotes()
+    }
+
+    fn remote_url(&self, remote: &str) -> Result<Option<String>> {
+        self.git.remote_url(remote)
+    }
+
+    fn root_commit(&self) -> Result<Option<String>> {
+        self.git.root_commit()
+    }
+
+    fn rev_parse(&self, rev: &str) -> Result<Option<String>> {
+        self.git.rev_parse(rev)
+    }
+
+    fn remote_tip(&self, _remote: &str, _branch: &str) -> Result<Option<String>> {
+        Err(refused("ask the remote"))
+    }
+
+    fn commit_tree_id(&self, commit: &str) -> Result<String> {
+        self.git.commit_tree_id(commit)
+    }
+
+    fn commit_parents(&self, commit: &str) -> Result<Vec<String>> {
+        self.git.commit_parents(commit)
+    }
+
+    fn merge_base(&self, one: &str, other: &str) -> Result<Option<String>> {
+        self.git.merge_base(one, other)
+    }
+
+    fn lfs(&self) -> Result<Lfs> {
+        self.git.lfs()
+    }
+
+    fn lfs_unconverted(&self) -> Result<Vec<String>> {
+        self.git.lfs_unconverted()
+    }
+
+    fn conflicted(&self) -> Result<Vec<String>> {
+        self.git.conflicted()
+    }
+
+    fn staged_diff(&self) -> Result<String> {
+        self.git.staged_diff()
+    }
+
+    fn staged_numstat(&self, ignore_whitespace: bool) -> Result<String> {
+        self.git.staged_numstat(ignore_whitespace)
+    }
+
+    fn staged_check(&self) -> Result<String> {
+        self.git.staged_check()
+    }
+
+    fn read_file(&self, path: &str) -> Result<Vec<u8>> {
+        self.git.read_file(path)
+    }
+
+    fn write_file(&self, path: &str, data: &[u8]) -> Result<()> {
+        self.git.write_file(path, data)
+    }
+
+    fn log_oneline(&self, range: &str) -> Result<Vec<String>> {
+        self.git.log_oneline(range)
+    }
+
+    fn log_authors(&self, range: &str) -> Result<Vec<String>> {
+        self.git.log_authors(range)
+    }
+
+    fn diffstat(&self, range: &str) -> Result<String> {
+        self.git.diffstat(range)
+    }
+
+    fn file_sizes(&self, commit: &str) -> Result<Vec<(String, u64)>> {
+        self.git.file_sizes(commit)
+    }
+
+    fn is_repository(&self) -> bool {
+        self.git.is_repository()
+    }
+
+    fn members(&self) -> Vec<(&str, &dyn Git)> {
+        self.git.members()
+    }
+}
+
+/// Only reads, what the store has locally. Turn off `auto_fetch` of a `GitCommand` so
+/// its loads don't fetch either.
+impl<'a> Store for Offline<'a> {
+    fn load(&self, document: &str) -> Result<Vec<u8>, store::Error> {
+        self.store.load(document)
+    }
+
+    fn save(&self, _document: &str, _data: &[u8]) -> Result<(), store::Error> {
+        Err(store::Error::Offline)
+    }
+
+    fn clean(&self, _document: &str) -> Result<(), store::Error> {
+        Err(store::Error::Offline)
+    }
+
+    fn documents(&self) -> Result<Vec<String>, store::Error> {
+        self.store.documents()
+    }
+
+    fn swap(
+        &self,
+        _document: &str,
+        _expected: Option<&[u8]>,
+        _data: &[u8],
+    ) -> Result<(), store::Error> {
+        Err(store::Error::Offline)
+    }
+
+    fn keep(&self, document: &str, data: &[u8]) -> Result<(), store::Error> {
+        self.store.keep(document, data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::FakeClock;
+    use crate::cmd::{Next, NextOpts, Status, StatusOpts};
+    use crate::config::Config;
+    use crate::git::fake::{FakeGit, MemoryStore};
+    use crate::session::{self, Store as _};
+    use crate::timer::fake::NoTimer;
+    use clap::Clap;
+
+    #[test]
+    fn classifies_commands() {
+        let reads = [
+            "status", "stats", "export", "env", "doctor", "verify", "replay", "help",
+        ];
+        let queues = ["start", "next", "sync"];
+        let online = [
+            "join",
+            "break",
+            "away",
+            "back",
+            "skip",
+            "pause",
+            "resume",
+            "claim",
+            "unclaim",
+            "done",
+            "clean",
+            "emergency",
+            "watch",
+            "order",
+            "update",
+            "import",
+            "preset",
+        ];
+        for command in &reads {
+            assert_eq!(capability(command), Capability::Reads, "{}", command);
+            assert!(check(command).is_ok());
+        }
+        for command in &queues {
+            assert_eq!(capability(command), Capability::Queues, "{}", command);
+            assert!(check(command).is_ok());
+        }
+        for command in &online {
+            assert_eq!(capability(command), Capability::Online, "{}", command);
+            assert_eq!(
+                check(command).unwrap_err().to_string(),
+                format!(
+                    "mob {} needs the remote, run it again without --offline",
+                    command
+                )
+            );
+        }
+    }
+
+    #[test]
+    fn skips_fetches_and_refuses_pushes() {
+        let git = FakeGit::default();
+        let memory = MemoryStore::default();
+        memory.save("session", b"{}").unwrap();
+        let offline = Offline::new(&git, &memory);
+
+        offline.run(&["fetch", "--all", "--prune"]).unwrap();
+        offline.run(&["add", "--all"]).unwrap();
+        let err = offline.run(&["push", "origin", "main"]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "mob is offline and can't push, run the command again without --offline"
+        );
+        assert!(offline.remote_tip("origin", "main").is_err());
+        assert_eq!(git.calls(), vec!["add --all"]);
+
+        assert_eq!(offline.load("session").unwrap(), b"{}");
+        assert!(matches!(
+            offline.save("session", b"[]"),
+            Err(store::Error::Offline)
+        ));
+        assert!(matches!(
+            offline.clean("session"),
+            Err(store::Error::Offline)
+        ));
+        assert_eq!(memory.load("session").unwrap(), b"{}");
+    }
+
+    #[test]
+    fn runs_commands_offline() {
+        let mut git = FakeGit::default();
+        git.snapshot.branch = Some("mob-session".into());
+        git.snapshot.changed = 1;
+        git.snapshot.unstaged = 1;
+        let memory = MemoryStore::default();
+        let working = session::Session {
+            id: Some("3f2a9c1e7b04".into()),
+            state: session::State::Working {
+                driver: "alice".into(),
+            },
+            drivers: session::Drivers::new(vec!["alice".into(), "bob".into()]),
+            settings: Some(session::Settings::default()),
+            ..session::Session::default()
+        };
+        session::SessionStore::new(&memory)
+            .save(working.clone())
+            .unwrap();
+        let offline = Offline::new(&git, &memory);
+        let store = session::SessionStore::new(&offline);
+        let config = || Config {
+            name: "alice".into(),
+            ..Config::default()
+        };
+        let clock = FakeClock::default();
+
+        Status::new(
+            StatusOpts::parse_from(["status"]),
+            &offline,
+            &store,
+            &clock,
+            config(),
+        )
+        .run()
+        .unwrap();
+
+        let err = Next::new(
+            &offline,
+            &store,
+            &NoTimer,
+            &clock,
+            NextOpts::parse_from(["next"]),
+            config(),
+        )
+        .run()
+        .unwrap_err();
+        assert!(err.to_string().contains("--offline"), "{}", err);
+        assert!(!git.calls().iter().any(|call| call.starts_with("push")));
+        assert_eq!(session::SessionStore::new(&memory).load().unwrap(), working);
+    }
+}
@@ -0,0 +1,694 @@
+//! Turns handed over without a connection. Their pushes and the saves of the session wait in
+//! this clone, listed in a file of the state dir, until `mob sync` pushes them. Once something
+//! waits everything after it waits too, so the remote gets it all in order.
+
+use super::store::{self, Store};
+use super::{Git, Lfs, Snapshot};
+use crate::session::rebase;
+use crate::state_dir::StateDir;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+
+const PENDING: &str = "pending.json";
+
+/// What git prints when the remote can't be reached at all, unlike a push that was cut off
+const UNREACHABLE: &[&str] = &[
+    "Could not resolve host",
+    "Could not resolve hostname",
+    "Temporary failure in name resolution",
+    "Network is unreachable",
+    "No route to host",
+    "Connection refused",
+    "Failed to connect to",
+];
+
+/// Whether git failed with `text` because there is no connection to the remote
+pub fn unreachable(text: &str) -> bool {
+    UNREACHABLE.iter().any(|pattern| text.contains(pattern))
+}
+
+/// What waits for `mob sync`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Pending {
+    /// Branches with commits the remote doesn't have yet
+    #[serde(default)]
+    pub branches: Vec<String>,
+    /// Documents saved in this clone only, with what they were before the first of those
+    /// saves, for saving on top of what others saved meanwhile. `None` when unknown.
+    #[serde(default)]
+    pub documents: BTreeMap<String, Option<String>>,
+}
+
+impl Pending {
+    /// What waits in the session of `state_dir`, `None` when nothing does
+    pub fn read(state_dir: &StateDir) -> Option<Pending> {
+        state_dir
+            .read::<Pending>(PENDING)
+            .filter(|pending| !pending.is_empty())
+    }
+
+    fn is_empty(&self) -> bool {
+        self.branches.is_empty() && self.documents.is_empty()
+    }
+
+    fn write(&self, state_dir: &StateDir) -> Result<()> {
+        match self.is_empty() {
+            true => state_dir.remove(PENDING),
+            false => state_dir.write(PENDING, self),
+        }
+    }
+}
+
+/// When pushes and saves wait instead of reaching the remote
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Mode {
+    /// Only while something waits already
+    Online,
+    /// Also once the remote turns out to be unreachable
+    Fallback,
+    /// Always, for `--offline`
+    Offline,
+}
+
+/// Wraps the git and store of a command, queueing what would reach the remote
+pub struct Queue<'a> {
+    git: &'a dyn Git,
+    store: &'a dyn Store,
+    state_dir: StateDir,
+    remote: String,
+    mode: Mode,
+    /// Documents kept by this command, a later load mustn't fetch theirs over them
+    kept: RefCell<BTreeMap<String, Vec<u8>>>,
+}
+
+impl<'a> Queue<'a> {
+    pub fn new(
+        git: &'a impl Git,
+        store: &'a impl Store,
+        state_dir: StateDir,
+        remote: &str,
+        mode: Mode,
+    ) -> Self {
+        Queue {
+            git,
+            store,
+            state_dir,
+            remote: remote.to_string(),
+            mode,
+            kept: RefCell::default(),
+        }
+    }
+
+    fn pending(&self) -> Option<Pending> {
+        Pending::read(&self.state_dir)
+    }
+
+    fn queueing(&self) -> bool {
+        self.mode == Mode::Offline || self.pending().is_some()
+    }
+
+    /// Whether a failure with `text` should rather wait for `mob sync`
+    fn falls_back(&self, text: &str) -> bool {
+        self.mode == Mode::Fallback && unreachable(text)
+    }
+
+    fn queued(&self, branch: &str) -> bool {
+        self.pending()
+            .is_some_and(|pending| pending.branches.iter().any(|queued| queued == branch))
+    }
+
+    /// Records the branch `args` push, the last of them
+    fn queue_push(&self, args: &[&str]) -> Result<()> {
+        if args.contains(&"--delete") {
+            return Err(anyhow!(
+                "mob can't delete a branch on the remote while offline, run the command \
+                 again once online and after mob sync"
+            ));
+        }
+        let branch = args.last().copied().unwrap_or_default();
+        let mut pending = self.pending().unwrap_or_default();
+        if !pending.branches.iter().any(|queued| queued == branch) {
+            pending.branches.push(branch.to_string());
+        }
+        pending.write(&self.state_dir)?;
+        log::warn!(
+            "{} is committed here, run 'mob sync' once online to push it",
+            branch
+        );
+        Ok(())
+    }
+
+    /// Saves `document` in this clone only, remembering what it was before the first save
+    /// that waits
+    fn queue_save(
+        &self,
+        document: &str,
+        expected: Option<&[u8]>,
+        data: &[u8],
+    ) -> Result<(), store::Error> {
+        let mut pending = self.pending().unwrap_or_default();
+        pending
+            .documents
+            .entry(document.to_string())
+            .or_insert_with(|| expected.map(|base| String::from_utf8_lossy(base).into_owned()));
+        self.store.keep(document, data)?;
+        self.kept
+            .borrow_mut()
+            .insert(document.to_string(), data.to_vec());
+        pending
+            .write(&self.state_dir)
+            .map_err(|err| std::io::Error::other(format!("{:#}", err)))?;
+        Ok(())
+    }
+
+    /// Pushes what waits, `false` when nothing does or mob is offline
+    pub fn flush(&self) -> Result<bool> {
+        let mut pending = match self.pending() {
+            Some(pending) if self.mode != Mode::Offline => pending,
+            _ => return Ok(false),
+        };
+        while let Some(branch) = pending.branches.first().cloned() {
+            self.git
+                .run(&["push", "--no-verify", self.remote.as_str(), "--", &branch])?;
+            log::info!("Pushed {}", branch);
+            pending.branches.remove(0);
+            pending.write(&self.state_dir)?;
+        }
+        let mut overtaken = vec![];
+        while let Some((document, base)) = pending.documents.pop_first() {
+            if let Err(err) = self.publish(&document, base.as_deref()) {
+                log::warn!("{:#}", err);
+                overtaken.push(document);
+            }
+            pending.write(&self.state_dir)?;
+        }
+        match overtaken.is_empty() {
+            true => {
+                log::info!("Pushed everything that waited, the remote is up to date");
+                Ok(true)
+            }
+            false => Err(anyhow!(
+                "Someone else changed the session while you were offline, it is as they left \
+                 it and your commits are pushed. Check with mob status"
+            )),
+        }
+    }
+
+    /// Saves the local `document` on the remote, on top of what others saved since `base`.
+    /// Theirs stays when both changed the same.
+    fn publish(&self, document: &str, base: Option<&str>) -> Result<()> {
+        let ours = self.store.load(document)?;
+        let theirs = match self.store.save(document, &ours) {
+            Ok(()) => return Ok(()),
+            Err(store::Error::Changed(_)) => self.store.load(document)?,
+            Err(err) => return Err(err.into()),
+        };
+        let base = base.ok_or_else(|| anyhow!("The session {} was changed meanwhile", document))?;
+        let rebased = rebase::rebase(
+            &serde_json::from_str(base)?,
+            &serde_json::from_slice(&ours)?,
+            &serde_json::from_slice(&theirs)?,
+        )
+        .map_err(|conflict| {
+            anyhow!(
+                "The session {} was changed meanwhile, and so was its {}",
+                document,
+                conflict
+            )
+        })?;
+        self.store
+            .save(document, &serde_json::to_vec_pretty(&rebased)?)?;
+        Ok(())
+    }
+}
+
+impl<'a> Git for Queue<'a> {
+    fn run(&self, args: &[&str]) -> Result<()> {
+        match args {
+            ["push", ..] if self.queueing() => self.queue_push(args),
+            ["push", ..] => match self.git.run(args) {
+                Err(err) if self.falls_back(&format!("{:#}", err)) => {
+                    log::warn!("The remote can't be reached: {:#}", err);
+                    self.queue_push(args)
+                }
+                pushed => pushed,
+            },
+            // Its commits are only here
+            ["branch", "-D", .., branch] if self.queued(branch) => {
+                log::info!("Keeping {}, it waits for mob sync", branch);
+                Ok(())
+            }
+            _ => self.git.run(args),
+        }
+    }
+
+    fn snapshot(&self) -> Result<Snapshot> {
+        self.git.snapshot()
+    }
+
+    fn has_branch(&self, branch: &str) -> Result<bool> {
+        self.git.has_branch(branch)
+    }
+
+    fn branches(&self) -> Result<Vec<String>> {
+        self.git.branches()
+    }
+
+    fn remotes(&self) -> Result<Vec<String>> {
+        self.git.remotes()
+    }
+
+    fn remote_url(&self, remote: &str) -> Result<Option<String>> {
+        self.git.remote_url(remote)
+    }
+
+    fn root_commit(&self) -> Result<Option<String>> {
+        self.git.root_commit()
+    }
+
+    /// A queued branch is on the remote as it is here, once `mob sync` pushed it
+    fn rev_parse(&self, rev: &str) -> Result<Option<String>> {
+        let tracked = rev
+            .strip_prefix(self.remote.as_str())
+            .and_then(|rev| rev.strip_prefix('/'));
+        match tracked {
+            Some(branch) if self.queued(branch) => self.git.rev_parse(branch),
+            _ => self.git.rev_parse(rev),
+        }
+    }
+
+    fn remote_tip(&self, remote: &str, branch: &str) -> Result<Option<String>> {
+        match self.queueing() {
+            true => Ok(None),
+            false => self.git.remote_tip(remote, branch),
+        }
+    }
+
+    fn commit_tree_id(&self, commit: &str) -> Result<String> {
+        self.git.commit_tree_id(commit)
+    }
+
+    fn commit_parents(&self, commit: &str) -> Result<Vec<String>> {
+        self.git.commit_parents(commit)
+    }
+
+    fn merge_base(&self, one: &str, other: &str) -> Result<Option<String>> {
+        self.git.merge_base(one, other)
+    }
+
+    fn lfs(&self) -> Result<Lfs> {
+        self.git.lfs()
+    }
+
+    fn lfs_unconverted(&self) -> Result<Vec<String>> {
+        self.git.lfs_unconverted()
+    }
+
+    fn conflicted(&self) -> Result<Vec<String>> {
+        self.git.conflicted()
+    }
+
+    fn staged_diff(&self) -> Result<String> {
+        self.git.staged_diff()
+    }
+
+    fn staged_numstat(&self, ignore_whitespace: bool) -> Result<String> {
+        self.git.staged_numstat(ignore_whitespace)
+    }
+
+    fn staged_check(&self) -> Result<String> {
+        self.git.staged_check()
+    }
+
+    fn read_file(&self, path: &str) -> Result<Vec<u8>> {
+        self.git.read_file(path)
+    }
+
+    fn write_file(&self, path: &str, data: &[u8]) -> Result<()> {
+        self.git.write_file(path, data)
+    }
+
+    fn log_oneline(&self, range: &str) -> Result<Vec<String>> {
+        self.git.log_oneline(range)
+    }
+
+    fn log_authors(&self, range: &str) -> Result<Vec<String>> {
+        self.git.log_authors(range)
+    }
+
+    fn diffstat(&self, range: &str) -> Result<String> {
+        self.git.diffstat(range)
+    }
+
+    fn file_sizes(&self, commit: &str) -> Result<Vec<(String, u64)>> {
+        self.git.file_sizes(commit)
+    }
+
+    fn is_repository(&self) -> bool {
+        self.git.is_repository()
+    }
+
+    fn check_branch_name(&self, name: &str) -> Result<()> {
+        self.git.check_branch_name(name)
+    }
+
+    fn members(&self) -> Vec<(&str, &dyn Git)> {
+        self.git.members()
+    }
+}
+
+impl<'a> Store for Queue<'a> {
+    fn load(&self, document: &str) -> Result<Vec<u8>, store::Error> {
+        match self.kept.borrow().get(document) {
+            Some(kept) => Ok(kept.clone()),
+            None => self.store.load(document),
+        }
+    }
+
+    fn save(&self, document: &str, data: &[u8]) -> Result<(), store::Error> {
+        if self.queueing() {
+            return self.queue_save(document, None, data);
+        }
+        match self.store.save(document, data) {
+            Err(store::Error::Conflict(err)) if self.falls_back(&format!("{:#}", err)) => {
+                self.queue_save(document, None, data)
+            }
+            saved => saved,
+        }
+    }
+
+    fn clean(&self, document: &str) -> Result<(), store::Error> {
+        self.store.clean(document)
+    }
+
+    fn documents(&self) -> Result<Vec<String>, store::Error> {
+        self.store.documents()
+    }
+
+    fn swap(
+        &self,
+        document: &str,
+        expected: Option<&[u8]>,
+        data: &[u8],
+    ) -> Result<(), store::Error> {
+        if self.queueing() {
+            return self.queue_save(document, expected, data);
+        }
+        match self.store.swap(document, expected, data) {
+            Err(store::Error::Conflict(err)) if self.falls_back(&format!("{:#}", err)) => {
+                self.queue_save(document, expected, data)
+            }
+            swapped => swapped,
+        }
+    }
+
+    fn keep(&self, document: &str, data: &[u8]) -> Result<(), store::Error> {
+        self.store.keep(document, data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::FakeClock;
+    use crate::cmd::{Next, NextOpts};
+    use crate::config::Config;
+    use crate::git::fake::{FakeGit, MemoryStore};
+    use crate::git::offline::Offline;
+    use crate::session::{self, Store as _};
+    use crate::timer::fake::NoTimer;
+    use clap::Clap;
+    use serde_json::json;
+
+    /// The documents here and on the remote, like a clone has them
+    #[derive(Default)]
+    struct Clone {
+        local: RefCell<BTreeMap<String, Vec<u8>>>,
+        remote: RefCell<BTreeMap<String, Vec<u8>>>,
+        /// What the remote had when fetched last
+        fetched: RefCell<BTreeMap<String, Vec<u8>>>,
+    }
+
+    impl Clone {
+        fn with(document: &str, data: &serde_json::Value) -> Self {
+            let clone = Clone::default();
+            let data = data.to_string().into_bytes();
+            for side in &[&clone.local, &clone.remote, &clone.fetched] {
+                side.borrow_mut().insert(document.into(), data.clone());
+            }
+            clone
+        }
+
+        fn remote(&self, document: &str) -> serde_json::Value {
+            serde_json::from_slice(&self.remote.borrow()[document]).unwrap()
+        }
+    }
+
+    impl Store for Clone {
+        fn load(&self, document: &str) -> Result<Vec<u8>, store::Error> {
+            self.local
+                .borrow()
+                .get(document)
+                .cloned()
+                .ok_or(store::Error::Missing)
+        }
+
+        fn save(&self, document: &str, data: &[u8]) -> Result<(), store::Error> {
+            let remote = self.remote.borrow().get(document).cloned();
+            if remote != self.fetched.borrow().get(document).cloned() {
+                let remote = remote.unwrap_or_default();
+                self.local
+                    .borrow_mut()
+                    .insert(document.into(), remote.clone());
+                self.fetched.borrow_mut().insert(document.into(), remote);
+                return Err(store::Error::Changed(document.into()));
+            }
+            for side in &[&self.local, &self.remote, &self.fetched] {
+                side.borrow_mut().insert(document.into(), data.to_vec());
+            }
+            Ok(())
+        }
+
+        fn clean(&self, document: &str) -> Result<(), store::Error> {
+            self.local.borrow_mut().remove(document);
+            Ok(())
+        }
+
+        fn keep(&self, document: &str, data: &[u8]) -> Result<(), store::Error> {
+            self.local
+                .borrow_mut()
+                .insert(document.into(), data.to_vec());
+            Ok(())
+        }
+    }
+
+    fn bytes(value: serde_json::Value) -> Vec<u8> {
+        value.to_string().into_bytes()
+    }
+
+    const PUSH: &[&str] = &["push", "--no-verify", "origin", "--", "mob-session"];
+
+    #[test]
+    fn stacks_turns_until_sync() {
+        let dir = tempfile::tempdir().unwrap();
+        let state_dir = StateDir::new(dir.path().to_path_buf());
+        let git = FakeGit::default();
+        let base = json!({"state": "Working", "history": [1]});
+        let clone = Clone::with("session", &base);
+        let offline = Queue::new(&git, &clone, state_dir.clone(), "origin", Mode::Offline);
+
+        // Two turns of the same person, each pushing the branch and saving the session
+        let first = json!({"state": "WaitingForNext", "history": [1, 2]});
+        let second = json!({"state": "WaitingForNext", "history": [1, 2, 3]});
+        offline.run(PUSH).unwrap();
+        offline
+            .swap("session", Some(&bytes(base.clone())), &bytes(first.clone()))
+            .unwrap();
+        offline.run(PUSH).unwrap();
+        offline
+            .swap("session", Some(&bytes(first)), &bytes(second.clone()))
+            .unwrap();
+        assert!(git.calls().is_empty());
+        assert_eq!(offline.remote_tip("origin", "mob-session").unwrap(), None);
+        assert_eq!(offline.load("session").unwrap(), bytes(second));
+        assert_eq!(clone.remote("session"), base);
+        assert_eq!(
+            Pending::read(&state_dir),
+            Some(Pending {
+                branches: vec!["mob-session".into()],
+                documents: vec![("session".into(), Some(base.to_string()))]
+                    .into_iter()
+                    .collect(),
+            })
+        );
+        assert!(!offline.flush().unwrap(), "offline nothing is pushed");
+
+        // Meanwhile someone else added to the history
+        clone.remote.borrow_mut().insert(
+            "session".into(),
+            bytes(json!({"state": "Working", "history": [1, 4]})),
+        );
+        let online = Queue::new(&git, &clone, state_dir.clone(), "origin", Mode::Online);
+        assert!(online.flush().unwrap());
+        assert_eq!(git.calls(), vec![PUSH.join(" ")]);
+        assert_eq!(
+            clone.remote("session"),
+            json!({"state": "WaitingForNext", "history": [1, 4, 2, 3]})
+        );
+        assert_eq!(Pending::read(&state_dir), None);
+        assert!(!online.flush().unwrap());
+    }
+
+    #[test]
+    fn leaves_the_session_to_others_who_changed_the_same() {
+        let dir = tempfile::tempdir().unwrap();
+        let state_dir = StateDir::new(dir.path().to_path_buf());
+        let git = FakeGit::default();
+        let base = json!({"state": {"Working": {"driver": "alice"}}});
+        let clone = Clone::with("session", &base);
+        let offline = Queue::new(&git, &clone, state_dir.clone(), "origin", Mode::Offline);
+        offline.run(PUSH).unwrap();
+        let ours = json!({"state": {"WaitingForNext": {"next": "bob"}}});
+        offline
+            .swap("session", Some(&bytes(base)), &bytes(ours))
+            .unwrap();
+
+        let theirs = json!({"state": {"Working": {"driver": "carol"}}});
+        clone
+            .remote
+            .borrow_mut()
+            .insert("session".into(), bytes(theirs.clone()));
+        let online = Queue::new(&git, &clone, state_dir.clone(), "origin", Mode::Online);
+        let err = online.flush().unwrap_err();
+        assert!(
+            err.to_string().contains("while you were offline"),
+            "{}",
+            err
+        );
+        assert_eq!(git.calls(), vec![PUSH.join(" ")]);
+        assert_eq!(clone.remote("session"), theirs);
+        assert_eq!(Pending::read(&state_dir), None);
+    }
+
+    #[test]
+    fn falls_back_when_the_remote_is_unreachable() {
+        let dir = tempfile::tempdir().unwrap();
+        let state_dir = StateDir::new(dir.path().to_path_buf());
+        let git = FakeGit::default();
+        let memory = MemoryStore::default();
+        git.fail(
+            "push",
+            0,
+            "fatal: unable to access: the remote end hung up unexpectedly",
+        );
+        let fallback = Queue::new(&git, &memory, state_dir.clone(), "origin", Mode::Fallback);
+        assert!(
+            fallback.run(PUSH).is_err(),
+            "a cut off push is retried instead"
+        );
+        assert_eq!(Pending::read(&state_dir), None);
+
+        git.fail(
+            "push",
+            0,
+            "fatal: unable to access: Could not resolve host: example.com",
+        );
+        fallback.run(PUSH).unwrap();
+        fallback.run(PUSH).unwrap();
+        fallback.save("session", b"{}").unwrap();
+        assert_eq!(git.calls(), vec![PUSH.join(" "), PUSH.join(" ")]);
+        assert_eq!(
+            Pending::read(&state_dir).map(|pending| pending.branches),
+            Some(vec!["mob-session".to_string()])
+        );
+
+        // Nothing passes what waits, not even commands that don't fall back
+        let online = Queue::new(&git, &memory, state_dir, "origin", Mode::Online);
+        online.run(PUSH).unwrap();
+        assert_eq!(git.calls().len(), 2);
+        let delete = online.run(&["push", "origin", "--delete", "--", "mob-session"]);
+        assert!(delete.is_err());
+    }
+
+    #[test]
+    fn keeps_queued_branches() {
+        let dir = tempfile::tempdir().unwrap();
+        let state_dir = StateDir::new(dir.path().to_path_buf());
+        let mut git = FakeGit::default();
+        git.revs.insert("mob-session".into(), "b2".into());
+        git.revs.insert("origin/mob-session".into(), "a1".into());
+        let memory = MemoryStore::default();
+        let queue = Queue::new(&git, &memory, state_dir, "origin", Mode::Offline);
+        assert_eq!(
+            queue.rev_parse("origin/mob-session").unwrap(),
+            Some("a1".into())
+        );
+
+        queue.run(PUSH).unwrap();
+        queue.run(&["branch", "-D", "--", "mob-session"]).unwrap();
+        queue.run(&["branch", "-D", "--", "other"]).unwrap();
+        assert_eq!(git.calls(), vec!["branch -D -- other"]);
+        // Where the remote gets it with mob sync
+        assert_eq!(
+            queue.rev_parse("origin/mob-session").unwrap(),
+            Some("b2".into())
+        );
+    }
+
+    #[test]
+    fn hands_over_offline() {
+        let dir = tempfile::tempdir().unwrap();
+        let state_dir = StateDir::new(dir.path().to_path_buf());
+        let mut git = FakeGit::default();
+        git.snapshot.branch = Some("mob-session".into());
+        git.snapshot.changed = 1;
+        git.snapshot.unstaged = 1;
+        let memory = MemoryStore::default();
+        let working = session::Session {
+            state: session::State::Working {
+                driver: "alice".into(),
+            },
+            drivers: session::Drivers::new(vec!["alice".into(), "bob".into()]),
+            settings: Some(session::Settings::default()),
+            ..session::Session::default()
+        };
+        session::SessionStore::new(&memory).save(working).unwrap();
+        let offline = Offline::new(&git, &memory);
+        let queue = Queue::new(
+            &offline,
+            &offline,
+            state_dir.clone(),
+            "origin",
+            Mode::Offline,
+        );
+        let store = session::SessionStore::new(&queue);
+        let config = Config {
+            name: "alice".into(),
+            ..Config::default()
+        };
+
+        Next::new(
+            &queue,
+            &store,
+            &NoTimer,
+            &FakeClock::default(),
+            NextOpts::parse_from(["next"]),
+            config,
+        )
+        .run()
+        .unwrap();
+        assert!(git.calls().iter().any(|call| call.starts_with("commit")));
+        assert!(!git.calls().iter().any(|call| call.starts_with("push")));
+        assert_eq!(
+            session::SessionStore::new(&memory).load().unwrap().state,
+            session::State::WaitingForNext {
+                next: Some("bob".into())
+            }
+        );
+        let pending = Pending::read(&state_dir).unwrap();
+        assert_eq!(pending.branches, vec!["mob-session".to_string()]);
+        assert!(pending.documents.contains_key("state"));
+    }
+}
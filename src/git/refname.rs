@@ -0,0 +1,126 @@
+//! Branch name rules, following `git check-ref-format --branch`.
+
+/// Why `name` can't be used as a branch, or `Ok` if git would accept it
+pub fn check_branch(name: &str) -> Result<(), &'static str> {
+    if name.is_empty() {
+        return Err("it is empty");
+    }
+    if name.starts_with('-') {
+        return Err("it starts with a dash and would be read as an option");
+    }
+    if name == "@" || name == "HEAD" {
+        return Err("it is reserved by git");
+    }
+    if name.starts_with('/') || name.ends_with('/') || name.contains("//") {
+        return Err("it has an empty path component");
+    }
+    if name.ends_with('.') {
+        return Err("it ends with a dot");
+    }
+    if name.contains("..") {
+        return Err("it contains `..`");
+    }
+    if name.contains("@{") {
+        return Err("it contains `@{`");
+    }
+    if name
+        .chars()
+        .any(|c| c.is_ascii_control() || c == ' ' || "~^:?*[\\".contains(c))
+    {
+        return Err("it contains spaces, control characters or one of ~^:?*[\\");
+    }
+    if name
+        .split('/')
+        .any(|part| part.starts_with('.') || part.ends_with(".lock"))
+    {
+        return Err("a path component starts with a dot or ends with .lock");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn valid_names() {
+        for name in &[
+            "mob-session",
+            "mob/payment-flow",
+            "fix-#123",
+            "ünïcödé/ブランチ",
+            "emoji-🚗",
+            "it's-\"quoted\"",
+            "a.b",
+            "x@y",
+        ] {
+            assert_eq!(check_branch(name), Ok(()), "{}", name);
+        }
+    }
+
+    #[test]
+    fn invalid_names() {
+        for name in &[
+            "",
+            "-f",
+            "--delete",
+            "HEAD",
+            "@",
+            "fix #123 login",
+            "a..b",
+            "a/",
+            "/a",
+            "a//b",
+            "a.",
+            ".hidden",
+            "a/.hidden",
+            "a.lock",
+            "a/b.lock/c",
+            "a@{1}",
+            "tab\there",
+            "new\nline",
+            "del\x7f",
+            "a~1",
+            "a^",
+            "a:b",
+            "a?",
+            "a*",
+            "a[b",
+            "a\\b",
+        ] {
+            assert!(check_branch(name).is_err(), "{:?} should be invalid", name);
+        }
+    }
+
+    #[test]
+    fn generated_names() {
+        // Every combination of an ordinary prefix, a nasty fragment and a suffix
+        let fragments = [
+            ("", true),
+            ("'", true),
+            ("\"", true),
+            ("#", true),
+            ("$(rm -rf)", false),
+            ("`id`", true),
+            ("ü", true),
+            ("日本", true),
+            (";", true),
+            ("|", true),
+            ("&", true),
+            (" ", false),
+            ("..", false),
+            ("\\", false),
+        ];
+        for (prefix, prefix_ok) in &[("mob", true), ("-", false), ("feat/x", true), (".", false)] {
+            for (fragment, fragment_ok) in &fragments {
+                for (suffix, suffix_ok) in
+                    &[("", true), ("-1", true), (".lock", false), ("/", false)]
+                {
+                    let name = format!("{}{}{}", prefix, fragment, suffix);
+                    let expected = *prefix_ok && *fragment_ok && *suffix_ok;
+                    assert_eq!(check_branch(&name).is_ok(), expected, "{:?}", name);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,353 @@
+//! Git facades for `--record` and `mob replay`: one writes down every call and its outcome,
+//! the other answers the same calls from what was written down.
+
+use super::{Git, Lfs, Snapshot};
+use crate::command::OutputError;
+use crate::record::{self, GitCall};
+use anyhow::{anyhow, Result};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use std::cell::RefCell;
+use std::collections::VecDeque;
+
+/// Records each call of the wrapped git
+pub struct RecordingGit<'a> {
+    git: &'a dyn Git,
+}
+
+impl<'a> RecordingGit<'a> {
+    pub fn new(git: &'a impl Git) -> Self {
+        record::repository(git.is_repository());
+        RecordingGit { git }
+    }
+
+    fn record<T: Serialize>(&self, call: &str, args: &[&str], result: Result<T>) -> Result<T> {
+        let status = match &result {
+            Ok(_) if call == "run" => Some(0),
+            Ok(_) => None,
+            Err(err) => err
+                .chain()
+                .find_map(|cause| cause.downcast_ref::<OutputError>())
+                .and_then(|output| output.status.code()),
+        };
+        record::git(GitCall {
+            call: call.to_string(),
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+            ok: result.is_ok(),
+            status,
+            result: match &result {
+                Ok(value) if call != "run" => serde_json::to_value(value).ok(),
+                _ => None,
+            },
+        });
+        result
+    }
+}
+
+impl<'a> Git for RecordingGit<'a> {
+    fn run(&self, args: &[&str]) -> Result<()> {
+        self.record("run", args, self.git.run(args))
+    }
+
+    fn snapshot(&self) -> Result<Snapshot> {
+        self.record("snapshot", &[], self.git.snapshot())
+    }
+
+    fn has_branch(&self, branch: &str) -> Result<bool> {
+        self.record("has_branch", &[branch], self.git.has_branch(branch))
+    }
+
+    fn branches(&self) -> Result<Vec<String>> {
+        self.record("branches", &[], self.git.branches())
+    }
+
+    fn remotes(&self) -> Result<Vec<String>> {
+        self.record("remotes", &[], self.git.remotes())
+    }
+
+    fn remote_url(&self, remote: &str) -> Result<Option<String>> {
+        self.record("remote_url", &[remote], self.git.remote_url(remote))
+    }
+
+    fn root_commit(&self) -> Result<Option<String>> {
+        self.record("root_commit", &[], self.git.root_commit())
+    }
+
+    fn rev_parse(&self, rev: &str) -> Result<Option<String>> {
+        self.record("rev_parse", &[rev], self.git.rev_parse(rev))
+    }
+
+    fn remote_tip(&self, remote: &str, branch: &str) -> Result<Option<String>> {
+        let result = self.git.remote_tip(remote, branch);
+        self.record("remote_tip", &[remote, branch], result)
+    }
+
+    fn commit_tree_id(&self, commit: &str) -> Result<String> {
+        self.record("commit_tree_id", &[commit], self.git.commit_tree_id(commit))
+    }
+
+    fn commit_parents(&self, commit: &str) -> Result<Vec<String>> {
+        self.record("commit_parents", &[commit], self.git.commit_parents(commit))
+    }
+
+    fn merge_base(&self, one: &str, other: &str) -> Result<Option<String>> {
+        let result = self.git.merge_base(one, other);
+        self.record("merge_base", &[one, other], result)
+    }
+
+    fn lfs(&self) -> Result<Lfs> {
+        self.record("lfs", &[], self.git.lfs())
+    }
+
+    fn lfs_unconverted(&self) -> Result<Vec<String>> {
+        self.record("lfs_unconverted", &[], self.git.lfs_unconverted())
+    }
+
+    fn conflicted(&self) -> Result<Vec<String>> {
+        self.record("conflicted", &[], self.git.conflicted())
+    }
+
+    fn staged_diff(&self) -> Result<String> {
+        self.record("staged_diff", &[], self.git.staged_diff())
+    }
+
+    fn staged_numstat(&self, ignore_whitespace: bool) -> Result<String> {
+        let args = [if ignore_whitespace { "-w" } else { "" }];
+        let numstat = self.git.staged_numstat(ignore_whitespace);
+        self.record("staged_numstat", &args, numstat)
+    }
+
+    fn staged_check(&self) -> Result<String> {
+        self.record("staged_check", &[], self.git.staged_check())
+    }
+
+    fn read_file(&self, path: &str) -> Result<Vec<u8>> {
+        self.record("read_file", &[path], self.git.read_file(path))
+    }
+
+    fn write_file(&self, path: &str, data: &[u8]) -> Result<()> {
+        self.record("write_file", &[path], self.git.write_file(path, data))
+    }
+
+    fn log_oneline(&self, range: &str) -> Result<Vec<String>> {
+        self.record("log_oneline", &[range], self.git.log_oneline(range))
+    }
+
+    fn log_authors(&self, range: &str) -> Result<Vec<String>> {
+        self.record("log_authors", &[range], self.git.log_authors(range))
+    }
+
+    fn diffstat(&self, range: &str) -> Result<String> {
+        self.record("diffstat", &[range], self.git.diffstat(range))
+    }
+
+    fn file_sizes(&self, commit: &str) -> Result<Vec<(String, u64)>> {
+        self.record("file_sizes", &[commit], self.git.file_sizes(commit))
+    }
+
+    fn is_repository(&self) -> bool {
+        self.git.is_repository()
+    }
+
+    fn members(&self) -> Vec<(&str, &dyn Git)> {
+        self.git.members()
+    }
+}
+
+/// Answers calls from a recording, in the order they were made. A call the recording
+/// doesn't have next fails, so a replay that goes another way stops there.
+pub struct ReplayGit {
+    calls: RefCell<VecDeque<GitCall>>,
+    repository: bool,
+}
+
+impl ReplayGit {
+    pub fn new(calls: Vec<GitCall>, repository: bool) -> Self {
+        ReplayGit {
+            calls: RefCell::new(calls.into()),
+            repository,
+        }
+    }
+
+    /// The recorded calls that weren't made
+    pub fn left(&self) -> Vec<GitCall> {
+        self.calls.borrow().iter().cloned().collect()
+    }
+
+    fn replay<T: DeserializeOwned>(&self, call: &str, args: &[&str]) -> Result<T> {
+        let mut calls = self.calls.borrow_mut();
+        let expected = calls.front().ok_or_else(|| {
+            anyhow!(
+                "replay made git {} {} after all recorded calls",
+                call,
+                args.join(" ")
+            )
+        })?;
+        if expected.call != call || expected.args != args {
+            return Err(anyhow!(
+                "replay made git {} {} where {} {} was recorded",
+                call,
+                args.join(" "),
+                expected.call,
+                expected.args.join(" ")
+            ));
+        }
+        let recorded = calls.pop_front().expect("checked above");
+        if !recorded.ok {
+            return Err(match recorded.status {
+                Some(status) => anyhow!("recorded git {} exited with {}", call, status),
+                None => anyhow!("recorded git {} failed", call),
+            });
+        }
+        Ok(serde_json::from_value(
+            recorded.result.unwrap_or(Value::Null),
+        )?)
+    }
+}
+
+impl Git for ReplayGit {
+    fn run(&self, args: &[&str]) -> Result<()> {
+        self.replay("run", args)
+    }
+
+    fn snapshot(&self) -> Result<Snapshot> {
+        self.replay("snapshot", &[])
+    }
+
+    fn has_branch(&self, branch: &str) -> Result<bool> {
+        self.replay("has_branch", &[branch])
+    }
+
+    fn branches(&self) -> Result<Vec<String>> {
+        self.replay("branches", &[])
+    }
+
+    fn remotes(&self) -> Result<Vec<String>> {
+        self.replay("remotes", &[])
+    }
+
+    fn remote_url(&self, remote: &str) -> Result<Option<String>> {
+        self.replay("remote_url", &[remote])
+    }
+
+    fn root_commit(&self) -> Result<Option<String>> {
+        self.replay("root_commit", &[])
+    }
+
+    fn rev_parse(&self, rev: &str) -> Result<Option<String>> {
+        self.replay("rev_parse", &[rev])
+    }
+
+    fn remote_tip(&self, remote: &str, branch: &str) -> Result<Option<String>> {
+        self.replay("remote_tip", &[remote, branch])
+    }
+
+    fn commit_tree_id(&self, commit: &str) -> Result<String> {
+        self.replay("commit_tree_id", &[commit])
+    }
+
+    fn commit_parents(&self, commit: &str) -> Result<Vec<String>> {
+        self.replay("commit_parents", &[commit])
+    }
+
+    fn merge_base(&self, one: &str, other: &str) -> Result<Option<String>> {
+        self.replay("merge_base", &[one, other])
+    }
+
+    fn lfs(&self) -> Result<Lfs> {
+        self.replay("lfs", &[])
+    }
+
+    fn lfs_unconverted(&self) -> Result<Vec<String>> {
+        self.replay("lfs_unconverted", &[])
+    }
+
+    fn conflicted(&self) -> Result<Vec<String>> {
+        self.replay("conflicted", &[])
+    }
+
+    fn staged_diff(&self) -> Result<String> {
+        self.replay("staged_diff", &[])
+    }
+
+    fn staged_numstat(&self, ignore_whitespace: bool) -> Result<String> {
+        self.replay(
+            "staged_numstat",
+            &[if ignore_whitespace { "-w" } else { "" }],
+        )
+    }
+
+    fn staged_check(&self) -> Result<String> {
+        self.replay("staged_check", &[])
+    }
+
+    fn read_file(&self, path: &str) -> Result<Vec<u8>> {
+        self.replay("read_file", &[path])
+    }
+
+    fn write_file(&self, path: &str, _data: &[u8]) -> Result<()> {
+        self.replay("write_file", &[path])
+    }
+
+    fn log_oneline(&self, range: &str) -> Result<Vec<String>> {
+        self.replay("log_oneline", &[range])
+    }
+
+    fn log_authors(&self, range: &str) -> Result<Vec<String>> {
+        self.replay("log_authors", &[range])
+    }
+
+    fn diffstat(&self, range: &str) -> Result<String> {
+        self.replay("diffstat", &[range])
+    }
+
+    fn file_sizes(&self, commit: &str) -> Result<Vec<(String, u64)>> {
+        self.replay("file_sizes", &[commit])
+    }
+
+    fn is_repository(&self) -> bool {
+        self.repository
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::fake::FakeGit;
+
+    #[test]
+    fn replays_what_was_recorded() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("mob.jsonl");
+        let fake = FakeGit {
+            branches: vec!["mob-session".to_string()].into_iter().collect(),
+            ..FakeGit::default()
+        };
+        record::start(&path, vec![]);
+        let git = RecordingGit::new(&fake);
+        git.run(&["fetch", "origin"]).unwrap();
+        assert!(git.has_branch("mob-session").unwrap());
+        assert!(git.commit_tree_id("abc").is_err());
+        record::finish(None).unwrap();
+
+        let entry = record::read(&path).unwrap().remove(0);
+        assert_eq!(entry.git[0].status, Some(0));
+        assert!(!entry.git[2].ok);
+
+        let replay = ReplayGit::new(entry.git.clone(), entry.repository);
+        replay.run(&["fetch", "origin"]).unwrap();
+        assert!(replay.has_branch("mob-session").unwrap());
+        assert_eq!(replay.left().len(), 1);
+        assert!(replay.commit_tree_id("abc").is_err());
+        assert!(replay.left().is_empty());
+
+        let replay = ReplayGit::new(entry.git, entry.repository);
+        let err = replay.run(&["push"]).unwrap_err();
+        assert!(
+            err.to_string().contains("where run fetch origin"),
+            "{}",
+            err
+        );
+        assert_eq!(replay.left().len(), 3);
+    }
+}
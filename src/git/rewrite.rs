@@ -0,0 +1,76 @@
+//! What became of a pushed commit on the remote. Some servers rewrite what they receive, like
+//! Gerrit hooks adding a Change-Id, and the local branch then looks diverged.
+
+use super::Git;
+use anyhow::Result;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommitInfo {
+    pub id: String,
+    pub tree: String,
+    pub parents: Vec<String>,
+}
+
+impl CommitInfo {
+    pub fn read(git: &dyn Git, id: &str) -> Result<CommitInfo> {
+        Ok(CommitInfo {
+            id: id.to_string(),
+            tree: git.commit_tree_id(id)?,
+            parents: git.commit_parents(id)?,
+        })
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Pushed {
+    /// The remote has exactly what was pushed
+    Same,
+    /// The server amended the commit or added one on top, the local branch can follow it
+    Rewritten,
+    /// Someone else pushed something else
+    Diverged,
+}
+
+pub fn classify(pushed: &CommitInfo, remote: &CommitInfo) -> Pushed {
+    if remote.id == pushed.id {
+        Pushed::Same
+    } else if remote.parents.contains(&pushed.id)
+        || (remote.tree == pushed.tree && remote.parents == pushed.parents)
+    {
+        Pushed::Rewritten
+    } else {
+        Pushed::Diverged
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn commit(id: &str, tree: &str, parents: &[&str]) -> CommitInfo {
+        CommitInfo {
+            id: id.into(),
+            tree: tree.into(),
+            parents: parents.iter().map(|parent| parent.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn classifies() {
+        let pushed = commit("b2", "t2", &["a1"]);
+        let cases = vec![
+            (commit("b2", "t2", &["a1"]), Pushed::Same),
+            // Amended with a Change-Id: new id, same tree and parents
+            (commit("c3", "t2", &["a1"]), Pushed::Rewritten),
+            // A commit added on top by the server
+            (commit("c3", "t3", &["b2"]), Pushed::Rewritten),
+            (commit("c3", "t3", &["a1"]), Pushed::Diverged),
+            // Same content on another history is still someone else's
+            (commit("c3", "t2", &["x9"]), Pushed::Diverged),
+            (commit("c3", "t3", &["x9", "b2"]), Pushed::Rewritten),
+        ];
+        for (remote, expected) in cases {
+            assert_eq!(classify(&pushed, &remote), expected, "{:?}", remote);
+        }
+    }
+}
@@ -0,0 +1,161 @@
+//! Signed commits for repositories that require them, with `commit.sign` or `--sign`. Git
+//! signs with gpg or ssh as its own config says, mob only asks for it and explains failures.
+
+use super::Git;
+use anyhow::Result;
+
+/// The tree without files, in every repository
+const EMPTY_TREE: &str = "4b825dc642cb6eb9a060e54bf8d69288fbee4904";
+
+/// What git printed when signing failed, by what can be done about it
+const NO_KEY: &[&str] = &[
+    "secret key not available",
+    "No secret key",
+    "user.signingkey",
+    "Couldn't load public key",
+    "No such file or directory",
+];
+const AGENT: &[&str] = &[
+    "Inappropriate ioctl for device",
+    "No agent running",
+    "agent refused operation",
+    "Couldn't get agent socket",
+    "could not connect to agent",
+    "No pinentry",
+    "Operation cancelled",
+];
+const SIGNING: &[&str] = &["failed to sign the data", "gpg failed", "ssh-keygen"];
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum Error {
+    #[error(
+        "Git has no key to sign with, set one with git config user.signingkey or run with --no-sign"
+    )]
+    NoKey,
+    #[error(
+        "The signing agent didn't answer, start gpg-agent or ssh-agent and unlock your key, or run with --no-sign"
+    )]
+    Agent,
+    #[error("Signing failed, check git config gpg.format and user.signingkey: {0}")]
+    Failed(String),
+}
+
+/// Commits with `args` after `git commit`, signed when `sign` is set
+pub fn commit(git: &dyn Git, sign: bool, args: &[&str]) -> Result<()> {
+    let mut commit = vec!["commit"];
+    if sign {
+        commit.push("-S");
+    }
+    commit.extend(args);
+    git.run(&commit).map_err(|err| match sign {
+        true => explain(err),
+        false => err,
+    })
+}
+
+/// Signs a commit nothing points to, so a key that can't sign shows at `mob start` and not
+/// at the first handover. Git prunes the commit with its other garbage.
+pub fn check(git: &dyn Git) -> Result<()> {
+    git.run(&[
+        "commit-tree",
+        "-S",
+        "-m",
+        "mob checks that commits can be signed",
+        EMPTY_TREE,
+    ])
+    .map_err(explain)
+}
+
+/// The typed error of a commit that failed to be signed, other failures as they are
+pub fn explain(err: anyhow::Error) -> anyhow::Error {
+    let text = format!("{:#}", err);
+    let found = |patterns: &[&str]| patterns.iter().any(|pattern| text.contains(pattern));
+    if !found(SIGNING) && !found(AGENT) && !text.contains("user.signingkey") {
+        return err;
+    }
+    let typed = if found(AGENT) {
+        Error::Agent
+    } else if found(NO_KEY) {
+        Error::NoKey
+    } else {
+        let stderr = text.rsplit("stderr:").next().unwrap_or(&text);
+        Error::Failed(stderr.trim().to_string())
+    };
+    log::trace!("Signing failed: {}", text);
+    typed.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::fake::FakeGit;
+
+    #[test]
+    fn signs_only_when_asked() {
+        let git = FakeGit::default();
+        commit(&git, true, &["--message", "wip", "--no-verify"]).unwrap();
+        commit(&git, false, &["--message", "wip"]).unwrap();
+        assert_eq!(
+            git.calls(),
+            vec!["commit -S --message wip --no-verify", "commit --message wip"]
+        );
+    }
+
+    #[test]
+    fn explains_what_went_wrong() {
+        let cases = vec![
+            (
+                "error: gpg failed to sign the data\ngpg: signing failed: Inappropriate ioctl for device",
+                Some(Error::Agent),
+            ),
+            (
+                "gpg: skipped \"alice\": No secret key\nerror: gpg failed to sign the data",
+                Some(Error::NoKey),
+            ),
+            (
+                "error: either user.signingkey or gpg.ssh.defaultKeyCommand needs to be configured",
+                Some(Error::NoKey),
+            ),
+            (
+                "error: Couldn't get agent socket?\nerror: ssh-keygen failed",
+                Some(Error::Agent),
+            ),
+            (
+                "error: gpg failed to sign the data",
+                Some(Error::Failed("error: gpg failed to sign the data".into())),
+            ),
+            ("fatal: not a git repository", None),
+        ];
+        for (stderr, expected) in cases {
+            let git = FakeGit::default();
+            git.fail("commit", 0, stderr);
+            let err = commit(&git, true, &["--message", "wip"]).unwrap_err();
+            assert_eq!(err.downcast_ref::<Error>(), expected.as_ref(), "{}", stderr);
+        }
+
+        // Unsigned commits fail as git said
+        let git = FakeGit::default();
+        git.fail("commit", 0, "error: gpg failed to sign the data");
+        let err = commit(&git, false, &["--message", "wip"]).unwrap_err();
+        assert!(err.downcast_ref::<Error>().is_none());
+    }
+
+    #[test]
+    fn checks_with_a_commit_nothing_points_to() {
+        let git = FakeGit::default();
+        check(&git).unwrap();
+        assert_eq!(
+            git.calls(),
+            vec![format!(
+                "commit-tree -S -m mob checks that commits can be signed {}",
+                EMPTY_TREE
+            )]
+        );
+        git.fail("commit-tree", 0, "gpg: signing failed: No pinentry");
+        assert_eq!(
+            check(&git).unwrap_err().downcast_ref::<Error>(),
+            Some(&Error::Agent)
+        );
+    }
+}
@@ -0,0 +1,45 @@
+//! Sizes of the files a commit changed, from `git diff-tree --name-only -z` and
+//! `git ls-tree -r -l -z` of the commit.
+
+use std::collections::HashMap;
+
+/// Each changed path with its size in the tree, 0 for removed files and submodules
+pub fn parse(changed: &str, tree: &str) -> Vec<(String, u64)> {
+    let sizes: HashMap<&str, u64> = tree
+        .split('\0')
+        .filter_map(|entry| {
+            let (meta, path) = entry.split_once('\t')?;
+            let size = meta.split_whitespace().nth(3)?.parse().unwrap_or(0);
+            Some((path, size))
+        })
+        .collect();
+    changed
+        .split('\0')
+        .filter(|path| !path.is_empty())
+        .map(|path| (path.to_string(), sizes.get(path).copied().unwrap_or(0)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses() {
+        let changed = "assets/video.mp4\0src/lib.rs\0gone.txt\0";
+        let tree = "100644 blob 0a1b2c 12345678\tassets/video.mp4\0\
+                    100644 blob 3d4e5f     420\tsrc/lib.rs\0\
+                    100644 blob 6a7b8c      10\tREADME.md\0\
+                    160000 commit 9d0e1f      -\tvendor/dep\0";
+        assert_eq!(
+            parse(changed, tree),
+            vec![
+                ("assets/video.mp4".to_string(), 12_345_678),
+                ("src/lib.rs".to_string(), 420),
+                ("gone.txt".to_string(), 0),
+            ]
+        );
+        assert_eq!(parse("vendor/dep\0", tree), vec![("vendor/dep".into(), 0)]);
+        assert!(parse("", tree).is_empty());
+    }
+}
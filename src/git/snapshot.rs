@@ -0,0 +1,196 @@
+//! The state of the working tree from one `git status --porcelain=v2 --branch` call.
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq)]
+#[error("unexpected line in git status: `{0}`")]
+pub struct ParseError(String);
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Snapshot {
+    /// None on a detached HEAD
+    pub branch: Option<String>,
+    /// None before the first commit
+    pub oid: Option<String>,
+    pub upstream: Option<String>,
+    pub ahead: usize,
+    pub behind: usize,
+    pub staged: usize,
+    pub unstaged: usize,
+    /// Tracked files with staged or unstaged changes, or both
+    pub changed: usize,
+    pub untracked: usize,
+    pub conflicted: usize,
+    /// The paths of the untracked files, of directories when all in them is untracked
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub untracked_files: Vec<String>,
+}
+
+impl Snapshot {
+    pub fn is_clean(&self) -> bool {
+        self.staged + self.unstaged + self.untracked + self.conflicted == 0
+    }
+
+    /// Files that differ from HEAD or are new
+    pub fn files(&self) -> usize {
+        self.changed + self.untracked + self.conflicted
+    }
+
+    pub fn is_on(&self, branch: &str) -> bool {
+        self.branch.as_deref() == Some(branch)
+    }
+
+    pub fn parse(output: &str) -> Result<Snapshot, ParseError> {
+        let mut snapshot = Snapshot::default();
+        for line in output.lines().filter(|line| !line.is_empty()) {
+            let error = || ParseError(line.to_string());
+            let (kind, rest) = line.split_at(1);
+            let rest = rest.strip_prefix(' ').ok_or_else(error)?;
+            match kind {
+                "#" => snapshot.parse_header(rest).ok_or_else(error)?,
+                "1" | "2" => {
+                    let xy = rest.get(..2).ok_or_else(error)?;
+                    let mut xy = xy.chars();
+                    snapshot.changed += 1;
+                    if xy.next() != Some('.') {
+                        snapshot.staged += 1;
+                    }
+                    if xy.next() != Some('.') {
+                        snapshot.unstaged += 1;
+                    }
+                }
+                "u" => snapshot.conflicted += 1,
+                "?" => {
+                    snapshot.untracked += 1;
+                    snapshot.untracked_files.push(rest.to_string());
+                }
+                "!" => (),
+                _ => return Err(error()),
+            }
+        }
+        Ok(snapshot)
+    }
+
+    fn parse_header(&mut self, header: &str) -> Option<()> {
+        let (key, value) = header.split_once(' ')?;
+        match key {
+            "branch.oid" => self.oid = Some(value.to_string()).filter(|oid| oid != "(initial)"),
+            "branch.head" => {
+                self.branch = Some(value.to_string()).filter(|head| head != "(detached)")
+            }
+            "branch.upstream" => self.upstream = Some(value.to_string()),
+            "branch.ab" => {
+                let (ahead, behind) = value.split_once(' ')?;
+                self.ahead = ahead.strip_prefix('+')?.parse().ok()?;
+                self.behind = behind.strip_prefix('-')?.parse().ok()?;
+            }
+            // Newer git versions may add headers, like branch.stash
+            _ => (),
+        }
+        Some(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clean_with_upstream() {
+        let snapshot = Snapshot::parse(
+            "# branch.oid 3e1f1a6c1b2d3e4f5a6b7c8d9e0f1a2b3c4d5e6f
+# branch.head mob-session
+# branch.upstream origin/mob-session
+# branch.ab +2 -1
+",
+        )
+        .unwrap();
+        assert!(snapshot.is_clean());
+        assert!(snapshot.is_on("mob-session"));
+        assert_eq!(snapshot.upstream.as_deref(), Some("origin/mob-session"));
+        assert_eq!((snapshot.ahead, snapshot.behind), (2, 1));
+    }
+
+    #[test]
+    fn changes_and_renames() {
+        let snapshot = Snapshot::parse(
+            "# branch.oid 3e1f1a6c1b2d3e4f5a6b7c8d9e0f1a2b3c4d5e6f
+# branch.head master
+1 M. N... 100644 100644 100644 3e1f1a6 3e1f1a7 src/staged.rs
+1 .M N... 100644 100644 100644 3e1f1a6 3e1f1a6 src/unstaged.rs
+1 MM N... 100644 100644 100644 3e1f1a6 3e1f1a7 src/both.rs
+2 R. N... 100644 100644 100644 3e1f1a6 3e1f1a6 R100 src/new name.rs\tsrc/old name.rs
+? notes.txt
+? dir with space/
+! target/
+",
+        )
+        .unwrap();
+        assert!(!snapshot.is_clean());
+        assert_eq!(snapshot.upstream, None);
+        assert_eq!(snapshot.staged, 3);
+        assert_eq!(snapshot.unstaged, 2);
+        assert_eq!(snapshot.untracked, 2);
+        assert_eq!(
+            snapshot.untracked_files,
+            vec!["notes.txt", "dir with space/"]
+        );
+        assert_eq!(snapshot.conflicted, 0);
+        assert_eq!(snapshot.changed, 4);
+        assert_eq!(snapshot.files(), 6);
+    }
+
+    #[test]
+    fn conflicts() {
+        let snapshot = Snapshot::parse(
+            "# branch.oid 3e1f1a6c1b2d3e4f5a6b7c8d9e0f1a2b3c4d5e6f
+# branch.head mob-session
+u UU N... 100644 100644 100644 100644 3e1f1a6 3e1f1a7 3e1f1a8 src/lib.rs
+u AA N... 000000 100644 100644 100644 0000000 3e1f1a7 3e1f1a8 README.md
+",
+        )
+        .unwrap();
+        assert_eq!(snapshot.conflicted, 2);
+        assert!(!snapshot.is_clean());
+    }
+
+    #[test]
+    fn detached_and_initial() {
+        let detached = Snapshot::parse(
+            "# branch.oid 3e1f1a6c1b2d3e4f5a6b7c8d9e0f1a2b3c4d5e6f\n# branch.head (detached)\n",
+        )
+        .unwrap();
+        assert_eq!(detached.branch, None);
+        assert!(detached.oid.is_some());
+
+        let initial = Snapshot::parse("# branch.oid (initial)\n# branch.head main\n").unwrap();
+        assert_eq!(initial.oid, None);
+        assert!(initial.is_on("main"));
+    }
+
+    #[test]
+    fn submodules() {
+        let snapshot = Snapshot::parse(
+            "# branch.oid 3e1f1a6c1b2d3e4f5a6b7c8d9e0f1a2b3c4d5e6f
+# branch.head master
+1 .M SC.. 160000 160000 160000 3e1f1a6 3e1f1a6 vendor/lib
+1 .M S..U 160000 160000 160000 3e1f1a6 3e1f1a6 vendor/other
+",
+        )
+        .unwrap();
+        assert_eq!(snapshot.unstaged, 2);
+        assert_eq!(snapshot.staged, 0);
+    }
+
+    #[test]
+    fn unknown_headers_and_bad_lines() {
+        assert!(Snapshot::parse("# branch.stash 3\n# branch.head x\n").is_ok());
+        assert_eq!(
+            Snapshot::parse("# branch.ab 2 1\n"),
+            Err(ParseError("# branch.ab 2 1".into()))
+        );
+        assert!(Snapshot::parse("X something\n").is_err());
+        assert!(Snapshot::parse("1\n").is_err());
+    }
+}
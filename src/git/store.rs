@@ -1,10 +1,17 @@
 use super::*;
 
 const SESSION_FILENAME: &str = "data";
-const SESSION_HEAD: &str = "mob-meta";
+/// Where the documents are kept, outside of the branches so no diff or merge ever sees them
+const REFS: &str = "refs/mob/";
+/// The branch documents were kept on before, the default one on it and others on a branch
+/// of their own. Read until the next save moves the document to its ref.
+const LEGACY_HEAD: &str = "mob-meta";
+/// The document of the default session
+pub const SESSION_DOCUMENT: &str = "session";
 const COMMIT_MESSAGE: &str = "mob metadata changed [skip ci]";
 
 #[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
 pub enum Error {
     #[error("failure pushing to origin: `{0}`")]
     Conflict(#[from] anyhow::Error),
@@ -16,60 +23,122 @@ pub enum Error {
 
     #[error("missing config")]
     Missing,
+
+    #[error("unable to access session file: `{0}`")]
+    Io(#[from] std::io::Error),
+
+    #[error("the session {0} was changed by someone else in the meantime, run the command again")]
+    Changed(String),
+
+    #[error("mob is offline and can't save the session, run the command again without --offline")]
+    Offline,
 }
 
+/// Keeps named documents that are saved and fetched independently of each other
 pub trait Store {
-    fn load(&self) -> Result<Vec<u8>, Error>;
-    fn save(&self, data: &[u8]) -> Result<(), Error>;
-    fn clean(&self) -> Result<(), Error>;
-}
+    fn load(&self, document: &str) -> Result<Vec<u8>, Error>;
+    fn save(&self, document: &str, data: &[u8]) -> Result<(), Error>;
+    fn clean(&self, document: &str) -> Result<(), Error>;
 
-impl<'repo> Store for GitCommand<'repo> {
-    fn save(&self, data: &[u8]) -> Result<(), store::Error> {
-        let filename = SESSION_FILENAME;
+    /// Names of the documents there are, for finding what was saved without knowing its name
+    fn documents(&self) -> Result<Vec<String>, Error> {
+        Ok(vec![])
+    }
 
-        let commit = CommitFile {
-            filename,
-            data,
-            reference: SESSION_HEAD,
-            message: COMMIT_MESSAGE,
+    /// Saves `data` only if the document is still `expected`, `None` when it was missing
+    fn swap(&self, document: &str, expected: Option<&[u8]>, data: &[u8]) -> Result<(), Error> {
+        let current = match self.load(document) {
+            Ok(current) => Some(current),
+            Err(Error::Missing) => None,
+            Err(err) => return Err(err),
         };
+        if current.as_deref() != expected {
+            return Err(Error::Changed(document.to_string()));
+        }
+        self.save(document, data)
+    }
 
-        self.create_commit(commit)?;
+    /// Saves `data` here only, for pushing it later
+    fn keep(&self, document: &str, data: &[u8]) -> Result<(), Error> {
+        self.save(document, data)
+    }
+}
 
-        self.run_quietly(&[
-            "push",
-            "--no-verify",
-            self.remote.as_str(),
-            format!("{}:{}", SESSION_HEAD, SESSION_HEAD).as_str(),
-        ])
-        .map_err(store::Error::Conflict) // TODO: should check for "rejected" in output
+/// The ref `document` is kept in
+pub fn reference(document: &str) -> String {
+    format!("{}{}", REFS, document)
+}
+
+/// The branch `document` was kept on before there were refs
+fn legacy_reference(document: &str) -> String {
+    if document == SESSION_DOCUMENT {
+        format!("refs/heads/{}", LEGACY_HEAD)
+    } else {
+        format!("refs/heads/{}-{}", LEGACY_HEAD, document)
+    }
+}
+
+/// The document kept in `reference`, `None` for references that aren't mob's
+fn document(reference: &str) -> Option<String> {
+    if let Some(document) = reference.strip_prefix(REFS) {
+        return Some(document.to_string()).filter(|document| !document.is_empty());
+    }
+    match reference
+        .strip_prefix("refs/heads/")?
+        .strip_prefix(LEGACY_HEAD)?
+    {
+        "" => Some(SESSION_DOCUMENT.to_string()),
+        document => document.strip_prefix('-').map(String::from),
     }
+}
 
-    fn load(&self) -> Result<Vec<u8>, store::Error> {
-        self.run_quietly(&["branch", "-D", SESSION_HEAD])
-            .unwrap_or_else(|err| {
-                log::trace!(
-                    "Could not delete local mob branch {}: {}",
-                    SESSION_HEAD,
-                    err
-                )
-            });
+/// The documents of `refs`, lines with an object and a reference like `ls-remote` and
+/// `for-each-ref` list them. Those still on a branch are listed once.
+fn documents(refs: &str) -> Vec<String> {
+    let mut documents: Vec<String> = vec![];
+    for document in refs
+        .lines()
+        .filter_map(|line| line.split_whitespace().nth(1))
+        .filter_map(document)
+    {
+        if !documents.contains(&document) {
+            documents.push(document);
+        }
+    }
+    documents
+}
 
-        self.run_quietly(&[
+/// Whether the push failed with `err` because the remote moved since it was fetched
+fn rejected(err: &anyhow::Error) -> bool {
+    let message = err.to_string();
+    ["[rejected]", "non-fast-forward", "fetch first"]
+        .iter()
+        .any(|reason| message.contains(reason))
+}
+
+impl<'repo> GitCommand<'repo> {
+    /// Replaces the local `reference` with the one of the remote, dropping it when the remote
+    /// has none. Without a connection the local one stays.
+    fn fetch_session(&self, reference: &str) {
+        let fetched = self.run_quietly(&[
             "fetch",
             self.remote.as_str(),
-            format!("{}:{}", SESSION_HEAD, SESSION_HEAD).as_str(),
-        ])
-        .unwrap_or_else(|err| {
-            log::trace!(
-                "Could not fetch remote mob branch {}: {}",
-                SESSION_HEAD,
-                err
-            )
-        });
+            format!("+{}:{}", reference, reference).as_str(),
+        ]);
+        match fetched {
+            Err(err) if err.to_string().contains("couldn't find remote ref") => {
+                self.run_quietly(&["update-ref", "-d", reference])
+                    .unwrap_or_else(|err| {
+                        log::trace!("Could not delete local {}: {}", reference, err)
+                    });
+            }
+            Err(err) => log::trace!("Could not fetch remote {}: {}", reference, err),
+            Ok(()) => {}
+        }
+    }
 
-        let commit = self.last_commit(SESSION_HEAD);
+    fn read_session(&self, reference: &str) -> Result<Vec<u8>, store::Error> {
+        let commit = self.last_commit(reference);
 
         let commit = match commit {
             Some(commit) => commit,
@@ -94,17 +163,198 @@ impl<'repo> Store for GitCommand<'repo> {
         Ok(blob.content().into())
     }
 
-    fn clean(&self) -> Result<(), store::Error> {
-        self.run_quietly(&["branch", "-D", SESSION_HEAD])
-            .unwrap_or_else(|err| log::trace!("Failed to delete local branch: {}", err));
+    /// The document from its ref, or from the branch of an older mob
+    fn read_document(&self, document: &str) -> Result<Vec<u8>, store::Error> {
+        match self.read_session(&reference(document)) {
+            Err(store::Error::Missing) => self.read_session(&legacy_reference(document)),
+            read => read,
+        }
+    }
+
+    /// Removes the branch `document` was kept on once it is saved to its ref
+    fn drop_legacy(&self, document: &str) {
+        let legacy = legacy_reference(document);
+        if self.last_commit(&legacy).is_none() {
+            return;
+        }
+        log::info!(
+            "Moved the session from the branch {} to {}",
+            legacy.trim_start_matches("refs/heads/"),
+            reference(document)
+        );
+        self.delete(&legacy);
+    }
+
+    /// Deletes `reference` here and on the remote, whichever has it
+    fn delete(&self, reference: &str) {
+        self.run_quietly(&["update-ref", "-d", reference])
+            .unwrap_or_else(|err| log::trace!("Failed to delete local {}: {}", reference, err));
         self.run_quietly(&[
             "push",
             self.remote.as_str(),
             "--no-verify",
             "--delete",
-            SESSION_HEAD,
+            reference,
+        ])
+        .unwrap_or_else(|err| log::trace!("Failed to delete remote {}: {}", reference, err));
+    }
+}
+
+impl<'repo> Store for GitCommand<'repo> {
+    fn save(&self, document: &str, data: &[u8]) -> Result<(), store::Error> {
+        let reference = reference(document);
+        let commit = CommitFile {
+            filename: SESSION_FILENAME,
+            data,
+            reference: &reference,
+            message: COMMIT_MESSAGE,
+        };
+
+        self.create_commit(commit)?;
+
+        self.run_quietly(&[
+            "push",
+            "--no-verify",
+            self.remote.as_str(),
+            format!("{}:{}", reference, reference).as_str(),
         ])
-        .unwrap_or_else(|err| log::trace!("Failed to remove remote branch: {}", err));
+        .map_err(|err| match rejected(&err) {
+            true => {
+                // Ours stays unpushed, the next load reads theirs even without fetching
+                self.fetch_session(&reference);
+                store::Error::Changed(document.to_string())
+            }
+            false => store::Error::Conflict(err),
+        })?;
+        self.drop_legacy(document);
+        Ok(())
+    }
+
+    /// Commits to the local ref, the next save pushes it along
+    fn keep(&self, document: &str, data: &[u8]) -> Result<(), store::Error> {
+        let reference = reference(document);
+        self.create_commit(CommitFile {
+            filename: SESSION_FILENAME,
+            data,
+            reference: &reference,
+            message: COMMIT_MESSAGE,
+        })?;
         Ok(())
     }
+
+    /// Falls back to the branch of an older mob and moves the document to its ref, unless
+    /// offline when the next save moves it
+    fn load(&self, document: &str) -> Result<Vec<u8>, store::Error> {
+        let reference = reference(document);
+        if self.auto_fetch {
+            self.fetch_session(&reference);
+        }
+        match self.read_session(&reference) {
+            Err(store::Error::Missing) => {
+                let legacy = legacy_reference(document);
+                if !self.auto_fetch {
+                    return self.read_session(&legacy);
+                }
+                self.fetch_session(&legacy);
+                let data = self.read_session(&legacy)?;
+                if let Err(err) = self.save(document, &data) {
+                    log::warn!(
+                        "Could not move the session from the branch {} to {}: {}",
+                        legacy.trim_start_matches("refs/heads/"),
+                        reference,
+                        err
+                    );
+                }
+                Ok(data)
+            }
+            read => read,
+        }
+    }
+
+    /// Compares with the local ref, the push is rejected if the remote moved since
+    fn swap(
+        &self,
+        document: &str,
+        expected: Option<&[u8]>,
+        data: &[u8],
+    ) -> Result<(), store::Error> {
+        let current = match self.read_document(document) {
+            Ok(current) => Some(current),
+            Err(store::Error::Missing) => None,
+            Err(err) => return Err(err),
+        };
+        if current.as_deref() != expected {
+            return Err(store::Error::Changed(document.to_string()));
+        }
+        self.save(document, data)
+    }
+
+    /// The refs on the remote when fetching, the local ones otherwise
+    fn documents(&self) -> Result<Vec<String>, store::Error> {
+        let refs = format!("{}*", REFS);
+        let legacy = format!("refs/heads/{}*", LEGACY_HEAD);
+        let output = match self.auto_fetch {
+            true => self.command.run_stdout([
+                "ls-remote",
+                self.remote.as_str(),
+                refs.as_str(),
+                legacy.as_str(),
+            ])?,
+            false => self.command.run_stdout([
+                "for-each-ref",
+                "--format=%(objectname) %(refname)",
+                refs.as_str(),
+                legacy.as_str(),
+            ])?,
+        };
+        Ok(documents(&output))
+    }
+
+    fn clean(&self, document: &str) -> Result<(), store::Error> {
+        self.delete(&reference(document));
+        self.delete(&legacy_reference(document));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_documents_in_refs() {
+        assert_eq!(reference(SESSION_DOCUMENT), "refs/mob/session");
+        assert_eq!(reference("state-review"), "refs/mob/state-review");
+        assert_eq!(legacy_reference(SESSION_DOCUMENT), "refs/heads/mob-meta");
+        assert_eq!(legacy_reference("state"), "refs/heads/mob-meta-state");
+    }
+
+    #[test]
+    fn lists_documents_of_refs_and_branches() {
+        let refs = "\
+1111111111111111111111111111111111111111\trefs/mob/session
+2222222222222222222222222222222222222222\trefs/mob/state
+3333333333333333333333333333333333333333\trefs/heads/mob-meta
+4444444444444444444444444444444444444444\trefs/heads/mob-meta-session-review
+5555555555555555555555555555555555555555\trefs/heads/mob-metadata
+6666666666666666666666666666666666666666 refs/heads/mob-session
+";
+        assert_eq!(documents(refs), vec!["session", "state", "session-review"]);
+        assert!(documents("").is_empty());
+    }
+
+    #[test]
+    fn tells_rejected_pushes_apart() {
+        let rejected_push = anyhow::anyhow!(
+            "process exited with status: exit status: 1\nstderr:\n\
+             To ../remote.git\n ! [rejected]        refs/mob/state -> refs/mob/state (fetch first)\n\
+             error: failed to push some refs to '../remote.git'"
+        );
+        assert!(rejected(&rejected_push));
+        let unreachable = anyhow::anyhow!(
+            "process exited with status: exit status: 128\nstderr:\n\
+             fatal: unable to access 'https://example.com/repo.git/': Could not resolve host"
+        );
+        assert!(!rejected(&unreachable));
+    }
 }
@@ -0,0 +1,392 @@
+//! Staged changes that are only whitespace: trailing spaces, final newlines and byte order
+//! marks that editors add or take away as they are set up. Found by comparing `git diff
+//! --cached --numstat` with and without `-w`, and fixed where `git diff --cached --check`
+//! points, which only looks at the lines the turn added.
+
+use std::collections::BTreeMap;
+
+const BOM: &str = "\u{feff}";
+
+/// The files of `numstat` with changed lines that `ignoring`, the numstat of the same
+/// changes with `-w`, doesn't count. Binary files have no lines and are left out.
+pub fn only_whitespace(numstat: &str, ignoring: &str) -> Vec<String> {
+    let ignoring = counts(ignoring);
+    counts(numstat)
+        .into_iter()
+        .filter(|(path, changed)| {
+            *changed > 0
+                && !ignoring
+                    .iter()
+                    .any(|(other, changed)| other == path && *changed > 0)
+        })
+        .map(|(path, _)| path)
+        .collect()
+}
+
+/// The path of each text file of `numstat` and how many lines were added and deleted
+fn counts(numstat: &str) -> Vec<(String, usize)> {
+    numstat
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(3, '\t');
+            let added = fields.next()?.parse::<usize>().ok()?;
+            let deleted = fields.next()?.parse::<usize>().ok()?;
+            Some((fields.next()?.to_string(), added + deleted))
+        })
+        .collect()
+}
+
+/// What to strip from a file. Other problems `--check` finds, like spaces before tabs in
+/// the indent, are left as the mob wrote them.
+#[derive(Debug, Default, PartialEq)]
+pub struct Problems {
+    /// Added lines that end in whitespace, counted from 1
+    pub trailing: Vec<usize>,
+    /// The first of the blank lines added at the end
+    pub blank_from: Option<usize>,
+    /// The turn put a byte order mark before the first line
+    pub bom: bool,
+}
+
+/// The problems of each file `check`, the output of `git diff --cached --check`, lists
+/// with lines like `src/lib.rs:12: trailing whitespace.`
+pub fn problems(check: &str) -> BTreeMap<String, Problems> {
+    let mut problems: BTreeMap<String, Problems> = BTreeMap::new();
+    // The added line itself follows each problem
+    for line in check.lines().filter(|line| !line.starts_with('+')) {
+        let (path, number, problem) = match located(line) {
+            Some(located) => located,
+            None => continue,
+        };
+        match problem {
+            "trailing whitespace." => problems.entry(path).or_default().trailing.push(number),
+            "new blank line at EOF." => problems.entry(path).or_default().blank_from = Some(number),
+            _ => {}
+        }
+    }
+    problems
+}
+
+/// The path, line number and problem of a `path:line: problem` line. Paths can have colons,
+/// problems don't.
+fn located(line: &str) -> Option<(String, usize, &str)> {
+    let (place, problem) = line.rsplit_once(": ")?;
+    let (path, number) = place.rsplit_once(':')?;
+    Some((path.to_string(), number.parse().ok()?, problem))
+}
+
+/// `content` without what `problems` points out, every other line stays byte for byte. A
+/// line ending in `\r\n` keeps it, the `\r` ends the line rather than trailing it.
+pub fn strip(content: &[u8], problems: &Problems) -> Vec<u8> {
+    let content = match problems.bom {
+        true => content.strip_prefix(BOM.as_bytes()).unwrap_or(content),
+        false => content,
+    };
+    let lines: Vec<&[u8]> = content.split_inclusive(|byte| *byte == b'\n').collect();
+    // Blank lines are only at the end when no text follows them
+    let end = lines
+        .iter()
+        .rposition(|line| !line.iter().all(u8::is_ascii_whitespace))
+        .map_or(0, |at| at + 1);
+    let end = match problems.blank_from {
+        Some(from) => end.max(from.saturating_sub(1)),
+        None => lines.len(),
+    };
+    let mut stripped = Vec::with_capacity(content.len());
+    for (at, line) in lines.into_iter().enumerate().take(end) {
+        let number = at + 1;
+        if !problems.trailing.contains(&number) {
+            stripped.extend_from_slice(line);
+            continue;
+        }
+        let (text, ending) = ending(line);
+        let end = text
+            .iter()
+            .rposition(|byte| !matches!(byte, b' ' | b'\t'))
+            .map_or(0, |at| at + 1);
+        stripped.extend_from_slice(&text[..end]);
+        stripped.extend_from_slice(ending);
+    }
+    stripped
+}
+
+/// `line` and the `\n` or `\r\n` it ends with, the last line may end without
+fn ending(line: &[u8]) -> (&[u8], &[u8]) {
+    let length = match line {
+        [.., b'\r', b'\n'] => 2,
+        [.., b'\n'] => 1,
+        _ => 0,
+    };
+    line.split_at(line.len() - length)
+}
+
+/// The files of `diff`, staged without context lines, whose first line got a byte order
+/// mark it didn't have. New files keep theirs, some tools want them.
+pub fn bom_added(diff: &str) -> Vec<String> {
+    let mut found: Vec<String> = vec![];
+    let mut file: Option<String> = None;
+    // The first removed and added line of a hunk that changes the first line
+    let mut first: Option<(Option<&str>, Option<&str>)> = None;
+    // Added lines can look like headers too
+    let mut in_hunk = false;
+    for line in diff.lines() {
+        if line.starts_with("diff --git ") {
+            file = None;
+            first = None;
+            in_hunk = false;
+        } else if let Some(ranges) = line.strip_prefix("@@ ") {
+            in_hunk = true;
+            first = match at_top(ranges) {
+                true => Some((None, None)),
+                false => None,
+            };
+        } else if let Some(path) = line.strip_prefix("+++ ").filter(|_| !in_hunk) {
+            file = path.strip_prefix("b/").map(String::from);
+        } else if let Some((removed, added)) = first.as_mut() {
+            if let Some(line) = line.strip_prefix('-') {
+                removed.get_or_insert(line);
+            } else if let Some(line) = line.strip_prefix('+').filter(|_| added.is_none()) {
+                *added = Some(line);
+                let gained = line.starts_with(BOM)
+                    && matches!(removed, Some(removed) if !removed.starts_with(BOM));
+                match &file {
+                    Some(file) if gained && !found.contains(file) => found.push(file.clone()),
+                    _ => {}
+                }
+            }
+        }
+    }
+    found
+}
+
+/// Whether the hunk of `ranges`, like `-1,2 +1,3 @@`, replaces the first line
+fn at_top(ranges: &str) -> bool {
+    let mut ranges = ranges.split_whitespace();
+    let starts_at_one = |range: Option<&str>, sign: char| {
+        let range = match range.and_then(|range| range.strip_prefix(sign)) {
+            Some(range) => range,
+            None => return false,
+        };
+        let mut parts = range.splitn(2, ',');
+        let start = parts.next().and_then(|start| start.parse::<usize>().ok());
+        let count = parts
+            .next()
+            .map_or(Some(1), |count| count.parse::<usize>().ok());
+        start == Some(1) && matches!(count, Some(count) if count > 0)
+    };
+    starts_at_one(ranges.next(), '-') && starts_at_one(ranges.next(), '+')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Trailing spaces, a final newline, line endings, a real change with a trailing space
+    /// and a new file ending in blank lines, as `git diff --cached --numstat` counts them
+    const NUMSTAT: &str = "\
+1\t1\tbom.txt
+1\t1\tcrlf.txt
+3\t0\tnew.txt
+1\t1\tnofinal.txt
+2\t2\treal.txt
+2\t2\ttrail.txt
+-\t-\tlogo.png
+0\t0\tscript.sh
+";
+    /// The same with `-w`
+    const IGNORING: &str = "\
+1\t1\tbom.txt
+3\t0\tnew.txt
+1\t1\treal.txt
+-\t-\tlogo.png
+";
+    const CHECK: &str = "\
+crlf.txt:1: trailing whitespace.
++k\r
+new.txt:1: trailing whitespace.
++new
+new.txt:2: new blank line at EOF.
+real.txt:1: trailing whitespace.
++p
+src/weird: name.rs:3: trailing whitespace.
++let x = 1;\t
+trail.txt:1: trailing whitespace.
++x
+trail.txt:2: trailing whitespace.
++y\t
+docs/guide.md:7: space before tab in indent.
++ \tstep
+";
+
+    #[test]
+    fn finds_files_with_only_whitespace_changed() {
+        assert_eq!(
+            only_whitespace(NUMSTAT, IGNORING),
+            vec!["crlf.txt", "nofinal.txt", "trail.txt"]
+        );
+        // Nothing ignored when nothing is only whitespace
+        assert!(only_whitespace(IGNORING, IGNORING).is_empty());
+        assert!(only_whitespace("", "").is_empty());
+        // Mode changes, binary files and garbage count no lines
+        assert!(only_whitespace("0\t0\tscript.sh\n-\t-\tlogo.png\nwarning: x\n", "").is_empty());
+        // Paths with tabs stay whole
+        assert_eq!(
+            only_whitespace("1\t1\tname\twith tab.txt\n", ""),
+            vec!["name\twith tab.txt"]
+        );
+    }
+
+    #[test]
+    fn reads_where_the_problems_are() {
+        let problems = problems(CHECK);
+        assert_eq!(
+            problems.keys().collect::<Vec<_>>(),
+            vec![
+                "crlf.txt",
+                "new.txt",
+                "real.txt",
+                "src/weird: name.rs",
+                "trail.txt"
+            ]
+        );
+        assert_eq!(
+            problems["new.txt"],
+            Problems {
+                trailing: vec![1],
+                blank_from: Some(2),
+                bom: false,
+            }
+        );
+        assert_eq!(problems["trail.txt"].trailing, vec![1, 2]);
+        assert_eq!(problems["src/weird: name.rs"].trailing, vec![3]);
+        assert!(!problems.contains_key("docs/guide.md"));
+    }
+
+    fn trailing(lines: &[usize]) -> Problems {
+        Problems {
+            trailing: lines.to_vec(),
+            ..Problems::default()
+        }
+    }
+
+    #[test]
+    fn strips_only_the_lines_pointed_at() {
+        // Line 2 had its trailing space before the turn, it stays
+        let content = b"fn main() {  \n    run(); \n\tdone();\t \n}";
+        assert_eq!(
+            strip(content, &trailing(&[1, 3])),
+            b"fn main() {\n    run(); \n\tdone();\n}".to_vec()
+        );
+        // Lines of only whitespace become empty
+        assert_eq!(strip(b"a\n   \nb\n", &trailing(&[2])), b"a\n\nb\n".to_vec());
+        // The last line without a newline
+        assert_eq!(strip(b"a\nb  ", &trailing(&[2])), b"a\nb".to_vec());
+        // Lines that aren't there, as when the file changed since
+        assert_eq!(strip(b"a \n", &trailing(&[5])), b"a \n".to_vec());
+        assert_eq!(strip(b"", &trailing(&[1])), b"".to_vec());
+    }
+
+    #[test]
+    fn keeps_line_endings() {
+        assert_eq!(
+            strip(b"one \r\ntwo\r\nthree\t\r\n", &trailing(&[1, 2, 3])),
+            b"one\r\ntwo\r\nthree\r\n".to_vec()
+        );
+        // Not UTF-8, still text
+        assert_eq!(
+            strip(b"caf\xe9 \nna\xefve\n", &trailing(&[1])),
+            b"caf\xe9\nna\xefve\n".to_vec()
+        );
+    }
+
+    #[test]
+    fn drops_blank_lines_added_at_the_end() {
+        let problems = Problems {
+            trailing: vec![1],
+            blank_from: Some(3),
+            ..Problems::default()
+        };
+        assert_eq!(
+            strip(b"new \nline\n\n  \n\t\n", &problems),
+            b"new\nline\n".to_vec()
+        );
+        // A file of only blank lines ends up empty
+        let problems = Problems {
+            blank_from: Some(1),
+            ..Problems::default()
+        };
+        assert_eq!(strip(b"\n\n", &problems), b"".to_vec());
+        // A line with text after them isn't blank
+        assert_eq!(strip(b"a\n\nb\n", &problems), b"a\n\nb\n".to_vec());
+    }
+
+    #[test]
+    fn finds_byte_order_marks_the_turn_added() {
+        let diff = "\
+diff --git a/Program.cs b/Program.cs
+index 1111111..2222222 100644
+--- a/Program.cs
++++ b/Program.cs
+@@ -1 +1 @@
+-using System;
++\u{feff}using System;
+@@ -8,0 +9 @@ class Program
++    static int answer = 42;
+diff --git a/App.csproj b/App.csproj
+index 3333333..4444444 100644
+--- a/App.csproj
++++ b/App.csproj
+@@ -1,2 +1,2 @@
+-\u{feff}<Project>
+-<PropertyGroup>
++\u{feff}<Project Sdk=\"Microsoft.NET.Sdk\">
++<PropertyGroup>
+diff --git a/New.cs b/New.cs
+new file mode 100644
+index 0000000..5555555
+--- /dev/null
++++ b/New.cs
+@@ -0,0 +1,2 @@
++\u{feff}namespace App;
++class New {}
+diff --git a/notes.md b/notes.md
+index 6666666..7777777 100644
+--- a/notes.md
++++ b/notes.md
+@@ -3 +3 @@
+-first
++\u{feff}first
+@@ -1,0 +2 @@
++\u{feff}inserted after the first line
+";
+        assert_eq!(bom_added(diff), vec!["Program.cs"]);
+        assert!(bom_added("").is_empty());
+    }
+
+    #[test]
+    fn strips_the_byte_order_mark() {
+        let problems = Problems {
+            bom: true,
+            ..Problems::default()
+        };
+        assert_eq!(
+            strip("\u{feff}using System;\n".as_bytes(), &problems),
+            b"using System;\n".to_vec()
+        );
+        assert_eq!(strip(b"plain\n", &problems), b"plain\n".to_vec());
+        assert_eq!(
+            strip("\u{feff}x\n".as_bytes(), &Problems::default()),
+            "\u{feff}x\n".as_bytes().to_vec()
+        );
+    }
+
+    #[test]
+    fn tells_hunks_at_the_top() {
+        assert!(at_top("-1 +1 @@"));
+        assert!(at_top("-1,3 +1,2 @@ fn main() {"));
+        assert!(!at_top("-0,0 +1,2 @@"));
+        assert!(!at_top("-1,0 +2 @@"));
+        assert!(!at_top("-3 +3 @@"));
+        assert!(!at_top("garbage"));
+    }
+}
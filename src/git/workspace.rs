@@ -0,0 +1,620 @@
+//! One session over several repositories, listed in a `.mob-workspace.toml` at the root of
+//! the workspace. Git commands run in every member repository with the same branch names,
+//! the session is kept in the primary one. Steps a member already took are skipped, so a
+//! command that failed in one repository goes on where it stopped when run again.
+
+use super::{Git, Lfs, Snapshot};
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+
+pub const WORKSPACE_FILE: &str = ".mob-workspace.toml";
+
+/// The repositories of a workspace, as `.mob-workspace.toml` lists them:
+///
+/// ```toml
+/// repos = ["api", "web"]
+/// primary = "api"
+/// ```
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Manifest {
+    /// Paths of the repositories, relative to the root of the workspace
+    pub repos: Vec<String>,
+    /// The repository that keeps the session, the first one unless named
+    #[serde(default)]
+    pub primary: Option<String>,
+}
+
+impl Manifest {
+    pub fn exists(root: &Path) -> bool {
+        root.join(WORKSPACE_FILE).is_file()
+    }
+
+    pub fn load(root: &Path) -> Result<Manifest> {
+        let path = root.join(WORKSPACE_FILE);
+        let text = fs::read_to_string(&path)
+            .with_context(|| format!("Could not read {}", path.display()))?;
+        Manifest::parse(&text).with_context(|| format!("{} is not a workspace", path.display()))
+    }
+
+    fn parse(text: &str) -> Result<Manifest> {
+        let manifest: Manifest = toml::from_str(text)?;
+        if manifest.repos.is_empty() {
+            return Err(anyhow!("it lists no repos"));
+        }
+        for (index, repo) in manifest.repos.iter().enumerate() {
+            if manifest.repos[..index].contains(repo) {
+                return Err(anyhow!("it lists {} twice", repo));
+            }
+        }
+        match &manifest.primary {
+            Some(primary) if !manifest.repos.contains(primary) => Err(anyhow!(
+                "the primary repository {} is not one of its repos",
+                primary
+            )),
+            _ => Ok(manifest),
+        }
+    }
+
+    /// The repository that keeps the session
+    pub fn primary(&self) -> &str {
+        self.primary.as_deref().unwrap_or(&self.repos[0])
+    }
+
+    /// The other repositories, in the order they are listed
+    pub fn others(&self) -> impl Iterator<Item = &str> {
+        let primary = self.primary();
+        self.repos
+            .iter()
+            .map(String::as_str)
+            .filter(move |repo| *repo != primary)
+    }
+}
+
+/// The git facade of a workspace, runs commands in each member repository and answers for
+/// them together. Commit ids only name commits of one repository, questions about commits
+/// and commands that name one go to the primary.
+pub struct Workspace<'a> {
+    /// By name, the primary first
+    members: Vec<(String, &'a dyn Git)>,
+}
+
+impl<'a> Workspace<'a> {
+    pub fn new(primary: (&str, &'a dyn Git), others: Vec<(&str, &'a dyn Git)>) -> Self {
+        let members = std::iter::once(primary)
+            .chain(others)
+            .map(|(name, git)| (name.to_string(), git))
+            .collect();
+        Workspace { members }
+    }
+
+    fn primary(&self) -> &'a dyn Git {
+        self.members[0].1
+    }
+
+    /// The member named by the first segment of `path` and the rest of it
+    fn member_of<'p>(&self, path: &'p str) -> Option<(&'a dyn Git, &'p str)> {
+        self.members.iter().find_map(|(name, git)| {
+            let rest = path.strip_prefix(name.as_str())?.strip_prefix('/')?;
+            Some((*git, rest))
+        })
+    }
+
+    fn member_or_fail<'p>(&self, path: &'p str) -> Result<(&'a dyn Git, &'p str)> {
+        self.member_of(path)
+            .ok_or_else(|| anyhow!("{} is in none of the repositories of the workspace", path))
+    }
+
+    /// `args` in each member, as far as the member didn't take that step already
+    fn fan_out(&self, args: &[&str]) -> Result<()> {
+        let mut done = vec![];
+        let mut failed = vec![];
+        for (name, git) in &self.members {
+            match step(*git, args) {
+                Ok(()) => done.push(name.as_str()),
+                Err(err) => {
+                    log::warn!("git {} failed in {}: {:#}", args.join(" "), name, err);
+                    failed.push((name.as_str(), err));
+                }
+            }
+        }
+        let names: Vec<&str> = failed.iter().map(|(name, _)| *name).collect();
+        let err = match failed.into_iter().next() {
+            Some((_, err)) => err,
+            None => return Ok(()),
+        };
+        let command = args.first().copied().unwrap_or_default();
+        let context = match done.is_empty() {
+            true => format!("git {} failed in {}", command, names.join(", ")),
+            false => format!(
+                "git {} failed in {} and went through in {}, run the command again once that is fixed",
+                command,
+                names.join(", "),
+                done.join(", ")
+            ),
+        };
+        Err(err.context(context))
+    }
+
+    /// Files of `pathspecs` to each member they are in, `None` when one of them is outside
+    /// the members
+    fn route<'p>(&self, pathspecs: &[&'p str]) -> Option<Vec<(&'a dyn Git, Vec<&'p str>)>> {
+        let mut routes: Vec<(&'a dyn Git, Vec<&'p str>)> = vec![];
+        for path in pathspecs {
+            let (git, rest) = self.member_of(path)?;
+            match routes
+                .iter_mut()
+                .find(|(member, _)| std::ptr::eq(*member, git))
+            {
+                Some((_, paths)) => paths.push(rest),
+                None => routes.push((git, vec![rest])),
+            }
+        }
+        Some(routes)
+    }
+
+    /// The paths each member answers, named from the root of the workspace
+    fn paths(&self, answer: impl Fn(&dyn Git) -> Result<Vec<String>>) -> Result<Vec<String>> {
+        let mut paths = vec![];
+        for (name, git) in &self.members {
+            paths.extend(
+                answer(*git)?
+                    .into_iter()
+                    .map(|path| format!("{}/{}", name, path)),
+            );
+        }
+        Ok(paths)
+    }
+
+    /// The lines of what each member answers, with `rename` applied to each line
+    fn renamed(
+        &self,
+        answer: impl Fn(&dyn Git) -> Result<String>,
+        rename: impl Fn(&str, &str) -> String,
+    ) -> Result<String> {
+        let mut out = String::new();
+        for (name, git) in &self.members {
+            for line in answer(*git)?.lines() {
+                out.push_str(&rename(name, line));
+                out.push('\n');
+            }
+        }
+        Ok(out)
+    }
+}
+
+/// Takes the step of `args` in `git`, unless it was taken there before
+fn step(git: &dyn Git, args: &[&str]) -> Result<()> {
+    let named = args
+        .iter()
+        .position(|arg| *arg == "--")
+        .and_then(|dashes| args.get(dashes + 1))
+        .copied();
+    match (args, named) {
+        (["commit", ..], _) if git.snapshot()?.staged == 0 => {
+            log::debug!("Nothing staged, skipping git {}", args.join(" "));
+            Ok(())
+        }
+        (["push", rest @ ..], Some(branch)) if rest.contains(&"--delete") => {
+            let remote = rest.iter().find(|arg| !arg.starts_with('-'));
+            match remote {
+                Some(remote) if git.remote_tip(remote, branch)?.is_none() => {
+                    log::debug!("{} is deleted already", branch);
+                    Ok(())
+                }
+                _ => git.run(args),
+            }
+        }
+        (["branch", "-D", ..], Some(branch))
+        | (["merge", ..], Some(branch))
+        | (["push", ..], Some(branch))
+            if !git.has_branch(branch)? =>
+        {
+            log::debug!("No branch {}, skipping git {}", branch, args.join(" "));
+            Ok(())
+        }
+        (["checkout", "-b", branch, ..], _) if git.has_branch(branch)? => {
+            git.run(&["checkout", branch])
+        }
+        _ => git.run(args),
+    }
+}
+
+/// Whether `arg` looks like the id of a commit rather than the name of a branch
+fn commit_id(arg: &str) -> bool {
+    (7..=40).contains(&arg.len()) && arg.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// `line` of a diff with its paths in the repository `name`
+fn diff_line(name: &str, line: &str) -> String {
+    if let Some(paths) = line.strip_prefix("diff --git a/") {
+        return format!(
+            "diff --git a/{}/{}",
+            name,
+            paths.replacen(" b/", &format!(" b/{}/", name), 1)
+        );
+    }
+    for side in &["--- a/", "+++ b/"] {
+        if let Some(path) = line.strip_prefix(side) {
+            return format!("{}{}/{}", side, name, path);
+        }
+    }
+    line.to_string()
+}
+
+impl<'a> Git for Workspace<'a> {
+    fn run(&self, args: &[&str]) -> Result<()> {
+        let pathspecs: Vec<&str> = match args.iter().position(|arg| *arg == "--") {
+            Some(dashes) if matches!(args.first(), Some(&"add") | Some(&"reset")) => {
+                args[dashes + 1..].to_vec()
+            }
+            _ => vec![],
+        };
+        if !pathspecs.is_empty() {
+            if let Some(routes) = self.route(&pathspecs) {
+                let options = &args[..args.len() - pathspecs.len()];
+                for (git, paths) in routes {
+                    let routed: Vec<&str> = options.iter().chain(&paths).copied().collect();
+                    git.run(&routed)?;
+                }
+                return Ok(());
+            }
+        }
+        if args.iter().any(|arg| commit_id(arg)) || args.first() == Some(&"reset") {
+            return self.primary().run(args);
+        }
+        self.fan_out(args)
+    }
+
+    fn snapshot(&self) -> Result<Snapshot> {
+        let mut snapshot = self.primary().snapshot()?;
+        for (_, git) in &self.members[1..] {
+            let member = git.snapshot()?;
+            snapshot.ahead += member.ahead;
+            snapshot.behind += member.behind;
+            snapshot.staged += member.staged;
+            snapshot.unstaged += member.unstaged;
+            snapshot.changed += member.changed;
+            snapshot.untracked += member.untracked;
+            snapshot.conflicted += member.conflicted;
+        }
+        Ok(snapshot)
+    }
+
+    fn has_branch(&self, branch: &str) -> Result<bool> {
+        for (_, git) in &self.members {
+            if git.has_branch(branch)? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    fn branches(&self) -> Result<Vec<String>> {
+        let mut branches = vec![];
+        for (_, git) in &self.members {
+            branches.extend(git.branches()?);
+        }
+        branches.sort();
+        branches.dedup();
+        Ok(branches)
+    }
+
+    fn remotes(&self) -> Result<Vec<String>> {
+        self.primary().remotes()
+    }
+
+    fn remote_url(&self, remote: &str) -> Result<Option<String>> {
+        self.primary().remote_url(remote)
+    }
+
+    fn root_commit(&self) -> Result<Option<String>> {
+        self.primary().root_commit()
+    }
+
+    fn rev_parse(&self, rev: &str) -> Result<Option<String>> {
+        self.primary().rev_parse(rev)
+    }
+
+    fn remote_tip(&self, remote: &str, branch: &str) -> Result<Option<String>> {
+        self.primary().remote_tip(remote, branch)
+    }
+
+    fn commit_tree_id(&self, commit: &str) -> Result<String> {
+        self.primary().commit_tree_id(commit)
+    }
+
+    fn commit_parents(&self, commit: &str) -> Result<Vec<String>> {
+        self.primary().commit_parents(commit)
+    }
+
+    fn merge_base(&self, one: &str, other: &str) -> Result<Option<String>> {
+        self.primary().merge_base(one, other)
+    }
+
+    fn lfs(&self) -> Result<Lfs> {
+        self.primary().lfs()
+    }
+
+    fn lfs_unconverted(&self) -> Result<Vec<String>> {
+        self.paths(|git| git.lfs_unconverted())
+    }
+
+    fn conflicted(&self) -> Result<Vec<String>> {
+        self.paths(|git| git.conflicted())
+    }
+
+    fn staged_diff(&self) -> Result<String> {
+        self.renamed(|git| git.staged_diff(), diff_line)
+    }
+
+    fn staged_numstat(&self, ignore_whitespace: bool) -> Result<String> {
+        self.renamed(
+            |git| git.staged_numstat(ignore_whitespace),
+            |name, line| match line.rsplitn(2, '\t').collect::<Vec<_>>()[..] {
+                [path, counts] => format!("{}\t{}/{}", counts, name, path),
+                _ => line.to_string(),
+            },
+        )
+    }
+
+    fn staged_check(&self) -> Result<String> {
+        // The lines with a problem start with the path, the ones with the content with a +
+        self.renamed(
+            |git| git.staged_check(),
+            |name, line| match line.starts_with('+') {
+                true => line.to_string(),
+                false => format!("{}/{}", name, line),
+            },
+        )
+    }
+
+    fn read_file(&self, path: &str) -> Result<Vec<u8>> {
+        let (git, path) = self.member_or_fail(path)?;
+        git.read_file(path)
+    }
+
+    fn write_file(&self, path: &str, data: &[u8]) -> Result<()> {
+        let (git, path) = self.member_or_fail(path)?;
+        git.write_file(path, data)
+    }
+
+    fn log_oneline(&self, range: &str) -> Result<Vec<String>> {
+        if range.split("..").any(commit_id) {
+            return self.primary().log_oneline(range);
+        }
+        let mut lines = vec![];
+        for (name, git) in &self.members {
+            lines.extend(
+                git.log_oneline(range)?
+                    .into_iter()
+                    .map(|line| format!("{}: {}", name, line)),
+            );
+        }
+        Ok(lines)
+    }
+
+    fn log_authors(&self, range: &str) -> Result<Vec<String>> {
+        if range.split("..").any(commit_id) {
+            return self.primary().log_authors(range);
+        }
+        let mut authors: Vec<String> = vec![];
+        for (_, git) in &self.members {
+            for author in git.log_authors(range)? {
+                if !authors.contains(&author) {
+                    authors.push(author);
+                }
+            }
+        }
+        Ok(authors)
+    }
+
+    fn diffstat(&self, range: &str) -> Result<String> {
+        if range.split("..").any(commit_id) {
+            return self.primary().diffstat(range);
+        }
+        self.renamed(
+            |git| git.diffstat(range),
+            |name, line| format!("{}: {}", name, line),
+        )
+    }
+
+    fn file_sizes(&self, commit: &str) -> Result<Vec<(String, u64)>> {
+        self.primary().file_sizes(commit)
+    }
+
+    fn is_repository(&self) -> bool {
+        self.primary().is_repository()
+    }
+
+    fn check_branch_name(&self, name: &str) -> Result<()> {
+        self.primary().check_branch_name(name)
+    }
+
+    fn members(&self) -> Vec<(&str, &dyn Git)> {
+        self.members
+            .iter()
+            .map(|(name, git)| (name.as_str(), *git))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::fake::FakeGit;
+
+    fn on(branches: &[&str]) -> FakeGit {
+        FakeGit::default().with_branches(branches)
+    }
+
+    #[test]
+    fn reads_the_manifest() {
+        let manifest = Manifest::parse("repos = [\"api\", \"web\"]").unwrap();
+        assert_eq!(manifest.primary(), "api");
+        assert_eq!(manifest.others().collect::<Vec<_>>(), ["web"]);
+        let manifest = Manifest::parse("repos = [\"api\", \"web\"]\nprimary = \"web\"").unwrap();
+        assert_eq!(manifest.primary(), "web");
+        assert_eq!(manifest.others().collect::<Vec<_>>(), ["api"]);
+
+        for (text, problem) in &[
+            ("repos = []", "it lists no repos"),
+            ("repos = [\"api\", \"api\"]", "it lists api twice"),
+            (
+                "repos = [\"api\"]\nprimary = \"web\"",
+                "the primary repository web is not one of its repos",
+            ),
+        ] {
+            assert_eq!(Manifest::parse(text).unwrap_err().to_string(), *problem);
+        }
+        assert!(Manifest::parse("repo = [\"api\"]").is_err());
+    }
+
+    #[test]
+    fn runs_in_every_repository() {
+        let (api, web) = (on(&[]), on(&[]));
+        let workspace = Workspace::new(("api", &api), vec![("web", &web)]);
+        workspace.run(&["fetch", "--all", "--prune"]).unwrap();
+        workspace.run(&["checkout", "mob-session"]).unwrap();
+        for git in &[&api, &web] {
+            assert_eq!(git.calls(), ["fetch --all --prune", "checkout mob-session"]);
+        }
+    }
+
+    #[test]
+    fn tells_where_a_step_failed() {
+        let (api, web) = (on(&["mob-session"]), on(&["mob-session"]));
+        web.fail("push", 0, "fatal: unable to access");
+        let workspace = Workspace::new(("api", &api), vec![("web", &web)]);
+        let push = ["push", "--no-verify", "origin", "--", "mob-session"];
+        let err = workspace.run(&push).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "git push failed in web and went through in api, run the command again once that is fixed"
+        );
+        assert!(format!("{:#}", err).contains("fatal: unable to access"));
+
+        // Run again, pushing is done again where it went through already
+        workspace.run(&push).unwrap();
+        assert_eq!(api.calls().len(), 2);
+        assert_eq!(web.calls().len(), 2);
+
+        api.fail("fetch", 0, "fatal: api is gone");
+        web.fail("fetch", 0, "fatal: web is gone");
+        let err = workspace.run(&["fetch", "--all"]).unwrap_err();
+        assert_eq!(err.to_string(), "git fetch failed in api, web");
+    }
+
+    #[test]
+    fn skips_steps_taken_already() {
+        // api went through mob done before web failed
+        let api = on(&["main", "origin/main"]);
+        let mut web = on(&["main", "origin/main", "mob-session"]);
+        web.snapshot.staged = 2;
+        web.remote_tips.insert("mob-session".into(), "b2".into());
+        let workspace = Workspace::new(("api", &api), vec![("web", &web)]);
+        for args in &[
+            &["merge", "--squash", "--ff", "--", "mob-session"][..],
+            &["commit", "--message", "Add login"],
+            &["branch", "-D", "--", "mob-session"],
+            &[
+                "push",
+                "--no-verify",
+                "origin",
+                "--delete",
+                "--",
+                "mob-session",
+            ],
+            &["push", "--no-verify", "origin", "--", "main"],
+        ] {
+            workspace.run(args).unwrap();
+        }
+        assert_eq!(api.calls(), ["push --no-verify origin -- main"]);
+        assert_eq!(web.calls().len(), 5);
+
+        // A branch created before is checked out
+        workspace.run(&["checkout", "-b", "mob-session"]).unwrap();
+        assert_eq!(api.calls().last().unwrap(), "checkout -b mob-session");
+        assert_eq!(web.calls().last().unwrap(), "checkout mob-session");
+    }
+
+    #[test]
+    fn names_commits_of_the_primary_only() {
+        let (api, web) = (on(&[]), on(&[]));
+        let workspace = Workspace::new(("api", &api), vec![("web", &web)]);
+        workspace.run(&["reset", "--keep", "1a2b3c4d"]).unwrap();
+        workspace.run(&["reset", "--soft", "HEAD~1"]).unwrap();
+        workspace
+            .log_oneline("c0ffee1..origin/mob-session")
+            .unwrap();
+        assert_eq!(
+            api.calls(),
+            ["reset --keep 1a2b3c4d", "reset --soft HEAD~1"]
+        );
+        assert!(web.calls().is_empty());
+    }
+
+    #[test]
+    fn names_files_from_the_workspace_root() {
+        let mut api = on(&[]);
+        api.conflicted = vec!["src/lib.rs".into()];
+        api.numstat = "1\t0\tsrc/lib.rs\n".into();
+        let mut web = on(&[]);
+        web.numstat = "0\t0\tindex.html\n".into();
+        web.whitespace_check = "index.html:3: trailing whitespace.\n+<p> \n".into();
+        web.staged =
+            "diff --git a/index.html b/index.html\n--- a/index.html\n+++ b/index.html\n".into();
+        web.worktree
+            .borrow_mut()
+            .insert("index.html".into(), b"<p>".to_vec());
+        let workspace = Workspace::new(("api", &api), vec![("web", &web)]);
+
+        assert_eq!(workspace.conflicted().unwrap(), ["api/src/lib.rs"]);
+        assert_eq!(
+            workspace.staged_numstat(false).unwrap(),
+            "1\t0\tapi/src/lib.rs\n0\t0\tweb/index.html\n"
+        );
+        assert_eq!(
+            workspace.staged_check().unwrap(),
+            "web/index.html:3: trailing whitespace.\n+<p> \n"
+        );
+        assert_eq!(
+            workspace.staged_diff().unwrap(),
+            "diff --git a/web/index.html b/web/index.html\n\
+             --- a/web/index.html\n+++ b/web/index.html\n"
+        );
+        assert_eq!(workspace.read_file("web/index.html").unwrap(), b"<p>");
+        assert!(workspace.read_file("docs/index.html").is_err());
+
+        workspace
+            .run(&["add", "--", "web/index.html", "api/src/lib.rs"])
+            .unwrap();
+        assert_eq!(web.calls(), ["add -- index.html"]);
+        assert_eq!(api.calls(), ["add -- src/lib.rs"]);
+    }
+
+    #[test]
+    fn adds_up_the_working_trees() {
+        let mut api = on(&["mob-session"]);
+        api.snapshot.branch = Some("mob-session".into());
+        api.snapshot.untracked = 1;
+        let mut web = on(&[]);
+        web.snapshot.changed = 2;
+        web.snapshot.unstaged = 2;
+        let workspace = Workspace::new(("api", &api), vec![("web", &web)]);
+        let snapshot = workspace.snapshot().unwrap();
+        assert!(snapshot.is_on("mob-session"));
+        assert_eq!(snapshot.files(), 3);
+        assert!(workspace.has_branch("mob-session").unwrap());
+        assert_eq!(
+            workspace
+                .members()
+                .iter()
+                .map(|(name, _)| *name)
+                .collect::<Vec<_>>(),
+            ["api", "web"]
+        );
+    }
+}
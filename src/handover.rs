@@ -0,0 +1,224 @@
+//! When to nag about a handover that is taking long and how long the next driver keeps
+//! their turn, kept free of clocks and processes.
+
+use crate::config::HandoverConfig;
+use crate::duration;
+use crate::session::{Session, State};
+use crate::timekeeping::Instant;
+use chrono::{DateTime, Duration, Utc};
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Target {
+    /// This user is awaited, or anyone is
+    Me,
+    /// Someone else is awaited, tell the channel
+    Channel(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Decision {
+    Nag(Target),
+    WaitUntil(DateTime<Utc>),
+    /// Someone started, or we nagged enough
+    Stop,
+}
+
+/// The turn kept for the awaited driver while `handover.reassign_after` hasn't passed
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reservation {
+    pub driver: String,
+    pub left: Duration,
+}
+
+impl fmt::Display for Reservation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "reserved for {} ({} left)",
+            self.driver,
+            duration::format(self.left).short()
+        )
+    }
+}
+
+/// Who the turn is kept for at `now`, `None` when anyone may start. It opens up once
+/// `reassign_after` minutes passed since `mob next`, without it the turn is never reserved.
+pub fn reservation(
+    now: DateTime<Utc>,
+    session: &Session,
+    config: &HandoverConfig,
+) -> Option<Reservation> {
+    let driver = match &session.state {
+        State::WaitingForNext { next: Some(next) } => next,
+        _ => return None,
+    };
+    let opens = session.times.waiting_since? + config.reassign_after?.duration();
+    Some(Reservation {
+        driver: driver.clone(),
+        left: opens - Instant::from(now),
+    })
+    .filter(|reservation| reservation.left > Duration::zero())
+}
+
+/// What to do at `now` after `sent` nags for the session's current wait
+pub fn decide(
+    now: DateTime<Utc>,
+    session: &Session,
+    me: &str,
+    config: &HandoverConfig,
+    sent: u32,
+) -> Decision {
+    let next = match &session.state {
+        State::WaitingForNext { next } => next,
+        _ => return Decision::Stop,
+    };
+    let since = match session.times.waiting_since {
+        Some(since) => since,
+        None => return Decision::Stop,
+    };
+    if sent >= config.max_nags {
+        return Decision::Stop;
+    }
+
+    let due = since + Duration::minutes(config.nag_after.get().max(1) * (i64::from(sent) + 1));
+    if now < due.utc() {
+        return Decision::WaitUntil(due.utc());
+    }
+    Decision::Nag(match next {
+        Some(next) if next != me => Target::Channel(next.clone()),
+        _ => Target::Me,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::Times;
+    use crate::timekeeping::Minutes;
+
+    fn waiting(next: Option<&str>, since: DateTime<Utc>) -> Session {
+        Session {
+            state: State::WaitingForNext {
+                next: next.map(String::from),
+            },
+            times: Times {
+                waiting_since: Some(since.into()),
+                ..Times::default()
+            },
+            ..Session::default()
+        }
+    }
+
+    fn config() -> HandoverConfig {
+        HandoverConfig {
+            nag_after: Minutes(2),
+            max_nags: 3,
+            channel_command: None,
+            reassign_after: None,
+            ask_message: true,
+        }
+    }
+
+    #[test]
+    fn schedule() {
+        let since = Utc::now();
+        let session = waiting(Some("bob"), since);
+        let at = |minutes| since + Duration::minutes(minutes);
+
+        assert_eq!(
+            decide(at(1), &session, "bob", &config(), 0),
+            Decision::WaitUntil(at(2))
+        );
+        assert_eq!(
+            decide(at(2), &session, "bob", &config(), 0),
+            Decision::Nag(Target::Me)
+        );
+        assert_eq!(
+            decide(at(3), &session, "bob", &config(), 1),
+            Decision::WaitUntil(at(4))
+        );
+        assert_eq!(
+            decide(at(30), &session, "alice", &config(), 2),
+            Decision::Nag(Target::Channel("bob".into()))
+        );
+        assert_eq!(
+            decide(at(30), &session, "bob", &config(), 3),
+            Decision::Stop
+        );
+    }
+
+    #[test]
+    fn anyone_nags_locally() {
+        let since = Utc::now();
+        let session = waiting(None, since);
+        assert_eq!(
+            decide(
+                since + Duration::minutes(5),
+                &session,
+                "alice",
+                &config(),
+                0
+            ),
+            Decision::Nag(Target::Me)
+        );
+    }
+
+    #[test]
+    fn a_driver_named_anyone_is_someone() {
+        let since = Utc::now();
+        let session = waiting(Some("anyone"), since);
+        let later = since + Duration::minutes(5);
+        assert_eq!(
+            decide(later, &session, "alice", &config(), 0),
+            Decision::Nag(Target::Channel("anyone".into()))
+        );
+        assert_eq!(
+            decide(later, &session, "anyone", &config(), 0),
+            Decision::Nag(Target::Me)
+        );
+    }
+
+    #[test]
+    fn suppressed_once_started() {
+        let since = Utc::now();
+        let mut session = waiting(Some("bob"), since);
+        session.state = State::Working {
+            driver: "bob".into(),
+        };
+        let late = since + Duration::minutes(10);
+        assert_eq!(decide(late, &session, "bob", &config(), 0), Decision::Stop);
+
+        let mut unknown = waiting(Some("bob"), since);
+        unknown.times.waiting_since = None;
+        assert_eq!(decide(late, &unknown, "bob", &config(), 0), Decision::Stop);
+    }
+
+    #[test]
+    fn reserved_until_reassign_after() {
+        let since = Utc::now();
+        let session = waiting(Some("bob"), since);
+        let reassigning = HandoverConfig {
+            reassign_after: Some(Minutes(2)),
+            ..config()
+        };
+        let at = |seconds| since + Duration::seconds(seconds);
+
+        let reserved = reservation(at(40), &session, &reassigning).unwrap();
+        assert_eq!(reserved.driver, "bob");
+        assert_eq!(reserved.to_string(), "reserved for bob (1m 20s left)");
+        assert!(reservation(at(119), &session, &reassigning).is_some());
+        assert_eq!(reservation(at(120), &session, &reassigning), None);
+        assert_eq!(reservation(at(600), &session, &reassigning), None);
+
+        // Never reserved without the setting, or when anyone may start
+        assert_eq!(reservation(at(40), &session, &config()), None);
+        assert_eq!(
+            reservation(at(40), &waiting(None, since), &reassigning),
+            None
+        );
+        let mut unknown = waiting(Some("bob"), since);
+        unknown.times.waiting_since = None;
+        assert_eq!(reservation(at(40), &unknown, &reassigning), None);
+    }
+}
@@ -1,9 +1,66 @@
+//! `mob` as a library. What code using it can rely on is in [`prelude`], and with the
+//! `async` feature in `nonblocking`. The other public modules serve the `mob` binary and
+//! its tests, they are hidden from the docs and may change with any release.
+
+#[doc(hidden)]
+pub mod alias;
+pub(crate) mod broadcast;
+#[doc(hidden)]
+pub mod ci;
+#[doc(hidden)]
+pub mod clock;
+#[doc(hidden)]
 pub mod cmd;
 mod command;
+#[doc(hidden)]
+pub mod completions;
+#[doc(hidden)]
 pub mod config;
-pub mod duration;
+pub(crate) mod dashboard;
+#[doc(hidden)]
+pub mod deadline;
+pub(crate) mod duration;
+#[doc(hidden)]
 pub mod emoji_logger;
+pub(crate) mod env;
+pub(crate) mod eta;
+#[doc(hidden)]
+pub mod events;
+#[doc(hidden)]
+pub mod explain;
+pub(crate) mod follow;
+#[doc(hidden)]
 pub mod git;
+pub(crate) mod handover;
+#[doc(hidden)]
+pub mod lock;
+pub(crate) mod metrics;
+#[cfg(feature = "async")]
+pub mod nonblocking;
 mod os;
+#[doc(hidden)]
+pub mod palette;
+pub mod prelude;
+pub(crate) mod preset;
+#[doc(hidden)]
+pub mod prompt;
+#[doc(hidden)]
+pub mod record;
+pub(crate) mod reorder;
+pub(crate) mod schedule;
+#[doc(hidden)]
 pub mod session;
+#[doc(hidden)]
+pub mod state_dir;
+pub(crate) mod stats;
+pub(crate) mod suggest;
+pub(crate) mod table;
+pub(crate) mod timekeeping;
+#[doc(hidden)]
 pub mod timer;
+pub(crate) mod timezone;
+pub(crate) mod until;
+#[doc(hidden)]
+pub mod update;
+#[doc(hidden)]
+pub mod warnings;
@@ -0,0 +1,334 @@
+//! Keeps two mob commands that change the session from interleaving in one repository, like
+//! `mob next` in one terminal while `mob start` runs in another. Commands that only read
+//! go without.
+//!
+//! The lock is a file created only if it doesn't exist, which is atomic on every platform,
+//! unlike `flock` and `LockFileEx` whose semantics differ between Unix and Windows. Waiters
+//! breaking the lock of a process that ended take turns through a second such file, so one
+//! can't remove the lock another one just took.
+
+use crate::os;
+use crate::state_dir::StateDir;
+use chrono::{DateTime, Local, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+pub const LOCK_FILE: &str = "lock.json";
+/// Held while a stale lock is broken
+const BREAKING_FILE: &str = "lock.json.breaking";
+/// How long a command waits for another one to finish
+pub const TIMEOUT: Duration = Duration::from_secs(10);
+const POLL: Duration = Duration::from_millis(50);
+/// How long a lock may stay unreadable while its holder writes it, after that the holder
+/// ended before it wrote who it is
+const GRACE: Duration = Duration::from_secs(1);
+
+/// Who holds the lock, written into it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Holder {
+    pub pid: u32,
+    pub since: DateTime<Utc>,
+}
+
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+pub enum Error {
+    #[error(
+        "Another mob command is running in this repository, held by process {} since {}. \
+        Try again when it is done.",
+        .0.pid,
+        .0.since.with_timezone(&Local).format("%H:%M:%S")
+    )]
+    Held(Holder),
+
+    #[error("Could not lock {path}")]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+}
+
+/// Holds the lock until dropped, which happens on a panic too
+#[derive(Debug)]
+pub struct Lock {
+    path: PathBuf,
+}
+
+impl Drop for Lock {
+    fn drop(&mut self) {
+        if let Err(err) = fs::remove_file(&self.path) {
+            log::debug!("Could not unlock {}: {}", self.path.display(), err);
+        }
+    }
+}
+
+/// Locks `state_dir` for this process, waiting up to `timeout` for another holder. A lock
+/// whose process `alive` tells has ended is broken right away.
+pub fn acquire(
+    state_dir: &StateDir,
+    timeout: Duration,
+    alive: &dyn Fn(u32) -> bool,
+) -> Result<Lock, Error> {
+    let path = state_dir.file(LOCK_FILE);
+    let io_error = |source| Error::Io {
+        path: path.clone(),
+        source,
+    };
+    fs::create_dir_all(state_dir.path()).map_err(io_error)?;
+    let deadline = Instant::now() + timeout;
+    loop {
+        match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(mut file) => {
+                let holder = Holder {
+                    pid: std::process::id(),
+                    since: Utc::now(),
+                };
+                // The lock is held from here on, removed when writing fails
+                let lock = Lock { path: path.clone() };
+                let json = serde_json::to_vec(&holder).map_err(io::Error::from);
+                json.and_then(|json| file.write_all(&json))
+                    .map_err(io_error)?;
+                return Ok(lock);
+            }
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {}
+            Err(err) => return Err(io_error(err)),
+        }
+
+        // Unreadable while the holder is still writing it
+        let holder: Option<Holder> = state_dir.read(LOCK_FILE);
+        let stale = match &holder {
+            Some(holder) => holder.pid != std::process::id() && !alive(holder.pid),
+            None => older_than(&path, GRACE),
+        };
+        if stale && break_stale(state_dir, holder.as_ref()).map_err(io_error)? {
+            continue;
+        }
+        if Instant::now() >= deadline {
+            return Err(Error::Held(holder.unwrap_or(Holder {
+                pid: 0,
+                since: Utc::now(),
+            })));
+        }
+        thread::sleep(POLL);
+    }
+}
+
+/// Removes the lock when it still is the `stale` one, `None` for a lock that stayed
+/// unreadable. True when it was removed, false when another waiter is breaking it.
+fn break_stale(state_dir: &StateDir, stale: Option<&Holder>) -> io::Result<bool> {
+    let breaking = state_dir.file(BREAKING_FILE);
+    match OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&breaking)
+    {
+        Ok(_) => {}
+        Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {
+            // Left behind by a waiter that ended while breaking
+            if older_than(&breaking, GRACE) {
+                remove(&breaking)?;
+            }
+            return Ok(false);
+        }
+        Err(err) => return Err(err),
+    }
+    let path = state_dir.file(LOCK_FILE);
+    let current: Option<Holder> = state_dir.read(LOCK_FILE);
+    let still = current.as_ref() == stale && (stale.is_some() || older_than(&path, GRACE));
+    let broken = match still {
+        true => {
+            match stale {
+                Some(holder) => {
+                    log::debug!("Breaking the lock of process {} that ended", holder.pid)
+                }
+                None => log::debug!("Breaking a lock that nobody wrote who holds it into"),
+            }
+            remove(&path).map(|_| true)
+        }
+        false => Ok(false),
+    };
+    remove(&breaking)?;
+    broken
+}
+
+fn remove(path: &Path) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+        _ => Ok(()),
+    }
+}
+
+/// Whether the file at `path` was last written `age` ago or longer
+fn older_than(path: &Path, age: Duration) -> bool {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .map(|modified| modified.elapsed().unwrap_or_default() >= age)
+        .unwrap_or(false)
+}
+
+/// Whether process `pid` still runs on this machine
+pub fn alive(pid: u32) -> bool {
+    os::alive(pid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{mpsc, Arc, Barrier};
+
+    fn state_dir() -> (tempfile::TempDir, StateDir) {
+        let dir = tempfile::tempdir().unwrap();
+        let state_dir = StateDir::new(dir.path().join("mob"));
+        (dir, state_dir)
+    }
+
+    #[test]
+    fn one_holder_at_a_time() {
+        let (_dir, state_dir) = state_dir();
+        let barrier = Arc::new(Barrier::new(2));
+        let (sender, receiver) = mpsc::channel();
+        let handles: Vec<_> = (0..2)
+            .map(|thread| {
+                let (state_dir, barrier, sender) =
+                    (state_dir.clone(), barrier.clone(), sender.clone());
+                thread::spawn(move || {
+                    barrier.wait();
+                    let _lock = acquire(&state_dir, Duration::from_secs(5), &|_| true).unwrap();
+                    sender.send((thread, "locked")).unwrap();
+                    thread::sleep(Duration::from_millis(100));
+                    sender.send((thread, "unlocked")).unwrap();
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        drop(sender);
+        let events: Vec<_> = receiver.iter().collect();
+        // Each holder unlocks before the other one locks
+        assert_eq!(events.len(), 4);
+        assert_eq!(events[0].0, events[1].0);
+        assert_eq!(events[1].1, "unlocked");
+        assert_eq!(events[2].0, events[3].0);
+        assert!(!state_dir.file(LOCK_FILE).exists());
+    }
+
+    #[test]
+    fn tells_who_holds_it() {
+        let (_dir, state_dir) = state_dir();
+        let holder = thread::spawn({
+            let state_dir = state_dir.clone();
+            move || acquire(&state_dir, Duration::ZERO, &|_| true).unwrap()
+        })
+        .join()
+        .unwrap();
+        let err = acquire(&state_dir, Duration::from_millis(120), &|_| true).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains(&format!("held by process {} since", std::process::id())),
+            "{}",
+            err
+        );
+        drop(holder);
+        acquire(&state_dir, Duration::ZERO, &|_| true).unwrap();
+    }
+
+    #[test]
+    fn breaks_stale_locks() {
+        let (_dir, state_dir) = state_dir();
+        state_dir
+            .write(
+                LOCK_FILE,
+                &Holder {
+                    pid: 4_000_000,
+                    since: Utc::now(),
+                },
+            )
+            .unwrap();
+        let err = acquire(&state_dir, Duration::ZERO, &|_| true).unwrap_err();
+        assert!(
+            err.to_string().contains("held by process 4000000"),
+            "{}",
+            err
+        );
+        let lock = acquire(&state_dir, Duration::ZERO, &|pid| pid != 4_000_000).unwrap();
+        let holder: Holder = state_dir.read(LOCK_FILE).unwrap();
+        assert_eq!(holder.pid, std::process::id());
+        drop(lock);
+        assert!(!state_dir.file(LOCK_FILE).exists());
+    }
+
+    #[test]
+    fn one_waiter_breaks_a_stale_lock() {
+        let (_dir, state_dir) = state_dir();
+        state_dir
+            .write(
+                LOCK_FILE,
+                &Holder {
+                    pid: 4_000_000,
+                    since: Utc::now(),
+                },
+            )
+            .unwrap();
+        let barrier = Arc::new(Barrier::new(4));
+        let (sender, receiver) = mpsc::channel();
+        let handles: Vec<_> = (0..4)
+            .map(|thread| {
+                let (state_dir, barrier, sender) =
+                    (state_dir.clone(), barrier.clone(), sender.clone());
+                thread::spawn(move || {
+                    barrier.wait();
+                    let _lock =
+                        acquire(&state_dir, Duration::from_secs(5), &|pid| pid != 4_000_000)
+                            .unwrap();
+                    sender.send((thread, "locked")).unwrap();
+                    thread::sleep(Duration::from_millis(50));
+                    sender.send((thread, "unlocked")).unwrap();
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        drop(sender);
+        let events: Vec<_> = receiver.iter().collect();
+        assert_eq!(events.len(), 8);
+        for pair in events.chunks(2) {
+            assert_eq!(pair[0].0, pair[1].0, "{:?}", events);
+            assert_eq!((pair[0].1, pair[1].1), ("locked", "unlocked"));
+        }
+        assert!(!state_dir.file(LOCK_FILE).exists());
+        assert!(!state_dir.file(BREAKING_FILE).exists());
+    }
+
+    #[test]
+    fn breaks_locks_nobody_wrote_into() {
+        let (_dir, state_dir) = state_dir();
+        fs::create_dir_all(state_dir.path()).unwrap();
+        fs::write(state_dir.file(LOCK_FILE), b"").unwrap();
+        let err = acquire(&state_dir, Duration::ZERO, &|_| true).unwrap_err();
+        assert!(err.to_string().contains("held by process 0"), "{}", err);
+
+        acquire(&state_dir, GRACE * 3, &|_| true).unwrap();
+    }
+
+    #[test]
+    fn released_on_panic() {
+        let (_dir, state_dir) = state_dir();
+        let result = thread::spawn({
+            let state_dir = state_dir.clone();
+            move || {
+                let _lock = acquire(&state_dir, Duration::ZERO, &|_| true).unwrap();
+                panic!("while holding the lock");
+            }
+        })
+        .join();
+        assert!(result.is_err());
+        assert!(!state_dir.file(LOCK_FILE).exists());
+    }
+}
@@ -1,10 +1,68 @@
-use anyhow::Result;
-use clap::Clap;
-use remotemob::{cmd, config, emoji_logger, git, session, session::Store, timer};
+use anyhow::{anyhow, Context, Result};
+use clap::{ArgSettings, Clap, IntoApp};
+use remotemob::clock::{self, Clock as _};
+use remotemob::git::replay::{RecordingGit, ReplayGit};
+use remotemob::session::Store as _;
+use remotemob::{
+    alias, ci, cmd, config, deadline, emoji_logger, events, explain, git, palette, prompt, record,
+    session, state_dir::StateDir, timer, update, warnings,
+};
+use std::env;
+use std::path::PathBuf;
 
 #[derive(Clap)]
 #[clap(version = clap::crate_version!(), author = clap::crate_authors!())]
 struct Opts {
+    /// Directory of a local session without git, see `mob start --local`
+    #[clap(long, global = true)]
+    dir: Option<PathBuf>,
+
+    /// Print a JSON report with warnings for scripts
+    #[clap(long, global = true)]
+    json: bool,
+
+    /// Answer every question with its default instead of asking, as when there is no
+    /// terminal. mob done merges and mob clean cleans without confirming
+    #[clap(long, global = true)]
+    yes: bool,
+
+    /// Give up after this many seconds without asking anything, also from MOB_TIMEOUT
+    #[clap(long, global = true, value_name = "SECS")]
+    timeout: Option<u64>,
+
+    /// Append what the command did to FILE as a line of JSON, for bug reports
+    #[clap(long, global = true, value_name = "FILE")]
+    record: Option<PathBuf>,
+
+    /// Skip fetches and refuse what would push, show the session as fetched last. Turns are
+    /// committed here and wait for mob sync
+    #[clap(long, global = true)]
+    offline: bool,
+
+    /// Sign the commits of the command, like commit.sign
+    #[clap(long, global = true, conflicts_with = "no-sign")]
+    sign: bool,
+
+    /// Don't sign the commits of the command, whatever commit.sign says
+    #[clap(long, global = true)]
+    no_sign: bool,
+
+    /// Print how the command fits into a mob session after it ran
+    #[clap(long, global = true)]
+    explain: bool,
+
+    /// Neither say nor show anything when a timer is up, like timer.silent
+    #[clap(long, global = true)]
+    silent: bool,
+
+    /// Work on the session NAME, for several mobs in one repository
+    #[clap(long, global = true, value_name = "NAME")]
+    session: Option<String>,
+
+    /// Run in every repository of the workspace at DIR, as its .mob-workspace.toml lists
+    #[clap(long, global = true, value_name = "DIR", conflicts_with = "dir")]
+    workspace: Option<PathBuf>,
+
     #[clap(subcommand)]
     subcmd: SubCommand,
 }
@@ -12,47 +70,747 @@ struct Opts {
 #[derive(Clap, Debug)]
 enum SubCommand {
     /// Get current status
-    #[clap(name = "status")]
+    #[clap(name = "status", visible_alias = "s")]
     Status(cmd::StatusOpts),
 
     /// Reorder drivers
     #[clap(name = "order")]
     Order,
 
+    /// List the rotation, add someone to it or take them out
+    #[clap(name = "drivers")]
+    Drivers(cmd::DriversOpts),
+
     /// Clean up all mob related stuff from this repo
     #[clap(name = "clean")]
-    Clean,
+    Clean(cmd::CleanOpts),
 
     /// Start mob session
     #[clap(name = "start")]
     Start(cmd::StartOpts),
 
+    /// Join a running session from this machine without taking the turn
+    #[clap(name = "join")]
+    Join(cmd::JoinOpts),
+
     /// Finish turn and sync repo
-    #[clap(name = "next")]
-    Next,
+    #[clap(name = "next", visible_aliases = &["n", "rotate"])]
+    Next(cmd::NextOpts),
+
+    /// Commit and push your work without ending the turn, or push the turns handed over
+    /// offline
+    #[clap(name = "sync")]
+    Sync,
+
+    /// Take a break or lunch, for a number of minutes or until a time
+    #[clap(name = "break")]
+    Break(cmd::BreakOpts),
+
+    /// Have the rotation skip you for a while, until a time or for a duration
+    #[clap(name = "away")]
+    Away(cmd::AwayOpts),
+
+    /// Be part of the rotation again after mob away
+    #[clap(name = "back")]
+    Back,
+
+    /// Pass the turn from the next driver to the one after, or to --to someone
+    #[clap(name = "skip")]
+    Skip(cmd::SkipOpts),
+
+    /// Interrupt the turn, like for a meeting, and keep what is left of it
+    #[clap(name = "pause")]
+    Pause(cmd::PauseOpts),
+
+    /// Give the turn mob pause interrupted back to its driver
+    #[clap(name = "resume")]
+    Resume,
+
+    /// Reserve the next turn when anyone may take it
+    #[clap(name = "claim")]
+    Claim,
+
+    /// Give back the next turn you claimed
+    #[clap(name = "unclaim")]
+    Unclaim,
+
+    /// Nag while waiting for the next driver to start
+    #[clap(name = "watch")]
+    Watch,
+
+    /// Keep pulling what the driver pushes while you watch, until the session stops
+    #[clap(name = "watch-remote")]
+    WatchRemote(cmd::WatchRemoteOpts),
 
     /// Stop session and merge branch
-    #[clap(name = "done")]
-    Done,
+    #[clap(name = "done", visible_aliases = &["stop", "finish"])]
+    Done(cmd::DoneOpts),
+
+    /// Commit and push everything, pause the session and tell the channel, for incidents
+    #[clap(name = "emergency")]
+    Emergency(cmd::EmergencyOpts),
+
+    /// Print the session as environment variables, for `eval "$(mob env)"`
+    #[clap(name = "env")]
+    Env(cmd::EnvOpts),
+
+    /// Tell whether there is a newer mob
+    #[clap(name = "update")]
+    Update(cmd::UpdateOpts),
+
+    /// Write the session to a file, to move it to another remote
+    #[clap(name = "export")]
+    Export(cmd::ExportOpts),
+
+    /// Recreate a session from mob export in this repository
+    #[clap(name = "import")]
+    Import(cmd::ImportOpts),
+
+    /// Show turns and time of the session, or of all finished ones
+    #[clap(name = "stats")]
+    Stats(cmd::StatsOpts),
+
+    /// Switch the session to the durations of a preset
+    #[clap(name = "preset")]
+    Preset(cmd::PresetOpts),
+
+    /// Look for and repair damage old versions left in the session
+    #[clap(name = "doctor")]
+    Doctor(cmd::DoctorOpts),
+
+    /// Check what must hold of the session without changing anything
+    #[clap(name = "verify")]
+    Verify(cmd::VerifyOpts),
+
+    /// Change the config in ~/.mob or in git config
+    #[clap(name = "config")]
+    Config(cmd::ConfigOpts),
+
+    /// Run the commands of a --record file again and check they do the same
+    #[clap(name = "replay")]
+    Replay(ReplayOpts),
+
+    /// Print the script that completes mob's commands in a shell, like
+    /// `mob completions zsh > ~/.zfunc/_mob`
+    #[clap(name = "completions")]
+    Completions(cmd::CompletionsOpts),
+}
+
+#[derive(Clap, Debug)]
+struct ReplayOpts {
+    /// A file written with --record
+    file: PathBuf,
+
+    /// Answer git from the recording and keep the session in memory
+    #[clap(long)]
+    against_fakes: bool,
+}
+
+/// The command line with the aliases of the config replaced
+fn args() -> Result<Vec<String>> {
+    let app = Opts::into_app();
+    let mut names = vec!["help".to_string()];
+    for command in app.get_subcommands() {
+        names.push(command.get_name().to_string());
+        names.extend(command.get_all_aliases().map(String::from));
+    }
+    let mut valued = vec![];
+    for arg in app
+        .get_arguments()
+        .filter(|arg| arg.is_set(ArgSettings::TakesValue))
+    {
+        valued.extend(arg.get_long().map(|long| format!("--{}", long)));
+        valued.extend(arg.get_short().map(|short| format!("-{}", short)));
+    }
+    // Paths and names that aren't UTF-8 would end up changed, better to refuse them
+    let args = env::args_os()
+        .map(|arg| {
+            arg.into_string().map_err(|arg| {
+                anyhow!(
+                    "The argument `{}` is not valid UTF-8",
+                    arg.to_string_lossy()
+                )
+            })
+        })
+        .collect::<Result<_>>()?;
+    alias::expand(
+        args,
+        &config::aliases(&config::Source::user()),
+        &alias::Commands { names, valued },
+    )
 }
 
 fn main() -> Result<()> {
     emoji_logger::init("debug");
-    let opts: Opts = Opts::parse();
+    let args = args()?;
+    let mut opts: Opts = Opts::parse_from(&args);
+    // The command as clap names it, for the layers it runs in and what is explained
+    let command = command_name(&args);
+    let offline = command.clone().filter(|_| opts.offline);
+    let json = opts.json;
+    // Scripts asking for JSON get nothing else on stdout
+    let explain = command.clone().filter(|_| opts.explain && !json);
+    let explain = Explain {
+        command: explain.as_deref(),
+        first_time: !json,
+    };
+    deadline::set(deadline::timeout(
+        opts.timeout,
+        env::var(deadline::ENV).ok(),
+    )?);
+    let context = ci::RunContext::detect(|name| env::var(name).ok());
+    ci::set(context);
+    if let Some(notice) = context.notice() {
+        log::warn!("{}", notice);
+    }
+    deadline::take_defaults(opts.yes || !prompt::attended());
+    global_flags(&mut opts.subcmd, json, opts.yes);
+    if let Some(name) = &opts.session {
+        check_session_name(name)?;
+    }
+    if let SubCommand::Replay(replay_opts) = opts.subcmd {
+        return replay(replay_opts);
+    }
+    if let SubCommand::Completions(completions_opts) = opts.subcmd {
+        return cmd::Completions::new(Opts::into_app(), completions_opts).run();
+    }
+    if let SubCommand::Config(config_opts) = opts.subcmd {
+        return cmd::Configure::new(config_opts, config::Source::user()).run();
+    }
+    if let Some(path) = &opts.record {
+        record::start(path, args);
+    }
 
-    let config = config::load()?;
+    let mut config = config::load(&config::Source::user())?;
+    config.timer.silent |= opts.silent;
+    config.git.offline = opts.offline;
+    context.apply(&mut config);
+    config.commit.sign = (config.commit.sign || opts.sign) && !opts.no_sign;
+    palette::Palette::set_global(config.display.palette);
+    record::config(&config);
 
-    let timer = timer::ConsoleTimer::new(config.commands());
-    let git = git::GitCommand::new(None, config.remote.clone())?;
-    let store = session::SessionStore::new(&git);
+    // Asked while the command runs and told at its end, bots under a deadline aren't held up
+    let update_check = match update::cache_file() {
+        Some(cache)
+            if config.update.check
+                && !deadline::active()
+                && !matches!(opts.subcmd, SubCommand::Update(_)) =>
+        {
+            Some(std::thread::spawn(move || {
+                update::Checker::new(&update::Curl, cache, clap::crate_version!())
+                    .check(clock::SystemClock.now_utc(), false)
+            }))
+        }
+        _ => None,
+    };
 
-    match opts.subcmd {
-        SubCommand::Start(opts) => cmd::Start::new(&git, &store, &timer, opts, config).run()?,
-        SubCommand::Next => cmd::Next::new(&git, &store, config).run()?,
-        SubCommand::Done => cmd::Done::new(&git, &store, config).run()?,
-        SubCommand::Clean => store.clean()?,
-        SubCommand::Status(opts) => cmd::Status::new(opts, &store, config).run()?,
-        SubCommand::Order => cmd::Order::new(&store).run()?,
+    let dir = match opts.dir {
+        Some(ref dir) => dir.clone(),
+        None => env::current_dir()?,
     };
+    let local = opts.dir.is_some()
+        || git::FileStore::exists(&dir)
+        || matches!(&opts.subcmd, SubCommand::Start(start) if start.local);
+    // Run from the root of a workspace, the session is kept in its primary repository
+    let workspace = match &opts.workspace {
+        Some(root) => Some(root.clone()),
+        None if !local && git::workspace::Manifest::exists(&dir) => Some(dir.clone()),
+        None => None,
+    };
+    let manifest = match workspace {
+        Some(root) => Some((git::workspace::Manifest::load(&root)?, root)),
+        None => None,
+    };
+
+    let target = Target {
+        command: command.as_deref().unwrap_or_default(),
+        session: opts.session.clone(),
+    };
+    let result = if local {
+        check_local(target.command)?;
+        let file_store = git::FileStore::new(&dir);
+        let state_dir = StateDir::new(dir.join(".mob-local.d"));
+        run_recorded(
+            opts.subcmd,
+            target,
+            config,
+            state_dir,
+            &git::NoopGit,
+            &file_store,
+            explain,
+        )
+    } else {
+        // An import keeps the session on the remote it moves to
+        let remote = match &opts.subcmd {
+            SubCommand::Import(import) => import.remote.clone(),
+            _ => None,
+        };
+        let moving = remote.is_some();
+        let remote = remote.unwrap_or_else(|| config.remote.clone());
+        let primary = manifest
+            .as_ref()
+            .map(|(manifest, root)| root.join(manifest.primary()));
+        git::GitCommand::new(primary, remote).and_then(|mut git| {
+            let mut config = config;
+            git.auto_fetch = config.git.auto_fetch;
+            if let Some(command) = &offline {
+                git::offline::check(command)?;
+                git.auto_fetch = false;
+            }
+            let state_dir = git.state_dir();
+            // Fetching would drop the session saved while offline
+            let name = target
+                .session
+                .as_deref()
+                .unwrap_or(session::DEFAULT_SESSION);
+            if cmd::pending(&state_dir, name) {
+                git.auto_fetch = false;
+            }
+            // A clone of a read-only mirror pushes to the remote chosen for it
+            let chosen = cmd::chosen_remote(&state_dir);
+            let chosen = match &opts.subcmd {
+                SubCommand::Start(_) if offline.is_none() => {
+                    let remote = chosen.unwrap_or_else(|| config.remote.clone());
+                    cmd::check_remote(&git, &remote, &state_dir, deadline::attended())?
+                        .or(Some(remote))
+                }
+                _ => chosen,
+            };
+            if let Some(chosen) = chosen {
+                if !moving {
+                    git.remote = chosen.clone();
+                }
+                config.remote = chosen;
+            }
+            let mut others = vec![];
+            if let Some((manifest, root)) = &manifest {
+                for repo in manifest.others() {
+                    let mut member =
+                        git::GitCommand::new(Some(root.join(repo)), git.remote.clone())
+                            .with_context(|| {
+                                format!("{} of the workspace is no repository", repo)
+                            })?;
+                    member.auto_fetch = git.auto_fetch;
+                    others.push((repo, member));
+                }
+            }
+            let workspace = manifest.as_ref().map(|(manifest, _)| {
+                let others = others
+                    .iter()
+                    .map(|(repo, member)| (*repo, member as &dyn git::Git))
+                    .collect();
+                git::workspace::Workspace::new((manifest.primary(), &git), others)
+            });
+            if offline.is_some() {
+                warnings::push(
+                    "offline",
+                    "offline, the session is as it was fetched last",
+                    "0.1.7",
+                );
+                let offline = match &workspace {
+                    Some(workspace) => git::offline::Offline::new(workspace, &git),
+                    None => git::offline::Offline::new(&git, &git),
+                };
+                return run_recorded(
+                    opts.subcmd,
+                    target,
+                    config,
+                    state_dir,
+                    &offline,
+                    &offline,
+                    explain,
+                );
+            }
+            match &workspace {
+                Some(workspace) => run_recorded(
+                    opts.subcmd,
+                    target,
+                    config,
+                    state_dir,
+                    workspace,
+                    &git,
+                    explain,
+                ),
+                None => run_recorded(opts.subcmd, target, config, state_dir, &git, &git, explain),
+            }
+        })
+    };
+    if let Err(err) = record::finish(result.as_ref().err()) {
+        log::warn!("Couldn't record the command, {}", err);
+    }
+
+    match update_check.map(|check| check.join()) {
+        Some(Ok(Ok(Some(newer)))) => update::announce(newer),
+        Some(Ok(Err(err))) => log::trace!("Could not check for a newer mob: {:#}", err),
+        _ => (),
+    }
+
+    let collected = warnings::take();
+    let newer = update::take();
+    let status = warnings::take_status();
+    if json {
+        println!(
+            "{}",
+            warnings::to_json(&collected, newer.as_ref(), status.as_ref())
+        );
+    } else {
+        let palette = palette::Palette::global();
+        eprint!("{}", warnings::to_lines(&collected, &palette));
+        if let Some(newer) = newer {
+            let notice = update::notice(&newer, clap::crate_version!());
+            eprintln!("{}", palette.paint(palette::Role::Muted, notice));
+        }
+    }
+    match result {
+        Err(err) if deadline::timed_out(&err) => {
+            eprintln!("Error: {:?}", err);
+            std::process::exit(deadline::EXIT_CODE)
+        }
+        result => result,
+    }
+}
+
+/// Hands the global flags to the commands that read them
+fn global_flags(subcmd: &mut SubCommand, json: bool, yes: bool) {
+    match subcmd {
+        SubCommand::Status(status) => status.json = json,
+        SubCommand::Verify(verify) => verify.json = json,
+        SubCommand::Done(done) => done.yes = yes,
+        SubCommand::Clean(clean) => clean.yes = yes,
+        _ => {}
+    }
+}
+
+/// Session names end up in branch and file names
+fn check_session_name(name: &str) -> Result<()> {
+    match session::SessionStore::valid_name(name) {
+        true => Ok(()),
+        false => Err(anyhow!(
+            "`{}` can't name a session, use letters, digits, - and _",
+            name
+        )),
+    }
+}
+
+/// Refuses the commands that only work on a repository, before a local session is touched
+fn check_local(command: &str) -> Result<()> {
+    match command {
+        "watch-remote" | "export" | "import" | "sync" => Err(anyhow!(
+            "mob {} needs a git repository, this session has none",
+            command
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// The subcommand of `args` as clap names it, without its aliases
+fn command_name(args: &[String]) -> Option<String> {
+    Opts::into_app()
+        .try_get_matches_from(args)
+        .ok()?
+        .subcommand_name()
+        .map(String::from)
+}
+
+/// Which command runs on which session
+struct Target<'a> {
+    /// As clap names it, for the layers the command runs in
+    command: &'a str,
+    /// From `--session`, the default session without it
+    session: Option<String>,
+}
+
+fn run_recorded(
+    subcmd: SubCommand,
+    target: Target,
+    config: config::Config,
+    state_dir: StateDir,
+    git: &impl git::Git,
+    store: &impl git::Store,
+    explain: Explain,
+) -> Result<()> {
+    if record::active() {
+        run(
+            subcmd,
+            target,
+            config,
+            state_dir,
+            &RecordingGit::new(git),
+            store,
+            explain,
+        )
+    } else {
+        run(subcmd, target, config, state_dir, git, store, explain)
+    }
+}
+
+/// Runs each recorded command against a git that answers as recorded and a session in memory
+fn replay(opts: ReplayOpts) -> Result<()> {
+    if !opts.against_fakes {
+        return Err(anyhow!(
+            "mob replay only runs --against-fakes, a real repository would be changed"
+        ));
+    }
+    let entries = record::read(&opts.file)?;
+    let mut different = 0;
+    let mut replayed = 0;
+    for (index, entry) in entries.iter().enumerate() {
+        let line = index + 1;
+        let command = entry.argv.join(" ");
+        let (subcmd, name) = match Opts::try_parse_from(&entry.argv) {
+            Ok(Opts {
+                subcmd: SubCommand::Replay(_),
+                ..
+            })
+            | Ok(Opts {
+                subcmd: SubCommand::Watch,
+                ..
+            })
+            | Ok(Opts {
+                subcmd: SubCommand::WatchRemote(_),
+                ..
+            })
+            | Ok(Opts {
+                subcmd: SubCommand::Config(_),
+                ..
+            }) => {
+                log::info!("{}: skipping {}, it can't be replayed", line, command);
+                continue;
+            }
+            Ok(opts) => {
+                let mut subcmd = opts.subcmd;
+                global_flags(&mut subcmd, opts.json, opts.yes);
+                (subcmd, opts.session)
+            }
+            Err(err) => return Err(anyhow!("line {}: {}", line, err)),
+        };
+        let mut config: config::Config = serde_json::from_value(entry.config.clone())
+            .map_err(|err| anyhow!("line {}: {}", line, err))?;
+        // Nothing may leave the replay
+        config.stats.shared_dir = None;
+
+        let documents = record::Documents::default();
+        if let Some(before) = &entry.before {
+            let name = name.as_deref().unwrap_or(session::DEFAULT_SESSION);
+            session::SessionStore::named(&documents, name).save(before.clone())?;
+        }
+        let git = ReplayGit::new(entry.git.clone(), entry.repository);
+        let state_dir = tempfile::tempdir()?;
+
+        record::replay(entry.prompts.clone());
+        record::start(&opts.file, entry.argv.clone());
+        let named_by_clap = command_name(&entry.argv);
+        let target = Target {
+            command: named_by_clap.as_deref().unwrap_or_default(),
+            session: name,
+        };
+        let result = run(
+            subcmd,
+            target,
+            config,
+            StateDir::new(state_dir.path().to_path_buf()),
+            &git,
+            &documents,
+            Explain::default(),
+        );
+        let after = record::take().and_then(|replay| replay.after);
+        record::stop_replay();
+        warnings::take();
+
+        replayed += 1;
+        let differences = record::differences(entry, after.as_ref(), &git.left(), &result);
+        if differences.is_empty() {
+            log::info!("{}: {} did the same", line, command);
+        } else {
+            different += 1;
+            for difference in differences {
+                log::error!("{}: {}, {}", line, command, difference);
+            }
+        }
+    }
+    match different {
+        0 => Ok(()),
+        _ => Err(anyhow!(
+            "{} of {} commands did something else",
+            different,
+            replayed
+        )),
+    }
+}
+
+fn run(
+    subcmd: SubCommand,
+    target: Target,
+    config: config::Config,
+    state_dir: StateDir,
+    git: &impl git::Git,
+    store: &impl git::Store,
+    explain: Explain,
+) -> Result<()> {
+    let name = target
+        .session
+        .as_deref()
+        .unwrap_or(session::DEFAULT_SESSION);
+    cmd::layered(
+        target.command,
+        name,
+        git,
+        store,
+        state_dir,
+        &config.clone(),
+        |layers| run_layered(subcmd, target.session.as_deref(), config, layers, explain),
+    )
+}
+
+/// Runs `subcmd` on the session `named` with `--session`, inside the layers
+fn run_layered(
+    subcmd: SubCommand,
+    named: Option<&str>,
+    config: config::Config,
+    layers: &cmd::Layers,
+    explain: Explain,
+) -> Result<()> {
+    let (git, store, git_store) = (layers.queue, layers.store, layers.queue);
+    let (root, state_dir) = (layers.root, layers.state_dir.clone());
+    #[cfg(feature = "notifications")]
+    let timer = timer::ConsoleTimer::new(config.commands())
+        .desktop(config.desktop_notifications())
+        .on_end(config.on_timer_end())
+        .bell(config.bell())
+        .broadcast(config.broadcast())
+        .state_dir(state_dir.clone());
+    #[cfg(not(feature = "notifications"))]
+    let timer = timer::LogTimer;
+    let clock = clock::SystemClock;
+    let events = events::EventLog::new(state_dir.clone());
+    let starting = matches!(subcmd, SubCommand::Start(_));
+    let (me, remote) = (config.name.clone(), config.remote.clone());
+
+    match subcmd {
+        SubCommand::Start(opts) => cmd::Start::new(git, store, &timer, &clock, opts, config)
+            .state_dir(state_dir)
+            .run()?,
+        SubCommand::Join(opts) => cmd::Join::new(git, store, opts, config).run()?,
+        SubCommand::Next(opts) => cmd::Next::new(git, store, &timer, &clock, opts, config).run()?,
+        SubCommand::Sync => {
+            if !layers.queue.flush()? {
+                cmd::Sync::new(git, store, &clock, config).run()?
+            }
+        }
+        SubCommand::Break(opts) => {
+            cmd::Break::new(git, store, &timer, &clock, opts, config).run()?
+        }
+        SubCommand::Away(opts) => cmd::Away::new(store, &clock, opts, config).run()?,
+        SubCommand::Back => cmd::Back::new(store, &clock, config).run()?,
+        SubCommand::Skip(opts) => cmd::Skip::new(store, &clock, opts, config).run()?,
+        SubCommand::Pause(opts) => cmd::Pause::new(store, &timer, &clock, opts, config).run()?,
+        SubCommand::Resume => cmd::Resume::new(store, &timer, &clock, config).run()?,
+        SubCommand::Claim => cmd::Claim::new(store, &clock, config).run()?,
+        SubCommand::Unclaim => cmd::Unclaim::new(store, config).run()?,
+        SubCommand::Done(opts) => {
+            cmd::Done::new(git, store, &clock, state_dir, opts, config).run()?
+        }
+        SubCommand::Clean(opts) if opts.all => {
+            for name in session::SessionStore::names(git_store)? {
+                log::info!("Cleaning the session {}", name);
+                let named = session::SessionStore::named(git_store, &name);
+                let state_dir = cmd::session_dir(root, &name);
+                let store = session::EnvStore::new(&named, state_dir.clone());
+                let events = events::EventLog::new(state_dir.clone());
+                cmd::Clean::new(
+                    &store,
+                    &events,
+                    &clock,
+                    state_dir,
+                    opts.clone(),
+                    config.clone(),
+                )
+                .run()?;
+            }
+        }
+        SubCommand::Clean(opts) => {
+            cmd::Clean::new(store, &events, &clock, state_dir, opts, config).run()?
+        }
+        SubCommand::Status(opts) => {
+            // Without a name every session shows, when there are several
+            let names = match named {
+                Some(_) => vec![],
+                None => session::SessionStore::names(git_store)?,
+            };
+            let mut sessions = vec![];
+            if names.len() > 1 {
+                for name in names {
+                    let session = session::SessionStore::named(git_store, &name).load()?;
+                    sessions.push((name, session));
+                }
+            }
+            cmd::Status::new(opts, git, store, &clock, config)
+                .sessions(sessions)
+                .state_dir(state_dir)
+                .run()?
+        }
+        SubCommand::Emergency(opts) => {
+            let channel = timer::ShellNotifier::new(
+                config.handover.channel_command.iter().cloned().collect(),
+            );
+            cmd::Emergency::new(git, store, &timer, &channel, &clock, opts, config).run()?;
+        }
+        SubCommand::Watch => {
+            #[cfg(feature = "notifications")]
+            let local = timer::ShellNotifier::new(config.commands());
+            #[cfg(not(feature = "notifications"))]
+            let local = timer::LogNotifier;
+            let channel = timer::ShellNotifier::new(
+                config.handover.channel_command.iter().cloned().collect(),
+            );
+            cmd::Watch::new(store, &local, &channel, &clock, config).run()?;
+        }
+        SubCommand::WatchRemote(opts) => {
+            cmd::WatchRemote::new(git, store, layers.root.clone(), opts, config).run()?
+        }
+        SubCommand::Order => cmd::Order::new(store, &clock, config).run()?,
+        SubCommand::Drivers(opts) => cmd::Drivers::new(store, &clock, opts, config).run()?,
+        SubCommand::Update(opts) => {
+            let cache = update::cache_file();
+            cmd::Update::new(&update::Curl, &clock, cache, clap::crate_version!(), opts).run()?
+        }
+        SubCommand::Export(opts) => cmd::Export::new(git, store, opts, config).run()?,
+        SubCommand::Env(opts) => cmd::Env::new(store, opts).run()?,
+        SubCommand::Import(opts) => cmd::Import::new(git, store, opts, config).run()?,
+        SubCommand::Preset(opts) => cmd::Preset::new(store, opts, config).run()?,
+        SubCommand::Stats(opts) => cmd::Stats::new(store, &clock, state_dir, opts, config).run()?,
+        SubCommand::Doctor(opts) => cmd::Doctor::new(git, store, state_dir, opts, config).run()?,
+        SubCommand::Verify(opts) => {
+            cmd::Verify::new(git, git_store, store, &clock, state_dir, opts, config).run()?
+        }
+        SubCommand::Replay(opts) => replay(opts)?,
+        SubCommand::Config(opts) => cmd::Configure::new(opts, config::Source::user()).run()?,
+        SubCommand::Completions(opts) => cmd::Completions::new(Opts::into_app(), opts).run()?,
+    };
+
+    // Shown once after the first start, so bots and tests don't use it up
+    let first_start = || {
+        starting
+            && explain.first_time
+            && deadline::attended()
+            && explain::marker().is_some_and(|marker| explain::first_time(&marker))
+    };
+    let command = match explain.command {
+        Some(command) => command,
+        None if first_start() => "start",
+        None => return Ok(()),
+    };
+    let session = layers.session_store.load()?;
+    let facts = explain::Facts::of(&session, &me, &remote, clock.now_utc());
+    print!("{}", explain::section(command, &facts));
     Ok(())
 }
+
+/// What `run_layered` explains after the command
+#[derive(Clone, Copy, Default)]
+struct Explain<'a> {
+    /// Asked for with `--explain`
+    command: Option<&'a str>,
+    /// The workflow may be told after a user's first `mob start`
+    first_time: bool,
+}
@@ -0,0 +1,423 @@
+//! Anonymized numbers of finished sessions, appended by `mob done` to monthly files in
+//! `metrics.dir` and summed up across repositories by `mob stats --metrics-dir`.
+
+use crate::session::{self, Session};
+use crate::stats::Report;
+use crate::warnings;
+use anyhow::{anyhow, Result};
+use chrono::{Datelike, Duration, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::{thread, time};
+
+/// How long appending waits for another repository's `mob done`
+const LOCK_TIMEOUT: time::Duration = time::Duration::from_secs(5);
+/// A lock this old was left by a `mob done` that died
+const LOCK_STALE: time::Duration = time::Duration::from_secs(30);
+
+/// One finished session without anything that tells who or what it was
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Record {
+    /// The hashed first commit of the repository, the same in every clone
+    pub repo: Option<String>,
+    /// The day the session ended, in UTC
+    pub date: NaiveDate,
+    pub minutes: i64,
+    pub turns: usize,
+    pub short_turns: usize,
+    pub breaks: usize,
+    /// The breaks the interval of the session asked for
+    pub breaks_due: usize,
+    pub drivers: usize,
+    /// Turns per driver, only with `metrics.include_names`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub names: Option<BTreeMap<String, usize>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "version")]
+enum VersionedRecord {
+    #[serde(rename = "v1")]
+    V1(Record),
+}
+
+/// The id of a repository as it appears in metrics, so files of many teams can be
+/// summed up without telling their repositories
+pub fn hash(id: &str) -> String {
+    let salted = format!("mob metrics {}", id);
+    git2::Oid::hash_object(git2::ObjectType::Blob, salted.as_bytes())
+        .map(|oid| oid.to_string())
+        .unwrap_or_default()
+}
+
+impl Record {
+    /// The record of `session` as `report` sums it up. `repo` is hashed, the names of the
+    /// drivers are left out unless `include_names`.
+    pub fn of(
+        session: &Session,
+        report: &Report,
+        repo: Option<&str>,
+        include_names: bool,
+    ) -> Record {
+        let breaks = session
+            .history
+            .records()
+            .iter()
+            .filter(
+                |record| matches!(record, session::Record::Break { at } if *at >= report.started),
+            )
+            .count();
+        let minutes = report.duration().num_minutes();
+        let breaks_due = session
+            .settings
+            .as_ref()
+            .and_then(|settings| settings.break_interval)
+            .filter(|interval| interval.get() > 0)
+            .map_or(0, |interval| (minutes / interval.get()) as usize);
+        Record {
+            repo: repo.map(hash),
+            date: report.ended.date().naive_utc(),
+            minutes,
+            turns: report.turns.values().sum(),
+            short_turns: report.short_turns.values().sum(),
+            breaks,
+            breaks_due,
+            drivers: report.turns.len(),
+            names: Some(report.turns.clone()).filter(|_| include_names),
+        }
+    }
+}
+
+/// The file of the month `date` is in
+fn file(dir: &Path, date: NaiveDate) -> PathBuf {
+    dir.join(format!("metrics-{}.jsonl", date.format("%Y-%m")))
+}
+
+/// Held while appending, other repositories finishing at the same time wait for it
+struct Lock(PathBuf);
+
+impl Lock {
+    fn acquire(file: &Path) -> Result<Lock> {
+        let path = file.with_extension("jsonl.lock");
+        let waiting = time::Instant::now();
+        loop {
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(_) => return Ok(Lock(path)),
+                Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {
+                    let stale = fs::metadata(&path)
+                        .and_then(|metadata| metadata.modified())
+                        .ok()
+                        .and_then(|modified| modified.elapsed().ok())
+                        .is_some_and(|age| age > LOCK_STALE);
+                    if stale {
+                        log::debug!("Removing the stale lock {}", path.display());
+                        let _ = fs::remove_file(&path);
+                        continue;
+                    }
+                    if waiting.elapsed() > LOCK_TIMEOUT {
+                        return Err(anyhow!(
+                            "{} has been locked for too long, remove it if no mob done is running",
+                            path.display()
+                        ));
+                    }
+                    thread::sleep(time::Duration::from_millis(10));
+                }
+                Err(err) => return Err(err.into()),
+            }
+        }
+    }
+}
+
+impl Drop for Lock {
+    fn drop(&mut self) {
+        if let Err(err) = fs::remove_file(&self.0) {
+            log::warn!("Could not remove the lock {}: {}", self.0.display(), err);
+        }
+    }
+}
+
+/// Appends `record` as a line to the file of its month in `dir`, returning the file
+pub fn append(dir: &Path, record: &Record) -> Result<PathBuf> {
+    fs::create_dir_all(dir)?;
+    let path = file(dir, record.date);
+    let mut line = serde_json::to_vec(&VersionedRecord::V1(record.clone()))?;
+    line.push(b'\n');
+    let _lock = Lock::acquire(&path)?;
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)?
+        .write_all(&line)?;
+    Ok(path)
+}
+
+/// Every record in the monthly files of `dir`. Lines that can't be read are skipped with
+/// a warning.
+pub fn read(dir: &Path) -> Result<Vec<Record>> {
+    let mut paths: Vec<PathBuf> = fs::read_dir(dir)
+        .map_err(|err| anyhow!("Could not read the metrics in {}: {}", dir.display(), err))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            let name = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .unwrap_or("");
+            name.starts_with("metrics-") && name.ends_with(".jsonl")
+        })
+        .collect();
+    paths.sort();
+    let mut records = vec![];
+    let mut skipped = 0;
+    for path in paths {
+        for line in fs::read_to_string(&path)?.lines() {
+            match serde_json::from_str::<VersionedRecord>(line) {
+                Ok(VersionedRecord::V1(record)) => records.push(record),
+                Err(_) if line.trim().is_empty() => (),
+                Err(_) => skipped += 1,
+            }
+        }
+    }
+    if skipped > 0 {
+        warnings::push(
+            "metrics-unreadable",
+            format!(
+                "skipped {} unreadable lines of metrics in {}",
+                skipped,
+                dir.display()
+            ),
+            "0.1.7",
+        );
+    }
+    Ok(records)
+}
+
+/// The records of many repositories summed up
+#[derive(Debug, Clone, PartialEq)]
+pub struct Aggregate {
+    pub sessions: usize,
+    pub repos: usize,
+    /// Sessions per ISO week, like `2021-W22`
+    pub weeks: BTreeMap<String, usize>,
+    pub minutes: i64,
+    pub turns: usize,
+    pub short_turns: usize,
+    pub breaks: usize,
+    pub breaks_due: usize,
+}
+
+impl Aggregate {
+    pub fn of(records: &[Record]) -> Aggregate {
+        let mut repos = BTreeSet::new();
+        let mut aggregate = Aggregate {
+            sessions: records.len(),
+            repos: 0,
+            weeks: BTreeMap::new(),
+            minutes: 0,
+            turns: 0,
+            short_turns: 0,
+            breaks: 0,
+            breaks_due: 0,
+        };
+        for record in records {
+            repos.insert(record.repo.as_deref());
+            let week = record.date.iso_week();
+            *aggregate
+                .weeks
+                .entry(format!("{}-W{:02}", week.year(), week.week()))
+                .or_insert(0) += 1;
+            aggregate.minutes += record.minutes;
+            aggregate.turns += record.turns;
+            aggregate.short_turns += record.short_turns;
+            aggregate.breaks += record.breaks;
+            aggregate.breaks_due += record.breaks_due;
+        }
+        aggregate.repos = repos.len();
+        aggregate
+    }
+
+    /// `None` without turns
+    pub fn average_turn(&self) -> Option<Duration> {
+        match self.turns {
+            0 => None,
+            turns => Some(Duration::minutes(self.minutes) / turns as i32),
+        }
+    }
+
+    /// The share of due breaks that were taken in percent, `None` when none were due
+    pub fn break_adherence(&self) -> Option<usize> {
+        match self.breaks_due {
+            0 => None,
+            due => Some(self.breaks.min(due) * 100 / due),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::{History, Settings, Times};
+    use crate::timekeeping::Minutes;
+    use chrono::{DateTime, TimeZone, Utc};
+
+    fn on(day: u32) -> DateTime<Utc> {
+        Utc.ymd(2021, 6, day).and_hms(12, 0, 0)
+    }
+
+    fn record(repo: &str, day: u32, minutes: i64, turns: usize) -> Record {
+        Record {
+            repo: Some(hash(repo)),
+            date: on(day).date().naive_utc(),
+            minutes,
+            turns,
+            short_turns: 0,
+            breaks: 1,
+            breaks_due: 2,
+            drivers: 2,
+            names: None,
+        }
+    }
+
+    #[test]
+    fn leaves_names_out() {
+        let session = Session {
+            times: Times {
+                started: Some((on(1) - Duration::hours(2)).into()),
+                ..Times::default()
+            },
+            settings: Some(Settings {
+                break_interval: Some(Minutes(50)),
+                ..Settings::default()
+            }),
+            history: History::new(vec![
+                session::Record::Turn {
+                    driver: "alice".into(),
+                    at: on(1) - Duration::hours(2),
+                },
+                session::Record::Break {
+                    at: on(1) - Duration::hours(1),
+                },
+                session::Record::Turn {
+                    driver: "bob".into(),
+                    at: on(1) - Duration::minutes(50),
+                },
+            ]),
+            ..Session::default()
+        };
+        let report = Report::of(&session, on(1)).unwrap();
+        let record = Record::of(&session, &report, Some("f00d"), false);
+        assert_eq!(
+            record,
+            Record {
+                repo: Some(hash("f00d")),
+                date: NaiveDate::from_ymd(2021, 6, 1),
+                minutes: 120,
+                turns: 2,
+                short_turns: 0,
+                breaks: 1,
+                breaks_due: 2,
+                drivers: 2,
+                names: None,
+            }
+        );
+        let line = serde_json::to_string(&VersionedRecord::V1(record)).unwrap();
+        assert!(
+            !line.contains("alice") && !line.contains("f00d"),
+            "{}",
+            line
+        );
+
+        let named = Record::of(&session, &report, None, true);
+        assert_eq!(named.repo, None);
+        assert_eq!(named.names.unwrap()["alice"], 1);
+    }
+
+    #[test]
+    fn hashes_stably() {
+        assert_eq!(hash("f00d"), hash("f00d"));
+        assert_ne!(hash("f00d"), hash("beef"));
+        assert_eq!(hash("f00d").len(), 40);
+    }
+
+    #[test]
+    fn appends_monthly() {
+        let dir = tempfile::tempdir().unwrap();
+        let june = append(dir.path(), &record("a", 1, 60, 4)).unwrap();
+        append(dir.path(), &record("b", 30, 60, 4)).unwrap();
+        let mut july = record("a", 30, 60, 4);
+        july.date = NaiveDate::from_ymd(2021, 7, 1);
+        let july = append(dir.path(), &july).unwrap();
+        assert_eq!(june.file_name().unwrap(), "metrics-2021-06.jsonl");
+        assert_eq!(july.file_name().unwrap(), "metrics-2021-07.jsonl");
+        assert_eq!(fs::read_to_string(&june).unwrap().lines().count(), 2);
+        assert_eq!(read(dir.path()).unwrap().len(), 3);
+        assert!(!june.with_extension("jsonl.lock").exists());
+    }
+
+    #[test]
+    fn appends_concurrently() {
+        let dir = tempfile::tempdir().unwrap();
+        let writers: Vec<_> = (0..8)
+            .map(|writer| {
+                let dir = dir.path().to_path_buf();
+                thread::spawn(move || {
+                    for _ in 0..10 {
+                        append(&dir, &record(&writer.to_string(), 1, 60, 4)).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+        warnings::take();
+        assert_eq!(read(dir.path()).unwrap().len(), 80);
+        assert!(warnings::take().is_empty());
+    }
+
+    #[test]
+    fn removes_stale_locks() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = file(dir.path(), on(1).date().naive_utc());
+        let lock = path.with_extension("jsonl.lock");
+        fs::write(&lock, "").unwrap();
+        let old = time::SystemTime::now() - LOCK_STALE * 2;
+        fs::File::open(&lock).unwrap().set_modified(old).unwrap();
+        append(dir.path(), &record("a", 1, 60, 4)).unwrap();
+        assert!(!lock.exists());
+    }
+
+    #[test]
+    fn aggregates_across_files() {
+        let dir = tempfile::tempdir().unwrap();
+        for record in &[
+            record("a", 1, 120, 8),
+            record("a", 2, 60, 4),
+            record("b", 8, 180, 6),
+        ] {
+            append(dir.path(), record).unwrap();
+        }
+        fs::write(
+            dir.path().join("metrics-2021-05.jsonl"),
+            "{\"version\": \"v9\"}\n\n",
+        )
+        .unwrap();
+        fs::write(dir.path().join("notes.txt"), "not metrics").unwrap();
+
+        warnings::take();
+        let aggregate = Aggregate::of(&read(dir.path()).unwrap());
+        assert_eq!(warnings::take()[0].code, "metrics-unreadable");
+        assert_eq!(aggregate.sessions, 3);
+        assert_eq!(aggregate.repos, 2);
+        assert_eq!(
+            aggregate.weeks.clone().into_iter().collect::<Vec<_>>(),
+            vec![("2021-W22".to_string(), 2), ("2021-W23".to_string(), 1)]
+        );
+        assert_eq!(aggregate.average_turn(), Some(Duration::minutes(20)));
+        assert_eq!(aggregate.break_adherence(), Some(50));
+        assert_eq!(Aggregate::of(&[]).average_turn(), None);
+        assert_eq!(Aggregate::of(&[]).break_adherence(), None);
+    }
+}
@@ -0,0 +1,469 @@
+//! Commands for async code, with the `async` feature. Each command runs the same code as the
+//! `mob` binary on a thread of its own and the returned futures resolve when it is done, so they
+//! work on any runtime. Dropping a future doesn't stop its command, a push that started
+//! finishes and the session is saved whole.
+//!
+//! The commands can't prompt here: they run as if nobody is at the terminal, questions take
+//! their default or fail, and a session needs its settings before the first `start`.
+
+use crate::config::Config;
+use crate::state_dir::StateDir;
+use crate::timer::{Countdown, Timer};
+use crate::{ci, clock::SystemClock, cmd, deadline, git, session};
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use std::future::Future;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::thread;
+
+struct Shared<T> {
+    output: Option<thread::Result<T>>,
+    waker: Option<Waker>,
+}
+
+/// Resolves to what blocking work on another thread returned
+pub struct Unblock<T> {
+    shared: Arc<Mutex<Shared<T>>>,
+}
+
+impl<T> Future for Unblock<T> {
+    type Output = T;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<T> {
+        let mut shared = self.shared.lock().expect("unblocked work panicked");
+        match shared.output.take() {
+            Some(Ok(output)) => Poll::Ready(output),
+            // The work panicked on its thread, it panics where it is awaited instead
+            Some(Err(panic)) => panic::resume_unwind(panic),
+            None => {
+                shared.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// Runs `work` on its own thread, it runs to the end even if the future is dropped. Polling
+/// panics if `work` did
+pub fn unblock<T, F>(work: F) -> Unblock<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+{
+    let shared = Arc::new(Mutex::new(Shared {
+        output: None,
+        waker: None,
+    }));
+    let done = shared.clone();
+    thread::spawn(move || {
+        let output = panic::catch_unwind(AssertUnwindSafe(work));
+        let mut done = done.lock().expect("future polled while panicking");
+        done.output = Some(output);
+        if let Some(waker) = done.waker.take() {
+            waker.wake();
+        }
+    });
+    Unblock { shared }
+}
+
+/// The timer a command started, to await instead of showing it in the terminal
+#[derive(Debug, Clone, PartialEq)]
+pub struct Alarm {
+    pub title: String,
+    pub message: String,
+    pub at: DateTime<Utc>,
+}
+
+impl Alarm {
+    /// Resolves when the timer is up
+    pub fn wait(&self) -> Unblock<()> {
+        let at = self.at;
+        unblock(move || {
+            if let Ok(left) = (at - Utc::now()).to_std() {
+                thread::sleep(left);
+            }
+        })
+    }
+}
+
+/// Keeps the timer a command asks for instead of running it
+#[derive(Default)]
+struct AlarmTimer {
+    alarm: Mutex<Option<Alarm>>,
+}
+
+impl Timer for AlarmTimer {
+    fn start(
+        &self,
+        _: Countdown,
+        title: &str,
+        duration: chrono::Duration,
+        message: &str,
+    ) -> Result<()> {
+        *self.alarm.lock().unwrap() = Some(Alarm {
+            title: title.to_string(),
+            message: message.to_string(),
+            at: Utc::now() + duration,
+        });
+        Ok(())
+    }
+}
+
+/// Where the session is kept
+#[derive(Debug, Clone)]
+pub enum Place {
+    /// A git repository, the session is on the configured remote
+    Repository(PathBuf),
+    /// A directory without git, as with `mob start --local`
+    Local(PathBuf),
+}
+
+/// A command with the git, store and state directory it runs against, built on its thread
+trait Command: Send + 'static {
+    type Output: Send + 'static;
+    /// As on the command line, for the layers it runs in
+    const NAME: &'static str;
+
+    fn run<G: git::Git, S: session::Store>(
+        self,
+        git: &G,
+        store: &S,
+        state_dir: StateDir,
+        config: Config,
+    ) -> Result<Self::Output>;
+}
+
+struct Start(cmd::StartOpts);
+struct Next(cmd::NextOpts);
+struct Done(cmd::DoneOpts);
+struct Load;
+
+impl Command for Start {
+    type Output = Option<Alarm>;
+    const NAME: &'static str = "start";
+
+    fn run<G: git::Git, S: session::Store>(
+        self,
+        git: &G,
+        store: &S,
+        state_dir: StateDir,
+        config: Config,
+    ) -> Result<Option<Alarm>> {
+        let timer = AlarmTimer::default();
+        cmd::Start::new(git, store, &timer, &SystemClock, self.0, config)
+            .state_dir(state_dir)
+            .run()?;
+        Ok(timer.alarm.into_inner().unwrap())
+    }
+}
+
+impl Command for Next {
+    type Output = Option<Alarm>;
+    const NAME: &'static str = "next";
+
+    fn run<G: git::Git, S: session::Store>(
+        self,
+        git: &G,
+        store: &S,
+        _: StateDir,
+        config: Config,
+    ) -> Result<Option<Alarm>> {
+        let timer = AlarmTimer::default();
+        cmd::Next::new(git, store, &timer, &SystemClock, self.0, config).run()?;
+        Ok(timer.alarm.into_inner().unwrap())
+    }
+}
+
+impl Command for Done {
+    type Output = ();
+    const NAME: &'static str = "done";
+
+    fn run<G: git::Git, S: session::Store>(
+        self,
+        git: &G,
+        store: &S,
+        state_dir: StateDir,
+        config: Config,
+    ) -> Result<()> {
+        cmd::Done::new(git, store, &SystemClock, state_dir, self.0, config).run()
+    }
+}
+
+impl Command for Load {
+    type Output = session::Session;
+    const NAME: &'static str = "status";
+
+    fn run<G: git::Git, S: session::Store>(
+        self,
+        _: &G,
+        store: &S,
+        _: StateDir,
+        _: Config,
+    ) -> Result<session::Session> {
+        Ok(store.load()?)
+    }
+}
+
+/// Runs `command` inside the layers the `mob` binary runs its commands in, holding the lock
+/// while it changes the session
+fn layered<C: Command>(
+    command: C,
+    git: &impl git::Git,
+    store: &impl git::Store,
+    state_dir: StateDir,
+    name: &str,
+    config: Config,
+) -> Result<C::Output> {
+    cmd::layered(
+        C::NAME,
+        name,
+        git,
+        store,
+        state_dir,
+        &config.clone(),
+        |layers| command.run(layers.queue, layers.store, layers.state_dir.clone(), config),
+    )
+}
+
+/// The mob commands as futures
+pub struct Mob {
+    place: Place,
+    config: Config,
+    /// Like `--session`
+    name: String,
+}
+
+impl Mob {
+    pub fn new(place: Place, config: Config) -> Self {
+        Mob {
+            place,
+            config,
+            name: session::DEFAULT_SESSION.to_string(),
+        }
+    }
+
+    /// Runs the commands on the session `name` instead of the default one, like `--session`
+    pub fn named(self, name: &str) -> Self {
+        Mob {
+            name: name.to_string(),
+            ..self
+        }
+    }
+
+    /// Like `mob start`, with the timer of the turn
+    pub fn start(&self, opts: cmd::StartOpts) -> Unblock<Result<Option<Alarm>>> {
+        self.spawn(Start(opts))
+    }
+
+    /// Like `mob next`, with the timer of a break when one is due
+    pub fn next(&self, opts: cmd::NextOpts) -> Unblock<Result<Option<Alarm>>> {
+        self.spawn(Next(opts))
+    }
+
+    pub fn done(&self, opts: cmd::DoneOpts) -> Unblock<Result<()>> {
+        self.spawn(Done(opts))
+    }
+
+    pub fn session(&self) -> Unblock<Result<session::Session>> {
+        self.spawn(Load)
+    }
+
+    fn spawn<C: Command>(&self, command: C) -> Unblock<Result<C::Output>> {
+        let place = self.place.clone();
+        let config = self.config.clone();
+        let name = self.name.clone();
+        unblock(move || {
+            // Nobody is at the terminal of this thread, prompts take their default or fail
+            deadline::take_defaults(true);
+            ci::set(ci::RunContext {
+                interactive: false,
+                ..ci::RunContext::default()
+            });
+            if !session::SessionStore::valid_name(&name) {
+                return Err(anyhow!(
+                    "`{}` can't name a session, use letters, digits, - and _",
+                    name
+                ));
+            }
+            match place {
+                Place::Local(dir) => {
+                    let store = git::FileStore::new(&dir);
+                    let state_dir = StateDir::new(dir.join(".mob-local.d"));
+                    layered(command, &git::NoopGit, &store, state_dir, &name, config)
+                }
+                Place::Repository(dir) => {
+                    let mut git = git::GitCommand::new(Some(dir), config.remote.clone())?;
+                    let state_dir = git.state_dir();
+                    // Fetching would drop the session saved while offline
+                    git.auto_fetch = config.git.auto_fetch && !cmd::pending(&state_dir, &name);
+                    layered(command, &git, &git, state_dir, &name, config)
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::Store;
+    use clap::Clap;
+    use std::sync::mpsc;
+    use std::task::Wake;
+    use std::time::Duration;
+
+    struct Unpark(thread::Thread);
+
+    impl Wake for Unpark {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    /// A current-thread executor, enough to drive one future
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let waker = Waker::from(Arc::new(Unpark(thread::current())));
+        let mut cx = Context::from_waker(&waker);
+        let mut future = Box::pin(future);
+        loop {
+            match future.as_mut().poll(&mut cx) {
+                Poll::Ready(output) => return output,
+                Poll::Pending => thread::park(),
+            }
+        }
+    }
+
+    fn mob(dir: &std::path::Path, name: &str) -> Mob {
+        Mob::new(
+            Place::Local(dir.into()),
+            Config {
+                name: name.into(),
+                ..Config::default()
+            },
+        )
+    }
+
+    #[test]
+    fn start_next_done() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_store = git::FileStore::new(dir.path());
+        session::SessionStore::new(&file_store)
+            .save(session::Session {
+                settings: Some(session::Settings::default()),
+                ..session::Session::default()
+            })
+            .unwrap();
+        let start = || cmd::StartOpts::parse_from(["start", "--local"]);
+
+        let alarm = block_on(mob(dir.path(), "alice").start(start()))
+            .unwrap()
+            .unwrap();
+        assert_eq!(alarm.title, "Your turn");
+        assert!(alarm.at > Utc::now());
+        block_on(mob(dir.path(), "alice").next(cmd::NextOpts::parse_from(["next"]))).unwrap();
+
+        block_on(mob(dir.path(), "bob").start(start())).unwrap();
+        block_on(mob(dir.path(), "bob").next(cmd::NextOpts::parse_from(["next"]))).unwrap();
+        let session = block_on(mob(dir.path(), "bob").session()).unwrap();
+        assert_eq!(
+            session.state,
+            session::State::WaitingForNext {
+                next: Some("alice".into())
+            }
+        );
+
+        block_on(mob(dir.path(), "alice").start(start())).unwrap();
+        block_on(mob(dir.path(), "alice").done(cmd::DoneOpts::parse_from(["done"]))).unwrap();
+        let session = block_on(mob(dir.path(), "alice").session()).unwrap();
+        assert_eq!(session.state, session::State::Stopped);
+        assert_eq!(session.drivers.all(), vec!["alice", "bob"]);
+    }
+
+    #[test]
+    fn runs_in_the_layers_of_the_binary() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_store = git::FileStore::new(dir.path());
+        session::SessionStore::named(&file_store, "payments")
+            .save(session::Session {
+                settings: Some(session::Settings::default()),
+                ..session::Session::default()
+            })
+            .unwrap();
+        let start = cmd::StartOpts::parse_from(["start", "--local"]);
+
+        block_on(mob(dir.path(), "alice").named("payments").start(start)).unwrap();
+        let session = session::SessionStore::named(&file_store, "payments")
+            .load()
+            .unwrap();
+        assert_eq!(
+            session.state,
+            session::State::Working {
+                driver: "alice".into()
+            }
+        );
+        let env = dir.path().join(".mob-local.d/sessions/payments/env.sh");
+        assert!(std::fs::read_to_string(env).unwrap().contains("alice"));
+        assert!(block_on(mob(dir.path(), "alice").named("../payments").session()).is_err());
+    }
+
+    /// Whether anyone would be asked on the thread of the command
+    struct Attended;
+
+    impl Command for Attended {
+        type Output = bool;
+        const NAME: &'static str = "status";
+
+        fn run<G: git::Git, S: session::Store>(
+            self,
+            _: &G,
+            _: &S,
+            _: StateDir,
+            _: Config,
+        ) -> Result<bool> {
+            let asked = deadline::answer::<bool>("Drive anyway?", None);
+            Ok(deadline::attended() || asked.is_ok())
+        }
+    }
+
+    #[test]
+    fn nobody_is_asked() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(!block_on(mob(dir.path(), "alice").spawn(Attended)).unwrap());
+        // The thread that awaits is left as it was
+        assert!(deadline::answer::<bool>("Drive anyway?", None)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn panics_where_awaited() {
+        let awaited = panic::catch_unwind(|| block_on(unblock(|| panic!("lost the remote"))));
+        let panic = awaited.unwrap_err();
+        assert_eq!(panic.downcast_ref::<&str>(), Some(&"lost the remote"));
+    }
+
+    #[test]
+    fn dropped_work_still_finishes() {
+        let (sender, finished) = mpsc::channel();
+        drop(unblock(move || {
+            thread::sleep(Duration::from_millis(20));
+            sender.send(()).unwrap();
+        }));
+        finished.recv_timeout(Duration::from_secs(5)).unwrap();
+    }
+
+    #[test]
+    fn alarm() {
+        let alarm = Alarm {
+            title: "Your turn".into(),
+            message: "mob next".into(),
+            at: Utc::now() + chrono::Duration::milliseconds(20),
+        };
+        block_on(alarm.wait());
+        assert!(Utc::now() >= alarm.at);
+    }
+}
@@ -11,6 +11,27 @@ pub fn command(base: &str) -> Cow<'_, Path> {
     Cow::from(Path::new(base))
 }
 
+/// Asks process `pid` to end, `false` when there is no such process any more.
+pub fn terminate(pid: u32) -> Result<bool, String> {
+    let output = crate::command::Command::new(command("kill"))
+        .run(["-TERM", pid.to_string().as_str()])
+        .map_err(|err| err.to_string())?;
+    match output.status.success() {
+        true => Ok(true),
+        false if output.stderr.contains("No such process") => Ok(false),
+        false => Err(output.stderr.trim().to_string()),
+    }
+}
+
+/// Whether process `pid` runs, also when it is another user's and can't be signalled.
+pub fn alive(pid: u32) -> bool {
+    match crate::command::Command::new(command("kill")).run(["-0", pid.to_string().as_str()]) {
+        Ok(output) => output.status.success() || !output.stderr.contains("No such process"),
+        // Without kill nothing can be told, so it is taken to be running
+        Err(_) => true,
+    }
+}
+
 /// Detect git command.
 #[allow(unused)]
 pub fn detect_git() -> Result<PathBuf, Error> {
@@ -22,3 +22,25 @@ pub fn exe_path(mut path: PathBuf) -> PathBuf {
 pub fn command<'a>(base: &'a str) -> Cow<'a, Path> {
     Cow::from(exe_path(PathBuf::from(base)))
 }
+
+/// Asks process `pid` to end, `false` when there is no such process any more.
+pub fn terminate(pid: u32) -> Result<bool, String> {
+    let output = crate::command::Command::new(command("taskkill"))
+        .run(["/PID", pid.to_string().as_str(), "/F"])
+        .map_err(|err| err.to_string())?;
+    match output.status.success() {
+        true => Ok(true),
+        false if output.stderr.contains("not found") => Ok(false),
+        false => Err(output.stderr.trim().to_string()),
+    }
+}
+
+/// Whether process `pid` runs, `tasklist` prints a note instead of a row when it doesn't.
+pub fn alive(pid: u32) -> bool {
+    let filter = format!("PID eq {}", pid);
+    match crate::command::Command::new(command("tasklist")).run(["/FI", filter.as_str(), "/NH"]) {
+        Ok(output) => output.stdout.contains(&pid.to_string()),
+        // Without tasklist nothing can be told, so it is taken to be running
+        Err(_) => true,
+    }
+}
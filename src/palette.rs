@@ -0,0 +1,180 @@
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// Styled text, built without the `color` feature it is the plain value
+#[cfg(feature = "color")]
+pub type Painted<D> = console::StyledObject<D>;
+#[cfg(not(feature = "color"))]
+pub type Painted<D> = D;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Color {
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+    /// Of the 256 colour palette
+    Color256(u8),
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum PaletteKind {
+    #[default]
+    Default,
+    /// Okabe-Ito colours, distinguishable with the common colour vision deficiencies
+    Colorblind,
+    /// No colours at all, only symbols, bold and underline
+    Mono,
+}
+
+/// What a piece of output means. The palette decides how it looks.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Role {
+    Current,
+    Next,
+    Stopped,
+    Hint,
+    Branch,
+    Base,
+    Marker,
+    Error,
+    Warning,
+    Info,
+    Debug,
+    Trace,
+    /// Side notes, dimmed everywhere
+    Muted,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Palette {
+    kind: PaletteKind,
+    /// Only styles tell it
+    #[cfg_attr(not(feature = "color"), allow(dead_code))]
+    forced: bool,
+}
+
+static GLOBAL: AtomicU8 = AtomicU8::new(0);
+
+impl Palette {
+    pub fn new(kind: PaletteKind) -> Self {
+        Palette {
+            kind,
+            forced: false,
+        }
+    }
+
+    /// Palette set with `set_global`, used where no config is at hand such as the logger
+    pub fn global() -> Self {
+        Palette::new(match GLOBAL.load(Ordering::Relaxed) {
+            1 => PaletteKind::Colorblind,
+            2 => PaletteKind::Mono,
+            _ => PaletteKind::Default,
+        })
+    }
+
+    pub fn set_global(kind: PaletteKind) {
+        GLOBAL.store(kind as u8, Ordering::Relaxed);
+    }
+
+    /// Style even when the output is not a terminal
+    pub fn force_styling(self, forced: bool) -> Self {
+        Palette { forced, ..self }
+    }
+
+    /// No colour at all when built without the `color` feature
+    pub fn color(&self, role: Role) -> Option<Color> {
+        if cfg!(not(feature = "color")) {
+            return None;
+        }
+        match self.kind {
+            PaletteKind::Default => Some(match role {
+                Role::Current | Role::Info => Color::Green,
+                Role::Next | Role::Debug => Color::Blue,
+                Role::Stopped | Role::Branch | Role::Marker | Role::Error => Color::Red,
+                Role::Hint | Role::Base => Color::Cyan,
+                Role::Warning => Color::Yellow,
+                Role::Trace => Color::Magenta,
+                Role::Muted => return None,
+            }),
+            PaletteKind::Colorblind => Some(Color::Color256(match role {
+                Role::Current | Role::Marker => 214, // orange
+                Role::Next | Role::Debug => 39,      // blue
+                Role::Stopped | Role::Error => 166,  // vermillion
+                Role::Hint | Role::Base => 117,      // sky blue
+                Role::Branch | Role::Trace => 175,   // reddish purple
+                Role::Warning => 220,                // yellow
+                Role::Info => 36,                    // bluish green
+                Role::Muted => return None,
+            })),
+            PaletteKind::Mono => None,
+        }
+    }
+
+    pub fn is_bold(&self, role: Role) -> bool {
+        if cfg!(not(feature = "color")) {
+            return false;
+        }
+        match self.kind {
+            PaletteKind::Mono => matches!(
+                role,
+                Role::Current | Role::Stopped | Role::Branch | Role::Marker | Role::Error
+            ),
+            _ => matches!(role, Role::Branch | Role::Base),
+        }
+    }
+
+    pub fn is_underlined(&self, role: Role) -> bool {
+        cfg!(feature = "color")
+            && self.kind == PaletteKind::Mono
+            && matches!(role, Role::Next | Role::Warning)
+    }
+
+    #[cfg(feature = "color")]
+    pub fn style(&self, role: Role) -> console::Style {
+        let mut style = console::Style::new().force_styling(self.forced);
+        if let Some(color) = self.color(role) {
+            style = style.fg(match color {
+                Color::Red => console::Color::Red,
+                Color::Green => console::Color::Green,
+                Color::Yellow => console::Color::Yellow,
+                Color::Blue => console::Color::Blue,
+                Color::Magenta => console::Color::Magenta,
+                Color::Cyan => console::Color::Cyan,
+                Color::Color256(index) => console::Color::Color256(index),
+            });
+        }
+        if self.is_bold(role) {
+            style = style.bold();
+        }
+        if self.is_underlined(role) {
+            style = style.underlined();
+        }
+        if role == Role::Muted {
+            style = style.dim();
+        }
+        style
+    }
+
+    #[cfg(feature = "color")]
+    pub fn paint<D>(&self, role: Role, value: D) -> Painted<D> {
+        self.style(role).apply_to(value)
+    }
+
+    #[cfg(not(feature = "color"))]
+    pub fn paint<D>(&self, _: Role, value: D) -> Painted<D> {
+        value
+    }
+
+    /// Symbol pointing out the driver, or the next driver, in lists
+    pub fn marker(&self, current: bool) -> &'static str {
+        match (self.kind, current) {
+            (PaletteKind::Mono, true) => "▶",
+            (PaletteKind::Mono, false) => "▷",
+            _ => "›",
+        }
+    }
+}
@@ -0,0 +1,17 @@
+//! What code using `mob` as a library needs, kept stable across releases. Everything else may
+//! change with any release. The fakes of the tests come with the `test-util` feature.
+
+pub use crate::clock::{Clock, SystemClock};
+pub use crate::config::Config;
+pub use crate::git::{Git, GitCommand, Store as Documents};
+#[cfg(feature = "async")]
+pub use crate::nonblocking::Mob;
+pub use crate::prompt::Prompt;
+pub use crate::session::{Drivers, Session, SessionStore, Settings, State, Store};
+pub use crate::timekeeping::Minutes;
+pub use crate::timer::Timer;
+
+#[cfg(any(test, feature = "test-util"))]
+pub use crate::clock::FakeClock;
+#[cfg(any(test, feature = "test-util"))]
+pub use crate::git::fake::{FakeGit, MemoryStore};
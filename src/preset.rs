@@ -0,0 +1,185 @@
+//! Named work and break durations to switch between, configured in `~/.mob`.
+
+use crate::session::Settings;
+use crate::timekeeping::Minutes;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use thiserror::Error;
+
+pub type Presets = BTreeMap<String, Preset>;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Preset {
+    /// Length of each turn
+    pub work: Minutes,
+    /// Break after each turn
+    #[serde(rename = "break", default)]
+    pub break_duration: Minutes,
+    /// How long a turn lasts at least before handing over without asking
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_turn: Option<Minutes>,
+}
+
+#[derive(Error, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum Error {
+    #[error("unknown preset `{name}`, available presets: {available}")]
+    Unknown { name: String, available: String },
+    #[error("preset `{0}` needs a work duration of at least one minute")]
+    InvalidWork(String),
+    #[error("preset `{0}` has a negative break duration")]
+    InvalidBreak(String),
+    #[error("preset `{0}` has a negative minimum turn")]
+    InvalidMinTurn(String),
+}
+
+impl Preset {
+    pub fn validate(&self, name: &str) -> Result<(), Error> {
+        if self.work < Minutes(1) {
+            return Err(Error::InvalidWork(name.to_string()));
+        }
+        if self.break_duration < Minutes(0) {
+            return Err(Error::InvalidBreak(name.to_string()));
+        }
+        if matches!(self.min_turn, Some(minutes) if minutes < Minutes(0)) {
+            return Err(Error::InvalidMinTurn(name.to_string()));
+        }
+        Ok(())
+    }
+
+    pub fn apply(&self, settings: &Settings) -> Settings {
+        // A preset's break comes after every turn
+        Settings {
+            work_duration: self.work,
+            break_duration: Some(self.break_duration),
+            break_interval: Some(self.work).filter(|_| self.break_duration > Minutes(0)),
+            min_turn_minutes: self.min_turn.unwrap_or(settings.min_turn_minutes),
+            ..settings.clone()
+        }
+    }
+}
+
+/// The presets used when the config doesn't have any
+pub fn builtin() -> Presets {
+    let mut presets = Presets::new();
+    presets.insert(
+        "focus".into(),
+        Preset {
+            work: Minutes(25),
+            break_duration: Minutes(5),
+            min_turn: None,
+        },
+    );
+    presets.insert(
+        "sprint".into(),
+        Preset {
+            work: Minutes(10),
+            break_duration: Minutes(10),
+            min_turn: None,
+        },
+    );
+    presets
+}
+
+/// Looks up a valid preset by name
+pub fn find<'a>(presets: &'a Presets, name: &str) -> Result<&'a Preset, Error> {
+    let preset = presets.get(name).ok_or_else(|| Error::Unknown {
+        name: name.to_string(),
+        available: if presets.is_empty() {
+            "none, add them to ~/.mob as [presets.<name>]".to_string()
+        } else {
+            presets.keys().cloned().collect::<Vec<_>>().join(", ")
+        },
+    })?;
+    preset.validate(name)?;
+    Ok(preset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use std::fs;
+
+    fn load(toml: &str) -> Config {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("mob.toml");
+        fs::write(&path, toml).unwrap();
+        confy::load_path(&path).unwrap()
+    }
+
+    #[test]
+    fn parse_presets() {
+        let config = load(
+            r#"
+name = "alice"
+remote = "origin"
+
+[presets.focus]
+work = 25
+break = 5
+
+[presets.pair]
+work = "15m"
+min_turn = 5
+"#,
+        );
+        assert_eq!(
+            find(&config.presets, "focus"),
+            Ok(&Preset {
+                work: Minutes(25),
+                break_duration: Minutes(5),
+                min_turn: None,
+            })
+        );
+        let pair = find(&config.presets, "pair").unwrap();
+        assert_eq!(pair.break_duration, Minutes(0));
+        assert_eq!(pair.min_turn, Some(Minutes(5)));
+        assert_eq!(
+            find(&config.presets, "sprint"),
+            Err(Error::Unknown {
+                name: "sprint".into(),
+                available: "focus, pair".into()
+            })
+        );
+    }
+
+    #[test]
+    fn builtin_without_config() {
+        let config = load("name = \"alice\"\nremote = \"origin\"\n");
+        assert_eq!(config.presets, builtin());
+    }
+
+    #[test]
+    fn validate() {
+        let mut presets = Presets::new();
+        presets.insert(
+            "zero".into(),
+            Preset {
+                work: Minutes(0),
+                break_duration: Minutes(5),
+                min_turn: None,
+            },
+        );
+        presets.insert(
+            "negative".into(),
+            Preset {
+                work: Minutes(10),
+                break_duration: Minutes(-1),
+                min_turn: None,
+            },
+        );
+        assert_eq!(
+            find(&presets, "zero"),
+            Err(Error::InvalidWork("zero".into()))
+        );
+        assert_eq!(
+            find(&presets, "negative"),
+            Err(Error::InvalidBreak("negative".into()))
+        );
+        assert!(find(&Presets::new(), "focus")
+            .unwrap_err()
+            .to_string()
+            .contains("none, add them"));
+    }
+}
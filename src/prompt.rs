@@ -0,0 +1,190 @@
+//! Asking the mob something. Commands only see [`Prompt`]: built with the `interactive`
+//! feature the terminal asks with dialoguer, without it every question takes its default.
+
+use anyhow::{anyhow, Result};
+use std::fmt::Display;
+use std::str::FromStr;
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+#[error("\"{0}\" needs an answer, which a build without the interactive feature can't ask for")]
+pub struct NoAnswer(pub String);
+
+pub trait Prompt {
+    fn confirm(&self, question: &str, default: bool) -> Result<bool>;
+
+    /// The index of the chosen item
+    fn select(&self, question: &str, items: &[&str], default: usize) -> Result<usize>;
+
+    /// A line passing `validate`, `default` when nothing is typed. Only an empty default
+    /// takes an empty answer.
+    fn input(
+        &self,
+        question: &str,
+        default: Option<&str>,
+        validate: &dyn Fn(&str) -> Result<(), String>,
+    ) -> Result<String>;
+
+    /// A line of text, `default` when nothing is typed
+    fn text(&self, question: &str, default: &str) -> Result<String> {
+        self.input(question, Some(default), &|_| Ok(()))
+    }
+
+    /// The indices of `items` in the order they were sorted into
+    fn sort(&self, question: &str, items: &[&str]) -> Result<Vec<usize>>;
+
+    /// The indices of the checked items, those `checked` says are at first
+    fn multi_select(&self, question: &str, items: &[&str], checked: &[bool]) -> Result<Vec<usize>>;
+}
+
+/// Takes the default of every question and fails those without one
+pub struct Defaults;
+
+impl Prompt for Defaults {
+    fn confirm(&self, question: &str, default: bool) -> Result<bool> {
+        log::trace!("Not asking \"{}\", taking the default", question);
+        Ok(default)
+    }
+
+    fn select(&self, question: &str, _: &[&str], default: usize) -> Result<usize> {
+        log::trace!("Not asking \"{}\", taking the default", question);
+        Ok(default)
+    }
+
+    fn input(
+        &self,
+        question: &str,
+        default: Option<&str>,
+        validate: &dyn Fn(&str) -> Result<(), String>,
+    ) -> Result<String> {
+        let default = default.ok_or_else(|| NoAnswer(question.to_string()))?;
+        validate(default).map_err(|reason| anyhow!("{}: {}", question, reason))?;
+        log::trace!("Not asking \"{}\", taking the default", question);
+        Ok(default.to_string())
+    }
+
+    fn sort(&self, question: &str, _: &[&str]) -> Result<Vec<usize>> {
+        Err(NoAnswer(question.to_string()).into())
+    }
+
+    fn multi_select(&self, question: &str, _: &[&str], checked: &[bool]) -> Result<Vec<usize>> {
+        log::trace!("Not asking \"{}\", taking the default", question);
+        Ok((0..checked.len()).filter(|index| checked[*index]).collect())
+    }
+}
+
+/// Asks at the terminal
+#[cfg(feature = "interactive")]
+pub struct Terminal;
+
+#[cfg(feature = "interactive")]
+impl Prompt for Terminal {
+    fn confirm(&self, question: &str, default: bool) -> Result<bool> {
+        Ok(dialoguer::Confirm::new()
+            .with_prompt(question)
+            .default(default)
+            .interact()?)
+    }
+
+    fn select(&self, question: &str, items: &[&str], default: usize) -> Result<usize> {
+        Ok(dialoguer::Select::new()
+            .with_prompt(question)
+            .default(default)
+            .items(items)
+            .interact()?)
+    }
+
+    fn input(
+        &self,
+        question: &str,
+        default: Option<&str>,
+        validate: &dyn Fn(&str) -> Result<(), String>,
+    ) -> Result<String> {
+        let mut input = dialoguer::Input::<String>::new();
+        input.with_prompt(question);
+        match default {
+            Some("") => input.allow_empty(true),
+            Some(default) => input.default(default.to_string()),
+            None => &mut input,
+        };
+        Ok(input
+            .validate_with(|answer: &String| validate(answer))
+            .interact_text()?)
+    }
+
+    fn sort(&self, question: &str, items: &[&str]) -> Result<Vec<usize>> {
+        Ok(dialoguer::Sort::new()
+            .with_prompt(question)
+            .items(items)
+            .interact()?)
+    }
+
+    fn multi_select(&self, question: &str, items: &[&str], checked: &[bool]) -> Result<Vec<usize>> {
+        Ok(dialoguer::MultiSelect::new()
+            .with_prompt(question)
+            .items(items)
+            .defaults(checked)
+            .interact()?)
+    }
+}
+
+/// The prompt this build asks with
+pub fn terminal() -> &'static dyn Prompt {
+    #[cfg(feature = "interactive")]
+    return &Terminal;
+    #[cfg(not(feature = "interactive"))]
+    return &Defaults;
+}
+
+/// Whether someone at a terminal could answer, scripts, hooks and editor tasks without one
+/// can't
+pub fn attended() -> bool {
+    #[cfg(feature = "interactive")]
+    return console::user_attended() && std::io::IsTerminal::is_terminal(&std::io::stdin());
+    #[cfg(not(feature = "interactive"))]
+    return false;
+}
+
+/// Asks for a `T` until the answer parses as one
+pub fn parse<T>(prompt: &dyn Prompt, question: &str, default: T) -> Result<T>
+where
+    T: FromStr + Display,
+    T::Err: Display,
+{
+    let answer = prompt.input(question, Some(&default.to_string()), &|answer| {
+        answer
+            .trim()
+            .parse::<T>()
+            .map(|_| ())
+            .map_err(|err| err.to_string())
+    })?;
+    answer.trim().parse().map_err(|err| anyhow!("{}", err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defaults_answer_or_fail() {
+        assert!(Defaults.confirm("Drive anyway?", true).unwrap());
+        assert_eq!(
+            Defaults
+                .select("Retry", &["Retry", "Take turn"], 1)
+                .unwrap(),
+            1
+        );
+        assert_eq!(parse(&Defaults, "Work duration", 10).unwrap(), 10);
+        let anything = |_: &str| Ok(());
+        assert_eq!(Defaults.input("Reason", Some(""), &anything).unwrap(), "");
+
+        let err = Defaults.input("Your name", None, &anything).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<NoAnswer>(),
+            Some(&NoAnswer("Your name".into()))
+        );
+        let refused = |_: &str| Err("no spaces".to_string());
+        let err = Defaults.input("Branch", Some("a b"), &refused).unwrap_err();
+        assert_eq!(err.to_string(), "Branch: no spaces");
+        assert!(Defaults.sort("Reorder", &["alice", "bob"]).is_err());
+    }
+}
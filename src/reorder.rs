@@ -0,0 +1,210 @@
+//! The rotation as a numbered list to edit with `mob drivers reorder --editor`. Lines can
+//! be moved or renumbered, `2.5 dave` goes between the second and third driver.
+
+use std::cmp::Ordering;
+
+const HELP: &str = "\
+# Reorder the rotation by moving the lines, or renumber a driver like 2.5 to put
+# them between the second and the third. Lines starting with # are left out.
+# Add and remove drivers with mob drivers add and remove, not here.
+# An empty list keeps the rotation as it is.
+";
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum Error {
+    #[error("{} not in the rotation, run mob drivers add first", .0.join(", "))]
+    Added(Vec<String>),
+
+    #[error("{} left out, run mob drivers remove to take them out", .0.join(", "))]
+    Dropped(Vec<String>),
+
+    #[error("{} listed more than once", .0.join(", "))]
+    Twice(Vec<String>),
+}
+
+/// The list to edit, numbered from 1 in the order of `drivers`
+pub fn render(drivers: &[String]) -> String {
+    let lines: String = drivers
+        .iter()
+        .enumerate()
+        .map(|(at, name)| format!("{}. {}\n", at + 1, name))
+        .collect();
+    format!("{}\n{}", lines, HELP)
+}
+
+/// The order of `drivers` as `edited`, `None` when nothing is left to order. Lines keep
+/// their place unless their number was changed, then that number is their place.
+pub fn parse(edited: &str, drivers: &[String]) -> Result<Option<Vec<String>>, Error> {
+    let lines: Vec<(Option<f64>, &str)> = edited
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(numbered)
+        .collect();
+    if lines.is_empty() {
+        return Ok(None);
+    }
+    check(lines.iter().map(|(_, name)| *name), drivers)?;
+
+    let mut places: Vec<(f64, bool, &str)> = lines
+        .into_iter()
+        .enumerate()
+        .map(|(at, (number, name))| {
+            let was = drivers.iter().position(|driver| driver == name);
+            match number {
+                Some(number) if Some(number) != was.map(|was| (was + 1) as f64) => {
+                    (number, true, name)
+                }
+                _ => ((at + 1) as f64, false, name),
+            }
+        })
+        .collect();
+    // Renumbered drivers take the place before whoever is there, the sort keeps the rest
+    places.sort_by(|a, b| {
+        a.0.partial_cmp(&b.0)
+            .unwrap_or(Ordering::Equal)
+            .then(b.1.cmp(&a.1))
+    });
+    Ok(Some(
+        places
+            .into_iter()
+            .map(|(_, _, name)| name.to_string())
+            .collect(),
+    ))
+}
+
+/// Fails unless `names` are `drivers`, each once
+fn check<'a>(names: impl Iterator<Item = &'a str>, drivers: &[String]) -> Result<(), Error> {
+    let names: Vec<&str> = names.collect();
+    let mut twice: Vec<String> = vec![];
+    for (at, name) in names.iter().enumerate() {
+        if names[..at].contains(name) && !twice.iter().any(|seen| seen == name) {
+            twice.push(name.to_string());
+        }
+    }
+    let added: Vec<String> = names
+        .iter()
+        .filter(|name| !drivers.iter().any(|driver| driver == *name))
+        .map(|name| name.to_string())
+        .collect();
+    let dropped: Vec<String> = drivers
+        .iter()
+        .filter(|driver| !names.contains(&driver.as_str()))
+        .cloned()
+        .collect();
+    match (added.is_empty(), dropped.is_empty(), twice.is_empty()) {
+        (false, _, _) => Err(Error::Added(added)),
+        (_, false, _) => Err(Error::Dropped(dropped)),
+        (_, _, false) => Err(Error::Twice(twice)),
+        _ => Ok(()),
+    }
+}
+
+/// The number in front of `line` like `2.`, `2)` or `2.5`, and the name after it
+fn numbered(line: &str) -> (Option<f64>, &str) {
+    let mut parts = line.splitn(2, char::is_whitespace);
+    let first = parts.next().unwrap_or_default();
+    let rest = parts.next().map(str::trim).unwrap_or_default();
+    match first.trim_end_matches(&['.', ')', ':'][..]).parse::<f64>() {
+        Ok(number) if number.is_finite() && !rest.is_empty() => (Some(number), rest),
+        _ => (None, line),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn drivers() -> Vec<String> {
+        ["alice", "bob", "carol", "dave"]
+            .iter()
+            .map(|name| name.to_string())
+            .collect()
+    }
+
+    fn order(edited: &str) -> Vec<String> {
+        parse(edited, &drivers()).unwrap().unwrap()
+    }
+
+    #[test]
+    fn reads_back_what_it_renders() {
+        let rendered = render(&drivers());
+        assert!(rendered.starts_with("1. alice\n2. bob\n3. carol\n4. dave\n\n# Reorder"));
+        assert_eq!(order(&rendered), drivers());
+    }
+
+    #[test]
+    fn takes_moved_lines() {
+        assert_eq!(
+            order("3. carol\n1. alice\n2. bob\n4. dave\n"),
+            vec!["carol", "alice", "bob", "dave"]
+        );
+        // Without numbers, blank lines, comments and stray spaces
+        assert_eq!(
+            order("# first\n\n  dave  \nbob\n\n# then\ncarol\r\nalice\n"),
+            vec!["dave", "bob", "carol", "alice"]
+        );
+    }
+
+    #[test]
+    fn takes_changed_numbers() {
+        assert_eq!(
+            order("1. alice\n2. bob\n3. carol\n2.5 dave\n"),
+            vec!["alice", "bob", "dave", "carol"]
+        );
+        // A number that is taken goes before whoever has it
+        assert_eq!(
+            order("1. alice\n2. bob\n1) carol\n4. dave\n"),
+            vec!["carol", "alice", "bob", "dave"]
+        );
+        assert_eq!(
+            order("4: alice\n3: bob\n2: carol\n1: dave\n"),
+            vec!["dave", "carol", "bob", "alice"]
+        );
+        // Moved and renumbered at once
+        assert_eq!(
+            order("2. bob\n1. alice\n3. carol\n0 dave\n"),
+            vec!["dave", "bob", "alice", "carol"]
+        );
+    }
+
+    #[test]
+    fn keeps_names_that_look_like_numbers() {
+        let drivers: Vec<String> = vec!["7".into(), "R2 D2".into()];
+        assert_eq!(
+            parse("2. R2 D2\n7\n", &drivers).unwrap().unwrap(),
+            vec!["R2 D2", "7"]
+        );
+    }
+
+    #[test]
+    fn keeps_the_rotation_when_emptied() {
+        assert_eq!(parse("", &drivers()), Ok(None));
+        assert_eq!(parse("# only comments\n\n", &drivers()), Ok(None));
+    }
+
+    #[test]
+    fn refuses_other_names() {
+        assert_eq!(
+            parse("alice\nbob\ncarol\ndave\nerin\n", &drivers()),
+            Err(Error::Added(vec!["erin".into()]))
+        );
+        assert_eq!(
+            parse("alice\ncarol\n", &drivers()),
+            Err(Error::Dropped(vec!["bob".into(), "dave".into()]))
+        );
+        assert_eq!(
+            parse("alice\nbob\ncarol\ndave\n3. bob\n", &drivers()),
+            Err(Error::Twice(vec!["bob".into()]))
+        );
+        // Renamed is both
+        assert_eq!(
+            parse("alice\nBob\ncarol\ndave\n", &drivers()),
+            Err(Error::Added(vec!["Bob".into()]))
+        );
+        assert_eq!(
+            Error::Dropped(vec!["bob".into(), "dave".into()]).to_string(),
+            "bob, dave left out, run mob drivers remove to take them out"
+        );
+    }
+}
@@ -0,0 +1,484 @@
+//! Time based suggestions for the mob. They are evaluated together and resolved to at
+//! most one, so a lunch isn't offered right after a break or a break at the end of the day.
+//!
+//! Precedence, highest first: stale > window > wrap-up > break, so lunch > wrap-up > break
+//! for a mob that is still there. A window like lunch is a break of its own and wrapping up
+//! beats one more break. A stale session ranks above them all, as offering a break or lunch
+//! to a mob that left hours ago only hides that the session was forgotten.
+
+use crate::duration;
+use crate::session::{Session, Settings, State, Window};
+use crate::timekeeping::{ClockTime, Instant, Minutes};
+use crate::clock::Clock;
+use crate::timezone::Zone;
+use chrono::{DateTime, Duration, FixedOffset, TimeZone, Utc};
+
+/// Sessions without any start or next for this long are probably forgotten
+pub const STALE_AFTER_HOURS: i64 = 4;
+/// Break length when the settings have an interval but no duration
+pub const DEFAULT_BREAK_MINUTES: Minutes = Minutes(5);
+/// No break is suggested this long before a window opens, the mob rests soon anyway
+pub const NO_BREAK_BEFORE_WINDOW_MINUTES: i64 = 30;
+
+/// Ordered by precedence, the greatest wins
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Kind {
+    Break,
+    WrapUp,
+    /// One of the fixed breaks of the settings, like lunch
+    Window,
+    Stale,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Suggestion {
+    pub kind: Kind,
+    /// How long the break or window lasts, the time left of the workday for wrap-up and
+    /// how long nothing happened for stale
+    pub duration: Duration,
+    /// Name of the window
+    pub window: Option<String>,
+}
+
+/// Everything that is due at `now`, in no particular order
+pub fn evaluate<Tz: TimeZone>(
+    now: &DateTime<Tz>,
+    session: &Session,
+    settings: &Settings,
+) -> Vec<Suggestion> {
+    let times = &session.times;
+    let since = |time: Instant| duration::elapsed(time, now.with_timezone(&Utc));
+    let turn = settings.work_duration.duration();
+    let mut suggestions = vec![];
+
+    if session.state != State::Stopped {
+        if let Some(idle) = times.last_activity.map(since) {
+            if idle >= Duration::hours(STALE_AFTER_HOURS) {
+                suggestions.push(Suggestion {
+                    kind: Kind::Stale,
+                    duration: idle,
+                    window: None,
+                });
+            }
+        }
+    }
+
+    let time = ClockTime::of(now);
+    let today = |at: Instant| at.date(&now.timezone()) == now.naive_local().date();
+    // Each window is taken once a day
+    let windows: Vec<&Window> = settings
+        .windows
+        .iter()
+        .filter(|window| times.window_taken(&window.name).map(today) != Some(true))
+        .collect();
+    if let Some((window, left)) = window_at(windows.iter().copied(), time) {
+        // Right after a break the window waits a turn, unless it closes before
+        let just_rested = times.last_break.map(since).map(|ago| ago < turn) == Some(true);
+        if !just_rested || left <= turn {
+            suggestions.push(Suggestion {
+                kind: Kind::Window,
+                duration: left,
+                window: Some(window.name.clone()),
+            });
+        }
+    }
+    let window_soon = windows.iter().any(|window| {
+        window.start > time
+            && window.start.left_from(time) <= Duration::minutes(NO_BREAK_BEFORE_WINDOW_MINUTES)
+    });
+
+    if let Some(end) = settings.workday_end {
+        let left = end.left_from(time);
+        if left < turn {
+            suggestions.push(Suggestion {
+                kind: Kind::WrapUp,
+                duration: left,
+                window: None,
+            });
+        }
+    }
+
+    let break_duration = settings.break_duration.unwrap_or(DEFAULT_BREAK_MINUTES);
+    if let (Some(interval), Some(rested)) = (settings.break_interval, times.rested()) {
+        if interval.get() > 0
+            && break_duration.get() > 0
+            && since(rested) >= interval.duration()
+            && !window_soon
+        {
+            suggestions.push(Suggestion {
+                kind: Kind::Break,
+                duration: break_duration.duration(),
+                window: None,
+            });
+        }
+    }
+
+    suggestions
+}
+
+/// The one suggestion to act on
+pub fn resolve(suggestions: Vec<Suggestion>) -> Option<Suggestion> {
+    suggestions
+        .into_iter()
+        .max_by_key(|suggestion| suggestion.kind)
+}
+
+/// The time the windows of a session are compared with, in its `zone` when it has one and
+/// in the local timezone otherwise
+pub fn now(clock: &dyn Clock, zone: Option<&Zone>) -> DateTime<FixedOffset> {
+    match zone {
+        Some(zone) => zone.at(clock.now_utc()),
+        None => clock.now_local(),
+    }
+}
+
+pub fn suggest<Tz: TimeZone>(
+    now: &DateTime<Tz>,
+    session: &Session,
+    settings: &Settings,
+) -> Option<Suggestion> {
+    resolve(evaluate(now, session, settings))
+}
+
+/// The window open at `time` and how long it stays open. Of overlapping windows the one that
+/// opened last applies, the earlier one had its chance.
+pub fn window_at<'w>(
+    windows: impl IntoIterator<Item = &'w Window>,
+    time: ClockTime,
+) -> Option<(&'w Window, Duration)> {
+    windows
+        .into_iter()
+        .filter(|window| window.contains(time))
+        .min_by_key(|window| std::cmp::Reverse(window.start))
+        .map(|window| (window, window.end.left_from(time)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::FakeClock;
+    use crate::session::Times;
+
+    fn at(h: u32, m: u32) -> DateTime<FixedOffset> {
+        FixedOffset::east(2 * 3600).ymd(2021, 6, 1).and_hms(h, m, 0)
+    }
+
+    fn utc(time: DateTime<FixedOffset>) -> Option<Instant> {
+        Some(time.with_timezone(&Utc).into())
+    }
+
+    fn window(name: &str, start: (u32, u32), end: (u32, u32)) -> Window {
+        Window {
+            name: name.into(),
+            start: ClockTime::hm(start.0, start.1),
+            end: ClockTime::hm(end.0, end.1),
+        }
+    }
+
+    fn settings() -> Settings {
+        Settings {
+            work_duration: Minutes(15),
+            break_duration: Some(Minutes(10)),
+            break_interval: Some(Minutes(60)),
+            windows: vec![window("Lunch", (12, 0), (13, 0))],
+            workday_end: Some(ClockTime::hm(17, 0)),
+            ..Settings::default()
+        }
+    }
+
+    fn session(times: Times) -> Session {
+        Session {
+            state: State::Working {
+                driver: "alice".into(),
+            },
+            times: Times {
+                last_activity: times.last_activity.or_else(|| utc(at(11, 55))),
+                ..times
+            },
+            ..Session::default()
+        }
+    }
+
+    fn kinds(now: DateTime<FixedOffset>, times: Times) -> Vec<Kind> {
+        let mut kinds: Vec<Kind> = evaluate(&now, &session(times), &settings())
+            .into_iter()
+            .map(|s| s.kind)
+            .collect();
+        kinds.sort();
+        kinds
+    }
+
+    fn resolved(now: DateTime<FixedOffset>, times: Times) -> Option<Kind> {
+        suggest(&now, &session(times), &settings()).map(|s| s.kind)
+    }
+
+    #[test]
+    fn nothing_due() {
+        let times = Times {
+            started: utc(at(9, 0)),
+            ..Times::default()
+        };
+        assert_eq!(kinds(at(9, 30), times), vec![]);
+    }
+
+    #[test]
+    fn break_after_interval() {
+        let times = Times {
+            started: utc(at(9, 0)),
+            ..Times::default()
+        };
+        let suggestion = suggest(&at(10, 0), &session(times), &settings()).unwrap();
+        assert_eq!(suggestion.kind, Kind::Break);
+        assert_eq!(suggestion.duration, Duration::minutes(10));
+
+        let rested = Times {
+            started: utc(at(9, 0)),
+            last_break: utc(at(9, 30)),
+            ..Times::default()
+        };
+        assert_eq!(resolved(at(10, 0), rested), None);
+    }
+
+    #[test]
+    fn lunch_beats_break() {
+        let times = Times {
+            started: utc(at(9, 0)),
+            ..Times::default()
+        };
+        assert_eq!(
+            kinds(at(12, 10), times.clone()),
+            vec![Kind::Break, Kind::Window]
+        );
+        let lunch = suggest(&at(12, 10), &session(times), &settings()).unwrap();
+        assert_eq!(lunch.kind, Kind::Window);
+        assert_eq!(lunch.duration, Duration::minutes(50));
+    }
+
+    #[test]
+    fn lunch_waits_a_turn_after_a_break() {
+        let times = Times {
+            started: utc(at(9, 0)),
+            last_break: utc(at(12, 0)),
+            ..Times::default()
+        };
+        assert_eq!(resolved(at(12, 5), times.clone()), None);
+        assert_eq!(resolved(at(12, 20), times.clone()), Some(Kind::Window));
+        // Unless the window closes before the turn is over
+        let late_break = Times {
+            last_break: utc(at(12, 40)),
+            ..times
+        };
+        assert_eq!(resolved(at(12, 50), late_break), Some(Kind::Window));
+    }
+
+    #[test]
+    fn one_lunch_a_day_and_it_counts_as_break() {
+        let times = Times {
+            started: utc(at(9, 0)),
+            last_lunch: utc(at(12, 0)),
+            ..Times::default()
+        };
+        assert_eq!(kinds(at(12, 30), times.clone()), vec![]);
+        assert_eq!(resolved(at(13, 0), times.clone()), Some(Kind::Break));
+        let yesterday = Times {
+            last_lunch: Some(utc(at(12, 0)).unwrap() - Duration::days(1)),
+            ..times
+        };
+        assert_eq!(resolved(at(12, 30), yesterday), Some(Kind::Window));
+    }
+
+    #[test]
+    fn several_windows_a_day() {
+        let tea = Settings {
+            windows: vec![
+                window("Tea", (10, 0), (10, 15)),
+                window("Lunch", (12, 0), (13, 0)),
+            ],
+            ..settings()
+        };
+        let times = Times {
+            started: utc(at(9, 0)),
+            last_break: utc(at(9, 0)),
+            ..Times::default()
+        };
+        let tea_time = suggest(&at(10, 5), &session(times.clone()), &tea).unwrap();
+        assert_eq!(tea_time.kind, Kind::Window);
+        assert_eq!(tea_time.window.as_deref(), Some("Tea"));
+        assert_eq!(tea_time.duration, Duration::minutes(10));
+
+        let mut had_tea = times;
+        had_tea.last_lunch = utc(at(10, 0));
+        had_tea
+            .windows
+            .insert("Tea".into(), utc(at(10, 0)).unwrap());
+        assert_eq!(
+            evaluate(&at(10, 10), &session(had_tea.clone()), &tea),
+            vec![]
+        );
+        let lunch = suggest(&at(12, 30), &session(had_tea), &tea).unwrap();
+        assert_eq!(lunch.window.as_deref(), Some("Lunch"));
+    }
+
+    #[test]
+    fn no_break_right_before_a_window() {
+        let times = Times {
+            started: utc(at(10, 0)),
+            ..Times::default()
+        };
+        assert_eq!(resolved(at(11, 15), times.clone()), Some(Kind::Break));
+        assert_eq!(resolved(at(11, 30), times.clone()), None);
+        assert_eq!(resolved(at(11, 50), times.clone()), None);
+        // Not once the window was taken today
+        let had_lunch = Times {
+            last_lunch: utc(at(10, 0)),
+            ..times
+        };
+        assert_eq!(resolved(at(11, 30), had_lunch), Some(Kind::Break));
+    }
+
+    #[test]
+    fn overlapping_windows() {
+        let windows = vec![
+            window("Lunch", (12, 0), (13, 0)),
+            window("Tea", (12, 30), (12, 45)),
+            window("Walk", (12, 30), (13, 30)),
+        ];
+        let open = |h, m| {
+            window_at(&windows, ClockTime::hm(h, m))
+                .map(|(window, left)| (window.name.as_str(), left.num_minutes()))
+        };
+        assert_eq!(open(11, 59), None);
+        assert_eq!(open(12, 0), Some(("Lunch", 60)));
+        // The one that opened last, the first of those that opened together
+        assert_eq!(open(12, 30), Some(("Tea", 15)));
+        assert_eq!(open(12, 45), Some(("Walk", 45)));
+        assert_eq!(open(13, 0), Some(("Walk", 30)));
+        assert_eq!(open(13, 30), None);
+        assert_eq!(
+            window_at(windows.iter().skip(1), ClockTime::hm(12, 15)),
+            None
+        );
+    }
+
+    #[test]
+    fn wrap_up_beats_break() {
+        let times = Times {
+            started: utc(at(13, 0)),
+            last_activity: utc(at(16, 45)),
+            ..Times::default()
+        };
+        assert_eq!(
+            kinds(at(16, 50), times.clone()),
+            vec![Kind::Break, Kind::WrapUp]
+        );
+        let wrap_up = suggest(&at(16, 50), &session(times), &settings()).unwrap();
+        assert_eq!(wrap_up.kind, Kind::WrapUp);
+        assert_eq!(wrap_up.duration, Duration::minutes(10));
+        assert_eq!(
+            resolved(
+                at(17, 30),
+                Times {
+                    started: utc(at(17, 0)),
+                    last_activity: utc(at(17, 0)),
+                    ..Times::default()
+                }
+            ),
+            Some(Kind::WrapUp)
+        );
+    }
+
+    #[test]
+    fn stale_beats_everything() {
+        let times = Times {
+            started: utc(at(7, 0)),
+            last_activity: utc(at(8, 0)),
+            ..Times::default()
+        };
+        assert_eq!(
+            kinds(at(12, 10), times.clone()),
+            vec![Kind::Break, Kind::Window, Kind::Stale]
+        );
+        assert_eq!(resolved(at(12, 10), times), Some(Kind::Stale));
+    }
+
+    #[test]
+    fn stopped_sessions_are_never_stale() {
+        let mut session = session(Times {
+            last_activity: utc(at(8, 0)),
+            ..Times::default()
+        });
+        session.state = State::Stopped;
+        assert_eq!(suggest(&at(16, 0), &session, &settings()), None);
+    }
+
+    #[test]
+    fn disabled_settings() {
+        let times = Times {
+            started: utc(at(9, 0)),
+            ..Times::default()
+        };
+        let off = Settings {
+            work_duration: Minutes(15),
+            ..Settings::default()
+        };
+        assert_eq!(evaluate(&at(12, 30), &session(times.clone()), &off), vec![]);
+        let no_break = Settings {
+            break_duration: Some(Minutes(0)),
+            ..settings()
+        };
+        assert_eq!(evaluate(&at(10, 30), &session(times), &no_break), vec![]);
+    }
+
+    #[test]
+    fn lunch_in_the_timezone_of_the_session() {
+        let times = Times {
+            started: utc(at(9, 0)),
+            ..Times::default()
+        };
+        // Lunch at noon in Berlin is at eleven in Lisbon
+        let lisbon = FakeClock::at(at(12, 10).with_timezone(&FixedOffset::east(3600)));
+        let berlin = Zone::fixed("Europe/Berlin", 2 * 3600);
+        let shared = now(&lisbon, Some(&berlin));
+        assert_eq!(shared, at(12, 10));
+        assert_eq!(resolved(shared, times.clone()), Some(Kind::Window));
+        assert_eq!(resolved(now(&lisbon, None), times), Some(Kind::Break));
+    }
+
+    #[test]
+    fn resolve_picks_by_precedence() {
+        let suggestion = |kind| Suggestion {
+            kind,
+            duration: Duration::zero(),
+            window: None,
+        };
+        let beats = |winner, loser| {
+            for suggestions in [
+                vec![suggestion(winner), suggestion(loser)],
+                vec![suggestion(loser), suggestion(winner)],
+            ] {
+                assert_eq!(resolve(suggestions).map(|s| s.kind), Some(winner));
+            }
+        };
+        // Lunch > wrap-up > break, and a stale session above everything
+        beats(Kind::Window, Kind::WrapUp);
+        beats(Kind::WrapUp, Kind::Break);
+        beats(Kind::Window, Kind::Break);
+        for kind in [Kind::Window, Kind::WrapUp, Kind::Break] {
+            beats(Kind::Stale, kind);
+        }
+
+        let all = [Kind::Break, Kind::WrapUp, Kind::Window, Kind::Stale];
+        for (i, winner) in all.iter().enumerate() {
+            for mask in 0..(1 << i) {
+                let mut suggestions: Vec<Suggestion> = (0..i)
+                    .filter(|j| mask & (1 << j) != 0)
+                    .map(|j| suggestion(all[j]))
+                    .collect();
+                suggestions.push(suggestion(*winner));
+                suggestions.reverse();
+                assert_eq!(resolve(suggestions).map(|s| s.kind), Some(*winner));
+            }
+        }
+        assert_eq!(resolve(vec![]), None);
+    }
+}
@@ -0,0 +1,153 @@
+//! What the mob answered when `mob next` offered a break, so the offer defaults to what it
+//! usually says at that time of day and that long after the last break.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// How many answers are kept, the oldest are dropped first
+const LIMIT: usize = 50;
+/// Same answers in a row it takes before the default follows them
+const AGREEING: usize = 2;
+
+/// When a break was offered, by local hour and minutes since the last break
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Context {
+    pub hour: u32,
+    pub rested: i64,
+}
+
+impl Context {
+    /// Two hours of the day and half hours of work are alike, from two hours on it's long
+    fn bucket(&self) -> (u32, i64) {
+        (self.hour / 2, (self.rested / 30).clamp(0, 4))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Answer {
+    pub at: DateTime<Utc>,
+    #[serde(flatten)]
+    pub context: Context,
+    pub took: bool,
+}
+
+/// The default the answers suggest and how many in a row agree on it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Learned {
+    pub took: bool,
+    pub times: usize,
+}
+
+impl fmt::Display for Learned {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let answer = if self.took { "Yes" } else { "No" };
+        write!(
+            f,
+            "defaulting to {} like the last {} times",
+            answer, self.times
+        )
+    }
+}
+
+/// The recent answers to break offers, oldest first
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BreakAnswers(Vec<Answer>);
+
+impl BreakAnswers {
+    pub fn new(answers: Vec<Answer>) -> Self {
+        BreakAnswers(answers)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn push(mut self, answer: Answer) -> Self {
+        self.0.push(answer);
+        if self.0.len() > LIMIT {
+            self.0.drain(..self.0.len() - LIMIT);
+        }
+        self
+    }
+
+    /// What the latest answers in `context` agree on, if enough of them do
+    pub fn learned(&self, context: Context) -> Option<Learned> {
+        let mut alike = self
+            .0
+            .iter()
+            .rev()
+            .filter(|answer| answer.context.bucket() == context.bucket())
+            .map(|answer| answer.took);
+        let took = alike.next()?;
+        let times = 1 + alike.take_while(|other| *other == took).count();
+        Some(Learned { took, times }).filter(|learned| learned.times >= AGREEING)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::{Clock, FakeClock};
+
+    fn answers(answers: &[(u32, i64, bool)]) -> BreakAnswers {
+        answers
+            .iter()
+            .fold(BreakAnswers::default(), |answers, (hour, rested, took)| {
+                answers.push(Answer {
+                    at: FakeClock::default().now_utc(),
+                    context: Context {
+                        hour: *hour,
+                        rested: *rested,
+                    },
+                    took: *took,
+                })
+            })
+    }
+
+    fn learned(history: &[(u32, i64, bool)], hour: u32, rested: i64) -> Option<(bool, usize)> {
+        answers(history)
+            .learned(Context { hour, rested })
+            .map(|learned| (learned.took, learned.times))
+    }
+
+    #[test]
+    fn follows_agreeing_answers_in_the_same_context() {
+        let declined = [(10, 50, false), (11, 40, false), (10, 55, false)];
+        assert_eq!(learned(&declined, 10, 45), Some((false, 3)));
+        // Another time of day, or much longer without a break, is another question
+        assert_eq!(learned(&declined, 14, 45), None);
+        assert_eq!(learned(&declined, 10, 100), None);
+        // Only the latest answers in a row count
+        let changed = [(10, 50, false), (10, 40, true), (10, 55, true)];
+        assert_eq!(learned(&changed, 10, 45), Some((true, 2)));
+        let mixed = [(10, 50, true), (10, 40, false)];
+        assert_eq!(learned(&mixed, 10, 45), None);
+        assert_eq!(learned(&[(10, 50, false)], 10, 45), None);
+        assert_eq!(learned(&[], 10, 45), None);
+        // Answers elsewhere in between don't break the row
+        let around = [(10, 50, false), (15, 10, true), (10, 40, false)];
+        assert_eq!(learned(&around, 10, 45), Some((false, 2)));
+        // Long after the last break is all alike
+        assert_eq!(
+            learned(&[(9, 130, true), (9, 400, true)], 8, 200),
+            Some((true, 2))
+        );
+    }
+
+    #[test]
+    fn keeps_the_latest() {
+        let many: Vec<_> = (0..LIMIT + 5).map(|i| (10, i as i64, true)).collect();
+        let answers = answers(&many);
+        assert_eq!(answers.0.len(), LIMIT);
+        assert_eq!(answers.0[0].context.rested, 5);
+        assert_eq!(
+            Learned {
+                took: false,
+                times: 3
+            }
+            .to_string(),
+            "defaulting to No like the last 3 times"
+        );
+    }
+}
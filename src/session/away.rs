@@ -0,0 +1,182 @@
+//! Drivers who stepped away for a while. The rotation skips them without removing them from
+//! the session, until they are back or their time is up.
+
+use super::latest::*;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum Error {
+    #[error("{0} is not one of the drivers, run mob join or mob start first")]
+    NotADriver(String),
+
+    #[error("Being away until {0} has already passed")]
+    Past(DateTime<Utc>),
+}
+
+/// When each driver who is away comes back
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Away(BTreeMap<String, DateTime<Utc>>);
+
+impl Away {
+    /// When `name` is back, if away at `now`
+    pub fn until(&self, name: &str, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        self.0.get(name).copied().filter(|until| *until > now)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn remove(mut self, name: &str) -> Self {
+        self.0.remove(name);
+        self
+    }
+
+    /// Without the drivers whose time was up at `now`
+    pub fn expire(self, now: DateTime<Utc>) -> Self {
+        Away(
+            self.0
+                .into_iter()
+                .filter(|(_, until)| *until > now)
+                .collect(),
+        )
+    }
+
+    /// With the names mapped by `rename`, the later return wins when names merge
+    pub fn rename(self, rename: impl Fn(&str) -> String) -> Self {
+        let mut away = BTreeMap::new();
+        for (name, until) in self.0 {
+            let until = match away.get(&rename(&name)) {
+                Some(other) if *other > until => *other,
+                _ => until,
+            };
+            away.insert(rename(&name), until);
+        }
+        Away(away)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    Away { until: DateTime<Utc> },
+    Back,
+}
+
+/// `name` leaving or coming back. When the session waits for someone who leaves, it waits
+/// for the next driver who is around instead.
+pub fn transition(
+    session: Session,
+    name: &str,
+    change: Change,
+    now: DateTime<Utc>,
+) -> Result<Session, Error> {
+    if !session.drivers.contains(name) {
+        return Err(Error::NotADriver(name.to_string()));
+    }
+    let mut away = session.away.expire(now);
+    match change {
+        Change::Away { until } if until <= now => return Err(Error::Past(until)),
+        Change::Away { until } => {
+            away.0.insert(name.to_string(), until);
+        }
+        Change::Back => away = away.remove(name),
+    }
+
+    let state = match session.state {
+        State::WaitingForNext { next: Some(next) }
+            if next == name && away.until(name, now).is_some() =>
+        {
+            State::WaitingForNext {
+                next: session.drivers.next(name, &away, now).or(Some(next)),
+            }
+        }
+        state => state,
+    };
+    Ok(Session {
+        state,
+        away,
+        ..session
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn session(drivers: &[&str], state: State) -> Session {
+        Session {
+            drivers: Drivers::new(drivers.iter().map(|name| name.to_string()).collect()),
+            state,
+            ..Session::default()
+        }
+    }
+
+    #[test]
+    fn leaves_and_comes_back() {
+        let now = Utc::now();
+        let until = now + Duration::hours(1);
+        let waiting = session(
+            &["alice", "bob", "carol"],
+            State::WaitingForNext {
+                next: Some("bob".into()),
+            },
+        );
+
+        let left = transition(waiting.clone(), "bob", Change::Away { until }, now).unwrap();
+        assert_eq!(left.away.until("bob", now), Some(until));
+        assert_eq!(
+            left.state,
+            State::WaitingForNext {
+                next: Some("carol".into())
+            }
+        );
+
+        let back = transition(left, "bob", Change::Back, now).unwrap();
+        assert!(back.away.is_empty());
+        // Carol was asked already
+        assert_eq!(
+            back.state,
+            State::WaitingForNext {
+                next: Some("carol".into())
+            }
+        );
+
+        assert_eq!(
+            transition(waiting.clone(), "dave", Change::Back, now),
+            Err(Error::NotADriver("dave".into()))
+        );
+        assert_eq!(
+            transition(waiting, "bob", Change::Away { until: now }, now),
+            Err(Error::Past(now))
+        );
+    }
+
+    #[test]
+    fn expires() {
+        let now = Utc::now();
+        let mut away = Away::default();
+        away.0.insert("alice".into(), now - Duration::minutes(1));
+        away.0.insert("bob".into(), now + Duration::minutes(1));
+        assert_eq!(away.until("alice", now), None);
+        assert!(away.until("bob", now).is_some());
+
+        let away = away.expire(now);
+        assert_eq!(away.0.keys().collect::<Vec<_>>(), vec!["bob"]);
+        assert!(away.expire(now + Duration::minutes(2)).is_empty());
+    }
+
+    #[test]
+    fn renames() {
+        let now = Utc::now();
+        let mut away = Away::default();
+        away.0.insert("Anna".into(), now + Duration::hours(1));
+        away.0.insert("anna".into(), now + Duration::hours(2));
+        let away = away.rename(|name| name.to_lowercase());
+        assert_eq!(away.until("anna", now), Some(now + Duration::hours(2)));
+        assert_eq!(away.0.len(), 1);
+    }
+}
@@ -1,40 +1,78 @@
+use crate::git::refname;
+use crate::{deadline, prompt, record};
 use anyhow::Result;
-use dialoguer::Input;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Branches {
     pub branch: String,
     pub base_branch: String,
+    /// Remote of the base branch when it isn't the session remote, as in fork workflows
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_remote: Option<String>,
 }
 
 impl Branches {
     pub fn with_remote(&self, remote: &str) -> Self {
         Self {
             branch: format!("{}/{}", remote, self.branch),
-            base_branch: format!("{}/{}", remote, self.base_branch),
+            base_branch: format!("{}/{}", self.base_remote(remote), self.base_branch),
+            base_remote: self.base_remote.clone(),
         }
     }
+
+    /// The feature branch named `name` with `prefix` before it, unless it has it already
+    pub fn prefixed(name: &str, prefix: Option<&str>) -> String {
+        match prefix {
+            Some(prefix) if !name.starts_with(prefix) => format!("{}{}", prefix, name),
+            _ => name.to_string(),
+        }
+    }
+
+    pub fn base_remote<'a>(&'a self, remote: &'a str) -> &'a str {
+        self.base_remote.as_deref().unwrap_or(remote)
+    }
+
+    /// Splits `upstream/main` into remote and branch when the prefix is a known remote.
+    /// The remote is left out when it is the session remote so nothing changes for them.
+    pub fn parse_base(base: &str, remotes: &[String], remote: &str) -> (Option<String>, String) {
+        match base.split_once('/') {
+            Some((prefix, branch)) if remotes.iter().any(|r| r == prefix) && !branch.is_empty() => {
+                if prefix == remote {
+                    (None, branch.to_string())
+                } else {
+                    (Some(prefix.to_string()), branch.to_string())
+                }
+            }
+            _ => (None, base.to_string()),
+        }
+    }
+
     pub fn ask(default: Branches) -> Result<Branches> {
+        if let Some(default) = deadline::answer("Branches", Some(default.clone()))? {
+            return Ok(default);
+        }
         let default_base_branch = Branches::default().base_branch;
         if default.base_branch != default_base_branch {
             log::info!("Note that you are not on {}", default_base_branch)
         }
 
-        let base_branch = Input::new()
-            .with_prompt("Base branch")
-            .default(default.base_branch)
-            .interact()?;
+        let prompt = prompt::terminal();
+        let base_branch = prompt.text("Base branch", &default.base_branch)?;
 
-        let branch = Input::new()
-            .with_prompt("Feature branch")
-            .default(default.branch)
-            .interact()?;
+        let branch = prompt.input("Feature branch", Some(&default.branch), &|name| {
+            refname::check_branch(name)
+                .map_err(|reason| format!("Not a valid branch name, {}", reason))
+        })?;
 
-        Ok(Branches {
-            branch,
-            base_branch,
-        })
+        Ok(record::answer(
+            "Branches",
+            Branches {
+                branch,
+                base_branch,
+                base_remote: default.base_remote,
+            },
+        ))
     }
 }
 
@@ -43,6 +81,63 @@ impl Default for Branches {
         Self {
             base_branch: "master".to_string(),
             branch: "mob-session".to_string(),
+            base_remote: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prefixed() {
+        assert_eq!(Branches::prefixed("payment-flow", None), "payment-flow");
+        assert_eq!(
+            Branches::prefixed("payment-flow", Some("mob/")),
+            "mob/payment-flow"
+        );
+        assert_eq!(
+            Branches::prefixed("mob/payment-flow", Some("mob/")),
+            "mob/payment-flow"
+        );
+    }
+
+    #[test]
+    fn parse_base() {
+        let remotes = vec!["origin".to_string(), "upstream".to_string()];
+        let parse = |base| Branches::parse_base(base, &remotes, "origin");
+
+        assert_eq!(parse("main"), (None, "main".into()));
+        assert_eq!(parse("origin/main"), (None, "main".into()));
+        assert_eq!(
+            parse("upstream/main"),
+            (Some("upstream".into()), "main".into())
+        );
+        assert_eq!(
+            parse("upstream/release/1.4"),
+            (Some("upstream".into()), "release/1.4".into())
+        );
+        assert_eq!(parse("release/1.4"), (None, "release/1.4".into()));
+        assert_eq!(parse("upstream/"), (None, "upstream/".into()));
+    }
+
+    #[test]
+    fn with_remote() {
+        let branches = Branches {
+            base_branch: "main".into(),
+            ..Branches::default()
+        };
+        let same = branches.with_remote("origin");
+        assert_eq!(same.branch, "origin/mob-session");
+        assert_eq!(same.base_branch, "origin/main");
+
+        let split = Branches {
+            base_remote: Some("upstream".into()),
+            ..branches
         }
+        .with_remote("origin");
+        assert_eq!(split.branch, "origin/mob-session");
+        assert_eq!(split.base_branch, "upstream/main");
     }
 }
@@ -0,0 +1,92 @@
+//! A session packed into one file by `mob export`, for `mob import` to recreate elsewhere.
+
+use super::latest::Session;
+use super::versioned_session::Versioned;
+use serde::{Deserialize, Serialize};
+
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+pub enum Error {
+    #[error("not an exported session: `{0}`")]
+    Format(#[from] serde_json::Error),
+
+    #[error("the session was exported by a newer mob, upgrade to read version `{0}`")]
+    NewerVersion(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Bundle {
+    /// First commit of the exported repository, `None` without one
+    pub root: Option<String>,
+    /// Remote the session was exported from
+    pub remote: String,
+    /// Settings, state and history, with the name of the mob branch in `branches`
+    pub session: Session,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "version")]
+enum VersionedBundle {
+    #[serde(rename = "v1")]
+    V1(Bundle),
+}
+
+impl Bundle {
+    pub fn to_vec(&self) -> Result<Vec<u8>, Error> {
+        Ok(serde_json::to_vec_pretty(&VersionedBundle::V1(
+            self.clone(),
+        ))?)
+    }
+
+    pub fn from_slice(data: &[u8]) -> Result<Bundle, Error> {
+        match serde_json::from_slice::<VersionedBundle>(data) {
+            Ok(VersionedBundle::V1(bundle)) => Ok(bundle),
+            Err(err) => match serde_json::from_slice::<Versioned>(data) {
+                Ok(Versioned {
+                    version: Some(version),
+                }) if version != "v1" => Err(Error::NewerVersion(version)),
+                _ => Err(Error::Format(err)),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::{Drivers, State};
+
+    #[test]
+    fn round_trip() {
+        let bundle = Bundle {
+            root: Some("c0ffee".into()),
+            remote: "origin".into(),
+            session: Session {
+                drivers: Drivers::new(vec!["alice".into()]),
+                state: State::WaitingForNext { next: None },
+                ..Session::default()
+            },
+        };
+        let data = bundle.to_vec().unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&data).unwrap();
+        assert_eq!(json["version"], "v1");
+        assert_eq!(json["session"]["branches"]["branch"], "mob-session");
+        assert_eq!(Bundle::from_slice(&data).unwrap(), bundle);
+    }
+
+    #[test]
+    fn versions() {
+        assert!(matches!(
+            Bundle::from_slice(br#"{"version": "v9", "sessions": []}"#),
+            Err(Error::NewerVersion(version)) if version == "v9"
+        ));
+        assert!(matches!(
+            Bundle::from_slice(br#"{"version": "v1", "remote": 1}"#),
+            Err(Error::Format(_))
+        ));
+        assert!(matches!(
+            Bundle::from_slice(b"PK\x03\x04"),
+            Err(Error::Format(_))
+        ));
+    }
+}
@@ -0,0 +1,142 @@
+//! Claiming the next turn when anyone may take it, so several people don't all prepare to
+//! start. A claim only reserves: whoever claimed still runs `mob start`.
+
+use super::latest::*;
+use chrono::{DateTime, Utc};
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum Error {
+    #[error("There is no session, run mob start")]
+    Stopped,
+
+    #[error("{0} is driving, claim the turn after they ran mob next")]
+    Working(String),
+
+    #[error("The session is paused for {0}, run mob start to resume it")]
+    Paused(String),
+
+    #[error("{0} already claimed the next turn")]
+    Claimed(String),
+
+    #[error("The next turn is already {0}'s, nothing to claim")]
+    Next(String),
+
+    #[error("The next turn is already yours")]
+    Yours,
+
+    #[error("You haven't claimed the next turn")]
+    NotClaimed,
+}
+
+/// `name` reserving the next turn of a session waiting for anyone
+pub fn claim(session: Session, name: &str, now: DateTime<Utc>) -> Result<Session, Error> {
+    match &session.state {
+        State::Stopped => Err(Error::Stopped),
+        State::Working { driver } => Err(Error::Working(driver.clone())),
+        State::Paused { reason, .. } => Err(Error::Paused(reason.clone())),
+        State::WaitingForNext { next: Some(next) } if next == name => Err(Error::Yours),
+        State::WaitingForNext { next: Some(next) } if session.times.claimed.is_some() => {
+            Err(Error::Claimed(next.clone()))
+        }
+        State::WaitingForNext { next: Some(next) } => Err(Error::Next(next.clone())),
+        State::WaitingForNext { next: None } => Ok(Session {
+            state: State::WaitingForNext {
+                next: Some(name.to_string()),
+            },
+            times: Times {
+                claimed: Some(now.into()),
+                ..session.times
+            },
+            ..session
+        }),
+    }
+}
+
+/// `name` giving back a turn they claimed, anyone may take it again
+pub fn unclaim(session: Session, name: &str) -> Result<Session, Error> {
+    match &session.state {
+        State::WaitingForNext { next: Some(next) } if session.times.claimed.is_some() => {
+            if next != name {
+                return Err(Error::Claimed(next.clone()));
+            }
+            Ok(Session {
+                state: State::WaitingForNext { next: None },
+                times: Times {
+                    claimed: None,
+                    ..session.times
+                },
+                ..session
+            })
+        }
+        _ => Err(Error::NotClaimed),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(state: State) -> Session {
+        Session {
+            drivers: Drivers::new(vec!["alice".into(), "bob".into()]),
+            state,
+            ..Session::default()
+        }
+    }
+
+    fn waiting(next: Option<&str>) -> State {
+        State::WaitingForNext {
+            next: next.map(String::from),
+        }
+    }
+
+    #[test]
+    fn claims_only_a_turn_for_anyone() {
+        let now = Utc::now();
+        let claimed = claim(session(waiting(None)), "bob", now).unwrap();
+        assert_eq!(claimed.state, waiting(Some("bob")));
+        assert_eq!(claimed.times.claimed, Some(now.into()));
+
+        let cases = vec![
+            (session(State::Stopped), Error::Stopped),
+            (
+                session(State::Working {
+                    driver: "alice".into(),
+                }),
+                Error::Working("alice".into()),
+            ),
+            (claimed.clone(), Error::Claimed("bob".into())),
+            (session(waiting(Some("bob"))), Error::Next("bob".into())),
+            (session(waiting(Some("carol"))), Error::Yours),
+        ];
+        for (session, expected) in cases {
+            assert_eq!(
+                claim(session.clone(), "carol", now),
+                Err(expected),
+                "{:?}",
+                session.state
+            );
+        }
+    }
+
+    #[test]
+    fn unclaims_own_claims() {
+        let now = Utc::now();
+        let claimed = claim(session(waiting(None)), "bob", now).unwrap();
+        assert_eq!(
+            unclaim(claimed.clone(), "alice"),
+            Err(Error::Claimed("bob".into()))
+        );
+
+        let released = unclaim(claimed, "bob").unwrap();
+        assert_eq!(released.state, waiting(None));
+        assert_eq!(released.times.claimed, None);
+
+        assert_eq!(
+            unclaim(session(waiting(Some("bob"))), "bob"),
+            Err(Error::NotClaimed)
+        );
+        assert_eq!(unclaim(released, "bob"), Err(Error::NotClaimed));
+    }
+}
@@ -0,0 +1,190 @@
+use super::latest::*;
+use crate::timekeeping::Minutes;
+
+/// Describes what changed between two versions of a session, from the point of view of `me`
+pub fn describe(old: &Session, new: &Session, me: &str) -> Vec<String> {
+    let name = |name: &str| -> String {
+        if name == me {
+            "you".to_string()
+        } else {
+            shown(name)
+        }
+    };
+
+    let mut changes = vec![];
+
+    if old.state != new.state {
+        changes.push(match (&old.state, &new.state) {
+            (State::Working { driver: from }, State::Working { driver }) => {
+                format!("{} took over from {}", name(driver), name(from))
+            }
+            (_, State::Working { driver }) => format!("{} started driving", name(driver)),
+            (old_state, State::WaitingForNext { next }) => {
+                let who = match old_state {
+                    State::Working { driver } => name(driver),
+                    _ => "someone".to_string(),
+                };
+                let next = match next {
+                    Some(next) => name(next),
+                    None => "anyone".to_string(),
+                };
+                format!("{} ran next, waiting for {}", who, next)
+            }
+            (_, State::Stopped) => "the session was stopped".to_string(),
+            (_, State::Paused { reason, .. }) => format!("the session was paused for {}", reason),
+        });
+    }
+
+    let old_drivers = old.drivers.all();
+    let new_drivers = new.drivers.all();
+    let joined: Vec<String> = new_drivers
+        .iter()
+        .filter(|driver| !old_drivers.contains(driver))
+        .map(|driver| name(driver))
+        .collect();
+    let left: Vec<String> = old_drivers
+        .iter()
+        .filter(|driver| !new_drivers.contains(driver))
+        .map(|driver| name(driver))
+        .collect();
+    if !joined.is_empty() {
+        changes.push(format!("{} joined", joined.join(", ")));
+    }
+    if !left.is_empty() {
+        changes.push(format!("{} left", left.join(", ")));
+    }
+    if joined.is_empty() && left.is_empty() && old_drivers != new_drivers {
+        changes.push(format!("drivers reordered to {}", new_drivers.join(", ")));
+    }
+
+    if old.branches.branch != new.branches.branch {
+        changes.push(format!("branch is now {}", new.branches.branch));
+    }
+    if old.branches.base_branch != new.branches.base_branch {
+        changes.push(format!("base branch is now {}", new.branches.base_branch));
+    }
+
+    if let (Some(old), Some(new)) = (&old.settings, &new.settings) {
+        changes.extend(describe_settings(old, new));
+    }
+
+    changes
+}
+
+/// Describes changed durations, as shown before applying a preset
+pub fn describe_settings(old: &Settings, new: &Settings) -> Vec<String> {
+    let minutes = |duration: Option<Minutes>| match duration {
+        Some(minutes) if minutes.get() > 0 => format!("{} minutes", minutes),
+        _ => "none".to_string(),
+    };
+
+    let mut changes = vec![];
+    if old.work_duration != new.work_duration {
+        changes.push(format!(
+            "work duration changed from {} to {} minutes",
+            old.work_duration, new.work_duration
+        ));
+    }
+    if minutes(old.break_duration) != minutes(new.break_duration) {
+        changes.push(format!(
+            "break changed from {} to {}",
+            minutes(old.break_duration),
+            minutes(new.break_duration)
+        ));
+    }
+    if old.min_turn_minutes != new.min_turn_minutes {
+        changes.push(format!(
+            "minimum turn changed from {} to {}",
+            minutes(Some(old.min_turn_minutes)),
+            minutes(Some(new.min_turn_minutes))
+        ));
+    }
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(drivers: &[&str], state: State) -> Session {
+        Session {
+            drivers: Drivers::new(drivers.iter().map(|d| d.to_string()).collect()),
+            settings: Some(Settings::default()),
+            state,
+            ..Session::default()
+        }
+    }
+
+    fn working(driver: &str) -> State {
+        State::Working {
+            driver: driver.into(),
+        }
+    }
+
+    #[test]
+    fn unchanged() {
+        let old = session(&["alice", "bob"], working("alice"));
+        assert!(describe(&old, &old.clone(), "alice").is_empty());
+    }
+
+    #[test]
+    fn took_over() {
+        let old = session(&["alice", "bob"], working("alice"));
+        let new = session(&["alice", "bob"], working("bob"));
+        assert_eq!(
+            describe(&old, &new, "alice"),
+            vec!["bob took over from you"]
+        );
+    }
+
+    #[test]
+    fn next_and_join() {
+        let old = session(&["alice"], working("alice"));
+        let new = session(
+            &["alice", "bob"],
+            State::WaitingForNext {
+                next: Some("bob".into()),
+            },
+        );
+        assert_eq!(
+            describe(&old, &new, "carol"),
+            vec!["alice ran next, waiting for bob", "bob joined"]
+        );
+    }
+
+    #[test]
+    fn reordered_and_settings() {
+        let old = session(&["alice", "bob"], State::Stopped);
+        let mut new = session(&["bob", "alice"], State::Stopped);
+        new.settings.as_mut().unwrap().work_duration = Minutes(15);
+        assert_eq!(
+            describe(&old, &new, "alice"),
+            vec![
+                "drivers reordered to bob, alice",
+                "work duration changed from 10 to 15 minutes"
+            ]
+        );
+    }
+
+    #[test]
+    fn settings() {
+        let old = Settings::default();
+        let new = Settings {
+            work_duration: Minutes(25),
+            break_duration: Some(Minutes(5)),
+            ..old.clone()
+        };
+        assert_eq!(
+            describe_settings(&old, &new),
+            vec![
+                "work duration changed from 10 to 25 minutes",
+                "break changed from none to 5 minutes"
+            ]
+        );
+        let no_break = Settings {
+            break_duration: Some(Minutes(0)),
+            ..old.clone()
+        };
+        assert!(describe_settings(&old, &no_break).is_empty());
+    }
+}
@@ -0,0 +1,146 @@
+//! The session is stored as two documents. Turns only change the small progress one, so
+//! concurrent `mob next`s don't rewrite or conflict on settings and drivers.
+
+use super::latest::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Version of both documents, older sessions are a single document
+pub const VERSION: &str = "v2";
+
+/// What rarely changes
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Setup {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub drivers: Drivers,
+    pub branches: Branches,
+    pub settings: Option<Settings>,
+    #[serde(default, skip_serializing_if = "Observers::is_empty")]
+    pub observers: Observers,
+}
+
+/// What changes every turn
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Progress {
+    pub state: State,
+    #[serde(default)]
+    pub times: Times,
+    #[serde(default)]
+    pub history: History,
+    #[serde(default, skip_serializing_if = "Away::is_empty")]
+    pub away: Away,
+    #[serde(default, skip_serializing_if = "BreakAnswers::is_empty")]
+    pub answers: BreakAnswers,
+    #[serde(default, skip_serializing_if = "NewFiles::is_empty")]
+    pub new_files: NewFiles,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tip: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub tips: BTreeMap<String, String>,
+}
+
+impl Default for Progress {
+    fn default() -> Self {
+        Self {
+            state: State::Stopped,
+            times: Times::default(),
+            history: History::default(),
+            away: Away::default(),
+            answers: BreakAnswers::default(),
+            new_files: NewFiles::default(),
+            tip: None,
+            tips: BTreeMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "version")]
+pub enum VersionedSetup {
+    #[serde(rename = "v2")]
+    V2(Setup),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "version")]
+pub enum VersionedProgress {
+    #[serde(rename = "v2")]
+    V2(Progress),
+}
+
+pub fn split(session: Session) -> (Setup, Progress) {
+    (
+        Setup {
+            id: session.id,
+            drivers: session.drivers,
+            branches: session.branches,
+            settings: session.settings,
+            observers: session.observers,
+        },
+        Progress {
+            state: session.state,
+            times: session.times,
+            history: session.history,
+            away: session.away,
+            answers: session.answers,
+            new_files: session.new_files,
+            tip: session.tip,
+            tips: session.tips,
+        },
+    )
+}
+
+pub fn compose(setup: Setup, progress: Progress) -> Session {
+    Session {
+        id: setup.id,
+        drivers: setup.drivers,
+        branches: setup.branches,
+        settings: setup.settings,
+        state: progress.state,
+        times: progress.times,
+        history: progress.history,
+        away: progress.away,
+        observers: setup.observers,
+        answers: progress.answers,
+        new_files: progress.new_files,
+        tip: progress.tip,
+        tips: progress.tips,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_and_compose() {
+        let session = Session {
+            drivers: Drivers::new(vec!["alice".into(), "bob".into()]),
+            settings: Some(Settings::default()),
+            state: State::Working {
+                driver: "bob".into(),
+            },
+            times: Times {
+                started: Some(chrono::Utc::now().into()),
+                ..Times::default()
+            },
+            ..Session::default()
+        };
+        let (setup, progress) = split(session.clone());
+        assert_eq!(setup.drivers, session.drivers);
+        assert_eq!(progress.state, session.state);
+        assert_eq!(compose(setup, progress), session);
+    }
+
+    #[test]
+    fn documents_carry_their_version() {
+        let (setup, progress) = split(Session::default());
+        for json in [
+            serde_json::to_value(VersionedSetup::V2(setup)).unwrap(),
+            serde_json::to_value(VersionedProgress::V2(progress)).unwrap(),
+        ] {
+            assert_eq!(json["version"], VERSION);
+        }
+    }
+}
@@ -1,8 +1,47 @@
+use super::away::Away;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Drivers(Vec<String>);
 
+/// Another driver would make the mob larger than `limits.max_drivers`
+#[derive(thiserror::Error, Debug, PartialEq)]
+#[error("The mob would have {drivers} drivers, limits.max_drivers allows {max}")]
+pub struct TooLarge {
+    pub drivers: usize,
+    pub max: usize,
+}
+
+/// Fewer drivers than `limits.min_drivers` requires
+#[derive(thiserror::Error, Debug, PartialEq)]
+#[error("limits.min_drivers requires {min} drivers, the mob would have {drivers}")]
+pub struct TooSmall {
+    pub drivers: usize,
+    pub min: usize,
+}
+
+/// Words mob tells for nobody in particular, like waiting for anyone to start. A driver of
+/// that name couldn't be told apart from them.
+pub const PLACEHOLDERS: &[&str] = &["anyone", "anybody", "someone", "somebody", "nobody"];
+
+/// Whether `name` is one of the placeholder words, in any case
+pub fn placeholder(name: &str) -> bool {
+    let name = name.trim().trim_end_matches('!');
+    PLACEHOLDERS
+        .iter()
+        .any(|word| word.eq_ignore_ascii_case(name))
+}
+
+/// `name` for messages, quoted when it is a placeholder word so a driver called anyone
+/// isn't read as nobody in particular
+pub fn shown(name: &str) -> String {
+    match placeholder(name) {
+        true => format!("\"{}\"", name),
+        false => name.to_string(),
+    }
+}
+
 impl Drivers {
     pub fn new(drivers: Vec<String>) -> Self {
         Drivers(drivers)
@@ -25,26 +64,64 @@ impl Drivers {
         self
     }
 
+    /// Inserts `name` like `insert` unless that makes more than `max` drivers
+    pub fn admit(
+        self,
+        after: Option<String>,
+        name: &str,
+        max: Option<usize>,
+    ) -> Result<Self, TooLarge> {
+        match max {
+            Some(max) if !self.contains(name) && self.0.len() >= max => Err(TooLarge {
+                drivers: self.0.len() + 1,
+                max,
+            }),
+            _ => Ok(self.insert(after, name)),
+        }
+    }
+
+    /// Fails when the mob has fewer than `min` drivers
+    pub fn require(&self, min: Option<usize>) -> Result<(), TooSmall> {
+        match min {
+            Some(min) if self.0.len() < min => Err(TooSmall {
+                drivers: self.0.len(),
+                min,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Removes `name` like `remove` unless that leaves fewer than `min` drivers
+    pub fn dismiss(self, name: &str, min: Option<usize>) -> Result<Self, TooSmall> {
+        let drivers = self.remove(name);
+        drivers.require(min)?;
+        Ok(drivers)
+    }
+
     pub fn contains(&self, name: &str) -> bool {
         self.0.contains(&name.to_string())
     }
 
-    pub fn next(&self, current: &str) -> Option<String> {
+    /// The driver after `current`, skipping those who are away unless everyone else is
+    pub fn next(&self, current: &str, away: &Away, now: DateTime<Utc>) -> Option<String> {
         match self.0.len() {
             0 => panic!("Next driver called before anyone started"),
             1 => None,
-            _ => Some(
-                self.0
+            len => {
+                let index = self
+                    .0
                     .iter()
                     .position(|name| name == current)
-                    .map(|index| {
-                        let next_index = (index + 1) % self.0.len();
-                        self.0[next_index].clone()
-                    })
                     .unwrap_or_else(|| {
                         panic!("Could not find current driver {} in drivers", current)
-                    }),
-            ),
+                    });
+                let after = (1..len).map(|step| &self.0[(index + step) % len]);
+                after
+                    .clone()
+                    .find(|name| away.until(name, now).is_none())
+                    .or_else(|| after.clone().next())
+                    .cloned()
+            }
         }
     }
 
@@ -68,3 +145,110 @@ impl Default for Drivers {
         Drivers(vec![])
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::away::{transition, Change};
+    use crate::session::{Session, State};
+    use chrono::{Duration, TimeZone};
+
+    fn now() -> DateTime<Utc> {
+        Utc.ymd(2021, 6, 1).and_hms(10, 0, 0)
+    }
+
+    fn drivers() -> Drivers {
+        Drivers::new(vec!["alice".into(), "bob".into(), "carol".into()])
+    }
+
+    #[test]
+    fn quotes_drivers_named_like_placeholders() {
+        assert!(placeholder("anyone"));
+        assert!(placeholder("Anyone!"));
+        assert!(placeholder(" nobody "));
+        assert!(!placeholder("anyonea"));
+        assert!(!placeholder("alice"));
+        assert_eq!(shown("anyone"), "\"anyone\"");
+        assert_eq!(shown("alice"), "alice");
+    }
+
+    #[test]
+    fn admits_up_to_the_limit() {
+        assert_eq!(
+            drivers().admit(None, "dave", Some(3)),
+            Err(TooLarge { drivers: 4, max: 3 })
+        );
+        assert_eq!(
+            drivers().admit(None, "bob", Some(3)).unwrap().all(),
+            vec!["alice", "bob", "carol"]
+        );
+        assert_eq!(
+            drivers().admit(None, "dave", Some(4)).unwrap().all().len(),
+            4
+        );
+        assert_eq!(drivers().admit(None, "dave", None).unwrap().all().len(), 4);
+    }
+
+    #[test]
+    fn keeps_the_minimum() {
+        assert_eq!(drivers().require(Some(3)), Ok(()));
+        assert_eq!(
+            drivers().require(Some(4)),
+            Err(TooSmall { drivers: 3, min: 4 })
+        );
+        assert_eq!(drivers().require(None), Ok(()));
+        assert_eq!(
+            drivers().dismiss("bob", Some(2)).unwrap().all(),
+            vec!["alice", "carol"]
+        );
+        assert_eq!(
+            drivers().dismiss("bob", Some(3)),
+            Err(TooSmall { drivers: 2, min: 3 })
+        );
+        assert_eq!(drivers().dismiss("bob", None).unwrap().all().len(), 2);
+    }
+
+    fn away(names: &[&str]) -> Away {
+        let session = Session {
+            drivers: drivers(),
+            state: State::Stopped,
+            ..Session::default()
+        };
+        let now = now();
+        let until = now + Duration::hours(1);
+        names
+            .iter()
+            .fold(session, |session, name| {
+                transition(session, name, Change::Away { until }, now).unwrap()
+            })
+            .away
+    }
+
+    #[test]
+    fn next_skips_who_is_away() {
+        let cases = vec![
+            ("alice", vec![], Some("bob")),
+            ("carol", vec![], Some("alice")),
+            ("alice", vec!["bob"], Some("carol")),
+            // Around the end of the list
+            ("bob", vec!["carol"], Some("alice")),
+            ("carol", vec!["alice"], Some("bob")),
+            // Everyone else away, the rotation goes on as if nobody was
+            ("alice", vec!["bob", "carol"], Some("bob")),
+            ("alice", vec!["alice", "bob", "carol"], Some("bob")),
+            // Only the one handing over is around
+            ("bob", vec!["alice", "carol"], Some("carol")),
+        ];
+        for (current, names, expected) in cases {
+            assert_eq!(
+                drivers().next(current, &away(&names), now()).as_deref(),
+                expected,
+                "{} with {:?} away",
+                current,
+                names
+            );
+        }
+        let alone = Drivers::new(vec!["alice".into()]);
+        assert_eq!(alone.next("alice", &Away::default(), now()), None);
+    }
+}
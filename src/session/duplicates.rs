@@ -0,0 +1,247 @@
+//! Drivers that are the same person under names differing only in case or spaces, as old
+//! versions of mob let in.
+
+use super::latest::*;
+
+/// The form that tells names apart: trimmed, single spaces and lowercase
+pub fn normalize(name: &str) -> String {
+    name.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Duplicate {
+    /// The name that is kept
+    pub canonical: String,
+    /// All names of the driver in rotation order, the canonical one included
+    pub variants: Vec<String>,
+}
+
+/// Groups of drivers with the same normalized name. The kept name is the one without stray
+/// spaces that drove last, or the first of those in the rotation.
+pub fn find(session: &Session) -> Vec<Duplicate> {
+    let mut groups: Vec<(String, Vec<String>)> = vec![];
+    for driver in session.drivers.all() {
+        let key = normalize(&driver);
+        match groups.iter_mut().find(|(existing, _)| *existing == key) {
+            Some((_, variants)) => variants.push(driver),
+            None => groups.push((key, vec![driver])),
+        }
+    }
+
+    groups
+        .into_iter()
+        .filter(|(_, variants)| variants.len() > 1)
+        .map(|(_, variants)| {
+            let tidy: Vec<&String> = variants
+                .iter()
+                .filter(|name| name.split_whitespace().collect::<Vec<_>>().join(" ") == **name)
+                .collect();
+            let candidates = if tidy.is_empty() {
+                variants.iter().collect()
+            } else {
+                tidy
+            };
+            // max_by_key keeps the last of equals, reversing keeps the first in rotation
+            let canonical = candidates
+                .into_iter()
+                .rev()
+                .max_by_key(|name| session.history.last_driven(name))
+                .expect("groups have variants")
+                .clone();
+            Duplicate {
+                canonical,
+                variants,
+            }
+        })
+        .collect()
+}
+
+/// Replaces every variant by its canonical name in the rotation, the state and the history
+pub fn merge(session: Session, duplicates: &[Duplicate]) -> Session {
+    let canonical = |name: &str| -> String {
+        duplicates
+            .iter()
+            .find(|duplicate| duplicate.variants.iter().any(|variant| variant == name))
+            .map(|duplicate| duplicate.canonical.clone())
+            .unwrap_or_else(|| name.to_string())
+    };
+
+    let mut drivers: Vec<String> = vec![];
+    for driver in session.drivers.all() {
+        let driver = canonical(&driver);
+        if !drivers.contains(&driver) {
+            drivers.push(driver);
+        }
+    }
+
+    let state = match session.state {
+        State::Working { driver } => State::Working {
+            driver: canonical(&driver),
+        },
+        State::WaitingForNext { next } => State::WaitingForNext {
+            next: next.map(|next| canonical(&next)),
+        },
+        state => state,
+    };
+
+    let history = History::new(
+        session
+            .history
+            .records()
+            .iter()
+            .map(|record| match record {
+                Record::Turn { driver, at } => Record::Turn {
+                    driver: canonical(driver),
+                    at: *at,
+                },
+                Record::Break { at } => Record::Break { at: *at },
+                Record::Sync { driver, at } => Record::Sync {
+                    driver: canonical(driver),
+                    at: *at,
+                },
+                Record::ShortTurn { driver, at } => Record::ShortTurn {
+                    driver: canonical(driver),
+                    at: *at,
+                },
+                Record::Outside {
+                    authors,
+                    commits,
+                    at,
+                } => Record::Outside {
+                    authors: authors.clone(),
+                    commits: *commits,
+                    at: *at,
+                },
+                Record::Reorder { by, drivers, at } => Record::Reorder {
+                    by: canonical(by),
+                    drivers: drivers.iter().map(|driver| canonical(driver)).collect(),
+                    at: *at,
+                },
+                Record::Identified { id, at } => Record::Identified {
+                    id: id.clone(),
+                    at: *at,
+                },
+            })
+            .collect(),
+    );
+
+    Session {
+        drivers: Drivers::new(drivers),
+        state,
+        history,
+        away: session.away.rename(canonical),
+        ..session
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn messy(drivers: &[&str], turns: &[&str], state: State) -> Session {
+        Session {
+            drivers: Drivers::new(drivers.iter().map(|d| d.to_string()).collect()),
+            history: History::of(turns),
+            state,
+            ..Session::default()
+        }
+    }
+
+    #[test]
+    fn normalizes() {
+        assert_eq!(normalize(" Anna  Berg "), "anna berg");
+        assert_eq!(normalize("anna\tberg"), "anna berg");
+        assert_eq!(normalize("ANNA"), "anna");
+    }
+
+    #[test]
+    fn finds_and_picks_the_kept_name() {
+        let session = messy(
+            &["anna", "bob", "Anna", "anna ", "carl", "Bob"],
+            &["Anna", "bob", "anna ", "Bob", "bob"],
+            State::Stopped,
+        );
+        assert_eq!(
+            find(&session),
+            vec![
+                // `anna ` drove later than Anna but has a stray space
+                Duplicate {
+                    canonical: "Anna".into(),
+                    variants: vec!["anna".into(), "Anna".into(), "anna ".into()],
+                },
+                Duplicate {
+                    canonical: "bob".into(),
+                    variants: vec!["bob".into(), "Bob".into()],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn without_history_the_first_wins() {
+        let found = find(&messy(&[" anna", "Anna", "anna"], &[], State::Stopped));
+        assert_eq!(found[0].canonical, "Anna");
+        let found = find(&messy(&[" anna", "anna  "], &[], State::Stopped));
+        assert_eq!(found[0].canonical, " anna");
+        assert!(find(&messy(&["anna", "bob"], &["anna"], State::Stopped)).is_empty());
+    }
+
+    #[test]
+    fn merges_everywhere() {
+        let session = messy(
+            &["anna", "bob", "Anna", "anna "],
+            &["anna", "bob", "anna "],
+            State::Working {
+                driver: "anna".into(),
+            },
+        );
+        let duplicates = find(&session);
+        let merged = merge(session.clone(), &duplicates);
+
+        assert_eq!(merged.drivers.all(), vec!["anna", "bob"]);
+        assert_eq!(
+            merged.state,
+            State::Working {
+                driver: "anna".into()
+            }
+        );
+        let drivers: Vec<&str> = merged
+            .history
+            .records()
+            .iter()
+            .filter_map(|record| match record {
+                Record::Turn { driver, .. } => Some(driver.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(drivers, vec!["anna", "bob", "anna"]);
+        assert_eq!(merged.history.consecutive(), Some(("anna", 1)));
+        assert!(find(&merged).is_empty());
+    }
+
+    #[test]
+    fn fixes_next_pointing_at_a_variant() {
+        let session = messy(
+            &["Anna", "bob", "anna "],
+            &["Anna"],
+            State::WaitingForNext {
+                next: Some("anna ".into()),
+            },
+        );
+        let merged = merge(session.clone(), &find(&session));
+        assert_eq!(
+            merged.state,
+            State::WaitingForNext {
+                next: Some("Anna".into())
+            }
+        );
+        assert_eq!(
+            merged.drivers.next("bob", &Default::default(), Utc::now()),
+            Some("Anna".into())
+        );
+    }
+}
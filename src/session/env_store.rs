@@ -0,0 +1,48 @@
+use super::latest::{Session, State};
+use super::session_store::{Result, Store};
+use crate::env::{self, ENV_FILE};
+use crate::state_dir::StateDir;
+
+/// Keeps the environment file in the state dir up to date with every save, and removes it
+/// once the session stopped.
+pub struct EnvStore<'a> {
+    store: &'a dyn Store,
+    state_dir: StateDir,
+}
+
+impl<'a> EnvStore<'a> {
+    pub fn new(store: &'a impl Store, state_dir: StateDir) -> Self {
+        EnvStore { store, state_dir }
+    }
+
+    fn forget(&self) {
+        self.state_dir
+            .remove(ENV_FILE)
+            .unwrap_or_else(|err| log::trace!("Could not remove {}: {}", ENV_FILE, err));
+    }
+}
+
+impl<'a> Store for EnvStore<'a> {
+    fn load(&self) -> Result<Session> {
+        self.store.load()
+    }
+
+    fn save(&self, session: Session) -> Result<()> {
+        self.store.save(session.clone())?;
+        if session.state == State::Stopped {
+            self.forget();
+            return Ok(());
+        }
+        let exports = env::exports(&env::variables(&session), None);
+        self.state_dir
+            .replace(ENV_FILE, exports.as_bytes())
+            .unwrap_or_else(|err| log::trace!("Could not write {}: {}", ENV_FILE, err));
+        Ok(())
+    }
+
+    fn clean(&self) -> Result<()> {
+        self.store.clean()?;
+        self.forget();
+        Ok(())
+    }
+}
@@ -0,0 +1,190 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// How many records are kept, the oldest are dropped first
+const LIMIT: usize = 100;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind")]
+pub enum Record {
+    /// Someone ran `mob start`
+    Turn {
+        driver: String,
+        at: DateTime<Utc>,
+    },
+    Break {
+        at: DateTime<Utc>,
+    },
+    /// Someone pushed their work with `mob sync` and kept driving
+    Sync {
+        driver: String,
+        at: DateTime<Utc>,
+    },
+    /// Someone ran `mob next` before the turn lasted `settings.min_turn_minutes`
+    ShortTurn {
+        driver: String,
+        at: DateTime<Utc>,
+    },
+    /// Commits reached the mob branch with plain git while nobody drove
+    Outside {
+        authors: Vec<String>,
+        commits: usize,
+        at: DateTime<Utc>,
+    },
+    /// Someone put the rotation in a new order with `mob drivers reorder`
+    Reorder {
+        by: String,
+        drivers: Vec<String>,
+        at: DateTime<Utc>,
+    },
+    /// A session from before ids got its id when it was next saved
+    Identified {
+        id: String,
+        at: DateTime<Utc>,
+    },
+}
+
+impl Record {
+    pub fn at(&self) -> DateTime<Utc> {
+        match self {
+            Record::Turn { at, .. }
+            | Record::Break { at }
+            | Record::Sync { at, .. }
+            | Record::ShortTurn { at, .. }
+            | Record::Outside { at, .. }
+            | Record::Reorder { at, .. }
+            | Record::Identified { at, .. } => *at,
+        }
+    }
+}
+
+/// The recent turns and breaks of the session, oldest first
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct History(Vec<Record>);
+
+impl History {
+    pub fn new(records: Vec<Record>) -> Self {
+        History(records)
+    }
+
+    /// A minute apart from 1 June 2021 at 9:00 UTC+1, each entry a turn of that driver, or
+    /// `"break"` or alice's `"sync"`, for tests
+    #[cfg(test)]
+    pub fn of(entries: &[&str]) -> Self {
+        use crate::clock::{Clock, FakeClock};
+        let start = FakeClock::on_june_first(9, 0).now_utc();
+        History(
+            entries
+                .iter()
+                .enumerate()
+                .map(|(minute, entry)| {
+                    let at = start + chrono::Duration::minutes(minute as i64);
+                    match *entry {
+                        "break" => Record::Break { at },
+                        "sync" => Record::Sync {
+                            driver: "alice".into(),
+                            at,
+                        },
+                        driver => Record::Turn {
+                            driver: driver.into(),
+                            at,
+                        },
+                    }
+                })
+                .collect(),
+        )
+    }
+
+    pub fn push(mut self, record: Record) -> Self {
+        self.0.push(record);
+        if self.0.len() > LIMIT {
+            self.0.drain(..self.0.len() - LIMIT);
+        }
+        self
+    }
+
+    pub fn records(&self) -> &[Record] {
+        &self.0
+    }
+
+    /// Who drove the latest turns and how many in a row, breaks and syncs in between don't
+    /// count
+    pub fn consecutive(&self) -> Option<(&str, usize)> {
+        let mut turns = self.0.iter().rev().filter_map(|record| match record {
+            Record::Turn { driver, .. } => Some(driver.as_str()),
+            _ => None,
+        });
+        let last = turns.next()?;
+        Some((last, 1 + turns.take_while(|driver| *driver == last).count()))
+    }
+
+    /// When `driver` started their latest turn
+    pub fn last_driven(&self, driver: &str) -> Option<DateTime<Utc>> {
+        self.0.iter().rev().find_map(|record| match record {
+            Record::Turn { driver: name, at } if name == driver => Some(*at),
+            _ => None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Case<'a> = (&'a [&'a str], Option<(&'a str, usize)>);
+
+    #[test]
+    fn consecutive() {
+        let cases: Vec<Case> = vec![
+            (&[], None),
+            (&["break"], None),
+            (&["alice"], Some(("alice", 1))),
+            (&["alice", "bob"], Some(("bob", 1))),
+            (&["bob", "alice", "alice"], Some(("alice", 2))),
+            (&["alice", "alice", "bob", "alice"], Some(("alice", 1))),
+            // Breaks and syncs don't end a streak
+            (&["alice", "break", "alice", "break"], Some(("alice", 2))),
+            (&["alice", "sync", "alice", "sync"], Some(("alice", 2))),
+            (
+                &["bob", "alice", "break", "alice", "alice"],
+                Some(("alice", 3)),
+            ),
+            (&["break", "break", "bob"], Some(("bob", 1))),
+        ];
+        for (entries, expected) in cases {
+            assert_eq!(
+                History::of(entries).consecutive(),
+                expected,
+                "{:?}",
+                entries
+            );
+        }
+    }
+
+    #[test]
+    fn last_driven() {
+        let history = History::of(&["alice", "bob", "alice", "break"]);
+        let at = |index| match &history.records()[index] {
+            Record::Turn { at, .. } => Some(*at),
+            _ => None,
+        };
+        assert_eq!(history.last_driven("alice"), at(2));
+        assert_eq!(history.last_driven("bob"), at(1));
+        assert_eq!(history.last_driven("carol"), None);
+    }
+
+    #[test]
+    fn keeps_the_latest() {
+        let mut history = History::default();
+        for minute in 0..LIMIT + 10 {
+            history = history.push(Record::Turn {
+                driver: format!("driver {}", minute),
+                at: Utc::now(),
+            });
+        }
+        assert_eq!(history.records().len(), LIMIT);
+        assert_eq!(history.consecutive(), Some(("driver 109", 1)));
+        assert_eq!(history.last_driven("driver 9"), None);
+        assert!(history.last_driven("driver 10").is_some());
+    }
+}
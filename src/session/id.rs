@@ -0,0 +1,90 @@
+//! Ids that tell sessions apart in events, reports, status and commits. The branch can be
+//! renamed mid-session, the id stays from `mob start` until the session ends.
+
+use super::latest::{Record, Session, State};
+use crate::timekeeping::Instant;
+use chrono::{DateTime, Utc};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+/// Hex digits of an id, short enough to read out and plenty for the sessions of a repository
+const LENGTH: usize = 12;
+
+/// The id of the session `driver` starts on `branch` at `at`
+pub fn generate(branch: &str, driver: &str, at: DateTime<Utc>) -> String {
+    let mut hasher = DefaultHasher::new();
+    (branch, driver, at.timestamp(), at.timestamp_subsec_nanos()).hash(&mut hasher);
+    format!("{:016x}", hasher.finish())[..LENGTH].to_string()
+}
+
+/// `session` with an id. Sessions started before there were ids get one from when and
+/// where they started, noted in their history at `now`.
+pub fn assign(session: Session, now: DateTime<Utc>) -> Session {
+    if session.id.is_some() || session.state == State::Stopped {
+        return session;
+    }
+    let started = session
+        .times
+        .started
+        .map(Instant::utc)
+        .or_else(|| session.history.records().first().map(Record::at))
+        .unwrap_or(now);
+    let id = generate(&session.branches.branch, "", started);
+    log::debug!("The session has no id yet, it is {} from now on", id);
+    Session {
+        history: session.history.push(Record::Identified {
+            id: id.clone(),
+            at: now,
+        }),
+        id: Some(id),
+        ..session
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::History;
+    use chrono::{Duration, TimeZone};
+
+    #[test]
+    fn generates_short_ids() {
+        let at = Utc.ymd(2021, 6, 1).and_hms(9, 0, 0);
+        let id = generate("mob-session", "alice", at);
+        assert_eq!(id.len(), LENGTH);
+        assert!(id.chars().all(|c| c.is_ascii_hexdigit()), "{}", id);
+        assert_eq!(generate("mob-session", "alice", at), id);
+        assert_ne!(generate("mob-session", "bob", at), id);
+        assert_ne!(generate("mob-other", "alice", at), id);
+        assert_ne!(
+            generate("mob-session", "alice", at + Duration::nanoseconds(1)),
+            id
+        );
+    }
+
+    #[test]
+    fn assigns_ids_to_older_sessions() {
+        let now = Utc.ymd(2021, 6, 1).and_hms(9, 0, 0);
+        let turn = Record::Turn {
+            driver: "alice".into(),
+            at: now - Duration::minutes(30),
+        };
+        let older = Session {
+            state: State::WaitingForNext { next: None },
+            history: History::default().push(turn.clone()),
+            ..Session::default()
+        };
+        let assigned = assign(older.clone(), now);
+        let id = assigned.id.clone().unwrap();
+        assert_eq!(
+            assigned.history.records(),
+            [turn, Record::Identified { id, at: now }]
+        );
+        // Once is enough, and the same session gets the same id
+        assert_eq!(assign(assigned.clone(), now + Duration::hours(1)), assigned);
+        assert_eq!(assign(older, now + Duration::hours(1)).id, assigned.id);
+
+        // A stopped session gets one when it starts
+        assert_eq!(assign(Session::default(), now).id, None);
+    }
+}
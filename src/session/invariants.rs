@@ -0,0 +1,400 @@
+//! What must hold of a session whatever happened to it, checked by `mob verify`. Each
+//! invariant is a plain function over the session and what git tells about it, features
+//! that add to the session add theirs to `ALL`.
+
+use super::latest::*;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// The session and what git tells about it, read once before checking
+pub struct Facts<'a> {
+    pub session: &'a Session,
+    pub now: DateTime<Utc>,
+    /// Who checks, their clone has the mob branch while they drive
+    pub me: &'a str,
+    /// Whether the mob branch is in this clone, `None` outside a repository
+    pub local_branch: Option<bool>,
+    /// The commits of the session documents in this clone, by document
+    pub documents: BTreeMap<String, Option<String>>,
+    /// What the remote has, `None` when it couldn't be asked
+    pub remote: Option<Remote>,
+}
+
+/// What the remote has of the session
+pub struct Remote {
+    /// Where the mob branch is, `None` when there is none
+    pub branch: Option<String>,
+    /// The commits of the session documents, by document
+    pub documents: BTreeMap<String, Option<String>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "outcome", content = "problem", rename_all = "lowercase")]
+pub enum Outcome {
+    Pass,
+    Fail(String),
+    /// What the check needs wasn't there to read, as without a connection
+    Skip(String),
+}
+
+pub struct Invariant {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub check: fn(&Facts) -> Outcome,
+}
+
+pub const ALL: &[Invariant] = &[
+    Invariant {
+        name: "driver",
+        description: "Whoever the session waits for is in the rotation",
+        check: driver,
+    },
+    Invariant {
+        name: "branches",
+        description: "The mob branch is there while the session runs",
+        check: branches,
+    },
+    Invariant {
+        name: "times",
+        description: "The session started before the turn, both before now",
+        check: times,
+    },
+    Invariant {
+        name: "history",
+        description: "The records of turns and breaks follow one another",
+        check: history,
+    },
+    Invariant {
+        name: "documents",
+        description: "This clone has the session the remote has",
+        check: documents,
+    },
+    Invariant {
+        name: "tip",
+        description: "The mob branch is where the session last saw it",
+        check: tip,
+    },
+];
+
+/// Every invariant with how it turned out, in the order of `ALL`
+pub fn check(facts: &Facts) -> Vec<(&'static Invariant, Outcome)> {
+    ALL.iter()
+        .map(|invariant| (invariant, (invariant.check)(facts)))
+        .collect()
+}
+
+fn driver(facts: &Facts) -> Outcome {
+    let session = facts.session;
+    let driver = match &session.state {
+        State::Working { driver } => Some(driver),
+        State::WaitingForNext { next } => next.as_ref(),
+        State::Paused { driver, .. } => driver.as_ref(),
+        _ => None,
+    };
+    match driver {
+        Some(driver) if !session.drivers.contains(driver) => Outcome::Fail(format!(
+            "{} isn't among the drivers {}",
+            shown(driver),
+            session.drivers.all().join(", ")
+        )),
+        _ => Outcome::Pass,
+    }
+}
+
+fn branches(facts: &Facts) -> Outcome {
+    let session = facts.session;
+    let branch = &session.branches.branch;
+    if session.state == State::Stopped {
+        return Outcome::Pass;
+    }
+    let mine = matches!(&session.state, State::Working { driver } if driver == facts.me);
+    if mine && facts.local_branch == Some(false) {
+        return Outcome::Fail(format!("You drive but {} isn't in this clone", branch));
+    }
+    match &facts.remote {
+        None => Outcome::Skip("the remote couldn't be asked".into()),
+        Some(remote) if remote.branch.is_none() => {
+            Outcome::Fail(format!("The remote has no {}", branch))
+        }
+        Some(_) => Outcome::Pass,
+    }
+}
+
+fn times(facts: &Facts) -> Outcome {
+    let times = &facts.session.times;
+    let now = facts.now.into();
+    match (times.started, times.turn_started) {
+        (Some(started), Some(turn)) if turn < started => Outcome::Fail(format!(
+            "The turn started at {}, before the session at {}",
+            turn, started
+        )),
+        (Some(started), _) if started > now => {
+            Outcome::Fail(format!("The session starts at {}, after now", started))
+        }
+        (_, Some(turn)) if turn > now => {
+            Outcome::Fail(format!("The turn starts at {}, after now", turn))
+        }
+        _ => Outcome::Pass,
+    }
+}
+
+fn history(facts: &Facts) -> Outcome {
+    let records = facts.session.history.records();
+    let backwards = records.windows(2).find(|pair| pair[1].at() < pair[0].at());
+    if let Some(pair) = backwards {
+        return Outcome::Fail(format!(
+            "A record of {} follows one of {}",
+            pair[1].at(),
+            pair[0].at()
+        ));
+    }
+    match records.last() {
+        Some(last) if last.at() > facts.now => {
+            Outcome::Fail(format!("The last record is of {}, after now", last.at()))
+        }
+        _ => Outcome::Pass,
+    }
+}
+
+fn documents(facts: &Facts) -> Outcome {
+    let remote = match &facts.remote {
+        Some(remote) => remote,
+        None => return Outcome::Skip("the remote couldn't be asked".into()),
+    };
+    let names: BTreeSet<&String> = facts
+        .documents
+        .keys()
+        .chain(remote.documents.keys())
+        .collect();
+    let differ: Vec<&str> = names
+        .into_iter()
+        .filter(|name| facts.documents.get(*name) != remote.documents.get(*name))
+        .map(String::as_str)
+        .collect();
+    match differ.is_empty() {
+        true => Outcome::Pass,
+        false => Outcome::Fail(format!(
+            "This clone differs from the remote in {}, run mob sync",
+            differ.join(", ")
+        )),
+    }
+}
+
+fn tip(facts: &Facts) -> Outcome {
+    let session = facts.session;
+    let (recorded, remote) = match (&session.tip, &facts.remote) {
+        (None, _) => return Outcome::Pass,
+        (Some(_), None) => return Outcome::Skip("the remote couldn't be asked".into()),
+        (Some(recorded), Some(remote)) => (recorded, remote),
+    };
+    match &remote.branch {
+        // Whether it should be there is for `branches` to tell
+        None => Outcome::Pass,
+        Some(branch) if branch == recorded || session.state == State::Stopped => Outcome::Pass,
+        Some(branch) => Outcome::Fail(format!(
+            "{} is at {} but the session recorded {}, commits went past mob",
+            session.branches.branch,
+            short(branch),
+            short(recorded)
+        )),
+    }
+}
+
+fn short(commit: &str) -> &str {
+    &commit[..commit.len().min(7)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timekeeping::{tests, Instant};
+    use chrono::Duration;
+
+    fn utc(time: &str) -> Instant {
+        tests::utc(&format!("2021-03-01 {}", time))
+    }
+
+    fn running() -> Session {
+        Session {
+            drivers: Drivers::new(vec!["alice".into(), "bob".into()]),
+            state: State::Working {
+                driver: "alice".into(),
+            },
+            times: Times {
+                started: Some(utc("09:00")),
+                turn_started: Some(utc("10:00")),
+                ..Times::default()
+            },
+            history: History::new(vec![
+                Record::Turn {
+                    driver: "bob".into(),
+                    at: utc("09:00").utc(),
+                },
+                Record::Turn {
+                    driver: "alice".into(),
+                    at: utc("10:00").utc(),
+                },
+            ]),
+            tip: Some("a1".into()),
+            ..Session::default()
+        }
+    }
+
+    fn refs(commit: &str) -> BTreeMap<String, Option<String>> {
+        vec![("state".to_string(), Some(commit.to_string()))]
+            .into_iter()
+            .collect()
+    }
+
+    fn facts(session: &Session) -> Facts {
+        Facts {
+            session,
+            now: utc("10:05").utc(),
+            me: "alice",
+            local_branch: Some(true),
+            documents: refs("d1"),
+            remote: Some(Remote {
+                branch: Some("a1".into()),
+                documents: refs("d1"),
+            }),
+        }
+    }
+
+    fn outcome(facts: &Facts, name: &str) -> Outcome {
+        check(facts)
+            .into_iter()
+            .find(|(invariant, _)| invariant.name == name)
+            .map(|(_, outcome)| outcome)
+            .unwrap()
+    }
+
+    fn failed(facts: &Facts) -> Vec<&'static str> {
+        check(facts)
+            .into_iter()
+            .filter(|(_, outcome)| matches!(outcome, Outcome::Fail(_)))
+            .map(|(invariant, _)| invariant.name)
+            .collect()
+    }
+
+    #[test]
+    fn a_running_session_holds() {
+        let session = running();
+        assert!(failed(&facts(&session)).is_empty());
+        assert!(failed(&facts(&Session::default())).is_empty());
+    }
+
+    #[test]
+    fn waits_for_a_driver_of_the_rotation() {
+        let session = Session {
+            state: State::WaitingForNext {
+                next: Some("carol".into()),
+            },
+            ..running()
+        };
+        assert_eq!(failed(&facts(&session)), vec!["driver"]);
+        assert_eq!(
+            outcome(&facts(&session), "driver"),
+            Outcome::Fail("carol isn't among the drivers alice, bob".into())
+        );
+        let session = Session {
+            state: State::Paused {
+                reason: "pause".into(),
+                driver: Some("anyone".into()),
+                remaining: None,
+            },
+            ..running()
+        };
+        assert_eq!(
+            outcome(&facts(&session), "driver"),
+            Outcome::Fail("\"anyone\" isn't among the drivers alice, bob".into())
+        );
+    }
+
+    #[test]
+    fn has_the_branches_while_running() {
+        let session = running();
+        let mut facts = facts(&session);
+        facts.local_branch = Some(false);
+        assert_eq!(failed(&facts), vec!["branches"]);
+        // Only the driver has it
+        facts.me = "bob";
+        assert!(failed(&facts).is_empty());
+
+        facts.remote.as_mut().unwrap().branch = None;
+        assert_eq!(
+            outcome(&facts, "branches"),
+            Outcome::Fail("The remote has no mob-session".into())
+        );
+        facts.remote = None;
+        assert!(matches!(outcome(&facts, "branches"), Outcome::Skip(_)));
+    }
+
+    #[test]
+    fn times_go_forward() {
+        let mut session = running();
+        session.times.turn_started = Some(utc("08:00"));
+        assert_eq!(failed(&facts(&session)), vec!["times"]);
+
+        let mut session = running();
+        session.times.turn_started = Some(utc("10:06"));
+        assert_eq!(failed(&facts(&session)), vec!["times"]);
+
+        let mut session = running();
+        session.times.started = Some(utc("11:00"));
+        session.times.turn_started = None;
+        assert_eq!(failed(&facts(&session)), vec!["times"]);
+    }
+
+    #[test]
+    fn records_follow_one_another() {
+        let mut session = running();
+        session.history = History::new(session.history.records().iter().rev().cloned().collect());
+        assert_eq!(failed(&facts(&session)), vec!["history"]);
+
+        let session = Session {
+            history: running().history.push(Record::Break {
+                at: utc("10:05").utc() + Duration::hours(1),
+            }),
+            ..running()
+        };
+        assert_eq!(failed(&facts(&session)), vec!["history"]);
+    }
+
+    #[test]
+    fn has_the_session_of_the_remote() {
+        let session = running();
+        let mut facts = facts(&session);
+        facts.remote.as_mut().unwrap().documents = refs("d2");
+        assert_eq!(
+            outcome(&facts, "documents"),
+            Outcome::Fail("This clone differs from the remote in state, run mob sync".into())
+        );
+        facts.documents.clear();
+        facts.remote.as_mut().unwrap().documents = refs("d1");
+        assert_eq!(failed(&facts), vec!["documents"]);
+        facts.remote = None;
+        assert!(matches!(outcome(&facts, "documents"), Outcome::Skip(_)));
+    }
+
+    #[test]
+    fn the_branch_is_at_the_recorded_tip() {
+        let session = running();
+        let mut facts = facts(&session);
+        facts.remote.as_mut().unwrap().branch = Some("b2b2b2b2b2".into());
+        assert_eq!(
+            outcome(&facts, "tip"),
+            Outcome::Fail(
+                "mob-session is at b2b2b2b but the session recorded a1, commits went past mob"
+                    .into()
+            )
+        );
+        let session = Session {
+            tip: None,
+            ..running()
+        };
+        let mut facts = super::tests::facts(&session);
+        facts.remote.as_mut().unwrap().branch = Some("b2".into());
+        assert!(failed(&facts).is_empty());
+    }
+}
@@ -1,5 +1,21 @@
+pub mod answers;
+pub mod away;
 mod branches;
+pub mod bundle;
+pub mod claim;
+pub mod diff;
+mod documents;
 mod drivers;
+pub mod duplicates;
+mod env_store;
+mod history;
+pub mod id;
+pub mod invariants;
+mod new_files;
+pub mod outside;
+pub mod rebase;
+pub mod roles;
+mod seen_store;
 #[allow(clippy::module_inception)]
 mod session;
 mod session_store;
@@ -8,6 +24,9 @@ mod settings;
 mod settings_v0;
 mod state;
 mod state_v0;
+pub mod teardown;
+mod times;
+mod tip_store;
 mod versioned_session;
 
 pub mod v0 {
@@ -21,13 +40,22 @@ pub mod v0 {
 
 pub mod latest {
     use super::*;
+    pub use answers::BreakAnswers;
+    pub use away::Away;
     pub use branches::Branches;
-    pub use drivers::Drivers;
+    pub use drivers::{placeholder, shown, Drivers, TooLarge, TooSmall, PLACEHOLDERS};
+    pub use history::{History, Record};
+    pub use new_files::NewFiles;
+    pub use roles::Observers;
     pub use session::Session;
-    pub use settings::Settings;
+    pub use settings::{Settings, Window};
     pub use state::State;
+    pub use times::Times;
 }
 
+pub use env_store::EnvStore;
 pub use latest::*;
-pub use session_store::{SessionStore, Store};
+pub use seen_store::SeenStore;
+pub use session_store::{Error, SessionStore, Store, DEFAULT_SESSION};
+pub use tip_store::TipStore;
 pub use versioned_session::VersionedSession;
@@ -0,0 +1,104 @@
+//! What the mob said about new files with `next.untracked = "ask"`, so the same scratch file
+//! isn't asked about at every handover.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct NewFiles {
+    /// Committed with the turns
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub included: BTreeSet<String>,
+    /// Left out of them
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub excluded: BTreeSet<String>,
+}
+
+impl NewFiles {
+    pub fn is_empty(&self) -> bool {
+        self.included.is_empty() && self.excluded.is_empty()
+    }
+
+    /// Of `files` those nothing was said about yet
+    pub fn unknown(&self, files: &[String]) -> Vec<String> {
+        files
+            .iter()
+            .filter(|file| !self.included.contains(*file) && !self.excluded.contains(*file))
+            .cloned()
+            .collect()
+    }
+
+    /// Of `files` those committed with turns
+    pub fn included(&self, files: &[String]) -> Vec<String> {
+        files
+            .iter()
+            .filter(|file| self.included.contains(*file))
+            .cloned()
+            .collect()
+    }
+
+    /// Remembers that of the `asked` files only the `included` ones are committed
+    pub fn decide(mut self, asked: &[String], included: &[String]) -> Self {
+        for file in asked {
+            match included.contains(file) {
+                true => {
+                    self.excluded.remove(file);
+                    self.included.insert(file.clone())
+                }
+                false => {
+                    self.included.remove(file);
+                    self.excluded.insert(file.clone())
+                }
+            };
+        }
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::{self, fake::MemoryStore};
+    use crate::session::{Session, SessionStore, Store};
+
+    fn files(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn asks_about_each_file_once() {
+        let new = files(&["notes.txt", "src/parser.rs", "debug.sh"]);
+        let decided = NewFiles::default().decide(&new[..2], &files(&["src/parser.rs"]));
+        assert_eq!(decided.unknown(&new), files(&["debug.sh"]));
+        assert_eq!(decided.included(&new), files(&["src/parser.rs"]));
+
+        // Changing one's mind moves the file
+        let decided = decided.decide(&files(&["notes.txt"]), &files(&["notes.txt"]));
+        assert_eq!(
+            decided.included(&new),
+            files(&["notes.txt", "src/parser.rs"])
+        );
+        assert!(decided.excluded.is_empty());
+    }
+
+    #[test]
+    fn kept_with_the_session() {
+        let memory = MemoryStore::default();
+        let store = SessionStore::new(&memory);
+        let new_files =
+            NewFiles::default().decide(&files(&["notes.txt", "a.rs"]), &files(&["a.rs"]));
+        store
+            .save(Session {
+                id: Some("3f2a9c1e7b04".into()),
+                new_files: new_files.clone(),
+                ..Session::default()
+            })
+            .unwrap();
+
+        assert_eq!(store.load().unwrap().new_files, new_files);
+        // Sessions without any don't mention them
+        store.save(Session::default()).unwrap();
+        let saved = String::from_utf8(git::Store::load(&memory, "state").unwrap()).unwrap();
+        assert!(!saved.contains("new_files"), "{}", saved);
+    }
+}
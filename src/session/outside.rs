@@ -0,0 +1,188 @@
+//! Commits that reached the mob branch with plain git, as when a driver pulls and pushes
+//! without running `mob start` while the session waits for the next one.
+
+use super::latest::*;
+use chrono::{DateTime, Utc};
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Moved {
+    /// Where the branch is on the remote now
+    pub tip: String,
+    /// The author of each new commit, newest first
+    pub authors: Vec<String>,
+}
+
+impl Moved {
+    /// Each author once, the one of the newest commit first
+    pub fn authors(&self) -> Vec<&str> {
+        let mut authors: Vec<&str> = vec![];
+        for author in &self.authors {
+            if !authors.contains(&author.as_str()) {
+                authors.push(author);
+            }
+        }
+        authors
+    }
+
+    /// The author of the newest commit who is in the rotation, they were driving
+    pub fn driver(&self, session: &Session) -> Option<String> {
+        self.authors
+            .iter()
+            .find(|author| session.drivers.contains(author))
+            .cloned()
+    }
+}
+
+impl fmt::Display for Moved {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let commits = match self.authors.len() {
+            1 => "1 new commit".to_string(),
+            commits => format!("{} new commits", commits),
+        };
+        match self.authors().as_slice() {
+            [] => write!(f, "the branch moved outside mob ({})", commits),
+            authors => write!(
+                f,
+                "the branch moved outside mob ({} by {})",
+                commits,
+                authors.join(", ")
+            ),
+        }
+    }
+}
+
+/// Whether the mob branch is at another `tip` on the remote than the session recorded, while
+/// the session says nobody is working on it
+pub fn moved(session: &Session, tip: &str) -> bool {
+    matches!(session.state, State::WaitingForNext { .. })
+        && matches!(&session.tip, Some(recorded) if recorded != tip)
+}
+
+/// Takes the commits into the session: with `as_driver` their author drives when they're in
+/// the rotation, otherwise the history records them
+pub fn adopt(session: Session, moved: &Moved, as_driver: bool, now: DateTime<Utc>) -> Session {
+    let session = Session {
+        tip: Some(moved.tip.clone()),
+        ..session
+    };
+    match moved.driver(&session).filter(|_| as_driver) {
+        Some(driver) => Session {
+            state: State::Working {
+                driver: driver.clone(),
+            },
+            times: Times {
+                last_activity: Some(now.into()),
+                turn_started: Some(now.into()),
+                turn_minutes: None,
+                waiting_since: None,
+                claimed: None,
+                ..session.times
+            },
+            history: session.history.push(Record::Turn { driver, at: now }),
+            ..session
+        },
+        None => Session {
+            history: session.history.push(Record::Outside {
+                authors: moved.authors().into_iter().map(String::from).collect(),
+                commits: moved.authors.len(),
+                at: now,
+            }),
+            ..session
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn waiting(tip: Option<&str>) -> Session {
+        Session {
+            drivers: Drivers::new(vec!["alice".into(), "dave".into()]),
+            state: State::WaitingForNext {
+                next: Some("alice".into()),
+            },
+            tip: tip.map(String::from),
+            ..Session::default()
+        }
+    }
+
+    fn moved_by(authors: &[&str]) -> Moved {
+        Moved {
+            tip: "b2".into(),
+            authors: authors.iter().map(|author| author.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn compares_the_recorded_tip() {
+        assert!(moved(&waiting(Some("a1")), "b2"));
+        assert!(!moved(&waiting(Some("a1")), "a1"));
+        // Sessions from before tips were recorded have nothing to compare
+        assert!(!moved(&waiting(None), "b2"));
+        let working = Session {
+            state: State::Working {
+                driver: "alice".into(),
+            },
+            ..waiting(Some("a1"))
+        };
+        assert!(!moved(&working, "b2"));
+    }
+
+    #[test]
+    fn describes() {
+        assert_eq!(
+            moved_by(&["dave", "dave"]).to_string(),
+            "the branch moved outside mob (2 new commits by dave)"
+        );
+        assert_eq!(
+            moved_by(&["erin", "dave", "erin"]).to_string(),
+            "the branch moved outside mob (3 new commits by erin, dave)"
+        );
+        assert_eq!(
+            moved_by(&["dave"]).to_string(),
+            "the branch moved outside mob (1 new commit by dave)"
+        );
+    }
+
+    #[test]
+    fn adopts_a_driver_of_the_rotation() {
+        let now = Utc::now();
+        let session = adopt(waiting(Some("a1")), &moved_by(&["erin", "dave"]), true, now);
+        assert_eq!(
+            session.state,
+            State::Working {
+                driver: "dave".into()
+            }
+        );
+        assert_eq!(session.tip.as_deref(), Some("b2"));
+        assert_eq!(session.times.turn_started, Some(now.into()));
+        assert_eq!(session.history.last_driven("dave"), Some(now));
+    }
+
+    #[test]
+    fn records_commits_of_others() {
+        let now = Utc::now();
+        let session = adopt(waiting(Some("a1")), &moved_by(&["erin", "erin"]), true, now);
+        assert_eq!(session.state, waiting(None).state);
+        assert_eq!(session.tip.as_deref(), Some("b2"));
+        assert_eq!(
+            session.history.records(),
+            &[Record::Outside {
+                authors: vec!["erin".into()],
+                commits: 2,
+                at: now,
+            }]
+        );
+
+        // Declining to hand the turn to a driver still records what they pushed
+        let session = adopt(waiting(Some("a1")), &moved_by(&["dave"]), false, now);
+        assert_eq!(session.state, waiting(None).state);
+        assert_eq!(session.history.last_driven("dave"), None);
+        assert!(matches!(
+            session.history.records(),
+            [Record::Outside { commits: 1, .. }]
+        ));
+    }
+}
@@ -0,0 +1,137 @@
+//! Saving on top of a session someone else saved since it was loaded. What only one side
+//! changed is kept, records both added to a list are kept in the order they were saved,
+//! and anything both changed differently means the change no longer applies.
+
+use serde_json::{Map, Value};
+
+/// Where `ours` and `theirs` both changed `base`, differently. Tells the field of the
+/// document, the rest of the path is for logs.
+#[derive(thiserror::Error, Debug, PartialEq)]
+#[error("{}", .0.first().map(String::as_str).unwrap_or("everything"))]
+pub struct Conflict(pub Vec<String>);
+
+/// `ours` applied on top of `theirs`, both documents changed from `base`
+pub fn rebase(base: &Value, ours: &Value, theirs: &Value) -> Result<Value, Conflict> {
+    merge(base, ours, theirs, &mut vec![])
+}
+
+fn merge(
+    base: &Value,
+    ours: &Value,
+    theirs: &Value,
+    path: &mut Vec<String>,
+) -> Result<Value, Conflict> {
+    if ours == base || ours == theirs {
+        return Ok(theirs.clone());
+    }
+    if theirs == base {
+        return Ok(ours.clone());
+    }
+    match (base, ours, theirs) {
+        (Value::Object(base), Value::Object(ours), Value::Object(theirs)) => {
+            let mut merged = Map::new();
+            let keys = base.keys().chain(ours.keys()).chain(theirs.keys());
+            for key in keys {
+                if merged.contains_key(key) {
+                    continue;
+                }
+                let field = |side: &Map<String, Value>| side.get(key).cloned();
+                path.push(key.clone());
+                // Missing fields are the ones left out when empty
+                let value = merge(
+                    &field(base).unwrap_or(Value::Null),
+                    &field(ours).unwrap_or(Value::Null),
+                    &field(theirs).unwrap_or(Value::Null),
+                    path,
+                )?;
+                path.pop();
+                if !value.is_null() || (ours.contains_key(key) && theirs.contains_key(key)) {
+                    merged.insert(key.clone(), value);
+                }
+            }
+            Ok(Value::Object(merged))
+        }
+        (Value::Array(base), Value::Array(ours), Value::Array(theirs)) => {
+            match (added(base, ours), added(base, theirs)) {
+                (Some(ours), Some(_)) => {
+                    Ok(Value::Array(theirs.iter().chain(ours).cloned().collect()))
+                }
+                _ => Err(Conflict(path.clone())),
+            }
+        }
+        _ => Err(Conflict(path.clone())),
+    }
+}
+
+/// What `changed` appended to `base`, which may have dropped as many of its oldest entries
+/// to stay in its limit, like the history does. `None` when it was changed otherwise.
+fn added<'a>(base: &[Value], changed: &'a [Value]) -> Option<&'a [Value]> {
+    let dropped = (0..base.len().max(1)).find(|dropped| changed.starts_with(&base[*dropped..]))?;
+    let added = &changed[base.len() - dropped..];
+    match dropped <= added.len() {
+        true => Some(added),
+        false => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn keeps_what_either_changed() {
+        let base = json!({"state": "Working", "away": {"bob": 1}, "times": {"started": 1}});
+        let ours = json!({"state": "WaitingForNext", "away": {"bob": 1}, "times": {"started": 1}});
+        let theirs = json!({"state": "Working", "times": {"started": 1, "break": 2}});
+        assert_eq!(
+            rebase(&base, &ours, &theirs),
+            Ok(json!({"state": "WaitingForNext", "times": {"started": 1, "break": 2}}))
+        );
+        // Alike changes are no conflict
+        assert_eq!(rebase(&base, &ours, &ours), Ok(ours.clone()));
+        assert_eq!(rebase(&base, &base, &theirs), Ok(theirs));
+    }
+
+    #[test]
+    fn keeps_records_both_added() {
+        let base = json!({"records": [1, 2, 3]});
+        let ours = json!({"records": [1, 2, 3, 4]});
+        let theirs = json!({"records": [1, 2, 3, 5, 6]});
+        assert_eq!(
+            rebase(&base, &ours, &theirs),
+            Ok(json!({"records": [1, 2, 3, 5, 6, 4]}))
+        );
+        // Past the limit the oldest records made room
+        let ours = json!({"records": [2, 3, 4]});
+        let theirs = json!({"records": [3, 5, 6]});
+        assert_eq!(
+            rebase(&base, &ours, &theirs),
+            Ok(json!({"records": [3, 5, 6, 4]}))
+        );
+    }
+
+    #[test]
+    fn refuses_what_both_changed() {
+        let base = json!({"state": {"Working": {"driver": "alice"}}});
+        let ours = json!({"state": {"Working": {"driver": "bob"}}});
+        let theirs = json!({"state": {"Working": {"driver": "carol"}}});
+        let conflict = rebase(&base, &ours, &theirs).unwrap_err();
+        assert_eq!(
+            conflict,
+            Conflict(vec!["state".into(), "Working".into(), "driver".into()])
+        );
+        assert_eq!(conflict.to_string(), "state");
+
+        // Removed on one side, added to on the other
+        let base = json!({"drivers": ["alice", "bob"]});
+        let ours = json!({"drivers": ["bob"]});
+        let theirs = json!({"drivers": ["alice", "bob", "carol"]});
+        assert_eq!(
+            rebase(&base, &ours, &theirs),
+            Err(Conflict(vec!["drivers".into()]))
+        );
+        let ours = json!({"drivers": ["carol", "dave"]});
+        assert!(rebase(&base, &ours, &theirs).is_err());
+    }
+}
@@ -0,0 +1,223 @@
+//! Who drives and who only follows along. Observers joined with `mob join --observe`: they
+//! see the session but don't take turns, until `mob claim` makes them drivers.
+
+use super::latest::*;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Role {
+    Driver,
+    Observer,
+}
+
+/// What a command does to the session, as far as the role matters
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+    Status,
+    Join,
+    Watch,
+    /// Observers become drivers by claiming
+    Claim,
+    Start,
+    Next,
+    Done,
+    Break,
+    /// Changing the order, the settings or the whole session
+    Settings,
+}
+
+impl Action {
+    pub const ALL: [Action; 9] = [
+        Action::Status,
+        Action::Join,
+        Action::Watch,
+        Action::Claim,
+        Action::Start,
+        Action::Next,
+        Action::Done,
+        Action::Break,
+        Action::Settings,
+    ];
+
+    pub fn allows(self, role: Role) -> bool {
+        match role {
+            Role::Driver => true,
+            Role::Observer => matches!(
+                self,
+                Action::Status | Action::Join | Action::Watch | Action::Claim
+            ),
+        }
+    }
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Action::Status => "mob status",
+            Action::Join => "mob join",
+            Action::Watch => "mob watch",
+            Action::Claim => "mob claim",
+            Action::Start => "mob start",
+            Action::Next => "mob next",
+            Action::Done => "mob done",
+            Action::Break => "mob break",
+            Action::Settings => "changing the session",
+        })
+    }
+}
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+#[error("You are observing this mob and {action} is for drivers, run mob claim to become one")]
+pub struct NotADriver {
+    pub action: Action,
+}
+
+/// Those who follow the session without taking turns
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Observers(Vec<String>);
+
+impl Observers {
+    pub fn new(observers: Vec<String>) -> Self {
+        Observers(observers)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.0.iter().any(|observer| observer == name)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn insert(mut self, name: &str) -> Self {
+        if !self.contains(name) {
+            self.0.push(name.to_string());
+        }
+        self
+    }
+
+    pub fn remove(mut self, name: &str) -> Self {
+        self.0.retain(|observer| observer != name);
+        self
+    }
+
+    pub fn all(&self) -> Vec<String> {
+        self.0.clone()
+    }
+}
+
+/// The role of `name`, who drives unless observing. Sessions from before observers only
+/// have drivers.
+pub fn role(session: &Session, name: &str) -> Role {
+    if session.observers.contains(name) && !session.drivers.contains(name) {
+        Role::Observer
+    } else {
+        Role::Driver
+    }
+}
+
+/// Whether `name` may do `action` in the session
+pub fn check(session: &Session, name: &str, action: Action) -> Result<(), NotADriver> {
+    if action.allows(role(session, name)) {
+        Ok(())
+    } else {
+        Err(NotADriver { action })
+    }
+}
+
+/// `name` following the session without driving, drivers stay drivers
+pub fn observe(session: Session, name: &str) -> Session {
+    if session.drivers.contains(name) {
+        return session;
+    }
+    Session {
+        observers: session.observers.insert(name),
+        ..session
+    }
+}
+
+/// `name` no longer observing but driving, as one of `drivers`
+pub fn promote(session: Session, name: &str, drivers: Drivers) -> Session {
+    Session {
+        drivers,
+        observers: session.observers.remove(name),
+        ..session
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session() -> Session {
+        observe(
+            Session {
+                drivers: Drivers::new(vec!["alice".into(), "bob".into()]),
+                state: State::Working {
+                    driver: "alice".into(),
+                },
+                ..Session::default()
+            },
+            "olga",
+        )
+    }
+
+    #[test]
+    fn observers_only_follow() {
+        let session = session();
+        for action in Action::ALL.iter().copied() {
+            let observer = matches!(
+                action,
+                Action::Status | Action::Join | Action::Watch | Action::Claim
+            );
+            assert_eq!(
+                check(&session, "olga", action).is_ok(),
+                observer,
+                "{}",
+                action
+            );
+            assert_eq!(check(&session, "alice", action), Ok(()), "{}", action);
+            // Not yet a member, like before a first mob start
+            assert_eq!(check(&session, "carol", action), Ok(()), "{}", action);
+        }
+        assert_eq!(
+            check(&session, "olga", Action::Next)
+                .unwrap_err()
+                .to_string(),
+            "You are observing this mob and mob next is for drivers, run mob claim to become one"
+        );
+    }
+
+    #[test]
+    fn drivers_stay_drivers() {
+        let session = observe(session(), "bob");
+        assert_eq!(session.observers.all(), vec!["olga"]);
+        assert_eq!(role(&session, "bob"), Role::Driver);
+        assert_eq!(role(&session, "olga"), Role::Observer);
+
+        let drivers = session.drivers.clone().insert(Some("alice".into()), "olga");
+        let promoted = promote(session, "olga", drivers);
+        assert_eq!(role(&promoted, "olga"), Role::Driver);
+        assert!(promoted.observers.is_empty());
+        assert_eq!(promoted.drivers.all(), vec!["alice", "olga", "bob"]);
+    }
+
+    #[test]
+    fn reads_sessions_without_observers() {
+        let session: Session = serde_json::from_value(serde_json::json!({
+            "drivers": ["alice", "bob"],
+            "branches": {"branch": "mob-session", "base_branch": "master"},
+            "settings": null,
+            "state": "Stopped",
+        }))
+        .unwrap();
+        assert!(session.observers.is_empty());
+        assert_eq!(role(&session, "alice"), Role::Driver);
+        let json = serde_json::to_value(&session).unwrap();
+        assert!(json.get("observers").is_none());
+
+        let json = serde_json::to_value(observe(session, "olga")).unwrap();
+        assert_eq!(json["observers"], serde_json::json!(["olga"]));
+    }
+}
@@ -0,0 +1,136 @@
+use super::diff;
+use super::latest::Session;
+use super::session_store::{Result, Store};
+use crate::state_dir::StateDir;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+pub(super) const LAST_SEEN_FILE: &str = "last_seen.json";
+
+#[derive(Serialize, Deserialize)]
+struct LastSeen {
+    hash: u64,
+    session: Session,
+}
+
+/// Remembers the session as this user last saw it and tells what others changed since.
+pub struct SeenStore<'a> {
+    store: &'a dyn Store,
+    state_dir: StateDir,
+    me: String,
+    announce: bool,
+}
+
+impl<'a> SeenStore<'a> {
+    /// With `announce` set every load logs what changed since the session was last seen
+    pub fn new(store: &'a impl Store, state_dir: StateDir, me: &str, announce: bool) -> Self {
+        SeenStore {
+            store,
+            state_dir,
+            me: me.to_string(),
+            announce,
+        }
+    }
+
+    pub fn changes(&self, session: &Session) -> Vec<String> {
+        match self.state_dir.read::<LastSeen>(LAST_SEEN_FILE) {
+            Some(seen) if seen.hash != hash(session) => {
+                diff::describe(&seen.session, session, &self.me)
+            }
+            _ => vec![],
+        }
+    }
+
+    fn remember(&self, session: &Session) {
+        let seen = LastSeen {
+            hash: hash(session),
+            session: session.clone(),
+        };
+        self.state_dir
+            .write(LAST_SEEN_FILE, &seen)
+            .unwrap_or_else(|err| log::trace!("Could not remember session: {}", err));
+    }
+}
+
+fn hash(session: &Session) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    serde_json::to_vec(session)
+        .unwrap_or_default()
+        .hash(&mut hasher);
+    hasher.finish()
+}
+
+impl<'a> Store for SeenStore<'a> {
+    fn load(&self) -> Result<Session> {
+        let session = self.store.load()?;
+        if self.announce {
+            let changes = self.changes(&session);
+            if !changes.is_empty() {
+                log::info!("Since you last looked: {}", changes.join(", "));
+            }
+        }
+        self.remember(&session);
+        Ok(session)
+    }
+
+    fn save(&self, session: Session) -> Result<()> {
+        self.store.save(session.clone())?;
+        self.remember(&session);
+        Ok(())
+    }
+
+    fn clean(&self) -> Result<()> {
+        self.store.clean()?;
+        self.state_dir
+            .remove(LAST_SEEN_FILE)
+            .unwrap_or_else(|err| log::trace!("Could not forget session: {}", err));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::fake::MemoryStore;
+    use crate::session::{SessionStore, State};
+
+    #[test]
+    fn remote_changed_between_commands() {
+        let dir = tempfile::tempdir().unwrap();
+        let memory = MemoryStore::default();
+        let remote = SessionStore::new(&memory);
+        let store = SeenStore::new(
+            &remote,
+            StateDir::new(dir.path().to_path_buf()),
+            "alice",
+            true,
+        );
+
+        store
+            .save(Session {
+                state: State::Working {
+                    driver: "alice".into(),
+                },
+                ..Session::default()
+            })
+            .unwrap();
+        let session = store.load().unwrap();
+        assert!(store.changes(&session).is_empty());
+
+        // Someone else takes over without us looking
+        remote
+            .save(Session {
+                state: State::Working {
+                    driver: "bob".into(),
+                },
+                ..session
+            })
+            .unwrap();
+        let session = remote.load().unwrap();
+        assert_eq!(store.changes(&session), vec!["bob took over from you"]);
+
+        store.load().unwrap();
+        assert!(store.changes(&session).is_empty());
+    }
+}
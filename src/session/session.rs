@@ -1,23 +1,59 @@
 use super::latest::*;
 use super::v0::{SessionV0, StateV0};
+use crate::timekeeping::Minutes;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::default::Default;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Session {
+    /// Tells the session apart from others outside mob, set by `mob start`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
     pub drivers: Drivers,
     pub branches: Branches,
     pub settings: Option<Settings>,
     pub state: State,
+    #[serde(default)]
+    pub times: Times,
+    #[serde(default)]
+    pub history: History,
+    /// Drivers the rotation skips for now
+    #[serde(default, skip_serializing_if = "Away::is_empty")]
+    pub away: Away,
+    /// Who follows along without driving
+    #[serde(default, skip_serializing_if = "Observers::is_empty")]
+    pub observers: Observers,
+    /// What the mob said to the breaks `mob next` offered
+    #[serde(default, skip_serializing_if = "BreakAnswers::is_empty")]
+    pub answers: BreakAnswers,
+    /// Which new files the mob wants in the turns, with `next.untracked = "ask"`
+    #[serde(default, skip_serializing_if = "NewFiles::is_empty")]
+    pub new_files: NewFiles,
+    /// Where the mob branch was on the remote when the session was last saved
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tip: Option<String>,
+    /// Where the mob branch was on the remote of each repository of a workspace
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub tips: BTreeMap<String, String>,
 }
 
 impl Default for Session {
     fn default() -> Self {
         Self {
+            id: None,
             drivers: Drivers::default(),
             branches: Branches::default(),
             settings: None,
             state: State::Stopped,
+            times: Times::default(),
+            history: History::default(),
+            away: Away::default(),
+            observers: Observers::default(),
+            answers: BreakAnswers::default(),
+            new_files: NewFiles::default(),
+            tip: None,
+            tips: BTreeMap::new(),
         }
     }
 }
@@ -25,12 +61,24 @@ impl Default for Session {
 impl From<SessionV0> for Session {
     fn from(session_v0: SessionV0) -> Self {
         Self {
+            id: None,
             drivers: session_v0.drivers,
             branches: session_v0.branches,
             settings: match session_v0.settings {
                 Some(settings) => Some(Settings {
                     commit_message: settings.commit_message,
-                    work_duration: settings.work_duration,
+                    work_duration: Minutes(settings.work_duration),
+                    break_duration: Some(Minutes(settings.break_duration)),
+                    break_interval: Some(Minutes(settings.break_interval)),
+                    windows: super::settings::lunch(
+                        settings.lunch_start.parse().ok(),
+                        settings.lunch_end.parse().ok(),
+                    )
+                    .into_iter()
+                    .collect(),
+                    workday_end: None,
+                    min_turn_minutes: Minutes(0),
+                    timezone: None,
                 }),
                 None => None,
             },
@@ -39,6 +87,17 @@ impl From<SessionV0> for Session {
                 StateV0::Working { driver } => State::Working { driver },
                 StateV0::WaitingForNext { next, .. } => State::WaitingForNext { next },
             },
+            times: Times {
+                last_break: Some(session_v0.last_break.into()),
+                ..Times::default()
+            },
+            history: History::default(),
+            away: Away::default(),
+            observers: Observers::default(),
+            answers: BreakAnswers::default(),
+            new_files: NewFiles::default(),
+            tip: None,
+            tips: BTreeMap::new(),
         }
     }
 }
@@ -1,12 +1,32 @@
-use super::latest::Session;
+use super::documents::{self, Progress, VersionedProgress, VersionedSetup};
+use super::duplicates;
+use super::id;
+use super::latest::{Branches, Session};
+use super::rebase::{self, rebase};
 use super::versioned_session::Versioned;
 use super::VersionedSession;
+use crate::clock::{Clock, SystemClock};
+use crate::deadline;
 use crate::git;
+use crate::git::store::SESSION_DOCUMENT;
+use crate::record;
 use crate::session::v0::SessionV0;
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+/// The document with state and times, settings and drivers stay in the session document
+const PROGRESS_DOCUMENT: &str = "state";
+
+/// How long a save may run past the deadline
+const SAVE_GRACE: std::time::Duration = std::time::Duration::from_secs(15);
+
+/// How often a document is saved again on top of what others saved in the meantime
+const SAVE_RETRIES: usize = 3;
 
 pub type Result<T, E = Error> = core::result::Result<T, E>;
 
 #[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
 pub enum Error {
     #[error("git error: `{0}`")]
     Git(#[from] git::store::Error),
@@ -16,6 +36,9 @@ pub enum Error {
 
     #[error("you're version is to old, upgrade to version `{0}`")]
     NewerVersion(String),
+
+    #[error("someone else already advanced the session ({0} changed), run mob status to see where it is")]
+    Overtaken(#[from] rebase::Conflict),
 }
 
 pub trait Store {
@@ -24,13 +47,174 @@ pub trait Store {
     fn clean(&self) -> Result<()>;
 }
 
+/// The session of a repository unless `--session` names another
+pub const DEFAULT_SESSION: &str = "default";
+
 pub struct SessionStore<'a> {
     store: &'a dyn git::Store,
+    /// Selected with `--session`, others keep their documents apart
+    name: String,
+    /// Each document as last loaded or saved, `None` when it was missing
+    loaded: RefCell<HashMap<String, Option<Vec<u8>>>>,
+    /// When saving notes what it changed, the machine's unless told otherwise
+    clock: &'a dyn Clock,
 }
 
 impl<'a> SessionStore<'a> {
     pub fn new(store: &'a impl git::Store) -> Self {
-        SessionStore { store }
+        Self::named(store, DEFAULT_SESSION)
+    }
+
+    pub fn named(store: &'a impl git::Store, name: &str) -> Self {
+        SessionStore {
+            store,
+            name: name.to_string(),
+            loaded: RefCell::new(HashMap::new()),
+            clock: &SystemClock,
+        }
+    }
+
+    pub fn clock(self, clock: &'a dyn Clock) -> Self {
+        SessionStore { clock, ..self }
+    }
+
+    /// Whether `name` can name a session, it ends up in branch and file names
+    pub fn valid_name(name: &str) -> bool {
+        !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    }
+
+    /// The sessions saved in `store`, the default one first and the others by name
+    pub fn names(store: &impl git::Store) -> Result<Vec<String>> {
+        let documents = store.documents()?;
+        let prefix = format!("{}-", SESSION_DOCUMENT);
+        let mut names: Vec<String> = documents
+            .iter()
+            .filter_map(|document| document.strip_prefix(&prefix))
+            .map(String::from)
+            .collect();
+        names.sort();
+        if documents.iter().any(|document| document == SESSION_DOCUMENT) {
+            names.insert(0, DEFAULT_SESSION.to_string());
+        }
+        Ok(names)
+    }
+
+    /// The document `document` is for this session, the default one keeps the plain names
+    fn document(&self, document: &str) -> String {
+        match self.name.as_str() {
+            DEFAULT_SESSION => document.to_string(),
+            name => format!("{}-{}", document, name),
+        }
+    }
+
+    /// A session that was never started, others work on a branch of their own
+    fn unsaved(&self) -> Session {
+        let session = Session::default();
+        match self.name.as_str() {
+            DEFAULT_SESSION => session,
+            name => Session {
+                branches: Branches {
+                    branch: format!("{}-{}", session.branches.branch, name),
+                    ..session.branches
+                },
+                ..session
+            },
+        }
+    }
+
+    fn read(&self, document: &str) -> Result<Option<Vec<u8>>> {
+        let document = self.document(document);
+        let data = match self.store.load(&document) {
+            Ok(data) => Some(data),
+            Err(git::store::Error::Missing) => None,
+            Err(error) => return Err(Error::Git(error)),
+        };
+        self.loaded.borrow_mut().insert(document, data.clone());
+        Ok(data)
+    }
+
+    /// Skips documents that didn't change. What others saved in the meantime is loaded and
+    /// the change made again on top of it, unless they changed the same. Only the document is
+    /// saved again, the code was pushed before.
+    fn write(&self, document: &str, data: Vec<u8>) -> Result<()> {
+        let document = self.document(document);
+        let mut data = data;
+        let mut retries = 0;
+        loop {
+            let loaded = self.loaded.borrow().get(&document).cloned();
+            let saved = match loaded {
+                Some(expected) if expected.as_deref() == Some(data.as_slice()) => return Ok(()),
+                Some(expected) => self.store.swap(&document, expected.as_deref(), &data),
+                None => self.store.save(&document, &data),
+            };
+            match saved {
+                Ok(()) => break,
+                Err(git::store::Error::Changed(changed)) if retries < SAVE_RETRIES => {
+                    retries += 1;
+                    log::info!(
+                        "Someone saved the {} of the session in the meantime, saving on top of it",
+                        changed
+                    );
+                    data = self.rebase(&document, data)?;
+                }
+                Err(err) => return Err(err.into()),
+            }
+        }
+        self.loaded.borrow_mut().insert(document, Some(data));
+        Ok(())
+    }
+
+    /// `data` on top of what is saved now, which is what `document` is loaded as from then on
+    fn rebase(&self, document: &str, data: Vec<u8>) -> Result<Vec<u8>> {
+        let base = self.loaded.borrow().get(document).cloned().flatten();
+        let theirs = match self.store.load(document) {
+            Ok(theirs) => Some(theirs),
+            Err(git::store::Error::Missing) => None,
+            Err(err) => return Err(Error::Git(err)),
+        };
+        self.loaded
+            .borrow_mut()
+            .insert(document.to_string(), theirs.clone());
+        let (base, theirs) = match (base, theirs) {
+            (Some(base), Some(theirs)) => (base, theirs),
+            // Started or cleaned by both at once
+            _ => return Err(git::store::Error::Changed(document.to_string()).into()),
+        };
+        let rebased = match rebase(
+            &serde_json::from_slice(&base)?,
+            &serde_json::from_slice(&data)?,
+            &serde_json::from_slice(&theirs)?,
+        ) {
+            Ok(rebased) => rebased,
+            Err(conflict) => {
+                log::debug!("Both changed {} of {}", conflict.0.join("/"), document);
+                return Err(conflict.into());
+            }
+        };
+        Ok(serde_json::to_vec_pretty(&rebased)?)
+    }
+
+    fn newer_or_invalid(data: &[u8], err: serde_json::Error) -> Error {
+        match serde_json::from_slice::<Versioned>(data) {
+            Ok(Versioned {
+                version: Some(version),
+            }) => Error::NewerVersion(version),
+            _ => Error::Format(err),
+        }
+    }
+
+    fn get_progress(&self, data: Option<Vec<u8>>) -> Result<Progress> {
+        let data = match data {
+            Some(data) => data,
+            None => return Ok(Progress::default()),
+        };
+        match serde_json::from_slice::<VersionedProgress>(&data) {
+            Ok(VersionedProgress::V2(progress)) => Ok(progress),
+            Err(err) => Err(Self::newer_or_invalid(&data, err)),
+        }
     }
 
     fn get_session(&self, data: Vec<u8>) -> Result<Session> {
@@ -43,32 +227,76 @@ impl<'a> SessionStore<'a> {
                     None => {
                         // Assume first unversioned
                         let session = serde_json::from_slice::<SessionV0>(data.as_slice())?;
+                        crate::warnings::push(
+                            "session-v0",
+                            "the session was created by an old version of mob and is upgraded when it is next saved",
+                            "0.1.7",
+                        );
                         Ok(VersionedSession::V0(session).latest())
                     }
                 }
             }
         }
     }
+
+    fn load_documents(&self) -> Result<Session> {
+        let data = match self.read(SESSION_DOCUMENT)? {
+            Some(data) => data,
+            None => return Ok(self.unsaved()),
+        };
+        let versioned = serde_json::from_slice::<Versioned>(&data)?;
+        if versioned.version.as_deref() != Some(documents::VERSION) {
+            // A single document from before the split, it has everything
+            return self.get_session(data);
+        }
+
+        let VersionedSetup::V2(setup) = serde_json::from_slice::<VersionedSetup>(&data)?;
+        let progress = self.get_progress(self.read(PROGRESS_DOCUMENT)?)?;
+        Ok(documents::compose(setup, progress))
+    }
 }
 
 impl<'a> Store for SessionStore<'a> {
     fn load(&self) -> Result<Session> {
-        match self.store.load() {
-            Ok(data) => self.get_session(data),
-            Err(git::store::Error::Missing) => Ok(Session::default()),
-            Err(error) => Err(Error::Git(error)),
+        let session = self.load_documents()?;
+        record::loaded(&session);
+        let duplicates = duplicates::find(&session);
+        if !duplicates.is_empty() {
+            let names: Vec<String> = duplicates
+                .iter()
+                .map(|duplicate| format!("{:?}", duplicate.variants))
+                .collect();
+            crate::warnings::push(
+                "duplicate-drivers",
+                format!(
+                    "some drivers are listed under several names, {}, run mob doctor --fix to merge them",
+                    names.join(", ")
+                ),
+                "0.1.7",
+            );
         }
+        Ok(session)
     }
 
     fn save(&self, session: Session) -> Result<()> {
-        let versioned_session = VersionedSession::V1(session);
-        let json = serde_json::to_vec_pretty(&versioned_session)?;
-        self.store.save(&json)?;
-        Ok(())
+        let session = id::assign(session, self.clock.now_utc());
+        record::saved(&session);
+        let (setup, progress) = documents::split(session);
+        let setup = serde_json::to_vec_pretty(&VersionedSetup::V2(setup))?;
+        let progress = serde_json::to_vec_pretty(&VersionedProgress::V2(progress))?;
+
+        // Progress first: until the setup follows, readers still see a single old document whole.
+        // A save that started finishes past the deadline, so the session isn't left half-saved.
+        deadline::with_grace(SAVE_GRACE, || {
+            self.write(PROGRESS_DOCUMENT, progress)?;
+            self.write(SESSION_DOCUMENT, setup)
+        })
     }
 
     fn clean(&self) -> Result<()> {
-        self.store.clean()?;
+        self.store.clean(&self.document(PROGRESS_DOCUMENT))?;
+        self.store.clean(&self.document(SESSION_DOCUMENT))?;
+        self.loaded.borrow_mut().clear();
         Ok(())
     }
 }
@@ -76,20 +304,28 @@ impl<'a> Store for SessionStore<'a> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::clock::FakeClock;
     use crate::git;
+    use crate::git::fake::MemoryStore;
+    use crate::git::Store as _;
+    use crate::session::{Drivers, Settings, State, Times};
+    use crate::timekeeping::{ClockTime, Minutes};
 
     struct MockStore {
         pub load_data: Vec<u8>,
     }
 
     impl git::Store for MockStore {
-        fn load(&self) -> std::result::Result<Vec<u8>, git::store::Error> {
-            Ok(self.load_data.clone())
+        fn load(&self, document: &str) -> std::result::Result<Vec<u8>, git::store::Error> {
+            match document {
+                SESSION_DOCUMENT => Ok(self.load_data.clone()),
+                _ => Err(git::store::Error::Missing),
+            }
         }
-        fn clean(&self) -> std::result::Result<(), git::store::Error> {
+        fn clean(&self, _: &str) -> std::result::Result<(), git::store::Error> {
             todo!()
         }
-        fn save(&self, _: &[u8]) -> std::result::Result<(), git::store::Error> {
+        fn save(&self, _: &str, _: &[u8]) -> std::result::Result<(), git::store::Error> {
             todo!()
         }
     }
@@ -164,8 +400,482 @@ mod tests {
 
         let session_store = SessionStore::new(&store);
 
-        if let Err(err) = session_store.load() {
-            panic!("Got error but expected oldest version: {:?}", err)
+        crate::warnings::take();
+        match session_store.load() {
+            Ok(session) => assert_eq!(
+                session.settings.unwrap().windows[0].start,
+                ClockTime::hm(11, 30)
+            ),
+            Err(err) => panic!("Got error but expected oldest version: {:?}", err),
         }
+        let warnings = crate::warnings::take();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].code, "session-v0");
+    }
+
+    fn working(driver: &str) -> Session {
+        Session {
+            id: Some("3f2a9c1e7b04".into()),
+            drivers: Drivers::new(vec!["alice".into(), "bob".into()]),
+            settings: Some(Settings::default()),
+            state: State::Working {
+                driver: driver.into(),
+            },
+            times: Times {
+                started: Some(chrono::Utc::now().into()),
+                ..Times::default()
+            },
+            ..Session::default()
+        }
+    }
+
+    fn document(memory: &MemoryStore, name: &str) -> serde_json::Value {
+        serde_json::from_slice(&memory.documents.borrow()[name]).unwrap()
+    }
+
+    #[test]
+    fn saves_two_documents() {
+        let memory = MemoryStore::default();
+        let store = SessionStore::new(&memory);
+        let session = working("bob");
+        store.save(session.clone()).unwrap();
+
+        let setup = document(&memory, SESSION_DOCUMENT);
+        assert_eq!(setup["version"], "v2");
+        assert_eq!(setup["drivers"], serde_json::json!(["alice", "bob"]));
+        assert!(setup.get("state").is_none());
+
+        let progress = document(&memory, PROGRESS_DOCUMENT);
+        assert_eq!(progress["version"], "v2");
+        assert_eq!(progress["state"]["Working"]["driver"], "bob");
+        assert!(progress.get("drivers").is_none());
+
+        assert_eq!(SessionStore::new(&memory).load().unwrap(), session);
+    }
+
+    #[test]
+    fn turns_only_write_progress() {
+        let memory = MemoryStore::default();
+        SessionStore::new(&memory).save(working("bob")).unwrap();
+        let saves = *memory.saves.borrow();
+
+        let store = SessionStore::new(&memory);
+        let session = store.load().unwrap();
+        store
+            .save(Session {
+                state: State::WaitingForNext {
+                    next: Some("alice".into()),
+                },
+                ..session.clone()
+            })
+            .unwrap();
+        assert_eq!(*memory.saves.borrow(), saves + 1);
+
+        store.save(store.load().unwrap()).unwrap();
+        assert_eq!(*memory.saves.borrow(), saves + 1, "nothing changed");
+    }
+
+    #[test]
+    fn migrates_single_document() {
+        let memory = MemoryStore::default();
+        let legacy = working("bob");
+        let data = serde_json::to_vec(&VersionedSession::V1(legacy.clone())).unwrap();
+        memory.save(SESSION_DOCUMENT, &data).unwrap();
+
+        let store = SessionStore::new(&memory);
+        let session = store.load().unwrap();
+        assert_eq!(session, legacy);
+
+        store.save(session).unwrap();
+        assert_eq!(document(&memory, SESSION_DOCUMENT)["version"], "v2");
+        assert_eq!(
+            document(&memory, PROGRESS_DOCUMENT)["state"]["Working"]["driver"],
+            "bob"
+        );
+        assert_eq!(SessionStore::new(&memory).load().unwrap(), legacy);
+    }
+
+    #[test]
+    fn gives_older_sessions_an_id() {
+        let memory = MemoryStore::default();
+        let legacy = Session {
+            id: None,
+            ..working("bob")
+        };
+        let data = serde_json::to_vec(&VersionedSession::V1(legacy.clone())).unwrap();
+        memory.save(SESSION_DOCUMENT, &data).unwrap();
+
+        let clock = FakeClock::default();
+        let store = SessionStore::new(&memory).clock(&clock);
+        assert_eq!(store.load().unwrap().id, None);
+        store.save(store.load().unwrap()).unwrap();
+        let saved = SessionStore::new(&memory).load().unwrap();
+        let id = saved.id.clone().unwrap();
+        assert!(matches!(
+            saved.history.records(),
+            [crate::session::Record::Identified { id: noted, at }]
+                if *noted == id && *at == clock.now_utc()
+        ));
+        assert_eq!(document(&memory, SESSION_DOCUMENT)["id"], id.as_str());
+    }
+
+    #[test]
+    fn missing_progress_is_stopped() {
+        let memory = MemoryStore::default();
+        SessionStore::new(&memory).save(working("bob")).unwrap();
+        memory.clean(PROGRESS_DOCUMENT).unwrap();
+
+        let session = SessionStore::new(&memory).load().unwrap();
+        assert_eq!(session.state, State::Stopped);
+        assert_eq!(session.drivers, working("bob").drivers);
+    }
+
+    #[test]
+    fn newer_progress() {
+        let memory = MemoryStore::default();
+        SessionStore::new(&memory).save(working("bob")).unwrap();
+        memory
+            .save(PROGRESS_DOCUMENT, br#"{"version": "v99", "baton": 1}"#)
+            .unwrap();
+
+        match SessionStore::new(&memory).load() {
+            Err(Error::NewerVersion(version)) => assert_eq!(version, "v99"),
+            other => panic!("Should fail but got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn refuses_to_overwrite_changes_of_others() {
+        let memory = MemoryStore::default();
+        SessionStore::new(&memory).save(working("bob")).unwrap();
+
+        let mine = SessionStore::new(&memory);
+        let theirs = SessionStore::new(&memory);
+        let session = mine.load().unwrap();
+        theirs.load().unwrap();
+        theirs.save(working("alice")).unwrap();
+
+        match mine.save(Session {
+            state: State::Stopped,
+            ..session.clone()
+        }) {
+            Err(Error::Overtaken(conflict)) => assert_eq!(
+                Error::Overtaken(conflict).to_string(),
+                "someone else already advanced the session (state changed), \
+                 run mob status to see where it is"
+            ),
+            other => panic!("Should conflict but got {:?}", other),
+        }
+        assert_eq!(
+            SessionStore::new(&memory).load().unwrap().state,
+            working("alice").state
+        );
+    }
+
+    #[test]
+    fn documents_conflict_independently() {
+        let memory = MemoryStore::default();
+        SessionStore::new(&memory).save(working("bob")).unwrap();
+
+        let mine = SessionStore::new(&memory);
+        let theirs = SessionStore::new(&memory);
+        let session = mine.load().unwrap();
+        let their_session = theirs.load().unwrap();
+
+        // They change settings while I finish my turn
+        theirs
+            .save(Session {
+                settings: Some(Settings {
+                    work_duration: Minutes(25),
+                    ..Settings::default()
+                }),
+                ..their_session
+            })
+            .unwrap();
+        mine.save(Session {
+            state: State::WaitingForNext { next: None },
+            ..session
+        })
+        .unwrap();
+
+        let session = SessionStore::new(&memory).load().unwrap();
+        assert_eq!(session.settings.unwrap().work_duration, Minutes(25));
+        assert_eq!(session.state, State::WaitingForNext { next: None });
+    }
+
+    /// Rejects the first push of a document when someone else's push got there first
+    #[derive(Default)]
+    struct RacingStore {
+        memory: MemoryStore,
+        /// Documents others push in the meantime, in the order they do
+        others: RefCell<Vec<(String, Vec<u8>)>>,
+    }
+
+    impl RacingStore {
+        /// `session` as someone else saves it on their clone of `memory`
+        fn others_save(&self, session: impl FnOnce(Session) -> Session) {
+            let clone = MemoryStore::default();
+            *clone.documents.borrow_mut() = self.memory.documents.borrow().clone();
+            let theirs = SessionStore::new(&clone);
+            theirs.save(session(theirs.load().unwrap())).unwrap();
+            let mut others = self.others.borrow_mut();
+            for (document, data) in clone.documents.borrow().iter() {
+                if self.memory.documents.borrow().get(document) != Some(data) {
+                    others.push((document.clone(), data.clone()));
+                }
+            }
+        }
+    }
+
+    impl git::Store for RacingStore {
+        fn load(&self, document: &str) -> Result<Vec<u8>, git::store::Error> {
+            self.memory.load(document)
+        }
+
+        fn save(&self, document: &str, data: &[u8]) -> Result<(), git::store::Error> {
+            let mut others = self.others.borrow_mut();
+            match others.iter().position(|(other, _)| other == document) {
+                Some(at) => {
+                    let (_, theirs) = others.remove(at);
+                    self.memory.save(document, &theirs)?;
+                    Err(git::store::Error::Changed(document.to_string()))
+                }
+                None => self.memory.save(document, data),
+            }
+        }
+
+        fn clean(&self, document: &str) -> Result<(), git::store::Error> {
+            self.memory.clean(document)
+        }
+    }
+
+    #[test]
+    fn saves_again_on_top_of_others() {
+        use crate::session::Record;
+        let racing = RacingStore::default();
+        SessionStore::new(&racing).save(working("bob")).unwrap();
+        let mine = SessionStore::new(&racing);
+        let session = mine.load().unwrap();
+        let now = chrono::Utc::now();
+
+        // Bob syncs while alice adds dave and ends bob's turn
+        racing.others_save(|session| Session {
+            history: session.history.push(Record::Sync {
+                driver: "bob".into(),
+                at: now,
+            }),
+            ..session
+        });
+        racing.others_save(|session| Session {
+            drivers: Drivers::new(vec!["alice".into(), "bob".into(), "carol".into()]),
+            ..session
+        });
+        mine.save(Session {
+            drivers: Drivers::new(vec!["alice".into(), "bob".into(), "dave".into()]),
+            state: State::WaitingForNext {
+                next: Some("alice".into()),
+            },
+            history: session.history.clone().push(Record::ShortTurn {
+                driver: "bob".into(),
+                at: now,
+            }),
+            ..session
+        })
+        .unwrap();
+
+        let saved = SessionStore::new(&racing.memory).load().unwrap();
+        assert_eq!(
+            saved.state,
+            State::WaitingForNext {
+                next: Some("alice".into())
+            }
+        );
+        assert_eq!(
+            saved.drivers,
+            Drivers::new(vec![
+                "alice".into(),
+                "bob".into(),
+                "carol".into(),
+                "dave".into()
+            ])
+        );
+        assert!(matches!(
+            saved.history.records(),
+            [Record::Sync { .. }, Record::ShortTurn { .. }]
+        ));
+        // The next save is on top of what was saved
+        mine.save(Session {
+            state: State::Stopped,
+            ..saved
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn gives_up_when_others_advanced_the_session() {
+        let racing = RacingStore::default();
+        SessionStore::new(&racing).save(working("bob")).unwrap();
+        let mine = SessionStore::new(&racing);
+        let session = mine.load().unwrap();
+
+        racing.others_save(|session| Session {
+            state: State::WaitingForNext { next: None },
+            ..session
+        });
+        match mine.save(Session {
+            state: State::Working {
+                driver: "alice".into(),
+            },
+            ..session
+        }) {
+            Err(Error::Overtaken(conflict)) => assert_eq!(conflict.to_string(), "state"),
+            other => panic!("Should conflict but got {:?}", other),
+        }
+        assert_eq!(
+            SessionStore::new(&racing.memory).load().unwrap().state,
+            State::WaitingForNext { next: None }
+        );
+    }
+
+    #[test]
+    fn stops_retrying_eventually() {
+        let racing = RacingStore::default();
+        SessionStore::new(&racing).save(working("bob")).unwrap();
+        let mine = SessionStore::new(&racing);
+        let session = mine.load().unwrap();
+        for _ in 0..=SAVE_RETRIES {
+            racing.others.borrow_mut().push((
+                PROGRESS_DOCUMENT.to_string(),
+                racing.memory.load(PROGRESS_DOCUMENT).unwrap(),
+            ));
+        }
+
+        match mine.save(Session {
+            state: State::Stopped,
+            ..session
+        }) {
+            Err(Error::Git(git::store::Error::Changed(document))) => {
+                assert_eq!(document, PROGRESS_DOCUMENT)
+            }
+            other => panic!("Should give up but got {:?}", other),
+        }
+        assert_eq!(racing.others.borrow().len(), 0);
+    }
+
+    #[test]
+    fn clean_removes_both() {
+        let memory = MemoryStore::default();
+        let store = SessionStore::new(&memory);
+        store.save(working("bob")).unwrap();
+        store.clean().unwrap();
+        assert!(memory.documents.borrow().is_empty());
+        assert_eq!(store.load().unwrap(), Session::default());
+    }
+
+    #[test]
+    fn named_sessions_keep_apart() {
+        let memory = MemoryStore::default();
+        SessionStore::new(&memory).save(working("bob")).unwrap();
+        let payments = SessionStore::named(&memory, "payments");
+        assert_eq!(payments.load().unwrap().branches.branch, "mob-session-payments");
+        payments.save(working("alice")).unwrap();
+
+        assert_eq!(
+            SessionStore::names(&memory).unwrap(),
+            vec!["default", "payments"]
+        );
+        assert!(memory.documents.borrow().contains_key("state-payments"));
+        assert_eq!(
+            SessionStore::new(&memory).load().unwrap().state,
+            working("bob").state
+        );
+
+        payments.clean().unwrap();
+        assert_eq!(SessionStore::names(&memory).unwrap(), vec!["default"]);
+        assert!(!SessionStore::valid_name("pay ments"));
+        assert!(SessionStore::valid_name("payments_2"));
+    }
+
+    #[test]
+    fn warns_about_duplicate_drivers() {
+        let memory = MemoryStore::default();
+        let store = SessionStore::new(&memory);
+        crate::warnings::take();
+        store
+            .save(Session {
+                drivers: Drivers::new(vec!["anna".into(), "Anna".into()]),
+                ..Session::default()
+            })
+            .unwrap();
+        store.load().unwrap();
+        let warnings = crate::warnings::take();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].code, "duplicate-drivers");
+    }
+
+    #[test]
+    fn forgets_who_is_back() {
+        use crate::session::away::{transition, Change};
+        let memory = MemoryStore::default();
+        let store = SessionStore::new(&memory);
+        let now = chrono::Utc::now();
+        let session = Session {
+            drivers: Drivers::new(vec!["alice".into(), "bob".into()]),
+            ..Session::default()
+        };
+        let back_before = now - chrono::Duration::minutes(1);
+        let session = transition(
+            session,
+            "alice",
+            Change::Away { until: back_before },
+            now - chrono::Duration::hours(1),
+        )
+        .unwrap();
+        let back_later = now + chrono::Duration::hours(1);
+        let session = transition(session, "bob", Change::Away { until: back_later }, now).unwrap();
+        store.save(session).unwrap();
+
+        let away = store.load().unwrap().away;
+        assert_eq!(
+            away.until("alice", back_before - chrono::Duration::hours(1)),
+            None
+        );
+        assert_eq!(away.until("bob", now), Some(back_later));
+    }
+
+    /// Takes its time like a push over a slow network and fails once the deadline passed
+    #[derive(Default)]
+    struct SlowStore(MemoryStore);
+
+    impl git::Store for SlowStore {
+        fn load(&self, document: &str) -> Result<Vec<u8>, git::store::Error> {
+            self.0.load(document)
+        }
+
+        fn save(&self, document: &str, data: &[u8]) -> Result<(), git::store::Error> {
+            std::thread::sleep(std::time::Duration::from_millis(30));
+            deadline::check().map_err(|err| git::store::Error::Conflict(err.into()))?;
+            self.0.save(document, data)
+        }
+
+        fn clean(&self, document: &str) -> Result<(), git::store::Error> {
+            self.0.clean(document)
+        }
+    }
+
+    #[test]
+    fn saves_whole_past_the_deadline() {
+        let slow = SlowStore::default();
+        let store = SessionStore::new(&slow);
+        let session = working("bob");
+        deadline::set(Some(std::time::Duration::from_millis(10)));
+        let saved = store.save(session.clone());
+        let after = deadline::check();
+        deadline::set(None);
+
+        saved.unwrap();
+        assert_eq!(after, Err(deadline::Error::TimedOut));
+        assert_eq!(slow.0.documents.borrow().len(), 2);
+        assert_eq!(SessionStore::new(&slow.0).load().unwrap(), session);
     }
 }
@@ -1,42 +1,319 @@
+use crate::timekeeping::{ClockTime, Minutes};
+use crate::timezone::Zone;
+use crate::{deadline, prompt, record, warnings};
 use anyhow::Result;
-use dialoguer::Input;
 use serde::{Deserialize, Serialize};
 
-type DurationMinutes = i64;
+/// Turns longer than this were likely meant as seconds or typed one digit too many
+const LONGEST_LIKELY_TURN: Minutes = Minutes(180);
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+/// A break the mob takes at the same time every day, like lunch
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Window {
+    pub name: String,
+    pub start: ClockTime,
+    pub end: ClockTime,
+}
+
+impl Window {
+    pub fn contains(&self, time: ClockTime) -> bool {
+        self.start <= time && time < self.end
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(from = "StoredSettings")]
 pub struct Settings {
     pub commit_message: String,
-    pub work_duration: DurationMinutes,
+    pub work_duration: Minutes,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub break_duration: Option<Minutes>,
+    /// Minutes of work after which a break is suggested
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub break_interval: Option<Minutes>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub windows: Vec<Window>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub workday_end: Option<ClockTime>,
+    /// Minutes a turn lasts before `mob next` hands over without asking, 0 for any
+    #[serde(default, skip_serializing_if = "Minutes::is_zero")]
+    pub min_turn_minutes: Minutes,
+    /// IANA name of the timezone of `windows` and `workday_end`, everyone's own without one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<String>,
+}
+
+/// Settings as stored, with the single lunch of older versions
+#[derive(Deserialize)]
+struct StoredSettings {
+    commit_message: String,
+    work_duration: Minutes,
+    #[serde(default)]
+    break_duration: Option<Minutes>,
+    #[serde(default)]
+    break_interval: Option<Minutes>,
+    #[serde(default)]
+    windows: Vec<Window>,
+    /// Deprecated for `windows`
+    #[serde(default)]
+    lunch_start: Option<ClockTime>,
+    /// Deprecated for `windows`
+    #[serde(default)]
+    lunch_end: Option<ClockTime>,
+    #[serde(default)]
+    workday_end: Option<ClockTime>,
+    #[serde(default)]
+    min_turn_minutes: Minutes,
+    #[serde(default)]
+    timezone: Option<String>,
+}
+
+impl From<StoredSettings> for Settings {
+    fn from(stored: StoredSettings) -> Self {
+        let mut windows = stored.windows;
+        if windows.is_empty() {
+            windows.extend(lunch(stored.lunch_start, stored.lunch_end));
+        }
+        Self {
+            commit_message: stored.commit_message,
+            work_duration: stored.work_duration,
+            break_duration: stored.break_duration,
+            break_interval: stored.break_interval,
+            windows,
+            workday_end: stored.workday_end,
+            min_turn_minutes: stored.min_turn_minutes,
+            timezone: stored.timezone,
+        }
+    }
+}
+
+/// The window of the old `lunch_start` and `lunch_end` settings
+pub fn lunch(start: Option<ClockTime>, end: Option<ClockTime>) -> Option<Window> {
+    match (start, end) {
+        (Some(start), Some(end)) if start < end => Some(Window {
+            name: "Lunch".into(),
+            start,
+            end,
+        }),
+        _ => None,
+    }
 }
 
 impl Default for Settings {
     fn default() -> Self {
         Self {
             commit_message: "mob sync [skip ci]".into(),
-            work_duration: 10,
+            work_duration: Minutes(10),
+            break_duration: None,
+            break_interval: None,
+            windows: vec![],
+            workday_end: None,
+            min_turn_minutes: Minutes(0),
+            timezone: None,
         }
     }
 }
 
+/// Parses `Tea 10:00-10:15, Lunch 12:00-13:00`, a window without a name is lunch
+fn parse_windows(windows: &str) -> Option<Vec<Window>> {
+    windows
+        .split(',')
+        .filter(|window| !window.trim().is_empty())
+        .map(|window| {
+            let window = window.trim();
+            let (name, times) = match window.rsplit_once(' ') {
+                Some((name, times)) => (name.trim(), times),
+                None => ("Lunch", window),
+            };
+            let (start, end) = times.split_once('-')?;
+            let start: ClockTime = start.parse().ok()?;
+            let end: ClockTime = end.parse().ok()?;
+            Some(Window {
+                name: name.to_string(),
+                start,
+                end,
+            })
+            .filter(|window| window.start < window.end)
+        })
+        .collect()
+}
+
 impl Settings {
+    /// Durations that are valid but likely not what was meant
+    pub fn suspicious(&self) -> Vec<String> {
+        let mut found = vec![];
+        if self.work_duration > LONGEST_LIKELY_TURN {
+            found.push(format!(
+                "turns last {} minutes, more than 3 hours",
+                self.work_duration
+            ));
+        }
+        let breaks = self.break_interval.filter(|every| !every.is_zero()).is_some();
+        if breaks && self.break_duration == Some(Minutes(0)) {
+            found.push("breaks are suggested but last less than a minute".into());
+        }
+        found
+    }
+
+    /// The zone of `timezone`, `None` without one or when this machine doesn't know it
+    pub fn zone(&self) -> Option<Zone> {
+        match Zone::find(self.timezone.as_deref()?) {
+            Ok(zone) => Some(zone),
+            Err(err) => {
+                log::warn!("{}, breaks follow your local time", err);
+                None
+            }
+        }
+    }
+
+    /// Warns about the `suspicious` durations, if there are any
+    pub fn warn_suspicious(&self) {
+        let found = self.suspicious();
+        if !found.is_empty() {
+            warnings::push("suspicious-duration", found.join(", "), "0.1.7");
+        }
+    }
+
     pub fn ask() -> Result<Self> {
         let default = Settings::default();
+        if let Some(default) = deadline::answer("Session settings", Some(default.clone()))? {
+            return Ok(default);
+        }
 
-        let commit_message = Input::new()
-            .with_prompt("Commit message")
-            .default(default.commit_message)
-            .interact()?;
+        let prompt = prompt::terminal();
+        let commit_message = prompt.text("Commit message", &default.commit_message)?;
 
-        let work_duration = Input::new()
-            .with_prompt("Work duration")
-            .default(default.work_duration)
-            .interact()?;
+        let work_duration = prompt::parse(prompt, "Work duration", default.work_duration)?;
+
+        let break_interval: Minutes = prompt::parse(
+            prompt,
+            "Suggest a break after this many minutes, 0 for never",
+            Minutes(0),
+        )?;
+
+        let windows = prompt.input(
+            "Fixed breaks as Tea 10:00-10:15, Lunch 12:00-13:00, empty for none",
+            Some(""),
+            &|windows| {
+                if parse_windows(windows).is_some() {
+                    Ok(())
+                } else {
+                    Err("Use the form Lunch 12:00-13:00, separated by commas".into())
+                }
+            },
+        )?;
+
+        let windows = parse_windows(&windows).unwrap_or_default();
+        let timezone = match windows.is_empty() {
+            true => String::new(),
+            false => prompt.input(
+                "Timezone of the fixed breaks like Europe/Berlin, empty for everyone's own",
+                Some(""),
+                &|zone| match zone.trim() {
+                    "" => Ok(()),
+                    zone => Zone::find(zone).map(drop).map_err(|err| err.to_string()),
+                },
+            )?,
+        };
 
         let config = Self {
             commit_message,
             work_duration,
+            break_interval: Some(break_interval).filter(|minutes| !minutes.is_zero()),
+            windows,
+            timezone: Some(timezone.trim().to_string()).filter(|zone| !zone.is_empty()),
+            ..default
         };
-        Ok(config)
+        Ok(record::answer("Session settings", config))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(h: u32, m: u32) -> ClockTime {
+        ClockTime::hm(h, m)
+    }
+
+    fn window(name: &str, start: ClockTime, end: ClockTime) -> Window {
+        Window {
+            name: name.into(),
+            start,
+            end,
+        }
+    }
+
+    #[test]
+    fn finds_suspicious_durations() {
+        let settings = |work, length: Option<i64>, every: Option<i64>| Settings {
+            work_duration: Minutes(work),
+            break_duration: length.map(Minutes),
+            break_interval: every.map(Minutes),
+            ..Settings::default()
+        };
+        let cases = vec![
+            (settings(10, None, None), 0),
+            (settings(180, None, None), 0),
+            (settings(181, None, None), 1),
+            (settings(10, Some(0), Some(60)), 1),
+            (settings(10, Some(0), Some(0)), 0),
+            (settings(10, Some(0), None), 0),
+            (settings(10, Some(1), Some(60)), 0),
+            (settings(600, Some(0), Some(60)), 2),
+        ];
+        for (settings, count) in cases {
+            assert_eq!(settings.suspicious().len(), count, "{:?}", settings);
+        }
+    }
+
+    #[test]
+    fn windows() {
+        assert_eq!(
+            parse_windows("11:30-12:30"),
+            Some(vec![window("Lunch", time(11, 30), time(12, 30))])
+        );
+        assert_eq!(
+            parse_windows(" Morning tea 10:00-10:15 , Lunch 12:00-13:15 "),
+            Some(vec![
+                window("Morning tea", time(10, 0), time(10, 15)),
+                window("Lunch", time(12, 0), time(13, 15))
+            ])
+        );
+        assert_eq!(parse_windows(""), Some(vec![]));
+        assert_eq!(parse_windows("Lunch 13:00-12:00"), None);
+        assert_eq!(parse_windows("Lunch 12:00-13:00, noon"), None);
+        assert_eq!(parse_windows("noon"), None);
+    }
+
+    #[test]
+    fn reads_the_old_lunch() {
+        let old: Settings = serde_json::from_str(
+            r#"{"commit_message": "sync", "work_duration": 10,
+                "lunch_start": "11:30:00", "lunch_end": "12:30:00"}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            old.windows,
+            vec![window("Lunch", time(11, 30), time(12, 30))]
+        );
+        let json = serde_json::to_string(&old).unwrap();
+        assert!(!json.contains("lunch_start"), "{}", json);
+        assert_eq!(serde_json::from_str::<Settings>(&json).unwrap(), old);
+
+        // The list wins over the old keys
+        let both: Settings = serde_json::from_str(
+            r#"{"commit_message": "sync", "work_duration": 10,
+                "lunch_start": "11:30:00", "lunch_end": "12:30:00",
+                "windows": [{"name": "Tea", "start": "10:00:00", "end": "10:15:00"}]}"#,
+        )
+        .unwrap();
+        assert_eq!(both.windows, vec![window("Tea", time(10, 0), time(10, 15))]);
+
+        let half: Settings = serde_json::from_str(
+            r#"{"commit_message": "sync", "work_duration": 10, "lunch_start": "11:30:00"}"#,
+        )
+        .unwrap();
+        assert!(half.windows.is_empty());
     }
 }
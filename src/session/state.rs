@@ -1,8 +1,24 @@
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
 pub enum State {
     Stopped,
-    Working { driver: String },
-    WaitingForNext { next: Option<String> },
+    Working {
+        driver: String,
+    },
+    WaitingForNext {
+        next: Option<String>,
+    },
+    /// Parked by `mob emergency`, anyone resumes it with `mob start`, or interrupted by
+    /// `mob pause` and resumed with `mob resume`
+    Paused {
+        reason: String,
+        /// Who drove when `mob pause` interrupted the turn, the turn is theirs again
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        driver: Option<String>,
+        /// Seconds that were left of the interrupted turn
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        remaining: Option<i64>,
+    },
 }
@@ -0,0 +1,263 @@
+//! What a session leaves on this machine, cleared when `mob done` or `mob clean` ends it.
+//! Each item is cleared on its own, one that can't be doesn't keep the others.
+
+use super::seen_store::LAST_SEEN_FILE;
+use crate::os;
+use crate::state_dir::StateDir;
+use crate::timer::{Detached, DETACHED_FILE};
+use chrono::{DateTime, Utc};
+use std::path::PathBuf;
+use std::{fmt, fs, io};
+
+/// Ends the processes of detached timers
+pub trait Processes {
+    /// Ends `pid`, `false` when it had exited already
+    fn terminate(&self, pid: u32) -> Result<bool, String>;
+}
+
+/// The processes of this machine
+pub struct System;
+
+impl Processes for System {
+    fn terminate(&self, pid: u32) -> Result<bool, String> {
+        os::terminate(pid)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Artifact {
+    /// A detached timer that would still tell the session is up
+    Timer(u32),
+    /// A file of the state dir only the ended session needs
+    File(PathBuf),
+}
+
+impl fmt::Display for Artifact {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Artifact::Timer(pid) => write!(f, "the timer with process id {}", pid),
+            Artifact::File(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Cleared {
+    Removed,
+    /// The timer had exited or the file was removed before
+    Gone,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Outcome {
+    pub artifact: Artifact,
+    /// Why it could not be cleared otherwise
+    pub result: Result<Cleared, String>,
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Teardown {
+    pub outcomes: Vec<Outcome>,
+}
+
+impl Teardown {
+    pub fn failures(&self) -> impl Iterator<Item = (&Artifact, &str)> {
+        self.outcomes
+            .iter()
+            .filter_map(|outcome| match &outcome.result {
+                Err(reason) => Some((&outcome.artifact, reason.as_str())),
+                Ok(_) => None,
+            })
+    }
+
+    /// Tells what was stopped and warns about everything left behind
+    pub fn report(&self) {
+        let stopped = self
+            .outcomes
+            .iter()
+            .filter(|outcome| {
+                matches!(outcome.artifact, Artifact::Timer(_))
+                    && outcome.result == Ok(Cleared::Removed)
+            })
+            .count();
+        if stopped > 0 {
+            log::info!("Stopped {} detached timers of the session", stopped);
+        }
+        for (artifact, reason) in self.failures() {
+            log::warn!("Could not clear {}: {}", artifact, reason);
+        }
+    }
+}
+
+fn remove(path: PathBuf) -> Outcome {
+    let result = match fs::remove_file(&path) {
+        Ok(()) => Ok(Cleared::Removed),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Cleared::Gone),
+        Err(err) => Err(err.to_string()),
+    };
+    Outcome {
+        artifact: Artifact::File(path),
+        result,
+    }
+}
+
+/// Stops the detached timers that aren't due yet and removes the files of the session.
+/// Timers that could not be stopped stay listed for the next teardown to try again.
+pub fn teardown(state_dir: &StateDir, processes: &dyn Processes, now: DateTime<Utc>) -> Teardown {
+    let mut outcomes = vec![];
+    let mut left = vec![];
+    let detached: Vec<Detached> = state_dir.read(DETACHED_FILE).unwrap_or_default();
+    for timer in detached {
+        // A due timer has run its commands and exited, its process id may be another's now
+        let result = match timer.due <= now {
+            true => Ok(Cleared::Gone),
+            false => processes.terminate(timer.pid).map(|stopped| match stopped {
+                true => Cleared::Removed,
+                false => Cleared::Gone,
+            }),
+        };
+        if result.is_err() {
+            left.push(timer.clone());
+        }
+        outcomes.push(Outcome {
+            artifact: Artifact::Timer(timer.pid),
+            result,
+        });
+    }
+    if left.is_empty() {
+        outcomes.push(remove(state_dir.file(DETACHED_FILE)));
+    } else if let Err(err) = state_dir.write(DETACHED_FILE, &left) {
+        log::trace!("Could not list the timers left: {:#}", err);
+    }
+
+    // What was seen of the ended session would be told as changes of the next one
+    outcomes.push(remove(state_dir.file(LAST_SEEN_FILE)));
+    // Writes that were cut short
+    if let Ok(entries) = fs::read_dir(state_dir.path()) {
+        let mut partial: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|extension| extension == "tmp"))
+            .collect();
+        partial.sort();
+        outcomes.extend(partial.into_iter().map(remove));
+    }
+    Teardown { outcomes }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+    use std::collections::HashMap;
+
+    /// Answers for each process id as told
+    #[derive(Default)]
+    struct FakeProcesses(HashMap<u32, Result<bool, String>>);
+
+    impl Processes for FakeProcesses {
+        fn terminate(&self, pid: u32) -> Result<bool, String> {
+            self.0
+                .get(&pid)
+                .cloned()
+                .unwrap_or_else(|| panic!("{} should not be terminated", pid))
+        }
+    }
+
+    fn now() -> DateTime<Utc> {
+        Utc.ymd(2021, 6, 1).and_hms(10, 0, 0)
+    }
+
+    fn timer(pid: u32, minutes: i64) -> Detached {
+        Detached {
+            pid,
+            due: now() + Duration::minutes(minutes),
+        }
+    }
+
+    #[test]
+    fn clears_every_artifact() {
+        let dir = tempfile::tempdir().unwrap();
+        let state_dir = StateDir::new(dir.path().to_path_buf());
+        state_dir
+            .write(
+                DETACHED_FILE,
+                &vec![timer(11, 5), timer(12, 5), timer(13, -5)],
+            )
+            .unwrap();
+        state_dir.write(LAST_SEEN_FILE, &"seen").unwrap();
+        state_dir.write("backup.json", &"kept").unwrap();
+        fs::write(state_dir.file("events.jsonl"), "").unwrap();
+        // Left by a write of backup.json that was cut short
+        fs::write(state_dir.file("backup.tmp"), "").unwrap();
+        let processes = FakeProcesses(vec![(11, Ok(true)), (12, Ok(false))].into_iter().collect());
+
+        let teardown = teardown(&state_dir, &processes, now());
+        let results: Vec<_> = teardown
+            .outcomes
+            .iter()
+            .map(|outcome| (outcome.artifact.to_string(), outcome.result.clone()))
+            .collect();
+        let file = |name| state_dir.file(name).display().to_string();
+        assert_eq!(
+            results,
+            vec![
+                ("the timer with process id 11".into(), Ok(Cleared::Removed)),
+                ("the timer with process id 12".into(), Ok(Cleared::Gone)),
+                // Due already, not signalled at all
+                ("the timer with process id 13".into(), Ok(Cleared::Gone)),
+                (file(DETACHED_FILE), Ok(Cleared::Removed)),
+                (file(LAST_SEEN_FILE), Ok(Cleared::Removed)),
+                (file("backup.tmp"), Ok(Cleared::Removed)),
+            ]
+        );
+        assert_eq!(teardown.failures().count(), 0);
+        assert!(state_dir.file("backup.json").exists());
+        assert!(state_dir.file("events.jsonl").exists());
+
+        // Nothing is left to clear a second time
+        let again = super::teardown(&state_dir, &processes, now());
+        assert!(again
+            .outcomes
+            .iter()
+            .all(|outcome| outcome.result == Ok(Cleared::Gone)));
+    }
+
+    #[test]
+    fn tells_what_is_left_behind() {
+        let dir = tempfile::tempdir().unwrap();
+        let state_dir = StateDir::new(dir.path().to_path_buf());
+        state_dir
+            .write(DETACHED_FILE, &vec![timer(21, 5), timer(22, 5)])
+            .unwrap();
+        // Can't be removed as a file, like one of another user or in a read-only directory
+        fs::create_dir_all(state_dir.file(LAST_SEEN_FILE).join("inside")).unwrap();
+        let processes = FakeProcesses(
+            vec![
+                (21, Err("Operation not permitted".to_string())),
+                (22, Ok(true)),
+            ]
+            .into_iter()
+            .collect(),
+        );
+
+        let teardown = teardown(&state_dir, &processes, now());
+        let failures: Vec<_> = teardown
+            .failures()
+            .map(|(artifact, _)| artifact.clone())
+            .collect();
+        assert_eq!(
+            failures,
+            vec![
+                Artifact::Timer(21),
+                Artifact::File(state_dir.file(LAST_SEEN_FILE))
+            ]
+        );
+        assert_eq!(
+            teardown.failures().next().unwrap().1,
+            "Operation not permitted"
+        );
+        // The timer is tried again next time
+        let left: Vec<Detached> = state_dir.read(DETACHED_FILE).unwrap();
+        assert_eq!(left, vec![timer(21, 5)]);
+    }
+}
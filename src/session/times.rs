@@ -0,0 +1,67 @@
+use crate::timekeeping::{Instant, Minutes};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// When things last happened, the inputs of the schedule
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Times {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub started: Option<Instant>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_break: Option<Instant>,
+    /// Last break in one of the windows of the settings
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_lunch: Option<Instant>,
+    /// When each window was last taken, by name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub windows: BTreeMap<String, Instant>,
+    /// Last start or next
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_activity: Option<Instant>,
+    /// When the session started waiting for the next driver
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub waiting_since: Option<Instant>,
+    /// When the next turn was claimed with mob claim
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub claimed: Option<Instant>,
+    /// When the driver ran mob start for the turn at hand, on their clock
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub turn_started: Option<Instant>,
+    /// How long the timer of that turn runs, `mob start 7` makes it shorter than the
+    /// work duration
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub turn_minutes: Option<Minutes>,
+    /// End of the break or lunch the mob is on
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub break_until: Option<Instant>,
+}
+
+impl Times {
+    /// Since when the mob has worked without a break or lunch
+    pub fn rested(&self) -> Option<Instant> {
+        vec![self.started, self.last_break, self.last_lunch]
+            .into_iter()
+            .flatten()
+            .max()
+    }
+
+    /// When the window `name` was last taken. Sessions of older versions only know of lunch.
+    pub fn window_taken(&self, name: &str) -> Option<Instant> {
+        if self.windows.is_empty() && name == "Lunch" {
+            return self.last_lunch;
+        }
+        self.windows.get(name).copied()
+    }
+
+    /// The window of the break taken last, if that was one
+    pub fn last_window(&self) -> Option<&str> {
+        if self.last_lunch <= self.last_break {
+            return None;
+        }
+        self.windows
+            .iter()
+            .max_by_key(|(_, at)| **at)
+            .map(|(name, _)| name.as_str())
+            .or(Some("Lunch"))
+    }
+}
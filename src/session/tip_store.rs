@@ -0,0 +1,161 @@
+use super::latest::{Session, State};
+use super::session_store::{Result, Store};
+use crate::git::Git;
+use std::collections::BTreeMap;
+
+/// Records where the mob branch is on the remote with every save, so commits pushed past mob
+/// show up as the branch having moved since.
+pub struct TipStore<'a> {
+    store: &'a dyn Store,
+    git: &'a dyn Git,
+    remote: String,
+}
+
+impl<'a> TipStore<'a> {
+    pub fn new(store: &'a impl Store, git: &'a impl Git, remote: &str) -> Self {
+        TipStore {
+            store,
+            git,
+            remote: remote.to_string(),
+        }
+    }
+
+    /// The remote-tracking branch, unless it wasn't fetched since another machine recorded
+    /// a newer tip
+    fn tip(&self, session: &Session) -> Option<String> {
+        if !self.git.is_repository() || session.state == State::Stopped {
+            return None;
+        }
+        let tracking = format!("{}/{}", self.remote, session.branches.branch);
+        let tracked = match self.git.rev_parse(&tracking) {
+            Ok(Some(tracked)) => tracked,
+            Ok(None) => return session.tip.clone(),
+            Err(err) => {
+                log::trace!("Could not read {}: {}", tracking, err);
+                return session.tip.clone();
+            }
+        };
+        let recorded = match &session.tip {
+            Some(recorded) if *recorded != tracked => recorded,
+            _ => return Some(tracked),
+        };
+        match self.git.merge_base(recorded, &tracked) {
+            // Behind what was recorded
+            Ok(Some(base)) if base == tracked => Some(recorded.clone()),
+            Ok(_) => Some(tracked),
+            Err(err) => {
+                log::trace!("Could not compare {} to {}: {}", tracking, recorded, err);
+                Some(recorded.clone())
+            }
+        }
+    }
+
+    /// The remote-tracking branch in each repository of a workspace, as recorded before
+    /// where it can't be read
+    fn tips(&self, session: &Session) -> BTreeMap<String, String> {
+        if session.state == State::Stopped {
+            return BTreeMap::new();
+        }
+        let tracking = format!("{}/{}", self.remote, session.branches.branch);
+        let mut tips = BTreeMap::new();
+        for (name, git) in self.git.members() {
+            let tip = match git.rev_parse(&tracking) {
+                Ok(Some(tip)) => Some(tip),
+                Ok(None) => session.tips.get(name).cloned(),
+                Err(err) => {
+                    log::trace!("Could not read {} in {}: {}", tracking, name, err);
+                    session.tips.get(name).cloned()
+                }
+            };
+            if let Some(tip) = tip {
+                tips.insert(name.to_string(), tip);
+            }
+        }
+        tips
+    }
+}
+
+impl<'a> Store for TipStore<'a> {
+    fn load(&self) -> Result<Session> {
+        self.store.load()
+    }
+
+    fn save(&self, session: Session) -> Result<()> {
+        let tip = self.tip(&session);
+        let tips = self.tips(&session);
+        self.store.save(Session {
+            tip,
+            tips,
+            ..session
+        })
+    }
+
+    fn clean(&self) -> Result<()> {
+        self.store.clean()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::fake::{FakeGit, MemoryStore};
+    use crate::git::workspace::Workspace;
+    use crate::session::SessionStore;
+
+    fn waiting(tip: Option<&str>) -> Session {
+        Session {
+            state: State::WaitingForNext { next: None },
+            tip: tip.map(String::from),
+            ..Session::default()
+        }
+    }
+
+    fn saved(git: &FakeGit, session: Session) -> Option<String> {
+        let memory = MemoryStore::default();
+        let sessions = SessionStore::new(&memory);
+        TipStore::new(&sessions, git, "origin")
+            .save(session)
+            .unwrap();
+        sessions.load().unwrap().tip
+    }
+
+    #[test]
+    fn records_the_tip() {
+        let mut git = FakeGit::default();
+        assert_eq!(saved(&git, waiting(Some("a1"))), Some("a1".into()));
+
+        git.revs.insert("origin/mob-session".into(), "b2".into());
+        assert_eq!(saved(&git, waiting(None)), Some("b2".into()));
+        git.merge_base = Some("a1".into());
+        assert_eq!(saved(&git, waiting(Some("a1"))), Some("b2".into()));
+        // Others pushed b2 and this machine didn't fetch it yet
+        git.merge_base = Some("b2".into());
+        assert_eq!(saved(&git, waiting(Some("c3"))), Some("c3".into()));
+
+        assert_eq!(saved(&git, Session::default()), None);
+    }
+
+    #[test]
+    fn records_the_tip_of_each_repository() {
+        let mut api = FakeGit::default();
+        api.revs.insert("origin/mob-session".into(), "a1".into());
+        let web = FakeGit::default();
+        let workspace = Workspace::new(("api", &api), vec![("web", &web)]);
+        let memory = MemoryStore::default();
+        let sessions = SessionStore::new(&memory);
+        let store = TipStore::new(&sessions, &workspace, "origin");
+
+        let mut session = waiting(None);
+        session.tips.insert("web".into(), "b2".into());
+        store.save(session).unwrap();
+        let saved = sessions.load().unwrap();
+        assert_eq!(saved.tip.as_deref(), Some("a1"));
+        assert_eq!(
+            saved.tips.into_iter().collect::<Vec<_>>(),
+            [("api".into(), "a1".into()), ("web".into(), "b2".into())]
+        );
+
+        store.save(Session::default()).unwrap();
+        assert!(sessions.load().unwrap().tips.is_empty());
+    }
+}
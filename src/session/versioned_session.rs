@@ -8,6 +8,8 @@ pub struct Versioned {
     pub version: Option<String>,
 }
 
+// Only ever read and migrated right away, never kept around
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "version")]
 pub enum VersionedSession {
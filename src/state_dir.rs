@@ -0,0 +1,73 @@
+use anyhow::Result;
+use serde::{de::DeserializeOwned, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Directory for state that is local to this machine and never shared with the mob,
+/// `.git/mob` inside a repository.
+#[derive(Debug, Clone)]
+pub struct StateDir {
+    dir: PathBuf,
+}
+
+impl StateDir {
+    pub fn new(dir: PathBuf) -> Self {
+        StateDir { dir }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn file(&self, name: &str) -> PathBuf {
+        self.dir.join(name)
+    }
+
+    /// Missing or unreadable files are treated as absent, local state is only a convenience
+    pub fn read<T: DeserializeOwned>(&self, name: &str) -> Option<T> {
+        let data = fs::read(self.file(name)).ok()?;
+        match serde_json::from_slice(&data) {
+            Ok(value) => Some(value),
+            Err(err) => {
+                log::trace!("Ignoring unreadable {}: {}", name, err);
+                None
+            }
+        }
+    }
+
+    pub fn write<T: Serialize>(&self, name: &str, value: &T) -> Result<()> {
+        self.replace(name, &serde_json::to_vec_pretty(value)?)
+    }
+
+    /// Writes `data` to a file next to `name` first, so readers see either all of it or the
+    /// file as it was
+    pub fn replace(&self, name: &str, data: &[u8]) -> Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let path = self.file(name);
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, data)?;
+        fs::rename(&tmp, &path)?;
+        Ok(())
+    }
+
+    /// Adds `value` as one JSON line, for logs that only grow
+    pub fn append<T: Serialize>(&self, name: &str, value: &T) -> Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.file(name))?;
+        let mut line = serde_json::to_vec(value)?;
+        line.push(b'\n');
+        file.write_all(&line)?;
+        Ok(())
+    }
+
+    pub fn remove(&self, name: &str) -> Result<()> {
+        match fs::remove_file(self.file(name)) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err.into()),
+            _ => Ok(()),
+        }
+    }
+}
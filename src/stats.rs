@@ -0,0 +1,429 @@
+//! Reports of finished sessions, archived by `mob done` and summed up by
+//! `mob stats --all-sessions`.
+
+use crate::config::StatsConfig;
+use crate::session::{Record, Session};
+use crate::state_dir::StateDir;
+use crate::timekeeping::Instant;
+use crate::warnings;
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Report {
+    /// The id of the session, none for sessions from before ids
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session: Option<String>,
+    pub started: DateTime<Utc>,
+    pub ended: DateTime<Utc>,
+    /// Turns per driver
+    pub turns: BTreeMap<String, usize>,
+    /// Turns per driver handed over before the minimum turn length
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub short_turns: BTreeMap<String, usize>,
+    /// The branch `mob done --target` delivered to instead of the base branch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+    /// Its times go backwards, as when the clock jumped back, so its duration is made up
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub suspect: bool,
+}
+
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "version")]
+enum VersionedReport {
+    #[serde(rename = "v1")]
+    V1(Report),
+}
+
+impl Report {
+    /// The report of `session` ending at `ended`, `None` if it never started
+    pub fn of(session: &Session, ended: DateTime<Utc>) -> Option<Report> {
+        let records = session.history.records();
+        let started = session
+            .times
+            .started
+            .map(Instant::utc)
+            .or_else(|| records.first().map(Record::at))?;
+        let during: Vec<&Record> = records
+            .iter()
+            .filter(|record| record.at() >= started)
+            .collect();
+        let suspect = ended < started
+            || during.iter().any(|record| record.at() > ended)
+            || during.windows(2).any(|pair| pair[1].at() < pair[0].at());
+        let mut turns = BTreeMap::new();
+        let mut short_turns = BTreeMap::new();
+        for record in records {
+            match record {
+                Record::Turn { driver, at } if *at >= started => {
+                    *turns.entry(driver.clone()).or_insert(0) += 1
+                }
+                Record::ShortTurn { driver, at } if *at >= started => {
+                    *short_turns.entry(driver.clone()).or_insert(0) += 1
+                }
+                _ => (),
+            }
+        }
+        Some(Report {
+            session: session.id.clone(),
+            started,
+            ended,
+            turns,
+            short_turns,
+            target: None,
+            suspect,
+        })
+    }
+
+    /// Zero for reports that end before they start
+    pub fn duration(&self) -> Duration {
+        std::cmp::max(self.ended - self.started, Duration::zero())
+    }
+}
+
+/// Where reports are archived: the state directory and the shared one if configured
+pub fn dirs(state_dir: &StateDir, config: &StatsConfig) -> Vec<PathBuf> {
+    let mut dirs = vec![state_dir.file("reports")];
+    dirs.extend(config.shared_dir.clone());
+    dirs
+}
+
+/// Writes `report` into `dir` as a file named after its end, returning the path
+pub fn archive(dir: &Path, report: &Report) -> Result<PathBuf> {
+    fs::create_dir_all(dir)?;
+    let data = serde_json::to_vec_pretty(&VersionedReport::V1(report.clone()))?;
+    let stem = format!("report-{}", report.ended.format("%Y%m%dT%H%M%SZ"));
+    let mut attempt = 0;
+    loop {
+        let name = match attempt {
+            0 => format!("{}.json", stem),
+            n => format!("{}-{}.json", stem, n),
+        };
+        let path = dir.join(name);
+        match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(mut file) => {
+                file.write_all(&data)?;
+                return Ok(path);
+            }
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => attempt += 1,
+            Err(err) => return Err(err.into()),
+        }
+    }
+}
+
+/// The reports in `dirs` of sessions that ended at `since` or later. Unreadable files are
+/// skipped with a warning and a report archived in several of them counts once.
+pub fn read(dirs: &[PathBuf], since: Option<DateTime<Utc>>) -> Vec<Report> {
+    let mut reports: Vec<Report> = vec![];
+    let mut skipped = vec![];
+    for dir in dirs {
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(err) => {
+                log::trace!("No archive in {}: {}", dir.display(), err);
+                continue;
+            }
+        };
+        let mut paths: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension() == Some("json".as_ref()))
+            .collect();
+        paths.sort();
+        for path in paths {
+            let report = fs::read(&path)
+                .ok()
+                .and_then(|data| serde_json::from_slice::<VersionedReport>(&data).ok());
+            match report {
+                Some(VersionedReport::V1(report)) => {
+                    if since.map(|since| report.ended >= since).unwrap_or(true)
+                        && !reports.contains(&report)
+                    {
+                        reports.push(report);
+                    }
+                }
+                None => skipped.push(path.display().to_string()),
+            }
+        }
+    }
+    if !skipped.is_empty() {
+        warnings::push(
+            "archive-unreadable",
+            format!("skipped unreadable session reports: {}", skipped.join(", ")),
+            "0.1.7",
+        );
+    }
+    reports
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Summary {
+    pub sessions: usize,
+    /// Sessions left out of the durations since their times go backwards
+    pub suspect: usize,
+    pub total: Duration,
+    pub turns: BTreeMap<String, usize>,
+    pub short_turns: BTreeMap<String, usize>,
+}
+
+impl Summary {
+    pub fn of(reports: &[Report]) -> Summary {
+        let mut summary = Summary {
+            sessions: 0,
+            suspect: 0,
+            total: Duration::zero(),
+            turns: BTreeMap::new(),
+            short_turns: BTreeMap::new(),
+        };
+        for report in reports {
+            if report.suspect {
+                summary.suspect += 1;
+            } else {
+                summary.sessions += 1;
+                summary.total = summary.total + report.duration();
+            }
+            for (driver, turns) in &report.turns {
+                *summary.turns.entry(driver.clone()).or_insert(0) += turns;
+            }
+            for (driver, turns) in &report.short_turns {
+                *summary.short_turns.entry(driver.clone()).or_insert(0) += turns;
+            }
+        }
+        summary
+    }
+
+    /// `None` without sessions
+    pub fn average(&self) -> Option<Duration> {
+        match self.sessions {
+            0 => None,
+            sessions => Some(self.total / sessions as i32),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::{History, Times};
+    use chrono::TimeZone;
+
+    fn at(day: u32, hour: u32) -> DateTime<Utc> {
+        Utc.ymd(2021, 6, day).and_hms(hour, 0, 0)
+    }
+
+    fn report(day: u32, hours: u32, turns: &[(&str, usize)]) -> Report {
+        Report {
+            session: None,
+            started: at(day, 9),
+            ended: at(day, 9 + hours),
+            turns: turns
+                .iter()
+                .map(|(driver, turns)| (driver.to_string(), *turns))
+                .collect(),
+            short_turns: BTreeMap::new(),
+            target: None,
+            suspect: false,
+        }
+    }
+
+    #[test]
+    fn reports_a_session() {
+        let turn = |driver: &str, hour| Record::Turn {
+            driver: driver.into(),
+            at: at(1, hour),
+        };
+        let session = Session {
+            id: Some("3f2a9c1e7b04".into()),
+            times: Times {
+                started: Some(at(1, 9).into()),
+                ..Times::default()
+            },
+            history: History::new(vec![
+                // Left from the session before
+                turn("carol", 8),
+                turn("alice", 9),
+                turn("bob", 10),
+                Record::Break { at: at(1, 11) },
+                turn("alice", 12),
+            ]),
+            ..Session::default()
+        };
+        assert_eq!(
+            Report::of(&session, at(1, 13)),
+            Some(Report {
+                session: Some("3f2a9c1e7b04".into()),
+                ..report(1, 4, &[("alice", 2), ("bob", 1)])
+            })
+        );
+        assert_eq!(Report::of(&Session::default(), at(1, 13)), None);
+    }
+
+    #[test]
+    fn marks_short_turns() {
+        let session = Session {
+            times: Times {
+                started: Some(at(1, 9).into()),
+                ..Times::default()
+            },
+            history: History::new(vec![
+                Record::ShortTurn {
+                    driver: "carol".into(),
+                    at: at(1, 8),
+                },
+                Record::Turn {
+                    driver: "alice".into(),
+                    at: at(1, 9),
+                },
+                Record::ShortTurn {
+                    driver: "alice".into(),
+                    at: at(1, 9),
+                },
+            ]),
+            ..Session::default()
+        };
+        let of_session = Report::of(&session, at(1, 10)).unwrap();
+        assert_eq!(of_session.turns["alice"], 1);
+        assert_eq!(
+            of_session.short_turns.into_iter().collect::<Vec<_>>(),
+            vec![("alice".to_string(), 1)]
+        );
+
+        let mut short = report(1, 2, &[("bob", 2)]);
+        short.short_turns.insert("bob".into(), 1);
+        let summary = Summary::of(&[short.clone(), short, report(2, 1, &[("bob", 1)])]);
+        assert_eq!(summary.turns["bob"], 5);
+        assert_eq!(summary.short_turns["bob"], 2);
+        // Reports from before are read without them
+        let old: Report = serde_json::from_str(
+            r#"{"started":"2021-06-01T09:00:00Z","ended":"2021-06-01T10:00:00Z","turns":{"bob":1}}"#,
+        )
+        .unwrap();
+        assert!(old.short_turns.is_empty());
+    }
+
+    #[test]
+    fn marks_backwards_sessions_suspect() {
+        let turn = |hour| Record::Turn {
+            driver: "alice".into(),
+            at: at(1, hour),
+        };
+        let session = |records| Session {
+            times: Times {
+                started: Some(at(1, 9).into()),
+                ..Times::default()
+            },
+            history: History::new(records),
+            ..Session::default()
+        };
+        let fine = Report::of(&session(vec![turn(9), turn(10)]), at(1, 11)).unwrap();
+        assert!(!fine.suspect);
+        // Ended before it started
+        let backwards = Report::of(&session(vec![turn(9)]), at(1, 8)).unwrap();
+        assert!(backwards.suspect);
+        assert_eq!(backwards.duration(), Duration::zero());
+        // A turn after the end, and turns out of order
+        let late = Report::of(&session(vec![turn(9), turn(12)]), at(1, 11)).unwrap();
+        assert!(late.suspect);
+        let unordered = Report::of(&session(vec![turn(10), turn(9)]), at(1, 11)).unwrap();
+        assert!(unordered.suspect);
+
+        let summary = Summary::of(&[fine, backwards, unordered]);
+        assert_eq!(summary.sessions, 1);
+        assert_eq!(summary.suspect, 2);
+        assert_eq!(summary.average(), Some(Duration::hours(2)));
+        assert_eq!(summary.turns["alice"], 5);
+        // Reports from before are read as fine
+        let old: Report = serde_json::from_str(
+            r#"{"started":"2021-06-01T09:00:00Z","ended":"2021-06-01T10:00:00Z","turns":{}}"#,
+        )
+        .unwrap();
+        assert!(!old.suspect);
+    }
+
+    #[test]
+    fn archives_without_overwriting() {
+        let dir = tempfile::tempdir().unwrap();
+        let first = archive(dir.path(), &report(1, 2, &[("alice", 3)])).unwrap();
+        let second = archive(dir.path(), &report(1, 2, &[("bob", 1)])).unwrap();
+        assert_eq!(first.file_name().unwrap(), "report-20210601T110000Z.json");
+        assert_eq!(
+            second.file_name().unwrap(),
+            "report-20210601T110000Z-1.json"
+        );
+        assert_eq!(read(&[dir.path().into()], None).len(), 2);
+    }
+
+    #[test]
+    fn reads_fixtures() {
+        let local = tempfile::tempdir().unwrap();
+        let shared = tempfile::tempdir().unwrap();
+        archive(local.path(), &report(1, 2, &[("alice", 3)])).unwrap();
+        archive(local.path(), &report(8, 4, &[("alice", 2), ("bob", 2)])).unwrap();
+        // Archived in both, counts once
+        archive(shared.path(), &report(8, 4, &[("alice", 2), ("bob", 2)])).unwrap();
+        archive(shared.path(), &report(15, 6, &[("carol", 5)])).unwrap();
+        fs::write(
+            local.path().join("report-partial.json"),
+            "{\"version\": \"v1\", \"sta",
+        )
+        .unwrap();
+        fs::write(
+            shared.path().join("report-newer.json"),
+            "{\"version\": \"v9\"}",
+        )
+        .unwrap();
+        fs::write(shared.path().join("notes.txt"), "not a report").unwrap();
+        let dirs = vec![
+            local.path().to_path_buf(),
+            shared.path().to_path_buf(),
+            local.path().join("missing"),
+        ];
+
+        warnings::take();
+        let all = read(&dirs, None);
+        assert_eq!(all.len(), 3);
+        let skipped = warnings::take();
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(skipped[0].code, "archive-unreadable");
+        assert!(skipped[0].message.contains("report-partial.json"));
+        assert!(skipped[0].message.contains("report-newer.json"));
+
+        let recent = read(&dirs, Some(at(8, 0)));
+        assert_eq!(recent.len(), 2);
+        let summary = Summary::of(&recent);
+        assert_eq!(summary.sessions, 2);
+        assert_eq!(summary.total, Duration::hours(10));
+        assert_eq!(summary.average(), Some(Duration::hours(5)));
+        assert_eq!(
+            summary.turns.into_iter().collect::<Vec<_>>(),
+            vec![
+                ("alice".to_string(), 2),
+                ("bob".to_string(), 2),
+                ("carol".to_string(), 5)
+            ]
+        );
+    }
+
+    #[test]
+    fn summarizes() {
+        let summary = Summary::of(&[
+            report(1, 2, &[("alice", 3), ("bob", 1)]),
+            report(2, 3, &[("bob", 2)]),
+        ]);
+        assert_eq!(summary.sessions, 2);
+        assert_eq!(summary.total, Duration::hours(5));
+        assert_eq!(summary.average(), Some(Duration::minutes(150)));
+        assert_eq!(summary.turns["alice"], 3);
+        assert_eq!(summary.turns["bob"], 3);
+        assert_eq!(Summary::of(&[]).average(), None);
+    }
+}
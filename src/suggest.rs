@@ -0,0 +1,85 @@
+//! Names close to one that was mistyped, like `relese/1.4` for `release/1.4`.
+
+/// At most this many suggestions are made
+pub const MAX: usize = 3;
+
+/// Edits per character of the longer name up to which a name counts as close
+const CLOSE: f64 = 0.34;
+
+/// Characters to insert, delete or replace to turn `one` into `other`, ignoring case
+pub fn distance(one: &str, other: &str) -> usize {
+    let one: Vec<char> = one.to_lowercase().chars().collect();
+    let other: Vec<char> = other.to_lowercase().chars().collect();
+    let mut previous: Vec<usize> = (0..=other.len()).collect();
+    for (i, a) in one.iter().enumerate() {
+        let mut row = vec![i + 1];
+        for (j, b) in other.iter().enumerate() {
+            let replace = previous[j] + usize::from(a != b);
+            row.push(replace.min(previous[j + 1] + 1).min(row[j] + 1));
+        }
+        previous = row;
+    }
+    previous[other.len()]
+}
+
+/// The `candidates` close to `wanted`, closest first and ties in the order given
+pub fn close<'a>(wanted: &str, candidates: impl IntoIterator<Item = &'a str>) -> Vec<&'a str> {
+    let mut close: Vec<(usize, &str)> = candidates
+        .into_iter()
+        .filter(|candidate| *candidate != wanted)
+        .filter_map(|candidate| {
+            let distance = distance(wanted, candidate);
+            let longer = wanted.chars().count().max(candidate.chars().count());
+            Some((distance, candidate)).filter(|_| distance as f64 <= CLOSE * longer as f64)
+        })
+        .collect();
+    close.sort_by_key(|(distance, _)| *distance);
+    close
+        .into_iter()
+        .take(MAX)
+        .map(|(_, candidate)| candidate)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn distances() {
+        assert_eq!(distance("relese/1.4", "release/1.4"), 1);
+        assert_eq!(distance("main", "main"), 0);
+        assert_eq!(distance("Anna", "anna"), 0);
+        assert_eq!(distance("", "main"), 4);
+        assert_eq!(distance("Zoë", "Zoe"), 1);
+        assert_eq!(distance("李小龙", "李小"), 1);
+    }
+
+    #[test]
+    fn closest_first() {
+        let branches = ["master", "release/1.3", "release/1.4", "develop"];
+        assert_eq!(
+            close("relese/1.4", branches.iter().copied()),
+            vec!["release/1.4", "release/1.3"]
+        );
+        assert_eq!(close("mastr", branches.iter().copied()), vec!["master"]);
+        assert!(close("hotfix", branches.iter().copied()).is_empty());
+        assert!(close("master", branches.iter().copied()).is_empty());
+    }
+
+    #[test]
+    fn ties_keep_their_order() {
+        let names = ["Björn", "Bjarn", "bjorn", "Jörg"];
+        assert_eq!(
+            close("Bjørn", names.iter().copied()),
+            vec!["Björn", "Bjarn", "bjorn"]
+        );
+        let many = ["v1.1", "v1.2", "v1.3", "v1.4"];
+        assert_eq!(
+            close("v1.0", many.iter().copied()),
+            vec!["v1.1", "v1.2", "v1.3"]
+        );
+        // One edit is a lot for short names
+        assert!(close("v1", ["v2"].iter().copied()).is_empty());
+    }
+}
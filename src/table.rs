@@ -0,0 +1,160 @@
+//! Columns aligned by how wide their text shows in a terminal, so names with CJK characters or
+//! emoji line up and colours don't count.
+
+/// Display width of `text`, without escape codes
+pub fn width(text: &str) -> usize {
+    #[cfg(feature = "color")]
+    return console::measure_text_width(text);
+    // Nothing is styled, there are no escape codes
+    #[cfg(not(feature = "color"))]
+    return unicode_width::UnicodeWidthStr::width(text);
+}
+
+/// Columns of the terminal on stdout, `None` when it isn't one
+pub fn terminal_width() -> Option<usize> {
+    terminal_size().map(|(_, columns)| columns)
+}
+
+/// Rows and columns of the terminal on stdout, `None` when it isn't one
+pub fn terminal_size() -> Option<(usize, usize)> {
+    #[cfg(feature = "color")]
+    return console::Term::stdout()
+        .size_checked()
+        .map(|(rows, columns)| (rows as usize, columns as usize));
+    #[cfg(not(feature = "color"))]
+    return None;
+}
+
+/// Spaces between two columns
+const GAP: usize = 2;
+
+#[derive(Debug, Default)]
+pub struct Table {
+    rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    pub fn new() -> Self {
+        Table::default()
+    }
+
+    pub fn row(mut self, cells: Vec<String>) -> Self {
+        self.rows.push(cells);
+        self
+    }
+
+    fn widths(&self) -> Vec<usize> {
+        let mut widths = vec![];
+        for row in &self.rows {
+            for (column, cell) in row.iter().enumerate() {
+                if widths.len() <= column {
+                    widths.push(0);
+                }
+                widths[column] = widths[column].max(width(cell));
+            }
+        }
+        widths
+    }
+
+    /// Width of the widest line
+    pub fn width(&self) -> usize {
+        let widths = self.widths();
+        widths.iter().sum::<usize>() + GAP * widths.len().saturating_sub(1)
+    }
+
+    /// The lines with each column padded to its widest cell, `None` if they don't fit in
+    /// `max_width`
+    pub fn lines(&self, max_width: Option<usize>) -> Option<Vec<String>> {
+        if matches!(max_width, Some(max) if self.width() > max) {
+            return None;
+        }
+        let widths = self.widths();
+        Some(
+            self.rows
+                .iter()
+                .map(|row| {
+                    let mut line = String::new();
+                    for (column, cell) in row.iter().enumerate() {
+                        line.push_str(cell);
+                        if column + 1 < row.len() {
+                            let padding = widths[column] - width(cell) + GAP;
+                            line.push_str(&" ".repeat(padding));
+                        }
+                    }
+                    line
+                })
+                .collect(),
+        )
+    }
+}
+
+// The fixtures are styled
+#[cfg(all(test, feature = "color"))]
+mod tests {
+    use super::*;
+
+    fn fixture() -> Table {
+        Table::new()
+            .row(vec!["Driver".into(), "Today".into(), "Last".into()])
+            .row(vec!["alice".into(), "2".into(), "now".into()])
+            .row(vec!["李小龙".into(), "10".into(), "never".into()])
+            .row(vec!["🚗 Zoë".into(), "1".into(), "1 hour ago".into()])
+            .row(vec![
+                console::style("bob").red().force_styling(true).to_string(),
+                "0".into(),
+                "never".into(),
+            ])
+    }
+
+    /// Display column where each cell of `line` starts
+    fn starts(line: &str, cells: &[&str]) -> Vec<usize> {
+        let plain = console::strip_ansi_codes(line).to_string();
+        let mut rest = plain.as_str();
+        let mut consumed = 0;
+        cells
+            .iter()
+            .map(|cell| {
+                let at = rest.find(cell).expect("cell in line");
+                let start = consumed + width(&rest[..at]);
+                consumed = start + width(cell);
+                rest = &rest[at + cell.len()..];
+                start
+            })
+            .collect()
+    }
+
+    #[test]
+    fn widths() {
+        assert_eq!(width("alice"), 5);
+        assert_eq!(width("李小龙"), 6);
+        assert_eq!(width("🚗 Zoë"), 6);
+        assert_eq!(
+            width(&console::style("bob").red().force_styling(true).to_string()),
+            3
+        );
+    }
+
+    #[test]
+    fn aligns_wide_characters() {
+        let lines = fixture().lines(None).unwrap();
+        let cells: Vec<Vec<&str>> = vec![
+            vec!["Driver", "Today", "Last"],
+            vec!["alice", "2", "now"],
+            vec!["李小龙", "10", "never"],
+            vec!["🚗 Zoë", "1", "1 hour ago"],
+            vec!["bob", "0", "never"],
+        ];
+        for (line, cells) in lines.iter().zip(&cells) {
+            assert_eq!(starts(line, cells), vec![0, 8, 15], "{:?}", line);
+            assert!(!line.ends_with(' '));
+        }
+        assert_eq!(fixture().width(), 25);
+    }
+
+    #[test]
+    fn refuses_narrow_terminals() {
+        assert!(fixture().lines(Some(25)).is_some());
+        assert!(fixture().lines(Some(24)).is_none());
+        assert_eq!(Table::new().lines(Some(0)), Some(vec![]));
+    }
+}
@@ -0,0 +1,445 @@
+//! The kinds of time a session deals with, kept apart so they can't be mixed up: an `Instant`
+//! that happened once, a `ClockTime` that comes around every day like the start of lunch,
+//! and `Minutes` that something lasts. They are stored as the chrono values they wrap, so
+//! sessions read and write as before.
+//!
+//! A clock time only becomes an instant on a day in a timezone, `ClockTime::on` is the one
+//! place that happens.
+
+use chrono::{DateTime, Duration, LocalResult, NaiveDate, NaiveTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::ops::{Add, Sub};
+use std::str::FromStr;
+
+/// A point in time, always in UTC. Stored in RFC 3339.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Instant(DateTime<Utc>);
+
+impl Instant {
+    pub fn utc(self) -> DateTime<Utc> {
+        self.0
+    }
+
+    /// The wall-clock time it was in `zone`
+    pub fn clock_time<Tz: TimeZone>(self, zone: &Tz) -> ClockTime {
+        ClockTime(self.0.with_timezone(zone).time())
+    }
+
+    /// The day it was in `zone`
+    pub fn date<Tz: TimeZone>(self, zone: &Tz) -> NaiveDate {
+        self.0.with_timezone(zone).naive_local().date()
+    }
+}
+
+impl From<DateTime<Utc>> for Instant {
+    fn from(at: DateTime<Utc>) -> Self {
+        Instant(at)
+    }
+}
+
+impl From<Instant> for DateTime<Utc> {
+    fn from(at: Instant) -> Self {
+        at.0
+    }
+}
+
+impl Add<Duration> for Instant {
+    type Output = Instant;
+
+    fn add(self, duration: Duration) -> Instant {
+        Instant(self.0 + duration)
+    }
+}
+
+impl Sub<Duration> for Instant {
+    type Output = Instant;
+
+    fn sub(self, duration: Duration) -> Instant {
+        Instant(self.0 - duration)
+    }
+}
+
+impl Sub for Instant {
+    type Output = Duration;
+
+    fn sub(self, earlier: Instant) -> Duration {
+        self.0 - earlier.0
+    }
+}
+
+impl fmt::Display for Instant {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// A time of the day on the wall clock, like `12:30`, in whatever timezone the mob is in.
+/// Stored like `12:30:00`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ClockTime(NaiveTime);
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+#[error("`{0}` is not a time like 13:30")]
+pub struct InvalidClockTime(pub String);
+
+impl ClockTime {
+    pub fn hm(hour: u32, minute: u32) -> ClockTime {
+        ClockTime(NaiveTime::from_hms(hour, minute, 0))
+    }
+
+    /// The time `at` shows on the clock of its own zone
+    pub fn of<Tz: TimeZone>(at: &DateTime<Tz>) -> ClockTime {
+        ClockTime(at.time())
+    }
+
+    pub fn naive(self) -> NaiveTime {
+        self.0
+    }
+
+    /// From `now` until this time the same day, zero once it has passed
+    pub fn left_from(self, now: ClockTime) -> Duration {
+        std::cmp::max(self.0.signed_duration_since(now.0), Duration::zero())
+    }
+
+    /// When it is this time on `date` in `zone`. The clocks changing decides:
+    /// - a time skipped when they go forward doesn't happen that day, `None`
+    /// - a time that happens twice when they go back is the first one if that is after
+    ///   `after`, the second one otherwise
+    pub fn on<Tz: TimeZone>(self, date: NaiveDate, zone: &Tz, after: Instant) -> Option<Instant> {
+        match zone.from_local_datetime(&date.and_time(self.0)) {
+            LocalResult::Single(at) => Some(Instant(at.with_timezone(&Utc))),
+            LocalResult::Ambiguous(first, _) if first.with_timezone(&Utc) > after.0 => {
+                Some(Instant(first.with_timezone(&Utc)))
+            }
+            LocalResult::Ambiguous(_, second) => Some(Instant(second.with_timezone(&Utc))),
+            LocalResult::None => None,
+        }
+    }
+}
+
+impl From<NaiveTime> for ClockTime {
+    fn from(time: NaiveTime) -> Self {
+        ClockTime(time)
+    }
+}
+
+impl FromStr for ClockTime {
+    type Err = InvalidClockTime;
+
+    /// Hours and minutes, `9:05` or `13:30`
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        NaiveTime::parse_from_str(input.trim(), "%H:%M")
+            .map(ClockTime)
+            .map_err(|_| InvalidClockTime(input.trim().to_string()))
+    }
+}
+
+impl fmt::Display for ClockTime {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0.format("%H:%M"))
+    }
+}
+
+/// How long something lasts, as the settings count it. Stored as a plain number, read from
+/// that or from how people write durations, like `10m`, `1h30m` or `120s`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(transparent)]
+pub struct Minutes(pub i64);
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+#[error("`{0}` is not a duration in whole minutes like 10, 10m, 1h30m or 120s")]
+pub struct InvalidMinutes(pub String);
+
+impl Minutes {
+    pub fn get(self) -> i64 {
+        self.0
+    }
+
+    pub fn duration(self) -> Duration {
+        Duration::minutes(self.0)
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.0 == 0
+    }
+}
+
+impl From<i64> for Minutes {
+    fn from(minutes: i64) -> Self {
+        Minutes(minutes)
+    }
+}
+
+/// A plain number counts minutes as it always did, otherwise each number needs its unit
+impl FromStr for Minutes {
+    type Err = InvalidMinutes;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let input = input.trim();
+        if let Ok(minutes) = input.parse() {
+            return Ok(Minutes(minutes));
+        }
+        let invalid = || InvalidMinutes(input.to_string());
+        let mut seconds: i64 = 0;
+        let mut number = String::new();
+        for c in input.chars() {
+            if c.is_ascii_digit() {
+                number.push(c);
+                continue;
+            }
+            let count: i64 = number.parse().map_err(|_| invalid())?;
+            number.clear();
+            let unit = match c {
+                'h' => 3600,
+                'm' => 60,
+                's' => 1,
+                _ => return Err(invalid()),
+            };
+            seconds = count
+                .checked_mul(unit)
+                .and_then(|counted| seconds.checked_add(counted))
+                .ok_or_else(invalid)?;
+        }
+        if input.is_empty() || !number.is_empty() || seconds % 60 != 0 {
+            return Err(invalid());
+        }
+        Ok(Minutes(seconds / 60))
+    }
+}
+
+impl<'de> Deserialize<'de> for Minutes {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Stored {
+            Number(i64),
+            Text(String),
+        }
+        match Stored::deserialize(deserializer)? {
+            Stored::Number(minutes) => Ok(Minutes(minutes)),
+            Stored::Text(text) => text.parse().map_err(serde::de::Error::custom),
+        }
+    }
+}
+
+impl fmt::Display for Minutes {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use chrono::{FixedOffset, NaiveDateTime};
+
+    /// Central European time of 2021, summer time from March 28 to October 31
+    #[derive(Clone, Copy, Debug)]
+    pub struct Berlin;
+
+    impl Berlin {
+        fn summer(utc: &NaiveDateTime) -> bool {
+            let start = NaiveDate::from_ymd(2021, 3, 28).and_hms(1, 0, 0);
+            let end = NaiveDate::from_ymd(2021, 10, 31).and_hms(1, 0, 0);
+            start <= *utc && *utc < end
+        }
+
+        pub fn offset(summer: bool) -> FixedOffset {
+            FixedOffset::east(if summer { 2 * 3600 } else { 3600 })
+        }
+
+        /// `local` like `2021-06-01 12:00`, in summer or winter time for the hour that
+        /// happens twice
+        pub fn at(local: &str, summer: bool) -> DateTime<Berlin> {
+            let local = NaiveDateTime::parse_from_str(local, "%Y-%m-%d %H:%M").unwrap();
+            let offset = Berlin::offset(summer);
+            let utc = local - Duration::seconds(offset.local_minus_utc() as i64);
+            Berlin.from_utc_datetime(&utc)
+        }
+    }
+
+    impl TimeZone for Berlin {
+        type Offset = FixedOffset;
+
+        fn from_offset(_: &FixedOffset) -> Self {
+            Berlin
+        }
+
+        fn offset_from_local_date(&self, local: &NaiveDate) -> LocalResult<FixedOffset> {
+            self.offset_from_local_datetime(&local.and_hms(0, 0, 0))
+        }
+
+        fn offset_from_local_datetime(&self, local: &NaiveDateTime) -> LocalResult<FixedOffset> {
+            let candidates: Vec<FixedOffset> = [true, false]
+                .iter()
+                .map(|summer| Self::offset(*summer))
+                .filter(|offset| {
+                    self.offset_from_utc_datetime(
+                        &(*local - Duration::seconds(offset.local_minus_utc() as i64)),
+                    ) == *offset
+                })
+                .collect();
+            match candidates.as_slice() {
+                [] => LocalResult::None,
+                [offset] => LocalResult::Single(*offset),
+                [first, second, ..] => LocalResult::Ambiguous(*first, *second),
+            }
+        }
+
+        fn offset_from_utc_date(&self, utc: &NaiveDate) -> FixedOffset {
+            self.offset_from_utc_datetime(&utc.and_hms(0, 0, 0))
+        }
+
+        fn offset_from_utc_datetime(&self, utc: &NaiveDateTime) -> FixedOffset {
+            Self::offset(Self::summer(utc))
+        }
+    }
+
+    pub fn utc(time: &str) -> Instant {
+        Instant(
+            Utc.from_utc_datetime(&NaiveDateTime::parse_from_str(time, "%Y-%m-%d %H:%M").unwrap()),
+        )
+    }
+
+    #[test]
+    fn clock_times_across_daylight_saving() {
+        let cases = vec![
+            (
+                "an ordinary day",
+                "2021-06-01",
+                "12:30",
+                "2021-06-01 00:00",
+                Some("2021-06-01 10:30"),
+            ),
+            (
+                "before the clocks go forward",
+                "2021-03-28",
+                "01:30",
+                "2021-03-28 00:00",
+                Some("2021-03-28 00:30"),
+            ),
+            ("skipped", "2021-03-28", "02:30", "2021-03-28 00:00", None),
+            (
+                "after the clocks go forward",
+                "2021-03-28",
+                "03:30",
+                "2021-03-28 00:00",
+                Some("2021-03-28 01:30"),
+            ),
+            (
+                "twice, the first is ahead",
+                "2021-10-31",
+                "02:30",
+                "2021-10-31 00:00",
+                Some("2021-10-31 00:30"),
+            ),
+            (
+                "twice, the first has passed",
+                "2021-10-31",
+                "02:30",
+                "2021-10-31 00:45",
+                Some("2021-10-31 01:30"),
+            ),
+            (
+                "twice, both have passed",
+                "2021-10-31",
+                "02:30",
+                "2021-10-31 02:00",
+                Some("2021-10-31 01:30"),
+            ),
+        ];
+        for (case, date, time, after, expected) in cases {
+            let date = NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap();
+            let time: ClockTime = time.parse().unwrap();
+            assert_eq!(
+                time.on(date, &Berlin, utc(after)),
+                expected.map(utc),
+                "{}",
+                case
+            );
+        }
+    }
+
+    #[test]
+    fn instants_in_a_timezone() {
+        // Half past midnight UTC is the next day in Berlin, lunch is noon there in summer
+        let late = utc("2021-06-01 22:30");
+        assert_eq!(late.date(&Berlin), NaiveDate::from_ymd(2021, 6, 2));
+        assert_eq!(late.clock_time(&Berlin), ClockTime::hm(0, 30));
+        assert_eq!(
+            utc("2021-06-01 10:00").clock_time(&Berlin),
+            ClockTime::hm(12, 0)
+        );
+        assert_eq!(
+            utc("2021-12-01 10:00").clock_time(&Berlin),
+            ClockTime::hm(11, 0)
+        );
+        assert_eq!(late + Duration::minutes(30) - late, Duration::minutes(30));
+    }
+
+    #[test]
+    fn parses_and_stores_as_before() {
+        assert_eq!("9:05".parse(), Ok(ClockTime::hm(9, 5)));
+        assert_eq!(" 13:30 ".parse(), Ok(ClockTime::hm(13, 30)));
+        assert_eq!(
+            "25:00".parse::<ClockTime>(),
+            Err(InvalidClockTime("25:00".into()))
+        );
+        assert_eq!(ClockTime::hm(9, 5).to_string(), "09:05");
+        assert_eq!(
+            ClockTime::hm(11, 30).left_from(ClockTime::hm(11, 0)),
+            Duration::minutes(30)
+        );
+        assert_eq!(
+            ClockTime::hm(11, 0).left_from(ClockTime::hm(11, 30)),
+            Duration::zero()
+        );
+
+        assert_eq!(
+            serde_json::to_string(&ClockTime::hm(11, 30)).unwrap(),
+            serde_json::to_string(&NaiveTime::from_hms(11, 30, 0)).unwrap()
+        );
+        let at = utc("2021-06-01 10:00");
+        assert_eq!(
+            serde_json::to_string(&at).unwrap(),
+            serde_json::to_string(&at.utc()).unwrap()
+        );
+        assert_eq!(serde_json::to_string(&Minutes(15)).unwrap(), "15");
+        assert_eq!(serde_json::from_str::<Minutes>("15").unwrap(), Minutes(15));
+        assert_eq!(
+            serde_json::from_str::<Minutes>("\"1h\"").unwrap(),
+            Minutes(60)
+        );
+    }
+
+    #[test]
+    fn parses_minutes_as_people_write_them() {
+        let cases = vec![
+            ("10", Some(10)),
+            (" 25 ", Some(25)),
+            ("0", Some(0)),
+            ("10m", Some(10)),
+            ("1h", Some(60)),
+            ("1h30m", Some(90)),
+            ("120s", Some(2)),
+            ("2m60s", Some(3)),
+            ("90s", None),
+            ("10 m", None),
+            ("m", None),
+            ("10x", None),
+            ("1h30", None),
+            ("", None),
+            ("ten", None),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(input.parse().ok(), expected.map(Minutes), "{:?}", input);
+        }
+        assert_eq!(
+            "90s".parse::<Minutes>().unwrap_err().to_string(),
+            "`90s` is not a duration in whole minutes like 10, 10m, 1h30m or 120s"
+        );
+    }
+}
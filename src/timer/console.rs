@@ -0,0 +1,395 @@
+//! The countdown in the terminal that runs the say and notify commands once it is up.
+
+use super::{desktop, fill, Countdown, Detached, Notifier, ShellNotifier, Timer, DETACHED_FILE};
+use crate::broadcast::Broadcast;
+use crate::state_dir::StateDir;
+use crate::{ci, deadline, duration, record};
+use anyhow::Result;
+use console::Term;
+use std::thread;
+
+const FONT_HEIGHT: usize = 7;
+
+pub struct ConsoleTimer<'a> {
+    notifier: ShellNotifier<'a>,
+    broadcast: Option<Broadcast<'a>>,
+    /// Where detached timers are listed
+    state_dir: Option<StateDir>,
+    /// Shows the notification of the desktop too
+    desktop: bool,
+    /// Run detached once the timer is up, with `{title}` and `{message}` replaced
+    on_end: Option<String>,
+    /// Rings the bell of the terminal once the timer is up
+    bell: bool,
+}
+
+impl<'a> ConsoleTimer<'a> {
+    pub fn new(commands: Vec<String>) -> ConsoleTimer<'a> {
+        ConsoleTimer {
+            notifier: ShellNotifier::new(commands),
+            broadcast: None,
+            state_dir: None,
+            desktop: false,
+            on_end: None,
+            bell: false,
+        }
+    }
+
+    /// Also shows a notification of the desktop once the timer is up
+    pub fn desktop(self, desktop: bool) -> ConsoleTimer<'a> {
+        ConsoleTimer { desktop, ..self }
+    }
+
+    /// Also runs `on_end` once the timer is up, without waiting for it
+    pub fn on_end(self, on_end: Option<String>) -> ConsoleTimer<'a> {
+        ConsoleTimer { on_end, ..self }
+    }
+
+    /// Also rings the bell of the terminal once the timer is up
+    pub fn bell(self, bell: bool) -> ConsoleTimer<'a> {
+        ConsoleTimer { bell, ..self }
+    }
+
+    /// Also tells `broadcast` about every timer
+    pub fn broadcast(self, broadcast: Option<Broadcast<'a>>) -> ConsoleTimer<'a> {
+        ConsoleTimer { broadcast, ..self }
+    }
+
+    /// Lists the timers it detaches in `state_dir`, so ending the session can stop them
+    pub fn state_dir(self, state_dir: StateDir) -> ConsoleTimer<'a> {
+        ConsoleTimer {
+            state_dir: Some(state_dir),
+            ..self
+        }
+    }
+
+    /// The command for the end of the timer, with its placeholders filled
+    fn end_command(&self, title: &str, message: &str) -> Option<String> {
+        let template = self.on_end.as_deref()?;
+        Some(fill(
+            template,
+            &[("{title}", title), ("{message}", message)],
+        ))
+    }
+
+    /// Starts the command for the end of the timer, a notifier that hangs or fails doesn't
+    /// hold up mob
+    fn run_end_command(&self, title: &str, message: &str) {
+        let command = match self.end_command(title, message) {
+            Some(command) => command,
+            None => return,
+        };
+        if let Err(err) = self.notifier.sh.spawn_detached(["-c", command.as_str()]) {
+            log::warn!("Could not run timer.on_end: {:#}", err);
+        }
+    }
+
+    fn remember(&self, pid: u32, duration: chrono::Duration) {
+        let state_dir = match &self.state_dir {
+            Some(state_dir) => state_dir,
+            None => return,
+        };
+        let mut detached: Vec<Detached> = state_dir.read(DETACHED_FILE).unwrap_or_default();
+        detached.push(Detached {
+            pid,
+            due: chrono::Utc::now() + duration,
+        });
+        if let Err(err) = state_dir.write(DETACHED_FILE, &detached) {
+            log::warn!("Could not list the detached timer {}: {:#}", pid, err);
+        }
+    }
+}
+
+impl<'a> Timer for ConsoleTimer<'a> {
+    fn start(
+        &self,
+        countdown: Countdown,
+        title: &str,
+        duration: chrono::Duration,
+        message: &str,
+    ) -> Result<()> {
+        if record::replaying() {
+            log::info!("{}, not counting down in a replay", title);
+            return Ok(());
+        }
+        if !ci::current().timers {
+            log::info!("{}, not counting down in CI", title);
+            return Ok(());
+        }
+        if let Some(broadcast) = &self.broadcast {
+            // The stopwatch in the room never holds up the turn
+            if let Err(err) = broadcast.started(countdown, duration) {
+                log::warn!("Could not tell the timer broadcast: {:#}", err);
+            }
+        }
+        // Counting down would outlast the deadline, the commands still run when it's up
+        if deadline::active() {
+            log::info!(
+                "{}, running detached for {}",
+                title,
+                duration::format(duration).human()
+            );
+            let mut also = match self.desktop {
+                true => vec![desktop::shell(title, message)],
+                false => vec![],
+            };
+            // Last, so a hung one holds up nothing else
+            also.extend(self.end_command(title, message));
+            if let Some(pid) = self.notifier.detach(duration, message, &also)? {
+                self.remember(pid, duration);
+            }
+            return Ok(());
+        }
+
+        let mut time_left = duration;
+        let second = chrono::Duration::seconds(1);
+
+        let term = Term::stdout();
+        term.set_title(title);
+        println!("\n{}", title);
+        while time_left >= chrono::Duration::zero() {
+            let formatted = duration::format(time_left).clock();
+            let letters = asci_time(formatted.as_str());
+
+            if time_left != duration {
+                term.clear_last_lines(FONT_HEIGHT)?;
+            }
+            print_ascii(&term, letters)?;
+            thread::sleep(second.to_std()?);
+            time_left = time_left - second;
+        }
+        term.clear_last_lines(1)?;
+        if self.bell {
+            term.write_str("\x07")?;
+        }
+
+        if self.desktop {
+            // Without a notification daemon, like in a container, the commands still run
+            if let Err(err) = desktop::notify(title, message) {
+                log::warn!(
+                    "Could not show a notification, set timer.silent to skip it: {:#}",
+                    err
+                );
+            }
+        }
+        self.run_end_command(title, message);
+        self.notifier.notify(message)
+    }
+
+    fn cancel(&self) -> Result<()> {
+        match self.broadcast.as_ref().filter(|_| !record::replaying()) {
+            Some(broadcast) => broadcast.cancelled(),
+            None => Ok(()),
+        }
+    }
+}
+
+fn asci_time(time: &str) -> Vec<&str> {
+    time.chars()
+        .map(|c| {
+            let d = c as usize;
+            match d {
+                58 => FONT[10], // :
+                32 => FONT[11], // <space>
+                48..=57 => FONT[d - 48],
+                _ => panic!("dont know what to do with '{}' {}", c, d),
+            }
+        })
+        .collect()
+}
+
+fn print_ascii(term: &Term, letters: Vec<&str>) -> Result<()> {
+    let lines: Vec<String> = (0..FONT_HEIGHT)
+        .map(|row| //for row in 0..5 {
+        letters
+            .iter()
+            .map(|l| l.split('\n').nth(row).unwrap())
+            .collect::<Vec<&str>>()
+            .join(" "))
+        .collect();
+    for line in lines {
+        term.write_line(line.as_str())?;
+    }
+    Ok(())
+}
+
+#[rustfmt::skip]
+const FONT: [&str; 12] = [
+"
+  ██████ 
+ ██  ████
+ ██ ██ ██
+ ████  ██
+  ██████ 
+",
+"
+  ██
+ ███
+  ██
+  ██
+  ██
+ ",
+"
+ ██████ 
+      ██
+  █████ 
+ ██     
+ ███████
+",
+"
+ ██████ 
+      ██
+  █████ 
+      ██
+ ██████ 
+",
+"
+ ██   ██
+ ██   ██
+ ███████
+      ██
+      ██
+",
+"
+ ███████
+ ██     
+ ███████
+      ██
+ ███████
+",
+"
+  ██████ 
+ ██      
+ ███████ 
+ ██    ██
+  ██████ 
+",
+"
+ ███████
+      ██
+     ██ 
+    ██  
+    ██  
+",
+"
+  █████ 
+ ██   ██
+  █████ 
+ ██   ██
+  █████ 
+",
+"
+  █████ 
+ ██   ██
+  ██████
+      ██
+  █████ 
+",
+"
+   
+ ██
+   
+ ██
+   
+",
+"
+     
+     
+     
+     
+     
+",
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn no_timer_in_ci() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("told");
+        let timer = ConsoleTimer::new(vec![format!("printf %s MESSAGE > '{}'", file.display())]);
+
+        ci::set(ci::RunContext::detect(|name| match name {
+            "CI" => Some("true".into()),
+            _ => None,
+        }));
+        let started = std::time::Instant::now();
+        timer
+            .start(
+                Countdown::Turn,
+                "Your turn",
+                chrono::Duration::seconds(2),
+                "mob next",
+            )
+            .unwrap();
+        ci::set(ci::RunContext::default());
+        // Counting down in the terminal would take the whole two seconds
+        assert!(started.elapsed() < std::time::Duration::from_secs(1));
+        assert!(!file.exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn detached_under_a_deadline() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("told");
+        let timer = ConsoleTimer::new(vec![format!("printf %s MESSAGE > '{}'", file.display())]);
+
+        deadline::set(Some(std::time::Duration::from_secs(30)));
+        let started = std::time::Instant::now();
+        timer
+            .start(
+                Countdown::Turn,
+                "Your turn",
+                chrono::Duration::seconds(1),
+                "mob next",
+            )
+            .unwrap();
+        deadline::set(None);
+        assert!(started.elapsed() < std::time::Duration::from_secs(1));
+
+        for _ in 0..100 {
+            if let Ok(told) = std::fs::read_to_string(&file) {
+                if !told.is_empty() {
+                    assert_eq!(told, "mob next");
+                    return;
+                }
+            }
+            thread::sleep(std::time::Duration::from_millis(50));
+        }
+        panic!("the detached timer never ran");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn runs_the_end_command_detached() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("ended");
+        let timer = ConsoleTimer::new(vec![]).on_end(Some(format!(
+            "printf '%s: %s' {{title}} {{message}} > '{}'",
+            file.display()
+        )));
+        assert_eq!(
+            timer.end_command("Your turn", "mob next bob").unwrap(),
+            format!(
+                "printf '%s: %s' 'Your turn' 'mob next bob' > '{}'",
+                file.display()
+            )
+        );
+
+        timer.run_end_command("Your turn", "mob next bob");
+        for _ in 0..100 {
+            if let Ok(ended) = std::fs::read_to_string(&file) {
+                if !ended.is_empty() {
+                    assert_eq!(ended, "Your turn: mob next bob");
+                    return;
+                }
+            }
+            thread::sleep(std::time::Duration::from_millis(50));
+        }
+        panic!("the end command never ran");
+    }
+}
@@ -0,0 +1,85 @@
+//! Notifications of the desktop that need nothing configured, shown with the tool each
+//! platform comes with. `notify_command` replaces them for desktops that have another.
+
+use crate::{command, os};
+use anyhow::Result;
+
+/// The program and arguments that show `message` under `title`
+pub fn command(title: &str, message: &str) -> (&'static str, Vec<String>) {
+    if cfg!(windows) {
+        // A toast of the WinRT API, PowerShell strings in single quotes only escape those
+        let quoted = |text: &str| format!("'{}'", text.replace('\'', "''"));
+        let script = format!(
+            "[Windows.UI.Notifications.ToastNotificationManager, Windows.UI.Notifications, \
+             ContentType = WindowsRuntime] > $null; \
+             $xml = [Windows.UI.Notifications.ToastNotificationManager]::GetTemplateContent(\
+             [Windows.UI.Notifications.ToastTemplateType]::ToastText02); \
+             $text = $xml.GetElementsByTagName('text'); \
+             $text.Item(0).AppendChild($xml.CreateTextNode({})) > $null; \
+             $text.Item(1).AppendChild($xml.CreateTextNode({})) > $null; \
+             [Windows.UI.Notifications.ToastNotificationManager]::CreateToastNotifier('mob')\
+             .Show([Windows.UI.Notifications.ToastNotification]::new($xml))",
+            quoted(title),
+            quoted(message)
+        );
+        (
+            "powershell",
+            vec!["-NoProfile".into(), "-Command".into(), script],
+        )
+    } else if cfg!(target_os = "macos") {
+        let quoted =
+            |text: &str| format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""));
+        let script = format!(
+            "display notification {} with title {}",
+            quoted(message),
+            quoted(title)
+        );
+        ("osascript", vec!["-e".into(), script])
+    } else {
+        // libnotify, which every freedesktop notification daemon understands
+        (
+            "notify-send",
+            vec!["--app-name=mob".into(), title.into(), message.into()],
+        )
+    }
+}
+
+/// Shows the notification and waits for the tool
+pub fn notify(title: &str, message: &str) -> Result<()> {
+    let (program, args) = command(title, message);
+    command::Command::new(os::command(program)).run_checked(args)?;
+    Ok(())
+}
+
+/// The notification as a line of a shell script, for timers that run detached. A missing
+/// tool doesn't fail the script.
+pub fn shell(title: &str, message: &str) -> String {
+    let (program, args) = command(title, message);
+    let quoted: Vec<String> = std::iter::once(program.to_string())
+        .chain(args)
+        .map(|arg| format!("'{}'", arg.replace('\'', "'\\''")))
+        .collect();
+    format!("{} || true", quoted.join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn quotes_for_the_shell() {
+        let sh = command::Command::new(os::command("sh"));
+        let message = "mob next O'Brien $(id) \"now\"";
+        let line = shell("Your turn", message).replace("|| true", "");
+        // Every argument arrives as it was, one per line
+        let printed = sh
+            .run_stdout([
+                "-c",
+                &format!("printf '%s\\n' {}", &line[line.find(' ').unwrap()..]),
+            ])
+            .unwrap();
+        let (_, args) = command("Your turn", message);
+        assert_eq!(printed.lines().collect::<Vec<_>>(), args);
+    }
+}
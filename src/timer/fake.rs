@@ -0,0 +1,62 @@
+//! Timers for tests: one that records what commands ask of it instead of counting down, and
+//! ones that start nothing or fail to
+
+use super::{Countdown, Timer};
+use anyhow::{anyhow, Result};
+use std::cell::RefCell;
+
+/// A countdown a command started
+#[derive(Debug, Clone, PartialEq)]
+pub struct Started {
+    pub countdown: Countdown,
+    pub title: String,
+    pub duration: chrono::Duration,
+    pub message: String,
+}
+
+#[derive(Default)]
+pub struct RecordingTimer {
+    pub started: RefCell<Vec<Started>>,
+    pub cancelled: RefCell<usize>,
+}
+
+impl Timer for RecordingTimer {
+    fn start(
+        &self,
+        countdown: Countdown,
+        title: &str,
+        duration: chrono::Duration,
+        message: &str,
+    ) -> Result<()> {
+        self.started.borrow_mut().push(Started {
+            countdown,
+            title: title.to_string(),
+            duration,
+            message: message.to_string(),
+        });
+        Ok(())
+    }
+
+    fn cancel(&self) -> Result<()> {
+        *self.cancelled.borrow_mut() += 1;
+        Ok(())
+    }
+}
+
+/// Starts nothing, for tests that don't look at the timer
+pub struct NoTimer;
+
+impl Timer for NoTimer {
+    fn start(&self, _: Countdown, _: &str, _: chrono::Duration, _: &str) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Fails to start, like a timer whose notifications aren't installed
+pub struct FailingTimer;
+
+impl Timer for FailingTimer {
+    fn start(&self, _: Countdown, _: &str, _: chrono::Duration, _: &str) -> Result<()> {
+        Err(anyhow!("notify-send: command not found"))
+    }
+}
@@ -0,0 +1,245 @@
+use crate::{command, duration, os};
+use anyhow::Result;
+use chrono::{self, DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "notifications")]
+mod console;
+pub mod desktop;
+#[cfg(any(test, feature = "test-util"))]
+pub mod fake;
+#[cfg(feature = "notifications")]
+pub use self::console::ConsoleTimer;
+
+/// What a timer counts down
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Countdown {
+    Turn,
+    Break,
+}
+
+/// The state dir file listing the timers running detached, for the teardown of the session
+pub const DETACHED_FILE: &str = "timers.json";
+
+/// A timer started in the background, it runs the commands once it is due and exits
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Detached {
+    pub pid: u32,
+    pub due: DateTime<Utc>,
+}
+
+pub trait Timer {
+    fn start(
+        &self,
+        countdown: Countdown,
+        title: &str,
+        duration: chrono::Duration,
+        message: &str,
+    ) -> Result<()>;
+
+    /// The turn ended before its timer did
+    fn cancel(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+pub trait Notifier {
+    fn notify(&self, message: &str) -> Result<()>;
+}
+
+/// Runs shell command templates with MESSAGE replaced, like the say and notify commands
+pub struct ShellNotifier<'a> {
+    sh: command::Command<'a>,
+    commands: Vec<String>,
+}
+
+impl<'a> ShellNotifier<'a> {
+    pub fn new(commands: Vec<String>) -> ShellNotifier<'a> {
+        ShellNotifier {
+            sh: command::Command::new(os::command("sh")),
+            commands,
+        }
+    }
+}
+
+impl<'a> ShellNotifier<'a> {
+    /// Runs the commands after `delay` in the background, without waiting here, followed by
+    /// the lines of `also`. Tells the process running them, if there are any.
+    pub fn detach(
+        &self,
+        delay: chrono::Duration,
+        message: &str,
+        also: &[String],
+    ) -> Result<Option<u32>> {
+        if self.commands.is_empty() && also.is_empty() {
+            return Ok(None);
+        }
+        let commands: Vec<String> = self
+            .commands
+            .iter()
+            .map(|cmd| substitute(cmd, message))
+            .chain(also.iter().cloned())
+            .collect();
+        let script = format!(
+            "sleep {}; {}",
+            delay.num_seconds().max(0),
+            commands.join("; ")
+        );
+        Ok(Some(self.sh.spawn_detached(["-c", script.as_str()])?))
+    }
+}
+
+impl<'a> Notifier for ShellNotifier<'a> {
+    fn notify(&self, message: &str) -> Result<()> {
+        for cmd in &self.commands {
+            let arg = substitute(cmd, message);
+            self.sh.run_checked(&["-c", arg.as_str()])?;
+        }
+        Ok(())
+    }
+}
+
+/// Only logs when a timer would be up, the timer of builds without the `notifications`
+/// feature
+pub struct LogTimer;
+
+impl Timer for LogTimer {
+    fn start(
+        &self,
+        _: Countdown,
+        title: &str,
+        duration: chrono::Duration,
+        message: &str,
+    ) -> Result<()> {
+        log::info!(
+            "{}, telling \"{}\" in {}",
+            title,
+            message,
+            duration::format(duration).human()
+        );
+        Ok(())
+    }
+}
+
+/// Logs what it is told instead of saying or showing it
+pub struct LogNotifier;
+
+impl Notifier for LogNotifier {
+    fn notify(&self, message: &str) -> Result<()> {
+        log::info!("{}", message);
+        Ok(())
+    }
+}
+
+/// Replaces MESSAGE in a shell command template, escaped for the quotes it appears in
+/// so driver names with quotes or `$` can't break or inject into the command.
+fn substitute(template: &str, message: &str) -> String {
+    fill(template, &[("MESSAGE", message)])
+}
+
+/// Replaces each placeholder of `values` in a shell command template with its value, escaped
+/// like [`substitute`] does
+fn fill(template: &str, values: &[(&str, &str)]) -> String {
+    #[derive(PartialEq)]
+    enum Quote {
+        None,
+        Single,
+        Double,
+    }
+
+    let mut quote = Quote::None;
+    let mut out = String::new();
+    let mut rest = template;
+    while let Some(c) = rest.chars().next() {
+        if let Some((placeholder, message)) = values
+            .iter()
+            .find(|(placeholder, _)| rest.starts_with(placeholder))
+        {
+            out.push_str(&match quote {
+                Quote::Single => message.replace('\'', "'\\''"),
+                Quote::Double => message
+                    .chars()
+                    .flat_map(|c| match c {
+                        '"' | '\\' | '$' | '`' => vec!['\\', c],
+                        _ => vec![c],
+                    })
+                    .collect(),
+                Quote::None => format!("'{}'", message.replace('\'', "'\\''")),
+            });
+            rest = &rest[placeholder.len()..];
+            continue;
+        }
+
+        out.push(c);
+        rest = &rest[c.len_utf8()..];
+        match (&quote, c) {
+            (Quote::None, '\'') => quote = Quote::Single,
+            (Quote::None, '"') => quote = Quote::Double,
+            (Quote::Single, '\'') | (Quote::Double, '"') => quote = Quote::None,
+            (Quote::None, '\\') | (Quote::Double, '\\') => {
+                // Keep the escaped character as is
+                if let Some(escaped) = rest.chars().next() {
+                    out.push(escaped);
+                    rest = &rest[escaped.len_utf8()..];
+                }
+            }
+            _ => (),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn substitute_nasty_messages() {
+        let sh = command::Command::new(os::command("sh"));
+        let messages = [
+            "mob next anna",
+            "mob next O'Brien",
+            "mob next \"quoted\" name",
+            "mob next $(touch /tmp/mob-pwned) `id` $HOME",
+            "mob next back\\slash ; | & > <",
+            "mob next ünïcödé 日本 🚗",
+            "mob next -n --flag",
+        ];
+        let templates = [
+            "printf %s MESSAGE",
+            "printf %s 'MESSAGE'",
+            "printf %s \"MESSAGE\"",
+            "printf %s \"it's \\\"MESSAGE\\\"\"",
+        ];
+        for template in &templates {
+            let expected = sh
+                .run_stdout(["-c", &template.replace("MESSAGE", "X")])
+                .unwrap();
+            for message in &messages {
+                let output = sh
+                    .run_stdout(["-c", substitute(template, message).as_str()])
+                    .unwrap();
+                assert_eq!(output, expected.replacen('X', message, 1), "{}", template);
+            }
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn fill_title_and_message() {
+        let sh = command::Command::new(os::command("sh"));
+        let template = "printf '%s|%s|%s' {title} \"{message}\" 'MESSAGE'";
+        let filled = fill(
+            template,
+            &[
+                ("{title}", "Bob's turn"),
+                ("{message}", "mob next \"$bob\""),
+            ],
+        );
+        assert_eq!(
+            sh.run_stdout(["-c", filled.as_str()]).unwrap(),
+            "Bob's turn|mob next \"$bob\"|MESSAGE"
+        );
+    }
+}
@@ -0,0 +1,446 @@
+//! Timezones by IANA name like `Europe/Berlin`, so a mob across timezones has its fixed
+//! breaks at the same moment for everyone. Read from the tz database of the system, the
+//! files in `/usr/share/zoneinfo` or `$TZDIR`, with the rule at their end for the years after
+//! their last transition.
+
+use chrono::{DateTime, Datelike, FixedOffset, NaiveDate, NaiveDateTime, Utc};
+use std::convert::TryInto;
+use std::path::{Path, PathBuf};
+use std::{env, fs};
+
+const ZONEINFO: &str = "/usr/share/zoneinfo";
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum Error {
+    #[error("`{0}` is not a timezone, use a name like Europe/Berlin")]
+    Unknown(String),
+    #[error("the timezone database has no valid {0}, reinstall tzdata")]
+    Unreadable(String),
+}
+
+/// The offsets from UTC a zone had and has
+#[derive(Debug, Clone, PartialEq)]
+pub struct Zone {
+    name: String,
+    /// When each offset started, in seconds since the epoch
+    transitions: Vec<(i64, i32)>,
+    /// The offset before the first transition
+    initial: i32,
+    /// How the offset changes after the last transition
+    rule: Option<Rule>,
+}
+
+impl Zone {
+    /// The zone `name` from the tz database
+    pub fn find(name: &str) -> Result<Zone, Error> {
+        let dir = env::var_os("TZDIR").map_or_else(|| PathBuf::from(ZONEINFO), PathBuf::from);
+        Zone::find_in(&dir, name)
+    }
+
+    fn find_in(dir: &Path, name: &str) -> Result<Zone, Error> {
+        let unknown = || Error::Unknown(name.to_string());
+        // Only names inside the database, never another file
+        let valid = |part: &str| {
+            !part.is_empty()
+                && part != "."
+                && part != ".."
+                && part
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "_+-".contains(c))
+        };
+        if !name.split('/').all(valid) {
+            return Err(unknown());
+        }
+        let data = fs::read(dir.join(name)).map_err(|_| unknown())?;
+        Zone::parse(name, &data).ok_or_else(|| Error::Unreadable(name.to_string()))
+    }
+
+    /// A zone that is `offset` seconds east of UTC all year, for tests
+    #[cfg(test)]
+    pub fn fixed(name: &str, offset: i32) -> Zone {
+        Zone {
+            name: name.to_string(),
+            transitions: vec![],
+            initial: offset,
+            rule: None,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The offset from UTC in this zone at `at`
+    pub fn offset(&self, at: DateTime<Utc>) -> FixedOffset {
+        let at = at.timestamp();
+        let seconds = match self.transitions.iter().rposition(|(start, _)| *start <= at) {
+            Some(index) if index + 1 < self.transitions.len() => self.transitions[index].1,
+            None if !self.transitions.is_empty() => self.initial,
+            last => match (&self.rule, last) {
+                (Some(rule), _) => rule.offset(at),
+                (None, Some(index)) => self.transitions[index].1,
+                (None, None) => self.initial,
+            },
+        };
+        FixedOffset::east(seconds)
+    }
+
+    /// `at` as the clock shows it in this zone
+    pub fn at(&self, at: DateTime<Utc>) -> DateTime<FixedOffset> {
+        at.with_timezone(&self.offset(at))
+    }
+
+    /// Reads a TZif file as RFC 8536 describes it, `None` when it isn't one
+    fn parse(name: &str, data: &[u8]) -> Option<Zone> {
+        let mut reader = Reader { data, at: 0 };
+        let header = reader.header()?;
+        // Version 2 and later repeat the data with 64 bit times, followed by the rule
+        let (header, wide) = match header.version {
+            0 => (header, false),
+            _ => {
+                reader.skip(header.length(false))?;
+                (reader.header()?, true)
+            }
+        };
+        let times: Vec<i64> = (0..header.times)
+            .map(|_| match wide {
+                true => reader.i64(),
+                false => reader.i32().map(i64::from),
+            })
+            .collect::<Option<_>>()?;
+        let indices = reader.take(header.times)?.to_vec();
+        let types: Vec<i32> = (0..header.types)
+            .map(|_| {
+                let offset = reader.i32()?;
+                reader.skip(2)?;
+                Some(offset)
+            })
+            .collect::<Option<_>>()?;
+        let transitions = times
+            .into_iter()
+            .zip(indices)
+            .map(|(time, index)| Some((time, *types.get(index as usize)?)))
+            .collect::<Option<_>>()?;
+        let rule = match wide {
+            true => {
+                reader.skip(header.length(true) - header.times * 9 - header.types * 6)?;
+                let footer = std::str::from_utf8(reader.rest()).ok()?;
+                let footer = footer.trim_matches('\n');
+                Some(footer).filter(|rule| !rule.is_empty()).map(Rule::parse)
+            }
+            false => None,
+        };
+        Some(Zone {
+            name: name.to_string(),
+            transitions,
+            initial: *types.first()?,
+            rule: rule.flatten(),
+        })
+    }
+}
+
+struct Header {
+    version: u8,
+    counts: [usize; 6],
+    times: usize,
+    types: usize,
+}
+
+impl Header {
+    /// Bytes of the data after the header
+    fn length(&self, wide: bool) -> usize {
+        let [utc, std, leaps, times, types, chars] = self.counts;
+        let time = if wide { 8 } else { 4 };
+        times * (time + 1) + types * 6 + chars + leaps * (time + 4) + std + utc
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    at: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, count: usize) -> Option<&'a [u8]> {
+        let taken = self.data.get(self.at..self.at.checked_add(count)?)?;
+        self.at += count;
+        Some(taken)
+    }
+
+    fn skip(&mut self, count: usize) -> Option<()> {
+        self.take(count).map(drop)
+    }
+
+    fn rest(&self) -> &'a [u8] {
+        &self.data[self.at..]
+    }
+
+    fn i32(&mut self) -> Option<i32> {
+        Some(i32::from_be_bytes(self.take(4)?.try_into().ok()?))
+    }
+
+    fn i64(&mut self) -> Option<i64> {
+        Some(i64::from_be_bytes(self.take(8)?.try_into().ok()?))
+    }
+
+    fn header(&mut self) -> Option<Header> {
+        if self.take(4)? != b"TZif" {
+            return None;
+        }
+        let version = match self.take(1)?[0] {
+            0 => 0,
+            version => version - b'0',
+        };
+        self.skip(15)?;
+        let mut counts = [0; 6];
+        for count in counts.iter_mut() {
+            *count = self.i32()? as usize;
+        }
+        Some(Header {
+            version,
+            counts,
+            times: counts[3],
+            types: counts[4],
+        })
+    }
+}
+
+/// A POSIX TZ string like `CET-1CEST,M3.5.0,M10.5.0/3`: standard time, and daylight saving
+/// time between two days of the year
+#[derive(Debug, Clone, PartialEq)]
+struct Rule {
+    standard: i32,
+    /// The offset and when it starts and ends, in local time
+    daylight: Option<(i32, Day, i32, Day, i32)>,
+}
+
+/// `Mm.w.d`, day `d` of week `w` of month `m`, where week 5 is the last
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Day {
+    month: u32,
+    week: u32,
+    weekday: u32,
+}
+
+impl Rule {
+    fn parse(rule: &str) -> Option<Rule> {
+        let mut rest = rule;
+        abbreviation(&mut rest)?;
+        let standard = -offset(&mut rest)?;
+        if rest.is_empty() {
+            return Some(Rule {
+                standard,
+                daylight: None,
+            });
+        }
+        abbreviation(&mut rest)?;
+        let daylight = match rest.starts_with(',') {
+            true => standard + 3600,
+            false => -offset(&mut rest)?,
+        };
+        let mut change = || -> Option<(Day, i32)> {
+            rest = rest.strip_prefix(",M")?;
+            let end = rest.find([',', '/']).unwrap_or(rest.len());
+            let mut parts = rest[..end].split('.').map(|part| part.parse().ok());
+            let day = Day {
+                month: parts.next()?.filter(|month| (1..=12).contains(month))?,
+                week: parts.next()?.filter(|week| (1..=5).contains(week))?,
+                weekday: parts.next()?.filter(|weekday| *weekday <= 6)?,
+            };
+            rest = &rest[end..];
+            let time = match rest.strip_prefix('/') {
+                Some(time) => {
+                    rest = time;
+                    offset(&mut rest)?
+                }
+                None => 2 * 3600,
+            };
+            Some((day, time))
+        };
+        // Rules by day of the year instead of week are left to the transitions before them
+        let (start, start_time) = change()?;
+        let (end, end_time) = change()?;
+        Some(Rule {
+            standard,
+            daylight: Some((daylight, start, start_time, end, end_time)),
+        })
+    }
+
+    fn offset(&self, at: i64) -> i32 {
+        let (daylight, start, start_time, end, end_time) = match self.daylight {
+            Some(daylight) => daylight,
+            None => return self.standard,
+        };
+        let year = NaiveDateTime::from_timestamp(at + i64::from(self.standard), 0).year();
+        let begins = start.at(year, start_time) - i64::from(self.standard);
+        let ends = end.at(year, end_time) - i64::from(daylight);
+        let summer = match begins < ends {
+            true => begins <= at && at < ends,
+            // South of the equator summer spans the new year
+            false => !(ends <= at && at < begins),
+        };
+        match summer {
+            true => daylight,
+            false => self.standard,
+        }
+    }
+}
+
+impl Day {
+    /// Seconds since the epoch of this day in `year` at `time`, as if local time were UTC
+    fn at(&self, year: i32, time: i32) -> i64 {
+        let first = NaiveDate::from_ymd(year, self.month, 1);
+        let weekday = first.weekday().num_days_from_sunday();
+        let mut day = 1 + (self.weekday + 7 - weekday) % 7 + (self.week - 1) * 7;
+        let next_month = match self.month {
+            12 => NaiveDate::from_ymd(year + 1, 1, 1),
+            month => NaiveDate::from_ymd(year, month + 1, 1),
+        };
+        let days = next_month.signed_duration_since(first).num_days() as u32;
+        while day > days {
+            day -= 7;
+        }
+        NaiveDate::from_ymd(year, self.month, day)
+            .and_hms(0, 0, 0)
+            .timestamp()
+            + i64::from(time)
+    }
+}
+
+/// Skips a name like `CET` or `<+03>`
+fn abbreviation(rest: &mut &str) -> Option<()> {
+    let end = match rest.strip_prefix('<') {
+        Some(quoted) => quoted.find('>')? + 2,
+        None => rest.find(|c: char| !c.is_ascii_alphabetic())?,
+    };
+    *rest = &rest[end..];
+    Some(())
+}
+
+/// Reads `[+-]hh[:mm[:ss]]` as seconds, west of Greenwich positive as POSIX has it
+fn offset(rest: &mut &str) -> Option<i32> {
+    let (sign, unsigned) = match rest.as_bytes().first()? {
+        b'-' => (-1, &rest[1..]),
+        b'+' => (1, &rest[1..]),
+        _ => (1, *rest),
+    };
+    let end = unsigned
+        .find(|c: char| !c.is_ascii_digit() && c != ':')
+        .unwrap_or(unsigned.len());
+    let mut seconds = 0;
+    for (part, unit) in unsigned[..end].split(':').zip([3600, 60, 1]) {
+        seconds += part.parse::<i32>().ok()? * unit;
+    }
+    *rest = &unsigned[end..];
+    Some(sign * seconds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    /// A TZif file of version 2 without transitions, only the `rule` at its end
+    fn tzif(rule: &str) -> Vec<u8> {
+        let header = |data: &mut Vec<u8>| {
+            data.extend(b"TZif2");
+            data.extend([0; 15]);
+            // One type with a name of four bytes
+            for count in [0u32, 0, 0, 0, 1, 4] {
+                data.extend(count.to_be_bytes());
+            }
+            data.extend(3600i32.to_be_bytes());
+            data.extend([0, 0]);
+            data.extend(b"CET\0");
+        };
+        let mut data = vec![];
+        header(&mut data);
+        header(&mut data);
+        data.extend(format!("\n{}\n", rule).bytes());
+        data
+    }
+
+    fn utc(date: &str) -> DateTime<Utc> {
+        Utc.datetime_from_str(date, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    #[test]
+    fn follows_daylight_saving_time() {
+        let berlin = Zone::parse("Europe/Berlin", &tzif("CET-1CEST,M3.5.0,M10.5.0/3")).unwrap();
+        let cases = vec![
+            ("2026-01-15 11:00", "12:00"),
+            ("2026-06-15 11:00", "13:00"),
+            // The last Sunday of March at 1:00 UTC, and of October
+            ("2026-03-29 00:59", "01:59"),
+            ("2026-03-29 01:00", "03:00"),
+            ("2026-10-25 00:59", "02:59"),
+            ("2026-10-25 01:00", "02:00"),
+        ];
+        for (at, local) in cases {
+            assert_eq!(berlin.at(utc(at)).format("%H:%M").to_string(), local, "{}", at);
+        }
+
+        // Winter in June, south of the equator
+        let sydney = Zone::parse("Australia/Sydney", &tzif("AEST-10AEDT,M10.1.0,M4.1.0/3")).unwrap();
+        assert_eq!(sydney.offset(utc("2026-06-15 00:00")), FixedOffset::east(10 * 3600));
+        assert_eq!(sydney.offset(utc("2026-12-15 00:00")), FixedOffset::east(11 * 3600));
+
+        let kolkata = Zone::parse("Asia/Kolkata", &tzif("IST-5:30")).unwrap();
+        assert_eq!(kolkata.offset(utc("2026-06-15 00:00")), FixedOffset::east(19800));
+        let quoted = Zone::parse("Etc/GMT-3", &tzif("<+03>-3")).unwrap();
+        assert_eq!(quoted.offset(utc("2026-06-15 00:00")), FixedOffset::east(3 * 3600));
+    }
+
+    #[test]
+    fn takes_the_transitions_before_the_rule() {
+        let mut data = b"TZif\0".to_vec();
+        data.extend([0; 15]);
+        for count in [0u32, 0, 0, 2, 2, 8] {
+            data.extend(count.to_be_bytes());
+        }
+        data.extend(0i32.to_be_bytes());
+        data.extend(1000i32.to_be_bytes());
+        data.extend([1, 0]);
+        data.extend(3600i32.to_be_bytes());
+        data.extend([0, 0]);
+        data.extend(7200i32.to_be_bytes());
+        data.extend([1, 4]);
+        data.extend(b"CET\0CEST");
+        let zone = Zone::parse("Test/Zone", &data).unwrap();
+        assert_eq!(zone.offset(Utc.timestamp(-1, 0)), FixedOffset::east(3600));
+        assert_eq!(zone.offset(Utc.timestamp(500, 0)), FixedOffset::east(7200));
+        assert_eq!(zone.offset(Utc.timestamp(2000, 0)), FixedOffset::east(3600));
+    }
+
+    #[test]
+    fn finds_only_zones_of_the_database() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("Europe")).unwrap();
+        fs::write(
+            dir.path().join("Europe/Lisbon"),
+            tzif("WET0WEST,M3.5.0/1,M10.5.0"),
+        )
+        .unwrap();
+        fs::write(dir.path().join("Broken"), b"not a zone").unwrap();
+        let find = |name: &str| Zone::find_in(dir.path(), name);
+        let lisbon = find("Europe/Lisbon").unwrap();
+        assert_eq!(lisbon.name(), "Europe/Lisbon");
+        assert_eq!(lisbon.offset(utc("2026-06-15 11:00")), FixedOffset::east(3600));
+        for name in ["Europe/Nowhere", "../etc/passwd", "/etc/passwd", "", "Europe//Lisbon"] {
+            assert_eq!(
+                find(name),
+                Err(Error::Unknown(name.to_string())),
+                "{}",
+                name
+            );
+        }
+        assert_eq!(
+            find("Broken"),
+            Err(Error::Unreadable("Broken".into()))
+        );
+        assert_eq!(
+            Error::Unknown("Berlin".into()).to_string(),
+            "`Berlin` is not a timezone, use a name like Europe/Berlin"
+        );
+    }
+}
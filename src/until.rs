@@ -0,0 +1,148 @@
+//! When a break ends, given as a wall-clock time like `13:30` or from now like `+15m`.
+
+use crate::timekeeping::{ClockTime, Instant};
+use chrono::{DateTime, Duration, TimeZone, Utc};
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum Error {
+    #[error("`{0}` is not a time like 13:30 or minutes from now like +15m")]
+    Invalid(String),
+
+    #[error("{0} has already passed today, add --tomorrow if you mean tomorrow")]
+    Past(String),
+
+    #[error("{0} doesn't exist that day because the clocks are changed")]
+    Skipped(String),
+}
+
+/// The end of a break starting at `now`. Wall-clock times are in the timezone of `now`
+/// and on the next day with `tomorrow`.
+pub fn parse<Tz: TimeZone>(
+    input: &str,
+    now: &DateTime<Tz>,
+    tomorrow: bool,
+) -> Result<DateTime<Utc>, Error> {
+    let input = input.trim();
+    let invalid = || Error::Invalid(input.to_string());
+
+    if let Some(minutes) = input.strip_prefix('+') {
+        let minutes = minutes
+            .strip_suffix('m')
+            .and_then(|minutes| minutes.parse::<i64>().ok())
+            .filter(|minutes| *minutes > 0)
+            .ok_or_else(invalid)?;
+        return Ok(now.with_timezone(&Utc) + Duration::minutes(minutes));
+    }
+
+    let time: ClockTime = input.parse().map_err(|_| invalid())?;
+    resolve(time, now, tomorrow, input)
+}
+
+/// When it is `time` next, today or with `tomorrow` on the next day, in the timezone of
+/// `now`, like the end of a window
+pub fn at<Tz: TimeZone>(
+    time: ClockTime,
+    now: &DateTime<Tz>,
+    tomorrow: bool,
+) -> Result<DateTime<Utc>, Error> {
+    resolve(time, now, tomorrow, &time.to_string())
+}
+
+fn resolve<Tz: TimeZone>(
+    time: ClockTime,
+    now: &DateTime<Tz>,
+    tomorrow: bool,
+    input: &str,
+) -> Result<DateTime<Utc>, Error> {
+    let mut date = now.naive_local().date();
+    if tomorrow {
+        date = date.succ();
+    }
+
+    // In local time first so the clocks changing in between moves the end with them
+    let zone = now.timezone();
+    let now = Instant::from(now.with_timezone(&Utc));
+    let end = time
+        .on(date, &zone, now)
+        .ok_or_else(|| Error::Skipped(input.to_string()))?;
+    if end <= now {
+        return Err(Error::Past(input.to_string()));
+    }
+    Ok(end.utc())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timekeeping::tests::Berlin;
+    use chrono::NaiveDateTime;
+
+    fn at(local: &str, summer: bool) -> DateTime<Berlin> {
+        Berlin::at(local, summer)
+    }
+
+    fn utc(time: &str) -> Result<DateTime<Utc>, Error> {
+        Ok(Utc.from_utc_datetime(&NaiveDateTime::parse_from_str(time, "%Y-%m-%d %H:%M").unwrap()))
+    }
+
+    #[test]
+    fn until() {
+        let noon = at("2021-06-01 12:00", true);
+        let cases = vec![
+            (&noon, "13:30", false, utc("2021-06-01 11:30")),
+            (&noon, " 13:30 ", false, utc("2021-06-01 11:30")),
+            (&noon, "9:05", true, utc("2021-06-02 07:05")),
+            (&noon, "+15m", false, utc("2021-06-01 10:15")),
+            (&noon, "+90m", true, utc("2021-06-01 11:30")),
+            (&noon, "11:00", false, Err(Error::Past("11:00".into()))),
+            (&noon, "12:00", false, Err(Error::Past("12:00".into()))),
+            (&noon, "11:00", true, utc("2021-06-02 09:00")),
+            (&noon, "+0m", false, Err(Error::Invalid("+0m".into()))),
+            (&noon, "+15", false, Err(Error::Invalid("+15".into()))),
+            (&noon, "+-5m", false, Err(Error::Invalid("+-5m".into()))),
+            (&noon, "25:00", false, Err(Error::Invalid("25:00".into()))),
+            (&noon, "lunch", false, Err(Error::Invalid("lunch".into()))),
+            (&noon, "", false, Err(Error::Invalid("".into()))),
+        ];
+        for (now, input, tomorrow, expected) in cases {
+            assert_eq!(
+                parse(input, now, tomorrow),
+                expected,
+                "{} at {}",
+                input,
+                now
+            );
+        }
+    }
+
+    #[test]
+    fn daylight_saving() {
+        // Clocks go from 02:00 to 03:00
+        let spring = at("2021-03-28 01:00", false);
+        // Clocks go from 03:00 back to 02:00
+        let fall = at("2021-10-31 01:30", true);
+        let fall_again = at("2021-10-31 02:45", true);
+        let evening_before = at("2021-03-27 23:00", false);
+
+        let cases = vec![
+            (&spring, "01:30", false, utc("2021-03-28 00:30")),
+            (&spring, "02:30", false, Err(Error::Skipped("02:30".into()))),
+            (&spring, "03:30", false, utc("2021-03-28 01:30")),
+            (&spring, "+90m", false, utc("2021-03-28 01:30")),
+            (&evening_before, "03:30", true, utc("2021-03-28 01:30")),
+            (&fall, "02:30", false, utc("2021-10-31 00:30")),
+            (&fall_again, "02:30", false, utc("2021-10-31 01:30")),
+            (&fall, "03:30", false, utc("2021-10-31 02:30")),
+        ];
+        for (now, input, tomorrow, expected) in cases {
+            assert_eq!(
+                parse(input, now, tomorrow),
+                expected,
+                "{} at {}",
+                input,
+                now
+            );
+        }
+    }
+}
@@ -0,0 +1,319 @@
+//! Telling when a newer mob is released, at most once a day and only with `update.check`.
+//! The releases are asked over `curl`, and nothing is sent while the cached answer is fresh.
+
+use crate::{command, os};
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const LATEST: &str = "https://api.github.com/repos/Byron/mob/releases/latest";
+/// Longer than this and the check gives up, it only ever delays the end of a command
+const TIMEOUT_SECONDS: &str = "3";
+
+thread_local! {
+    static NEWER: RefCell<Option<Release>> = const { RefCell::new(None) };
+}
+
+/// Has the end of the command tell about `release`
+pub fn announce(release: Release) {
+    NEWER.with(|newer| *newer.borrow_mut() = Some(release));
+}
+
+/// The release to tell about, leaving none
+pub fn take() -> Option<Release> {
+    NEWER.with(|newer| newer.borrow_mut().take())
+}
+
+/// Answers a GET request with the body
+pub trait Http {
+    fn get(&self, url: &str) -> Result<String>;
+}
+
+pub struct Curl;
+
+impl Http for Curl {
+    fn get(&self, url: &str) -> Result<String> {
+        command::Command::new(os::command("curl")).run_stdout([
+            "--silent",
+            "--show-error",
+            "--fail",
+            "--location",
+            "--max-time",
+            TIMEOUT_SECONDS,
+            "--header",
+            "Accept: application/vnd.github+json",
+            url,
+        ])
+    }
+}
+
+/// A version like 0.4.1 or v1.0.0-beta.2
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Version {
+    numbers: (u64, u64, u64),
+    /// Comes before the release without it
+    pre: Option<String>,
+}
+
+impl Version {
+    pub fn parse(version: &str) -> Option<Version> {
+        let version = version.trim().trim_start_matches('v');
+        // Build metadata doesn't count
+        let version = version.split('+').next()?;
+        let (numbers, pre) = match version.split_once('-') {
+            Some((numbers, pre)) if !pre.is_empty() => (numbers, Some(pre.to_string())),
+            Some(_) => return None,
+            None => (version, None),
+        };
+        let mut numbers = numbers.split('.').map(|number| number.parse::<u64>().ok());
+        let version = Version {
+            numbers: (numbers.next()??, numbers.next()??, numbers.next()??),
+            pre,
+        };
+        match numbers.next() {
+            Some(_) => None,
+            None => Some(version),
+        }
+    }
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.numbers
+            .cmp(&other.numbers)
+            .then_with(|| match (&self.pre, &other.pre) {
+                (None, None) => Ordering::Equal,
+                (None, Some(_)) => Ordering::Greater,
+                (Some(_), None) => Ordering::Less,
+                (Some(pre), Some(other)) => compare_pre(pre, other),
+            })
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Dot separated identifiers, numbers by value and before words
+fn compare_pre(pre: &str, other: &str) -> Ordering {
+    let mut left = pre.split('.');
+    let mut right = other.split('.');
+    loop {
+        let ordering = match (left.next(), right.next()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(a), Some(b)) => match (a.parse::<u64>(), b.parse::<u64>()) {
+                (Ok(a), Ok(b)) => a.cmp(&b),
+                (Ok(_), Err(_)) => Ordering::Less,
+                (Err(_), Ok(_)) => Ordering::Greater,
+                (Err(_), Err(_)) => a.cmp(b),
+            },
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+}
+
+/// A published release of mob
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Release {
+    pub version: String,
+    pub url: String,
+}
+
+#[derive(Deserialize)]
+struct GithubRelease {
+    tag_name: String,
+    html_url: String,
+}
+
+/// The last time the releases were asked and what they said
+#[derive(Serialize, Deserialize)]
+struct Cache {
+    checked: DateTime<Utc>,
+    latest: Option<Release>,
+}
+
+/// Where the last answer is kept, next to other caches of the user
+pub fn cache_file() -> Option<PathBuf> {
+    directories::ProjectDirs::from("", "", "mob").map(|dirs| dirs.cache_dir().join("update.json"))
+}
+
+pub struct Checker<'a> {
+    http: &'a dyn Http,
+    cache: PathBuf,
+    current: &'a str,
+}
+
+impl<'a> Checker<'a> {
+    pub fn new(http: &'a dyn Http, cache: PathBuf, current: &'a str) -> Checker<'a> {
+        Checker {
+            http,
+            cache,
+            current,
+        }
+    }
+
+    /// The release newer than this version, asking for it unless the cache is from the
+    /// last day or `force` is given
+    pub fn check(&self, now: DateTime<Utc>, force: bool) -> Result<Option<Release>> {
+        let cached = read(&self.cache);
+        let latest = match cached {
+            Some(cache) if !force && now - cache.checked < Duration::days(1) => cache.latest,
+            cached => {
+                let asked = self.latest();
+                // A failure counts as checked too, so an offline laptop doesn't ask every time
+                let latest = match &asked {
+                    Ok(latest) => Some(latest.clone()),
+                    Err(_) => cached.and_then(|cache| cache.latest),
+                };
+                write(
+                    &self.cache,
+                    &Cache {
+                        checked: now,
+                        latest: latest.clone(),
+                    },
+                )?;
+                asked?;
+                latest
+            }
+        };
+        let current = Version::parse(self.current)
+            .ok_or_else(|| anyhow!("`{}` is not a version", self.current))?;
+        Ok(latest.filter(|latest| {
+            Version::parse(&latest.version)
+                .map(|version| version > current)
+                .unwrap_or(false)
+        }))
+    }
+
+    fn latest(&self) -> Result<Release> {
+        let release: GithubRelease = serde_json::from_str(&self.http.get(LATEST)?)?;
+        Ok(Release {
+            version: release.tag_name.trim_start_matches('v').to_string(),
+            url: release.html_url,
+        })
+    }
+}
+
+fn read(path: &Path) -> Option<Cache> {
+    serde_json::from_str(&fs::read_to_string(path).ok()?).ok()
+}
+
+fn write(path: &Path, cache: &Cache) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, serde_json::to_string(cache)?)?;
+    Ok(())
+}
+
+/// The line printed at the end of a command
+pub fn notice(release: &Release, current: &str) -> String {
+    format!(
+        "mob {} is out, you have {}: {}",
+        release.version, current, release.url
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct FakeHttp {
+        requests: RefCell<usize>,
+        body: Option<&'static str>,
+    }
+
+    impl Http for FakeHttp {
+        fn get(&self, url: &str) -> Result<String> {
+            assert_eq!(url, LATEST);
+            *self.requests.borrow_mut() += 1;
+            self.body
+                .map(String::from)
+                .ok_or_else(|| anyhow!("curl: (28) Operation timed out"))
+        }
+    }
+
+    const RELEASE: &str = r#"{"tag_name": "v0.5.0", "html_url": "https://github.com/Byron/mob/releases/tag/v0.5.0", "draft": false}"#;
+
+    fn version(version: &str) -> Version {
+        Version::parse(version).unwrap()
+    }
+
+    #[test]
+    fn compares_versions() {
+        assert!(version("0.5.0") > version("0.4.12"));
+        assert!(version("v1.0.0") > version("0.99.99"));
+        assert!(version("1.0.0") > version("1.0.0-beta.2"));
+        assert!(version("1.0.0-beta.11") > version("1.0.0-beta.2"));
+        assert!(version("1.0.0-beta") > version("1.0.0-alpha.1"));
+        assert!(version("1.0.0-alpha.1") > version("1.0.0-alpha"));
+        assert!(version("1.0.0-rc.1") > version("1.0.0-1"));
+        assert_eq!(version("0.4.1+build.5"), version("0.4.1"));
+        for invalid in &["", "1.0", "1.0.0.0", "one.two.three", "1.0.0-", "latest"] {
+            assert_eq!(Version::parse(invalid), None, "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn asks_once_a_day() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = dir.path().join("cache").join("update.json");
+        let http = FakeHttp {
+            body: Some(RELEASE),
+            ..FakeHttp::default()
+        };
+        let checker = Checker::new(&http, cache.clone(), "0.4.1");
+        let now = Utc::now();
+
+        let newer = checker.check(now, false).unwrap().unwrap();
+        assert_eq!(newer.version, "0.5.0");
+        assert_eq!(
+            notice(&newer, "0.4.1"),
+            "mob 0.5.0 is out, you have 0.4.1: https://github.com/Byron/mob/releases/tag/v0.5.0"
+        );
+        assert!(checker
+            .check(now + Duration::hours(23), false)
+            .unwrap()
+            .is_some());
+        assert_eq!(*http.requests.borrow(), 1);
+
+        checker.check(now + Duration::hours(23), true).unwrap();
+        assert_eq!(*http.requests.borrow(), 2);
+        checker.check(now + Duration::hours(48), false).unwrap();
+        assert_eq!(*http.requests.borrow(), 3);
+
+        let current = Checker::new(&http, cache, "0.5.0");
+        assert_eq!(current.check(now, false).unwrap(), None);
+    }
+
+    #[test]
+    fn failures_count_as_checked() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = dir.path().join("update.json");
+        let offline = FakeHttp::default();
+        let checker = Checker::new(&offline, cache.clone(), "0.4.1");
+        let now = Utc::now();
+
+        assert!(checker.check(now, false).is_err());
+        assert_eq!(checker.check(now, false).unwrap(), None);
+        assert_eq!(*offline.requests.borrow(), 1);
+
+        let garbage = FakeHttp {
+            body: Some("<html>rate limited</html>"),
+            ..FakeHttp::default()
+        };
+        let checker = Checker::new(&garbage, cache, "0.4.1");
+        assert!(checker.check(now, true).is_err());
+    }
+}
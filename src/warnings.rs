@@ -0,0 +1,130 @@
+//! Deprecation and compatibility warnings collected while a command runs and reported at
+//! the end, dimmed for people and structured for scripts using `--json`.
+
+use crate::palette::{Palette, Role};
+use crate::update::Release;
+use serde::Serialize;
+use std::cell::RefCell;
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Warning {
+    /// Stable identifier scripts can match on
+    pub code: &'static str,
+    pub message: String,
+    /// Version that started warning about it
+    pub since_version: &'static str,
+}
+
+thread_local! {
+    static WARNINGS: RefCell<Vec<Warning>> = const { RefCell::new(vec![]) };
+    static STATUS: RefCell<Option<serde_json::Value>> = const { RefCell::new(None) };
+}
+
+/// Adds a warning, repeated codes are only reported once
+pub fn push(code: &'static str, message: impl Into<String>, since_version: &'static str) {
+    WARNINGS.with(|warnings| {
+        let mut warnings = warnings.borrow_mut();
+        if !warnings.iter().any(|warning| warning.code == code) {
+            warnings.push(Warning {
+                code,
+                message: message.into(),
+                since_version,
+            });
+        }
+    });
+}
+
+/// Everything collected so far, leaving the collector empty
+pub fn take() -> Vec<Warning> {
+    WARNINGS.with(|warnings| warnings.replace(vec![]))
+}
+
+/// What `mob status --json` adds to the report
+pub fn set_status(status: serde_json::Value) {
+    STATUS.with(|current| current.replace(Some(status)));
+}
+
+/// The status set while the command ran, leaving none
+pub fn take_status() -> Option<serde_json::Value> {
+    STATUS.with(|status| status.replace(None))
+}
+
+#[derive(Serialize)]
+struct Report<'a> {
+    warnings: &'a [Warning],
+    /// A newer release, with `update.check`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    update: Option<&'a Release>,
+    /// The session from `mob status`, or the checks of `mob verify`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status: Option<&'a serde_json::Value>,
+}
+
+/// The JSON document printed with `--json`
+pub fn to_json(
+    warnings: &[Warning],
+    update: Option<&Release>,
+    status: Option<&serde_json::Value>,
+) -> String {
+    serde_json::to_string_pretty(&Report {
+        warnings,
+        update,
+        status,
+    })
+    .expect("warnings serialize")
+}
+
+/// One dimmed line per warning
+pub fn to_lines(warnings: &[Warning], palette: &Palette) -> String {
+    warnings
+        .iter()
+        .map(|warning| {
+            let line = format!(
+                "warning[{}]: {} (since {})",
+                warning.code, warning.message, warning.since_version
+            );
+            format!("{}\n", palette.paint(Role::Muted, line))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::palette::PaletteKind;
+
+    #[test]
+    fn collects_once_per_code() {
+        take();
+        push("a", "first", "0.1.7");
+        push("a", "again", "0.1.7");
+        push("b", "second", "0.1.7");
+        let warnings = take();
+        assert_eq!(warnings.len(), 2);
+        assert_eq!(warnings[0].message, "first");
+        assert!(take().is_empty());
+    }
+
+    #[test]
+    fn rendering() {
+        let warnings = vec![Warning {
+            code: "raw-flag",
+            message: "use --json".into(),
+            since_version: "0.1.7",
+        }];
+        let palette = Palette::new(PaletteKind::Mono).force_styling(true);
+        let line = "warning[raw-flag]: use --json (since 0.1.7)";
+        let expected = if cfg!(feature = "color") {
+            format!("\u{1b}[2m{}\u{1b}[0m\n", line)
+        } else {
+            format!("{}\n", line)
+        };
+        assert_eq!(to_lines(&warnings, &palette), expected);
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&to_json(&warnings, None, None)).unwrap(),
+            serde_json::json!({"warnings": [
+                {"code": "raw-flag", "message": "use --json", "since_version": "0.1.7"}
+            ]})
+        );
+    }
+}
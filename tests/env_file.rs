@@ -0,0 +1,90 @@
+//! The environment file in the state dir across a whole session: written at start, kept up
+//! to date with every handover and removed when the session is done.
+
+use clap::Clap;
+use remotemob::session::{self, Store as _};
+use remotemob::{clock::SystemClock, cmd, config::Config, git, state_dir::StateDir, timer};
+use std::fs;
+
+fn config(name: &str) -> Config {
+    Config {
+        name: name.into(),
+        ..Config::default()
+    }
+}
+
+fn env_file(state_dir: &StateDir) -> Option<String> {
+    fs::read_to_string(state_dir.file("env.sh")).ok()
+}
+
+fn sourced(state_dir: &StateDir, name: &str) -> Option<String> {
+    let line = env_file(state_dir)?
+        .lines()
+        .find(|line| line.starts_with(&format!("export {}=", name)))?
+        .to_string();
+    Some(line.split_once('=')?.1.trim_matches('\'').to_string())
+}
+
+#[test]
+fn env_file_follows_the_session() {
+    let dir = tempfile::tempdir().unwrap();
+    let files = git::FileStore::new(dir.path());
+    let inner = session::SessionStore::new(&files);
+    let state_dir = StateDir::new(dir.path().join(".mob-local.d"));
+    let store = session::EnvStore::new(&inner, state_dir.clone());
+    let start = |name: &str| {
+        cmd::Start::new(
+            &git::NoopGit,
+            &store,
+            &timer::LogTimer,
+            &SystemClock,
+            cmd::StartOpts::parse_from(["start", "--local", "--preset", "focus"]),
+            config(name),
+        )
+        .run()
+        .unwrap()
+    };
+    let next = |name: &str| {
+        cmd::Next::new(
+            &git::NoopGit,
+            &store,
+            &timer::LogTimer,
+            &SystemClock,
+            cmd::NextOpts::parse_from(["next"]),
+            config(name),
+        )
+        .run()
+        .unwrap()
+    };
+    assert_eq!(env_file(&state_dir), None);
+
+    start("alice");
+    assert_eq!(sourced(&state_dir, "MOB_ACTIVE").as_deref(), Some("1"));
+    assert_eq!(sourced(&state_dir, "MOB_DRIVER").as_deref(), Some("alice"));
+    assert_eq!(sourced(&state_dir, "MOB_STATE").as_deref(), Some("working"));
+    let branch = store.load().unwrap().branches.branch;
+    assert_eq!(sourced(&state_dir, "MOB_BRANCH"), Some(branch));
+
+    next("alice");
+    assert_eq!(sourced(&state_dir, "MOB_DRIVER").as_deref(), Some(""));
+    assert_eq!(sourced(&state_dir, "MOB_STATE").as_deref(), Some("waiting"));
+    start("bob");
+    assert_eq!(sourced(&state_dir, "MOB_DRIVER").as_deref(), Some("bob"));
+
+    cmd::Done::new(
+        &git::NoopGit,
+        &store,
+        &SystemClock,
+        state_dir.clone(),
+        cmd::DoneOpts::parse_from(["done"]),
+        config("bob"),
+    )
+    .run()
+    .unwrap();
+    assert_eq!(env_file(&state_dir), None);
+
+    start("alice");
+    assert!(env_file(&state_dir).is_some());
+    store.clean().unwrap();
+    assert_eq!(env_file(&state_dir), None);
+}
@@ -0,0 +1,75 @@
+//! A whole session of a build without the default features, where nobody can be asked and
+//! every question takes its default. Run it with `cargo test --no-default-features --test
+//! minimal`.
+#![cfg(not(any(feature = "interactive", feature = "notifications", feature = "color")))]
+
+use clap::Clap;
+use remotemob::session::{self, Store as _};
+use remotemob::{clock::SystemClock, cmd, config::Config, git, state_dir::StateDir, timer};
+
+fn config(name: &str) -> Config {
+    Config {
+        name: name.into(),
+        ..Config::default()
+    }
+}
+
+fn start(store: &session::SessionStore, name: &str) {
+    cmd::Start::new(
+        &git::NoopGit,
+        store,
+        &timer::LogTimer,
+        &SystemClock,
+        cmd::StartOpts::parse_from(["start", "--local"]),
+        config(name),
+    )
+    .run()
+    .unwrap();
+}
+
+fn next(store: &session::SessionStore, name: &str) {
+    cmd::Next::new(
+        &git::NoopGit,
+        store,
+        &timer::LogTimer,
+        &SystemClock,
+        cmd::NextOpts::parse_from(["next"]),
+        config(name),
+    )
+    .run()
+    .unwrap();
+}
+
+#[test]
+fn start_next_done() {
+    let dir = tempfile::tempdir().unwrap();
+    let files = git::FileStore::new(dir.path());
+    let store = session::SessionStore::new(&files);
+
+    // The settings of a new session take their defaults
+    start(&store, "alice");
+    let session = store.load().unwrap();
+    assert_eq!(session.settings, Some(session::Settings::default()));
+    next(&store, "alice");
+    start(&store, "bob");
+    next(&store, "bob");
+    match store.load().unwrap().state {
+        session::State::WaitingForNext { next } => assert_eq!(next.as_deref(), Some("alice")),
+        other => panic!("Expected alice to be next, got {:?}", other),
+    }
+
+    start(&store, "alice");
+    cmd::Done::new(
+        &git::NoopGit,
+        &store,
+        &SystemClock,
+        StateDir::new(dir.path().join(".mob-local.d")),
+        cmd::DoneOpts::parse_from(["done"]),
+        config("alice"),
+    )
+    .run()
+    .unwrap();
+    let session = store.load().unwrap();
+    assert_eq!(session.state, session::State::Stopped);
+    assert_eq!(session.drivers.all(), vec!["alice", "bob"]);
+}
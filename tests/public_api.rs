@@ -0,0 +1,35 @@
+//! The modules and the prelude the library exposes, against `tests/public-api.txt`. A change
+//! there is a change of the public API: update the file on purpose and mention it in the
+//! release notes. Modules only the binary needs are public but hidden, making one of them
+//! part of the API shows here too.
+
+use std::fs;
+use std::path::Path;
+
+/// The lines of `file` that make something public or hide it, attributes included
+fn exposed(file: &str) -> Vec<String> {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join(file);
+    fs::read_to_string(&path)
+        .unwrap()
+        .lines()
+        .map(str::trim)
+        .filter(|line| {
+            line.starts_with("pub ") || line.starts_with("#[cfg") || line.starts_with("#[doc")
+        })
+        .map(|line| format!("{}: {}", file, line))
+        .collect()
+}
+
+#[test]
+fn public_api_is_unchanged() {
+    let mut api = exposed("src/lib.rs");
+    api.extend(exposed("src/prelude.rs"));
+    let api = api.join("\n") + "\n";
+    let fixture = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/public-api.txt");
+    let expected = fs::read_to_string(&fixture).unwrap();
+    assert!(
+        api == expected,
+        "The public API changed, update tests/public-api.txt if that was intended:\n{}",
+        api
+    );
+}